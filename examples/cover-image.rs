@@ -1,13 +1,15 @@
 use raytracer::{
     camera::{self, consts::ImageResolution, Camera, CameraBuilder},
     color::{self, Color},
+    config::Config,
     light::{Light, PointLight},
     material::{self, Material},
     pattern::Pattern3D,
+    render::RenderSettings,
     shape::{Cube, Plane, Shape, ShapeBuilder, Sphere},
     transform::Transform,
     tuple::{Point, Vector},
-    world::World,
+    world::{UnitScale, World},
 };
 
 const RESOLUTION: ImageResolution = camera::consts::HD;
@@ -21,6 +23,11 @@ const WHITE_MATERIAL: Material = Material {
     index_of_refraction: material::consts::VACUUM_INDEX_OF_REFRACTION,
     shininess: 200.0,
     transparency: 0.0,
+    dispersion: 0.0,
+    spectrum: None,
+    blend: None,
+    portal: None,
+    dirt: None,
 };
 
 const BLUE_MATERIAL: Material = Material {
@@ -29,7 +36,18 @@ const BLUE_MATERIAL: Material = Material {
         green: 0.831,
         blue: 0.914,
     }),
-    ..WHITE_MATERIAL
+    diffuse: 0.7,
+    ambient: 0.1,
+    specular: 0.0,
+    reflectivity: 0.1,
+    index_of_refraction: material::consts::VACUUM_INDEX_OF_REFRACTION,
+    shininess: 200.0,
+    transparency: 0.0,
+    dispersion: 0.0,
+    spectrum: None,
+    blend: None,
+    portal: None,
+    dirt: None,
 };
 
 const RED_MATERIAL: Material = Material {
@@ -38,7 +56,18 @@ const RED_MATERIAL: Material = Material {
         green: 0.322,
         blue: 0.388,
     }),
-    ..WHITE_MATERIAL
+    diffuse: 0.7,
+    ambient: 0.1,
+    specular: 0.0,
+    reflectivity: 0.1,
+    index_of_refraction: material::consts::VACUUM_INDEX_OF_REFRACTION,
+    shininess: 200.0,
+    transparency: 0.0,
+    dispersion: 0.0,
+    spectrum: None,
+    blend: None,
+    portal: None,
+    dirt: None,
 };
 
 const PURPLE_MATERIAL: Material = Material {
@@ -47,10 +76,23 @@ const PURPLE_MATERIAL: Material = Material {
         green: 0.404,
         blue: 0.550,
     }),
-    ..WHITE_MATERIAL
+    diffuse: 0.7,
+    ambient: 0.1,
+    specular: 0.0,
+    reflectivity: 0.1,
+    index_of_refraction: material::consts::VACUUM_INDEX_OF_REFRACTION,
+    shininess: 200.0,
+    transparency: 0.0,
+    dispersion: 0.0,
+    spectrum: None,
+    blend: None,
+    portal: None,
+    dirt: None,
 };
 
 fn main() {
+    let config = Config::from_args(std::env::args()).unwrap();
+
     let std_transform =
         Transform::scaling(0.5, 0.5, 0.5).unwrap() * Transform::translation(1.0, -1.0, 1.0);
 
@@ -84,6 +126,11 @@ fn main() {
             reflectivity: 0.7,
             transparency: 0.7,
             index_of_refraction: 1.5,
+            dispersion: 0.0,
+            spectrum: None,
+            blend: None,
+            portal: None,
+            dirt: None,
         },
         transform: large_object,
     }));
@@ -164,6 +211,7 @@ fn main() {
     let main_light = Light::Point(PointLight {
         position: Point::new(50.0, 100.0, -50.0),
         intensity: color::consts::WHITE,
+        group: None,
     });
 
     let secondary_light = Light::Point(PointLight {
@@ -173,11 +221,14 @@ fn main() {
             green: 0.2,
             blue: 0.2,
         },
+        group: None,
     });
 
     let world = World {
         objects,
         lights: vec![main_light, secondary_light],
+        units: UnitScale::default().with_epsilon(config.epsilon),
+        ..Default::default()
     };
 
     let camera = Camera::try_from(CameraBuilder {
@@ -193,6 +244,8 @@ fn main() {
     })
     .unwrap();
 
-    let image = camera.render(&world).to_image();
+    let image = camera
+        .render_with_settings(&world, &RenderSettings::from_config(&config))
+        .to_image();
     image.save("image.png").unwrap();
 }
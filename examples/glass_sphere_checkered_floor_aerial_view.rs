@@ -1,18 +1,22 @@
 use raytracer::{
     camera::{self, consts::ImageResolution, Camera, CameraBuilder},
     color::{self, Color},
+    config::Config,
     light::{AreaLight, AreaLightBuilder, Light},
     material::{self, Material},
     pattern::{Pattern3D, Pattern3DSpec},
+    render::RenderSettings,
     shape::{Plane, Shape, ShapeBuilder, Sphere},
     transform::Transform,
     tuple::{Point, Vector},
-    world::World,
+    world::{UnitScale, World},
 };
 
 const RESOLUTION: ImageResolution = camera::consts::HD;
 
 fn main() {
+    let config = Config::from_args(std::env::args()).unwrap();
+
     let floor = Shape::Plane(Plane::from(ShapeBuilder {
         material: Material {
             pattern: Pattern3D::Checker(Pattern3DSpec::new(
@@ -106,6 +110,8 @@ fn main() {
             green_sphere,
         ],
         lights: vec![light],
+        units: UnitScale::default().with_epsilon(config.epsilon),
+        ..Default::default()
     };
 
     let camera = Camera::try_from(CameraBuilder {
@@ -117,6 +123,8 @@ fn main() {
     })
     .unwrap();
 
-    let image = camera.render(&world).to_image();
+    let image = camera
+        .render_with_settings(&world, &RenderSettings::from_config(&config))
+        .to_image();
     image.save("image.png").unwrap();
 }
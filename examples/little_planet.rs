@@ -0,0 +1,18 @@
+use raytracer::{
+    camera::{self, consts::ImageResolution},
+    config::Config,
+    examples,
+    render::RenderSettings,
+};
+
+const RESOLUTION: ImageResolution = camera::consts::HD;
+
+fn main() {
+    let config = Config::from_args(std::env::args()).unwrap();
+    let scene = examples::little_planet(RESOLUTION).with_config(&config);
+
+    let image = scene
+        .render_with_settings(&RenderSettings::from_config(&config))
+        .to_image();
+    image.save("image.png").unwrap();
+}
@@ -0,0 +1,23 @@
+use raytracer::{config::Config, render::RenderFarm};
+
+fn main() {
+    let config = Config::from_args(std::env::args()).unwrap();
+
+    let directory = std::env::args()
+        .skip(1)
+        .find(|arg| !arg.starts_with("--"))
+        .unwrap_or_else(|| "jobs".to_string());
+
+    let report = RenderFarm::new(directory)
+        .with_config(config)
+        .run()
+        .unwrap();
+
+    for (manifest, err) in &report.failed {
+        eprintln!("{}: {err}", manifest.display());
+    }
+
+    if !report.failed.is_empty() {
+        std::process::exit(1);
+    }
+}
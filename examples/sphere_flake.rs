@@ -0,0 +1,20 @@
+use raytracer::{
+    camera::{self, consts::ImageResolution},
+    config::Config,
+    examples,
+    render::RenderSettings,
+};
+
+const RESOLUTION: ImageResolution = camera::consts::HD;
+
+const DEPTH: u32 = 4;
+
+fn main() {
+    let config = Config::from_args(std::env::args()).unwrap();
+    let scene = examples::sphere_flake(RESOLUTION, DEPTH).with_config(&config);
+
+    let image = scene
+        .render_with_settings(&RenderSettings::from_config(&config))
+        .to_image();
+    image.save("image.png").unwrap();
+}
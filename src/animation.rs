@@ -0,0 +1,1182 @@
+use thiserror::Error;
+
+use crate::{
+    camera::{self, Camera, FocusTarget},
+    render::{AccumulationBuffer, RenderSettings, RenderStats},
+    sampling::splitmix64,
+    shape::ShapeId,
+    transform::{self, Transform},
+    tuple::{Point, Vector},
+    world::World,
+};
+
+/// The error type when trying to evaluate an animation.
+#[derive(Debug, PartialEq, Error)]
+pub enum Error {
+    /// The error type when an animation refers to a shape that is not in the world.
+    #[error("no shape in the world has the identifier {0:?}")]
+    MissingObject(ShapeId),
+
+    /// The error type when the tracks of an animation produce an invalid transformation, such as
+    /// a scaling to zero.
+    #[error(transparent)]
+    InvalidTransform(#[from] transform::Error),
+
+    /// The error type when an animated camera can't focus on it's target.
+    #[error(transparent)]
+    InvalidFocus(#[from] camera::Error),
+}
+
+/// Curve followed by a value on it's way from one keyframe to the next one.
+///
+/// The quadratic curves are the ones followed by thrown objects, so a bouncing ball only needs
+/// [EaseOut](Easing::EaseOut) on the way up and [EaseIn](Easing::EaseIn) on the way down.
+///
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum Easing {
+    /// Constant speed.
+    #[default]
+    Linear,
+
+    /// Starts slow and speeds up.
+    EaseIn,
+
+    /// Starts fast and slows down.
+    EaseOut,
+
+    /// Starts slow, speeds up in the middle and slows down again.
+    EaseInOut,
+
+    /// Keeps the value of the previous keyframe until the next one is reached.
+    Step,
+}
+
+/// Value of a track at a given time.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Keyframe<T> {
+    /// Time of the keyframe, in the same units as every other keyframe, like seconds or frames.
+    pub time: f64,
+
+    /// Value of the track at the time of the keyframe.
+    pub value: T,
+
+    /// Curve followed from the previous keyframe to this one.
+    pub easing: Easing,
+}
+
+/// Values that can be blended between keyframes.
+pub trait Interpolate: Copy {
+    /// Blends this value with `other`, where `t = 0.0` is this value and `t = 1.0` is `other`.
+    fn interpolate(self, other: Self, t: f64) -> Self;
+}
+
+/// Sequence of keyframes that animates a single value.
+///
+/// # Examples
+///
+/// ```
+/// use raytracer::animation::{Easing, Keyframe, Track};
+///
+/// let track = Track::new([
+///     Keyframe { time: 0.0, value: 0.0, easing: Easing::Linear },
+///     Keyframe { time: 2.0, value: 10.0, easing: Easing::Linear },
+/// ]);
+///
+/// assert_eq!(track.sample(1.0), Some(5.0));
+///
+/// // The track holds it's first and last values outside of it's keyframes.
+/// assert_eq!(track.sample(-1.0), Some(0.0));
+/// assert_eq!(track.sample(3.0), Some(10.0));
+/// ```
+///
+#[derive(Clone, Debug, PartialEq)]
+pub struct Track<T> {
+    keyframes: Vec<Keyframe<T>>,
+}
+
+/// Tracks for each component of the transformation of an object. Components without keyframes
+/// are left untouched.
+///
+/// Components are combined like a [Transform] built from a scaling, followed by rotations around
+/// the `x`, `y` and `z` axes, followed by a translation.
+///
+/// # Examples
+///
+/// A ball that bounces once.
+///
+/// ```
+/// use raytracer::{
+///     animation::{Easing, Keyframe, TransformTracks, Track},
+///     tuple::{Point, Vector},
+/// };
+///
+/// let height = |time, y, easing| Keyframe {
+///     time,
+///     value: Vector::new(0.0, y, 0.0),
+///     easing,
+/// };
+///
+/// let tracks = TransformTracks {
+///     translation: Track::new([
+///         height(0.0, 0.0, Easing::Linear),
+///         height(0.5, 2.0, Easing::EaseOut),
+///         height(1.0, 0.0, Easing::EaseIn),
+///     ]),
+///     ..Default::default()
+/// };
+///
+/// let top = tracks.transform_at(0.5).unwrap() * Point::new(0.0, 0.0, 0.0);
+///
+/// assert_eq!(top, Point::new(0.0, 2.0, 0.0));
+/// ```
+///
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TransformTracks {
+    /// Offset of the object.
+    pub translation: Track<Vector>,
+
+    /// Rotation of the object around the `x`, `y` and `z` axes, in degrees.
+    pub rotation: Track<Vector>,
+
+    /// Scale of the object along the `x`, `y` and `z` axes.
+    pub scaling: Track<Vector>,
+}
+
+/// Animation of a single shape of a world, either an object of the world or a shape inside of
+/// one of it's groups.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ObjectAnimation {
+    /// Identifier of the animated shape.
+    pub object: ShapeId,
+
+    /// Tracks applied on top of the shape's own transformation. Rotations and scalings happen
+    /// around the pivot of the shape, which is the origin of it's own space, so a sphere spins in
+    /// place wherever it is in the world.
+    pub tracks: TransformTracks,
+}
+
+/// Animation of the objects of a world.
+///
+/// An animation is played by an [AnimatedWorld], which moves the animated objects of a single
+/// world from frame to frame.
+///
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Animation {
+    /// Animations of each of the animated objects.
+    pub objects: Vec<ObjectAnimation>,
+}
+
+/// World whose objects are moved in place by an [Animation].
+///
+/// Every frame is rendered from the same world, instead of a copy of it, and going to another
+/// time only changes the [transformation](World::set_transform) of the animated objects. Each of
+/// them is placed by applying it's tracks on top of the transformation it had when the world was
+/// animated, so the transformations don't pile up from frame to frame.
+///
+/// # Examples
+///
+/// ```
+/// use raytracer::{
+///     animation::{AnimatedWorld, Animation, Easing, Keyframe, ObjectAnimation, TransformTracks, Track},
+///     shape::Shape,
+///     transform::Transform,
+///     tuple::Vector,
+///     world::World,
+/// };
+///
+/// let sphere = Shape::Sphere(Default::default());
+/// let id = sphere.id();
+///
+/// let world = World {
+///     objects: vec![sphere],
+///     ..Default::default()
+/// };
+///
+/// let animation = Animation {
+///     objects: vec![ObjectAnimation {
+///         object: id,
+///         tracks: TransformTracks {
+///             rotation: Track::new([
+///                 Keyframe { time: 0.0, value: Vector::new(0.0, 0.0, 0.0), easing: Easing::Linear },
+///                 Keyframe { time: 24.0, value: Vector::new(0.0, 360.0, 0.0), easing: Easing::Linear },
+///             ]),
+///             ..Default::default()
+///         },
+///     }],
+/// };
+///
+/// let mut animated = AnimatedWorld::new(world, animation).unwrap();
+///
+/// for frame in 0..24 {
+///     let world = animated.set_time(frame as f64).unwrap();
+///     // Render the frame...
+/// }
+/// ```
+///
+#[derive(Debug)]
+pub struct AnimatedWorld {
+    world: World,
+    animation: Animation,
+    rest: Vec<Transform>,
+}
+
+/// Path followed by a camera during an animation.
+///
+/// # Examples
+///
+/// A camera that dollies towards a sphere, with a handheld look, keeping the sphere in focus.
+///
+/// ```
+/// use raytracer::{
+///     animation::{CameraPath, CameraShake, Easing, Keyframe, Track},
+///     camera::{Camera, CameraBuilder, FocusTarget},
+///     shape::Shape,
+///     tuple::{Point, Vector},
+///     world::World,
+/// };
+///
+/// let sphere = Shape::Sphere(Default::default());
+/// let focus = Some(FocusTarget::Object(sphere.id()));
+///
+/// let world = World {
+///     objects: vec![sphere],
+///     ..Default::default()
+/// };
+///
+/// let camera = Camera::try_from(CameraBuilder {
+///     width: 160,
+///     height: 90,
+///     field_of_view: std::f64::consts::FRAC_PI_3,
+///     transform: Default::default(),
+/// })
+/// .unwrap()
+/// .with_depth_of_field(0.05, 1.0)
+/// .unwrap();
+///
+/// let path = CameraPath {
+///     from: Track::new([
+///         Keyframe { time: 0.0, value: Point::new(0.0, 1.0, -10.0), easing: Easing::Linear },
+///         Keyframe { time: 48.0, value: Point::new(0.0, 1.0, -5.0), easing: Easing::EaseInOut },
+///     ]),
+///     to: Track::new([
+///         Keyframe { time: 0.0, value: Point::new(0.0, 0.0, 0.0), easing: Easing::Linear },
+///     ]),
+///     up: Vector::new(0.0, 1.0, 0.0),
+///     shake: Some(CameraShake {
+///         amplitude: 0.5,
+///         frequency: 0.1,
+///         seed: 7,
+///     }),
+///     focus,
+/// };
+///
+/// for frame in 0..48 {
+///     let camera = path.camera_at(&camera, &world, frame as f64).unwrap();
+///     // Render the frame...
+/// }
+/// ```
+///
+#[derive(Clone, Debug, PartialEq)]
+pub struct CameraPath {
+    /// Position of the camera.
+    pub from: Track<Point>,
+
+    /// Point the camera looks at.
+    pub to: Track<Point>,
+
+    /// Direction that is up for the camera.
+    pub up: Vector,
+
+    /// Jitter applied on top of the path.
+    pub shake: Option<CameraShake>,
+
+    /// Target the camera keeps in focus, when it has a [depth of
+    /// field](Camera::with_depth_of_field).
+    pub focus: Option<FocusTarget>,
+}
+
+/// Procedural jitter of the direction a camera looks at, that mimics a camera held by hand.
+///
+/// The camera turns around it's own axes following a smooth noise, without leaving the path it
+/// follows. The same seed always gives the same shake, so every render of an animation is the
+/// same.
+///
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct CameraShake {
+    /// Maximum angle the camera turns away from the path, in degrees.
+    pub amplitude: f64,
+
+    /// Average number of changes of direction per unit of time of the animation.
+    pub frequency: f64,
+
+    /// Seed of the noise, so different cameras can shake differently.
+    pub seed: u64,
+}
+
+/// Motion blur of the objects that move while the shutter of the camera is open.
+///
+/// The image is averaged from sub-frames rendered at different times around the time of the
+/// frame. Times are spread following the [curve](ShutterCurve) of the shutter, so the parts of a
+/// streak where the shutter was only half open are fainter, like they are in a real camera.
+///
+/// # Examples
+///
+/// ```
+/// use raytracer::{
+///     animation::{AnimatedWorld, Animation, MotionBlur, ShutterCurve},
+///     camera::{Camera, CameraBuilder},
+///     render::{AccumulationBuffer, RenderSettings},
+///     world::World,
+/// };
+///
+/// let camera = Camera::try_from(CameraBuilder {
+///     width: 16,
+///     height: 16,
+///     field_of_view: std::f64::consts::FRAC_PI_3,
+///     transform: Default::default(),
+/// }).unwrap();
+///
+/// let blur = MotionBlur {
+///     shutter: 0.5,
+///     curve: ShutterCurve::Triangle,
+///     samples: 8,
+/// };
+///
+/// let mut buffer = AccumulationBuffer::new(camera.width(), camera.height());
+///
+/// AnimatedWorld::new(World::default(), Animation::default())
+///     .unwrap()
+///     .render_motion_blur(&camera, &RenderSettings::default(), &blur, 12.0, &mut buffer)
+///     .unwrap();
+///
+/// assert_eq!(buffer.sample_count(0, 0), 8);
+/// ```
+///
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct MotionBlur {
+    /// Time the shutter stays open, in the same units as the keyframes, centered on the time of
+    /// the frame.
+    pub shutter: f64,
+
+    /// How much the shutter is open during that time.
+    pub curve: ShutterCurve,
+
+    /// Number of sub-frames rendered for each frame.
+    pub samples: usize,
+}
+
+/// How much the shutter of a camera is open while it's exposing a frame.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub enum ShutterCurve {
+    /// Opens and closes instantly, so every moment counts the same.
+    #[default]
+    Box,
+
+    /// Opens gradually until the middle of the exposure, and then closes gradually.
+    Triangle,
+
+    /// Opens gradually during the given fraction of the exposure, stays fully open, and then
+    /// closes gradually during that same fraction. The fraction goes from `0.0`, which is a
+    /// [Box](ShutterCurve::Box) shutter, to `0.5`, which is a [Triangle](ShutterCurve::Triangle)
+    /// shutter.
+    Trapezoid {
+        /// Fraction of the exposure spent opening or closing the shutter.
+        ramp: f64,
+    },
+}
+
+impl Easing {
+    /// Maps the fraction of time elapsed between two keyframes, from `0.0` to `1.0`, to the
+    /// fraction of the way the value has moved between them.
+    pub fn apply(self, t: f64) -> f64 {
+        let t = t.clamp(0.0, 1.0);
+
+        match self {
+            Self::Linear => t,
+            Self::EaseIn => t * t,
+            Self::EaseOut => t * (2.0 - t),
+            Self::EaseInOut => {
+                if t < 0.5 {
+                    2.0 * t * t
+                } else {
+                    1.0 - 2.0 * (1.0 - t).powi(2)
+                }
+            }
+            Self::Step => {
+                if t < 1.0 {
+                    0.0
+                } else {
+                    1.0
+                }
+            }
+        }
+    }
+}
+
+impl Interpolate for f64 {
+    fn interpolate(self, other: Self, t: f64) -> Self {
+        self + (other - self) * t
+    }
+}
+
+impl Interpolate for Vector {
+    fn interpolate(self, other: Self, t: f64) -> Self {
+        self + (other - self) * t
+    }
+}
+
+impl Interpolate for Point {
+    fn interpolate(self, other: Self, t: f64) -> Self {
+        self + (other - self) * t
+    }
+}
+
+impl<T> Track<T> {
+    /// Constructs a track from it's keyframes, which don't need to be sorted by time.
+    pub fn new<I>(keyframes: I) -> Self
+    where
+        I: IntoIterator<Item = Keyframe<T>>,
+    {
+        let mut keyframes: Vec<_> = keyframes.into_iter().collect();
+        keyframes.sort_by(|a, b| a.time.total_cmp(&b.time));
+
+        Self { keyframes }
+    }
+
+    /// Keyframes of the track, sorted by time.
+    pub fn keyframes(&self) -> &[Keyframe<T>] {
+        &self.keyframes
+    }
+
+    /// Whether the track has no keyframes.
+    pub fn is_empty(&self) -> bool {
+        self.keyframes.is_empty()
+    }
+}
+
+impl<T> Track<T>
+where
+    T: Interpolate,
+{
+    /// Value of the track at the given time, or `None` if the track has no keyframes.
+    pub fn sample(&self, time: f64) -> Option<T> {
+        let next = self
+            .keyframes
+            .partition_point(|keyframe| keyframe.time <= time);
+
+        if next == 0 {
+            return self.keyframes.first().map(|keyframe| keyframe.value);
+        }
+
+        let previous = &self.keyframes[next - 1];
+
+        let Some(next) = self.keyframes.get(next) else {
+            return Some(previous.value);
+        };
+
+        let t = (time - previous.time) / (next.time - previous.time);
+
+        Some(previous.value.interpolate(next.value, next.easing.apply(t)))
+    }
+}
+
+impl<T> Default for Track<T> {
+    fn default() -> Self {
+        Self { keyframes: vec![] }
+    }
+}
+
+impl TransformTracks {
+    /// Transformation given by the tracks at the given time.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the scaling track scales any component to zero.
+    ///
+    pub fn transform_at(&self, time: f64) -> Result<Transform, transform::Error> {
+        let mut transform = Transform::default();
+
+        if let Some(Vector(scaling)) = self.scaling.sample(time) {
+            transform = Transform::scaling(scaling.x, scaling.y, scaling.z)?;
+        }
+
+        if let Some(Vector(degrees)) = self.rotation.sample(time) {
+            transform = Transform::rotation_z(degrees.z.to_radians())
+                * Transform::rotation_y(degrees.y.to_radians())
+                * Transform::rotation_x(degrees.x.to_radians())
+                * transform;
+        }
+
+        if let Some(Vector(offset)) = self.translation.sample(time) {
+            transform = Transform::translation(offset.x, offset.y, offset.z) * transform;
+        }
+
+        Ok(transform)
+    }
+}
+
+impl ObjectAnimation {
+    /// Transformation of the shape at the given time, from the transformation it has at rest.
+    fn transform_at(&self, rest: Transform, time: f64) -> Result<Transform, transform::Error> {
+        let Point(pivot) = rest * Point::new(0.0, 0.0, 0.0);
+
+        Ok(Transform::translation(pivot.x, pivot.y, pivot.z)
+            * self.tracks.transform_at(time)?
+            * Transform::translation(-pivot.x, -pivot.y, -pivot.z)
+            * rest)
+    }
+}
+
+impl AnimatedWorld {
+    /// Prepares a world to be moved by an animation, keeping the transformation that each
+    /// animated shape has at rest.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the animation refers to a shape that is not in the world.
+    ///
+    pub fn new(world: World, animation: Animation) -> Result<Self, Error> {
+        let rest = animation
+            .objects
+            .iter()
+            .map(|object| {
+                world
+                    .shape(object.object)
+                    .map(|shape| shape.transform())
+                    .ok_or(Error::MissingObject(object.object))
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(Self {
+            world,
+            animation,
+            rest,
+        })
+    }
+
+    /// World with the animated objects where they were at the last time that was set, or at rest
+    /// if no time was set yet.
+    pub fn world(&self) -> &World {
+        &self.world
+    }
+
+    /// Animation that moves the world.
+    pub fn animation(&self) -> &Animation {
+        &self.animation
+    }
+
+    /// Moves every animated object to where it is at the given time, and returns the world.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the tracks of an animated object produce an invalid transformation, in which case
+    /// no object is moved.
+    ///
+    pub fn set_time(&mut self, time: f64) -> Result<&World, Error> {
+        let transforms = self
+            .animation
+            .objects
+            .iter()
+            .zip(&self.rest)
+            .map(|(object, rest)| object.transform_at(*rest, time))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        for (object, transform) in self.animation.objects.iter().zip(transforms) {
+            self.world
+                .set_transform(object.object, transform)
+                .map_err(|_| Error::MissingObject(object.object))?;
+        }
+
+        Ok(&self.world)
+    }
+
+    /// Renders the frame at the given time with [motion blur](MotionBlur), adding one sample
+    /// per sub-frame to every pixel of an [AccumulationBuffer]. Returns statistics about the
+    /// rays casted during the process.
+    ///
+    /// Only the objects move during the exposure, so a moving camera should be placed where it
+    /// is at the time of the frame. The objects are left where they are at that time.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the objects can't be [moved](AnimatedWorld::set_time) to the time of any of the
+    /// sub-frames.
+    ///
+    /// # Panics:
+    ///
+    /// * If the dimensions of the buffer don't match the dimensions of the camera.
+    ///
+    pub fn render_motion_blur(
+        &mut self,
+        camera: &Camera,
+        settings: &RenderSettings,
+        blur: &MotionBlur,
+        time: f64,
+        buffer: &mut AccumulationBuffer,
+    ) -> Result<RenderStats, Error> {
+        let mut stats = RenderStats::default();
+
+        for time in blur.times(time) {
+            let world = self.set_time(time)?;
+            stats += camera.render_samples(world, settings, buffer, 1);
+        }
+
+        self.set_time(time)?;
+
+        Ok(stats)
+    }
+}
+
+impl CameraPath {
+    /// Transformation of the camera at the given time, or `None` if the path has no keyframes.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the camera is at the same point it looks at.
+    ///
+    pub fn transform_at(&self, time: f64) -> Option<Result<Transform, transform::Error>> {
+        let from = self.from.sample(time)?;
+        let to = self.to.sample(time)?;
+
+        Some(
+            Transform::view(from, to, self.up).map(|view| match self.shake {
+                Some(shake) => shake.transform_at(time) * view,
+                None => view,
+            }),
+        )
+    }
+
+    /// Returns a copy of the given camera moved to where the path is at the given time, and
+    /// focused on the target of the path in the given world, which should be the world at that
+    /// same time. The camera is left where it is if the path has no keyframes.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the camera is at the same point it looks at, or if it can't
+    /// [focus](Camera::focus_on) on it's target.
+    ///
+    pub fn camera_at(&self, camera: &Camera, world: &World, time: f64) -> Result<Camera, Error> {
+        let camera = match self.transform_at(time) {
+            Some(transform) => camera.with_transform(transform?),
+            None => *camera,
+        };
+
+        match self.focus {
+            Some(target) => Ok(camera.focus_on(world, target)?),
+            None => Ok(camera),
+        }
+    }
+}
+
+impl CameraShake {
+    /// Rotation of the camera around it's `x`, `y` and `z` axes at the given time, in degrees.
+    pub fn rotation_at(&self, time: f64) -> Vector {
+        let noise = |axis| self.amplitude * self.noise(axis, time * self.frequency);
+
+        Vector::new(noise(0), noise(1), noise(2))
+    }
+
+    /// Transformation applied on top of the view of the camera at the given time.
+    fn transform_at(&self, time: f64) -> Transform {
+        let Vector(degrees) = self.rotation_at(time);
+
+        Transform::rotation_z(degrees.z.to_radians())
+            * Transform::rotation_y(degrees.y.to_radians())
+            * Transform::rotation_x(degrees.x.to_radians())
+    }
+
+    /// Smooth value noise from `-1.0` to `1.0`, that blends random values placed at every whole
+    /// number.
+    fn noise(&self, axis: u64, x: f64) -> f64 {
+        let cell = x.floor();
+        let t = x - cell;
+
+        let value = |cell: f64| {
+            let hash = splitmix64(self.seed ^ splitmix64(axis) ^ (cell as i64 as u64));
+            (hash >> 11) as f64 / (1_u64 << 53) as f64 * 2.0 - 1.0
+        };
+
+        value(cell).interpolate(value(cell + 1.0), Easing::EaseInOut.apply(t))
+    }
+}
+
+impl MotionBlur {
+    /// Times at which the sub-frames of the frame at the given time are rendered.
+    ///
+    /// Sub-frames are spread evenly over the [curve](ShutterCurve) of the shutter, so more of
+    /// them are taken while it's fully open.
+    ///
+    pub fn times(&self, time: f64) -> impl Iterator<Item = f64> + '_ {
+        let samples = self.samples.max(1);
+
+        (0..samples).map(move |i| {
+            let u = (i as f64 + 0.5) / samples as f64;
+            time + (self.curve.sample(u) - 0.5) * self.shutter
+        })
+    }
+}
+
+impl ShutterCurve {
+    /// How much the shutter is open at the given fraction of the exposure, from `0.0` to `1.0`,
+    /// scaled so that the area under the curve is `1.0`.
+    pub fn weight(self, t: f64) -> f64 {
+        if !(0.0..=1.0).contains(&t) {
+            return 0.0;
+        }
+
+        let ramp = self.ramp();
+        let height = 1.0 / (1.0 - ramp);
+        let distance_to_edge = t.min(1.0 - t);
+
+        if distance_to_edge < ramp {
+            height * distance_to_edge / ramp
+        } else {
+            height
+        }
+    }
+
+    /// Maps a fraction of the light let in by the shutter, from `0.0` to `1.0`, to the fraction
+    /// of the exposure when it was let in. Evenly spread values are mapped to times that are
+    /// denser where the shutter is more open.
+    pub fn sample(self, u: f64) -> f64 {
+        let u = u.clamp(0.0, 1.0);
+        let ramp = self.ramp();
+        let height = 1.0 / (1.0 - ramp);
+
+        // Fraction of the light let in while the shutter is opening, and while it's closing.
+        let edge = height * ramp / 2.0;
+
+        if u < edge {
+            (2.0 * ramp * u / height).sqrt()
+        } else if u > 1.0 - edge {
+            1.0 - (2.0 * ramp * (1.0 - u) / height).sqrt()
+        } else {
+            ramp + (u - edge) / height
+        }
+    }
+
+    fn ramp(self) -> f64 {
+        match self {
+            Self::Box => 0.0,
+            Self::Triangle => 0.5,
+            Self::Trapezoid { ramp } => ramp.clamp(0.0, 0.5),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        assert_approx,
+        ray::Ray,
+        shape::{Shape, ShapeBuilder, Sphere},
+    };
+
+    use super::*;
+
+    fn keyframe<T>(time: f64, value: T, easing: Easing) -> Keyframe<T> {
+        Keyframe {
+            time,
+            value,
+            easing,
+        }
+    }
+
+    #[test]
+    fn easing_curves_start_and_end_at_the_keyframes() {
+        for easing in [
+            Easing::Linear,
+            Easing::EaseIn,
+            Easing::EaseOut,
+            Easing::EaseInOut,
+            Easing::Step,
+        ] {
+            assert_approx!(easing.apply(0.0), 0.0);
+            assert_approx!(easing.apply(1.0), 1.0);
+        }
+    }
+
+    #[test]
+    fn easing_curves_at_the_middle_of_a_keyframe() {
+        assert_approx!(Easing::Linear.apply(0.5), 0.5);
+        assert_approx!(Easing::EaseIn.apply(0.5), 0.25);
+        assert_approx!(Easing::EaseOut.apply(0.5), 0.75);
+        assert_approx!(Easing::EaseInOut.apply(0.25), 0.125);
+        assert_approx!(Easing::EaseInOut.apply(0.75), 0.875);
+        assert_approx!(Easing::Step.apply(0.99), 0.0);
+    }
+
+    #[test]
+    fn sampling_an_empty_track() {
+        let track: Track<f64> = Track::default();
+
+        assert!(track.is_empty());
+        assert_eq!(track.sample(0.0), None);
+    }
+
+    #[test]
+    fn sampling_a_track_between_keyframes() {
+        let track = Track::new([
+            keyframe(2.0, 10.0, Easing::EaseIn),
+            keyframe(0.0, 0.0, Easing::Linear),
+            keyframe(1.0, 4.0, Easing::Linear),
+        ]);
+
+        assert_eq!(track.keyframes()[1].time, 1.0);
+
+        assert_approx!(track.sample(0.5).unwrap(), 2.0);
+        assert_approx!(track.sample(1.0).unwrap(), 4.0);
+        assert_approx!(track.sample(1.5).unwrap(), 4.0 + 6.0 * 0.25);
+        assert_approx!(track.sample(-1.0).unwrap(), 0.0);
+        assert_approx!(track.sample(5.0).unwrap(), 10.0);
+    }
+
+    #[test]
+    fn the_components_of_a_transform_are_combined_in_order() {
+        let tracks = TransformTracks {
+            translation: Track::new([keyframe(0.0, Vector::new(0.0, 0.0, 5.0), Easing::Linear)]),
+            rotation: Track::new([keyframe(0.0, Vector::new(0.0, 90.0, 0.0), Easing::Linear)]),
+            scaling: Track::new([keyframe(0.0, Vector::new(2.0, 2.0, 2.0), Easing::Linear)]),
+        };
+
+        assert_eq!(
+            tracks.transform_at(0.0).unwrap(),
+            Transform::translation(0.0, 0.0, 5.0)
+                * Transform::rotation_y(std::f64::consts::FRAC_PI_2)
+                * Transform::scaling(2.0, 2.0, 2.0).unwrap()
+        );
+
+        assert_eq!(
+            TransformTracks::default().transform_at(0.0).unwrap(),
+            Transform::default()
+        );
+    }
+
+    #[test]
+    fn trying_to_scale_an_object_to_zero() {
+        let tracks = TransformTracks {
+            scaling: Track::new([keyframe(0.0, Vector::new(0.0, 1.0, 1.0), Easing::Linear)]),
+            ..Default::default()
+        };
+
+        assert!(tracks.transform_at(0.0).is_err());
+    }
+
+    #[test]
+    fn animating_an_object_of_a_world() {
+        let world = World {
+            objects: vec![Shape::Sphere(Default::default())],
+            ..Default::default()
+        };
+
+        let id = world.objects[0].id();
+
+        let animation = Animation {
+            objects: vec![ObjectAnimation {
+                object: id,
+                tracks: TransformTracks {
+                    translation: Track::new([
+                        keyframe(0.0, Vector::new(0.0, 0.0, 0.0), Easing::Linear),
+                        keyframe(1.0, Vector::new(0.0, 4.0, 0.0), Easing::Linear),
+                    ]),
+                    ..Default::default()
+                },
+            }],
+        };
+
+        let ray = Ray {
+            origin: Point::new(0.0, 2.0, -5.0),
+            direction: Vector::new(0.0, 0.0, 1.0),
+        };
+
+        assert!(world.intersect(&ray).is_empty());
+
+        let mut animated = AnimatedWorld::new(world, animation).unwrap();
+        let xs = animated.set_time(0.5).unwrap().intersect(&ray);
+
+        assert_eq!(xs.len(), 2);
+        assert_eq!(xs[0].object.id(), id);
+        drop(xs);
+
+        // Every time is placed from the rest pose, so going back doesn't leave the sphere behind.
+        assert!(animated.set_time(0.0).unwrap().intersect(&ray).is_empty());
+    }
+
+    #[test]
+    fn animated_objects_turn_and_grow_around_their_own_origin() {
+        let sphere = Shape::Sphere(Sphere::from(ShapeBuilder {
+            transform: Transform::translation(5.0, 0.0, 0.0),
+            ..Default::default()
+        }));
+        let id = sphere.id();
+
+        let world = World {
+            objects: vec![sphere],
+            ..Default::default()
+        };
+
+        let animation = Animation {
+            objects: vec![ObjectAnimation {
+                object: id,
+                tracks: TransformTracks {
+                    rotation: Track::new([keyframe(
+                        0.0,
+                        Vector::new(0.0, 90.0, 0.0),
+                        Easing::Linear,
+                    )]),
+                    scaling: Track::new([keyframe(
+                        0.0,
+                        Vector::new(2.0, 2.0, 2.0),
+                        Easing::Linear,
+                    )]),
+                    ..Default::default()
+                },
+            }],
+        };
+
+        let mut animated = AnimatedWorld::new(world, animation).unwrap();
+        let world = animated.set_time(0.0).unwrap();
+        let transform = world.shape(id).unwrap().transform();
+
+        assert_eq!(
+            transform * Point::new(0.0, 0.0, 0.0),
+            Point::new(5.0, 0.0, 0.0)
+        );
+        assert_eq!(
+            transform * Point::new(1.0, 0.0, 0.0),
+            Point::new(5.0, 0.0, -2.0)
+        );
+    }
+
+    fn camera() -> Camera {
+        Camera::try_from(crate::camera::CameraBuilder {
+            width: 11,
+            height: 11,
+            field_of_view: std::f64::consts::FRAC_PI_2,
+            transform: Default::default(),
+        })
+        .unwrap()
+    }
+
+    fn camera_path(shake: Option<CameraShake>) -> CameraPath {
+        CameraPath {
+            from: Track::new([
+                keyframe(0.0, Point::new(0.0, 0.0, -5.0), Easing::Linear),
+                keyframe(1.0, Point::new(0.0, 0.0, -3.0), Easing::Linear),
+            ]),
+            to: Track::new([keyframe(0.0, Point::new(0.0, 0.0, 0.0), Easing::Linear)]),
+            up: Vector::new(0.0, 1.0, 0.0),
+            shake,
+            focus: None,
+        }
+    }
+
+    #[test]
+    fn moving_a_camera_along_a_path() {
+        let camera = camera_path(None)
+            .camera_at(&camera(), &World::default(), 0.5)
+            .unwrap();
+        let ray = camera.ray_for_pixel(5, 5);
+
+        assert_eq!(ray.origin, Point::new(0.0, 0.0, -4.0));
+        assert_eq!(ray.direction, Vector::new(0.0, 0.0, 1.0));
+    }
+
+    #[test]
+    fn a_camera_path_without_keyframes_leaves_the_camera_untouched() {
+        let path = CameraPath {
+            from: Track::default(),
+            to: Track::default(),
+            up: Vector::new(0.0, 1.0, 0.0),
+            shake: None,
+            focus: None,
+        };
+
+        assert_eq!(
+            path.camera_at(&camera(), &World::default(), 0.0).unwrap(),
+            camera()
+        );
+    }
+
+    #[test]
+    fn a_shaking_camera_turns_without_leaving_it_s_path() {
+        let shake = CameraShake {
+            amplitude: 2.0,
+            frequency: 3.0,
+            seed: 42,
+        };
+
+        let steady = camera_path(None)
+            .camera_at(&camera(), &World::default(), 0.3)
+            .unwrap();
+        let shaking = camera_path(Some(shake))
+            .camera_at(&camera(), &World::default(), 0.3)
+            .unwrap();
+
+        let steady = steady.ray_for_pixel(5, 5);
+        let shaking = shaking.ray_for_pixel(5, 5);
+
+        assert_eq!(shaking.origin, steady.origin);
+        assert_ne!(shaking.direction, steady.direction);
+
+        let angle = shaking.direction.dot(steady.direction).acos().to_degrees();
+        assert!(angle <= 2.0 * 3.0_f64.sqrt());
+    }
+
+    #[test]
+    fn camera_shake_is_smooth_bounded_and_repeatable() {
+        let shake = CameraShake {
+            amplitude: 1.5,
+            frequency: 2.0,
+            seed: 7,
+        };
+
+        let mut previous = shake.rotation_at(0.0);
+
+        for step in 1..=1000 {
+            let time = f64::from(step) * 0.001;
+            let rotation = shake.rotation_at(time);
+
+            for degrees in [rotation.0.x, rotation.0.y, rotation.0.z] {
+                assert!(degrees.abs() <= 1.5);
+            }
+
+            assert!((rotation - previous).magnitude() < 0.05);
+            assert_eq!(rotation, shake.rotation_at(time));
+
+            previous = rotation;
+        }
+
+        let other = CameraShake { seed: 8, ..shake };
+        assert_ne!(shake.rotation_at(0.5), other.rotation_at(0.5));
+    }
+
+    #[test]
+    fn a_shake_without_amplitude_does_nothing() {
+        let shake = CameraShake {
+            amplitude: 0.0,
+            frequency: 2.0,
+            seed: 7,
+        };
+
+        assert_eq!(
+            camera_path(Some(shake))
+                .camera_at(&camera(), &World::default(), 0.7)
+                .unwrap(),
+            camera_path(None)
+                .camera_at(&camera(), &World::default(), 0.7)
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn an_animated_camera_keeps_it_s_target_in_focus() {
+        let world = World {
+            objects: vec![Shape::Sphere(Default::default())],
+            ..Default::default()
+        };
+
+        let path = CameraPath {
+            focus: Some(FocusTarget::Object(world.objects[0].id())),
+            ..camera_path(None)
+        };
+
+        let camera = camera().with_depth_of_field(0.1, 1.0).unwrap();
+
+        for (time, distance) in [(0.0, 5.0), (0.5, 4.0), (1.0, 3.0)] {
+            let camera = path.camera_at(&camera, &world, time).unwrap();
+            assert_approx!(camera.focal_distance(), distance);
+        }
+
+        assert_eq!(
+            path.camera_at(&camera, &World::default(), 0.0).unwrap_err(),
+            Error::InvalidFocus(camera::Error::MissingFocusObject)
+        );
+    }
+
+    #[test]
+    fn trying_to_animate_a_missing_object() {
+        let missing = Shape::Sphere(Default::default()).id();
+
+        let animation = Animation {
+            objects: vec![ObjectAnimation {
+                object: missing,
+                tracks: Default::default(),
+            }],
+        };
+
+        assert_eq!(
+            AnimatedWorld::new(World::default(), animation).unwrap_err(),
+            Error::MissingObject(missing)
+        );
+    }
+
+    #[test]
+    fn a_box_shutter_spreads_sub_frames_evenly() {
+        let blur = MotionBlur {
+            shutter: 1.0,
+            curve: ShutterCurve::Box,
+            samples: 4,
+        };
+
+        let times: Vec<_> = blur.times(10.0).collect();
+
+        for (time, expected) in times.into_iter().zip([9.625, 9.875, 10.125, 10.375]) {
+            assert_approx!(time, expected);
+        }
+    }
+
+    #[test]
+    fn a_triangle_shutter_takes_more_sub_frames_at_the_middle() {
+        assert_approx!(ShutterCurve::Triangle.sample(0.125), 0.25);
+        assert_approx!(ShutterCurve::Triangle.sample(0.5), 0.5);
+        assert_approx!(ShutterCurve::Triangle.sample(0.875), 0.75);
+
+        assert_approx!(ShutterCurve::Triangle.weight(0.25), 1.0);
+        assert_approx!(ShutterCurve::Triangle.weight(0.5), 2.0);
+        assert_approx!(ShutterCurve::Triangle.weight(1.5), 0.0);
+    }
+
+    #[test]
+    fn shutter_curves_let_in_all_the_light() {
+        for curve in [
+            ShutterCurve::Box,
+            ShutterCurve::Triangle,
+            ShutterCurve::Trapezoid { ramp: 0.2 },
+        ] {
+            let steps = 10_000;
+            let light_until = |t: f64| {
+                (0..steps)
+                    .map(|i| curve.weight((i as f64 + 0.5) / steps as f64 * t) * t)
+                    .sum::<f64>()
+                    / steps as f64
+            };
+
+            assert!((light_until(1.0) - 1.0).abs() < 1e-3);
+
+            for u in [0.05, 0.3, 0.5, 0.9] {
+                assert!((light_until(curve.sample(u)) - u).abs() < 1e-3);
+            }
+        }
+    }
+
+    #[test]
+    fn rendering_a_frame_with_motion_blur() {
+        let camera = camera();
+        let blur = MotionBlur {
+            shutter: 1.0,
+            curve: ShutterCurve::Triangle,
+            samples: 3,
+        };
+
+        let mut buffer = AccumulationBuffer::new(camera.width(), camera.height());
+
+        let stats = AnimatedWorld::new(World::default(), Animation::default())
+            .unwrap()
+            .render_motion_blur(&camera, &RenderSettings::default(), &blur, 0.0, &mut buffer)
+            .unwrap();
+
+        assert_eq!(buffer.sample_count(5, 5), 3);
+        assert_eq!(stats.primary_rays, 3 * 11 * 11);
+    }
+}
@@ -1,19 +1,48 @@
 use std::{
+    collections::HashMap,
     num::NonZeroUsize,
     sync::{Arc, Mutex},
 };
 
-use indicatif::ProgressBar;
-use rayon::ThreadPoolBuilder;
+use rayon::ThreadPoolBuildError;
+use serde::Deserialize;
 use thiserror::Error;
 
-use crate::{canvas::Canvas, float, ray::Ray, transform::Transform, tuple::Point, world::World};
+use crate::{
+    canvas::Canvas,
+    color, float,
+    intersection::Intersection,
+    progress::{ProgressBar, Stopwatch},
+    ray::Ray,
+    render::{
+        self, AccumulationBuffer, AdaptiveSampling, HalfResolutionBuffer, RayPath, RayPaths,
+        RenderSettings, RenderStats, ReprojectedPixel, ReprojectionCache, SurfaceSample, Tile,
+        TileTimes,
+    },
+    sampling::splitmix64,
+    scene::UnknownFields,
+    shape::ShapeId,
+    transform::{self, Transform},
+    tuple::{Point, Tuple, Vector},
+    world::{UnitScale, World, DEFAULT_LIGHT_GROUP},
+};
+
+#[cfg(feature = "tokio")]
+use std::{any::Any, panic::AssertUnwindSafe};
+
+#[cfg(feature = "tokio")]
+use crate::render::RenderedTile;
 
 /// Module constants.
 pub mod consts;
 
-/// Default number of threads using during the world-rendering process.
-const DEFAULT_RENDER_THREADS: usize = 8;
+mod lens;
+
+pub use self::lens::{Lens, SensorSize};
+
+/// Mixed into the seed of a pixel to seed the samples of the lens, so they're not shifted by the
+/// same offset as the samples of the pixel.
+const LENS_SALT: u64 = 0x6c65_6e73_5f73_616c;
 
 /// The error type when trying to create a camera.
 ///
@@ -31,6 +60,29 @@ pub enum Error {
     ///
     #[error("field of view angle cannot be straight")]
     MultipleOfPiFieldOfView,
+
+    /// The error type when trying to give a camera a negative aperture, or a focal distance that
+    /// is not positive.
+    #[error("aperture cannot be negative and focal distance must be positive")]
+    InvalidDepthOfField,
+
+    /// The error type when trying to focus a camera on an object that is not in the world.
+    #[error("focus object is not in the world")]
+    MissingFocusObject,
+
+    /// The error type when trying to focus a camera on a point that is behind it.
+    #[error("focus point cannot be behind the camera")]
+    FocusBehindCamera,
+
+    /// The error type when trying to give a camera a projection that can't cover it's field of
+    /// view, such as a stereographic projection of a full turn.
+    #[error("field of view is too wide for the projection")]
+    FieldOfViewTooWide,
+
+    /// The error type when trying to parse a [Lens] from a description that doesn't have a
+    /// positive focal length, has an f-number that isn't positive, or names an unknown sensor.
+    #[error("invalid lens `{0}`")]
+    InvalidLens(String),
 }
 
 /// Viewport into a scene.
@@ -60,7 +112,30 @@ pub enum Error {
 /// }).unwrap();
 /// ```
 ///
-#[derive(Copy, Clone, Debug)]
+/// # Deserialization
+///
+/// Cameras are deserialized from the fields of a [CameraBuilder], except that the field of view
+/// is given in degrees.
+///
+/// ```json
+/// {
+///     "width": 1920,
+///     "height": 1080,
+///     "field_of_view": 60.0,
+///     "transform": {
+///         "type": "view",
+///         "from": { "x": 0.0, "y": 1.5, "z": -5.0 },
+///         "to": { "x": 0.0, "y": 1.0, "z": 0.0 },
+///         "up": { "x": 0.0, "y": 1.0, "z": 0.0 }
+///     }
+/// }
+/// ```
+///
+/// A camera can also be given an `aperture` and a `focal_distance` to enable it's [depth of
+/// field](Camera::with_depth_of_field), and a `projection`, which is `"perspective"` by default.
+///
+#[derive(Copy, Clone, Debug, Deserialize)]
+#[serde(try_from = "CameraDeserializer")]
 pub struct Camera {
     hsize: usize,
     vsize: usize,
@@ -70,6 +145,9 @@ pub struct Camera {
     half_width: f64,
     transform: Transform,
     transform_inverse: Transform,
+    aperture: f64,
+    focal_distance: f64,
+    projection: Projection,
 }
 
 /// Builder for a camera.
@@ -92,6 +170,74 @@ pub struct CameraBuilder {
     pub transform: Transform,
 }
 
+/// How a camera [maps the directions](Camera::with_projection) around it to the image.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all(deserialize = "snake_case"))]
+pub enum Projection {
+    /// Rays through a flat image plane in front of the camera, which keeps straight lines
+    /// straight, like a real lens.
+    #[default]
+    Perspective,
+
+    /// The [stereographic
+    /// projection](https://en.wikipedia.org/wiki/Stereographic_projection) of the sphere of
+    /// directions around the camera, centered on the direction the camera looks at.
+    ///
+    /// It can cover any field of view narrower than a full turn. A camera looking straight down
+    /// with a field of view of about 300 degrees wraps the ground around the image into a "little
+    /// planet", with the sky around it.
+    ///
+    Stereographic,
+}
+
+/// What a camera [focuses on](Camera::focus_on).
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum FocusTarget {
+    /// A point of the world.
+    Point(Point),
+
+    /// The center of an object of the world.
+    Object(ShapeId),
+}
+
+#[derive(Debug, PartialEq, Deserialize)]
+struct CameraDeserializer {
+    width: usize,
+    height: usize,
+    field_of_view: f64,
+
+    #[serde(default, deserialize_with = "transform::deserialize_chain")]
+    transform: Transform,
+
+    #[serde(default)]
+    aperture: f64,
+
+    focal_distance: Option<f64>,
+
+    #[serde(default)]
+    projection: Projection,
+
+    #[serde(flatten)]
+    _unknown: UnknownFields,
+}
+
+impl TryFrom<CameraDeserializer> for Camera {
+    type Error = Error;
+
+    fn try_from(value: CameraDeserializer) -> Result<Self, Self::Error> {
+        let camera = Self::try_from(CameraBuilder {
+            width: value.width,
+            height: value.height,
+            field_of_view: value.field_of_view.to_radians(),
+            transform: value.transform,
+        })?
+        .with_projection(value.projection)?;
+
+        let focal_distance = value.focal_distance.unwrap_or(camera.focal_distance);
+        camera.with_depth_of_field(value.aperture, focal_distance)
+    }
+}
+
 impl TryFrom<CameraBuilder> for Camera {
     type Error = Error;
 
@@ -111,27 +257,62 @@ impl TryFrom<CameraBuilder> for Camera {
 
         let vsize = NonZeroUsize::new(vsize).ok_or(Error::NullDimension)?.get();
 
-        let half_view = (field_of_view / 2.0).tan();
-        let aspect = hsize as f64 / vsize as f64;
-
-        let (half_width, half_height) = if aspect < 1.0 {
-            (half_view * aspect, half_view)
-        } else {
-            (half_view, half_view / aspect)
-        };
-
-        let pixel_size = (half_width * 2.0) / hsize as f64;
-
-        Ok(Self {
+        let camera = Self {
             hsize,
             vsize,
             field_of_view,
-            pixel_size,
-            half_height,
-            half_width,
+            pixel_size: 0.0,
+            half_height: 0.0,
+            half_width: 0.0,
             transform,
             transform_inverse: transform.inverse(),
-        })
+            aperture: 0.0,
+            focal_distance: 1.0,
+            projection: Projection::Perspective,
+        };
+
+        Ok(camera.with_half_view((field_of_view / 2.0).tan()))
+    }
+}
+
+impl Projection {
+    /// Direction, relative to the camera, of the point `(x, y)` of the image plane, which is at
+    /// one unit in front of the camera.
+    fn direction(self, x: f64, y: f64) -> Vector {
+        match self {
+            Self::Perspective => Vector::new(x, y, -1.0),
+            Self::Stereographic => {
+                let q = (x * x + y * y) / 4.0;
+                Vector::new(x, y, q - 1.0) * (1.0 + q).recip()
+            }
+        }
+    }
+
+    /// Point of the image plane, if any, that maps to the given direction relative to the
+    /// camera. This is the inverse of [Projection::direction].
+    fn image_point(self, direction: Vector) -> Option<(f64, f64)> {
+        let Vector(Tuple { x, y, z, .. }) = direction;
+
+        match self {
+            // The camera looks towards the negative `z` axis, with the image at `z = -1`.
+            Self::Perspective => (z < 0.0).then(|| (-x / z, -y / z)),
+            Self::Stereographic => {
+                let Vector(Tuple { x, y, z, .. }) = direction.normalize().ok()?;
+                (z < 1.0).then(|| (2.0 * x / (1.0 - z), 2.0 * y / (1.0 - z)))
+            }
+        }
+    }
+}
+
+impl From<Point> for FocusTarget {
+    fn from(point: Point) -> Self {
+        Self::Point(point)
+    }
+}
+
+impl From<ShapeId> for FocusTarget {
+    fn from(id: ShapeId) -> Self {
+        Self::Object(id)
     }
 }
 
@@ -145,15 +326,22 @@ impl PartialEq for Camera {
             && float::approx(self.half_height, other.half_height)
             && self.transform == other.transform
             && self.transform_inverse == other.transform_inverse
+            && float::approx(self.aperture, other.aperture)
+            && float::approx(self.focal_distance, other.focal_distance)
+            && self.projection == other.projection
     }
 }
 
 impl Camera {
     /// Renders the given world using the camera.
     ///
-    /// The rendering process is multithreaded by default, using a thread-pool with a default
-    /// number of threads. This value can be overridden passing the environment variable
-    /// `RENDER_THREADS` with the desired number of threads.
+    /// The rendering process is multithreaded by default, using a thread-pool with a fixed
+    /// number of threads that the environment doesn't change, such as with `RENDER_THREADS`.
+    /// Render with the settings [built from](RenderSettings::from_config) a
+    /// [Config](crate::config::Config) to use a different number of threads.
+    ///
+    /// This is the same as calling [Camera::render_with_settings] with the default
+    /// [RenderSettings].
     ///
     /// # Panics:
     ///
@@ -161,272 +349,2332 @@ impl Camera {
     /// * If [Mutex::lock](https://doc.rust-lang.org/std/sync/struct.Mutex.html#method.lock) fails.
     ///
     pub fn render(&self, world: &World) -> Canvas {
-        let mut image = Canvas::new(self.hsize, self.vsize);
-        let mutex = Arc::new(Mutex::new(&mut image));
+        self.render_with_settings(world, &RenderSettings::default())
+    }
 
-        let threads: usize = std::env::var("RENDER_THREADS")
-            .map_or(DEFAULT_RENDER_THREADS, |value| {
-                value.parse().unwrap_or(DEFAULT_RENDER_THREADS)
-            });
+    /// Renders the given world using the camera with custom settings.
+    ///
+    /// The image is divided in tiles that are rendered in parallel, and dispatched to the
+    /// thread-pool in the order given by the settings' [TileOrder](crate::render::TileOrder).
+    ///
+    /// # Panics:
+    ///
+    /// * If [ThreadPoolBuilder::build](https://docs.rs/rayon/latest/rayon/struct.ThreadPoolBuilder.html#method.build) fails.
+    /// * If [Mutex::lock](https://doc.rust-lang.org/std/sync/struct.Mutex.html#method.lock) fails.
+    ///
+    pub fn render_with_settings(&self, world: &World, settings: &RenderSettings) -> Canvas {
+        self.render_with_stats(world, settings).0
+    }
 
-        let pool = ThreadPoolBuilder::new()
-            .num_threads(threads)
-            .build()
-            .unwrap();
+    /// Renders the given world using the camera with custom settings, and returns statistics
+    /// about the rays casted during the process alongside the image.
+    ///
+    /// Each thread counts it's own rays, and the counters are only merged once a tile is done, so
+    /// gathering the statistics doesn't introduce contention between threads.
+    ///
+    /// # Panics:
+    ///
+    /// * If [ThreadPoolBuilder::build](https://docs.rs/rayon/latest/rayon/struct.ThreadPoolBuilder.html#method.build) fails.
+    /// * If [Mutex::lock](https://doc.rust-lang.org/std/sync/struct.Mutex.html#method.lock) fails.
+    ///
+    pub fn render_with_stats(
+        &self,
+        world: &World,
+        settings: &RenderSettings,
+    ) -> (Canvas, RenderStats) {
+        let (image, stats, _) = self.render_with_tile_times(world, settings);
+        (image, stats)
+    }
 
-        let progress_bar = if std::env::args().any(|arg| arg == "--progress") {
-            ProgressBar::new((self.hsize * self.vsize) as u64)
-        } else {
-            ProgressBar::hidden()
-        };
+    /// Renders the given world using the camera with custom settings, and returns statistics
+    /// about the rays casted during the process and the time each tile took to render alongside
+    /// the image.
+    ///
+    /// The [heatmap](TileTimes::to_heatmap) of the times shows which regions of the image, and
+    /// thus which objects and materials, are the most expensive to render.
+    ///
+    /// # Panics:
+    ///
+    /// * If [ThreadPoolBuilder::build](https://docs.rs/rayon/latest/rayon/struct.ThreadPoolBuilder.html#method.build) fails.
+    /// * If [Mutex::lock](https://doc.rust-lang.org/std/sync/struct.Mutex.html#method.lock) fails.
+    ///
+    pub fn render_with_tile_times(
+        &self,
+        world: &World,
+        settings: &RenderSettings,
+    ) -> (Canvas, RenderStats, TileTimes) {
+        if settings.half_resolution_indirect {
+            return self.render_with_half_resolution_indirect(world, settings);
+        }
 
-        pool.scope(|s| {
-            for y in 0..self.vsize {
-                let image = Arc::clone(&mutex);
-                let progress_bar = ProgressBar::clone(&progress_bar);
+        let mut image = Canvas::new(self.hsize, self.vsize);
 
-                s.spawn(move |_| {
-                    let mut buffer = Vec::with_capacity(self.hsize);
+        let (stats, times) = self.render_tiles(
+            settings,
+            |x, y| {
+                render::record(|stats| stats.primary_rays += 1);
+                settings
+                    .integrator
+                    .color_at(world, &self.ray_for_pixel(x, y))
+            },
+            |x, y, color| image.write_pixel(x, y, color),
+        );
 
-                    for x in 0..self.hsize {
-                        let ray = self.ray_for_pixel(x, y);
-                        let color = world.color_at(&ray, crate::world::RECURSION_DEPTH);
-                        buffer.push((x, color));
+        (image, stats, times)
+    }
 
-                        progress_bar.inc(1);
-                    }
+    /// Renders the contribution of each of the [light groups](crate::light::Light::group) of the
+    /// world to it's own image, by the name of the group, in a single traversal of the world.
+    ///
+    /// The images add up to the image rendered by [Camera::render_with_settings], so the balance
+    /// between the lights can be changed afterwards by scaling each image before adding them up,
+    /// instead of rendering the world again. The color that the integrator can't tell apart by
+    /// light is added to the [DEFAULT_LIGHT_GROUP] image, which is always rendered.
+    ///
+    /// # Examples
+    ///
+    /// Rendering the key light of a world twice as bright as it was lit.
+    ///
+    /// ```
+    /// use raytracer::{
+    ///     camera::{Camera, CameraBuilder},
+    ///     color,
+    ///     light::{Light, PointLight},
+    ///     render::RenderSettings,
+    ///     tuple::Point,
+    ///     world::{World, DEFAULT_LIGHT_GROUP},
+    /// };
+    ///
+    /// let light = |x| Light::Point(PointLight {
+    ///     position: Point::new(x, 10.0, -10.0),
+    ///     intensity: color::consts::WHITE,
+    ///     group: None,
+    /// });
+    ///
+    /// let world = World {
+    ///     lights: vec![light(-10.0).with_group("key"), light(10.0)],
+    ///     ..Default::default()
+    /// };
+    ///
+    /// let camera = Camera::try_from(CameraBuilder {
+    ///     width: 32,
+    ///     height: 32,
+    ///     field_of_view: std::f64::consts::FRAC_PI_3,
+    ///     transform: Default::default(),
+    /// }).unwrap();
+    ///
+    /// let passes = camera.render_light_groups(&world, &RenderSettings::default());
+    /// let image = &(&passes["key"] * 2.0) + &passes[DEFAULT_LIGHT_GROUP];
+    /// ```
+    ///
+    /// # Panics:
+    ///
+    /// * If [ThreadPoolBuilder::build](https://docs.rs/rayon/latest/rayon/struct.ThreadPoolBuilder.html#method.build) fails.
+    /// * If [Mutex::lock](https://doc.rust-lang.org/std/sync/struct.Mutex.html#method.lock) fails.
+    ///
+    pub fn render_light_groups(
+        &self,
+        world: &World,
+        settings: &RenderSettings,
+    ) -> HashMap<String, Canvas> {
+        let groups = world.light_group_members();
+
+        let mut passes: Vec<_> = groups
+            .iter()
+            .map(|_| Canvas::new(self.hsize, self.vsize))
+            .collect();
+
+        self.render_tiles(
+            settings,
+            |x, y| {
+                render::record(|stats| stats.primary_rays += 1);
+
+                let colors = settings
+                    .integrator
+                    .light_colors_at(world, &self.ray_for_pixel(x, y));
+
+                groups
+                    .iter()
+                    .map(|(name, lights)| {
+                        let unlit = if *name == DEFAULT_LIGHT_GROUP {
+                            colors.unlit
+                        } else {
+                            color::consts::BLACK
+                        };
+
+                        lights
+                            .iter()
+                            .filter_map(|&i| colors.lights.get(i))
+                            .fold(unlit, |acc, &color| acc + color)
+                    })
+                    .collect::<Vec<_>>()
+            },
+            |x, y, colors| {
+                for (pass, color) in passes.iter_mut().zip(colors) {
+                    pass.write_pixel(x, y, color);
+                }
+            },
+        );
 
-                    let mut image = image.lock().unwrap();
-                    for (x, pixel) in buffer {
-                        image.write_pixel(x, y, pixel);
-                    }
-                });
-            }
-        });
+        groups
+            .into_iter()
+            .map(|(name, _)| name.to_string())
+            .zip(passes)
+            .collect()
+    }
 
-        image
+    /// Renders a number of samples for every pixel of the image and adds them to an
+    /// [AccumulationBuffer], returning statistics about the rays casted during the process.
+    ///
+    /// Each sample is taken at a random position inside of it's pixel, so calling this method
+    /// repeatedly with the same buffer progressively refines the image.
+    ///
+    /// # Panics:
+    ///
+    /// * If the dimensions of the buffer don't match the dimensions of the camera.
+    /// * If [ThreadPoolBuilder::build](https://docs.rs/rayon/latest/rayon/struct.ThreadPoolBuilder.html#method.build) fails.
+    /// * If [Mutex::lock](https://doc.rust-lang.org/std/sync/struct.Mutex.html#method.lock) fails.
+    ///
+    pub fn render_samples(
+        &self,
+        world: &World,
+        settings: &RenderSettings,
+        buffer: &mut AccumulationBuffer,
+        samples: usize,
+    ) -> RenderStats {
+        self.render_samples_per_pixel(world, settings, buffer, |_, _| samples)
     }
 
-    fn ray_for_pixel(&self, x: usize, y: usize) -> Ray {
-        let xoffset = (x as f64 + 0.5) * self.pixel_size;
-        let yoffset = (y as f64 + 0.5) * self.pixel_size;
+    /// Renders the given world taking more samples for the pixels that are noisier, such as the
+    /// ones in soft shadows or out of focus, and adds them to an [AccumulationBuffer]. Returns
+    /// statistics about the rays casted during the process.
+    ///
+    /// Samples already in the buffer count towards the limits of the [AdaptiveSampling]
+    /// settings, so the buffer can be refined further with more permissive settings.
+    ///
+    /// # Panics:
+    ///
+    /// * If the dimensions of the buffer don't match the dimensions of the camera.
+    /// * If [ThreadPoolBuilder::build](https://docs.rs/rayon/latest/rayon/struct.ThreadPoolBuilder.html#method.build) fails.
+    /// * If [Mutex::lock](https://doc.rust-lang.org/std/sync/struct.Mutex.html#method.lock) fails.
+    ///
+    pub fn render_adaptive(
+        &self,
+        world: &World,
+        settings: &RenderSettings,
+        adaptive: &AdaptiveSampling,
+        buffer: &mut AccumulationBuffer,
+    ) -> RenderStats {
+        let min_samples = adaptive.min_samples.max(2).min(adaptive.max_samples);
+        let samples_per_pass = adaptive.samples_per_pass.max(1);
+
+        let mut stats = self.render_samples_per_pixel(world, settings, buffer, |count, _| {
+            min_samples.saturating_sub(count) as usize
+        });
 
-        let world_x = self.half_width - xoffset;
-        let world_y = self.half_height - yoffset;
+        loop {
+            let mut pending = false;
 
-        let pixel = self.transform_inverse * Point::new(world_x, world_y, -1.0);
-        let origin = self.transform_inverse * Point::new(0.0, 0.0, 0.0);
+            stats += self.render_samples_per_pixel(world, settings, buffer, |count, noise| {
+                if count >= adaptive.max_samples || noise <= adaptive.noise_threshold {
+                    return 0;
+                }
 
-        // The transformation is ensured to be isomorphic, therefore `pixel` and `origin` are
-        // always going to be different points because `Point::new(... -1)` is always different to
-        // `Point::new(... 0)`.
-        //
-        #[allow(clippy::unwrap_used)]
-        let direction = (pixel - origin).normalize().unwrap();
+                pending = true;
+                samples_per_pass.min(adaptive.max_samples - count) as usize
+            });
 
-        Ray { origin, direction }
+            if !pending {
+                break stats;
+            }
+        }
     }
-}
-
-#[cfg(test)]
-mod tests {
-    use crate::{assert_approx, color::Color, tuple::Vector, world::test_world};
 
-    use super::*;
+    /// Renders the indirect color of the image at half of it's resolution and upsamples it with
+    /// a bilateral filter guided by the surfaces seen by every pixel, before adding it to the
+    /// direct color rendered at full resolution.
+    fn render_with_half_resolution_indirect(
+        &self,
+        world: &World,
+        settings: &RenderSettings,
+    ) -> (Canvas, RenderStats, TileTimes) {
+        let mut indirect = HalfResolutionBuffer::new(self.hsize, self.vsize);
+
+        // Every pixel of the half resolution camera is centered on the corner shared by a block of
+        // 2x2 full resolution pixels.
+        let half_resolution = Self {
+            hsize: indirect.width(),
+            vsize: indirect.height(),
+            pixel_size: self.pixel_size * 2.0,
+            ..*self
+        };
 
-    #[test]
-    fn constructing_a_camera() {
-        let hsize = 160;
-        let vsize = 120;
-        let field_of_view = std::f64::consts::FRAC_PI_2;
+        let (mut stats, half_resolution_times) = half_resolution.render_tiles(
+            settings,
+            |x, y| {
+                let ray = half_resolution.ray_for_pixel(x, y);
+                render::record(|stats| stats.primary_rays += 1);
 
-        let c = Camera::try_from(CameraBuilder {
-            width: hsize,
-            height: vsize,
-            field_of_view,
-            transform: Default::default(),
-        })
-        .unwrap();
+                let surface = surface_sample(world, &ray);
+                (settings.integrator.indirect_color_at(world, &ray), surface)
+            },
+            |x, y, (color, surface)| indirect.write_pixel(x, y, color, surface),
+        );
 
-        assert_eq!(c.hsize, hsize);
-        assert_eq!(c.vsize, vsize);
-        assert_approx!(c.field_of_view, std::f64::consts::FRAC_PI_2);
-        assert_eq!(c.transform, Transform::default());
-    }
+        let mut image = Canvas::new(self.hsize, self.vsize);
 
-    #[test]
-    fn the_pixel_size_for_a_horizontal_canvas() {
-        let c = Camera::try_from(CameraBuilder {
-            width: 200,
-            height: 125,
-            field_of_view: std::f64::consts::FRAC_PI_2,
-            transform: Default::default(),
-        })
-        .unwrap();
+        let (full_resolution_stats, mut times) = self.render_tiles(
+            settings,
+            |x, y| {
+                let ray = self.ray_for_pixel(x, y);
+                render::record(|stats| stats.primary_rays += 1);
 
-        assert_approx!(c.pixel_size, 0.01);
-    }
+                let surface = surface_sample(world, &ray);
+                let direct = settings.integrator.direct_color_at(world, &ray);
 
-    #[test]
-    fn the_pixel_size_for_a_vertical_canvas() {
-        let c = Camera::try_from(CameraBuilder {
-            width: 125,
-            height: 200,
-            field_of_view: std::f64::consts::FRAC_PI_2,
-            transform: Default::default(),
-        })
-        .unwrap();
+                direct + indirect.upsample(x, y, surface)
+            },
+            |x, y, color| image.write_pixel(x, y, color),
+        );
 
-        assert_approx!(c.pixel_size, 0.01);
-    }
+        stats += full_resolution_stats;
 
-    #[test]
-    fn constructing_a_ray_through_the_center_of_the_canvas() {
-        let c = Camera::try_from(CameraBuilder {
-            width: 201,
-            height: 101,
-            field_of_view: std::f64::consts::FRAC_PI_2,
-            transform: Default::default(),
-        })
-        .unwrap();
+        // Each half resolution pixel covers a block of 2x2 full resolution pixels.
+        for &(tile, time) in half_resolution_times.tiles() {
+            let tile = Tile {
+                x: tile.x * 2,
+                y: tile.y * 2,
+                width: tile.width * 2,
+                height: tile.height * 2,
+            };
 
-        let r = c.ray_for_pixel(100, 50);
+            times.record(tile, time);
+        }
 
-        assert_eq!(r.origin, Point::new(0.0, 0.0, 0.0));
-        assert_eq!(r.direction, Vector::new(0.0, 0.0, -1.0));
+        (image, stats, times)
     }
 
-    #[test]
-    fn constructing_a_ray_through_a_corner_of_the_canvas() {
-        let c = Camera::try_from(CameraBuilder {
-            width: 201,
-            height: 101,
-            field_of_view: std::f64::consts::FRAC_PI_2,
-            transform: Default::default(),
-        })
-        .unwrap();
-
-        let r = c.ray_for_pixel(0, 0);
+    /// Renders the next frame of an animation, reusing the colors of the previous frame stored in
+    /// a [ReprojectionCache] for the surfaces that were already visible in it. Returns statistics
+    /// about the rays casted during the process alongside the image.
+    ///
+    /// A primary ray is still casted for every pixel to find the surface it sees, but only the
+    /// pixels whose surface wasn't visible in the previous frame are shaded, which skips every
+    /// shadow, reflection and refraction ray for the rest of them. The cache is then updated
+    /// with this frame.
+    ///
+    /// # Panics:
+    ///
+    /// * If the dimensions of the cache don't match the dimensions of the camera.
+    /// * If [ThreadPoolBuilder::build](https://docs.rs/rayon/latest/rayon/struct.ThreadPoolBuilder.html#method.build) fails.
+    /// * If [Mutex::lock](https://doc.rust-lang.org/std/sync/struct.Mutex.html#method.lock) fails.
+    ///
+    pub fn render_reprojected(
+        &self,
+        world: &World,
+        settings: &RenderSettings,
+        cache: &mut ReprojectionCache,
+    ) -> (Canvas, RenderStats) {
+        assert_eq!(
+            (cache.width(), cache.height()),
+            (self.hsize, self.vsize),
+            "the reprojection cache must have the same dimensions as the camera"
+        );
 
-        assert_eq!(r.origin, Point::new(0.0, 0.0, 0.0));
-        assert_eq!(r.direction, Vector::new(0.66519, 0.33259, -0.66851));
-    }
+        let mut image = Canvas::new(self.hsize, self.vsize);
+        let mut pixels = vec![None; self.hsize * self.vsize];
+        let mut reused_pixels = 0;
+
+        let previous = &*cache;
+
+        let (stats, _) = self.render_tiles(
+            settings,
+            |x, y| {
+                let ray = self.ray_for_pixel(x, y);
+                render::record(|stats| stats.primary_rays += 1);
+
+                let mut xs = world.intersect(&ray);
+
+                let Some(hit) = Intersection::hit(&mut xs) else {
+                    return (settings.integrator.color_at(world, &ray), None, false);
+                };
+
+                let object = hit.object.id();
+                let point = ray.position(hit.t);
+
+                let (color, reused) = match previous.lookup(object, point) {
+                    Some(color) => (color, true),
+                    None => (settings.integrator.color_at(world, &ray), false),
+                };
+
+                let pixel = ReprojectedPixel {
+                    object,
+                    point,
+                    color,
+                };
+
+                (color, Some(pixel), reused)
+            },
+            |x, y, (color, pixel, reused)| {
+                image.write_pixel(x, y, color);
+                pixels[y * self.hsize + x] = pixel;
+                reused_pixels += usize::from(reused);
+            },
+        );
 
-    #[test]
-    fn constructing_a_ray_when_the_camera_is_transformed() {
-        let c = Camera::try_from(CameraBuilder {
-            width: 201,
-            height: 101,
-            field_of_view: std::f64::consts::FRAC_PI_2,
-            transform: Transform::rotation_y(std::f64::consts::FRAC_PI_4)
-                * Transform::translation(0.0, -2.0, 5.0),
-        })
-        .unwrap();
+        cache.update(*self, pixels, reused_pixels);
 
-        let r = c.ray_for_pixel(100, 50);
+        (image, stats)
+    }
 
-        assert_eq!(r.origin, Point::new(0.0, 2.0, -5.0));
+    /// Renders as many samples for each pixel as `samples` says, given the number of samples the
+    /// pixel already has in the buffer and it's noise.
+    fn render_samples_per_pixel<F>(
+        &self,
+        world: &World,
+        settings: &RenderSettings,
+        buffer: &mut AccumulationBuffer,
+        mut samples: F,
+    ) -> RenderStats
+    where
+        F: FnMut(u32, f64) -> usize,
+    {
         assert_eq!(
-            r.direction,
-            Vector::new(2_f64.sqrt() / 2.0, 0.0, -2_f64.sqrt() / 2.0)
+            (buffer.width(), buffer.height()),
+            (self.hsize, self.vsize),
+            "the accumulation buffer must have the same dimensions as the camera"
         );
-    }
 
-    #[test]
-    fn rendering_a_world_with_a_camera() {
-        let w = test_world();
+        let samples: Vec<_> = (0..self.vsize)
+            .flat_map(|y| (0..self.hsize).map(move |x| (x, y)))
+            .map(|(x, y)| {
+                let taken = buffer.sample_count(x, y);
+                (taken, samples(taken, buffer.noise(x, y)))
+            })
+            .collect();
 
-        let from = Point::new(0.0, 0.0, -5.0);
-        let to = Point::new(0.0, 0.0, 0.0);
-        let up = Vector::new(0.0, 1.0, 0.0);
+        if samples.iter().all(|&(_, samples)| samples == 0) {
+            return RenderStats::default();
+        }
 
-        let c = Camera::try_from(CameraBuilder {
+        self.render_tiles(
+            settings,
+            |x, y| {
+                let (taken, samples) = samples[y * self.hsize + x];
+
+                // Every pixel has it's own sequence, which is continued on every pass.
+                let seed = (y * self.hsize + x) as u64;
+
+                // The lens takes it's samples from other dimensions of the sampler, so they're not
+                // correlated with the samples of the pixel, and continues them on every pass too.
+                let lens = settings
+                    .sampler
+                    .decorrelated_sequence(taken, splitmix64(seed ^ LENS_SALT));
+
+                settings
+                    .sampler
+                    .seeded_sequence(taken, seed)
+                    .zip(lens)
+                    .take(samples)
+                    .map(|([u, v], lens)| {
+                        let ray = self.ray_through_lens(x as f64 + u, y as f64 + v, lens);
+
+                        render::record(|stats| stats.primary_rays += 1);
+                        settings.integrator.color_at(world, &ray)
+                    })
+                    .collect::<Vec<_>>()
+            },
+            |x, y, colors| {
+                for color in colors {
+                    buffer.add_sample(x, y, color);
+                }
+            },
+        )
+        .0
+    }
+
+    /// Renders the given world in a background thread-pool, and returns a stream of the tiles of
+    /// the image as they're done, so the image can be sent to clients while it's rendered without
+    /// blocking the thread that awaits it.
+    ///
+    /// Tiles are started in the order given by the settings'
+    /// [TileOrder](crate::render::TileOrder), but they may be done in a different order. The
+    /// stream doesn't depend on any particular async runtime, and dropping it cancels the pixels
+    /// that are left to render.
+    ///
+    /// The indirect color is never rendered at half resolution, regardless of the settings.
+    ///
+    /// When the render fails, because it's thread-pool can't be built or because shading a pixel
+    /// panics, the stream yields the [error](crate::render::Error) and ends, instead of ending as
+    /// if the image was done.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::sync::Arc;
+    ///
+    /// use raytracer::{
+    ///     camera::{Camera, CameraBuilder},
+    ///     world::World,
+    /// };
+    /// use tokio_stream::StreamExt;
+    ///
+    /// # tokio::runtime::Builder::new_current_thread().build().unwrap().block_on(async {
+    /// let camera = Camera::try_from(CameraBuilder {
+    ///     width: 64,
+    ///     height: 64,
+    ///     field_of_view: std::f64::consts::FRAC_PI_3,
+    ///     transform: Default::default(),
+    /// }).unwrap();
+    ///
+    /// let mut tiles = camera.render_async(Arc::new(World::default()), Default::default());
+    ///
+    /// while let Some(rendered) = tiles.next().await {
+    ///     let rendered = rendered.unwrap();
+    ///     println!("{:?} has {} pixels", rendered.tile, rendered.pixels.len());
+    /// }
+    /// # });
+    /// ```
+    ///
+    #[cfg(feature = "tokio")]
+    pub fn render_async(
+        &self,
+        world: Arc<World>,
+        settings: RenderSettings,
+    ) -> impl tokio_stream::Stream<Item = Result<RenderedTile, render::Error>> {
+        let (sender, receiver) = tokio::sync::mpsc::unbounded_channel();
+        let camera = *self;
+
+        std::thread::spawn(move || {
+            let tiles = sender.clone();
+
+            let rendered = std::panic::catch_unwind(AssertUnwindSafe(|| {
+                camera.render_tiles_with(
+                    &settings,
+                    |x, y| {
+                        if tiles.is_closed() {
+                            return color::consts::BLACK;
+                        }
+
+                        render::record(|stats| stats.primary_rays += 1);
+                        settings
+                            .integrator
+                            .color_at(&world, &camera.ray_for_pixel(x, y))
+                    },
+                    |tile, pixels| {
+                        // The stream was dropped, so nobody is waiting for the tile anymore.
+                        let _ = tiles.send(Ok(RenderedTile { tile, pixels }));
+                    },
+                )
+            }));
+
+            let err = match rendered {
+                Ok(Ok(_)) => return,
+                Ok(Err(err)) => render::Error::from(err),
+                Err(payload) => render::Error::Panicked(panic_message(payload.as_ref())),
+            };
+
+            let _ = sender.send(Err(err));
+        });
+
+        tokio_stream::wrappers::UnboundedReceiverStream::new(receiver)
+    }
+
+    /// Shades every pixel of the image in tiles rendered in parallel, and hands the results of
+    /// each tile to `write` once it's done.
+    fn render_tiles<T, S, W>(
+        &self,
+        settings: &RenderSettings,
+        shade: S,
+        mut write: W,
+    ) -> (RenderStats, TileTimes)
+    where
+        T: Send,
+        S: Fn(usize, usize) -> T + Sync,
+        W: FnMut(usize, usize, T) + Send,
+    {
+        self.render_tiles_with(settings, shade, |tile, values| {
+            for ((x, y), value) in tile.pixels().zip(values) {
+                write(x, y, value);
+            }
+        })
+        .unwrap()
+    }
+
+    /// Shades every pixel of the image in tiles rendered in parallel, and hands each tile with
+    /// the results of it's pixels, row by row, to `write` once it's done. Returns the statistics
+    /// of the render alongside the time each tile took, or the error of the thread-pool when it
+    /// can't be built.
+    fn render_tiles_with<T, S, W>(
+        &self,
+        settings: &RenderSettings,
+        shade: S,
+        write: W,
+    ) -> Result<(RenderStats, TileTimes), ThreadPoolBuildError>
+    where
+        T: Send,
+        S: Fn(usize, usize) -> T + Sync,
+        W: FnMut(Tile, Vec<T>) + Send,
+    {
+        let mut stats = RenderStats::default();
+        let mut times = TileTimes::new(self.hsize, self.vsize);
+        let mutex = Arc::new(Mutex::new((write, &mut stats, &mut times)));
+
+        let progress_bar = ProgressBar::new((self.hsize * self.vsize) as u64, settings.progress);
+
+        let tiles = settings
+            .tile_order
+            .tiles(self.hsize, self.vsize, settings.tile_size);
+
+        let stopwatch = Stopwatch::start();
+
+        log::info!(
+            "rendering a {}x{} image in {} tiles with {} threads",
+            self.hsize,
+            self.vsize,
+            tiles.len(),
+            settings.threads
+        );
+
+        let shade = &shade;
+        let origin = self.origin();
+
+        // Tiles are spawned in FIFO order so they start rendering in the order they were sorted.
+        render::scope_fifo(settings.threads, |s| {
+            for tile in tiles {
+                let mutex = Arc::clone(&mutex);
+                let progress_bar = ProgressBar::clone(&progress_bar);
+
+                s.spawn_fifo(move |_| {
+                    let tile_stopwatch = Stopwatch::start();
+
+                    // Discard anything recorded by this thread outside of a tile.
+                    render::take_thread_stats();
+
+                    let _viewpoint = render::set_viewpoint(origin);
+
+                    let mut buffer = Vec::with_capacity(tile.width * tile.height);
+
+                    for (x, y) in tile.pixels() {
+                        buffer.push(shade(x, y));
+                        progress_bar.inc(1);
+                    }
+
+                    let tile_stats = render::take_thread_stats();
+
+                    let tile_time = tile_stopwatch.elapsed();
+
+                    let mut guard = mutex.lock().unwrap();
+                    let (write, stats, times) = &mut *guard;
+
+                    write(tile, buffer);
+
+                    **stats += tile_stats;
+                    times.record(tile, tile_time);
+
+                    log::debug!(
+                        "rendered the {}x{} tile at ({}, {}) in {tile_stopwatch}",
+                        tile.width,
+                        tile.height,
+                        tile.x,
+                        tile.y
+                    );
+                });
+            }
+        })?;
+
+        drop(mutex);
+
+        log::info!(
+            "rendered a {}x{} image in {stopwatch}, casting {} rays",
+            self.hsize,
+            self.vsize,
+            stats.total_rays()
+        );
+
+        Ok((stats, times))
+    }
+
+    /// Traces the rays of the given pixels with the integrator of the settings and records every
+    /// ray casted for each of them, which can then be exported to visualize the paths they
+    /// follow.
+    ///
+    /// Pixels are traced one by one in the calling thread with a single ray through their center,
+    /// so only a small subset of the pixels should be recorded.
+    ///
+    /// Only integrators that [record their rays](crate::integrator::Whitted::record_ray_paths)
+    /// give any segments. Every integrator has recording off by default, because it stores every
+    /// ray that it traces, so renders only pay for it when the paths are going to be exported.
+    ///
+    pub fn record_ray_paths<I>(
+        &self,
+        world: &World,
+        settings: &RenderSettings,
+        pixels: I,
+    ) -> RayPaths
+    where
+        I: IntoIterator<Item = (usize, usize)>,
+    {
+        let paths = pixels
+            .into_iter()
+            .map(|(x, y)| RayPath {
+                pixel: (x, y),
+                segments: render::record_path(|| {
+                    settings
+                        .integrator
+                        .color_at(world, &self.ray_for_pixel(x, y));
+                }),
+            })
+            .collect();
+
+        RayPaths { paths }
+    }
+
+    /// Width of the rendered image in number of pixels.
+    pub fn width(&self) -> usize {
+        self.hsize
+    }
+
+    /// Height of the rendered image in number of pixels.
+    pub fn height(&self) -> usize {
+        self.vsize
+    }
+
+    /// Field of view of the camera, in radians.
+    pub fn field_of_view(&self) -> f64 {
+        self.field_of_view
+    }
+
+    /// Computes the ray that starts at the camera and passes through the center of the pixel at
+    /// column `x` and row `y` of the image.
+    ///
+    /// Pixels outside of the image bounds yield rays outside of the camera's field of view.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use raytracer::{
+    ///     camera::{Camera, CameraBuilder},
+    ///     tuple::{Point, Vector},
+    /// };
+    ///
+    /// let camera = Camera::try_from(CameraBuilder {
+    ///     width: 201,
+    ///     height: 101,
+    ///     field_of_view: std::f64::consts::FRAC_PI_2,
+    ///     transform: Default::default(),
+    /// }).unwrap();
+    ///
+    /// let ray = camera.ray_for_pixel(100, 50);
+    ///
+    /// assert_eq!(ray.origin, Point::new(0.0, 0.0, 0.0));
+    /// assert_eq!(ray.direction, Vector::new(0.0, 0.0, -1.0));
+    /// ```
+    ///
+    pub fn ray_for_pixel(&self, x: usize, y: usize) -> Ray {
+        self.ray_for_position(x as f64 + 0.5, y as f64 + 0.5)
+    }
+
+    /// Computes the ray that starts at the camera and passes through an arbitrary position of the
+    /// image, where `(0.0, 0.0)` is the top-left corner of the first pixel and `(width, height)`
+    /// is the bottom-right corner of the last one.
+    ///
+    /// This is useful to cast multiple rays through different spots of the same pixel.
+    ///
+    pub fn ray_for_position(&self, x: f64, y: f64) -> Ray {
+        let pixel = self.transform_inverse * (Point::new(0.0, 0.0, 0.0) + self.direction_at(x, y));
+        let origin = self.origin();
+
+        // The transformation is ensured to be isomorphic, therefore `pixel` and `origin` are
+        // always going to be different points because the direction of every projection is never
+        // null.
+        //
+        #[allow(clippy::unwrap_used)]
+        let direction = (pixel - origin).normalize().unwrap();
+
+        Ray { origin, direction }
+    }
+
+    /// Direction, relative to the camera, that the given position of the image looks at.
+    fn direction_at(&self, x: f64, y: f64) -> Vector {
+        let world_x = self.half_width - x * self.pixel_size;
+        let world_y = self.half_height - y * self.pixel_size;
+
+        self.projection.direction(world_x, world_y)
+    }
+
+    /// Copy of the camera with an image plane that spans `half_view` units from it's center to
+    /// the edge of it's widest dimension.
+    fn with_half_view(self, half_view: f64) -> Self {
+        let aspect = self.hsize as f64 / self.vsize as f64;
+
+        let (half_width, half_height) = if aspect < 1.0 {
+            (half_view * aspect, half_view)
+        } else {
+            (half_view, half_view / aspect)
+        };
+
+        Self {
+            pixel_size: (half_width * 2.0) / self.hsize as f64,
+            half_width,
+            half_height,
+            ..self
+        }
+    }
+
+    /// Gives the camera another projection, which maps the same field of view to the image in a
+    /// different way.
+    ///
+    /// Every render goes through the projection of the camera, so a scene can be rendered with
+    /// any projection just by changing it's camera.
+    ///
+    /// # Examples
+    ///
+    /// A "little planet" of a scene, seen from one unit above the ground.
+    ///
+    /// ```
+    /// use raytracer::{
+    ///     camera::{Camera, CameraBuilder, Projection},
+    ///     transform::Transform,
+    ///     tuple::{Point, Vector},
+    /// };
+    ///
+    /// let camera = Camera::try_from(CameraBuilder {
+    ///     width: 100,
+    ///     height: 100,
+    ///     field_of_view: 300_f64.to_radians(),
+    ///     transform: Transform::view(
+    ///         Point::new(0.0, 1.0, 0.0),
+    ///         Point::new(0.0, 0.0, 0.0),
+    ///         Vector::new(0.0, 0.0, 1.0),
+    ///     )
+    ///     .unwrap(),
+    /// })
+    /// .unwrap()
+    /// .with_projection(Projection::Stereographic)
+    /// .unwrap();
+    ///
+    /// // The center of the image looks straight down, and the edges look up to the sky.
+    /// let up = Vector::new(0.0, 1.0, 0.0);
+    ///
+    /// assert_eq!(camera.ray_for_position(50.0, 50.0).direction, -up);
+    /// assert!(camera.ray_for_position(0.0, 50.0).direction.dot(up) > 0.0);
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Fails if the field of view of the camera is too wide for the projection, which for a
+    /// stereographic projection is a full turn or more.
+    ///
+    pub fn with_projection(self, projection: Projection) -> Result<Self, Error> {
+        let half_angle = self.field_of_view / 2.0;
+
+        let half_view = match projection {
+            Projection::Perspective => half_angle.tan(),
+            Projection::Stereographic if half_angle.abs() < std::f64::consts::PI => {
+                2.0 * (half_angle / 2.0).tan()
+            }
+            Projection::Stereographic => return Err(Error::FieldOfViewTooWide),
+        };
+
+        Ok(Self { projection, ..self }.with_half_view(half_view))
+    }
+
+    /// Projection that the camera uses to map directions to the image.
+    pub fn projection(&self) -> Projection {
+        self.projection
+    }
+
+    /// Copy of the camera that renders an image of `width` by `height` pixels, keeping the extent
+    /// of the widest dimension of it's image plane. The other dimension follows the new aspect
+    /// ratio, so the corners of the image stay at the corners of the image plane.
+    pub(crate) fn with_resolution(&self, width: usize, height: usize) -> Self {
+        Self {
+            hsize: width,
+            vsize: height,
+            ..*self
+        }
+        .with_half_view(self.half_width.max(self.half_height))
+    }
+
+    /// Copy of the camera placed with another transformation.
+    pub(crate) fn with_transform(&self, transform: Transform) -> Self {
+        Self {
+            transform,
+            transform_inverse: transform.inverse(),
+            ..*self
+        }
+    }
+
+    /// Gives the camera a lens with the given radius, that only keeps the points at
+    /// `focal_distance` from the camera in focus.
+    ///
+    /// Points nearer or further away are blurred the more the wider the aperture is. An aperture
+    /// of `0.0`, which is the default, keeps every point in focus like a pinhole camera. The blur
+    /// only appears when taking multiple samples per pixel, with [Camera::render_samples] or
+    /// [Camera::render_adaptive], the other renders cast every ray from the center of the lens.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the aperture is negative or the focal distance is not positive.
+    ///
+    pub fn with_depth_of_field(self, aperture: f64, focal_distance: f64) -> Result<Self, Error> {
+        let valid = (0.0..f64::INFINITY).contains(&aperture) && focal_distance > 0.0;
+
+        if !valid {
+            return Err(Error::InvalidDepthOfField);
+        }
+
+        Ok(Self {
+            aperture,
+            focal_distance,
+            ..self
+        })
+    }
+
+    /// Gives the camera the field of view and the aperture of a real lens, keeping it's focal
+    /// distance. The aperture depends on the `units` the world is modeled in, because the size of
+    /// the lens is physical.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the field of view of the lens is too wide for the projection of the camera.
+    ///
+    pub fn with_lens(self, lens: &Lens, units: UnitScale) -> Result<Self, Error> {
+        let camera = Self {
+            field_of_view: lens.field_of_view(),
+            ..self
+        }
+        .with_projection(self.projection)?;
+
+        camera.with_depth_of_field(lens.aperture(units), self.focal_distance)
+    }
+
+    /// Moves the focus of the camera to the given point, or to the center of the given object of
+    /// the world, keeping it's aperture.
+    ///
+    /// Focusing on an object every frame of an animation keeps that object sharp while it, or the
+    /// camera, moves.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use raytracer::{
+    ///     camera::{Camera, CameraBuilder},
+    ///     shape::{Shape, ShapeBuilder, Sphere},
+    ///     transform::Transform,
+    ///     tuple::{Point, Vector},
+    ///     world::World,
+    /// };
+    ///
+    /// let hero = Shape::Sphere(Sphere::from(ShapeBuilder {
+    ///     transform: Transform::translation(0.0, 0.0, 3.0),
+    ///     ..Default::default()
+    /// }));
+    /// let id = hero.id();
+    ///
+    /// let world = World {
+    ///     objects: vec![hero],
+    ///     ..Default::default()
+    /// };
+    ///
+    /// let camera = Camera::try_from(CameraBuilder {
+    ///     width: 160,
+    ///     height: 90,
+    ///     field_of_view: std::f64::consts::FRAC_PI_3,
+    ///     transform: Transform::view(
+    ///         Point::new(0.0, 0.0, -5.0),
+    ///         Point::new(0.0, 0.0, 0.0),
+    ///         Vector::new(0.0, 1.0, 0.0),
+    ///     )
+    ///     .unwrap(),
+    /// })
+    /// .unwrap()
+    /// .with_depth_of_field(0.1, 1.0)
+    /// .unwrap();
+    ///
+    /// let camera = camera.focus_on(&world, id).unwrap();
+    ///
+    /// assert_eq!(camera.focal_distance(), 8.0);
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Fails if the object is not in the world, or if the projection of the camera can't see the
+    /// point, which is a point behind a perspective camera, or right behind a camera of any other
+    /// projection.
+    ///
+    pub fn focus_on<T>(&self, world: &World, target: T) -> Result<Self, Error>
+    where
+        T: Into<FocusTarget>,
+    {
+        let point = match target.into() {
+            FocusTarget::Point(point) => point,
+            FocusTarget::Object(id) => world.shape(id).ok_or(Error::MissingFocusObject)?.center(),
+        };
+
+        let direction = self.transform * point - Point::new(0.0, 0.0, 0.0);
+
+        // A perspective camera focuses on a plane perpendicular to the direction it looks at,
+        // which is the negative `z` axis, while other projections focus on a sphere around the
+        // camera, as described in [Camera::ray_through_lens].
+        let distance = match self.projection {
+            Projection::Perspective => -direction.0.z,
+            Projection::Stereographic => direction.magnitude(),
+        };
+
+        if distance < float::EPSILON || self.projection.image_point(direction).is_none() {
+            return Err(Error::FocusBehindCamera);
+        }
+
+        self.with_depth_of_field(self.aperture, distance)
+    }
+
+    /// Radius of the lens of the camera.
+    pub fn aperture(&self) -> f64 {
+        self.aperture
+    }
+
+    /// Distance from the camera to the plane that is in focus.
+    pub fn focal_distance(&self) -> f64 {
+        self.focal_distance
+    }
+
+    /// Computes the ray that passes through an arbitrary position of the image, like
+    /// [Camera::ray_for_position], but that starts at a point of the lens instead of it's center.
+    ///
+    /// The point of the lens is given by a sample in the range `[0, 1)` for each coordinate, which
+    /// is spread evenly over the lens. Every ray through the same position of the image meets at
+    /// the focal distance.
+    ///
+    pub fn ray_through_lens(&self, x: f64, y: f64, [u, v]: [f64; 2]) -> Ray {
+        if self.aperture == 0.0 {
+            return self.ray_for_position(x, y);
+        }
+
+        // Perspective directions are one unit deep, so the focus is on a plane at the focal
+        // distance, while the focus of other projections is on a sphere around the camera.
+        let focus = Point::new(0.0, 0.0, 0.0) + self.direction_at(x, y) * self.focal_distance;
+
+        let radius = self.aperture * u.sqrt();
+        let (sin, cos) = (2.0 * std::f64::consts::PI * v).sin_cos();
+        let lens = Point::new(radius * cos, radius * sin, 0.0);
+
+        let origin = self.transform_inverse * lens;
+        let focus = self.transform_inverse * focus;
+
+        // The focal distance is always positive, so the focus is never on the lens.
+        #[allow(clippy::unwrap_used)]
+        let direction = (focus - origin).normalize().unwrap();
+
+        Ray { origin, direction }
+    }
+
+    /// Position of the camera in world space.
+    pub(crate) fn origin(&self) -> Point {
+        self.transform_inverse * Point::new(0.0, 0.0, 0.0)
+    }
+
+    /// Returns the column and row of the pixel of the image that sees the given point, or `None`
+    /// if the point is behind the camera or outside of it's field of view.
+    pub(crate) fn project(&self, point: Point) -> Option<(usize, usize)> {
+        let direction = self.transform * point - Point::new(0.0, 0.0, 0.0);
+        let (x, y) = self.projection.image_point(direction)?;
+
+        let x = (self.half_width - x) / self.pixel_size;
+        let y = (self.half_height - y) / self.pixel_size;
+
+        let inside = (0.0..self.hsize as f64).contains(&x) && (0.0..self.vsize as f64).contains(&y);
+        inside.then_some((x as usize, y as usize))
+    }
+
+    /// Computes the rays for multiple pixels at once, in the same order as they are given.
+    pub fn rays_for_pixels<I>(&self, pixels: I) -> Vec<Ray>
+    where
+        I: IntoIterator<Item = (usize, usize)>,
+    {
+        pixels
+            .into_iter()
+            .map(|(x, y)| self.ray_for_pixel(x, y))
+            .collect()
+    }
+
+    /// Computes the rays for every pixel of the row `y` of the image, from left to right.
+    pub fn rays_for_row(&self, y: usize) -> Vec<Ray> {
+        self.rays_for_pixels((0..self.hsize).map(|x| (x, y)))
+    }
+
+    /// Returns an iterator over the rays for every pixel of the image, alongside the coordinates
+    /// of the pixel they pass through. Pixels are traversed row by row, starting from the
+    /// top-left corner of the image.
+    ///
+    pub fn rays(&self) -> impl Iterator<Item = ((usize, usize), Ray)> + '_ {
+        (0..self.vsize)
+            .flat_map(move |y| (0..self.hsize).map(move |x| (x, y)))
+            .map(|(x, y)| ((x, y), self.ray_for_pixel(x, y)))
+    }
+}
+
+/// Depth and normal of the first surface hit by a ray, if any.
+fn surface_sample(world: &World, ray: &Ray) -> Option<SurfaceSample> {
+    let mut xs = world.intersect(ray);
+    let hit = Intersection::hit(&mut xs)?;
+
+    let point = ray.position(hit.t);
+
+    Some(SurfaceSample {
+        depth: hit.t,
+        normal: hit.normal_at(point, ray),
+    })
+}
+
+/// Message of a caught panic, which is a string unless it was raised with a payload of another
+/// type.
+#[cfg(feature = "tokio")]
+fn panic_message(payload: &(dyn Any + Send)) -> String {
+    payload
+        .downcast_ref::<&str>()
+        .map(|message| message.to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "unknown reason".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        assert_approx,
+        color::{self, Color},
+        light::{Light, PointLight},
+        render::TileOrder,
+        tuple::Vector,
+        world::test_world,
+    };
+
+    use super::*;
+
+    #[test]
+    fn constructing_a_camera() {
+        let hsize = 160;
+        let vsize = 120;
+        let field_of_view = std::f64::consts::FRAC_PI_2;
+
+        let c = Camera::try_from(CameraBuilder {
+            width: hsize,
+            height: vsize,
+            field_of_view,
+            transform: Default::default(),
+        })
+        .unwrap();
+
+        assert_eq!(c.hsize, hsize);
+        assert_eq!(c.vsize, vsize);
+        assert_approx!(c.field_of_view, std::f64::consts::FRAC_PI_2);
+        assert_eq!(c.transform, Transform::default());
+    }
+
+    #[test]
+    fn the_pixel_size_for_a_horizontal_canvas() {
+        let c = Camera::try_from(CameraBuilder {
+            width: 200,
+            height: 125,
+            field_of_view: std::f64::consts::FRAC_PI_2,
+            transform: Default::default(),
+        })
+        .unwrap();
+
+        assert_approx!(c.pixel_size, 0.01);
+    }
+
+    #[test]
+    fn the_pixel_size_for_a_vertical_canvas() {
+        let c = Camera::try_from(CameraBuilder {
+            width: 125,
+            height: 200,
+            field_of_view: std::f64::consts::FRAC_PI_2,
+            transform: Default::default(),
+        })
+        .unwrap();
+
+        assert_approx!(c.pixel_size, 0.01);
+    }
+
+    #[test]
+    fn constructing_a_ray_through_the_center_of_the_canvas() {
+        let c = Camera::try_from(CameraBuilder {
+            width: 201,
+            height: 101,
+            field_of_view: std::f64::consts::FRAC_PI_2,
+            transform: Default::default(),
+        })
+        .unwrap();
+
+        let r = c.ray_for_pixel(100, 50);
+
+        assert_eq!(r.origin, Point::new(0.0, 0.0, 0.0));
+        assert_eq!(r.direction, Vector::new(0.0, 0.0, -1.0));
+    }
+
+    #[test]
+    fn constructing_a_ray_through_a_corner_of_the_canvas() {
+        let c = Camera::try_from(CameraBuilder {
+            width: 201,
+            height: 101,
+            field_of_view: std::f64::consts::FRAC_PI_2,
+            transform: Default::default(),
+        })
+        .unwrap();
+
+        let r = c.ray_for_pixel(0, 0);
+
+        assert_eq!(r.origin, Point::new(0.0, 0.0, 0.0));
+        assert_eq!(r.direction, Vector::new(0.66519, 0.33259, -0.66851));
+    }
+
+    #[test]
+    fn constructing_a_ray_when_the_camera_is_transformed() {
+        let c = Camera::try_from(CameraBuilder {
+            width: 201,
+            height: 101,
+            field_of_view: std::f64::consts::FRAC_PI_2,
+            transform: Transform::rotation_y(std::f64::consts::FRAC_PI_4)
+                * Transform::translation(0.0, -2.0, 5.0),
+        })
+        .unwrap();
+
+        let r = c.ray_for_pixel(100, 50);
+
+        assert_eq!(r.origin, Point::new(0.0, 2.0, -5.0));
+        assert_eq!(
+            r.direction,
+            Vector::new(2_f64.sqrt() / 2.0, 0.0, -2_f64.sqrt() / 2.0)
+        );
+    }
+
+    #[test]
+    fn rendering_a_world_with_a_camera() {
+        let w = test_world();
+
+        let from = Point::new(0.0, 0.0, -5.0);
+        let to = Point::new(0.0, 0.0, 0.0);
+        let up = Vector::new(0.0, 1.0, 0.0);
+
+        let c = Camera::try_from(CameraBuilder {
+            width: 11,
+            height: 11,
+            field_of_view: std::f64::consts::FRAC_PI_2,
+            transform: Transform::view(from, to, up).unwrap(),
+        })
+        .unwrap();
+
+        let image = c.render(&w);
+
+        assert_eq!(
+            image.pixel_at(5, 5),
+            &Color {
+                red: 0.38066,
+                green: 0.47583,
+                blue: 0.2855,
+            }
+        );
+    }
+
+    #[test]
+    fn comparing_cameras() {
+        let c0 = Camera::try_from(CameraBuilder {
+            width: 100,
+            height: 200,
+            field_of_view: std::f64::consts::FRAC_PI_3,
+            transform: Default::default(),
+        })
+        .unwrap();
+
+        let c1 = Camera::try_from(CameraBuilder {
+            width: 100,
+            height: 200,
+            field_of_view: std::f64::consts::FRAC_PI_3,
+            transform: Default::default(),
+        })
+        .unwrap();
+
+        let c2 = Camera::try_from(CameraBuilder {
+            width: 200,
+            height: 300,
+            field_of_view: std::f64::consts::FRAC_PI_6,
+            transform: Transform::scaling(1.0, 2.0, 3.0).unwrap(),
+        })
+        .unwrap();
+
+        assert_eq!(c0, c1);
+        assert_ne!(c0, c2);
+    }
+
+    #[test]
+    fn trying_to_create_a_camera_with_null_dimensions() {
+        let c = Camera::try_from(CameraBuilder {
+            width: 0,
+            height: 0,
+            field_of_view: std::f64::consts::FRAC_PI_2,
+            transform: Default::default(),
+        });
+
+        assert_eq!(c, Err(Error::NullDimension));
+    }
+
+    #[test]
+    fn trying_to_create_a_camera_with_a_multiple_of_pi_field_of_view() {
+        let c0 = Camera::try_from(CameraBuilder {
+            width: 100,
+            height: 200,
+            field_of_view: 0.0,
+            transform: Default::default(),
+        });
+
+        let c1 = Camera::try_from(CameraBuilder {
+            width: 100,
+            height: 200,
+            field_of_view: std::f64::consts::PI,
+            transform: Default::default(),
+        });
+
+        let c2 = Camera::try_from(CameraBuilder {
+            width: 100,
+            height: 200,
+            field_of_view: 3.0 * std::f64::consts::PI,
+            transform: Default::default(),
+        });
+
+        assert_eq!(c0, Err(Error::MultipleOfPiFieldOfView));
+        assert_eq!(c1, Err(Error::MultipleOfPiFieldOfView));
+        assert_eq!(c2, Err(Error::MultipleOfPiFieldOfView));
+    }
+
+    #[test]
+    fn constructing_a_ray_through_an_arbitrary_position_of_the_canvas() {
+        let c = Camera::try_from(CameraBuilder {
+            width: 201,
+            height: 101,
+            field_of_view: std::f64::consts::FRAC_PI_2,
+            transform: Default::default(),
+        })
+        .unwrap();
+
+        assert_eq!(c.ray_for_position(100.5, 50.5), c.ray_for_pixel(100, 50));
+        assert_eq!(c.ray_for_position(0.5, 0.5), c.ray_for_pixel(0, 0));
+    }
+
+    fn camera_looking_at_the_origin() -> Camera {
+        Camera::try_from(CameraBuilder {
+            width: 11,
+            height: 11,
+            field_of_view: std::f64::consts::FRAC_PI_2,
+            transform: Transform::view(
+                Point::new(0.0, 0.0, -5.0),
+                Point::new(0.0, 0.0, 0.0),
+                Vector::new(0.0, 1.0, 0.0),
+            )
+            .unwrap(),
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn rays_through_a_pinhole_lens_start_at_the_camera() {
+        let c = camera_looking_at_the_origin();
+
+        assert_eq!(c.aperture(), 0.0);
+        assert_eq!(
+            c.ray_through_lens(3.5, 7.5, [0.3, 0.8]),
+            c.ray_for_position(3.5, 7.5)
+        );
+    }
+
+    #[test]
+    fn rays_through_a_lens_meet_at_the_focal_distance() {
+        let c = camera_looking_at_the_origin()
+            .with_depth_of_field(0.5, 4.0)
+            .unwrap();
+
+        let center = c.ray_for_position(3.5, 7.5);
+        let focus = center.position(4.0 / center.direction.dot(Vector::new(0.0, 0.0, 1.0)));
+
+        for lens in [[0.0, 0.0], [0.5, 0.25], [0.99, 0.7]] {
+            let ray = c.ray_through_lens(3.5, 7.5, lens);
+
+            assert!((ray.origin - Point::new(0.0, 0.0, -5.0)).magnitude() <= 0.5 + float::EPSILON);
+            assert_approx!(ray.origin.0.z, -5.0);
+
+            let t = (focus - ray.origin).magnitude();
+            assert_eq!(ray.position(t), focus);
+        }
+    }
+
+    #[test]
+    fn trying_to_give_a_camera_an_invalid_depth_of_field() {
+        let c = camera_looking_at_the_origin();
+
+        assert_eq!(
+            c.with_depth_of_field(-0.1, 1.0),
+            Err(Error::InvalidDepthOfField)
+        );
+        assert_eq!(
+            c.with_depth_of_field(0.1, 0.0),
+            Err(Error::InvalidDepthOfField)
+        );
+        assert_eq!(
+            c.with_depth_of_field(0.1, f64::NAN),
+            Err(Error::InvalidDepthOfField)
+        );
+    }
+
+    #[test]
+    fn focusing_a_camera_on_a_point() {
+        let c = camera_looking_at_the_origin()
+            .with_depth_of_field(0.1, 1.0)
+            .unwrap();
+
+        let focused = c
+            .focus_on(&World::default(), Point::new(2.0, 1.0, 1.0))
+            .unwrap();
+
+        assert_approx!(focused.focal_distance(), 6.0);
+        assert_eq!(focused.aperture(), 0.1);
+
+        assert_eq!(
+            c.focus_on(&World::default(), Point::new(0.0, 0.0, -6.0)),
+            Err(Error::FocusBehindCamera)
+        );
+    }
+
+    #[test]
+    fn focusing_a_wide_camera_on_points_off_it_s_axis() {
+        let c = stereographic_camera(std::f64::consts::PI * 1.5)
+            .with_depth_of_field(0.1, 1.0)
+            .unwrap();
+
+        // The focus is on a sphere around the camera, so it's the distance to the point, not
+        // the depth of the point along the direction the camera looks at.
+        let off_axis = c
+            .focus_on(&World::default(), Point::new(3.0, 0.0, -4.0))
+            .unwrap();
+
+        assert_approx!(off_axis.focal_distance(), 5.0);
+
+        // The camera sees points more than 90 degrees away from the direction it looks at.
+        let beside = c
+            .focus_on(&World::default(), Point::new(3.0, 0.0, 4.0))
+            .unwrap();
+
+        assert_approx!(beside.focal_distance(), 5.0);
+
+        assert_eq!(
+            c.focus_on(&World::default(), Point::new(0.0, 0.0, 5.0)),
+            Err(Error::FocusBehindCamera)
+        );
+    }
+
+    #[test]
+    fn focusing_a_camera_on_an_object() {
+        use crate::shape::{Group, GroupBuilder, Shape, ShapeBuilder, Sphere};
+
+        let sphere = Shape::Sphere(Sphere::from(ShapeBuilder {
+            transform: Transform::scaling(2.0, 2.0, 2.0).unwrap(),
+            ..Default::default()
+        }));
+        let id = sphere.id();
+
+        let world = World {
+            objects: vec![Shape::Group(Group::from(GroupBuilder {
+                children: [sphere],
+                transform: Transform::translation(0.0, 0.0, 3.0),
+            }))],
+            ..Default::default()
+        };
+
+        let c = camera_looking_at_the_origin();
+
+        assert_approx!(c.focus_on(&world, id).unwrap().focal_distance(), 8.0);
+        assert_approx!(
+            c.focus_on(&world, world.objects[0].id())
+                .unwrap()
+                .focal_distance(),
+            8.0
+        );
+        assert_eq!(
+            c.focus_on(&World::default(), id),
+            Err(Error::MissingFocusObject)
+        );
+    }
+
+    #[test]
+    fn giving_a_camera_a_real_lens() {
+        let lens: Lens = "24mm f/4 micro four thirds".parse().unwrap();
+
+        let c = camera_looking_at_the_origin()
+            .with_depth_of_field(0.0, 5.0)
+            .unwrap()
+            .with_lens(&lens, UnitScale::CENTIMETERS)
+            .unwrap();
+
+        assert_approx!(c.field_of_view, lens.field_of_view());
+        assert_approx!(c.aperture(), 0.3);
+        assert_approx!(c.focal_distance(), 5.0);
+        assert_approx!(c.half_width, 17.3 / 2.0 / 24.0);
+    }
+
+    #[test]
+    fn deserializing_a_camera_with_a_depth_of_field() {
+        let c: Camera = serde_json::from_str(
+            r#"{
+                "width": 11,
+                "height": 11,
+                "field_of_view": 90.0,
+                "aperture": 0.2,
+                "focal_distance": 3.0
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(c.aperture(), 0.2);
+        assert_eq!(c.focal_distance(), 3.0);
+
+        let c: Result<Camera, _> = serde_json::from_str(
+            r#"{ "width": 11, "height": 11, "field_of_view": 90.0, "aperture": -1.0 }"#,
+        );
+
+        assert!(c.is_err());
+    }
+
+    #[test]
+    fn recording_the_paths_of_rays_bouncing_off_a_mirror() {
+        use crate::{
+            integrator::Whitted,
+            material::Material,
+            shape::{Plane, Shape, ShapeBuilder},
+        };
+
+        let mut world = test_world();
+        world.objects.push(Shape::Plane(Plane::from(ShapeBuilder {
+            material: Material {
+                reflectivity: 1.0,
+                ..Default::default()
+            },
+            transform: Transform::translation(0.0, 0.0, 5.0)
+                * Transform::rotation_x(std::f64::consts::FRAC_PI_2),
+        })));
+
+        let settings = RenderSettings {
+            integrator: Arc::new(Whitted {
+                recursion_depth: 2,
+                record_ray_paths: true,
+            }),
+            ..Default::default()
+        };
+
+        let c = camera_looking_at_the_origin();
+        let paths = c.record_ray_paths(&world, &settings, [(5, 5), (0, 0)]);
+
+        let center = &paths.paths[0];
+        assert_eq!(center.pixel, (5, 5));
+        assert_eq!(center.segments[0].origin, Point::new(0.0, 0.0, -5.0));
+        assert_eq!(center.segments[0].hit, Some(Point::new(0.0, 0.0, -1.0)));
+        assert_eq!(center.segments[0].bounce, 0);
+
+        // The corner ray misses the spheres, hits the mirror and bounces back past the camera.
+        let corner = &paths.paths[1];
+        assert_eq!(
+            corner
+                .segments
+                .iter()
+                .map(|segment| (segment.bounce, segment.hit.is_some()))
+                .collect::<Vec<_>>(),
+            vec![(0, true), (1, false)]
+        );
+        assert_approx!(corner.segments[1].origin.0.z, 5.0);
+
+        let settings = RenderSettings {
+            integrator: Arc::new(Whitted {
+                recursion_depth: 2,
+                record_ray_paths: false,
+            }),
+            ..Default::default()
+        };
+
+        let paths = c.record_ray_paths(&world, &settings, [(5, 5)]);
+        assert!(paths.paths[0].segments.is_empty());
+    }
+
+    #[test]
+    fn constructing_rays_for_multiple_pixels() {
+        let c = Camera::try_from(CameraBuilder {
+            width: 4,
+            height: 3,
+            field_of_view: std::f64::consts::FRAC_PI_2,
+            transform: Default::default(),
+        })
+        .unwrap();
+
+        let rays = c.rays_for_pixels([(3, 2), (0, 1)]);
+
+        assert_eq!(rays, vec![c.ray_for_pixel(3, 2), c.ray_for_pixel(0, 1)]);
+
+        let row = c.rays_for_row(1);
+
+        assert_eq!(row.len(), 4);
+        assert_eq!(row[2], c.ray_for_pixel(2, 1));
+    }
+
+    #[test]
+    fn iterating_over_the_rays_of_every_pixel() {
+        let c = Camera::try_from(CameraBuilder {
+            width: 4,
+            height: 3,
+            field_of_view: std::f64::consts::FRAC_PI_2,
+            transform: Default::default(),
+        })
+        .unwrap();
+
+        let rays: Vec<_> = c.rays().collect();
+
+        assert_eq!(rays.len(), 12);
+        assert_eq!(rays[0], ((0, 0), c.ray_for_pixel(0, 0)));
+        assert_eq!(rays[5], ((1, 1), c.ray_for_pixel(1, 1)));
+        assert_eq!(rays[11], ((3, 2), c.ray_for_pixel(3, 2)));
+    }
+
+    #[test]
+    fn rendering_a_world_in_any_tile_order_produces_the_same_image() {
+        let w = test_world();
+
+        let c = Camera::try_from(CameraBuilder {
+            width: 11,
+            height: 7,
+            field_of_view: std::f64::consts::FRAC_PI_2,
+            transform: Transform::view(
+                Point::new(0.0, 0.0, -5.0),
+                Point::new(0.0, 0.0, 0.0),
+                Vector::new(0.0, 1.0, 0.0),
+            )
+            .unwrap(),
+        })
+        .unwrap();
+
+        let reference = c.render(&w);
+
+        for tile_order in [TileOrder::Scanline, TileOrder::Spiral, TileOrder::Hilbert] {
+            let image = c.render_with_settings(
+                &w,
+                &RenderSettings {
+                    threads: 2,
+                    tile_size: 3,
+                    tile_order,
+                    ..Default::default()
+                },
+            );
+
+            for (x, y) in c.rays().map(|(pixel, _)| pixel) {
+                assert_eq!(image.pixel_at(x, y), reference.pixel_at(x, y));
+            }
+        }
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn rendering_a_world_asynchronously() {
+        use tokio_stream::StreamExt;
+
+        let w = test_world();
+
+        let c = Camera::try_from(CameraBuilder {
+            width: 11,
+            height: 7,
+            field_of_view: std::f64::consts::FRAC_PI_2,
+            transform: Transform::view(
+                Point::new(0.0, 0.0, -5.0),
+                Point::new(0.0, 0.0, 0.0),
+                Vector::new(0.0, 1.0, 0.0),
+            )
+            .unwrap(),
+        })
+        .unwrap();
+
+        let reference = c.render(&w);
+
+        let settings = RenderSettings {
+            threads: 2,
+            tile_size: 3,
+            ..Default::default()
+        };
+
+        let mut tiles = c.render_async(Arc::new(w), settings);
+        let mut rendered_pixels = 0;
+
+        while let Some(rendered) = tiles.next().await {
+            let rendered = rendered.unwrap();
+
+            for ((x, y), color) in rendered.tile.pixels().zip(rendered.pixels) {
+                assert_eq!(color, *reference.pixel_at(x, y));
+                rendered_pixels += 1;
+            }
+        }
+
+        assert_eq!(rendered_pixels, 11 * 7);
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn rendering_asynchronously_reports_a_panic_instead_of_ending_the_stream() {
+        use tokio_stream::StreamExt;
+
+        use crate::integrator::Integrator;
+
+        #[derive(Debug)]
+        struct Broken;
+
+        impl Integrator for Broken {
+            fn color_at(&self, _: &World, _: &Ray) -> color::Color {
+                panic!("broken integrator");
+            }
+        }
+
+        let c = Camera::try_from(CameraBuilder {
+            width: 4,
+            height: 4,
+            field_of_view: std::f64::consts::FRAC_PI_2,
+            transform: Default::default(),
+        })
+        .unwrap();
+
+        let settings = RenderSettings {
+            threads: 1,
+            integrator: Arc::new(Broken),
+            ..Default::default()
+        };
+
+        let items: Vec<_> = c
+            .render_async(Arc::new(World::default()), settings)
+            .collect()
+            .await;
+
+        assert!(matches!(
+            items.as_slice(),
+            [Err(render::Error::Panicked(message))] if message == "broken integrator"
+        ));
+    }
+
+    #[test]
+    fn rendering_logs_when_it_starts_and_finishes() {
+        use std::thread::{self, ThreadId};
+
+        struct Logger(Mutex<Vec<(ThreadId, log::Level, String)>>);
+
+        impl log::Log for Logger {
+            fn enabled(&self, _: &log::Metadata) -> bool {
+                true
+            }
+
+            fn log(&self, record: &log::Record) {
+                self.0.lock().unwrap().push((
+                    thread::current().id(),
+                    record.level(),
+                    record.args().to_string(),
+                ));
+            }
+
+            fn flush(&self) {}
+        }
+
+        static LOGGER: Logger = Logger(Mutex::new(vec![]));
+
+        log::set_logger(&LOGGER).unwrap();
+        log::set_max_level(log::LevelFilter::Debug);
+
+        let c = Camera::try_from(CameraBuilder {
+            width: 4,
+            height: 2,
+            field_of_view: std::f64::consts::FRAC_PI_2,
+            transform: Default::default(),
+        })
+        .unwrap();
+
+        c.render_with_settings(
+            &World::default(),
+            &RenderSettings {
+                threads: 2,
+                tile_size: 2,
+                ..Default::default()
+            },
+        );
+
+        let records = LOGGER.0.lock().unwrap();
+
+        let current_thread: Vec<_> = records
+            .iter()
+            .filter(|(thread, ..)| *thread == thread::current().id())
+            .map(|(_, level, message)| (*level, message.as_str()))
+            .collect();
+
+        assert_eq!(
+            current_thread[0],
+            (
+                log::Level::Info,
+                "rendering a 4x2 image in 2 tiles with 2 threads"
+            )
+        );
+        assert!(current_thread[1].1.starts_with("rendered a 4x2 image in"));
+
+        let tiles = records
+            .iter()
+            .filter(|(_, level, message)| {
+                *level == log::Level::Debug && message.starts_with("rendered the 2x2 tile")
+            })
+            .count();
+
+        assert!(tiles >= 2);
+    }
+
+    #[test]
+    fn gathering_statistics_while_rendering_a_world() {
+        let w = test_world();
+
+        let c = Camera::try_from(CameraBuilder {
             width: 11,
             height: 11,
             field_of_view: std::f64::consts::FRAC_PI_2,
-            transform: Transform::view(from, to, up).unwrap(),
+            transform: Transform::view(
+                Point::new(0.0, 0.0, -5.0),
+                Point::new(0.0, 0.0, 0.0),
+                Vector::new(0.0, 1.0, 0.0),
+            )
+            .unwrap(),
         })
         .unwrap();
 
-        let image = c.render(&w);
-
-        assert_eq!(
-            image.pixel_at(5, 5),
-            &Color {
-                red: 0.38066,
-                green: 0.47583,
-                blue: 0.2855,
-            }
+        let (_, stats) = c.render_with_stats(
+            &w,
+            &RenderSettings {
+                threads: 4,
+                tile_size: 2,
+                tile_order: TileOrder::Spiral,
+                ..Default::default()
+            },
         );
+
+        // The test world has a single light, so every primary ray that hits an object casts
+        // exactly one shadow ray. Nothing in it is reflective or transparent.
+        assert_eq!(stats.primary_rays, 11 * 11);
+        assert!(stats.shadow_rays > 0 && stats.shadow_rays <= stats.primary_rays);
+        assert_eq!(stats.reflection_rays, 0);
+        assert_eq!(stats.refraction_rays, 0);
+        assert_eq!(stats.portal_rays, 0);
+        // Shadow rays stop testing objects as soon as they find one between the point and the
+        // light.
+        assert!(stats.intersection_tests >= 2 * stats.primary_rays + stats.shadow_rays);
+        assert!(stats.intersection_tests <= 2 * (stats.primary_rays + stats.shadow_rays));
     }
 
     #[test]
-    fn comparing_cameras() {
-        let c0 = Camera::try_from(CameraBuilder {
-            width: 100,
-            height: 200,
-            field_of_view: std::f64::consts::FRAC_PI_3,
+    fn timing_the_tiles_of_a_render() {
+        let c = Camera::try_from(CameraBuilder {
+            width: 11,
+            height: 11,
+            field_of_view: std::f64::consts::FRAC_PI_2,
             transform: Default::default(),
         })
         .unwrap();
 
-        let c1 = Camera::try_from(CameraBuilder {
-            width: 100,
-            height: 200,
-            field_of_view: std::f64::consts::FRAC_PI_3,
-            transform: Default::default(),
+        let settings = RenderSettings {
+            threads: 4,
+            tile_size: 4,
+            ..Default::default()
+        };
+
+        let (_, _, times) = c.render_with_tile_times(&test_world(), &settings);
+
+        // Every pixel is covered by exactly one tile.
+        let mut pixels: Vec<_> = times
+            .tiles()
+            .iter()
+            .flat_map(|(tile, _)| tile.pixels())
+            .collect();
+        pixels.sort();
+
+        let mut expected: Vec<_> = c.rays().map(|(pixel, _)| pixel).collect();
+        expected.sort();
+
+        assert_eq!(times.tiles().len(), 9);
+        assert_eq!(pixels, expected);
+
+        let heatmap = times.to_heatmap();
+        assert_eq!((heatmap.width, heatmap.height), (11, 11));
+
+        // The tiles of the 6x6 half resolution pass are scaled up to the pixels they cover.
+        let (_, _, times) = c.render_with_tile_times(
+            &test_world(),
+            &RenderSettings {
+                half_resolution_indirect: true,
+                ..settings
+            },
+        );
+
+        let mut scaled: Vec<_> = times.tiles()[9..]
+            .iter()
+            .map(|(tile, _)| (tile.x, tile.y, tile.width, tile.height))
+            .collect();
+        scaled.sort();
+
+        assert_eq!(
+            scaled,
+            [(0, 0, 8, 8), (0, 8, 8, 4), (8, 0, 4, 8), (8, 8, 4, 4)]
+        );
+    }
+
+    #[test]
+    fn accumulating_samples_over_multiple_passes() {
+        let w = test_world();
+
+        let c = Camera::try_from(CameraBuilder {
+            width: 11,
+            height: 11,
+            field_of_view: std::f64::consts::FRAC_PI_2,
+            transform: Transform::view(
+                Point::new(0.0, 0.0, -5.0),
+                Point::new(0.0, 0.0, 0.0),
+                Vector::new(0.0, 1.0, 0.0),
+            )
+            .unwrap(),
         })
         .unwrap();
 
-        let c2 = Camera::try_from(CameraBuilder {
-            width: 200,
-            height: 300,
-            field_of_view: std::f64::consts::FRAC_PI_6,
-            transform: Transform::scaling(1.0, 2.0, 3.0).unwrap(),
+        let settings = RenderSettings {
+            threads: 2,
+            ..Default::default()
+        };
+
+        let mut buffer = AccumulationBuffer::new(11, 11);
+
+        let stats = c.render_samples(&w, &settings, &mut buffer, 2);
+        assert_eq!(stats.primary_rays, 11 * 11 * 2);
+
+        c.render_samples(&w, &settings, &mut buffer, 1);
+
+        for (x, y) in c.rays().map(|(pixel, _)| pixel) {
+            assert_eq!(buffer.sample_count(x, y), 3);
+        }
+
+        // Rays through the corners of the image miss every object of the world.
+        assert_eq!(buffer.mean(0, 0), color::consts::BLACK);
+        assert_ne!(buffer.mean(5, 5), color::consts::BLACK);
+    }
+
+    #[test]
+    fn the_samples_of_the_lens_continue_across_passes() {
+        let w = test_world();
+
+        let c = camera_looking_at_the_origin()
+            .with_depth_of_field(0.5, 3.0)
+            .unwrap();
+
+        let settings = RenderSettings {
+            threads: 2,
+            sampler: crate::sampling::Sampler::Halton,
+            ..Default::default()
+        };
+
+        let mut at_once = AccumulationBuffer::new(11, 11);
+        c.render_samples(&w, &settings, &mut at_once, 4);
+
+        let mut in_passes = AccumulationBuffer::new(11, 11);
+        c.render_samples(&w, &settings, &mut in_passes, 2);
+        c.render_samples(&w, &settings, &mut in_passes, 2);
+
+        for (x, y) in c.rays().map(|(pixel, _)| pixel) {
+            assert_eq!(at_once.mean(x, y), in_passes.mean(x, y));
+        }
+    }
+
+    #[test]
+    fn adaptive_sampling_takes_more_samples_for_noisier_pixels() {
+        let w = test_world();
+
+        let c = Camera::try_from(CameraBuilder {
+            width: 11,
+            height: 11,
+            field_of_view: std::f64::consts::FRAC_PI_2,
+            transform: Transform::view(
+                Point::new(0.0, 0.0, -5.0),
+                Point::new(0.0, 0.0, 0.0),
+                Vector::new(0.0, 1.0, 0.0),
+            )
+            .unwrap(),
         })
         .unwrap();
 
-        assert_eq!(c0, c1);
-        assert_ne!(c0, c2);
+        let adaptive = AdaptiveSampling {
+            min_samples: 2,
+            max_samples: 9,
+            samples_per_pass: 4,
+            noise_threshold: 0.0,
+        };
+
+        let mut buffer = AccumulationBuffer::new(11, 11);
+
+        let stats = c.render_adaptive(
+            &w,
+            &RenderSettings {
+                threads: 2,
+                ..Default::default()
+            },
+            &adaptive,
+            &mut buffer,
+        );
+
+        // Every sample of the corners misses the world, so they're black without any noise. The
+        // shading at the center of the image changes slightly with every sample.
+        assert_eq!(buffer.sample_count(0, 0), 2);
+        assert_eq!(buffer.sample_count(5, 5), 9);
+
+        let total_samples: u64 = c
+            .rays()
+            .map(|((x, y), _)| buffer.sample_count(x, y) as u64)
+            .sum();
+
+        assert_eq!(stats.primary_rays, total_samples);
     }
 
     #[test]
-    fn trying_to_create_a_camera_with_null_dimensions() {
+    fn projecting_a_point_into_the_image() {
         let c = Camera::try_from(CameraBuilder {
-            width: 0,
-            height: 0,
+            width: 201,
+            height: 101,
             field_of_view: std::f64::consts::FRAC_PI_2,
+            transform: Transform::rotation_y(std::f64::consts::FRAC_PI_4)
+                * Transform::translation(0.0, -2.0, 5.0),
+        })
+        .unwrap();
+
+        for (x, y) in [(0, 0), (100, 50), (37, 90), (200, 100)] {
+            let ray = c.ray_for_pixel(x, y);
+            assert_eq!(c.project(ray.position(3.0)), Some((x, y)));
+        }
+
+        let behind = c.ray_for_pixel(100, 50).position(-3.0);
+        assert_eq!(c.project(behind), None);
+    }
+
+    fn stereographic_camera(field_of_view: f64) -> Camera {
+        Camera::try_from(CameraBuilder {
+            width: 201,
+            height: 101,
+            field_of_view,
             transform: Default::default(),
-        });
+        })
+        .unwrap()
+        .with_projection(Projection::Stereographic)
+        .unwrap()
+    }
 
-        assert_eq!(c, Err(Error::NullDimension));
+    #[test]
+    fn constructing_rays_with_a_stereographic_projection() {
+        let c = stereographic_camera(3.0 * std::f64::consts::FRAC_PI_2);
+
+        assert_eq!(c.projection(), Projection::Stereographic);
+        assert_eq!(
+            c.ray_for_pixel(100, 50).direction,
+            Vector::new(0.0, 0.0, -1.0)
+        );
+
+        // The edges of the widest dimension are half the field of view away from the center,
+        // which is behind the camera for a field of view wider than a half turn.
+        let edge = c.ray_for_position(0.0, 50.5);
+
+        assert_eq!(edge.origin, Point::new(0.0, 0.0, 0.0));
+        assert_eq!(
+            edge.direction,
+            Vector::new(2_f64.sqrt() / 2.0, 0.0, 2_f64.sqrt() / 2.0)
+        );
     }
 
     #[test]
-    fn trying_to_create_a_camera_with_a_multiple_of_pi_field_of_view() {
-        let c0 = Camera::try_from(CameraBuilder {
-            width: 100,
-            height: 200,
-            field_of_view: 0.0,
+    fn a_stereographic_projection_cannot_cover_a_full_turn() {
+        let c = Camera::try_from(CameraBuilder {
+            width: 11,
+            height: 11,
+            field_of_view: 2.5 * std::f64::consts::PI,
             transform: Default::default(),
-        });
+        })
+        .unwrap();
 
-        let c1 = Camera::try_from(CameraBuilder {
-            width: 100,
-            height: 200,
-            field_of_view: std::f64::consts::PI,
+        assert_eq!(
+            c.with_projection(Projection::Stereographic),
+            Err(Error::FieldOfViewTooWide)
+        );
+    }
+
+    #[test]
+    fn changing_back_to_a_perspective_projection() {
+        let perspective = Camera::try_from(CameraBuilder {
+            width: 201,
+            height: 101,
+            field_of_view: std::f64::consts::FRAC_PI_2,
             transform: Default::default(),
-        });
+        })
+        .unwrap();
 
-        let c2 = Camera::try_from(CameraBuilder {
-            width: 100,
-            height: 200,
-            field_of_view: 3.0 * std::f64::consts::PI,
+        let c = stereographic_camera(std::f64::consts::FRAC_PI_2)
+            .with_projection(Projection::Perspective)
+            .unwrap();
+
+        assert_eq!(c, perspective);
+    }
+
+    #[test]
+    fn changing_the_resolution_keeps_the_corners_of_the_image_plane() {
+        let c = Camera::try_from(CameraBuilder {
+            width: 1000,
+            height: 3,
+            field_of_view: std::f64::consts::FRAC_PI_2,
             transform: Default::default(),
-        });
+        })
+        .unwrap();
 
-        assert_eq!(c0, Err(Error::MultipleOfPiFieldOfView));
-        assert_eq!(c1, Err(Error::MultipleOfPiFieldOfView));
-        assert_eq!(c2, Err(Error::MultipleOfPiFieldOfView));
+        // Scaling both dimensions by the same factor rounds them to another aspect ratio.
+        let scaled = c.with_resolution(333, 1);
+
+        assert_approx!(scaled.half_width, c.half_width);
+        assert_approx!(scaled.half_height, scaled.half_width / 333.0);
+
+        let top_left = scaled.ray_for_position(0.0, 0.0).direction;
+        let bottom_right = scaled.ray_for_position(333.0, 1.0).direction;
+
+        assert_eq!(
+            bottom_right,
+            Vector::new(-top_left.0.x, -top_left.0.y, top_left.0.z)
+        );
+    }
+
+    #[test]
+    fn projecting_a_point_into_a_stereographic_image() {
+        let c = stereographic_camera(5.0 * std::f64::consts::FRAC_PI_3)
+            .with_transform(Transform::rotation_x(1.0) * Transform::translation(1.0, -2.0, 5.0));
+
+        for (x, y) in [(0, 0), (100, 50), (37, 90), (200, 100)] {
+            let ray = c.ray_for_pixel(x, y);
+            assert_eq!(c.project(ray.position(3.0)), Some((x, y)));
+        }
+
+        let behind = c.ray_for_pixel(100, 50).position(-3.0);
+        assert_eq!(c.project(behind), None);
+    }
+
+    #[test]
+    fn deserializing_a_camera_with_a_projection() {
+        let c: Camera = serde_json::from_str(
+            r#"{
+                "width": 11,
+                "height": 11,
+                "field_of_view": 300.0,
+                "projection": "stereographic"
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(c.projection(), Projection::Stereographic);
+
+        let c: Result<Camera, _> = serde_json::from_str(
+            r#"{ "width": 11, "height": 11, "field_of_view": 90.0, "projection": "fisheye" }"#,
+        );
+
+        assert!(c.is_err());
+    }
+
+    #[test]
+    fn light_group_passes_add_up_to_the_whole_image() {
+        let mut w = test_world();
+        w.lights.push(Light::Point(PointLight {
+            position: Point::new(10.0, 10.0, -10.0),
+            intensity: Color {
+                red: 0.5,
+                green: 0.5,
+                blue: 1.0,
+            },
+            group: None,
+        }));
+        w.lights[0] = w.lights[0].clone().with_group("key");
+
+        let c = camera_looking_at_the_origin();
+        let settings = RenderSettings::default();
+
+        let passes = c.render_light_groups(&w, &settings);
+        let image = c.render_with_settings(&w, &settings);
+
+        assert_eq!(passes.len(), 2);
+
+        let sum = &passes["key"] + &passes[DEFAULT_LIGHT_GROUP];
+
+        for y in 0..c.vsize {
+            for x in 0..c.hsize {
+                assert_eq!(sum.pixel_at(x, y), image.pixel_at(x, y));
+            }
+        }
+
+        // The key light alone is the image rendered with only that light.
+        w.lights.truncate(1);
+        let key = c.render_with_settings(&w, &settings);
+
+        assert_eq!(passes["key"].pixel_at(5, 5), key.pixel_at(5, 5));
+        assert_ne!(passes["key"].pixel_at(5, 5), image.pixel_at(5, 5));
+    }
+
+    #[test]
+    fn reprojecting_a_frame_seen_from_the_same_place() {
+        let world = test_world();
+        let c = Camera::try_from(CameraBuilder {
+            width: 11,
+            height: 11,
+            field_of_view: std::f64::consts::FRAC_PI_2,
+            transform: Transform::view(
+                Point::new(0.0, 0.0, -5.0),
+                Point::new(0.0, 0.0, 0.0),
+                Vector::new(0.0, 1.0, 0.0),
+            )
+            .unwrap(),
+        })
+        .unwrap();
+
+        let settings = RenderSettings::default();
+        let mut cache = ReprojectionCache::new(11, 11);
+
+        let (first, first_stats) = c.render_reprojected(&world, &settings, &mut cache);
+
+        let reference = c.render(&world);
+
+        assert_eq!(cache.reused_pixels(), 0);
+
+        let (second, second_stats) = c.render_reprojected(&world, &settings, &mut cache);
+
+        // Every pixel that sees the spheres is reused, so no shadow rays are casted.
+        assert!(cache.reused_pixels() > 0);
+        assert!(first_stats.shadow_rays > 0);
+        assert_eq!(second_stats.shadow_rays, 0);
+        assert_eq!(second_stats.primary_rays, 11 * 11);
+
+        for (x, y) in c.rays().map(|(pixel, _)| pixel) {
+            assert_eq!(first.pixel_at(x, y), reference.pixel_at(x, y));
+            assert_eq!(second.pixel_at(x, y), first.pixel_at(x, y));
+        }
+    }
+
+    #[test]
+    fn reprojecting_a_frame_after_moving_the_camera() {
+        let world = test_world();
+        let camera_at = |x| {
+            Camera::try_from(CameraBuilder {
+                width: 21,
+                height: 21,
+                field_of_view: std::f64::consts::FRAC_PI_2,
+                transform: Transform::view(
+                    Point::new(x, 0.0, -5.0),
+                    Point::new(0.0, 0.0, 0.0),
+                    Vector::new(0.0, 1.0, 0.0),
+                )
+                .unwrap(),
+            })
+            .unwrap()
+        };
+
+        let settings = RenderSettings::default();
+        let mut cache = ReprojectionCache::new(21, 21);
+
+        camera_at(0.0).render_reprojected(&world, &settings, &mut cache);
+        let (_, stats) = camera_at(0.5).render_reprojected(&world, &settings, &mut cache);
+
+        // Most of the sphere is still visible, but the regions it uncovered have to be shaded.
+        assert!(cache.reused_pixels() > 0);
+        assert!(stats.shadow_rays > 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "the reprojection cache must have the same dimensions as the camera")]
+    fn reprojecting_with_a_cache_of_different_dimensions() {
+        let c = Camera::try_from(CameraBuilder {
+            width: 4,
+            height: 4,
+            field_of_view: std::f64::consts::FRAC_PI_2,
+            transform: Default::default(),
+        })
+        .unwrap();
+
+        c.render_reprojected(
+            &World::default(),
+            &RenderSettings::default(),
+            &mut ReprojectionCache::new(2, 2),
+        );
+    }
+
+    #[test]
+    fn rendering_the_indirect_color_at_half_resolution() {
+        let world = test_world();
+        let c = Camera::try_from(CameraBuilder {
+            width: 11,
+            height: 11,
+            field_of_view: std::f64::consts::FRAC_PI_2,
+            transform: Transform::view(
+                Point::new(0.0, 0.0, -5.0),
+                Point::new(0.0, 0.0, 0.0),
+                Vector::new(0.0, 1.0, 0.0),
+            )
+            .unwrap(),
+        })
+        .unwrap();
+
+        let settings = RenderSettings {
+            half_resolution_indirect: true,
+            ..Default::default()
+        };
+
+        let reference = c.render(&world);
+        let (image, stats) = c.render_with_stats(&world, &settings);
+
+        // Whitted ray tracing has no indirect color, so the image doesn't change.
+        for (x, y) in c.rays().map(|(pixel, _)| pixel) {
+            assert_eq!(image.pixel_at(x, y), reference.pixel_at(x, y));
+        }
+
+        assert_eq!(stats.primary_rays, 11 * 11 + 6 * 6);
+    }
+
+    #[test]
+    fn rendering_ambient_occlusion_at_half_resolution() {
+        let world = World {
+            objects: vec![crate::shape::Shape::Plane(Default::default())],
+            lights: vec![],
+            ..Default::default()
+        };
+
+        let c = Camera::try_from(CameraBuilder {
+            width: 8,
+            height: 8,
+            field_of_view: std::f64::consts::FRAC_PI_2,
+            transform: Transform::view(
+                Point::new(0.0, 1.0, 0.0),
+                Point::new(0.0, 0.0, 0.0),
+                Vector::new(0.0, 0.0, 1.0),
+            )
+            .unwrap(),
+        })
+        .unwrap();
+
+        let settings = RenderSettings {
+            integrator: Arc::new(crate::integrator::AmbientOcclusion::default()),
+            half_resolution_indirect: true,
+            ..Default::default()
+        };
+
+        let image = c.render_with_settings(&world, &settings);
+
+        // Nothing occludes the plane, so it's as white as it is at full resolution.
+        for (x, y) in c.rays().map(|(pixel, _)| pixel) {
+            assert_eq!(*image.pixel_at(x, y), color::consts::WHITE);
+        }
     }
 }
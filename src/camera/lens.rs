@@ -0,0 +1,233 @@
+use std::str::FromStr;
+
+use crate::world::UnitScale;
+
+use super::Error;
+
+/// Size of the sensor of a real camera, in millimeters.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct SensorSize {
+    /// Width of the sensor in millimeters.
+    pub width: f64,
+
+    /// Height of the sensor in millimeters.
+    pub height: f64,
+}
+
+/// Lens of a real camera, used to describe the field of view and the aperture of a
+/// [Camera](super::Camera) the way photographers do.
+///
+/// # Examples
+///
+/// A lens can be parsed from it's focal length, it's f-number and the name of the sensor, in any
+/// order. The sensor is [full frame](SensorSize::FULL_FRAME) by default, and a lens without an
+/// f-number is a pinhole that keeps everything in focus. The names of the sensors are `full
+/// frame`, `aps-c`, `micro four thirds` and `super 35`.
+///
+/// ```
+/// use raytracer::{
+///     camera::{Camera, CameraBuilder, Lens, SensorSize},
+///     world::UnitScale,
+/// };
+///
+/// let lens: Lens = "50mm f/1.8 full frame".parse().unwrap();
+///
+/// assert_eq!(
+///     lens,
+///     Lens {
+///         focal_length: 50.0,
+///         f_number: Some(1.8),
+///         sensor: SensorSize::FULL_FRAME,
+///     }
+/// );
+///
+/// let camera = Camera::try_from(CameraBuilder {
+///     width: 160,
+///     height: 90,
+///     field_of_view: std::f64::consts::FRAC_PI_3,
+///     transform: Default::default(),
+/// })
+/// .unwrap()
+/// .with_lens(&lens, UnitScale::METERS)
+/// .unwrap();
+///
+/// assert!((camera.aperture() - 0.0139).abs() < 0.0001);
+/// assert_eq!(camera.focal_distance(), 1.0);
+/// ```
+///
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Lens {
+    /// Focal length in millimeters.
+    pub focal_length: f64,
+
+    /// Ratio between the focal length and the diameter of the aperture, or `None` for a pinhole.
+    pub f_number: Option<f64>,
+
+    /// Sensor behind the lens.
+    pub sensor: SensorSize,
+}
+
+impl SensorSize {
+    /// Sensor of the size of a 35mm film frame.
+    pub const FULL_FRAME: Self = Self {
+        width: 36.0,
+        height: 24.0,
+    };
+
+    /// Sensor of most consumer digital cameras.
+    pub const APS_C: Self = Self {
+        width: 23.6,
+        height: 15.6,
+    };
+
+    /// Sensor of Micro Four Thirds cameras.
+    pub const MICRO_FOUR_THIRDS: Self = Self {
+        width: 17.3,
+        height: 13.0,
+    };
+
+    /// Sensor of most digital cinema cameras.
+    pub const SUPER_35: Self = Self {
+        width: 24.89,
+        height: 18.66,
+    };
+}
+
+impl Lens {
+    /// Field of view of the lens in radians, along the longest side of it's sensor, which is the
+    /// side that [CameraBuilder::field_of_view](super::CameraBuilder::field_of_view) spans in
+    /// images that aren't square.
+    pub fn field_of_view(&self) -> f64 {
+        let side = self.sensor.width.max(self.sensor.height);
+        2.0 * (side / (2.0 * self.focal_length)).atan()
+    }
+
+    /// Radius of the aperture of the lens, in the units of a world.
+    pub fn aperture(&self, units: UnitScale) -> f64 {
+        self.f_number.map_or(0.0, |f_number| {
+            let radius_in_meters = self.focal_length / f_number / 2.0 / 1000.0;
+            radius_in_meters / units.meters_per_unit()
+        })
+    }
+}
+
+impl FromStr for Lens {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || Error::InvalidLens(s.to_string());
+
+        let mut focal_length = None;
+        let mut f_number = None;
+        let mut sensor = vec![];
+
+        for token in s.split_whitespace() {
+            let token = token.to_lowercase();
+
+            if let Some(value) = token.strip_suffix("mm") {
+                focal_length = Some(value.parse::<f64>().map_err(|_| invalid())?);
+            } else if let Some(value) = token.strip_prefix("f/") {
+                f_number = Some(value.parse::<f64>().map_err(|_| invalid())?);
+            } else {
+                sensor.push(token);
+            }
+        }
+
+        let sensor = match sensor.join(" ").as_str() {
+            "" | "full frame" | "full-frame" => SensorSize::FULL_FRAME,
+            "aps-c" | "apsc" => SensorSize::APS_C,
+            "micro four thirds" | "mft" => SensorSize::MICRO_FOUR_THIRDS,
+            "super 35" | "super35" => SensorSize::SUPER_35,
+            _ => return Err(invalid()),
+        };
+
+        let focal_length = focal_length
+            .filter(|length| *length > 0.0)
+            .ok_or_else(invalid)?;
+
+        if f_number.is_some_and(|f_number: f64| f_number <= 0.0 || !f_number.is_finite()) {
+            return Err(invalid());
+        }
+
+        Ok(Self {
+            focal_length,
+            f_number,
+            sensor,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::assert_approx;
+
+    use super::*;
+
+    #[test]
+    fn parsing_a_lens() {
+        assert_eq!(
+            "35mm f/2.8 APS-C".parse(),
+            Ok(Lens {
+                focal_length: 35.0,
+                f_number: Some(2.8),
+                sensor: SensorSize::APS_C,
+            })
+        );
+        assert_eq!(
+            "super 35 85mm".parse(),
+            Ok(Lens {
+                focal_length: 85.0,
+                f_number: None,
+                sensor: SensorSize::SUPER_35,
+            })
+        );
+    }
+
+    #[test]
+    fn parsing_an_invalid_lens() {
+        for description in ["f/1.8", "0mm", "50mm f/0", "50mm f/x", "50mm medium format"] {
+            assert_eq!(
+                description.parse::<Lens>(),
+                Err(Error::InvalidLens(description.to_string()))
+            );
+        }
+    }
+
+    #[test]
+    fn the_field_of_view_of_a_lens() {
+        let lens = Lens {
+            focal_length: 50.0,
+            f_number: None,
+            sensor: SensorSize::FULL_FRAME,
+        };
+
+        assert_approx!(lens.field_of_view().to_degrees(), 39.59775);
+
+        // A smaller sensor sees less through the same lens.
+        let small = Lens {
+            sensor: SensorSize::MICRO_FOUR_THIRDS,
+            ..lens
+        };
+
+        assert!(small.field_of_view() < lens.field_of_view());
+    }
+
+    #[test]
+    fn the_aperture_of_a_lens_in_the_units_of_the_world() {
+        let lens = Lens {
+            focal_length: 50.0,
+            f_number: Some(2.0),
+            sensor: SensorSize::FULL_FRAME,
+        };
+
+        assert_approx!(lens.aperture(UnitScale::METERS), 0.0125);
+        assert_approx!(lens.aperture(UnitScale::MILLIMETERS), 12.5);
+
+        let pinhole = Lens {
+            f_number: None,
+            ..lens
+        };
+
+        assert_eq!(pinhole.aperture(UnitScale::METERS), 0.0);
+    }
+}
@@ -1,9 +1,37 @@
-use std::collections::HashMap;
+use std::{
+    collections::HashMap,
+    ops::{Add, Mul},
+};
 
-use image::{ImageBuffer, Rgb, RgbImage};
+use image::{DynamicImage, ImageBuffer, Rgb, Rgb32FImage, RgbImage};
+use serde::Deserialize;
 
 use crate::color::{self, Color};
 
+/// How the colors of an image are fit into the range of values of the format it's saved in.
+///
+/// Lighting isn't limited to the range from black to white, so surfaces lit by multiple lights,
+/// or seen through many reflections, often end up brighter than white.
+///
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all(deserialize = "snake_case"))]
+pub enum ColorRange {
+    /// Each channel is clamped between `0.0` and `1.0`, so colors brighter than white lose their
+    /// hue and details.
+    #[default]
+    Clamp,
+
+    /// Every color is scaled by the same factor, so the brightest channel of the image becomes
+    /// `1.0`. This keeps the hue of the brightest parts of the image, at the cost of darkening the
+    /// rest of it.
+    NormalizeToMax,
+
+    /// Colors are kept as floating point values as they are, which can only be saved in formats
+    /// with floating point channels, like OpenEXR.
+    Hdr,
+}
+
+/// Image rendered by a camera, with the color of each of it's pixels.
 #[derive(Debug)]
 pub struct Canvas {
     pub(crate) width: usize,
@@ -28,27 +56,203 @@ impl Canvas {
         self.pixels.insert((x, y), color);
     }
 
+    /// Returns a copy of the image with it's exposure changed by `stops`. Every stop up doubles
+    /// the brightness of the image, and every stop down halves it.
+    ///
+    /// Colors aren't clamped until the image is converted to bytes, so details that are too bright
+    /// to be seen at the original exposure can be brought back by lowering it.
+    ///
+    pub fn with_exposure(&self, stops: f64) -> Self {
+        self * stops.exp2()
+    }
+
+    /// Returns a copy of the image at each of the exposure offsets in `stops`, in the same order.
+    /// See [DEFAULT_EXPOSURE_BRACKET](crate::render::DEFAULT_EXPOSURE_BRACKET) for the usual
+    /// offsets.
+    pub fn bracket(&self, stops: &[f64]) -> Vec<Self> {
+        stops
+            .iter()
+            .map(|&stops| self.with_exposure(stops))
+            .collect()
+    }
+
+    /// Returns the brightest value of each channel of each pixel between this image and `other`.
+    ///
+    /// # Panics
+    ///
+    /// If the images don't have the same dimensions.
+    ///
+    pub fn max(&self, other: &Self) -> Self {
+        self.zip_with(other, |lhs, rhs| Color {
+            red: lhs.red.max(rhs.red),
+            green: lhs.green.max(rhs.green),
+            blue: lhs.blue.max(rhs.blue),
+        })
+    }
+
+    fn map<F>(&self, f: F) -> Self
+    where
+        F: Fn(Color) -> Color,
+    {
+        Self {
+            width: self.width,
+            height: self.height,
+            pixels: self
+                .pixels
+                .iter()
+                .map(|(&coordinates, &color)| (coordinates, f(color)))
+                .collect(),
+        }
+    }
+
+    /// Combines each pixel of this image with the same pixel of `other`. Missing pixels are black
+    /// in both images, so `f` must give black when combining two black pixels.
+    fn zip_with<F>(&self, other: &Self, f: F) -> Self
+    where
+        F: Fn(Color, Color) -> Color,
+    {
+        assert_eq!(
+            (self.width, self.height),
+            (other.width, other.height),
+            "canvases must have the same dimensions to be combined"
+        );
+
+        let coordinates = self.pixels.keys().chain(other.pixels.keys());
+
+        Self {
+            width: self.width,
+            height: self.height,
+            pixels: coordinates
+                .map(|&(x, y)| ((x, y), f(*self.pixel_at(x, y), *other.pixel_at(x, y))))
+                .collect(),
+        }
+    }
+
+    /// Returns a copy of the image with it's colors fit into the given `range`. Colors are kept as
+    /// they are with a [ColorRange::Hdr] range.
+    pub(crate) fn fit_to(&self, range: ColorRange) -> Self {
+        match range {
+            ColorRange::Clamp => self.map(|color| Color {
+                red: color.red.clamp(0.0, 1.0),
+                green: color.green.clamp(0.0, 1.0),
+                blue: color.blue.clamp(0.0, 1.0),
+            }),
+            ColorRange::NormalizeToMax => {
+                let brightest = self
+                    .pixels
+                    .values()
+                    .flat_map(|color| [color.red, color.green, color.blue])
+                    .fold(0.0, f64::max);
+
+                if brightest > 0.0 {
+                    self * brightest.recip()
+                } else {
+                    self.map(|color| color)
+                }
+            }
+            ColorRange::Hdr => self.map(|color| color),
+        }
+    }
+
+    /// Converts the image to the pixels of a file, with it's colors fit into the given `range`.
+    /// Images are converted to 8 bits per channel, unless the range is [ColorRange::Hdr], which
+    /// keeps every channel as a 32 bits floating point value.
+    pub fn to_dynamic_image(&self, range: ColorRange) -> DynamicImage {
+        match range {
+            ColorRange::Hdr => {
+                let image = Rgb32FImage::from_fn(self.width as u32, self.height as u32, |x, y| {
+                    let color = self.pixel_at(x as usize, y as usize);
+                    Rgb([color.red as f32, color.green as f32, color.blue as f32])
+                });
+
+                DynamicImage::ImageRgb32F(image)
+            }
+            _ => DynamicImage::ImageRgb8(self.fit_to(range).to_image()),
+        }
+    }
+
+    /// Converts the image to 8 bits per channel, [clamping](ColorRange::Clamp) every channel
+    /// between `0.0` and `1.0`.
     pub fn to_image(&self) -> RgbImage {
         let mut img_buf = ImageBuffer::new(self.width as u32, self.height as u32);
 
         for (x, y, pixel) in img_buf.enumerate_pixels_mut() {
             let Color { red, green, blue } = self.pixel_at(x as usize, y as usize);
 
-            let red = (red * 255.0) as u8;
-            let green = (green * 255.0) as u8;
-            let blue = (blue * 255.0) as u8;
+            let red = (red.clamp(0.0, 1.0) * 255.0) as u8;
+            let green = (green.clamp(0.0, 1.0) * 255.0) as u8;
+            let blue = (blue.clamp(0.0, 1.0) * 255.0) as u8;
 
             *pixel = Rgb([red, green, blue]);
         }
 
         img_buf
     }
+
+    /// Returns the pixels of the image as RGBA bytes, row by row, which is the layout used by the
+    /// [ImageData](https://developer.mozilla.org/en-US/docs/Web/API/ImageData) of a browser's
+    /// canvas.
+    pub fn to_rgba(&self) -> Vec<u8> {
+        DynamicImage::ImageRgb8(self.to_image())
+            .into_rgba8()
+            .into_raw()
+    }
+}
+
+/// Sum of two images, such as the passes of a render, pixel by pixel.
+///
+/// # Panics
+///
+/// If the images don't have the same dimensions.
+///
+impl Add for &Canvas {
+    type Output = Canvas;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        self.zip_with(rhs, |lhs, rhs| lhs + rhs)
+    }
+}
+
+/// Product of two images pixel by pixel, such as an image and a mask.
+///
+/// # Panics
+///
+/// If the images don't have the same dimensions.
+///
+impl Mul for &Canvas {
+    type Output = Canvas;
+
+    fn mul(self, rhs: Self) -> Self::Output {
+        self.zip_with(rhs, |lhs, rhs| lhs * rhs)
+    }
+}
+
+/// Scales every channel of the image by the same factor.
+impl Mul<f64> for &Canvas {
+    type Output = Canvas;
+
+    fn mul(self, rhs: f64) -> Self::Output {
+        self.map(|color| color * rhs)
+    }
+}
+
+/// Scales each channel of the image by the channel of the color, which tints it.
+impl Mul<Color> for &Canvas {
+    type Output = Canvas;
+
+    fn mul(self, rhs: Color) -> Self::Output {
+        self.map(|color| color * rhs)
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn rgb(red: f64, green: f64, blue: f64) -> Color {
+        Color { red, green, blue }
+    }
+
     #[test]
     fn creating_a_canvas() {
         let c = Canvas::new(10, 20);
@@ -104,4 +308,151 @@ mod tests {
         assert_eq!(img[(2, 1)], Rgb([0, 127, 0]));
         assert_eq!(img[(4, 2)], Rgb([0, 0, 255]));
     }
+
+    #[test]
+    fn fitting_the_colors_of_a_canvas_into_a_range() {
+        let mut c = Canvas::new(2, 1);
+
+        c.write_pixel(0, 0, rgb(4.0, 1.0, -0.5));
+        c.write_pixel(1, 0, rgb(0.5, 0.25, 2.0));
+
+        let clamped = c.fit_to(ColorRange::Clamp);
+        assert_eq!(clamped.pixel_at(0, 0), &rgb(1.0, 1.0, 0.0));
+        assert_eq!(clamped.pixel_at(1, 0), &rgb(0.5, 0.25, 1.0));
+
+        let normalized = c.fit_to(ColorRange::NormalizeToMax);
+        assert_eq!(normalized.pixel_at(0, 0), &rgb(1.0, 0.25, -0.125));
+        assert_eq!(normalized.pixel_at(1, 0), &rgb(0.125, 0.0625, 0.5));
+
+        let hdr = c.fit_to(ColorRange::Hdr);
+        assert_eq!(hdr.pixel_at(0, 0), c.pixel_at(0, 0));
+    }
+
+    #[test]
+    fn normalizing_a_black_canvas() {
+        let c = Canvas::new(1, 1);
+
+        let normalized = c.fit_to(ColorRange::NormalizeToMax);
+
+        assert_eq!(normalized.pixel_at(0, 0), &color::consts::BLACK);
+    }
+
+    #[test]
+    fn converting_a_canvas_to_an_image_in_each_range() {
+        let mut c = Canvas::new(1, 1);
+
+        c.write_pixel(0, 0, rgb(2.0, 1.0, 0.5));
+
+        let clamped = c.to_dynamic_image(ColorRange::Clamp).into_rgb8();
+        assert_eq!(clamped[(0, 0)], Rgb([255, 255, 127]));
+
+        let normalized = c.to_dynamic_image(ColorRange::NormalizeToMax).into_rgb8();
+        assert_eq!(normalized[(0, 0)], Rgb([255, 127, 63]));
+
+        let DynamicImage::ImageRgb32F(hdr) = c.to_dynamic_image(ColorRange::Hdr) else {
+            panic!("expected an image with floating point channels");
+        };
+        assert_eq!(hdr[(0, 0)], Rgb([2.0, 1.0, 0.5]));
+    }
+
+    #[test]
+    fn changing_the_exposure_of_a_canvas() {
+        let mut c = Canvas::new(2, 1);
+
+        c.write_pixel(0, 0, rgb(2.0, 1.0, 0.5));
+
+        let brighter = c.with_exposure(1.0);
+        let darker = c.with_exposure(-2.0);
+
+        assert_eq!(brighter.pixel_at(0, 0), &rgb(4.0, 2.0, 1.0));
+        assert_eq!(darker.pixel_at(0, 0), &rgb(0.5, 0.25, 0.125));
+        assert_eq!(darker.pixel_at(1, 0), &color::consts::BLACK);
+
+        assert_eq!(darker.to_image()[(0, 0)], Rgb([127, 63, 31]));
+    }
+
+    #[test]
+    fn bracketing_the_exposure_of_a_canvas() {
+        let mut c = Canvas::new(1, 1);
+
+        c.write_pixel(0, 0, rgb(1.0, 1.0, 1.0));
+
+        let bracket = c.bracket(&[-2.0, 0.0, 2.0]);
+
+        assert_eq!(bracket.len(), 3);
+        assert_eq!(bracket[0].pixel_at(0, 0), &rgb(0.25, 0.25, 0.25));
+        assert_eq!(bracket[1].pixel_at(0, 0), &rgb(1.0, 1.0, 1.0));
+        assert_eq!(bracket[2].pixel_at(0, 0), &rgb(4.0, 4.0, 4.0));
+    }
+
+    #[test]
+    fn adding_and_multiplying_canvases() {
+        let mut a = Canvas::new(2, 1);
+        let mut b = Canvas::new(2, 1);
+
+        a.write_pixel(0, 0, rgb(0.5, 1.0, 2.0));
+        b.write_pixel(0, 0, rgb(0.25, 0.5, 0.5));
+        b.write_pixel(1, 0, rgb(1.0, 0.0, 0.5));
+
+        let sum = &a + &b;
+        let product = &a * &b;
+
+        assert_eq!(sum.pixel_at(0, 0), &rgb(0.75, 1.5, 2.5));
+        assert_eq!(sum.pixel_at(1, 0), &rgb(1.0, 0.0, 0.5));
+        assert_eq!(product.pixel_at(0, 0), &rgb(0.125, 0.5, 1.0));
+        assert_eq!(product.pixel_at(1, 0), &color::consts::BLACK);
+    }
+
+    #[test]
+    fn scaling_and_tinting_a_canvas() {
+        let mut c = Canvas::new(1, 1);
+
+        c.write_pixel(0, 0, rgb(0.5, 1.0, 2.0));
+
+        assert_eq!((&c * 2.0).pixel_at(0, 0), &rgb(1.0, 2.0, 4.0));
+        assert_eq!(
+            (&c * rgb(1.0, 0.5, 0.0)).pixel_at(0, 0),
+            &rgb(0.5, 0.5, 0.0)
+        );
+    }
+
+    #[test]
+    fn the_maximum_of_two_canvases() {
+        let mut a = Canvas::new(2, 1);
+        let mut b = Canvas::new(2, 1);
+
+        a.write_pixel(0, 0, rgb(0.5, 1.0, -2.0));
+        b.write_pixel(0, 0, rgb(0.25, 1.5, -1.0));
+        b.write_pixel(1, 0, rgb(-1.0, 0.0, 0.5));
+
+        let max = a.max(&b);
+
+        assert_eq!(max.pixel_at(0, 0), &rgb(0.5, 1.5, -1.0));
+        assert_eq!(max.pixel_at(1, 0), &rgb(0.0, 0.0, 0.5));
+    }
+
+    #[test]
+    #[should_panic(expected = "canvases must have the same dimensions to be combined")]
+    fn adding_canvases_of_different_dimensions() {
+        let _ = &Canvas::new(2, 1) + &Canvas::new(1, 2);
+    }
+
+    #[test]
+    fn getting_the_rgba_bytes_of_a_canvas() {
+        let mut c = Canvas::new(2, 2);
+
+        c.write_pixel(1, 0, color::consts::RED);
+        c.write_pixel(0, 1, color::consts::WHITE);
+
+        assert_eq!(
+            c.to_rgba(),
+            [
+                [0, 0, 0, 255],
+                [255, 0, 0, 255],
+                [255, 255, 255, 255],
+                [0, 0, 0, 255]
+            ]
+            .concat()
+        );
+    }
 }
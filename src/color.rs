@@ -2,7 +2,7 @@ use std::ops::{Add, Mul, Sub};
 
 use serde::Deserialize;
 
-use crate::float;
+use crate::{float, scene::UnknownFields};
 
 /// Module constants.
 pub mod consts;
@@ -26,6 +26,9 @@ pub struct ColorDeserializer {
     red: u8,
     green: u8,
     blue: u8,
+
+    #[serde(flatten)]
+    _unknown: UnknownFields,
 }
 
 impl PartialEq for Color {
@@ -0,0 +1,277 @@
+use thiserror::Error;
+
+use crate::{float, integrator};
+
+/// Environment variable that overrides the default number of threads used to render an image.
+pub const THREADS_VAR: &str = "RENDER_THREADS";
+
+/// Environment variable that overrides the default maximum number of bounces of a ray.
+pub const RECURSION_DEPTH_VAR: &str = "RENDER_RECURSION_DEPTH";
+
+/// Environment variable that overrides the default distance above a surface at which the rays
+/// that leave it start.
+pub const EPSILON_VAR: &str = "RENDER_EPSILON";
+
+/// The error type when trying to read the overrides of a configuration.
+#[derive(Debug, PartialEq, Error)]
+pub enum Error {
+    /// The error type when an environment variable or argument doesn't hold a valid value for
+    /// the default it overrides.
+    #[error("invalid value `{value}` for {name}, expected {expected}")]
+    InvalidOverride {
+        /// Name of the environment variable or argument.
+        name: String,
+
+        /// Value that was given.
+        value: String,
+
+        /// Description of the values that are accepted.
+        expected: &'static str,
+    },
+}
+
+/// Defaults of the renderer, that can be tuned for a deployment without changing any code or
+/// scene.
+///
+/// The library never reads the configuration on it's own, programs read it once at startup and
+/// pass it to what they render, such as with [RenderSettings::from_config] and
+/// [Scene::with_config](crate::scene::Scene::with_config).
+///
+/// Each default can be overridden with an environment variable, or with a command line argument
+/// of the program, which takes precedence over the environment variable:
+///
+/// | Default                                    | Environment variable     | Argument                 |
+/// | ------------------------------------------ | ------------------------ | ------------------------ |
+/// | [threads](Config::threads)                 | `RENDER_THREADS`         | `--threads=<N>`          |
+/// | [recursion_depth](Config::recursion_depth) | `RENDER_RECURSION_DEPTH` | `--recursion-depth=<N>`  |
+/// | [epsilon](Config::epsilon)                 | `RENDER_EPSILON`         | `--epsilon=<METERS>`     |
+/// | [progress](Config::progress)               |                          | `--progress`             |
+///
+/// [RenderSettings::from_config]: crate::render::RenderSettings::from_config
+///
+/// # Examples
+///
+/// ```
+/// use raytracer::config::{Config, Error};
+///
+/// let config = Config::from_sources(
+///     |name| (name == "RENDER_THREADS").then(|| "4".to_string()),
+///     ["render", "--threads=2", "--epsilon=0.0001"].map(String::from),
+/// )
+/// .unwrap();
+///
+/// assert_eq!(config.threads, 2);
+/// assert_eq!(config.recursion_depth, Config::DEFAULT.recursion_depth);
+/// assert_eq!(config.epsilon, 0.0001);
+///
+/// assert!(matches!(
+///     Config::from_sources(|_| None, ["render", "--threads=0"].map(String::from)),
+///     Err(Error::InvalidOverride { .. })
+/// ));
+/// ```
+///
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Config {
+    /// Number of threads used to render an image with the
+    /// [RenderSettings](crate::render::RenderSettings) of the configuration. By default this
+    /// value is `8`.
+    pub threads: usize,
+
+    /// Maximum number of times a ray can bounce off surfaces with the
+    /// [integrators](crate::integrator) of the configuration. By default this value is `5`.
+    pub recursion_depth: u8,
+
+    /// Minimum distance in meters above a surface at which the rays that leave it start, so they
+    /// don't hit the surface they leave because of floating point errors. Rays leave surfaces far
+    /// from the origin of the world from further away, in proportion to the magnitude of their
+    /// coordinates. By default this value is a hundredth of a millimeter.
+    pub epsilon: f64,
+
+    /// Whether a progress bar is displayed in the terminal while rendering with the
+    /// [RenderSettings](crate::render::RenderSettings) of the configuration. By default this
+    /// value is `false`.
+    pub progress: bool,
+}
+
+impl Config {
+    /// Defaults used when nothing is overridden.
+    pub const DEFAULT: Self = Self {
+        threads: 8,
+        recursion_depth: integrator::RECURSION_DEPTH,
+        epsilon: float::EPSILON,
+        progress: false,
+    };
+
+    /// Reads the overrides from the environment variables of the program and the given command
+    /// line arguments, which are usually [std::env::args].
+    ///
+    /// # Errors
+    ///
+    /// Fails when an override is not a valid value for the default it overrides, such as a
+    /// number of threads that is not a positive integer.
+    ///
+    pub fn from_args<A>(args: A) -> Result<Self, Error>
+    where
+        A: IntoIterator<Item = String>,
+    {
+        Self::from_sources(|name| std::env::var(name).ok(), args)
+    }
+
+    /// Reads the overrides from the given lookup of environment variables and list of command
+    /// line arguments.
+    ///
+    /// # Errors
+    ///
+    /// Fails when an override is not a valid value for the default it overrides.
+    ///
+    pub fn from_sources<V, A>(var: V, args: A) -> Result<Self, Error>
+    where
+        V: Fn(&str) -> Option<String>,
+        A: IntoIterator<Item = String>,
+    {
+        let args: Vec<_> = args.into_iter().collect();
+
+        // Only the override with the highest precedence is used, so an invalid value is reported
+        // instead of being silently replaced by one of lower precedence.
+        let value = |var_name: &str, flag: &str| {
+            let prefix = format!("--{flag}=");
+
+            args.iter()
+                .rev()
+                .find_map(|arg| arg.strip_prefix(&prefix))
+                .map(|value| (format!("--{flag}"), value.to_string()))
+                .or_else(|| var(var_name).map(|value| (var_name.to_string(), value)))
+        };
+
+        fn parse<T: std::str::FromStr>(
+            (name, value): (String, String),
+            expected: &'static str,
+            is_valid: impl Fn(&T) -> bool,
+        ) -> Result<T, Error> {
+            match value.parse() {
+                Ok(parsed) if is_valid(&parsed) => Ok(parsed),
+                _ => Err(Error::InvalidOverride {
+                    name,
+                    value,
+                    expected,
+                }),
+            }
+        }
+
+        let default = Self::DEFAULT;
+
+        Ok(Self {
+            threads: value(THREADS_VAR, "threads")
+                .map(|value| parse(value, "a positive integer", |threads| *threads > 0))
+                .transpose()?
+                .unwrap_or(default.threads),
+            recursion_depth: value(RECURSION_DEPTH_VAR, "recursion-depth")
+                .map(|value| parse(value, "an integer from 0 to 255", |_| true))
+                .transpose()?
+                .unwrap_or(default.recursion_depth),
+            epsilon: value(EPSILON_VAR, "epsilon")
+                .map(|value| {
+                    parse(value, "a positive number", |e: &f64| {
+                        e.is_finite() && *e > 0.0
+                    })
+                })
+                .transpose()?
+                .unwrap_or(default.epsilon),
+            progress: args.iter().any(|arg| arg == "--progress"),
+        })
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(vars: &[(&str, &str)], args: &[&str]) -> Result<Config, Error> {
+        Config::from_sources(
+            |name| {
+                vars.iter()
+                    .find(|(var, _)| *var == name)
+                    .map(|(_, value)| value.to_string())
+            },
+            args.iter().map(|arg| arg.to_string()),
+        )
+    }
+
+    #[test]
+    fn the_defaults_are_used_when_nothing_is_overridden() {
+        assert_eq!(config(&[], &["render"]), Ok(Config::DEFAULT));
+    }
+
+    #[test]
+    fn displaying_the_progress_with_an_argument() {
+        let config = config(&[], &["render", "--progress"]).unwrap();
+
+        assert!(config.progress);
+        assert_eq!(
+            Config {
+                progress: false,
+                ..config
+            },
+            Config::DEFAULT
+        );
+    }
+
+    #[test]
+    fn overriding_the_defaults_with_environment_variables() {
+        let config = config(
+            &[
+                (THREADS_VAR, "3"),
+                (RECURSION_DEPTH_VAR, "10"),
+                (EPSILON_VAR, "1e-4"),
+            ],
+            &[],
+        );
+
+        assert_eq!(
+            config.unwrap(),
+            Config {
+                threads: 3,
+                recursion_depth: 10,
+                epsilon: 1e-4,
+                progress: false,
+            }
+        );
+    }
+
+    #[test]
+    fn arguments_take_precedence_over_environment_variables() {
+        let config = config(
+            &[(THREADS_VAR, "3"), (RECURSION_DEPTH_VAR, "10")],
+            &["render", "--threads=1", "--threads=2"],
+        )
+        .unwrap();
+
+        assert_eq!(config.threads, 2);
+        assert_eq!(config.recursion_depth, 10);
+    }
+
+    #[test]
+    fn invalid_overrides_are_rejected() {
+        let rejected = |result: Result<Config, Error>| match result {
+            Err(Error::InvalidOverride { name, .. }) => name,
+            Ok(_) => panic!("override was accepted"),
+        };
+
+        assert_eq!(rejected(config(&[(THREADS_VAR, "many")], &[])), THREADS_VAR);
+        assert_eq!(rejected(config(&[], &["--threads=0"])), "--threads");
+        assert_eq!(
+            rejected(config(&[], &["--recursion-depth=-1"])),
+            "--recursion-depth"
+        );
+        assert_eq!(
+            rejected(config(&[(EPSILON_VAR, "0.001")], &["--epsilon=0"])),
+            "--epsilon"
+        );
+    }
+}
@@ -0,0 +1,963 @@
+use std::sync::Arc;
+
+use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
+
+use crate::{
+    camera::{consts::ImageResolution, Camera, CameraBuilder, Projection},
+    color::{self, Color},
+    light::{AreaLight, AreaLightBuilder, Light, PointLight},
+    material::{self, Material},
+    pattern::{Pattern3D, Pattern3DSpec},
+    scene::Scene,
+    shape::{
+        Billboard, BillboardBuilder, BillboardOrientation, Cube, Curve, CurveBuilder, Cylinder,
+        CylinderBuilder, Group, GroupBuilder, Instance, InstanceBuilder, Plane, Shape,
+        ShapeBuilder, SmoothTriangle, Sphere, Triangle, TriangleBuilder,
+    },
+    transform::Transform,
+    tuple::{Point, Vector},
+    world::World,
+};
+
+/// Number of spheres along each half of the sides of the grid of [glass_spheres].
+const SPHERES: i32 = 12;
+
+/// Width of each cell of the grid of [glass_spheres].
+const CELL_WIDTH: f64 = 2.2;
+
+/// Seed of the random placement of the spheres of [glass_spheres], so the scene is the same every
+/// time it's built.
+const GLASS_SPHERES_SEED: u64 = 42;
+
+const METAL: Material = Material {
+    pattern: Pattern3D::Solid(Color {
+        red: 0.4863,
+        green: 0.5176,
+        blue: 0.5294,
+    }),
+    ambient: 0.1,
+    diffuse: 0.9,
+    index_of_refraction: material::consts::VACUUM_INDEX_OF_REFRACTION,
+    reflectivity: 0.1,
+    shininess: 5.0,
+    specular: 0.2,
+    transparency: 0.0,
+    dispersion: 0.0,
+    spectrum: None,
+    blend: None,
+    portal: None,
+    dirt: None,
+};
+
+const GLASS: Material = Material {
+    pattern: Pattern3D::Solid(Color {
+        red: 0.1,
+        green: 0.1,
+        blue: 0.1,
+    }),
+    ambient: 0.1,
+    diffuse: 0.9,
+    index_of_refraction: material::consts::GLASS_INDEX_OF_REFRACTION,
+    reflectivity: 0.5,
+    shininess: 400.0,
+    specular: 0.9,
+    transparency: 1.0,
+    dispersion: 0.0,
+    spectrum: None,
+    blend: None,
+    portal: None,
+    dirt: None,
+};
+
+/// A metallic sphere and two smaller colored spheres in a corner with checkered walls, lit by an
+/// area light.
+///
+/// # Examples
+///
+/// ```no_run
+/// use raytracer::{camera, examples};
+///
+/// let scene = examples::checkered_walls_metallic_sphere(camera::consts::HD);
+/// scene.render().to_image().save("image.png").unwrap();
+/// ```
+///
+pub fn checkered_walls_metallic_sphere(resolution: ImageResolution) -> Scene {
+    let floor = Shape::Plane(Plane::default());
+
+    let wall_material = Material {
+        pattern: Pattern3D::Checker(Pattern3DSpec::new(
+            color::consts::WHITE,
+            color::consts::BLACK,
+            Default::default(),
+        )),
+        ..Default::default()
+    };
+
+    let left_wall = Shape::Plane(Plane::from(ShapeBuilder {
+        material: wall_material.clone(),
+        transform: Transform::translation(0.0, 1.0, 0.0)
+            * Transform::rotation_z(std::f64::consts::FRAC_PI_2),
+    }));
+
+    let right_wall = Shape::Plane(Plane::from(ShapeBuilder {
+        material: wall_material,
+        transform: Transform::rotation_x(std::f64::consts::FRAC_PI_2),
+    }));
+
+    let metallic_sphere = Shape::Sphere(Sphere::from(ShapeBuilder {
+        material: Material {
+            reflectivity: 0.25,
+            pattern: Pattern3D::Solid(Color {
+                red: 0.5,
+                green: 0.5,
+                blue: 0.5,
+            }),
+            ..Default::default()
+        },
+        transform: Transform::translation(4.0, 1.0, -4.0),
+    }));
+
+    let red_sphere = Shape::Sphere(Sphere::from(ShapeBuilder {
+        material: Material {
+            pattern: Pattern3D::Solid(color::consts::RED),
+            ..Default::default()
+        },
+        transform: Transform::translation(4.0, 0.5, -6.0)
+            * Transform::scaling(0.5, 0.5, 0.5).unwrap(),
+    }));
+
+    let blue_sphere = Shape::Sphere(Sphere::from(ShapeBuilder {
+        material: Material {
+            pattern: Pattern3D::Solid(Color {
+                red: 0.5,
+                green: 0.5,
+                blue: 0.9,
+            }),
+            ..Default::default()
+        },
+        transform: Transform::translation(6.0, 0.25, -4.5)
+            * Transform::scaling(0.25, 0.25, 0.25).unwrap(),
+    }));
+
+    let light = Light::Area(AreaLight::from(AreaLightBuilder {
+        corner: Point::new(5.0, 5.0, -10.0),
+        horizontal_dir: Vector::new(4.0, 0.0, 0.0),
+        horizontal_cells: 8,
+        vertical_dir: Vector::new(0.0, 4.0, 0.0),
+        vertical_cells: 8,
+        intensity: color::consts::WHITE,
+    }));
+
+    let world = World {
+        objects: vec![
+            floor,
+            left_wall,
+            right_wall,
+            metallic_sphere,
+            red_sphere,
+            blue_sphere,
+        ],
+        lights: vec![light],
+        ..Default::default()
+    };
+
+    Scene {
+        camera: camera(
+            resolution,
+            Point::new(10.0, 3.0, -10.0),
+            Point::new(0.0, 0.0, 0.0),
+        ),
+        world,
+        names: Default::default(),
+    }
+}
+
+/// A grid of randomly sized metallic and glass spheres over a checkered floor.
+///
+/// The spheres are placed randomly, but with the same random sequence every time, so the scene
+/// always looks the same.
+///
+/// # Examples
+///
+/// ```no_run
+/// use raytracer::{camera, examples};
+///
+/// let scene = examples::glass_spheres(camera::consts::HD);
+/// scene.render().to_image().save("image.png").unwrap();
+/// ```
+///
+pub fn glass_spheres(resolution: ImageResolution) -> Scene {
+    let mut rng = StdRng::seed_from_u64(GLASS_SPHERES_SEED);
+
+    let mut spheres = Group::default();
+    let materials = [METAL, GLASS];
+
+    for x in -SPHERES..SPHERES {
+        for z in -SPHERES..SPHERES {
+            let jitter_x = rng.gen_range(-0.2..=0.2);
+            let jitter_z = rng.gen_range(-0.2..=0.2);
+
+            let x = (f64::from(x) + 0.5 + jitter_x) * CELL_WIDTH;
+            let z = (f64::from(z) + 0.5 + jitter_z) * CELL_WIDTH;
+
+            let scaling_factor = rng.gen_range(0.25..=1.0);
+            let transform = Transform::translation(x, 0.0, z)
+                * Transform::scaling(scaling_factor, scaling_factor, scaling_factor).unwrap()
+                * Transform::translation(0.0, 1.0, 0.0);
+
+            let material = materials.choose(&mut rng).unwrap().clone();
+
+            let sphere = Shape::Sphere(Sphere::from(ShapeBuilder {
+                material,
+                transform,
+            }));
+            spheres.push(sphere);
+        }
+    }
+
+    let floor = Shape::Plane(Plane::from(ShapeBuilder {
+        material: Material {
+            pattern: Pattern3D::Checker(Pattern3DSpec::new(
+                Color {
+                    red: 0.9264,
+                    green: 0.902,
+                    blue: 0.8392,
+                },
+                Color {
+                    red: 0.9412,
+                    green: 0.9176,
+                    blue: 0.8392,
+                },
+                Transform::scaling(0.33, 0.33, 0.33).unwrap(),
+            )),
+            specular: 0.1,
+            ..Default::default()
+        },
+        ..Default::default()
+    }));
+
+    let light = Light::Point(PointLight {
+        position: Point::new(-40.0, 40.0, 0.0),
+        intensity: color::consts::WHITE,
+        group: None,
+    });
+
+    spheres.divide(256);
+
+    let world = World {
+        objects: vec![floor, Shape::Group(spheres)],
+        lights: vec![light],
+        ..Default::default()
+    };
+
+    Scene {
+        camera: camera(
+            resolution,
+            Point::new(5.0, 7.0, -10.0),
+            Point::new(0.0, 0.0, 0.0),
+        ),
+        world,
+        names: Default::default(),
+    }
+}
+
+/// The spheres of [glass_spheres] wrapped around a "little planet", seen from above it's center
+/// with a [stereographic projection](Projection::Stereographic).
+///
+/// # Examples
+///
+/// ```no_run
+/// use raytracer::{camera, examples};
+///
+/// let scene = examples::little_planet(camera::consts::HD);
+/// scene.render().to_image().save("image.png").unwrap();
+/// ```
+///
+pub fn little_planet(resolution: ImageResolution) -> Scene {
+    let Scene { world, .. } = glass_spheres(resolution);
+
+    let camera = Camera::try_from(CameraBuilder {
+        width: resolution.width,
+        height: resolution.height,
+        field_of_view: 300_f64.to_radians(),
+        transform: Transform::view(
+            Point::new(0.0, 3.0, 0.0),
+            Point::new(0.0, 0.0, 0.0),
+            Vector::new(0.0, 0.0, 1.0),
+        )
+        .unwrap(),
+    })
+    .unwrap()
+    .with_projection(Projection::Stereographic)
+    .unwrap();
+
+    Scene {
+        camera,
+        world,
+        names: Default::default(),
+    }
+}
+
+/// A striped sphere over a white floor, lit by a red and a green area light.
+///
+/// # Examples
+///
+/// ```no_run
+/// use raytracer::{camera, examples};
+///
+/// let scene = examples::striped_sphere_multiple_lights(camera::consts::HD);
+/// scene.render().to_image().save("image.png").unwrap();
+/// ```
+///
+pub fn striped_sphere_multiple_lights(resolution: ImageResolution) -> Scene {
+    let floor = Shape::Plane(Plane::from(ShapeBuilder {
+        material: Material {
+            pattern: Pattern3D::Solid(color::consts::WHITE),
+            ..Default::default()
+        },
+        ..Default::default()
+    }));
+
+    let striped_sphere = Shape::Sphere(Sphere::from(ShapeBuilder {
+        material: Material {
+            pattern: Pattern3D::Stripe(Pattern3DSpec::new(
+                color::consts::WHITE,
+                Color {
+                    red: 0.7,
+                    green: 0.4,
+                    blue: 0.1,
+                },
+                Transform::scaling(0.25, 0.25, 0.25).unwrap()
+                    * Transform::rotation_z(-std::f64::consts::FRAC_PI_4)
+                    * Transform::translation(0.5, 0.0, 0.0),
+            )),
+            specular: 0.3,
+            ..Default::default()
+        },
+        transform: Transform::translation(0.0, 1.0, 0.0),
+    }));
+
+    let right_light = Light::Area(AreaLight::from(AreaLightBuilder {
+        corner: Point::new(10.0, 10.0, 10.0),
+        horizontal_dir: Vector::new(4.0, 0.0, 0.0),
+        horizontal_cells: 4,
+        vertical_dir: Vector::new(0.0, 4.0, 0.0),
+        vertical_cells: 4,
+        intensity: color::consts::RED,
+    }));
+
+    let left_light = Light::Area(AreaLight::from(AreaLightBuilder {
+        corner: Point::new(-10.0, 10.0, 10.0),
+        horizontal_dir: Vector::new(4.0, 0.0, 0.0),
+        horizontal_cells: 8,
+        vertical_dir: Vector::new(0.0, 4.0, 0.0),
+        vertical_cells: 8,
+        intensity: Color {
+            red: 0.3216,
+            green: 0.6784,
+            blue: 0.03,
+        },
+    }));
+
+    let world = World {
+        objects: vec![floor, striped_sphere],
+        lights: vec![left_light, right_light],
+        ..Default::default()
+    };
+
+    Scene {
+        camera: camera(
+            resolution,
+            Point::new(0.0, 3.0, 5.0),
+            Point::new(0.0, 1.0, 0.0),
+        ),
+        world,
+        names: Default::default(),
+    }
+}
+
+/// The classic sphere flake test scene by Eric Haines: a reflective sphere with nine spheres a
+/// third of it's size attached to it's surface, each of them with nine smaller spheres of their
+/// own, recursively up to the given depth.
+///
+/// A sphere flake of depth `n` has `(9^(n + 1) - 1) / 8` spheres, so it's a standard stress test
+/// for the bounding volume hierarchy. Each level of the flake is built once and shared by the
+/// nine [instances](crate::shape::Instance) of it in the level above, so only ten shapes are
+/// stored for each level, and the flake is already organized in a hierarchy of nested bounding
+/// volumes.
+///
+/// # Examples
+///
+/// ```no_run
+/// use raytracer::{camera, examples};
+///
+/// let scene = examples::sphere_flake(camera::consts::HD, 4);
+/// scene.render().to_image().save("image.png").unwrap();
+/// ```
+///
+pub fn sphere_flake(resolution: ImageResolution, depth: u32) -> Scene {
+    let material = Material {
+        pattern: Pattern3D::Solid(Color {
+            red: 0.8,
+            green: 0.6,
+            blue: 0.4,
+        }),
+        reflectivity: 0.3,
+        specular: 0.6,
+        shininess: 100.0,
+        ..Default::default()
+    };
+
+    let mut flake = Shape::Sphere(Sphere::from(ShapeBuilder {
+        material: material.clone(),
+        ..Default::default()
+    }));
+
+    for _ in 0..depth {
+        flake = sphere_flake_level(Arc::new(flake), &material);
+    }
+
+    let flake = Shape::Instance(Instance::from(InstanceBuilder {
+        prototype: Arc::new(flake),
+        transform: Transform::translation(0.0, 1.0, 0.0),
+        material: None,
+    }));
+
+    // The flake never extends further than twice the radius of it's biggest sphere.
+    let floor = Shape::Plane(Plane::from(ShapeBuilder {
+        material: Material {
+            pattern: Pattern3D::Checker(Pattern3DSpec::new(
+                color::consts::WHITE,
+                Color {
+                    red: 0.3,
+                    green: 0.3,
+                    blue: 0.3,
+                },
+                Default::default(),
+            )),
+            ..Default::default()
+        },
+        transform: Transform::translation(0.0, -1.0, 0.0),
+    }));
+
+    let light = Light::Point(PointLight {
+        position: Point::new(-10.0, 10.0, -10.0),
+        intensity: color::consts::WHITE,
+        group: None,
+    });
+
+    let world = World {
+        objects: vec![floor, flake],
+        lights: vec![light],
+        ..Default::default()
+    };
+
+    let camera = Camera::try_from(CameraBuilder {
+        width: resolution.width,
+        height: resolution.height,
+        field_of_view: std::f64::consts::FRAC_PI_3,
+        transform: Transform::orbit(
+            Point::new(0.0, 1.0, 0.0),
+            6.0,
+            std::f64::consts::FRAC_PI_6,
+            std::f64::consts::FRAC_PI_8,
+        )
+        .unwrap(),
+    })
+    .unwrap();
+
+    Scene {
+        camera,
+        world,
+        names: Default::default(),
+    }
+}
+
+/// Builds the next level of a sphere flake, which is a sphere with nine instances of the previous
+/// level around it, six around it's equator and three above it.
+fn sphere_flake_level(previous: Arc<Shape>, material: &Material) -> Shape {
+    use std::f64::consts::{FRAC_PI_2, FRAC_PI_3, FRAC_PI_6};
+
+    let directions = (0..6)
+        .map(|i| (f64::from(i) * FRAC_PI_3, 0.0))
+        .chain((0..3).map(|i| (FRAC_PI_6 + f64::from(i) * 2.0 * FRAC_PI_3, FRAC_PI_3)));
+
+    let mut level = Group::from(GroupBuilder {
+        children: [Shape::Sphere(Sphere::from(ShapeBuilder {
+            material: material.clone(),
+            ..Default::default()
+        }))],
+        transform: Default::default(),
+    });
+
+    for (azimuth, elevation) in directions {
+        let (sin_azimuth, cos_azimuth) = azimuth.sin_cos();
+        let (sin_elevation, cos_elevation) = elevation.sin_cos();
+
+        let direction = Vector::new(
+            cos_elevation * sin_azimuth,
+            sin_elevation,
+            cos_elevation * cos_azimuth,
+        );
+
+        // Each instance is turned so it grows away from the sphere it's attached to, and touches it's
+        // surface.
+        let offset = direction * (1.0 + 1.0 / 3.0);
+        let transform = Transform::translation(offset.0.x, offset.0.y, offset.0.z)
+            * Transform::rotation_y(azimuth)
+            * Transform::rotation_x(FRAC_PI_2 - elevation)
+            * Transform::scaling(1.0 / 3.0, 1.0 / 3.0, 1.0 / 3.0).unwrap();
+
+        level.push(Shape::Instance(Instance::from(InstanceBuilder {
+            prototype: Arc::clone(&previous),
+            transform,
+            material: None,
+        })));
+    }
+
+    Shape::Group(level)
+}
+
+/// Random scene, which is always the same for the same seed, meant to look for panics and invalid
+/// colors in the renderer and in the extensions built on top of it.
+///
+/// The scene has a handful of shapes of every kind with random transforms and random materials,
+/// including groups and instances of shared shapes, from one to three point or area lights, and a camera that looks at the origin from a random
+/// point around it. Every value is picked from a range that makes a valid scene, so rendering it
+/// must never panic nor give colors that aren't finite, no matter the seed.
+///
+/// # Examples
+///
+/// Fuzzing an integrator.
+///
+/// ```
+/// use raytracer::{camera::consts::ImageResolution, examples};
+///
+/// let resolution = ImageResolution { width: 8, height: 6 };
+///
+/// for seed in 0..4 {
+///     let image = examples::random(resolution, seed).render().to_image();
+///     assert_eq!(image.dimensions(), (8, 6));
+/// }
+/// ```
+///
+pub fn random(resolution: ImageResolution, seed: u64) -> Scene {
+    let mut rng = StdRng::seed_from_u64(seed);
+
+    let objects = (0..rng.gen_range(1..=12))
+        .map(|_| random_shape(&mut rng, true))
+        .collect();
+
+    let lights = (0..rng.gen_range(1..=3))
+        .map(|_| random_light(&mut rng))
+        .collect();
+
+    // The camera is kept away from the poles, so it's never looking parallel to it's up vector.
+    let azimuth: f64 = rng.gen_range(0.0..std::f64::consts::TAU);
+    let elevation: f64 = rng.gen_range(-1.2..1.2);
+    let distance = rng.gen_range(8.0..15.0);
+
+    let from = Point::new(
+        distance * elevation.cos() * azimuth.cos(),
+        distance * elevation.sin(),
+        distance * elevation.cos() * azimuth.sin(),
+    );
+
+    Scene {
+        camera: camera(resolution, from, Point::new(0.0, 0.0, 0.0)),
+        world: World {
+            objects,
+            lights,
+            ..Default::default()
+        },
+        names: Default::default(),
+    }
+}
+
+fn random_shape(rng: &mut StdRng, allow_groups: bool) -> Shape {
+    let material = random_material(rng);
+    let transform = random_transform(rng);
+
+    match rng.gen_range(0..if allow_groups { 11 } else { 9 }) {
+        0 => Shape::Sphere(Sphere::from(ShapeBuilder {
+            material,
+            transform,
+        })),
+        1 => Shape::Cube(Cube::from(ShapeBuilder {
+            material,
+            transform,
+        })),
+        2 => {
+            let min = rng.gen_range(-2.0..0.0);
+
+            Shape::Cylinder(Cylinder::from(CylinderBuilder {
+                material,
+                transform,
+                min,
+                max: min + rng.gen_range(0.1..3.0),
+                closed: rng.gen(),
+            }))
+        }
+        3 => Shape::Plane(Plane::from(ShapeBuilder {
+            material,
+            transform: Transform::translation(0.0, rng.gen_range(-3.0..-1.0), 0.0),
+        })),
+        4 | 5 => Shape::Triangle(random_triangle(rng, material)),
+        6 => {
+            let triangle = random_triangle(rng, material);
+            let [a, b, c] = triangle.vertices();
+
+            // The normals lean away from the normal of the face by less than it's length, so
+            // their blend at any point of the triangle is never null.
+            #[allow(clippy::unwrap_used)]
+            let normal = (b - a).cross(c - a).normalize().unwrap();
+            let mut lean = || random_point(rng, 0.5) - Point::new(0.0, 0.0, 0.0);
+
+            Shape::SmoothTriangle(SmoothTriangle {
+                triangle,
+                n0: normal + lean() * 0.5,
+                n1: normal + lean() * 0.5,
+                n2: normal + lean() * 0.5,
+            })
+        }
+        7 => {
+            let root = random_point(rng, 3.0);
+            let mut bend = || root + (random_point(rng, 1.0) - Point::new(0.0, 0.0, 0.0));
+
+            Shape::Curve(Curve::from(CurveBuilder {
+                material,
+                transform,
+                points: [root, bend(), bend(), bend()],
+                root_radius: rng.gen_range(0.02..0.2),
+                tip_radius: rng.gen_range(0.01..0.1),
+                ..Default::default()
+            }))
+        }
+        8 => Shape::Billboard(Billboard::from(BillboardBuilder {
+            material,
+            transform,
+            orientation: if rng.gen() {
+                BillboardOrientation::Spherical
+            } else {
+                BillboardOrientation::Cylindrical
+            },
+            ..Default::default()
+        })),
+        9 => Shape::Group(Group::from(GroupBuilder {
+            children: (0..rng.gen_range(1..=4))
+                .map(|_| random_shape(rng, false))
+                .collect::<Vec<_>>(),
+            transform,
+        })),
+        _ => {
+            let prototype = Arc::new(random_shape(rng, false));
+            let mut instances = vec![];
+
+            for _ in 0..rng.gen_range(1..=4) {
+                let transform = random_transform(rng);
+                let material = rng.gen_bool(0.5).then(|| random_material(rng));
+
+                instances.push(Shape::Instance(Instance::from(InstanceBuilder {
+                    prototype: Arc::clone(&prototype),
+                    transform,
+                    material,
+                })));
+            }
+
+            Shape::Group(Group::from(GroupBuilder {
+                children: instances,
+                transform,
+            }))
+        }
+    }
+}
+
+/// Triangle close to the origin.
+fn random_triangle(rng: &mut StdRng, material: Material) -> Triangle {
+    let v0 = random_point(rng, 4.0);
+
+    // The sides are along different axes, so they're never collinear.
+    let vertices = [
+        v0,
+        v0 + Vector::new(rng.gen_range(0.5..2.0), 0.0, rng.gen_range(-1.0..1.0)),
+        v0 + Vector::new(0.0, rng.gen_range(0.5..2.0), rng.gen_range(-1.0..1.0)),
+    ];
+
+    #[allow(clippy::unwrap_used)]
+    Triangle::try_from(TriangleBuilder { material, vertices }).unwrap()
+}
+
+fn random_material(rng: &mut StdRng) -> Material {
+    let colors = [random_color(rng), random_color(rng)];
+    let spec = Pattern3DSpec::new(colors[0], colors[1], random_transform(rng));
+
+    let pattern = match rng.gen_range(0..5) {
+        0 => Pattern3D::Solid(colors[0]),
+        1 => Pattern3D::Stripe(spec),
+        2 => Pattern3D::Gradient(spec),
+        3 => Pattern3D::Ring(spec),
+        _ => Pattern3D::Checker(spec),
+    };
+
+    // Only some of the materials are reflective or transparent, like in most scenes.
+    let mut optional = |probability| {
+        if rng.gen_bool(probability) {
+            rng.gen_range(0.0..=1.0)
+        } else {
+            0.0
+        }
+    };
+
+    let reflectivity = optional(0.3);
+    let transparency = optional(0.3);
+
+    #[allow(clippy::unwrap_used)]
+    let (_, index_of_refraction) = *material::consts::INDICES_OF_REFRACTION.choose(rng).unwrap();
+
+    Material {
+        pattern,
+        ambient: rng.gen_range(0.0..=0.3),
+        diffuse: rng.gen_range(0.0..=1.0),
+        specular: rng.gen_range(0.0..=1.0),
+        shininess: rng.gen_range(1.0..=300.0),
+        index_of_refraction,
+        reflectivity,
+        transparency,
+        dispersion: rng.gen_range(0.0..=0.01),
+        ..Default::default()
+    }
+}
+
+fn random_light(rng: &mut StdRng) -> Light {
+    let intensity = random_color(rng);
+    let position = random_point(rng, 10.0) + Vector::new(0.0, 10.0, 0.0);
+
+    if rng.gen_bool(0.5) {
+        return Light::Point(PointLight {
+            position,
+            intensity,
+            group: None,
+        });
+    }
+
+    Light::Area(AreaLight::from(AreaLightBuilder {
+        corner: position,
+        horizontal_dir: Vector::new(rng.gen_range(0.5..3.0), 0.0, 0.0),
+        horizontal_cells: rng.gen_range(1..=3),
+        vertical_dir: Vector::new(0.0, 0.0, rng.gen_range(0.5..3.0)),
+        vertical_cells: rng.gen_range(1..=3),
+        intensity,
+    }))
+}
+
+/// Translation, rotation and scaling of a shape, which keep it close to the origin.
+fn random_transform(rng: &mut StdRng) -> Transform {
+    let mut scale = || rng.gen_range(0.2..2.0);
+    let (x, y, z) = (scale(), scale(), scale());
+
+    let position = random_point(rng, 4.0);
+
+    // The scale is never zero, so the scaling is always invertible.
+    #[allow(clippy::unwrap_used)]
+    let scaling = Transform::scaling(x, y, z).unwrap();
+
+    Transform::translation(position.0.x, position.0.y, position.0.z)
+        * Transform::rotation_x(rng.gen_range(0.0..std::f64::consts::TAU))
+        * Transform::rotation_y(rng.gen_range(0.0..std::f64::consts::TAU))
+        * scaling
+}
+
+fn random_point(rng: &mut StdRng, extent: f64) -> Point {
+    let mut coordinate = || rng.gen_range(-extent..extent);
+    Point::new(coordinate(), coordinate(), coordinate())
+}
+
+fn random_color(rng: &mut StdRng) -> Color {
+    Color {
+        red: rng.gen(),
+        green: rng.gen(),
+        blue: rng.gen(),
+    }
+}
+
+/// Camera with the field of view used by every showcase scene, looking from `from` to `to`.
+fn camera(resolution: ImageResolution, from: Point, to: Point) -> Camera {
+    Camera::try_from(CameraBuilder {
+        width: resolution.width,
+        height: resolution.height,
+        field_of_view: std::f64::consts::FRAC_PI_3,
+        transform: Transform::view(from, to, Vector::new(0.0, 1.0, 0.0)).unwrap(),
+    })
+    .unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+
+    const RESOLUTION: ImageResolution = ImageResolution {
+        width: 16,
+        height: 9,
+    };
+
+    #[test]
+    fn building_the_showcase_scenes() {
+        let scenes = [
+            checkered_walls_metallic_sphere(RESOLUTION),
+            glass_spheres(RESOLUTION),
+            striped_sphere_multiple_lights(RESOLUTION),
+            little_planet(RESOLUTION),
+        ];
+
+        for scene in &scenes {
+            assert_eq!(scene.camera.width(), 16);
+            assert_eq!(scene.camera.height(), 9);
+        }
+
+        assert_eq!(scenes[0].world.objects.len(), 6);
+        assert_eq!(scenes[1].world.objects.len(), 2);
+        assert_eq!(scenes[2].world.lights.len(), 2);
+    }
+
+    #[test]
+    fn the_glass_spheres_are_always_in_the_same_place() {
+        let first = glass_spheres(RESOLUTION);
+        let second = glass_spheres(RESOLUTION);
+
+        assert_eq!(first.world.objects, second.world.objects);
+    }
+
+    fn count_spheres(shape: &Shape) -> usize {
+        match shape {
+            Shape::Group(group) => group.children.iter().map(count_spheres).sum(),
+            Shape::Instance(instance) => count_spheres(instance.prototype()),
+            Shape::Sphere(_) => 1,
+            _ => 0,
+        }
+    }
+
+    #[test]
+    fn a_sphere_flake_has_nine_copies_of_each_level() {
+        for (depth, spheres) in [(0, 1), (1, 10), (2, 91), (3, 820)] {
+            let scene = sphere_flake(RESOLUTION, depth);
+
+            assert_eq!(count_spheres(&scene.world.objects[1]), spheres);
+        }
+    }
+
+    #[test]
+    fn the_spheres_of_a_flake_touch_their_parent() {
+        let scene = sphere_flake(RESOLUTION, 1);
+
+        let Shape::Instance(flake) = &scene.world.objects[1] else {
+            panic!();
+        };
+        let Shape::Group(level) = flake.prototype().as_ref() else {
+            panic!();
+        };
+
+        let parent = level.children[0].transform() * Point::new(0.0, 0.0, 0.0);
+        let child = level.children[1].transform() * Point::new(0.0, 0.0, 0.0);
+
+        crate::assert_approx!((child - parent).magnitude(), 1.0 + 1.0 / 3.0);
+    }
+
+    #[test]
+    fn every_level_of_a_flake_is_shared_by_it_s_instances() {
+        let scene = sphere_flake(RESOLUTION, 3);
+
+        let Shape::Instance(flake) = &scene.world.objects[1] else {
+            panic!();
+        };
+        let Shape::Group(level) = flake.prototype().as_ref() else {
+            panic!();
+        };
+
+        let prototypes: Vec<_> = level.children[1..]
+            .iter()
+            .map(|child| match child {
+                Shape::Instance(instance) => instance.prototype(),
+                _ => panic!(),
+            })
+            .collect();
+
+        assert_eq!(prototypes.len(), 9);
+        assert!(prototypes.iter().all(|p| Arc::ptr_eq(p, prototypes[0])));
+
+        assert_eq!(Arc::strong_count(prototypes[0]), 9);
+    }
+
+    #[test]
+    fn a_random_scene_is_always_the_same_for_the_same_seed() {
+        let first = random(RESOLUTION, 7);
+        let second = random(RESOLUTION, 7);
+        let other = random(RESOLUTION, 8);
+
+        assert_eq!(first.world.objects, second.world.objects);
+        assert_eq!(first.world.lights, second.world.lights);
+        assert_ne!(first.world.objects, other.world.objects);
+    }
+
+    #[test]
+    fn random_scenes_have_shapes_of_every_kind() {
+        fn kinds(shape: &Shape, found: &mut HashSet<&'static str>) {
+            let kind = match shape {
+                Shape::Billboard(_) => "billboard",
+                Shape::Cube(_) => "cube",
+                Shape::Curve(_) => "curve",
+                Shape::Cylinder(_) => "cylinder",
+                Shape::Group(group) => {
+                    for child in &group.children {
+                        kinds(child, found);
+                    }
+                    "group"
+                }
+                Shape::Instance(instance) => {
+                    kinds(instance.prototype(), found);
+                    "instance"
+                }
+                Shape::Plane(_) => "plane",
+                Shape::SmoothTriangle(_) => "smooth_triangle",
+                Shape::Sphere(_) => "sphere",
+                Shape::Triangle(_) => "triangle",
+            };
+
+            found.insert(kind);
+        }
+
+        let mut found = HashSet::new();
+
+        for seed in 0..32 {
+            for object in &random(RESOLUTION, seed).world.objects {
+                kinds(object, &mut found);
+            }
+        }
+
+        assert_eq!(found.len(), 10);
+    }
+
+    #[test]
+    fn random_scenes_render_finite_colors() {
+        let resolution = ImageResolution {
+            width: 8,
+            height: 6,
+        };
+
+        for seed in 0..32 {
+            let image = random(resolution, seed).render();
+
+            for y in 0..image.height {
+                for x in 0..image.width {
+                    let Color { red, green, blue } = *image.pixel_at(x, y);
+
+                    assert!(
+                        red.is_finite() && green.is_finite() && blue.is_finite(),
+                        "seed {seed} rendered a color that isn't finite at ({x}, {y})"
+                    );
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,3072 @@
+use std::{
+    f64::consts::PI,
+    fmt::Debug,
+    ops::{Add, Mul},
+    sync::Arc,
+};
+
+use rand::Rng;
+
+use crate::{
+    camera::Camera,
+    color::{self, Color},
+    float,
+    intersection::{self, Computation, Intersection},
+    material::{Material, Portal},
+    pattern::Pattern3D,
+    ray::Ray,
+    render,
+    spectrum::{self, Spectrum},
+    tuple::{Point, Vector},
+    world::World,
+};
+
+/// Color of the edges of a camera frustum drawn by [Gizmos].
+const FRUSTUM_COLOR: Color = Color {
+    red: 1.0,
+    green: 1.0,
+    blue: 0.0,
+};
+
+/// Default number of times a ray can bounce off reflective or refractive surfaces.
+pub(crate) const RECURSION_DEPTH: u8 = 5;
+
+/// Lighting algorithm used to compute the color seen by rays casted into a world.
+///
+/// Integrators are selected at render time through
+/// [RenderSettings::integrator](crate::render::RenderSettings::integrator), so new lighting
+/// algorithms can be added without changing the [World] itself.
+///
+/// # Examples
+///
+/// Rendering a world with ambient occlusion instead of direct lighting.
+///
+/// ```
+/// use std::sync::Arc;
+///
+/// use raytracer::{
+///     camera::{Camera, CameraBuilder},
+///     integrator::AmbientOcclusion,
+///     render::RenderSettings,
+///     world::World,
+/// };
+///
+/// let camera = Camera::try_from(CameraBuilder {
+///     width: 32,
+///     height: 32,
+///     field_of_view: std::f64::consts::FRAC_PI_3,
+///     transform: Default::default(),
+/// }).unwrap();
+///
+/// let settings = RenderSettings {
+///     integrator: Arc::new(AmbientOcclusion::default()),
+///     ..Default::default()
+/// };
+///
+/// let image = camera.render_with_settings(&World::default(), &settings);
+/// ```
+///
+pub trait Integrator: Debug + Send + Sync {
+    /// Computes the color seen by a ray casted into the world.
+    fn color_at(&self, world: &World, ray: &Ray) -> Color;
+
+    /// Computes the part of the color seen by a ray that changes sharply across the image, such
+    /// as textures, direct lighting and mirror reflections.
+    ///
+    /// Together with [Integrator::indirect_color_at], it adds up to [Integrator::color_at]. By
+    /// default, all of the color is direct.
+    ///
+    fn direct_color_at(&self, world: &World, ray: &Ray) -> Color {
+        self.color_at(world, ray)
+    }
+
+    /// Computes the part of the color seen by a ray that is expensive to compute but changes
+    /// smoothly across surfaces, such as light bounced off diffuse surfaces, so it can be computed
+    /// at a lower resolution than the rest of the image.
+    ///
+    /// Together with [Integrator::direct_color_at], it adds up to [Integrator::color_at]. By
+    /// default, there is no indirect color.
+    ///
+    fn indirect_color_at(&self, _world: &World, _ray: &Ray) -> Color {
+        color::consts::BLACK
+    }
+
+    /// Computes the color seen by a ray split by the light of the world it comes from, which is
+    /// what the [light group passes](crate::camera::Camera::render_light_groups) of a render are
+    /// made of.
+    ///
+    /// The colors add up to [Integrator::color_at]. By default, the integrator can't tell the
+    /// lights apart, so all of the color is [unlit](LightColors::unlit).
+    ///
+    fn light_colors_at(&self, world: &World, ray: &Ray) -> LightColors {
+        LightColors {
+            lights: vec![color::consts::BLACK; world.lights.len()],
+            unlit: self.color_at(world, ray),
+        }
+    }
+}
+
+/// Color seen by a ray split by the light it comes from, as computed by
+/// [Integrator::light_colors_at].
+#[derive(Clone, Debug, PartialEq)]
+pub struct LightColors {
+    /// Color that comes from each light of the world, in the same order as the lights.
+    pub lights: Vec<Color>,
+
+    /// Color that doesn't come from any light, such as the color of integrators that ignore the
+    /// lights of the world.
+    pub unlit: Color,
+}
+
+/// Color carried by the rays of an integrator, either as a single color or split by the light it
+/// comes from, so the same tracing code computes both.
+trait Radiance:
+    Sized + Add<Output = Self> + Mul<f64, Output = Self> + Mul<Color, Output = Self>
+{
+    /// No color at all, in a world with the given number of lights.
+    fn black(lights: usize) -> Self;
+
+    /// Color that comes from the light at `index`, in a world with the given number of lights.
+    fn from_light(lights: usize, index: usize, color: Color) -> Self;
+}
+
+/// Classic recursive ray tracing, as described in the book.
+///
+/// Surfaces are lit directly by the light sources of the world, and rays are only spawned from
+/// reflective and transparent surfaces.
+///
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Whitted {
+    /// Maximum number of times a ray can bounce off reflective or refractive surfaces.
+    pub recursion_depth: u8,
+
+    /// Whether [Camera::record_ray_paths](crate::camera::Camera::record_ray_paths) records rays.
+    pub record_ray_paths: bool,
+}
+
+/// Ambient occlusion, which darkens the points of a surface that are enclosed by other surfaces.
+///
+/// Light sources are ignored, and surfaces are shaded with their own color scaled by the fraction
+/// of the hemisphere above them that is not occluded.
+///
+/// All of the color computed by ambient occlusion is indirect.
+///
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct AmbientOcclusion {
+    /// Number of occlusion rays casted for each hit.
+    pub samples: usize,
+
+    /// Maximum distance at which a surface occludes another.
+    pub distance: f64,
+
+    /// Whether [Camera::record_ray_paths](crate::camera::Camera::record_ray_paths) records rays.
+    pub record_ray_paths: bool,
+}
+
+/// Path tracing, which adds the light bounced off diffuse surfaces to the direct lighting of the
+/// world.
+///
+/// Each hit spawns a single diffuse ray in a random direction, so renders are noisy unless many
+/// samples are taken for each pixel.
+///
+/// The indirect color of a path traced ray is the light bounced off the first diffuse surface it
+/// hits.
+///
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct PathTracer {
+    /// Maximum number of times a ray can bounce off any surface.
+    pub max_depth: u8,
+
+    /// Whether [Camera::record_ray_paths](crate::camera::Camera::record_ray_paths) records rays.
+    pub record_ray_paths: bool,
+}
+
+/// Spectral ray tracing, where each ray carries a single wavelength of light instead of a color.
+///
+/// Every ray casted from the camera samples a random wavelength of the visible spectrum, which is
+/// traced through the world like [Whitted] ray tracing does, and it's converted to a color by the
+/// [response](spectrum::sensor_response) of the camera sensor at the end. Surfaces and lights
+/// take part with their [spectrum](Material::spectrum) when they have one, or with a spectrum
+/// [upsampled](spectrum::upsample) from their color otherwise.
+///
+/// This makes it possible to render effects that depend on the wavelength of light, such as the
+/// rainbows of a prism made of a material with [dispersion](Material::dispersion), or materials
+/// that look the same under one light and different under another. A single wavelength per ray
+/// is very noisy, so images need many samples per pixel to converge, and a world without any of
+/// these effects converges to the same image that [Whitted] ray tracing renders.
+///
+/// # Examples
+///
+/// A glass prism that splits white light.
+///
+/// ```
+/// use std::sync::Arc;
+///
+/// use raytracer::{
+///     camera::{Camera, CameraBuilder},
+///     integrator::Spectral,
+///     material::Material,
+///     render::{AccumulationBuffer, RenderSettings},
+///     shape::{Cube, Shape, ShapeBuilder},
+///     transform::Transform,
+///     world::World,
+/// };
+///
+/// let camera = Camera::try_from(CameraBuilder {
+///     width: 16,
+///     height: 16,
+///     field_of_view: std::f64::consts::FRAC_PI_3,
+///     transform: Default::default(),
+/// }).unwrap();
+///
+/// let mut world = World::default();
+/// world.objects.push(Shape::Cube(Cube::from(ShapeBuilder {
+///     material: Material {
+///         transparency: 1.0,
+///         index_of_refraction: 1.5168,
+///         dispersion: 0.0042,
+///         ..Default::default()
+///     },
+///     transform: Transform::rotation_y(0.5),
+/// })));
+///
+/// let settings = RenderSettings {
+///     integrator: Arc::new(Spectral::default()),
+///     ..Default::default()
+/// };
+///
+/// let mut buffer = AccumulationBuffer::new(camera.width(), camera.height());
+/// camera.render_samples(&world, &settings, &mut buffer, 16);
+///
+/// let image = buffer.to_canvas();
+/// ```
+///
+#[derive(Clone, Debug, PartialEq)]
+pub struct Spectral {
+    /// Maximum number of times a ray can bounce off reflective or refractive surfaces.
+    pub recursion_depth: u8,
+
+    /// Spectrum by which the color of every light of the world is multiplied, such as the
+    /// spiky spectrum of a fluorescent lamp. By default lights only emit the spectrum upsampled
+    /// from their color.
+    pub illuminant: Option<Arc<Spectrum>>,
+
+    /// Whether [Camera::record_ray_paths](crate::camera::Camera::record_ray_paths) records rays.
+    pub record_ray_paths: bool,
+}
+
+/// Whitted ray tracing that follows the linear polarization of light through the reflections and
+/// refractions of transparent surfaces, to render a camera with a polarizing filter in front of it.
+///
+/// Light reflected off glass or water is partially polarized, and at
+/// [Brewster's angle](https://en.wikipedia.org/wiki/Brewster%27s_angle) it's polarized
+/// parallel to the surface, so a filter that only lets through the light polarized in the other
+/// direction removes the reflection and shows what's behind the surface instead. Surfaces that
+/// are both reflective and transparent split light with the exact Fresnel equations for each
+/// polarization, instead of the approximation used by [Whitted] ray tracing. Light sources and
+/// the shading of surfaces are unpolarized.
+///
+/// Without a filter, the image is the same as the one rendered by [Whitted] ray tracing, except
+/// for the more accurate reflections. The filter doesn't darken unpolarized light, as if the
+/// exposure of the camera was raised to make up for it.
+///
+/// # Examples
+///
+/// A camera with a filter that lets through light polarized vertically in the image, which removes
+/// the glare of a lake seen from the shore.
+///
+/// ```
+/// use std::{f64::consts::FRAC_PI_2, sync::Arc};
+///
+/// use raytracer::{
+///     camera::{Camera, CameraBuilder},
+///     integrator::Polarized,
+///     render::RenderSettings,
+///     world::World,
+/// };
+///
+/// let camera = Camera::try_from(CameraBuilder {
+///     width: 32,
+///     height: 32,
+///     field_of_view: std::f64::consts::FRAC_PI_3,
+///     transform: Default::default(),
+/// }).unwrap();
+///
+/// let settings = RenderSettings {
+///     integrator: Arc::new(Polarized::default().with_filter(&camera, FRAC_PI_2)),
+///     ..Default::default()
+/// };
+///
+/// let image = camera.render_with_settings(&World::default(), &settings);
+/// ```
+///
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Polarized {
+    recursion_depth: u8,
+    filter: Option<PolarizingFilter>,
+
+    /// Whether [Camera::record_ray_paths](crate::camera::Camera::record_ray_paths) records rays.
+    pub record_ray_paths: bool,
+}
+
+/// Linear polarizing filter in front of a camera.
+#[derive(Copy, Clone, Debug, PartialEq)]
+struct PolarizingFilter {
+    /// Direction of the horizontal axis of the image, in world space.
+    horizontal: Vector,
+
+    /// Angle of the transmission axis of the filter from the horizontal axis of the image.
+    angle: f64,
+}
+
+/// Sensitivity of a ray to the polarization of the light that it carries back to the camera, as
+/// the Stokes parameters of linear polarization measured from a reference direction perpendicular
+/// to the ray.
+///
+/// Rays are traced from the camera, so instead of the polarization of the light, it's the filter
+/// of the camera that is carried through the world, transformed by each surface in reverse. The
+/// light that reaches the camera along a ray is it's unpolarized radiance times the first
+/// parameter.
+///
+#[derive(Copy, Clone, Debug, PartialEq)]
+struct Polarization {
+    reference: Vector,
+    stokes: [f64; 3],
+}
+
+/// Mueller matrix of a surface for linear polarization, given by the fractions of light polarized
+/// perpendicular and parallel to the plane of incidence that go through it, and the term that
+/// mixes both polarizations.
+#[derive(Copy, Clone, Debug, PartialEq)]
+struct Mueller {
+    perpendicular: f64,
+    parallel: f64,
+    cross: f64,
+}
+
+/// Debug integrator that draws markers for the light sources of the world, and optionally the
+/// frustum of a camera, on top of the image rendered by another integrator, so misplaced lights
+/// can be seen right away instead of guessed from the shading.
+///
+/// Point lights are drawn as dots in their own color, and area lights as the outline of their
+/// rectangle. Dome lights surround the whole world, so they aren't drawn. A camera frustum is
+/// drawn as the edges of the pyramid that the camera sees, up to a given distance, which is useful
+/// to see where a camera is from a different viewpoint. Markers are drawn with a flat color, they
+/// don't cast shadows and they're hidden behind the shapes in front of them. Only rays casted from
+/// the camera see them.
+///
+/// # Examples
+///
+/// Looking at the frustum of the main camera of a scene from a camera above it.
+///
+/// ```
+/// use std::sync::Arc;
+///
+/// use raytracer::{
+///     camera::{Camera, CameraBuilder},
+///     integrator::{Gizmos, Whitted},
+///     render::RenderSettings,
+///     transform::Transform,
+///     tuple::{Point, Vector},
+///     world::World,
+/// };
+///
+/// let camera = Camera::try_from(CameraBuilder {
+///     width: 32,
+///     height: 32,
+///     field_of_view: std::f64::consts::FRAC_PI_3,
+///     transform: Default::default(),
+/// }).unwrap();
+///
+/// let debug_camera = Camera::try_from(CameraBuilder {
+///     width: 32,
+///     height: 32,
+///     field_of_view: std::f64::consts::FRAC_PI_3,
+///     transform: Transform::view(
+///         Point::new(0.0, 10.0, 0.0),
+///         Point::new(0.0, 0.0, 0.0),
+///         Vector::new(0.0, 0.0, 1.0),
+///     )
+///     .unwrap(),
+/// }).unwrap();
+///
+/// let gizmos = Gizmos::new(Arc::new(Whitted::default()), 0.1).with_frustum(&camera, 5.0);
+///
+/// let settings = RenderSettings {
+///     integrator: Arc::new(gizmos),
+///     ..Default::default()
+/// };
+///
+/// let image = debug_camera.render_with_settings(&World::default(), &settings);
+/// ```
+///
+#[derive(Clone, Debug)]
+pub struct Gizmos {
+    integrator: Arc<dyn Integrator>,
+    size: f64,
+    frustum: Vec<GizmoSegment>,
+}
+
+/// Line drawn by [Gizmos], as a capsule of the given radius around the segment from `start` to
+/// `end`. A segment that starts and ends at the same point is a dot.
+#[derive(Copy, Clone, Debug, PartialEq)]
+struct GizmoSegment {
+    start: Point,
+    end: Point,
+    radius: f64,
+    color: Color,
+}
+
+impl Default for Whitted {
+    fn default() -> Self {
+        Self {
+            recursion_depth: RECURSION_DEPTH,
+            record_ray_paths: false,
+        }
+    }
+}
+
+impl Default for Spectral {
+    fn default() -> Self {
+        Self {
+            recursion_depth: RECURSION_DEPTH,
+            illuminant: None,
+            record_ray_paths: false,
+        }
+    }
+}
+
+impl Default for Polarized {
+    fn default() -> Self {
+        Self::new(RECURSION_DEPTH)
+    }
+}
+
+impl Default for AmbientOcclusion {
+    fn default() -> Self {
+        Self {
+            samples: 16,
+            distance: 1.0,
+            record_ray_paths: false,
+        }
+    }
+}
+
+impl Default for PathTracer {
+    fn default() -> Self {
+        Self {
+            max_depth: RECURSION_DEPTH,
+            record_ray_paths: false,
+        }
+    }
+}
+
+impl Integrator for Whitted {
+    fn color_at(&self, world: &World, ray: &Ray) -> Color {
+        self.trace(world, ray, self.recursion_depth)
+    }
+
+    fn light_colors_at(&self, world: &World, ray: &Ray) -> LightColors {
+        self.trace(world, ray, self.recursion_depth)
+    }
+}
+
+impl Integrator for AmbientOcclusion {
+    fn color_at(&self, world: &World, ray: &Ray) -> Color {
+        let mut xs = world.intersect(ray);
+        let hit = Intersection::hit(&mut xs);
+
+        if self.record_ray_paths {
+            render::record_segment(ray, hit.map(|hit| hit.t), 0);
+        }
+
+        let Some(hit) = hit else {
+            return color::consts::BLACK;
+        };
+
+        let mut comps = hit.prepare_computation_with_offset(ray, xs, world.units.surface_offset());
+        apply_dirt(world, &mut comps);
+
+        let surface_color = comps
+            .material
+            .pattern
+            .color_at_object(comps.intersection.object, comps.over_point);
+
+        if self.samples == 0 {
+            return surface_color;
+        }
+
+        surface_color * unoccluded_fraction(world, &comps, self.samples, self.distance)
+    }
+
+    fn direct_color_at(&self, _world: &World, _ray: &Ray) -> Color {
+        color::consts::BLACK
+    }
+
+    fn indirect_color_at(&self, world: &World, ray: &Ray) -> Color {
+        self.color_at(world, ray)
+    }
+}
+
+impl Integrator for PathTracer {
+    fn color_at(&self, world: &World, ray: &Ray) -> Color {
+        self.trace(world, ray, self.max_depth, Lighting::All)
+    }
+
+    fn direct_color_at(&self, world: &World, ray: &Ray) -> Color {
+        self.trace(world, ray, self.max_depth, Lighting::Direct)
+    }
+
+    fn indirect_color_at(&self, world: &World, ray: &Ray) -> Color {
+        self.trace(world, ray, self.max_depth, Lighting::Indirect)
+    }
+
+    fn light_colors_at(&self, world: &World, ray: &Ray) -> LightColors {
+        self.trace(world, ray, self.max_depth, Lighting::All)
+    }
+}
+
+impl Integrator for Spectral {
+    fn color_at(&self, world: &World, ray: &Ray) -> Color {
+        let wavelength =
+            rand::thread_rng().gen_range(spectrum::MIN_WAVELENGTH..spectrum::MAX_WAVELENGTH);
+
+        spectrum::sensor_response(wavelength) * self.radiance_at(world, ray, wavelength)
+    }
+
+    fn light_colors_at(&self, world: &World, ray: &Ray) -> LightColors {
+        let wavelength =
+            rand::thread_rng().gen_range(spectrum::MIN_WAVELENGTH..spectrum::MAX_WAVELENGTH);
+
+        let response = spectrum::sensor_response(wavelength);
+        let radiance: LightColors = self.trace(world, ray, wavelength, self.recursion_depth);
+
+        LightColors {
+            lights: radiance
+                .lights
+                .into_iter()
+                .map(|color| response * color.red)
+                .collect(),
+            unlit: response * radiance.unlit.red,
+        }
+    }
+}
+
+impl Integrator for Polarized {
+    fn color_at(&self, world: &World, ray: &Ray) -> Color {
+        self.trace(
+            world,
+            ray,
+            self.camera_polarization(ray),
+            self.recursion_depth,
+        )
+    }
+
+    fn light_colors_at(&self, world: &World, ray: &Ray) -> LightColors {
+        self.trace(
+            world,
+            ray,
+            self.camera_polarization(ray),
+            self.recursion_depth,
+        )
+    }
+}
+
+impl Integrator for Gizmos {
+    fn color_at(&self, world: &World, ray: &Ray) -> Color {
+        self.gizmo_at(world, ray)
+            .unwrap_or_else(|| self.integrator.color_at(world, ray))
+    }
+
+    fn direct_color_at(&self, world: &World, ray: &Ray) -> Color {
+        self.gizmo_at(world, ray)
+            .unwrap_or_else(|| self.integrator.direct_color_at(world, ray))
+    }
+
+    fn indirect_color_at(&self, world: &World, ray: &Ray) -> Color {
+        match self.gizmo_at(world, ray) {
+            Some(_) => color::consts::BLACK,
+            None => self.integrator.indirect_color_at(world, ray),
+        }
+    }
+
+    fn light_colors_at(&self, world: &World, ray: &Ray) -> LightColors {
+        match self.gizmo_at(world, ray) {
+            Some(color) => LightColors {
+                lights: vec![color::consts::BLACK; world.lights.len()],
+                unlit: color,
+            },
+            None => self.integrator.light_colors_at(world, ray),
+        }
+    }
+}
+
+impl LightColors {
+    /// Sum of the colors of every light and the unlit color, which is the whole color seen by the
+    /// ray.
+    pub fn total(&self) -> Color {
+        self.lights
+            .iter()
+            .fold(self.unlit, |acc, &color| acc + color)
+    }
+}
+
+impl Add for LightColors {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        Self {
+            lights: self
+                .lights
+                .into_iter()
+                .zip(rhs.lights)
+                .map(|(lhs, rhs)| lhs + rhs)
+                .collect(),
+            unlit: self.unlit + rhs.unlit,
+        }
+    }
+}
+
+impl Mul<f64> for LightColors {
+    type Output = Self;
+
+    fn mul(self, rhs: f64) -> Self::Output {
+        self * gray(rhs)
+    }
+}
+
+impl Mul<Color> for LightColors {
+    type Output = Self;
+
+    fn mul(self, rhs: Color) -> Self::Output {
+        Self {
+            lights: self.lights.into_iter().map(|color| color * rhs).collect(),
+            unlit: self.unlit * rhs,
+        }
+    }
+}
+
+impl Radiance for Color {
+    fn black(_lights: usize) -> Self {
+        color::consts::BLACK
+    }
+
+    fn from_light(_lights: usize, _index: usize, color: Color) -> Self {
+        color
+    }
+}
+
+impl Radiance for LightColors {
+    fn black(lights: usize) -> Self {
+        Self {
+            lights: vec![color::consts::BLACK; lights],
+            unlit: color::consts::BLACK,
+        }
+    }
+
+    fn from_light(lights: usize, index: usize, color: Color) -> Self {
+        let mut colors = Self::black(lights);
+        colors.lights[index] = color;
+        colors
+    }
+}
+
+/// Parts of the lighting computed at the first hit of a path.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Lighting {
+    All,
+    Direct,
+    Indirect,
+}
+
+impl Whitted {
+    fn trace<R: Radiance>(&self, world: &World, ray: &Ray, recursion_depth: u8) -> R {
+        let mut xs = world.intersect(ray);
+        let hit = Intersection::hit(&mut xs);
+
+        if self.record_ray_paths {
+            render::record_segment(
+                ray,
+                hit.map(|hit| hit.t),
+                self.recursion_depth.saturating_sub(recursion_depth),
+            );
+        }
+
+        let lights = world.lights.len();
+
+        hit.map_or_else(
+            || R::black(lights),
+            |hit| {
+                let offset = world.units.surface_offset();
+                let mut comps = hit.prepare_computation_with_offset(ray, xs, offset);
+                apply_dirt(world, &mut comps);
+
+                self.shade_hit(world, comps, recursion_depth)
+            },
+        )
+    }
+
+    fn shade_hit<R: Radiance>(&self, world: &World, comps: Computation, recursion_depth: u8) -> R {
+        let lights = world.lights.len();
+
+        if let Some(portal) = &comps.material.portal {
+            if recursion_depth == 0 {
+                return R::black(lights);
+            }
+
+            return self.trace(
+                world,
+                &portal_ray(world, &comps, portal),
+                recursion_depth - 1,
+            );
+        }
+
+        let object = comps.intersection.object;
+        let material = &comps.material;
+
+        let surface_color =
+            world
+                .lights
+                .iter()
+                .enumerate()
+                .fold(R::black(lights), |acc, (i, light)| {
+                    let light_intensity =
+                        light.intensity_at(world, comps.over_point, comps.normalv);
+
+                    acc + R::from_light(
+                        lights,
+                        i,
+                        material.lighting(
+                            object,
+                            light,
+                            comps.over_point,
+                            comps.eyev,
+                            comps.normalv,
+                            light_intensity,
+                        ),
+                    )
+                });
+
+        if lights == 0 {
+            return surface_color;
+        }
+
+        let reflected_color: R = self.reflected_color(world, &comps, recursion_depth);
+        let refracted_color = self.refracted_color(world, &comps, recursion_depth);
+
+        // Reflections and refractions are added once for each light of the world, like the
+        // shading of the book does.
+        surface_color + fresnel_blend(&comps, reflected_color, refracted_color) * lights as f64
+    }
+
+    fn reflected_color<R: Radiance>(
+        &self,
+        world: &World,
+        comps: &Computation<'_>,
+        recursion_depth: u8,
+    ) -> R {
+        let lights = world.lights.len();
+
+        if recursion_depth == 0 {
+            return R::black(lights);
+        }
+
+        reflection(comps).map_or_else(
+            || R::black(lights),
+            |(ray, reflectivity)| self.trace::<R>(world, &ray, recursion_depth - 1) * reflectivity,
+        )
+    }
+
+    fn refracted_color<R: Radiance>(
+        &self,
+        world: &World,
+        comps: &Computation<'_>,
+        recursion_depth: u8,
+    ) -> R {
+        let lights = world.lights.len();
+
+        if recursion_depth == 0 {
+            return R::black(lights);
+        }
+
+        refraction(comps).map_or_else(
+            || R::black(lights),
+            |(ray, transparency)| self.trace::<R>(world, &ray, recursion_depth - 1) * transparency,
+        )
+    }
+}
+
+impl PathTracer {
+    fn trace<R: Radiance>(&self, world: &World, ray: &Ray, depth: u8, lighting: Lighting) -> R {
+        let lights = world.lights.len();
+
+        let mut xs = world.intersect(ray);
+        let hit = Intersection::hit(&mut xs);
+
+        if self.record_ray_paths {
+            render::record_segment(
+                ray,
+                hit.map(|hit| hit.t),
+                self.max_depth.saturating_sub(depth),
+            );
+        }
+
+        let Some(hit) = hit else {
+            return R::black(lights);
+        };
+
+        let mut comps = hit.prepare_computation_with_offset(ray, xs, world.units.surface_offset());
+        apply_dirt(world, &mut comps);
+
+        let object = comps.intersection.object;
+        let material = &comps.material;
+
+        if let Some(portal) = &material.portal {
+            if depth == 0 {
+                return R::black(lights);
+            }
+
+            return self.trace(
+                world,
+                &portal_ray(world, &comps, portal),
+                depth - 1,
+                lighting,
+            );
+        }
+
+        // Only the first hit of a path is split, every ray spawned from it computes all of it's
+        // lighting.
+        let direct = lighting != Lighting::Indirect;
+        let indirect = lighting != Lighting::Direct;
+
+        let direct_color = if direct {
+            world
+                .lights
+                .iter()
+                .enumerate()
+                .fold(R::black(lights), |acc, (i, light)| {
+                    let light_intensity =
+                        light.intensity_at(world, comps.over_point, comps.normalv);
+
+                    acc + R::from_light(
+                        lights,
+                        i,
+                        material.lighting(
+                            object,
+                            light,
+                            comps.over_point,
+                            comps.eyev,
+                            comps.normalv,
+                            light_intensity,
+                        ),
+                    )
+                })
+        } else {
+            R::black(lights)
+        };
+
+        if depth == 0 {
+            return direct_color;
+        }
+
+        let indirect_color = if !indirect || float::approx(material.diffuse, 0.0) {
+            R::black(lights)
+        } else {
+            let bounce_ray = Ray {
+                origin: comps.over_point,
+                direction: cosine_weighted_direction(comps.normalv),
+            };
+
+            // Diffuse bounces are counted as reflections, as they are reflections off rough
+            // surfaces.
+            render::record(|stats| stats.reflection_rays += 1);
+
+            let surface_color = material.pattern.color_at_object(object, comps.over_point);
+            self.trace::<R>(world, &bounce_ray, depth - 1, Lighting::All)
+                * surface_color
+                * material.diffuse
+        };
+
+        if !direct {
+            return indirect_color;
+        }
+
+        let reflected_color = reflection(&comps).map_or_else(
+            || R::black(lights),
+            |(ray, weight)| self.trace::<R>(world, &ray, depth - 1, Lighting::All) * weight,
+        );
+        let refracted_color = refraction(&comps).map_or_else(
+            || R::black(lights),
+            |(ray, weight)| self.trace::<R>(world, &ray, depth - 1, Lighting::All) * weight,
+        );
+
+        direct_color + indirect_color + fresnel_blend(&comps, reflected_color, refracted_color)
+    }
+}
+
+impl Spectral {
+    /// Radiance of the given wavelength, in nanometers, seen by a ray casted into the world.
+    fn radiance_at(&self, world: &World, ray: &Ray, wavelength: f64) -> f64 {
+        self.trace::<Color>(world, ray, wavelength, self.recursion_depth)
+            .red
+    }
+
+    /// Traces a ray of a single wavelength. The returned color is a gray with the radiance of that
+    /// wavelength, so the shading routines of the other integrators can be reused as they are.
+    fn trace<R: Radiance>(
+        &self,
+        world: &World,
+        ray: &Ray,
+        wavelength: f64,
+        recursion_depth: u8,
+    ) -> R {
+        let mut xs = world.intersect(ray);
+        let hit = Intersection::hit(&mut xs);
+
+        if self.record_ray_paths {
+            render::record_segment(
+                ray,
+                hit.map(|hit| hit.t),
+                self.recursion_depth.saturating_sub(recursion_depth),
+            );
+        }
+
+        let lights = world.lights.len();
+
+        let Some(hit) = hit else {
+            return R::black(lights);
+        };
+
+        let offset = world.units.surface_offset();
+        let mut comps = hit.prepare_computation_at_wavelength(ray, xs, offset, wavelength);
+        apply_dirt(world, &mut comps);
+
+        if let Some(portal) = &comps.material.portal {
+            if recursion_depth == 0 {
+                return R::black(lights);
+            }
+
+            return self.trace(
+                world,
+                &portal_ray(world, &comps, portal),
+                wavelength,
+                recursion_depth - 1,
+            );
+        }
+
+        let object = comps.intersection.object;
+        let reflectance = match &comps.material.spectrum {
+            Some(spectrum) => spectrum.value_at(wavelength),
+            None => spectrum::upsample(
+                comps
+                    .material
+                    .pattern
+                    .color_at_object(object, comps.over_point),
+                wavelength,
+            ),
+        };
+
+        let material = Material {
+            pattern: Pattern3D::Solid(gray(reflectance)),
+            ..comps.material.as_ref().clone()
+        };
+
+        let illuminant = self
+            .illuminant
+            .as_ref()
+            .map_or(1.0, |illuminant| illuminant.value_at(wavelength));
+
+        let surface_color =
+            world
+                .lights
+                .iter()
+                .enumerate()
+                .fold(R::black(lights), |acc, (i, light)| {
+                    let emission =
+                        spectrum::upsample(light.effective_color(), wavelength) * illuminant;
+                    let light_intensity =
+                        light.intensity_at(world, comps.over_point, comps.normalv);
+
+                    acc + R::from_light(
+                        lights,
+                        i,
+                        material.lighting(
+                            object,
+                            &light.with_color(gray(emission)),
+                            comps.over_point,
+                            comps.eyev,
+                            comps.normalv,
+                            light_intensity,
+                        ),
+                    )
+                });
+
+        if lights == 0 || recursion_depth == 0 {
+            return surface_color;
+        }
+
+        let reflected_color = reflection(&comps).map_or_else(
+            || R::black(lights),
+            |(ray, weight)| self.trace::<R>(world, &ray, wavelength, recursion_depth - 1) * weight,
+        );
+        let refracted_color = refraction(&comps).map_or_else(
+            || R::black(lights),
+            |(ray, weight)| self.trace::<R>(world, &ray, wavelength, recursion_depth - 1) * weight,
+        );
+
+        // Like in [Whitted] ray tracing, reflections and refractions are added once for each
+        // light of the world, so both converge to the same image.
+        surface_color + fresnel_blend(&comps, reflected_color, refracted_color) * lights as f64
+    }
+}
+
+impl Polarized {
+    /// Constructs the integrator without a filter in front of the camera.
+    pub fn new(recursion_depth: u8) -> Self {
+        Self {
+            recursion_depth,
+            filter: None,
+            record_ray_paths: false,
+        }
+    }
+
+    /// Puts a linear polarizing filter in front of `camera`, which only lets through the light
+    /// polarized along it's transmission axis. The axis is at `angle` radians from the horizontal
+    /// axis of the image, counterclockwise, so an angle of `0.0` lets through the light polarized
+    /// horizontally and an angle of `FRAC_PI_2` the light polarized vertically.
+    pub fn with_filter(self, camera: &Camera, angle: f64) -> Self {
+        let (width, height) = (camera.width() as f64, camera.height() as f64);
+
+        let left = camera.ray_for_position(0.0, height / 2.0).direction;
+        let right = camera.ray_for_position(width, height / 2.0).direction;
+
+        Self {
+            filter: (right - left)
+                .normalize()
+                .ok()
+                .map(|horizontal| PolarizingFilter { horizontal, angle }),
+            ..self
+        }
+    }
+
+    /// Polarization that a ray casted from the camera is sensitive to, through the filter if
+    /// there is one.
+    fn camera_polarization(&self, ray: &Ray) -> Polarization {
+        self.filter
+            .and_then(|filter| filter.polarization_for(ray.direction))
+            .unwrap_or_else(|| Polarization::unpolarized(ray.direction))
+    }
+
+    fn trace<R: Radiance>(
+        &self,
+        world: &World,
+        ray: &Ray,
+        polarization: Polarization,
+        recursion_depth: u8,
+    ) -> R {
+        let lights = world.lights.len();
+
+        // Rays that can't carry any light to the camera through the filter aren't traced at all.
+        if polarization.stokes[0] <= float::EPSILON {
+            return R::black(lights);
+        }
+
+        let mut xs = world.intersect(ray);
+        let hit = Intersection::hit(&mut xs);
+
+        if self.record_ray_paths {
+            render::record_segment(
+                ray,
+                hit.map(|hit| hit.t),
+                self.recursion_depth.saturating_sub(recursion_depth),
+            );
+        }
+
+        let Some(hit) = hit else {
+            return R::black(lights);
+        };
+
+        let mut comps = hit.prepare_computation_with_offset(ray, xs, world.units.surface_offset());
+        apply_dirt(world, &mut comps);
+
+        let material = &comps.material;
+
+        if let Some(portal) = &material.portal {
+            if recursion_depth == 0 {
+                return R::black(lights);
+            }
+
+            let ray = portal_ray(world, &comps, portal);
+            let polarization = polarization.realigned(ray.direction);
+
+            return self.trace(world, &ray, polarization, recursion_depth - 1);
+        }
+
+        let object = comps.intersection.object;
+
+        let surface_color =
+            world
+                .lights
+                .iter()
+                .enumerate()
+                .fold(R::black(lights), |acc, (i, light)| {
+                    let light_intensity =
+                        light.intensity_at(world, comps.over_point, comps.normalv);
+
+                    acc + R::from_light(
+                        lights,
+                        i,
+                        material.lighting(
+                            object,
+                            light,
+                            comps.over_point,
+                            comps.eyev,
+                            comps.normalv,
+                            light_intensity,
+                        ),
+                    )
+                })
+                * polarization.stokes[0];
+
+        if recursion_depth == 0 {
+            return surface_color;
+        }
+
+        // Only surfaces that are both reflective and transparent split light by the Fresnel
+        // equations, like the other integrators do.
+        let (reflected, refracted) = if (material.reflectivity * material.transparency) > 0.0 {
+            Mueller::fresnel(&comps)
+        } else {
+            (Mueller::IDENTITY, Mueller::IDENTITY)
+        };
+
+        let reflected_color = reflection(&comps).map_or_else(
+            || R::black(lights),
+            |(ray, weight)| {
+                let polarization = polarization.through(&comps, ray.direction, reflected);
+                self.trace::<R>(world, &ray, polarization, recursion_depth - 1) * weight
+            },
+        );
+        let refracted_color = refraction(&comps).map_or_else(
+            || R::black(lights),
+            |(ray, weight)| {
+                let polarization = polarization.through(&comps, ray.direction, refracted);
+                self.trace::<R>(world, &ray, polarization, recursion_depth - 1) * weight
+            },
+        );
+
+        surface_color + reflected_color + refracted_color
+    }
+}
+
+impl PolarizingFilter {
+    /// Polarization that a ray casted from the camera along `direction` is sensitive to, or
+    /// `None` if the ray is parallel to the horizontal axis of the image.
+    fn polarization_for(&self, direction: Vector) -> Option<Polarization> {
+        let reference = perpendicular_part(self.horizontal, direction)?;
+        let angle = 2.0 * self.angle;
+
+        Some(Polarization {
+            reference,
+            stokes: [1.0, angle.cos(), angle.sin()],
+        })
+    }
+}
+
+impl Polarization {
+    /// Sensitivity of a ray without a filter, which is the same for light polarized in any
+    /// direction.
+    fn unpolarized(direction: Vector) -> Self {
+        Self {
+            reference: any_perpendicular(direction),
+            stokes: [1.0, 0.0, 0.0],
+        }
+    }
+
+    /// Sensitivity of the ray that continues along `direction` from the surface of a hit, after
+    /// going through the surface with the given Mueller matrix.
+    fn through(self, comps: &Computation<'_>, direction: Vector, mueller: Mueller) -> Self {
+        let incoming = -comps.eyev;
+
+        // Both rays are in the plane of incidence, so the direction perpendicular to that plane
+        // is a reference for both of them. At normal incidence every direction is.
+        let Ok(perpendicular) = incoming.cross(comps.normalv).normalize() else {
+            let realigned = self.realigned(direction);
+            return Self {
+                stokes: mueller.apply(realigned.stokes),
+                ..realigned
+            };
+        };
+
+        let [intensity, q, u] = self.stokes;
+
+        let cos = self.reference.dot(perpendicular);
+        let sin = self.reference.cross(perpendicular).dot(incoming);
+        let (sin, cos) = (2.0 * sin * cos, cos.powi(2) - sin.powi(2));
+
+        Self {
+            reference: perpendicular,
+            stokes: mueller.apply([intensity, q * cos + u * sin, u * cos - q * sin]),
+        }
+    }
+
+    /// Same sensitivity for a ray that continues along `direction` without interacting with a
+    /// surface, such as a ray that goes through a portal.
+    fn realigned(self, direction: Vector) -> Self {
+        Self {
+            reference: perpendicular_part(self.reference, direction)
+                .unwrap_or_else(|| any_perpendicular(direction)),
+            ..self
+        }
+    }
+}
+
+impl Mueller {
+    /// Matrix of a surface that lets every polarization through unchanged.
+    const IDENTITY: Self = Self {
+        perpendicular: 1.0,
+        parallel: 1.0,
+        cross: 1.0,
+    };
+
+    /// Matrices of the light reflected off and refracted through the surface of a hit, as given
+    /// by the Fresnel equations.
+    fn fresnel(comps: &Computation<'_>) -> (Self, Self) {
+        let (n1, n2) = (comps.n1, comps.n2);
+        let cos_i = comps.eyev.dot(comps.normalv);
+        let sin2_t = (n1 / n2).powi(2) * (1.0 - cos_i.powi(2));
+
+        // Under total internal reflection all of the light is reflected.
+        if sin2_t > 1.0 {
+            return (Self::IDENTITY, Self::IDENTITY);
+        }
+
+        let cos_t = (1.0 - sin2_t).sqrt();
+
+        let rs = (n1 * cos_i - n2 * cos_t) / (n1 * cos_i + n2 * cos_t);
+        let rp = (n2 * cos_i - n1 * cos_t) / (n2 * cos_i + n1 * cos_t);
+        let (ts, tp) = (1.0 - rs.powi(2), 1.0 - rp.powi(2));
+
+        (
+            Self {
+                perpendicular: rs.powi(2),
+                parallel: rp.powi(2),
+                cross: rs * rp,
+            },
+            Self {
+                perpendicular: ts,
+                parallel: tp,
+                cross: (ts * tp).sqrt(),
+            },
+        )
+    }
+
+    /// Applies the matrix to Stokes parameters measured from the direction perpendicular to the
+    /// plane of incidence.
+    fn apply(&self, [intensity, q, u]: [f64; 3]) -> [f64; 3] {
+        let sum = (self.perpendicular + self.parallel) / 2.0;
+        let difference = (self.perpendicular - self.parallel) / 2.0;
+
+        [
+            sum * intensity + difference * q,
+            difference * intensity + sum * q,
+            self.cross * u,
+        ]
+    }
+}
+
+impl Gizmos {
+    /// Draws markers for the lights of the world on top of the image rendered by `integrator`.
+    /// Light dots have a radius of `size`, and lines are a quarter of that.
+    pub fn new(integrator: Arc<dyn Integrator>, size: f64) -> Self {
+        Self {
+            integrator,
+            size,
+            frustum: vec![],
+        }
+    }
+
+    /// Draws the frustum of `camera` too, with the edges that leave the camera `length` units
+    /// long.
+    pub fn with_frustum(mut self, camera: &Camera, length: f64) -> Self {
+        let (width, height) = (camera.width() as f64, camera.height() as f64);
+
+        let corners = [(0.0, 0.0), (width, 0.0), (width, height), (0.0, height)].map(|(x, y)| {
+            let ray = camera.ray_for_position(x, y);
+            ray.origin + ray.direction * length
+        });
+
+        let origin = camera.ray_for_position(0.0, 0.0).origin;
+
+        for (i, corner) in corners.iter().enumerate() {
+            let next = corners[(i + 1) % corners.len()];
+
+            for (start, end) in [(origin, *corner), (*corner, next)] {
+                self.frustum.push(GizmoSegment {
+                    start,
+                    end,
+                    radius: self.size / 4.0,
+                    color: FRUSTUM_COLOR,
+                });
+            }
+        }
+
+        self
+    }
+
+    /// Color of the nearest marker seen by the ray, unless there's a shape in front of it.
+    fn gizmo_at(&self, world: &World, ray: &Ray) -> Option<Color> {
+        let lights = world.lights.iter().flat_map(|light| {
+            let color = light.effective_color();
+            let outline = light.outline();
+
+            let segments: Vec<_> = if outline.len() == 1 {
+                vec![(outline[0], outline[0], self.size)]
+            } else {
+                (0..outline.len())
+                    .map(|i| {
+                        let next = outline[(i + 1) % outline.len()];
+                        (outline[i], next, self.size / 4.0)
+                    })
+                    .collect()
+            };
+
+            segments
+                .into_iter()
+                .map(move |(start, end, radius)| GizmoSegment {
+                    start,
+                    end,
+                    radius,
+                    color,
+                })
+        });
+
+        let (t, color) = lights
+            .chain(self.frustum.iter().copied())
+            .filter_map(|segment| segment.hit(ray).map(|t| (t, segment.color)))
+            .min_by(|(t0, _), (t1, _)| t0.total_cmp(t1))?;
+
+        let mut xs = world.intersect(ray);
+        let hidden = Intersection::hit(&mut xs).is_some_and(|hit| hit.t < t);
+
+        (!hidden).then_some(color)
+    }
+}
+
+impl GizmoSegment {
+    /// Distance along the ray to the point where it passes closest to the segment, if it passes
+    /// within the radius of the segment.
+    fn hit(&self, ray: &Ray) -> Option<f64> {
+        let axis = self.end - self.start;
+        let offset = ray.origin - self.start;
+
+        let a = ray.direction.dot(ray.direction);
+        let b = ray.direction.dot(axis);
+        let c = axis.dot(axis);
+        let d = ray.direction.dot(offset);
+        let e = axis.dot(offset);
+
+        // The position along the segment where the ray passes closest to it, which is the start
+        // of the segment for dots, and anywhere along it for rays parallel to it.
+        let denominator = a * c - b * b;
+        let s = if float::approx(c, 0.0) {
+            0.0
+        } else if float::approx(denominator, 0.0) {
+            (e / c).clamp(0.0, 1.0)
+        } else {
+            ((a * e - b * d) / denominator).clamp(0.0, 1.0)
+        };
+
+        let t = (b * s - d) / a;
+        let closest = ray.position(t) - (self.start + axis * s);
+
+        (t > 0.0 && closest.magnitude() <= self.radius).then_some(t)
+    }
+}
+
+/// Ray reflected off the hit and the fraction of the light it carries, if the surface is
+/// reflective.
+fn reflection(comps: &Computation<'_>) -> Option<(Ray, f64)> {
+    let reflectiveness = comps.material.reflectivity;
+
+    if float::approx(reflectiveness, 0.0) {
+        return None;
+    }
+
+    render::record(|stats| stats.reflection_rays += 1);
+
+    let reflection_ray = Ray {
+        origin: comps.over_point,
+        direction: comps.reflectv,
+    };
+
+    Some((reflection_ray, reflectiveness))
+}
+
+/// Ray refracted through the hit and the fraction of the light it carries, if the surface is
+/// transparent and there is no total internal reflection.
+fn refraction(comps: &Computation<'_>) -> Option<(Ray, f64)> {
+    let transparency = comps.material.transparency;
+
+    // Snell's Law: n1 * sin(oi) = n2 * sin(ot)
+    let n_ratio = comps.n1 / comps.n2;
+    let cos_i = comps.eyev.dot(comps.normalv);
+    let sin2_t = n_ratio.powi(2) * (1.0 - cos_i.powi(2));
+
+    let is_total_internal_refraction = sin2_t > 1.0;
+
+    if float::approx(transparency, 0.0) || is_total_internal_refraction {
+        return None;
+    }
+
+    let cos_t = (1.0 - sin2_t).sqrt();
+    let direction = comps.normalv * (n_ratio * cos_i - cos_t) - comps.eyev * n_ratio;
+
+    render::record(|stats| stats.refraction_rays += 1);
+
+    let refraction_ray = Ray {
+        origin: comps.under_point,
+        direction,
+    };
+
+    Some((refraction_ray, transparency))
+}
+
+/// Ray that continues out of the paired portal after hitting a portal, or straight through the
+/// portal when it's hit from behind.
+fn portal_ray(world: &World, comps: &Computation<'_>, portal: &Portal) -> Ray {
+    render::record(|stats| stats.portal_rays += 1);
+
+    if comps.inside {
+        return Ray {
+            origin: comps.under_point,
+            direction: -comps.eyev,
+        };
+    }
+
+    let origin = portal.transform * comps.point;
+    let direction = portal.transform * -comps.eyev;
+
+    // The ray starts slightly ahead of the paired portal, so it doesn't hit it's surface again.
+    // The transform is invertible, so it never turns the direction into a null vector.
+    #[allow(clippy::unwrap_used)]
+    let origin = intersection::offset_point(
+        origin,
+        direction.normalize().unwrap(),
+        world.units.surface_offset(),
+    );
+
+    Ray { origin, direction }
+}
+
+/// Combines the reflected and refracted colors of a hit, using the Fresnel effect for surfaces
+/// that are both reflective and transparent.
+fn fresnel_blend<R: Radiance>(
+    comps: &Computation<'_>,
+    reflected_color: R,
+    refracted_color: R,
+) -> R {
+    let material = &comps.material;
+
+    if (material.reflectivity * material.transparency) > 0.0 {
+        let reflectance = comps.schlick();
+        reflected_color * reflectance + refracted_color * (1.0 - reflectance)
+    } else {
+        reflected_color + refracted_color
+    }
+}
+
+/// Part of `vector` that is perpendicular to `direction`, normalized, or `None` if both are
+/// parallel.
+fn perpendicular_part(vector: Vector, direction: Vector) -> Option<Vector> {
+    let direction = direction.normalize().ok()?;
+
+    (vector - direction * vector.dot(direction))
+        .normalize()
+        .ok()
+}
+
+/// Some direction perpendicular to `direction`.
+fn any_perpendicular(direction: Vector) -> Vector {
+    [Vector::new(1.0, 0.0, 0.0), Vector::new(0.0, 1.0, 0.0)]
+        .into_iter()
+        .find_map(|axis| perpendicular_part(axis, direction))
+        .unwrap_or(Vector::new(1.0, 0.0, 0.0))
+}
+
+/// Fraction of the hemisphere above the point of a computation that isn't occluded by any surface
+/// closer than `distance`, sampled with `samples` rays.
+fn unoccluded_fraction(
+    world: &World,
+    comps: &Computation<'_>,
+    samples: usize,
+    distance: f64,
+) -> f64 {
+    let unoccluded = (0..samples)
+        .filter(|_| {
+            let direction = cosine_weighted_direction(comps.normalv);
+            let target = comps.over_point + direction * distance;
+
+            !world.is_shadowed(target, comps.over_point)
+        })
+        .count();
+
+    unoccluded as f64 / samples as f64
+}
+
+/// Mixes the color of the material of a computation with the color of it's dirt, by the fraction of
+/// the hemisphere above the point that is occluded.
+fn apply_dirt(world: &World, comps: &mut Computation<'_>) {
+    let Some(dirt) = comps.material.dirt else {
+        return;
+    };
+
+    if dirt.samples == 0 {
+        return;
+    }
+
+    let surface_color = comps
+        .material
+        .pattern
+        .color_at_object(comps.intersection.object, comps.over_point);
+    let occluded = 1.0 - unoccluded_fraction(world, comps, dirt.samples, dirt.distance);
+
+    comps.material.to_mut().pattern =
+        Pattern3D::Solid(surface_color + (dirt.color - surface_color) * occluded);
+}
+
+/// Gray color with the same value in every channel.
+fn gray(value: f64) -> Color {
+    Color {
+        red: value,
+        green: value,
+        blue: value,
+    }
+}
+
+/// Random direction in the hemisphere around a normal, more likely to be close to the normal than
+/// to the surface.
+fn cosine_weighted_direction(normal: Vector) -> Vector {
+    let mut rng = rand::thread_rng();
+    let (r1, r2): (f64, f64) = (rng.gen(), rng.gen());
+
+    let helper = if normal.0.x.abs() > 0.9 {
+        Vector::new(0.0, 1.0, 0.0)
+    } else {
+        Vector::new(1.0, 0.0, 0.0)
+    };
+
+    // The normal is never parallel to the helper axis, so their cross product is never zero.
+    let tangent = helper.cross(normal).normalize().unwrap();
+    let bitangent = normal.cross(tangent);
+
+    let phi = 2.0 * PI * r1;
+    let radius = r2.sqrt();
+
+    tangent * (radius * phi.cos()) + bitangent * (radius * phi.sin()) + normal * (1.0 - r2).sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::{FRAC_PI_2, FRAC_PI_4};
+
+    use crate::{
+        assert_approx,
+        camera::CameraBuilder,
+        light::{
+            AreaLight, AreaLightBuilder, DomeLight, DomeLightBuilder, DomeSource, Light, PointLight,
+        },
+        material::{Dirt, Material},
+        shape::{Cube, Plane, Shape, ShapeBuilder, Sphere},
+        transform::Transform,
+        tuple::Point,
+        world::test_world,
+    };
+
+    use super::*;
+
+    #[test]
+    fn shading_an_intersection() {
+        let world = test_world();
+
+        let ray = Ray {
+            origin: Point::new(0.0, 0.0, -5.0),
+            direction: Vector::new(0.0, 0.0, 1.0),
+        };
+
+        let i = Intersection {
+            t: 4.0,
+            object: &world.objects[0],
+            u: None,
+            v: None,
+            instance: None,
+        };
+
+        let comps = i.prepare_computation(&ray, [i]);
+
+        let shade: Color = Whitted::default().shade_hit(&world, comps, RECURSION_DEPTH);
+
+        assert_eq!(
+            shade,
+            Color {
+                red: 0.38066,
+                green: 0.47583,
+                blue: 0.2855,
+            }
+        );
+    }
+
+    #[test]
+    fn shading_an_intersection_from_the_inside() {
+        let world = World {
+            lights: vec![Light::Point(PointLight {
+                position: Point::new(0.0, 0.25, 0.0),
+                intensity: color::consts::WHITE,
+                group: None,
+            })],
+            ..test_world()
+        };
+
+        let ray = Ray {
+            origin: Point::new(0.0, 0.0, 0.0),
+            direction: Vector::new(0.0, 0.0, 1.0),
+        };
+
+        let i = Intersection {
+            t: 0.5,
+            object: &world.objects[1],
+            u: None,
+            v: None,
+            instance: None,
+        };
+
+        let comps = i.prepare_computation(&ray, [i]);
+
+        let shade: Color = Whitted::default().shade_hit(&world, comps, RECURSION_DEPTH);
+
+        assert_eq!(
+            shade,
+            Color {
+                red: 0.90498,
+                green: 0.90498,
+                blue: 0.90498,
+            }
+        );
+    }
+
+    #[test]
+    fn shade_hit_when_there_is_no_light() {
+        let world = World {
+            lights: vec![],
+            ..test_world()
+        };
+
+        let ray = Ray {
+            origin: Point::new(0.0, 0.0, 0.0),
+            direction: Vector::new(0.0, 0.0, 1.0),
+        };
+
+        let i = Intersection {
+            t: 0.5,
+            object: &world.objects[1],
+            u: None,
+            v: None,
+            instance: None,
+        };
+
+        let comps = i.prepare_computation(&ray, [i]);
+
+        let shade: Color = Whitted::default().shade_hit(&world, comps, RECURSION_DEPTH);
+
+        assert_eq!(shade, color::consts::BLACK);
+    }
+
+    #[test]
+    fn the_color_when_a_ray_misses() {
+        let world = test_world();
+
+        let ray = Ray {
+            origin: Point::new(0.0, 0.0, -5.0),
+            direction: Vector::new(0.0, 1.0, 0.0),
+        };
+
+        let color_at = Whitted::default().color_at(&world, &ray);
+
+        assert_eq!(color_at, color::consts::BLACK);
+    }
+
+    #[test]
+    fn the_color_when_a_ray_hits() {
+        let world = test_world();
+
+        let ray = Ray {
+            origin: Point::new(0.0, 0.0, -5.0),
+            direction: Vector::new(0.0, 0.0, 1.0),
+        };
+
+        let color_at = Whitted::default().color_at(&world, &ray);
+
+        assert_eq!(
+            color_at,
+            Color {
+                red: 0.38066,
+                green: 0.47583,
+                blue: 0.2855,
+            }
+        );
+    }
+
+    #[test]
+    fn the_color_when_an_intersection_behind_the_ray() {
+        let mut world = test_world();
+
+        let outer_object = &mut world.objects[0];
+        outer_object.as_mut().material = Material {
+            ambient: 1.0,
+            ..outer_object.as_ref().material.clone()
+        };
+
+        let inner_object = &mut world.objects[1];
+        inner_object.as_mut().material = Material {
+            ambient: 1.0,
+            ..inner_object.as_ref().material.clone()
+        };
+
+        let ray = Ray {
+            origin: Point::new(0.0, 0.0, 0.75),
+            direction: Vector::new(0.0, 0.0, -1.0),
+        };
+
+        let color_at = Whitted::default().color_at(&world, &ray);
+        let inner = &world.objects[1];
+
+        assert_eq!(Pattern3D::Solid(color_at), inner.as_ref().material.pattern);
+    }
+
+    #[test]
+    fn shade_hit_is_given_an_intersection_in_shadow() {
+        let object0 = Shape::Sphere(Default::default());
+
+        let object1 = Shape::Sphere(Sphere::from(ShapeBuilder {
+            transform: Transform::translation(0.0, 0.0, 10.0),
+            ..Default::default()
+        }));
+
+        let light = Light::Point(PointLight {
+            position: Point::new(0.0, 0.0, -10.0),
+            intensity: color::consts::WHITE,
+            group: None,
+        });
+
+        let world = World {
+            objects: vec![object0, object1.clone()],
+            lights: vec![light],
+            ..Default::default()
+        };
+
+        let ray = Ray {
+            origin: Point::new(0.0, 0.0, 5.0),
+            direction: Vector::new(0.0, 0.0, 1.0),
+        };
+
+        let i = Intersection {
+            t: 4.0,
+            object: &object1,
+            u: None,
+            v: None,
+            instance: None,
+        };
+
+        let comps = i.prepare_computation(&ray, [i]);
+
+        let shade: Color = Whitted::default().shade_hit(&world, comps, RECURSION_DEPTH);
+
+        assert_eq!(
+            shade,
+            Color {
+                red: 0.1,
+                green: 0.1,
+                blue: 0.1
+            }
+        );
+    }
+
+    #[test]
+    fn the_reflected_color_for_a_non_reflective_material() {
+        let mut world = test_world();
+
+        let ray = Ray {
+            origin: Point::new(0.0, 0.0, 0.0),
+            direction: Vector::new(0.0, 0.0, 1.0),
+        };
+
+        let object = &mut world.objects[1];
+        object.as_mut().material = Material {
+            ambient: 1.0,
+            ..object.as_ref().material.clone()
+        };
+
+        let i = Intersection {
+            t: 1.0,
+            object: &world.objects[1],
+            u: None,
+            v: None,
+            instance: None,
+        };
+
+        let comps = i.prepare_computation(&ray, [i]);
+
+        let shade: Color = Whitted::default().reflected_color(&world, &comps, RECURSION_DEPTH);
+
+        assert_eq!(shade, color::consts::BLACK);
+    }
+
+    #[test]
+    fn the_reflected_color_for_a_reflective_material() {
+        let world = test_world();
+
+        let object = Shape::Plane(Plane::from(ShapeBuilder {
+            material: Material {
+                reflectivity: 0.5,
+                ..Default::default()
+            },
+            transform: Transform::translation(0.0, -1.0, 0.0),
+        }));
+
+        let ray = Ray {
+            origin: Point::new(0.0, 0.0, -3.0),
+            direction: Vector::new(0.0, -2_f64.sqrt() / 2.0, 2_f64.sqrt() / 2.0),
+        };
+
+        let i = Intersection {
+            t: 2_f64.sqrt(),
+            object: &object,
+            u: None,
+            v: None,
+            instance: None,
+        };
+
+        let comps = i.prepare_computation(&ray, [i]);
+
+        let shade: Color = Whitted::default().reflected_color(&world, &comps, RECURSION_DEPTH);
+
+        assert_eq!(
+            shade,
+            Color {
+                red: 0.19033,
+                green: 0.23791,
+                blue: 0.14275,
+            }
+        );
+    }
+
+    #[test]
+    fn shade_hit_with_a_reflective_material() {
+        let world = test_world();
+
+        let object = Shape::Plane(Plane::from(ShapeBuilder {
+            material: Material {
+                reflectivity: 0.5,
+                ..Default::default()
+            },
+            transform: Transform::translation(0.0, -1.0, 0.0),
+        }));
+
+        let ray = Ray {
+            origin: Point::new(0.0, 0.0, -3.0),
+            direction: Vector::new(0.0, -2_f64.sqrt() / 2.0, 2_f64.sqrt() / 2.0),
+        };
+
+        let i = Intersection {
+            t: 2_f64.sqrt(),
+            object: &object,
+            u: None,
+            v: None,
+            instance: None,
+        };
+
+        let comps = i.prepare_computation(&ray, [i]);
+
+        let shade: Color = Whitted::default().shade_hit(&world, comps, RECURSION_DEPTH);
+
+        assert_eq!(
+            shade,
+            Color {
+                red: 0.87676,
+                green: 0.92435,
+                blue: 0.82918,
+            }
+        );
+    }
+
+    #[test]
+    fn color_at_with_mutually_reflective_surfaces() {
+        let lower_object = Shape::Sphere(Sphere::from(ShapeBuilder {
+            material: Material {
+                reflectivity: 1.0,
+                ..Default::default()
+            },
+            transform: Transform::translation(0.0, -1.0, 0.0),
+        }));
+
+        let upper_object = Shape::Sphere(Sphere::from(ShapeBuilder {
+            material: lower_object.as_ref().material.clone(),
+            transform: Transform::translation(0.0, 1.0, 0.0),
+        }));
+
+        let light = Light::Point(PointLight {
+            position: Point::new(0.0, 0.0, 0.0),
+            intensity: color::consts::WHITE,
+            group: None,
+        });
+
+        let world = World {
+            objects: vec![lower_object, upper_object],
+            lights: vec![light],
+            ..Default::default()
+        };
+
+        let ray = Ray {
+            origin: Point::new(0.0, 0.0, 0.0),
+            direction: Vector::new(0.0, 1.0, 0.0),
+        };
+
+        // This should not stack overflow, so it should not panic.
+        Whitted::default().color_at(&world, &ray);
+    }
+
+    #[test]
+    fn the_reflected_color_at_the_maximum_recursive_depth() {
+        let object = Shape::Sphere(Sphere::from(ShapeBuilder {
+            material: Material {
+                reflectivity: 0.5,
+                ..Default::default()
+            },
+            transform: Transform::translation(0.0, -1.0, 0.0),
+        }));
+
+        let mut w = test_world();
+        w.objects.push(object);
+
+        let ray = Ray {
+            origin: Point::new(0.0, 0.0, -3.0),
+            direction: Vector::new(0.0, -2_f64.sqrt() / 2.0, 2_f64.sqrt() / 2.0),
+        };
+
+        let i = Intersection {
+            t: 2_f64.sqrt(),
+            object: &w.objects[2],
+            u: None,
+            v: None,
+            instance: None,
+        };
+
+        let comps = i.prepare_computation(&ray, [i]);
+
+        let shade: Color = Whitted::default().reflected_color(&w, &comps, 0);
+
+        assert_eq!(shade, color::consts::BLACK);
+    }
+
+    #[test]
+    fn the_refracted_color_with_an_opaque_surface() {
+        let world = test_world();
+
+        let ray = Ray {
+            origin: Point::new(0.0, 0.0, -5.0),
+            direction: Vector::new(0.0, 0.0, 1.0),
+        };
+
+        let xs = [
+            Intersection {
+                t: 4.0,
+                object: &world.objects[0],
+                u: None,
+                v: None,
+                instance: None,
+            },
+            Intersection {
+                t: 6.0,
+                object: &world.objects[0],
+                u: None,
+                v: None,
+                instance: None,
+            },
+        ];
+
+        let comps = xs[0].prepare_computation(&ray, xs);
+
+        let shade: Color = Whitted::default().refracted_color(&world, &comps, RECURSION_DEPTH);
+
+        assert_eq!(shade, color::consts::BLACK);
+    }
+
+    #[test]
+    fn the_refracted_color_at_the_maximum_recursive_depth() {
+        let mut world = test_world();
+
+        let object = &mut world.objects[0];
+        object.as_mut().material = Material {
+            index_of_refraction: 1.5,
+            transparency: 1.0,
+            ..object.as_ref().material.clone()
+        };
+
+        let ray = Ray {
+            origin: Point::new(0.0, 0.0, -5.0),
+            direction: Vector::new(0.0, 0.0, 1.0),
+        };
+
+        let xs = [
+            Intersection {
+                t: 4.0,
+                object: &world.objects[0],
+                u: None,
+                v: None,
+                instance: None,
+            },
+            Intersection {
+                t: 6.0,
+                object: &world.objects[0],
+                u: None,
+                v: None,
+                instance: None,
+            },
+        ];
+
+        let comps = xs[0].prepare_computation(&ray, xs);
+
+        let shade: Color = Whitted::default().refracted_color(&world, &comps, 0);
+
+        assert_eq!(shade, color::consts::BLACK);
+    }
+
+    #[test]
+    fn the_refracted_color_under_total_internal_reflection() {
+        let mut world = test_world();
+
+        let object = &mut world.objects[0];
+        object.as_mut().material = Material {
+            index_of_refraction: 1.5,
+            transparency: 1.0,
+            ..object.as_ref().material.clone()
+        };
+
+        let ray = Ray {
+            origin: Point::new(0.0, 0.0, 2_f64.sqrt() / 2.0),
+            direction: Vector::new(0.0, 1.0, 0.0),
+        };
+
+        let xs = [
+            Intersection {
+                t: -2_f64.sqrt() / 2.0,
+                object: &world.objects[0],
+                u: None,
+                v: None,
+                instance: None,
+            },
+            Intersection {
+                t: 2_f64.sqrt() / 2.0,
+                object: &world.objects[0],
+                u: None,
+                v: None,
+                instance: None,
+            },
+        ];
+
+        let comps = xs[1].prepare_computation(&ray, xs);
+
+        let shade: Color = Whitted::default().refracted_color(&world, &comps, RECURSION_DEPTH);
+
+        assert_eq!(shade, color::consts::BLACK);
+    }
+
+    #[test]
+    fn shade_hit_with_a_transparent_material() {
+        let mut world = test_world();
+
+        let floor = Shape::Plane(Plane::from(ShapeBuilder {
+            material: Material {
+                index_of_refraction: 1.5,
+                transparency: 0.5,
+                ..Default::default()
+            },
+            transform: Transform::translation(0.0, -1.0, 0.0),
+        }));
+
+        let ball = Shape::Sphere(Sphere::from(ShapeBuilder {
+            material: Material {
+                ambient: 0.5,
+                pattern: Pattern3D::Solid(color::consts::RED),
+                ..Default::default()
+            },
+            transform: Transform::translation(0.0, -3.5, -0.5),
+        }));
+
+        world.objects.push(floor);
+        world.objects.push(ball);
+
+        let ray = Ray {
+            origin: Point::new(0.0, 0.0, -3.0),
+            direction: Vector::new(0.0, -2_f64.sqrt() / 2.0, 2_f64.sqrt() / 2.0),
+        };
+
+        let xs = [Intersection {
+            t: 2_f64.sqrt(),
+            object: &world.objects[2],
+            u: None,
+            v: None,
+            instance: None,
+        }];
+
+        let comps = xs[0].prepare_computation(&ray, xs);
+
+        let shade: Color = Whitted::default().shade_hit(&world, comps, RECURSION_DEPTH);
+
+        assert_eq!(
+            shade,
+            Color {
+                red: 0.93642,
+                green: 0.68642,
+                blue: 0.68642
+            }
+        );
+    }
+
+    #[test]
+    fn shade_hit_with_a_reflective_and_transparent_material() {
+        let mut world = test_world();
+
+        let ray = Ray {
+            origin: Point::new(0.0, 0.0, -3.0),
+            direction: Vector::new(0.0, -2_f64.sqrt() / 2.0, 2_f64.sqrt() / 2.0),
+        };
+
+        let floor = Shape::Plane(Plane::from(ShapeBuilder {
+            material: Material {
+                index_of_refraction: 1.5,
+                reflectivity: 0.5,
+                transparency: 0.5,
+                ..Default::default()
+            },
+            transform: Transform::translation(0.0, -1.0, 0.0),
+        }));
+
+        let ball = Shape::Sphere(Sphere::from(ShapeBuilder {
+            material: Material {
+                ambient: 0.5,
+                pattern: Pattern3D::Solid(color::consts::RED),
+                ..Default::default()
+            },
+            transform: Transform::translation(0.0, -3.5, -0.5),
+        }));
+
+        world.objects.push(floor);
+        world.objects.push(ball);
+
+        let xs = [Intersection {
+            t: 2_f64.sqrt(),
+            object: &world.objects[2],
+            u: None,
+            v: None,
+            instance: None,
+        }];
+
+        let comps = xs[0].prepare_computation(&ray, xs);
+
+        let shade: Color = Whitted::default().shade_hit(&world, comps, RECURSION_DEPTH);
+
+        assert_eq!(
+            shade,
+            Color {
+                red: 0.93391,
+                green: 0.69643,
+                blue: 0.69243
+            }
+        );
+    }
+
+    #[test]
+    fn ambient_occlusion_of_an_unoccluded_surface_is_its_own_color() {
+        let world = World {
+            objects: vec![Shape::Plane(Default::default())],
+            lights: vec![],
+            ..Default::default()
+        };
+
+        let ray = Ray {
+            origin: Point::new(0.0, 1.0, 0.0),
+            direction: Vector::new(0.0, -1.0, 0.0),
+        };
+
+        let color_at = AmbientOcclusion::default().color_at(&world, &ray);
+
+        assert_eq!(color_at, color::consts::WHITE);
+    }
+
+    #[test]
+    fn ambient_occlusion_of_an_enclosed_surface_is_black() {
+        let world = World {
+            objects: vec![
+                Shape::Plane(Default::default()),
+                Shape::Sphere(Sphere::from(ShapeBuilder {
+                    transform: Transform::scaling(5.0, 5.0, 5.0).unwrap(),
+                    ..Default::default()
+                })),
+            ],
+            lights: vec![],
+            ..Default::default()
+        };
+
+        let ray = Ray {
+            origin: Point::new(0.0, 1.0, 0.0),
+            direction: Vector::new(0.0, -1.0, 0.0),
+        };
+
+        let color_at = AmbientOcclusion {
+            samples: 8,
+            distance: 20.0,
+            ..Default::default()
+        }
+        .color_at(&world, &ray);
+
+        assert_eq!(color_at, color::consts::BLACK);
+    }
+
+    fn floor_under_a_dome(material: Material) -> World {
+        World {
+            objects: vec![
+                Shape::Plane(Plane::from(ShapeBuilder {
+                    material,
+                    ..Default::default()
+                })),
+                Shape::Sphere(Sphere::from(ShapeBuilder {
+                    transform: Transform::scaling(5.0, 5.0, 5.0).unwrap(),
+                    ..Default::default()
+                })),
+            ],
+            lights: vec![],
+            ..Default::default()
+        }
+    }
+
+    fn dirty_floor_under_a_dome(dirt: Dirt) -> World {
+        floor_under_a_dome(Material {
+            dirt: Some(dirt),
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn dirt_is_sampled_without_lights() {
+        let ray = Ray {
+            origin: Point::new(0.0, 1.0, 0.0),
+            direction: Vector::new(0.0, -1.0, 0.0),
+        };
+
+        // Without occlusion samples of it's own, ambient occlusion shows the surface color as it
+        // is, so only the dirt darkens it.
+        let integrator = AmbientOcclusion {
+            samples: 0,
+            distance: 0.0,
+            ..Default::default()
+        };
+
+        let enclosed = dirty_floor_under_a_dome(Dirt {
+            color: color::consts::RED,
+            distance: 20.0,
+            samples: 8,
+        });
+
+        assert_eq!(integrator.color_at(&enclosed, &ray), color::consts::RED);
+
+        // Surfaces farther than the distance of the dirt don't dirty the floor.
+        let open = dirty_floor_under_a_dome(Dirt {
+            color: color::consts::RED,
+            distance: 0.5,
+            samples: 8,
+        });
+
+        assert_eq!(integrator.color_at(&open, &ray), color::consts::WHITE);
+    }
+
+    #[test]
+    fn shading_a_dirty_surface() {
+        let light = Light::Point(PointLight {
+            position: Point::new(0.0, 2.0, 0.0),
+            intensity: color::consts::WHITE,
+            group: None,
+        });
+
+        let mut dirty = dirty_floor_under_a_dome(Dirt {
+            color: color::consts::RED,
+            distance: 20.0,
+            samples: 8,
+        });
+        dirty.lights = vec![light.clone()];
+
+        let mut red = floor_under_a_dome(Material {
+            pattern: Pattern3D::Solid(color::consts::RED),
+            ..Default::default()
+        });
+        red.lights = vec![light];
+
+        let ray = Ray {
+            origin: Point::new(0.0, 1.0, 0.0),
+            direction: Vector::new(0.0, -1.0, 0.0),
+        };
+
+        assert_eq!(
+            Whitted::default().color_at(&dirty, &ray),
+            Whitted::default().color_at(&red, &ray)
+        );
+    }
+
+    #[test]
+    fn splitting_the_color_by_the_light_it_comes_from() {
+        let mut world = test_world();
+        world.objects.push(Shape::Plane(Plane::from(ShapeBuilder {
+            material: Material {
+                reflectivity: 0.5,
+                ..Default::default()
+            },
+            transform: Transform::translation(0.0, -1.0, 0.0),
+        })));
+
+        let key = world.lights[0].clone();
+        let fill = Light::Point(PointLight {
+            position: Point::new(10.0, 10.0, -10.0),
+            intensity: gray(0.5),
+            group: None,
+        });
+        world.lights.push(fill.clone());
+
+        let ray = Ray {
+            origin: Point::new(0.0, 0.0, -3.0),
+            direction: Vector::new(0.0, -2_f64.sqrt() / 2.0, 2_f64.sqrt() / 2.0),
+        };
+
+        let integrator = Whitted::default();
+        let colors = integrator.light_colors_at(&world, &ray);
+
+        assert_eq!(colors.unlit, color::consts::BLACK);
+        assert_eq!(colors.total(), integrator.color_at(&world, &ray));
+
+        // Straight to a surface that isn't reflective, each light gives the same color as if it
+        // was alone in the world.
+        let ray = Ray {
+            origin: Point::new(0.0, 0.0, -5.0),
+            direction: Vector::new(0.0, 0.0, 1.0),
+        };
+
+        let colors = integrator.light_colors_at(&world, &ray);
+
+        for (light, color) in [key, fill].into_iter().zip(colors.lights) {
+            let mut alone = world.clone();
+            alone.lights = vec![light];
+
+            assert_eq!(color, integrator.color_at(&alone, &ray));
+        }
+    }
+
+    #[test]
+    fn every_integrator_that_uses_lights_splits_the_color_by_light() {
+        let mut world = test_world();
+        world.lights.push(Light::Point(PointLight {
+            position: Point::new(10.0, 10.0, -10.0),
+            intensity: gray(0.5),
+            group: None,
+        }));
+
+        let ray = Ray {
+            origin: Point::new(0.0, 0.0, -5.0),
+            direction: Vector::new(0.0, 0.0, 1.0),
+        };
+
+        let integrator = Polarized::default();
+        let colors = integrator.light_colors_at(&world, &ray);
+
+        assert_eq!(colors.unlit, color::consts::BLACK);
+        assert_ne!(colors.lights[1], color::consts::BLACK);
+        assert_eq!(colors.total(), integrator.color_at(&world, &ray));
+
+        // The spectral integrator picks a random wavelength for each ray, so the split is compared
+        // with a single wavelength.
+        let integrator = Spectral::default();
+        let colors: LightColors = integrator.trace(&world, &ray, 550.0, RECURSION_DEPTH);
+
+        assert_eq!(colors.unlit, color::consts::BLACK);
+        assert_ne!(colors.lights[1], color::consts::BLACK);
+        assert_approx!(
+            colors.total().red,
+            integrator.radiance_at(&world, &ray, 550.0)
+        );
+    }
+
+    #[test]
+    fn integrators_that_ignore_lights_only_have_unlit_color() {
+        let world = test_world();
+
+        let ray = Ray {
+            origin: Point::new(0.0, 0.0, -5.0),
+            direction: Vector::new(0.0, 0.0, 1.0),
+        };
+
+        let integrator = AmbientOcclusion::default();
+        let colors = integrator.light_colors_at(&world, &ray);
+
+        assert_eq!(colors.lights, [color::consts::BLACK]);
+        assert_ne!(colors.unlit, color::consts::BLACK);
+    }
+
+    #[test]
+    fn the_path_tracer_without_bounces_only_computes_direct_lighting() {
+        let world = test_world();
+
+        let ray = Ray {
+            origin: Point::new(0.0, 0.0, -5.0),
+            direction: Vector::new(0.0, 0.0, 1.0),
+        };
+
+        let color_at = PathTracer {
+            max_depth: 0,
+            ..Default::default()
+        }
+        .color_at(&world, &ray);
+
+        assert_eq!(
+            color_at,
+            Color {
+                red: 0.38066,
+                green: 0.47583,
+                blue: 0.2855,
+            }
+        );
+    }
+
+    #[test]
+    fn the_path_tracer_adds_light_bounced_off_diffuse_surfaces() {
+        let world = test_world();
+
+        let ray = Ray {
+            origin: Point::new(0.0, 0.0, -5.0),
+            direction: Vector::new(0.0, 0.0, 1.0),
+        };
+
+        let direct = PathTracer {
+            max_depth: 0,
+            ..Default::default()
+        }
+        .color_at(&world, &ray);
+        let color_at = PathTracer::default().color_at(&world, &ray);
+
+        assert!(color_at.red >= direct.red);
+        assert!(color_at.green >= direct.green);
+        assert!(color_at.blue >= direct.blue);
+    }
+
+    #[test]
+    fn the_direct_color_of_a_path_doesnt_bounce_off_diffuse_surfaces() {
+        let world = test_world();
+
+        let ray = Ray {
+            origin: Point::new(0.0, 0.0, -5.0),
+            direction: Vector::new(0.0, 0.0, 1.0),
+        };
+
+        render::take_thread_stats();
+
+        let direct = PathTracer::default().direct_color_at(&world, &ray);
+
+        assert_eq!(render::take_thread_stats().reflection_rays, 0);
+        assert_eq!(
+            direct,
+            PathTracer {
+                max_depth: 0,
+                ..Default::default()
+            }
+            .color_at(&world, &ray)
+        );
+    }
+
+    #[test]
+    fn the_indirect_color_of_a_path_is_only_the_light_bounced_off_the_first_hit() {
+        let world = test_world();
+
+        let ray = Ray {
+            origin: Point::new(0.0, 0.0, -5.0),
+            direction: Vector::new(0.0, 0.0, 1.0),
+        };
+
+        render::take_thread_stats();
+
+        PathTracer {
+            max_depth: 1,
+            ..Default::default()
+        }
+        .indirect_color_at(&world, &ray);
+
+        let stats = render::take_thread_stats();
+
+        // The bounce ray and the shadow ray of the surface it hits, but not the shadow ray of the
+        // first hit.
+        assert_eq!(stats.reflection_rays, 1);
+        assert!(stats.shadow_rays <= 1);
+    }
+
+    #[test]
+    fn splitting_the_color_of_other_integrators() {
+        let world = World {
+            objects: vec![Shape::Plane(Default::default())],
+            lights: vec![],
+            ..Default::default()
+        };
+
+        let ray = Ray {
+            origin: Point::new(0.0, 1.0, 0.0),
+            direction: Vector::new(0.0, -1.0, 0.0),
+        };
+
+        let whitted = Whitted::default();
+        let occlusion = AmbientOcclusion::default();
+
+        assert_eq!(
+            whitted.direct_color_at(&world, &ray),
+            whitted.color_at(&world, &ray)
+        );
+        assert_eq!(
+            whitted.indirect_color_at(&world, &ray),
+            color::consts::BLACK
+        );
+
+        assert_eq!(
+            occlusion.direct_color_at(&world, &ray),
+            color::consts::BLACK
+        );
+        assert_eq!(
+            occlusion.indirect_color_at(&world, &ray),
+            color::consts::WHITE
+        );
+    }
+
+    fn portal_world() -> World {
+        let (entrance, exit) = Portal::pair(Transform::translation(100.0, 0.0, 0.0));
+
+        let doorway = |portal, transform| {
+            Shape::Cube(Cube::from(ShapeBuilder {
+                material: Material {
+                    portal: Some(portal),
+                    ..Default::default()
+                },
+                transform,
+            }))
+        };
+
+        let ball = |color, transform| {
+            Shape::Sphere(Sphere::from(ShapeBuilder {
+                material: Material {
+                    pattern: Pattern3D::Solid(color),
+                    ambient: 1.0,
+                    diffuse: 0.0,
+                    specular: 0.0,
+                    ..Default::default()
+                },
+                transform,
+            }))
+        };
+
+        let door_transform = Transform::scaling(1.0, 1.0, 0.01).unwrap();
+
+        World {
+            objects: vec![
+                doorway(entrance, door_transform),
+                doorway(
+                    exit,
+                    Transform::translation(100.0, 0.0, 0.0) * door_transform,
+                ),
+                ball(color::consts::GREEN, Transform::translation(0.0, 0.0, 5.0)),
+                ball(color::consts::RED, Transform::translation(100.0, 0.0, 5.0)),
+            ],
+            lights: vec![Light::Point(PointLight {
+                position: Point::new(0.0, 0.0, -10.0),
+                intensity: color::consts::WHITE,
+                group: None,
+            })],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn a_ray_going_through_a_portal_continues_at_its_pair() {
+        let world = portal_world();
+
+        let ray = Ray {
+            origin: Point::new(0.0, 0.0, -5.0),
+            direction: Vector::new(0.0, 0.0, 1.0),
+        };
+
+        render::take_thread_stats();
+
+        assert_eq!(
+            Whitted::default().color_at(&world, &ray),
+            color::consts::RED
+        );
+
+        // One ray comes out of the paired doorway, and then goes through it's back side.
+        assert_eq!(render::take_thread_stats().portal_rays, 2);
+
+        assert_eq!(
+            PathTracer {
+                max_depth: 3,
+                ..Default::default()
+            }
+            .color_at(&world, &ray),
+            color::consts::RED
+        );
+    }
+
+    #[test]
+    fn a_ray_missing_a_portal_is_not_teleported() {
+        let world = portal_world();
+
+        let ray = Ray {
+            origin: Point::new(0.0, 0.0, 10.0),
+            direction: Vector::new(0.0, 0.0, -1.0),
+        };
+
+        assert_eq!(
+            Whitted::default().color_at(&world, &ray),
+            color::consts::GREEN
+        );
+    }
+
+    #[test]
+    fn a_portal_at_the_maximum_recursive_depth_is_black() {
+        let world = portal_world();
+
+        let ray = Ray {
+            origin: Point::new(0.0, 0.0, -5.0),
+            direction: Vector::new(0.0, 0.0, 1.0),
+        };
+
+        assert_eq!(
+            Whitted {
+                recursion_depth: 0,
+                ..Default::default()
+            }
+            .color_at(&world, &ray),
+            color::consts::BLACK
+        );
+    }
+
+    #[test]
+    fn cosine_weighted_directions_are_in_the_hemisphere_of_the_normal() {
+        let normal = Vector::new(0.0, 0.0, 1.0);
+
+        for _ in 0..100 {
+            let direction = cosine_weighted_direction(normal);
+
+            assert_approx!(direction.magnitude(), 1.0);
+            assert!(direction.dot(normal) >= 0.0);
+        }
+    }
+
+    /// Color that a spectral integrator converges to for a ray, averaged over every wavelength
+    /// sampled by the sensor.
+    fn spectral_color_at(integrator: &Spectral, world: &World, ray: &Ray) -> Color {
+        let wavelengths: Vec<_> = spectrum::wavelengths().collect();
+
+        wavelengths.iter().fold(color::consts::BLACK, |acc, &w| {
+            acc + spectrum::sensor_response(w)
+                * (integrator.radiance_at(world, ray, w) / wavelengths.len() as f64)
+        })
+    }
+
+    #[test]
+    fn a_spectral_render_converges_to_the_same_colors_without_spectral_effects() {
+        let world = test_world();
+
+        let ray = Ray {
+            origin: Point::new(0.0, 0.0, -5.0),
+            direction: Vector::new(0.0, 0.0, 1.0),
+        };
+
+        assert_eq!(
+            spectral_color_at(&Spectral::default(), &world, &ray),
+            Whitted::default().color_at(&world, &ray)
+        );
+
+        // Reflections are added once for each light of the world, and not at all without them.
+        let mut reflective = test_world();
+
+        reflective
+            .objects
+            .push(Shape::Plane(Plane::from(ShapeBuilder {
+                material: Material {
+                    reflectivity: 0.5,
+                    ..Default::default()
+                },
+                transform: Transform::translation(0.0, -1.0, 0.0),
+            })));
+        reflective.lights.push(Light::Point(PointLight {
+            position: Point::new(10.0, 10.0, -10.0),
+            intensity: color::consts::WHITE * 0.5,
+            group: None,
+        }));
+
+        let ray = Ray {
+            origin: Point::new(0.0, 0.0, -3.0),
+            direction: Vector::new(0.0, -2_f64.sqrt() / 2.0, 2_f64.sqrt() / 2.0),
+        };
+
+        for lights in [2, 0] {
+            reflective.lights.truncate(lights);
+
+            assert_eq!(
+                spectral_color_at(&Spectral::default(), &reflective, &ray),
+                Whitted::default().color_at(&reflective, &ray)
+            );
+        }
+    }
+
+    #[test]
+    fn the_spectrum_of_a_material_replaces_its_color() {
+        let mut world = test_world();
+        world.objects[0].as_mut().material.spectrum =
+            Some(Arc::new(Spectrum::new(vec![(500.0, 0.0)]).unwrap()));
+
+        let ray = Ray {
+            origin: Point::new(0.0, 0.0, -5.0),
+            direction: Vector::new(0.0, 0.0, 1.0),
+        };
+
+        let color = spectral_color_at(&Spectral::default(), &world, &ray);
+
+        // Only the specular highlight, which has the color of the light, is left.
+        assert_approx!(color.red, color.green);
+        assert_approx!(color.green, color.blue);
+        assert!(color.red < 0.1);
+    }
+
+    #[test]
+    fn an_illuminant_changes_the_light_of_every_light() {
+        let world = test_world();
+
+        let ray = Ray {
+            origin: Point::new(0.0, 0.0, -5.0),
+            direction: Vector::new(0.0, 0.0, 1.0),
+        };
+
+        let integrator = Spectral {
+            illuminant: Some(Arc::new(Spectrum::new(vec![(500.0, 0.0)]).unwrap())),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            spectral_color_at(&integrator, &world, &ray),
+            color::consts::BLACK
+        );
+    }
+
+    #[test]
+    fn dispersive_materials_refract_each_wavelength_in_a_different_direction() {
+        let shape = Shape::Sphere(Sphere::from(ShapeBuilder {
+            material: Material {
+                transparency: 1.0,
+                index_of_refraction: 1.5,
+                dispersion: 0.01,
+                ..Default::default()
+            },
+            ..Default::default()
+        }));
+
+        let ray = Ray {
+            origin: Point::new(0.0, 0.5, -5.0),
+            direction: Vector::new(0.0, 0.0, 1.0),
+        };
+
+        let direction_at = |wavelength| {
+            let xs = shape.intersect(&ray);
+            let comps = xs[0].prepare_computation_at_wavelength(&ray, xs.clone(), 0.0, wavelength);
+
+            refraction(&comps).unwrap().0.direction
+        };
+
+        let blue = direction_at(450.0);
+        let red = direction_at(650.0);
+
+        // Shorter wavelengths are bent more towards the center of the sphere.
+        assert!(blue.0.y < red.0.y);
+    }
+
+    /// Glass floor without a color of it's own, seen at Brewster's angle, that reflects a white
+    /// sphere. Returns the world and the ray that sees the reflection.
+    fn glass_floor_at_brewsters_angle() -> (World, Ray) {
+        let angle = 1.5_f64.atan();
+        let direction = Vector::new(0.0, -angle.cos(), angle.sin());
+        let reflected = Vector::new(0.0, angle.cos(), angle.sin());
+
+        let floor = Shape::Plane(Plane::from(ShapeBuilder {
+            material: Material {
+                pattern: Pattern3D::Solid(color::consts::BLACK),
+                ambient: 0.0,
+                diffuse: 0.0,
+                specular: 0.0,
+                reflectivity: 1.0,
+                transparency: 1.0,
+                index_of_refraction: 1.5,
+                ..Default::default()
+            },
+            ..Default::default()
+        }));
+
+        let center = Point::new(0.0, 0.0, 0.0) + reflected * 10.0;
+        let sphere = Shape::Sphere(Sphere::from(ShapeBuilder {
+            material: Material {
+                ambient: 1.0,
+                diffuse: 0.0,
+                specular: 0.0,
+                ..Default::default()
+            },
+            transform: Transform::translation(center.0.x, center.0.y, center.0.z),
+        }));
+
+        let world = World {
+            objects: vec![floor, sphere],
+            lights: vec![Light::Point(PointLight {
+                position: Point::new(0.0, 10.0, 0.0),
+                intensity: color::consts::WHITE,
+                group: None,
+            })],
+            ..Default::default()
+        };
+
+        let ray = Ray {
+            origin: Point::new(0.0, 0.0, 0.0) + direction * -5.0,
+            direction,
+        };
+
+        (world, ray)
+    }
+
+    fn polarized_with_filter(angle: f64) -> Polarized {
+        Polarized {
+            filter: Some(PolarizingFilter {
+                horizontal: Vector::new(1.0, 0.0, 0.0),
+                angle,
+            }),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn the_polarized_integrator_without_a_filter_renders_opaque_surfaces_like_whitted() {
+        let world = test_world();
+
+        let ray = Ray {
+            origin: Point::new(0.0, 0.0, -5.0),
+            direction: Vector::new(0.0, 0.0, 1.0),
+        };
+
+        assert_eq!(
+            Polarized::default().color_at(&world, &ray),
+            Whitted::default().color_at(&world, &ray)
+        );
+    }
+
+    #[test]
+    fn light_reflected_at_brewsters_angle_is_polarized_parallel_to_the_surface() {
+        let (world, ray) = glass_floor_at_brewsters_angle();
+
+        let unfiltered = Polarized::default().color_at(&world, &ray);
+        let horizontal = polarized_with_filter(0.0).color_at(&world, &ray);
+        let vertical = polarized_with_filter(FRAC_PI_2).color_at(&world, &ray);
+
+        assert!(unfiltered.red > 0.0);
+        assert_eq!(horizontal, unfiltered * 2.0);
+        assert_eq!(vertical, color::consts::BLACK);
+    }
+
+    #[test]
+    fn a_diagonal_filter_lets_through_part_of_a_polarized_reflection() {
+        let (world, ray) = glass_floor_at_brewsters_angle();
+
+        let unfiltered = Polarized::default().color_at(&world, &ray);
+        let diagonal = polarized_with_filter(FRAC_PI_4).color_at(&world, &ray);
+
+        assert_eq!(diagonal, unfiltered);
+    }
+
+    #[test]
+    fn the_filter_of_a_camera_is_measured_from_the_horizontal_axis_of_its_images() {
+        let camera = Camera::try_from(CameraBuilder {
+            width: 10,
+            height: 10,
+            field_of_view: FRAC_PI_2,
+            transform: Default::default(),
+        })
+        .unwrap();
+
+        let polarized = Polarized::default().with_filter(&camera, 0.5);
+        let filter = polarized.filter.unwrap();
+
+        assert_approx!(filter.horizontal.dot(Vector::new(1.0, 0.0, 0.0)).abs(), 1.0);
+        assert_approx!(filter.angle, 0.5);
+    }
+
+    fn gizmos() -> Gizmos {
+        Gizmos::new(Arc::new(Whitted::default()), 0.1)
+    }
+
+    fn ray_towards_z(x: f64, y: f64) -> Ray {
+        Ray {
+            origin: Point::new(x, y, -10.0),
+            direction: Vector::new(0.0, 0.0, 1.0),
+        }
+    }
+
+    #[test]
+    fn drawing_a_point_light_as_a_dot() {
+        let mut world = test_world();
+        world.lights[0] = Light::Point(PointLight {
+            position: Point::new(0.0, 0.0, -2.0),
+            intensity: color::consts::RED,
+            group: None,
+        });
+
+        assert_eq!(
+            gizmos().color_at(&world, &ray_towards_z(0.05, 0.0)),
+            color::consts::RED
+        );
+        assert_ne!(
+            gizmos().color_at(&world, &ray_towards_z(0.0, 0.2)),
+            color::consts::RED
+        );
+    }
+
+    #[test]
+    fn lights_behind_a_shape_are_hidden_by_it() {
+        let mut world = test_world();
+        world.lights[0] = Light::Point(PointLight {
+            position: Point::new(0.0, 0.0, 2.0),
+            intensity: color::consts::RED,
+            group: None,
+        });
+
+        let ray = ray_towards_z(0.0, 0.0);
+
+        assert_eq!(
+            gizmos().color_at(&world, &ray),
+            Whitted::default().color_at(&world, &ray)
+        );
+    }
+
+    #[test]
+    fn whitted_lights_surfaces_with_a_dome_light() {
+        let world = World {
+            objects: vec![
+                Shape::Plane(Default::default()),
+                Shape::Sphere(Sphere::from(ShapeBuilder {
+                    transform: Transform::translation(0.0, 1.0, 0.0),
+                    ..Default::default()
+                })),
+            ],
+            lights: vec![Light::Dome(DomeLight::from(DomeLightBuilder {
+                source: DomeSource::Gradient {
+                    top: color::consts::WHITE,
+                    bottom: color::consts::BLACK,
+                },
+                intensity: color::consts::WHITE,
+                samples: 64,
+            }))],
+            ..Default::default()
+        };
+
+        let integrator = Whitted::default();
+        let down = |x| Ray {
+            origin: Point::new(x, 5.0, -5.0),
+            direction: Vector::new(0.0, -1.0, 1.0).normalize().unwrap(),
+        };
+
+        // The floor is lit from the whole sky far away from the sphere, and only from the sides of
+        // the sky right next to it.
+        let open = integrator.color_at(&world, &down(10.0));
+        let next_to_sphere = integrator.color_at(
+            &world,
+            &Ray {
+                origin: Point::new(0.0, 5.0, -1.05),
+                direction: Vector::new(0.0, -1.0, 0.0),
+            },
+        );
+
+        assert!(open.red > 0.5, "{open:?}");
+        assert!(next_to_sphere.red > 0.0, "{next_to_sphere:?}");
+        assert!(next_to_sphere.red < open.red, "{next_to_sphere:?}");
+
+        // Missed rays aren't lit by the dome.
+        let up = Ray {
+            origin: Point::new(0.0, 5.0, 0.0),
+            direction: Vector::new(0.0, 1.0, 0.0),
+        };
+
+        assert_eq!(integrator.color_at(&world, &up), color::consts::BLACK);
+    }
+
+    #[test]
+    fn drawing_the_outline_of_an_area_light() {
+        let world = World {
+            lights: vec![Light::Area(AreaLight::from(AreaLightBuilder {
+                corner: Point::new(-1.0, -1.0, 0.0),
+                horizontal_dir: Vector::new(2.0, 0.0, 0.0),
+                horizontal_cells: 2,
+                vertical_dir: Vector::new(0.0, 2.0, 0.0),
+                vertical_cells: 2,
+                intensity: color::consts::WHITE,
+            }))],
+            ..Default::default()
+        };
+
+        assert_eq!(
+            gizmos().color_at(&world, &ray_towards_z(1.0, 0.5)),
+            color::consts::WHITE
+        );
+        assert_eq!(
+            gizmos().color_at(&world, &ray_towards_z(0.5, 0.5)),
+            color::consts::BLACK
+        );
+    }
+
+    #[test]
+    fn drawing_the_frustum_of_a_camera() {
+        let camera = Camera::try_from(CameraBuilder {
+            width: 10,
+            height: 10,
+            field_of_view: std::f64::consts::FRAC_PI_2,
+            transform: Default::default(),
+        })
+        .unwrap();
+
+        let gizmos = gizmos().with_frustum(&camera, 3.0_f64.sqrt());
+        let world = World::default();
+
+        // The frustum of the camera reaches the corners of a square that goes from `-1.0` to `1.0`
+        // at `z = -1.0`, and it's seen here from the side.
+        let ray = |origin: Point| Ray {
+            origin,
+            direction: Vector::new(1.0, 0.0, 0.0),
+        };
+
+        assert_eq!(
+            gizmos.color_at(&world, &ray(Point::new(-5.0, 1.0, -1.0))),
+            FRUSTUM_COLOR
+        );
+        assert_eq!(
+            gizmos.color_at(&world, &ray(Point::new(-5.0, 0.5, -0.5))),
+            FRUSTUM_COLOR
+        );
+        assert_eq!(
+            gizmos.color_at(&world, &ray(Point::new(-5.0, 0.0, -0.5))),
+            color::consts::BLACK
+        );
+    }
+}
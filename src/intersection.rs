@@ -1,23 +1,64 @@
+use std::borrow::Cow;
+
+use smallvec::SmallVec;
+
 use crate::{
     float,
+    material::Material,
     ray::Ray,
     shape::Shape,
-    tuple::{Point, Vector},
+    transform::Transform,
+    tuple::{Point, Tuple, Vector},
 };
 
+/// Number of units in the last place of the largest coordinate of a point that rays leave a
+/// surface by, so the offset grows with the rounding error of points far from the origin.
+const OFFSET_ULPS: f64 = (1 << 12) as f64;
+
+/// Intersections of a ray with one or more shapes.
+///
+/// Most rays only intersect a handful of shapes, so the first few intersections are stored inline
+/// instead of in a heap allocation.
+///
+pub(crate) type Intersections<'a> = SmallVec<[Intersection<'a>; 4]>;
+
+/// Intersection of a ray with a shape, which borrows the shape it hit.
+///
+/// Shapes are owned by the world, group or instance they're in, so the intersection refers to the
+/// shape itself rather than to it's [ShapeId](crate::shape::ShapeId), which is what identifies the
+/// shape without the borrow.
+///
 #[derive(Copy, Clone, Debug)]
 pub struct Intersection<'a> {
     pub t: f64,
     pub object: &'a Shape,
     pub u: Option<f64>,
     pub v: Option<f64>,
+
+    /// Placement of the [instance](crate::shape::Instance) the object was hit through, if any.
+    pub instance: Option<Placement<'a>>,
 }
 
+/// Placement of a shape hit through one or more [instances](crate::shape::Instance), which share
+/// the shape with every other instance of it.
+#[derive(Copy, Clone, Debug)]
+pub struct Placement<'a> {
+    /// Transform from the space of the world to the space the shape is defined in.
+    pub transform_inverse: Transform,
+
+    /// Material that replaces the one of the shape, if any.
+    pub material: Option<&'a Material>,
+}
+
+// Some of the computed values are not used by the shading routines yet, but they are part of the
+// book's specification and are checked by the tests.
+#[cfg_attr(not(test), allow(dead_code))]
 #[derive(Debug)]
 pub struct Computation<'a> {
     pub eyev: Vector,
     pub inside: bool,
     pub intersection: Intersection<'a>,
+    pub material: Cow<'a, Material>,
     pub n1: f64,
     pub n2: f64,
     pub normalv: Vector,
@@ -30,34 +71,112 @@ pub struct Computation<'a> {
 impl PartialEq for Intersection<'_> {
     fn eq(&self, other: &Self) -> bool {
         float::approx(self.t, other.t)
-            && self.object == other.object
+            && self.is_on_same_surface(other)
             && float::approx_some(self.u, other.u)
             && float::approx_some(self.v, other.v)
     }
 }
 
 impl<'a> Intersection<'a> {
+    /// Computes the values used to shade the intersection, with the points over and under the
+    /// surface placed at the default [EPSILON](float::EPSILON) from it, which suits worlds
+    /// modeled in meters.
+    //
+    // The renderer always follows the units of the world, but the book's specification of the
+    // shading routines is checked with this offset.
+    #[cfg_attr(not(test), allow(dead_code))]
     pub fn prepare_computation<T>(self, ray: &Ray, intersections: T) -> Computation<'a>
     where
         T: IntoIterator<Item = Intersection<'a>>,
+    {
+        self.prepare_computation_with_offset(ray, intersections, float::EPSILON)
+    }
+
+    /// Computes the values used to shade the intersection, with the points over and under the
+    /// surface placed at least at `offset` from it, so the offset can follow the
+    /// [units](crate::world::UnitScale) of the world. Points far from the origin are placed
+    /// further away, as described in [offset_point].
+    pub fn prepare_computation_with_offset<T>(
+        self,
+        ray: &Ray,
+        intersections: T,
+        offset: f64,
+    ) -> Computation<'a>
+    where
+        T: IntoIterator<Item = Intersection<'a>>,
+    {
+        self.prepare(ray, intersections, offset, |material| {
+            material.index_of_refraction
+        })
+    }
+
+    /// Computes the values used to shade the intersection for light of the given wavelength, in
+    /// nanometers, which changes the indices of refraction of dispersive materials.
+    pub(crate) fn prepare_computation_at_wavelength<T>(
+        self,
+        ray: &Ray,
+        intersections: T,
+        offset: f64,
+        wavelength: f64,
+    ) -> Computation<'a>
+    where
+        T: IntoIterator<Item = Intersection<'a>>,
+    {
+        self.prepare(ray, intersections, offset, |material| {
+            material.index_of_refraction_at(wavelength)
+        })
+    }
+
+    fn prepare<T, F>(
+        self,
+        ray: &Ray,
+        intersections: T,
+        offset: f64,
+        index_of_refraction: F,
+    ) -> Computation<'a>
+    where
+        T: IntoIterator<Item = Intersection<'a>>,
+        F: Fn(&Material) -> f64,
     {
         let point = ray.position(self.t);
         let eyev = -ray.direction;
 
-        let normalv = self.object.normal_at(point, &self);
+        let normalv = self.normal_at(point, ray);
         let inside = normalv.dot(eyev) < 0.0;
         let normalv = if inside { -normalv } else { normalv };
         let reflectv = ray.direction.reflect(normalv);
 
-        let over_point = point + normalv * float::EPSILON;
-        let under_point = point - normalv * float::EPSILON;
+        let over_point = offset_point(point, normalv, offset);
+        let under_point = offset_point(point, -normalv, offset);
+
+        let (n1, n2) = self.find_n1_and_n2(intersections, index_of_refraction);
 
-        let (n1, n2) = self.find_n1_and_n2(intersections);
+        // Layered materials are resolved once per hit so the shading routines only deal with a
+        // single set of material properties.
+        let distance = (point - ray.origin).magnitude();
+        let material = self
+            .material()
+            .resolve_at(self.object, self.object_point(point), distance);
+
+        // Billboards are textured by the coordinates of the hit, because the same point of a
+        // billboard shows a different part of it's texture depending on where it's seen from.
+        // Triangles use them to blend the colors and texture coordinates of their vertices.
+        let material = match (self.object, self.u, self.v) {
+            (Shape::Billboard(billboard), Some(u), Some(v)) => {
+                billboard.material_at(material, (u, v))
+            }
+            (Shape::Triangle(triangle), Some(u), Some(v)) => triangle.material_at(material, (u, v)),
+            (Shape::SmoothTriangle(smooth), Some(u), Some(v)) => {
+                smooth.triangle.material_at(material, (u, v))
+            }
+            _ => material,
+        };
 
         Computation {
             eyev,
             inside,
             intersection: self,
+            material,
             n1,
             n2,
             normalv,
@@ -68,31 +187,32 @@ impl<'a> Intersection<'a> {
         }
     }
 
-    fn find_n1_and_n2<T>(&self, intersections: T) -> (f64, f64)
+    fn find_n1_and_n2<T, F>(&self, intersections: T, index_of_refraction: F) -> (f64, f64)
     where
         T: IntoIterator<Item = Intersection<'a>>,
+        F: Fn(&Material) -> f64,
     {
         let (mut n1, mut n2) = (1.0, 1.0);
-        let mut visited: Vec<&Shape> = vec![];
+        let mut visited: SmallVec<[Intersection<'a>; 4]> = SmallVec::new();
 
         let hit = Some(self);
 
         for i in intersections {
             if Some(&i) == hit {
-                if let Some(object) = visited.last() {
-                    n1 = object.as_ref().material.index_of_refraction;
+                if let Some(entered) = visited.last() {
+                    n1 = index_of_refraction(entered.material());
                 }
             }
 
-            if let Some(index) = visited.iter().position(|s| s == &i.object) {
+            if let Some(index) = visited.iter().position(|v| v.is_on_same_surface(&i)) {
                 visited.remove(index);
             } else {
-                visited.push(i.object);
+                visited.push(i);
             }
 
             if Some(&i) == hit {
-                if let Some(object) = visited.last() {
-                    n2 = object.as_ref().material.index_of_refraction;
+                if let Some(entered) = visited.last() {
+                    n2 = index_of_refraction(entered.material());
                 }
 
                 break;
@@ -102,6 +222,55 @@ impl<'a> Intersection<'a> {
         (n1, n2)
     }
 
+    /// Material of the intersected shape, or the one that replaces it in the instance it was hit
+    /// through.
+    pub(crate) fn material(&self) -> &'a Material {
+        self.instance
+            .and_then(|placement| placement.material)
+            .unwrap_or(&self.object.as_ref().material)
+    }
+
+    /// Normal of the intersected shape at a point in world space, hit by `ray`.
+    pub(crate) fn normal_at(&self, point: Point, ray: &Ray) -> Vector {
+        let Some(placement) = self.instance else {
+            return self.object.normal_at(point, self, ray);
+        };
+
+        // The shape only knows it's normals in the space it's defined in, which is moved into
+        // the world by the instance.
+        let transform_inverse = placement.transform_inverse;
+        let normal = self.object.normal_at(
+            transform_inverse * point,
+            self,
+            &ray.transform(transform_inverse),
+        );
+
+        let mut world_normal = transform_inverse.transpose() * normal;
+        world_normal.0.w = 0.0;
+
+        // The transform of an instance is always invertible, so a normal of the surface is never
+        // null in world space.
+        #[allow(clippy::unwrap_used)]
+        world_normal.normalize().unwrap()
+    }
+
+    /// Point in world space moved into the space the intersected shape is defined in, which is
+    /// the same point unless the shape was hit through an instance.
+    fn object_point(&self, point: Point) -> Point {
+        self.instance
+            .map_or(point, |placement| placement.transform_inverse * point)
+    }
+
+    /// Whether both intersections are on the surface of the same shape, placed by the same
+    /// instance.
+    fn is_on_same_surface(&self, other: &Self) -> bool {
+        self.object.id() == other.object.id()
+            && match (self.instance, other.instance) {
+                (Some(a), Some(b)) => a.transform_inverse == b.transform_inverse,
+                (a, b) => a.is_none() && b.is_none(),
+            }
+    }
+
     pub fn sort(intersections: &mut [Intersection<'_>]) {
         intersections.sort_unstable_by(|i1, i2| {
             if float::approx(i1.t, i2.t) {
@@ -141,12 +310,32 @@ impl<'a> Computation<'a> {
     }
 }
 
+/// Moves a point of a surface along a normal, so the rays that start there don't hit that surface
+/// again because of rounding errors.
+///
+/// The point is moved by `offset`, or by a number of units in the last place of it's largest
+/// coordinate when that's further. The rounding error of a hit grows with the magnitude of it's
+/// coordinates, so a constant offset that is big enough near the origin causes acne on surfaces
+/// far away from it, while this one doesn't need to be tuned for each world. This follows the
+/// method of "A Fast and Robust Method for Avoiding Self-Intersection" from Ray Tracing Gems,
+/// scaled by the largest coordinate instead of each one, because every coordinate of a hit
+/// carries the error of the distance along the ray.
+///
+pub(crate) fn offset_point(point: Point, normal: Vector, offset: f64) -> Point {
+    let Point(Tuple { x, y, z, .. }) = point;
+    let magnitude = x.abs().max(y.abs()).max(z.abs());
+
+    point + normal * offset.max(magnitude * f64::EPSILON * OFFSET_ULPS)
+}
+
 #[cfg(test)]
 mod tests {
+    use std::sync::Arc;
+
     use crate::{
         assert_approx,
         material::Material,
-        shape::{ShapeBuilder, Sphere},
+        shape::{Instance, InstanceBuilder, ShapeBuilder, Sphere},
         transform::Transform,
     };
 
@@ -176,6 +365,7 @@ mod tests {
             object: &o,
             u: None,
             v: None,
+            instance: None,
         };
 
         assert_approx!(i.t, 3.5);
@@ -191,15 +381,17 @@ mod tests {
             object: &o,
             u: None,
             v: None,
+            instance: None,
         };
         let i1 = Intersection {
             t: 2.0,
             object: &o,
             u: None,
             v: None,
+            instance: None,
         };
 
-        let xs = vec![&i0, &i1];
+        let xs = [&i0, &i1];
 
         assert_eq!(xs.len(), 2);
         assert_approx!(xs[0].t, 1.0);
@@ -215,12 +407,14 @@ mod tests {
             object: &o,
             u: None,
             v: None,
+            instance: None,
         };
         let i1 = Intersection {
             t: 2.0,
             object: &o,
             u: None,
             v: None,
+            instance: None,
         };
 
         let mut xs = [i0, i1];
@@ -237,12 +431,14 @@ mod tests {
             object: &o,
             u: None,
             v: None,
+            instance: None,
         };
         let i1 = Intersection {
             t: 1.0,
             object: &o,
             u: None,
             v: None,
+            instance: None,
         };
 
         let mut xs = [i0, i1];
@@ -259,12 +455,14 @@ mod tests {
             object: &o,
             u: None,
             v: None,
+            instance: None,
         };
         let i1 = Intersection {
             t: -1.0,
             object: &o,
             u: None,
             v: None,
+            instance: None,
         };
 
         let mut xs = [i0, i1];
@@ -281,24 +479,28 @@ mod tests {
             object: &o,
             u: None,
             v: None,
+            instance: None,
         };
         let i1 = Intersection {
             t: 7.0,
             object: &o,
             u: None,
             v: None,
+            instance: None,
         };
         let i2 = Intersection {
             t: -3.0,
             object: &o,
             u: None,
             v: None,
+            instance: None,
         };
         let i3 = Intersection {
             t: 2.0,
             object: &o,
             u: None,
             v: None,
+            instance: None,
         };
 
         let mut xs = [i0, i1, i2, i3];
@@ -320,24 +522,28 @@ mod tests {
             object: &o,
             u: None,
             v: None,
+            instance: None,
         };
         let i1 = Intersection {
             t: 7.0,
             object: &o,
             u: None,
             v: None,
+            instance: None,
         };
         let i2 = Intersection {
             t: -3.0,
             object: &o,
             u: None,
             v: None,
+            instance: None,
         };
         let i3 = Intersection {
             t: 2.0,
             object: &o,
             u: None,
             v: None,
+            instance: None,
         };
 
         let mut xs = [i0, i1, i2, i3];
@@ -359,6 +565,7 @@ mod tests {
             object: &o,
             u: None,
             v: None,
+            instance: None,
         };
 
         let comps = i.prepare_computation(&r, [i]);
@@ -384,6 +591,7 @@ mod tests {
             object: &o,
             u: None,
             v: None,
+            instance: None,
         };
 
         let comps = i.prepare_computation(&r, [i]);
@@ -405,6 +613,7 @@ mod tests {
             object: &o,
             u: None,
             v: None,
+            instance: None,
         };
 
         let comps = i.prepare_computation(&r, [i]);
@@ -432,6 +641,7 @@ mod tests {
             object: &o,
             u: None,
             v: None,
+            instance: None,
         };
 
         let comps = i.prepare_computation(&r, [i]);
@@ -454,6 +664,7 @@ mod tests {
             object: &o,
             u: None,
             v: None,
+            instance: None,
         };
 
         let comps = i.prepare_computation(&r, [i]);
@@ -495,62 +706,161 @@ mod tests {
             object: &a,
             u: None,
             v: None,
+            instance: None,
         };
         let i1 = Intersection {
             t: 2.75,
             object: &b,
             u: None,
             v: None,
+            instance: None,
         };
         let i2 = Intersection {
             t: 3.25,
             object: &c,
             u: None,
             v: None,
+            instance: None,
         };
         let i3 = Intersection {
             t: 4.75,
             object: &b,
             u: None,
             v: None,
+            instance: None,
         };
         let i4 = Intersection {
             t: 5.25,
             object: &c,
             u: None,
             v: None,
+            instance: None,
         };
         let i5 = Intersection {
             t: 6.0,
             object: &a,
             u: None,
             v: None,
+            instance: None,
         };
 
         let xs = [i0, i1, i2, i3, i4, i5];
+        let ior = |material: &Material| material.index_of_refraction;
 
-        let (n1, n2) = i0.find_n1_and_n2(xs);
+        let (n1, n2) = i0.find_n1_and_n2(xs, ior);
         assert_approx!(n1, 1.0);
         assert_approx!(n2, 1.5);
 
-        let (n1, n2) = i1.find_n1_and_n2(xs);
+        let (n1, n2) = i1.find_n1_and_n2(xs, ior);
         assert_approx!(n1, 1.5);
         assert_approx!(n2, 2.0);
 
-        let (n1, n2) = i2.find_n1_and_n2(xs);
+        let (n1, n2) = i2.find_n1_and_n2(xs, ior);
         assert_approx!(n1, 2.0);
         assert_approx!(n2, 2.5);
 
-        let (n1, n2) = i3.find_n1_and_n2(xs);
+        let (n1, n2) = i3.find_n1_and_n2(xs, ior);
         assert_approx!(n1, 2.5);
         assert_approx!(n2, 2.5);
 
-        let (n1, n2) = i4.find_n1_and_n2(xs);
+        let (n1, n2) = i4.find_n1_and_n2(xs, ior);
         assert_approx!(n1, 2.5);
         assert_approx!(n2, 1.5);
 
-        let (n1, n2) = i5.find_n1_and_n2(xs);
+        let (n1, n2) = i5.find_n1_and_n2(xs, ior);
+        assert_approx!(n1, 1.5);
+        assert_approx!(n2, 1.0);
+    }
+
+    #[test]
+    fn finding_n1_and_n2_between_overlapping_clones_of_a_shape() {
+        let a = Shape::Sphere(Sphere::from(ShapeBuilder {
+            material: Material {
+                index_of_refraction: 1.5,
+                ..glass_material()
+            },
+            transform: Transform::translation(0.0, 0.0, -0.25),
+        }));
+
+        let mut b = a.clone();
+        b.as_mut().material.index_of_refraction = 2.0;
+
+        assert_ne!(a.id(), b.id());
+
+        let intersection = |t, object| Intersection {
+            t,
+            object,
+            u: None,
+            v: None,
+            instance: None,
+        };
+
+        let xs = [
+            intersection(1.0, &a),
+            intersection(1.5, &b),
+            intersection(3.0, &a),
+            intersection(3.5, &b),
+        ];
+        let ior = |material: &Material| material.index_of_refraction;
+
+        let (n1, n2) = xs[1].find_n1_and_n2(xs, ior);
         assert_approx!(n1, 1.5);
+        assert_approx!(n2, 2.0);
+
+        let (n1, n2) = xs[2].find_n1_and_n2(xs, ior);
+        assert_approx!(n1, 2.0);
+        assert_approx!(n2, 2.0);
+
+        let (n1, n2) = xs[3].find_n1_and_n2(xs, ior);
+        assert_approx!(n1, 2.0);
+        assert_approx!(n2, 1.0);
+    }
+
+    #[test]
+    fn finding_n1_and_n2_between_overlapping_instances_of_a_shape() {
+        let sphere = Arc::new(glass_sphere());
+
+        let instance = |z, material| {
+            Shape::Instance(Instance::from(InstanceBuilder {
+                prototype: Arc::clone(&sphere),
+                transform: Transform::translation(0.0, 0.0, z),
+                material,
+            }))
+        };
+
+        let a = instance(-0.25, None);
+        let b = instance(
+            0.25,
+            Some(Material {
+                index_of_refraction: 2.0,
+                ..glass_material()
+            }),
+        );
+
+        let ray = Ray {
+            origin: Point::new(0.0, 0.0, -5.0),
+            direction: Vector::new(0.0, 0.0, 1.0),
+        };
+
+        let mut xs: Intersections<'_> = a.intersect(&ray);
+        xs.extend(b.intersect(&ray));
+        Intersection::sort(&mut xs);
+
+        // Every hit is on the same shape, so they're told apart by the instance they belong to.
+        assert!(xs.iter().all(|i| i.object.id() == sphere.id()));
+
+        let ior = |material: &Material| material.index_of_refraction;
+
+        let (n1, n2) = xs[1].find_n1_and_n2(xs.clone(), ior);
+        assert_approx!(n1, 1.5);
+        assert_approx!(n2, 2.0);
+
+        let (n1, n2) = xs[2].find_n1_and_n2(xs.clone(), ior);
+        assert_approx!(n1, 2.0);
+        assert_approx!(n2, 2.0);
+
+        let (n1, n2) = xs[3].find_n1_and_n2(xs.clone(), ior);
+        assert_approx!(n1, 2.0);
         assert_approx!(n2, 1.0);
     }
 
@@ -571,6 +881,7 @@ mod tests {
             object: &o,
             u: None,
             v: None,
+            instance: None,
         };
 
         let comps = i.prepare_computation(&r, [i]);
@@ -597,12 +908,14 @@ mod tests {
                 object: &o,
                 u: None,
                 v: None,
+                instance: None,
             },
             Intersection {
                 t: 2_f64.sqrt() / 2.0,
                 object: &o,
                 u: None,
                 v: None,
+                instance: None,
             },
         ];
 
@@ -628,12 +941,14 @@ mod tests {
                 object: &s,
                 u: None,
                 v: None,
+                instance: None,
             },
             Intersection {
                 t: 1.0,
                 object: &s,
                 u: None,
                 v: None,
+                instance: None,
             },
         ];
 
@@ -658,6 +973,7 @@ mod tests {
             object: &s,
             u: None,
             v: None,
+            instance: None,
         }];
 
         let comps = xs[0].prepare_computation(&r, xs);
@@ -666,4 +982,83 @@ mod tests {
 
         assert_approx!(reflectance, 0.48873);
     }
+
+    #[test]
+    fn intersections_with_a_few_shapes_are_not_heap_allocated() {
+        let s = Shape::Sphere(Default::default());
+
+        let r = Ray {
+            origin: Point::new(0.0, 0.0, -5.0),
+            direction: Vector::new(0.0, 0.0, 1.0),
+        };
+
+        let mut xs = s.intersect(&r);
+        xs.extend(s.intersect(&r));
+
+        assert_eq!(xs.len(), 4);
+        assert!(!xs.spilled());
+    }
+
+    #[test]
+    fn points_near_the_origin_are_offset_by_the_given_distance() {
+        let normal = Vector::new(0.0, 1.0, 0.0);
+
+        assert_eq!(
+            offset_point(Point::new(1.0, 2.0, 3.0), normal, 0.5),
+            Point::new(1.0, 2.5, 3.0)
+        );
+    }
+
+    #[test]
+    fn points_far_from_the_origin_are_offset_further() {
+        let normal = Vector::new(0.0, 1.0, 0.0);
+        let point = Point::new(1e9, 0.0, 0.0);
+
+        let offset = (offset_point(point, normal, 1e-5) - point).magnitude();
+
+        assert!(offset > 1e-4);
+        assert!(offset < 1e-2);
+    }
+
+    #[test]
+    fn rays_leaving_surfaces_far_from_the_origin_do_not_hit_them_again() {
+        use crate::{shape::Plane, world::World};
+
+        let far = 1e6;
+
+        let world = World {
+            objects: vec![Shape::Plane(Plane::from(ShapeBuilder {
+                transform: Transform::translation(far, far, far)
+                    * Transform::rotation_x(0.3)
+                    * Transform::rotation_z(0.2),
+                ..Default::default()
+            }))],
+            ..Default::default()
+        };
+
+        for i in 0..100 {
+            let angle = f64::from(i) * 0.618;
+
+            let origin = Point::new(
+                far + angle.sin() * 1e4,
+                far + 2e4,
+                far + angle.cos() * 1e4 - 1e5,
+            );
+            let target = Point::new(far + (angle * 3.1).sin() * 500.0, far, far);
+
+            let r = Ray {
+                origin,
+                direction: (target - origin).normalize().unwrap(),
+            };
+
+            let mut xs = world.intersect(&r);
+            let hit = Intersection::hit(&mut xs).unwrap();
+
+            // An offset that would be far too small for a constant offset this far away.
+            let comps = hit.prepare_computation_with_offset(&r, xs.clone(), 1e-12);
+            let light = comps.over_point + comps.normalv * far;
+
+            assert!(!world.is_shadowed(light, comps.over_point));
+        }
+    }
 }
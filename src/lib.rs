@@ -1,4 +1,6 @@
 #![deny(rustdoc::broken_intra_doc_links)]
+// Test fixtures use the rounded values printed in the book.
+#![cfg_attr(test, allow(clippy::approx_constant))]
 
 //! Stochastic ray tracer based on The Ray Tracer Challenge book by Jamis Buck.
 
@@ -6,7 +8,10 @@ mod canvas;
 mod float;
 mod intersection;
 mod matrix;
-mod ray;
+mod progress;
+
+/// Animations of the objects of a world.
+pub mod animation;
 
 /// Camera module.
 pub mod camera;
@@ -14,6 +19,15 @@ pub mod camera;
 /// Colors module.
 pub mod color;
 
+/// Defaults of the renderer that can be overridden without changing any code.
+pub mod config;
+
+/// Showcase scenes built in code.
+pub mod examples;
+
+/// Lighting algorithms used to render a world.
+pub mod integrator;
+
 /// Light sources for a world.
 pub mod light;
 
@@ -26,12 +40,30 @@ pub mod model;
 /// Patterns for materials.
 pub mod pattern;
 
+/// Rays casted into a world.
+pub mod ray;
+
+/// Settings for the rendering process.
+pub mod render;
+
+/// Sequences of samples used to spread rays over an area.
+pub mod sampling;
+
+/// Scenes described in JSON.
+pub mod scene;
+
 /// Geometric shapes module.
 pub mod shape;
 
+/// Reference scenes to check that the ray tracer still renders it's core features correctly.
+pub mod testing;
+
 /// Linear transformations for shapes.
 pub mod transform;
 
+/// Spectral distributions of light, used by spectral renders.
+pub mod spectrum;
+
 /// Tuples module.
 pub mod tuple;
 
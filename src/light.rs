@@ -1,20 +1,79 @@
-use rand::Rng;
+use std::cell::RefCell;
+
+use serde::Deserialize;
+use thiserror::Error;
 
 use crate::{
     color::Color,
+    sampling::Sampler,
+    scene::{Checked, UnknownFields},
     tuple::{Point, Vector},
-    world::World,
+    world::{UnitScale, World},
 };
 
+mod dome;
+
+pub use self::dome::{DomeLight, DomeLightBuilder, DomeSource};
+
+use self::dome::DomeLightDeserializer;
+
+/// The error type when trying to deserialize a light.
+#[derive(Debug, PartialEq, Error)]
+pub enum Error {
+    /// The error type when trying to deserialize an area light without cells in any of it's
+    /// directions.
+    #[error("area light must have at least one cell in each direction")]
+    NullCells,
+
+    /// The error type when trying to make lights fall off from a distance that is not a positive
+    /// number.
+    #[error("light falloff must start at a positive distance, got {0} meters")]
+    NonPositiveFalloffDistance(f64),
+}
+
 /// A world's light source.
 ///
 /// Light are used to illumite objects in the world.
 ///
-#[derive(Copy, Clone, Debug, PartialEq)]
+/// # Deserialization
+///
+/// Lights are deserialized from an object with a `type` field, either `"point"` or `"area"`,
+/// and the fields of [PointLight] or [AreaLightBuilder] respectively. Area lights also accept an
+/// optional `sampler` field, with the name of the [Sampler] used to jitter their cells.
+///
+/// Every light also accepts an optional `group`, with the name of the [light
+/// group](Light::group) it's rendered to.
+///
+/// Dome lights have a `"dome"` type and a `source`, which is either an `"image"` with the fields
+/// of an [ImageTexture](crate::pattern::ImageTexture), or a `"gradient"` with a `top` and a
+/// `bottom` color. They also accept an optional `intensity`, which is white by default, and an
+/// optional number of `samples`, which is 64 by default.
+///
+/// ```json
+/// {
+///     "type": "dome",
+///     "source": { "type": "image", "path": "studio.exr" },
+///     "samples": 128
+/// }
+/// ```
+///
+/// ```json
+/// {
+///     "type": "point",
+///     "position": { "x": -10.0, "y": 10.0, "z": -10.0 },
+///     "intensity": { "red": 255, "green": 255, "blue": 255 }
+/// }
+/// ```
+///
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(try_from = "LightDeserializer")]
 pub enum Light {
     /// An area light.
     Area(AreaLight),
 
+    /// A dome light.
+    Dome(DomeLight),
+
     /// A point light.
     Point(PointLight),
 }
@@ -35,16 +94,21 @@ pub enum Light {
 /// let light = Light::Point(PointLight {
 ///     position: Point::new(1.0, 1.0, 1.0),
 ///     intensity: color::consts::WHITE,
+///     group: None,
 /// });
 /// ```
 ///
-#[derive(Copy, Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, Deserialize)]
 pub struct PointLight {
     /// Position of the light.
     pub position: Point,
 
     /// Color of the light.
     pub intensity: Color,
+
+    /// Name of the [light group](Light::group) the light is rendered to, if any.
+    #[serde(default)]
+    pub group: Option<String>,
 }
 
 /// A rectangular grid of lights.
@@ -77,7 +141,7 @@ pub struct PointLight {
 /// }));
 /// ```
 ///
-#[derive(Copy, Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct AreaLight {
     corner: Point,
     uvec: Vector,
@@ -86,10 +150,12 @@ pub struct AreaLight {
     vsteps: usize,
     pub(crate) samples: usize,
     intensity: Color,
+    sampler: Sampler,
+    group: Option<String>,
 }
 
 /// Builder for an area light.
-#[derive(Copy, Clone, Debug, PartialEq)]
+#[derive(Copy, Clone, Debug, PartialEq, Deserialize)]
 pub struct AreaLightBuilder {
     /// Position of the bottom-left corner of the rectangular area light.
     pub corner: Point,
@@ -114,6 +180,165 @@ pub struct AreaLightBuilder {
     pub intensity: Color,
 }
 
+/// How the light of point and area lights fades as it travels away from them.
+///
+/// By default lights shine as bright at every distance. A physically based inverse-square
+/// falloff can be declared instead, and since it's measured in meters it gives the same
+/// brightness to a world modeled in any [units](crate::world::UnitScale).
+///
+/// # Examples
+///
+/// ```
+/// use raytracer::{
+///     light::{Error, LightFalloff},
+///     world::{UnitScale, World},
+/// };
+///
+/// // Lights are as bright as their color two meters away from them, or 2000 units away in a
+/// // world modeled in millimeters.
+/// let world = World {
+///     light_falloff: LightFalloff::inverse_square(2.0).unwrap(),
+///     units: UnitScale::MILLIMETERS,
+///     ..Default::default()
+/// };
+///
+/// assert_eq!(
+///     LightFalloff::inverse_square(0.0),
+///     Err(Error::NonPositiveFalloffDistance(0.0))
+/// );
+/// ```
+///
+/// # Deserialization
+///
+/// The falloff is deserialized from an object with a `type` field, either `"none"` or
+/// `"inverse_square"`, the latter with the `reference_distance` in meters at which lights are
+/// as bright as their color.
+///
+/// ```json
+/// { "light_falloff": { "type": "inverse_square", "reference_distance": 2.0 } }
+/// ```
+///
+#[derive(Copy, Clone, Debug, Default, PartialEq, Deserialize)]
+#[serde(try_from = "LightFalloffDeserializer")]
+pub enum LightFalloff {
+    /// Lights shine as bright at every distance.
+    #[default]
+    None,
+
+    /// Lights shine as bright as their color at a reference distance in meters, and their
+    /// brightness changes with the inverse of the square of the distance.
+    InverseSquare(f64),
+}
+
+#[derive(Debug, PartialEq, Deserialize)]
+#[serde(rename_all(deserialize = "snake_case"))]
+#[serde(tag = "type")]
+enum LightFalloffDeserializer {
+    None(UnknownFields),
+    InverseSquare(InverseSquareDeserializer),
+}
+
+#[derive(Debug, PartialEq, Deserialize)]
+struct InverseSquareDeserializer {
+    reference_distance: f64,
+
+    #[serde(flatten)]
+    _unknown: UnknownFields,
+}
+
+impl TryFrom<LightFalloffDeserializer> for LightFalloff {
+    type Error = Error;
+
+    fn try_from(value: LightFalloffDeserializer) -> Result<Self, Self::Error> {
+        match value {
+            LightFalloffDeserializer::None(_) => Ok(Self::None),
+            LightFalloffDeserializer::InverseSquare(falloff) => {
+                Self::inverse_square(falloff.reference_distance)
+            }
+        }
+    }
+}
+
+impl LightFalloff {
+    /// Constructs an inverse-square falloff, where lights are as bright as their color at a
+    /// given distance in meters.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the distance is not a positive finite number.
+    ///
+    pub fn inverse_square(reference_distance: f64) -> Result<Self, Error> {
+        if reference_distance.is_finite() && reference_distance > 0.0 {
+            Ok(Self::InverseSquare(reference_distance))
+        } else {
+            Err(Error::NonPositiveFalloffDistance(reference_distance))
+        }
+    }
+
+    /// Factor by which the light is scaled after travelling `distance` units of a world.
+    pub(crate) fn attenuation(&self, distance: f64, units: UnitScale) -> f64 {
+        match *self {
+            Self::None => 1.0,
+            Self::InverseSquare(reference_distance) => {
+                let meters = distance * units.meters_per_unit();
+
+                // Surfaces touching the light would get infinitely bright otherwise.
+                (reference_distance / meters.max(f64::EPSILON)).powi(2)
+            }
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Deserialize)]
+#[serde(rename_all(deserialize = "snake_case"))]
+#[serde(tag = "type")]
+enum LightDeserializer {
+    Area(AreaLightDeserializer),
+    Dome(Box<DomeLightDeserializer>),
+    Point(Checked<PointLight>),
+}
+
+#[derive(Debug, PartialEq, Deserialize)]
+struct AreaLightDeserializer {
+    #[serde(flatten)]
+    builder: AreaLightBuilder,
+
+    #[serde(default)]
+    sampler: Sampler,
+
+    #[serde(default)]
+    group: Option<String>,
+
+    #[serde(flatten)]
+    _unknown: UnknownFields,
+}
+
+impl TryFrom<LightDeserializer> for Light {
+    type Error = Error;
+
+    fn try_from(value: LightDeserializer) -> Result<Self, Self::Error> {
+        Ok(match value {
+            LightDeserializer::Area(AreaLightDeserializer {
+                builder,
+                sampler,
+                group,
+                ..
+            }) => {
+                if builder.horizontal_cells == 0 || builder.vertical_cells == 0 {
+                    return Err(Error::NullCells);
+                }
+
+                Self::Area(AreaLight {
+                    group,
+                    ..AreaLight::from(builder).with_sampler(sampler)
+                })
+            }
+            LightDeserializer::Dome(light) => Self::Dome(DomeLight::from(*light)),
+            LightDeserializer::Point(light) => Self::Point(light.inner),
+        })
+    }
+}
+
 impl From<AreaLightBuilder> for AreaLight {
     fn from(builder: AreaLightBuilder) -> Self {
         let AreaLightBuilder {
@@ -139,33 +364,101 @@ impl From<AreaLightBuilder> for AreaLight {
             vsteps,
             samples: usteps * vsteps,
             intensity,
+            sampler: Default::default(),
+            group: None,
         }
     }
 }
 
 impl Light {
-    pub(crate) fn intensity_at(&self, world: &World, point: Point) -> f64 {
+    /// Name of the light group the light is rendered to by
+    /// [Camera::render_light_groups](crate::camera::Camera::render_light_groups), or `None` if
+    /// it's rendered to the [DEFAULT_LIGHT_GROUP](crate::world::DEFAULT_LIGHT_GROUP).
+    ///
+    /// Each light names it's own group, so a light is never in more than one group and the groups
+    /// follow the lights as they're added to or removed from a world.
+    ///
+    pub fn group(&self) -> Option<&str> {
         match self {
-            Self::Area(area_light) => area_light.intensity_at(world, point, || {
-                let mut rng = rand::thread_rng();
-                rng.gen::<u8>() as f64 / 255.0
-            }),
+            Self::Area(area_light) => area_light.group.as_deref(),
+            Self::Dome(dome_light) => dome_light.group.as_deref(),
+            Self::Point(point_light) => point_light.group.as_deref(),
+        }
+    }
+
+    /// Moves the light to the light group named `group`.
+    pub fn with_group(mut self, group: &str) -> Self {
+        let light_group = match &mut self {
+            Self::Area(area_light) => &mut area_light.group,
+            Self::Dome(dome_light) => &mut dome_light.group,
+            Self::Point(point_light) => &mut point_light.group,
+        };
+
+        *light_group = Some(group.to_string());
+        self
+    }
+
+    /// Fraction of the light that reaches `point`, on a surface with normal `normalv`, without
+    /// being blocked by any object.
+    pub(crate) fn intensity_at(&self, world: &World, point: Point, normalv: Vector) -> f64 {
+        match self {
+            Self::Area(area_light) => {
+                // Cells take consecutive samples of the sequence, and each sample gives the
+                // jitter of a cell in both directions.
+                let jitter = RefCell::new(area_light.sampler.sequence(0).flatten());
+
+                area_light.intensity_at(world, point, || jitter.borrow_mut().next().unwrap_or(0.5))
+            }
+            Self::Dome(dome_light) => dome_light.intensity_at(world, point, normalv),
             Self::Point(point_light) => point_light.intensity_at(world, point),
         }
     }
 
-    pub(crate) fn cells(&self) -> Vec<Point> {
+    /// Directions towards each sample of the light from `point`, with the color of the light
+    /// coming from each of them.
+    pub(crate) fn directions_at(&self, point: Point) -> Vec<(Vector, Color)> {
+        let towards = |position: Point| {
+            (position - point)
+                .normalize()
+                .unwrap_or(Vector::new(0.0, 0.0, 0.0))
+        };
+
         match self {
             Self::Area(area_light) => {
-                let mut cells = vec![];
+                let mut directions = vec![];
                 for v in 0..area_light.vsteps {
                     for u in 0..area_light.usteps {
-                        cells.push(area_light.point_on_light(u, v, || 0.5));
+                        let cell = area_light.point_on_light(u, v, || 0.5);
+                        directions.push((towards(cell), area_light.intensity));
                     }
                 }
 
-                cells
+                directions
             }
+            Self::Dome(dome_light) => dome_light.directions().collect(),
+            Self::Point(point_light) => {
+                vec![(towards(point_light.position), point_light.intensity)]
+            }
+        }
+    }
+
+    /// Corners of an area light in order around it, or the position of a point light. Dome lights
+    /// surround the whole world, so they don't have an outline.
+    pub(crate) fn outline(&self) -> Vec<Point> {
+        match self {
+            Self::Area(area_light) => {
+                let width = area_light.uvec * area_light.usteps as f64;
+                let height = area_light.vvec * area_light.vsteps as f64;
+                let corner = area_light.corner;
+
+                vec![
+                    corner,
+                    corner + width,
+                    corner + width + height,
+                    corner + height,
+                ]
+            }
+            Self::Dome(_) => vec![],
             Self::Point(point_light) => vec![point_light.position],
         }
     }
@@ -173,9 +466,25 @@ impl Light {
     pub(crate) fn effective_color(&self) -> Color {
         match self {
             Self::Area(area_light) => area_light.intensity,
+            Self::Dome(dome_light) => dome_light.intensity(),
             Self::Point(point_light) => point_light.intensity,
         }
     }
+
+    /// Copy of the light with a different color.
+    pub(crate) fn with_color(&self, intensity: Color) -> Self {
+        match self {
+            Self::Area(area_light) => Self::Area(AreaLight {
+                intensity,
+                ..area_light.clone()
+            }),
+            Self::Dome(dome_light) => Self::Dome(dome_light.with_intensity(intensity)),
+            Self::Point(point_light) => Self::Point(PointLight {
+                intensity,
+                ..point_light.clone()
+            }),
+        }
+    }
 }
 
 impl PointLight {
@@ -183,12 +492,42 @@ impl PointLight {
         if world.is_shadowed(self.position, point) {
             0.0
         } else {
-            1.0
+            world
+                .light_falloff
+                .attenuation((self.position - point).magnitude(), world.units)
         }
     }
 }
 
 impl AreaLight {
+    /// Changes the sampler used to jitter the position of the light in each of it's cells, which
+    /// takes random samples by default.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use raytracer::{
+    ///     color,
+    ///     light::{AreaLight, AreaLightBuilder},
+    ///     sampling::Sampler,
+    ///     tuple::{Point, Vector},
+    /// };
+    ///
+    /// let light = AreaLight::from(AreaLightBuilder {
+    ///     corner: Point::new(-1.0, 2.0, 4.0),
+    ///     horizontal_dir: Vector::new(2.0, 0.0, 0.0),
+    ///     horizontal_cells: 4,
+    ///     vertical_dir: Vector::new(0.0, 2.0, 0.0),
+    ///     vertical_cells: 4,
+    ///     intensity: color::consts::WHITE,
+    /// })
+    /// .with_sampler(Sampler::Halton);
+    /// ```
+    ///
+    pub fn with_sampler(self, sampler: Sampler) -> Self {
+        Self { sampler, ..self }
+    }
+
     fn intensity_at<F>(&self, world: &World, point: Point, jitter: F) -> f64
     where
         F: Fn() -> f64,
@@ -200,7 +539,9 @@ impl AreaLight {
                 let light_position = self.point_on_light(u, v, &jitter);
 
                 if !world.is_shadowed(light_position, point) {
-                    total += 1.0;
+                    total += world
+                        .light_falloff
+                        .attenuation((light_position - point).magnitude(), world.units);
                 }
             }
         }
@@ -241,6 +582,7 @@ mod tests {
         let light = PointLight {
             position,
             intensity,
+            group: None,
         };
 
         assert_eq!(light.position, position);
@@ -252,14 +594,70 @@ mod tests {
         let w = test_world();
         let light = &w.lights[0];
 
-        assert_approx!(light.intensity_at(&w, Point::new(0.0, 1.0001, 0.0)), 1.0);
-        assert_approx!(light.intensity_at(&w, Point::new(-1.0001, 0.0, 0.0)), 1.0);
-        assert_approx!(light.intensity_at(&w, Point::new(0.0, 0.0, -1.0001)), 1.0);
+        // Point lights shine on surfaces no matter which way they face.
+        let normalv = Vector::new(0.0, 1.0, 0.0);
+
+        assert_approx!(
+            light.intensity_at(&w, Point::new(0.0, 1.0001, 0.0), normalv),
+            1.0
+        );
+        assert_approx!(
+            light.intensity_at(&w, Point::new(-1.0001, 0.0, 0.0), normalv),
+            1.0
+        );
+        assert_approx!(
+            light.intensity_at(&w, Point::new(0.0, 0.0, -1.0001), normalv),
+            1.0
+        );
+
+        assert_approx!(
+            light.intensity_at(&w, Point::new(0.0, 0.0, 1.0001), normalv),
+            0.0
+        );
+        assert_approx!(
+            light.intensity_at(&w, Point::new(1.0001, 0.0, 0.0), normalv),
+            0.0
+        );
+        assert_approx!(
+            light.intensity_at(&w, Point::new(0.0, -1.0001, 0.0), normalv),
+            0.0
+        );
+        assert_approx!(
+            light.intensity_at(&w, Point::new(0.0, 0.0, 0.0), normalv),
+            0.0
+        );
+    }
+
+    #[test]
+    fn the_light_of_a_point_light_falls_off_with_the_distance_in_meters() {
+        let light = Light::Point(PointLight {
+            position: Point::new(0.0, 0.0, 0.0),
+            intensity: color::consts::WHITE,
+            group: None,
+        });
+        let normalv = Vector::new(0.0, 1.0, 0.0);
+
+        let mut w = World {
+            light_falloff: LightFalloff::inverse_square(2.0).unwrap(),
+            ..Default::default()
+        };
+
+        assert_approx!(
+            light.intensity_at(&w, Point::new(0.0, 2.0, 0.0), normalv),
+            1.0
+        );
+        assert_approx!(
+            light.intensity_at(&w, Point::new(0.0, 4.0, 0.0), normalv),
+            0.25
+        );
 
-        assert_approx!(light.intensity_at(&w, Point::new(0.0, 0.0, 1.0001)), 0.0);
-        assert_approx!(light.intensity_at(&w, Point::new(1.0001, 0.0, 0.0)), 0.0);
-        assert_approx!(light.intensity_at(&w, Point::new(0.0, -1.0001, 0.0)), 0.0);
-        assert_approx!(light.intensity_at(&w, Point::new(0.0, 0.0, 0.0)), 0.0);
+        // The same distance in millimeters is a thousand times closer to the light.
+        w.units = UnitScale::MILLIMETERS;
+
+        assert_approx!(
+            light.intensity_at(&w, Point::new(0.0, 2000.0, 0.0), normalv),
+            1.0
+        );
     }
 
     #[test]
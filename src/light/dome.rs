@@ -0,0 +1,469 @@
+use std::{f64::consts::PI, sync::Arc};
+
+use serde::Deserialize;
+
+use crate::{
+    color::{self, Color},
+    pattern::ImageTexture,
+    scene::{Checked, UnknownFields},
+    tuple::{Point, Vector},
+    world::World,
+};
+
+/// Distance to the dome of a dome light. The dome is infinitely far away from the scene, but
+/// shadow rays need to end somewhere.
+const DOME_DISTANCE: f64 = 1.0e6;
+
+/// Largest grid of directions that an image is sampled on, so larger images are sampled on every
+/// few pixels.
+const MAX_IMAGE_GRID: (usize, usize) = (512, 256);
+
+/// Grid of directions that a gradient is sampled on.
+const GRADIENT_GRID: (usize, usize) = (64, 32);
+
+/// Number of directions that a dome light is sampled on by default.
+const DEFAULT_SAMPLES: usize = 64;
+
+/// A light that surrounds the whole world, like the sky or a studio HDRI.
+///
+/// Dome lights illuminate objects from every direction at once. Their light is sampled on a fixed
+/// set of directions, chosen with more of them towards the brightest parts of the dome, so a small
+/// but bright sun in an image gets most of the samples and casts a clear shadow.
+///
+/// A dome light only lights the objects of a world, rays that don't hit any object are still
+/// black.
+///
+/// # Examples
+///
+/// A dome light must be built from a [DomeLightBuilder].
+///
+/// ```
+/// use raytracer::{
+///     color,
+///     light::{DomeLight, DomeLightBuilder, DomeSource, Light},
+/// };
+///
+/// // Sky that fades from light blue above the horizon to dirt below it.
+/// let light = Light::Dome(DomeLight::from(DomeLightBuilder {
+///     source: DomeSource::Gradient {
+///         top: color::consts::LIGHT_SKY_BLUE,
+///         bottom: color::consts::DIRT,
+///     },
+///     intensity: color::consts::WHITE,
+///     samples: 32,
+/// }));
+/// ```
+///
+#[derive(Clone, Debug, PartialEq)]
+pub struct DomeLight {
+    source: DomeSource,
+    intensity: Color,
+    samples: Arc<[DomeSample]>,
+    pub(super) group: Option<String>,
+}
+
+/// Colors of a dome light in each direction.
+#[derive(Clone, Debug, PartialEq)]
+pub enum DomeSource {
+    /// An image that wraps around the world with spherical mapping, such as an HDRI.
+    Image(Arc<ImageTexture>),
+
+    /// A gradient from the color right above the world to the color right below it.
+    Gradient {
+        /// Color right above the world.
+        top: Color,
+
+        /// Color right below the world.
+        bottom: Color,
+    },
+}
+
+/// Builder for a dome light.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DomeLightBuilder {
+    /// Colors of the light in each direction.
+    pub source: DomeSource,
+
+    /// Color that the colors of the source are multiplied by.
+    pub intensity: Color,
+
+    /// Number of directions the light is sampled on.
+    pub samples: usize,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct DomeSample {
+    direction: Vector,
+    color: Color,
+}
+
+#[derive(Debug, PartialEq, Deserialize)]
+pub(super) struct DomeLightDeserializer {
+    source: DomeSourceDeserializer,
+
+    #[serde(default = "default_intensity")]
+    intensity: Color,
+
+    #[serde(default = "default_samples")]
+    samples: usize,
+
+    #[serde(default)]
+    group: Option<String>,
+
+    #[serde(flatten)]
+    _unknown: UnknownFields,
+}
+
+#[derive(Debug, PartialEq, Deserialize)]
+#[serde(rename_all(deserialize = "snake_case"))]
+#[serde(tag = "type")]
+enum DomeSourceDeserializer {
+    Image(Box<ImageTexture>),
+    Gradient(Checked<GradientDeserializer>),
+}
+
+#[derive(Debug, PartialEq, Deserialize)]
+struct GradientDeserializer {
+    top: Color,
+    bottom: Color,
+}
+
+fn default_intensity() -> Color {
+    color::consts::WHITE
+}
+
+fn default_samples() -> usize {
+    DEFAULT_SAMPLES
+}
+
+impl From<DomeLightDeserializer> for DomeLight {
+    fn from(value: DomeLightDeserializer) -> Self {
+        let source = match value.source {
+            DomeSourceDeserializer::Image(image) => DomeSource::Image(Arc::new(*image)),
+            DomeSourceDeserializer::Gradient(gradient) => DomeSource::Gradient {
+                top: gradient.inner.top,
+                bottom: gradient.inner.bottom,
+            },
+        };
+
+        Self {
+            group: value.group,
+            ..Self::from(DomeLightBuilder {
+                source,
+                intensity: value.intensity,
+                samples: value.samples,
+            })
+        }
+    }
+}
+
+impl From<DomeLightBuilder> for DomeLight {
+    fn from(builder: DomeLightBuilder) -> Self {
+        let samples = builder.source.importance_samples(builder.samples);
+
+        Self {
+            source: builder.source,
+            intensity: builder.intensity,
+            samples: samples.into(),
+            group: None,
+        }
+    }
+}
+
+impl DomeLight {
+    /// Colors of the light in each direction.
+    pub fn source(&self) -> &DomeSource {
+        &self.source
+    }
+
+    /// Number of directions the light is sampled on, which is zero for a completely black dome.
+    pub fn samples(&self) -> usize {
+        self.samples.len()
+    }
+
+    pub(super) fn intensity(&self) -> Color {
+        self.intensity
+    }
+
+    /// Copy of the light with a different intensity, which keeps the directions it's sampled on.
+    pub(super) fn with_intensity(&self, intensity: Color) -> Self {
+        Self {
+            intensity,
+            ..self.clone()
+        }
+    }
+
+    /// Directions towards the light from any point, with the color of the light coming from each
+    /// of them.
+    pub(super) fn directions(&self) -> impl Iterator<Item = (Vector, Color)> + '_ {
+        self.samples
+            .iter()
+            .map(|sample| (sample.direction, sample.color * self.intensity))
+    }
+
+    /// Fraction of the directions above a surface with normal `normalv` that reach the dome from
+    /// `point` without hitting any object.
+    pub(super) fn intensity_at(&self, world: &World, point: Point, normalv: Vector) -> f64 {
+        let above: Vec<_> = self
+            .samples
+            .iter()
+            .filter(|sample| sample.direction.dot(normalv) > 0.0)
+            .collect();
+
+        if above.is_empty() {
+            return 1.0;
+        }
+
+        let unoccluded = above
+            .iter()
+            .filter(|sample| !world.is_shadowed(point + sample.direction * DOME_DISTANCE, point))
+            .count();
+
+        unoccluded as f64 / above.len() as f64
+    }
+}
+
+impl DomeSource {
+    /// Color of the source at the given `u` and `v` coordinates of a spherical mapping.
+    fn color_at(&self, (u, v): (f64, f64)) -> Color {
+        match self {
+            Self::Image(image) => image.color_at((u, v)),
+            Self::Gradient { top, bottom } => {
+                let t = (direction_at((u, v)).0.y + 1.0) / 2.0;
+                *bottom + (*top - *bottom) * t
+            }
+        }
+    }
+
+    fn grid(&self) -> (usize, usize) {
+        match self {
+            Self::Image(image) => (
+                image.width().clamp(1, MAX_IMAGE_GRID.0),
+                image.height().clamp(1, MAX_IMAGE_GRID.1),
+            ),
+            Self::Gradient { .. } => GRADIENT_GRID,
+        }
+    }
+
+    /// Picks `count` directions on a grid over the source, each with a chance proportional to the
+    /// brightness of it's cell times the solid angle the cell covers.
+    ///
+    /// Directions are picked by spreading evenly spaced samples over the cumulative brightness of
+    /// the cells, so the same source always gives the same directions. The color of each direction
+    /// is divided by the chance of picking it, so a white dome lights a surface facing up with the
+    /// same intensity as a white point light right above it.
+    ///
+    fn importance_samples(&self, count: usize) -> Vec<DomeSample> {
+        let (width, height) = self.grid();
+
+        let cells: Vec<_> = (0..height)
+            .flat_map(|row| (0..width).map(move |column| (column, row)))
+            .map(|(column, row)| {
+                let uv = (
+                    (column as f64 + 0.5) / width as f64,
+                    1.0 - (row as f64 + 0.5) / height as f64,
+                );
+
+                let color = self.color_at(uv);
+                let polar = (1.0 - uv.1) * PI;
+                let solid_angle = polar.sin() * (PI / height as f64) * (2.0 * PI / width as f64);
+
+                (uv, color, luminance(color).max(0.0) * solid_angle)
+            })
+            .collect();
+
+        let cumulative: Vec<f64> = cells
+            .iter()
+            .scan(0.0, |total, &(_, _, weight)| {
+                *total += weight;
+                Some(*total)
+            })
+            .collect();
+
+        let total = cumulative.last().copied().unwrap_or(0.0);
+
+        if total <= 0.0 {
+            return vec![];
+        }
+
+        (0..count)
+            .map(|i| {
+                let target = (i as f64 + 0.5) / count as f64 * total;
+                let cell = cumulative
+                    .partition_point(|&sum| sum < target)
+                    .min(cells.len() - 1);
+
+                let (uv, color, _) = cells[cell];
+
+                DomeSample {
+                    direction: direction_at(uv),
+                    color: color * (total / (luminance(color) * PI)),
+                }
+            })
+            .collect()
+    }
+}
+
+fn luminance(color: Color) -> f64 {
+    0.2126 * color.red + 0.7152 * color.green + 0.0722 * color.blue
+}
+
+/// Direction at the given `u` and `v` coordinates of a spherical mapping, so an image wraps
+/// around a dome light the same way it wraps around a sphere.
+fn direction_at((u, v): (f64, f64)) -> Vector {
+    let azimuth = (0.5 - u) * 2.0 * PI;
+    let polar = (1.0 - v) * PI;
+
+    Vector::new(
+        polar.sin() * azimuth.sin(),
+        polar.cos(),
+        polar.sin() * azimuth.cos(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{DynamicImage, Rgb32FImage};
+
+    use crate::{
+        assert_approx,
+        shape::{Plane, Shape},
+        transform::Transform,
+        world::World,
+    };
+
+    use super::*;
+
+    fn white_dome(samples: usize) -> DomeLight {
+        DomeLight::from(DomeLightBuilder {
+            source: DomeSource::Gradient {
+                top: color::consts::WHITE,
+                bottom: color::consts::WHITE,
+            },
+            intensity: color::consts::WHITE,
+            samples,
+        })
+    }
+
+    #[test]
+    fn directions_follow_spherical_mapping() {
+        assert_eq!(direction_at((0.5, 0.5)), Vector::new(0.0, 0.0, 1.0));
+        assert_eq!(direction_at((0.25, 0.5)), Vector::new(1.0, 0.0, 0.0));
+        assert_eq!(direction_at((0.0, 0.5)), Vector::new(0.0, 0.0, -1.0));
+        assert_eq!(direction_at((0.3, 1.0)), Vector::new(0.0, 1.0, 0.0));
+        assert_eq!(direction_at((0.7, 0.0)), Vector::new(0.0, -1.0, 0.0));
+    }
+
+    #[test]
+    fn a_white_dome_lights_a_surface_like_a_white_light_above_it() {
+        let light = white_dome(256);
+        let normalv = Vector::new(0.0, 1.0, 0.0);
+
+        let lit = light
+            .directions()
+            .map(|(direction, color)| color * direction.dot(normalv).max(0.0))
+            .fold(color::consts::BLACK, |acc, color| acc + color)
+            * (1.0 / light.samples() as f64);
+
+        assert!((lit.red - 1.0).abs() < 0.05, "{lit:?}");
+        assert!((lit.blue - 1.0).abs() < 0.05, "{lit:?}");
+    }
+
+    #[test]
+    fn most_samples_go_towards_the_brightest_part_of_a_dome() {
+        // Dark image with a bright sun in a single pixel.
+        let mut image = Rgb32FImage::from_pixel(32, 16, image::Rgb([0.01, 0.01, 0.01]));
+        image.put_pixel(8, 2, image::Rgb([100.0, 100.0, 100.0]));
+
+        let light = DomeLight::from(DomeLightBuilder {
+            source: DomeSource::Image(Arc::new(ImageTexture::from(&DynamicImage::from(image)))),
+            intensity: color::consts::WHITE,
+            samples: 64,
+        });
+
+        let sun = direction_at((8.5 / 32.0, 1.0 - 2.5 / 16.0));
+
+        let towards_sun = light
+            .directions()
+            .filter(|(direction, _)| *direction == sun)
+            .count();
+
+        assert!(towards_sun > 48, "{towards_sun}");
+    }
+
+    #[test]
+    fn a_black_dome_has_no_samples() {
+        let light = DomeLight::from(DomeLightBuilder {
+            source: DomeSource::Gradient {
+                top: color::consts::BLACK,
+                bottom: color::consts::BLACK,
+            },
+            intensity: color::consts::WHITE,
+            samples: 16,
+        });
+
+        assert_eq!(light.samples(), 0);
+    }
+
+    #[test]
+    fn the_dome_light_intensity_function() {
+        let light = white_dome(64);
+        let point = Point::new(0.0, 0.0, 0.0);
+        let up = Vector::new(0.0, 1.0, 0.0);
+
+        let open = World::default();
+        assert_approx!(light.intensity_at(&open, point, up), 1.0);
+
+        // A ceiling above the point blocks every direction above it.
+        let mut covered = World::default();
+        covered.objects.push(Shape::Plane(Plane::default()));
+        covered.objects[0].set_transform(Transform::translation(0.0, 1.0, 0.0));
+
+        assert_approx!(light.intensity_at(&covered, point, up), 0.0);
+
+        // A wall to the side blocks about half of the directions above the point.
+        let mut walled = World::default();
+        walled.objects.push(Shape::Plane(Plane::default()));
+        walled.objects[0].set_transform(
+            Transform::translation(1.0, 0.0, 0.0)
+                * Transform::rotation_z(std::f64::consts::FRAC_PI_2),
+        );
+
+        let intensity = light.intensity_at(&walled, point, up);
+        assert!(intensity > 0.3 && intensity < 0.7, "{intensity}");
+    }
+
+    #[test]
+    fn deserializing_a_dome_light() {
+        let light: DomeLight = serde_json::from_str::<DomeLightDeserializer>(
+            r#"{
+                "source": {
+                    "type": "gradient",
+                    "top": { "red": 255, "green": 255, "blue": 255 },
+                    "bottom": { "red": 0, "green": 0, "blue": 0 }
+                },
+                "samples": 8
+            }"#,
+        )
+        .unwrap()
+        .into();
+
+        assert_eq!(
+            light.source(),
+            &DomeSource::Gradient {
+                top: color::consts::WHITE,
+                bottom: color::consts::BLACK
+            }
+        );
+        assert_eq!(light.intensity(), color::consts::WHITE);
+        assert_eq!(light.samples(), 8);
+
+        // Three quarters of the light of the gradient comes from above the horizon.
+        let above = light
+            .directions()
+            .filter(|(direction, _)| direction.0.y > 0.0)
+            .count();
+
+        assert_eq!(above, 6);
+    }
+}
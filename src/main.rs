@@ -0,0 +1,179 @@
+use std::{
+    env, fs,
+    path::Path,
+    process::ExitCode,
+    thread,
+    time::{Duration, SystemTime},
+};
+
+use raytracer::{
+    config::Config,
+    render::{RenderFarm, RenderJob, RenderQueue, RenderSettings},
+    scene::{self, MaterialOverrides, Scene, SceneFormat},
+};
+
+/// How often the watched files are checked for changes.
+const WATCH_INTERVAL: Duration = Duration::from_millis(500);
+
+const USAGE: &str = "usage: raytracer convert <input> <output>
+       raytracer farm <directory> [--threads=<n>] [--recursion-depth=<n>] [--epsilon=<meters>]
+       raytracer watch <scene> <image> [--threads=<n>] [--recursion-depth=<n>] [--epsilon=<meters>]
+
+convert: Translates a scene between formats, from and to the format given by the extension of
+each path: `.json`, or `.yaml` and `.yml`.
+
+farm: Renders every job described by the `.job.json` manifests of a directory that isn't done
+yet, resuming the ones that were interrupted. The options override the defaults used to render
+the jobs, as do the `RENDER_*` environment variables.
+
+watch: Renders a scene to an image, and renders it again every time the scene or the
+`overrides.json` file next to it changes, until it's interrupted. Errors are reported without
+stopping, so the scene can be fixed and saved again.
+
+Both `farm` and `watch` display a progress bar with the `--progress` option.";
+
+fn main() -> ExitCode {
+    let args: Vec<_> = env::args().skip(1).collect();
+
+    let result = match args.as_slice() {
+        [command, input, output] if command == "convert" => {
+            convert(Path::new(input), Path::new(output))
+        }
+        [command, directory, options @ ..]
+            if command == "farm" && options.iter().all(|option| option.starts_with("--")) =>
+        {
+            farm(Path::new(directory), options)
+        }
+        [command, scene, output, options @ ..]
+            if command == "watch" && options.iter().all(|option| option.starts_with("--")) =>
+        {
+            watch(Path::new(scene), Path::new(output), options)
+        }
+        _ => {
+            eprintln!("{USAGE}");
+            return ExitCode::from(2);
+        }
+    };
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(message) => {
+            eprintln!("error: {message}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn format(path: &Path) -> Result<SceneFormat, String> {
+    SceneFormat::from_path(path).ok_or_else(|| {
+        format!(
+            "unknown format of `{}`, expected a `.json`, `.yaml` or `.yml` file",
+            path.display()
+        )
+    })
+}
+
+fn convert(input: &Path, output: &Path) -> Result<(), String> {
+    let (from, to) = (format(input)?, format(output)?);
+
+    let spec = fs::read_to_string(input)
+        .map_err(|err| format!("failed to read `{}`: {err}", input.display()))?;
+
+    let converted = scene::convert(&spec, from, to)
+        .map_err(|err| format!("invalid scene `{}`: {err}", input.display()))?;
+
+    fs::write(output, converted)
+        .map_err(|err| format!("failed to write `{}`: {err}", output.display()))
+}
+
+fn farm(directory: &Path, options: &[String]) -> Result<(), String> {
+    let config = Config::from_args(options.iter().cloned()).map_err(|err| err.to_string())?;
+
+    let report = RenderFarm::new(directory)
+        .with_config(config)
+        .run()
+        .map_err(|err| err.to_string())?;
+
+    for (manifest, _) in &report.rendered {
+        println!("rendered {}", manifest.display());
+    }
+
+    for (manifest, err) in &report.failed {
+        eprintln!("{}: {err}", manifest.display());
+    }
+
+    match report.failed.len() {
+        0 => Ok(()),
+        failed => Err(format!("{failed} jobs failed")),
+    }
+}
+
+fn watch(scene_path: &Path, output: &Path, options: &[String]) -> Result<(), String> {
+    let config = Config::from_args(options.iter().cloned()).map_err(|err| err.to_string())?;
+    let format = format(scene_path)?;
+
+    let watched = [
+        scene_path.to_path_buf(),
+        MaterialOverrides::path_beside(scene_path),
+    ];
+
+    let modified = || -> Vec<Option<SystemTime>> {
+        watched
+            .iter()
+            .map(|path| {
+                fs::metadata(path)
+                    .and_then(|metadata| metadata.modified())
+                    .ok()
+            })
+            .collect()
+    };
+
+    let mut rendered = None;
+
+    loop {
+        // The times are read before rendering, so changes made during a render trigger the next.
+        let current = modified();
+
+        if rendered.as_ref() != Some(&current) {
+            rendered = Some(current);
+
+            match render_watched(scene_path, format, output, &config) {
+                Ok(()) => println!("rendered {}", output.display()),
+                Err(message) => eprintln!("error: {message}"),
+            }
+        }
+
+        thread::sleep(WATCH_INTERVAL);
+    }
+}
+
+fn render_watched(
+    scene_path: &Path,
+    format: SceneFormat,
+    output: &Path,
+    config: &Config,
+) -> Result<(), String> {
+    let spec = fs::read_to_string(scene_path)
+        .map_err(|err| format!("failed to read `{}`: {err}", scene_path.display()))?;
+
+    let scene = match format {
+        SceneFormat::Json => Scene::from_json(&spec),
+        SceneFormat::Yaml => Scene::from_yaml(&spec),
+    }
+    .map_err(|err| format!("invalid scene `{}`: {err}", scene_path.display()))?;
+
+    let overrides = MaterialOverrides::beside(scene_path).map_err(|err| {
+        let path = MaterialOverrides::path_beside(scene_path);
+        format!("invalid overrides `{}`: {err}", path.display())
+    })?;
+    let scene = scene.with_config(config).with_overrides(&overrides);
+
+    let mut queue = RenderQueue::new(config.threads);
+    queue.push(RenderJob::new(scene, output).with_settings(RenderSettings::from_config(config)));
+
+    queue
+        .run()
+        .into_iter()
+        .try_for_each(|result| result.map(|_| ()))
+        .map_err(|err| err.to_string())
+}
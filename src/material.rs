@@ -1,9 +1,17 @@
+use std::{borrow::Cow, sync::Arc};
+
+use serde::Deserialize;
+use thiserror::Error;
+
 use crate::{
     color::{self, Color},
     float,
     light::Light,
-    pattern::Pattern3D,
-    shape::Shape,
+    pattern::{ImageTexture, Pattern3D},
+    scene::{Checked, UnknownFields},
+    shape::{Shape, ShapeId},
+    spectrum::Spectrum,
+    transform::{self, Transform},
     tuple::{Point, Vector},
 };
 
@@ -26,6 +34,61 @@ pub mod consts {
 
     /// Average index of refraction of diamond at room temperature.
     pub const DIAMOND_INDEX_OF_REFRACTION: f64 = 2.417;
+
+    /// Wavelength, in nanometers, of the yellow sodium line at which the index of refraction of a
+    /// material is measured.
+    pub const REFERENCE_WAVELENGTH: f64 = 587.6;
+
+    /// Known media and their indices of refraction, used by [ior_by_name].
+    ///
+    /// To support a new medium, add it's lowercase name to this table.
+    ///
+    pub const INDICES_OF_REFRACTION: &[(&str, f64)] = &[
+        ("vacuum", VACUUM_INDEX_OF_REFRACTION),
+        ("air", AIR_INDEX_OF_REFRACTION),
+        ("ice", 1.309),
+        ("water", WATER_INDEX_OF_REFRACTION),
+        ("ethanol", 1.361),
+        ("glass", GLASS_INDEX_OF_REFRACTION),
+        ("olive_oil", 1.47),
+        ("plexiglass", 1.49),
+        ("crown_glass", 1.52),
+        ("flint_glass", 1.62),
+        ("sapphire", 1.77),
+        ("diamond", DIAMOND_INDEX_OF_REFRACTION),
+    ];
+
+    /// Looks up the index of refraction of a medium by it's name in [INDICES_OF_REFRACTION].
+    ///
+    /// Names are case-insensitive, and spaces can be used instead of underscores.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use raytracer::material::consts;
+    ///
+    /// assert_eq!(consts::ior_by_name("diamond"), Some(consts::DIAMOND_INDEX_OF_REFRACTION));
+    /// assert_eq!(consts::ior_by_name("Crown Glass"), Some(1.52));
+    /// assert_eq!(consts::ior_by_name("mercury"), None);
+    /// ```
+    ///
+    pub fn ior_by_name(name: &str) -> Option<f64> {
+        let name = name.trim().to_ascii_lowercase().replace(' ', "_");
+
+        INDICES_OF_REFRACTION
+            .iter()
+            .find(|(medium, _)| *medium == name)
+            .map(|(_, index_of_refraction)| *index_of_refraction)
+    }
+}
+
+/// The error type when trying to deserialize a material.
+#[derive(Debug, PartialEq, Error)]
+pub enum Error {
+    /// The error type when the index of refraction is given as the name of a medium that has no
+    /// known index of refraction.
+    #[error("unknown medium for the index of refraction: `{0}`")]
+    UnknownMedium(String),
 }
 
 /// The material for an object.
@@ -33,7 +96,25 @@ pub mod consts {
 /// Materials use the [Phong's reflection model](https://learnopengl.com/Lighting/Basic-Lighting)
 /// to compute shading.
 ///
-#[derive(Copy, Clone, Debug)]
+/// # Deserialization
+///
+/// Every field of a deserialized material is optional, taking it's [default](Material::default)
+/// value when missing. The index of refraction can be either a number or the name of any medium in
+/// [consts::INDICES_OF_REFRACTION].
+///
+/// ```json
+/// {
+///     "pattern": { "type": "solid", "color": { "red": 0, "green": 0, "blue": 0 } },
+///     "diffuse": 0.1,
+///     "shininess": 300.0,
+///     "reflectivity": 0.9,
+///     "transparency": 0.9,
+///     "index_of_refraction": "glass"
+/// }
+/// ```
+///
+#[derive(Clone, Debug, Deserialize)]
+#[serde(try_from = "MaterialDeserializer")]
 pub struct Material {
     /// The pattern of the material.
     pub pattern: Pattern3D,
@@ -84,6 +165,362 @@ pub struct Material {
 
     /// Controls the transparency of the material.
     pub transparency: f64,
+
+    /// How much the index of refraction of the material changes with the wavelength of light,
+    /// which splits white light into a rainbow when it's refracted. It's the `B` coefficient of
+    /// [Cauchy's equation](https://en.wikipedia.org/wiki/Cauchy%27s_equation), in squared
+    /// micrometers, such as `0.0042` for crown glass.
+    ///
+    /// Only [spectral](crate::integrator::Spectral) renders take dispersion into account. See
+    /// [Material::index_of_refraction_at] for details.
+    ///
+    pub dispersion: f64,
+
+    /// Fraction of light reflected by the material at each wavelength, which replaces the color of
+    /// it's pattern in [spectral](crate::integrator::Spectral) renders. Other renders keep using
+    /// the pattern, so it should have a color that looks like the spectrum.
+    ///
+    /// Two materials with different spectra can look the same under one light and different
+    /// under another, just like real materials do.
+    ///
+    pub spectrum: Option<Arc<Spectrum>>,
+
+    /// Another material that is layered on top of this one.
+    ///
+    /// Check out [MaterialBlend] for more details.
+    ///
+    pub blend: Option<Arc<MaterialBlend>>,
+
+    /// Turns the surface into a portal that rays go through to reappear somewhere else.
+    ///
+    /// Check out [Portal] for more details.
+    ///
+    pub portal: Option<Portal>,
+
+    /// Darkens the creases and corners of the surface.
+    ///
+    /// Check out [Dirt] for more details.
+    ///
+    pub dirt: Option<Dirt>,
+}
+
+/// Dirt that gathers where a surface is enclosed by other surfaces, like the creases and corners
+/// of an object, or the ground right under it.
+///
+/// The color of the surface is mixed with the color of the dirt by the fraction of the hemisphere
+/// above each point that is occluded by other surfaces, which is sampled by casting rays in random
+/// directions when the point is shaded. It doesn't depend on the lights of the world, so it
+/// grounds objects even in scenes lit from far away, and it gives stylized renders some contact
+/// dirt.
+///
+/// # Examples
+///
+/// ```
+/// use raytracer::{
+///     color,
+///     material::{Dirt, Material},
+/// };
+///
+/// let dusty = Material {
+///     dirt: Some(Dirt {
+///         color: color::consts::BLACK,
+///         distance: 0.5,
+///         samples: 16,
+///     }),
+///     ..Default::default()
+/// };
+/// ```
+///
+/// # Deserialization
+///
+/// Every field of a deserialized dirt is optional, taking it's [default](Dirt::default) value
+/// when missing.
+///
+/// ```json
+/// { "color": { "red": 40, "green": 30, "blue": 20 }, "distance": 0.5, "samples": 16 }
+/// ```
+///
+#[derive(Copy, Clone, Debug, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct Dirt {
+    /// Color of the dirt.
+    pub color: Color,
+
+    /// Maximum distance at which a surface dirties another.
+    pub distance: f64,
+
+    /// Number of occlusion rays casted for each shaded point.
+    pub samples: usize,
+}
+
+/// A surface that teleports the rays that hit it.
+///
+/// Rays that hit a portal are not shaded. Instead, they continue from the point where they hit the
+/// portal, moved along with their direction by the portal's transform. Portals come in pairs,
+/// where each portal takes rays to the other one, such as a door that leads to a room somewhere
+/// else in the world, or to the other end of the same room.
+///
+/// Portals are one-sided. Rays that hit them from behind, or from the inside of a closed shape,
+/// go straight through them, so rays that come out of a portal can leave the shape of it's pair.
+/// Portals still cast shadows, and they count as a bounce towards the maximum recursion depth.
+///
+/// # Examples
+///
+/// Two doorways with the same shape, 10 units apart, that lead to each other.
+///
+/// ```
+/// use raytracer::{
+///     material::{Material, Portal},
+///     shape::{Cube, Shape, ShapeBuilder},
+///     transform::Transform,
+/// };
+///
+/// let (entrance, exit) = Portal::pair(Transform::translation(10.0, 0.0, 0.0));
+///
+/// let doorway = |portal, transform| {
+///     Shape::Cube(Cube::from(ShapeBuilder {
+///         material: Material {
+///             portal: Some(portal),
+///             ..Default::default()
+///         },
+///         transform,
+///     }))
+/// };
+///
+/// let objects = [
+///     doorway(entrance, Transform::scaling(1.0, 2.0, 0.01).unwrap()),
+///     doorway(
+///         exit,
+///         Transform::translation(10.0, 0.0, 0.0) * Transform::scaling(1.0, 2.0, 0.01).unwrap(),
+///     ),
+/// ];
+/// ```
+///
+#[derive(Copy, Clone, Debug, PartialEq, Deserialize)]
+pub struct Portal {
+    /// Transform from the space around this portal to the space around it's pair.
+    #[serde(deserialize_with = "transform::deserialize_chain")]
+    pub transform: Transform,
+}
+
+/// A material layered on top of another one, using a mask to determine how much of each material
+/// is visible at each point of the surface.
+///
+/// # Examples
+///
+/// A red matte paint with reflective metallic patches that show through it.
+///
+/// ```
+/// use std::sync::Arc;
+///
+/// use raytracer::{
+///     color,
+///     material::{Material, MaterialBlend},
+///     pattern::{Pattern3D, Pattern3DSpec},
+///     transform::Transform,
+/// };
+///
+/// let metal = Material {
+///     pattern: Pattern3D::Solid(color::consts::WHITE),
+///     reflectivity: 0.8,
+///     ..Default::default()
+/// };
+///
+/// let painted_metal = Material {
+///     pattern: Pattern3D::Solid(color::consts::RED),
+///     specular: 0.0,
+///     blend: Some(Arc::new(MaterialBlend {
+///         material: metal,
+///         mask: Pattern3D::Checker(Pattern3DSpec::new(
+///             color::consts::BLACK,
+///             color::consts::WHITE,
+///             Transform::scaling(0.1, 0.1, 0.1).unwrap(),
+///         )),
+///     })),
+///     ..Default::default()
+/// };
+/// ```
+///
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+pub struct MaterialBlend {
+    /// Material layered on top of the base material.
+    pub material: Material,
+
+    /// Pattern that controls how much of the layered material is visible at each point, where
+    /// black only shows the base material, white only shows the layered material, and shades of
+    /// gray mix them. The brightness of the mask is the average of it's color channels.
+    ///
+    pub mask: Pattern3D,
+}
+
+/// Changes to some of the fields of a material, which keeps the rest of them as they are.
+///
+/// Overrides are used to tweak the materials of a scene without editing the scene itself. See
+/// [MaterialOverrides](crate::scene::MaterialOverrides) for details.
+///
+/// # Examples
+///
+/// ```
+/// use raytracer::material::{Material, MaterialOverride};
+///
+/// let mut material = Material {
+///     diffuse: 0.3,
+///     specular: 0.2,
+///     ..Default::default()
+/// };
+///
+/// MaterialOverride {
+///     diffuse: Some(0.7),
+///     ..Default::default()
+/// }
+/// .apply_to(&mut material);
+///
+/// assert_eq!((material.diffuse, material.specular), (0.7, 0.2));
+/// ```
+///
+/// # Deserialization
+///
+/// An override is deserialized like a [Material], with any of it's `pattern`, `ambient`,
+/// `diffuse`, `specular`, `shininess`, `index_of_refraction`, `reflectivity`, `transparency` and
+/// `dispersion` fields.
+///
+/// ```json
+/// { "reflectivity": 0.5, "index_of_refraction": "diamond" }
+/// ```
+///
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+#[serde(try_from = "MaterialOverrideDeserializer")]
+pub struct MaterialOverride {
+    /// Replaces the [pattern](Material::pattern) of the material.
+    pub pattern: Option<Pattern3D>,
+
+    /// Replaces the [ambient](Material::ambient) component of the material.
+    pub ambient: Option<f64>,
+
+    /// Replaces the [diffuse](Material::diffuse) component of the material.
+    pub diffuse: Option<f64>,
+
+    /// Replaces the [specular](Material::specular) component of the material.
+    pub specular: Option<f64>,
+
+    /// Replaces the [shininess](Material::shininess) of the material.
+    pub shininess: Option<f64>,
+
+    /// Replaces the [index of refraction](Material::index_of_refraction) of the material.
+    pub index_of_refraction: Option<f64>,
+
+    /// Replaces the [reflectivity](Material::reflectivity) of the material.
+    pub reflectivity: Option<f64>,
+
+    /// Replaces the [transparency](Material::transparency) of the material.
+    pub transparency: Option<f64>,
+
+    /// Replaces the [dispersion](Material::dispersion) of the material.
+    pub dispersion: Option<f64>,
+}
+
+#[derive(Debug, PartialEq, Deserialize)]
+struct MaterialDeserializer {
+    pattern: Option<Pattern3D>,
+    ambient: Option<f64>,
+    diffuse: Option<f64>,
+    specular: Option<f64>,
+    shininess: Option<f64>,
+    index_of_refraction: Option<IndexOfRefractionDeserializer>,
+    reflectivity: Option<f64>,
+    transparency: Option<f64>,
+    dispersion: Option<f64>,
+    spectrum: Option<Spectrum>,
+    blend: Option<Checked<MaterialBlend>>,
+    portal: Option<Checked<Portal>>,
+    dirt: Option<Checked<Dirt>>,
+
+    #[serde(flatten)]
+    _unknown: UnknownFields,
+}
+
+#[derive(Debug, PartialEq, Deserialize)]
+struct MaterialOverrideDeserializer {
+    pattern: Option<Pattern3D>,
+    ambient: Option<f64>,
+    diffuse: Option<f64>,
+    specular: Option<f64>,
+    shininess: Option<f64>,
+    index_of_refraction: Option<IndexOfRefractionDeserializer>,
+    reflectivity: Option<f64>,
+    transparency: Option<f64>,
+    dispersion: Option<f64>,
+
+    #[serde(flatten)]
+    _unknown: UnknownFields,
+}
+
+#[derive(Debug, PartialEq, Deserialize)]
+#[serde(untagged)]
+enum IndexOfRefractionDeserializer {
+    Value(f64),
+    Medium(String),
+}
+
+impl TryFrom<MaterialDeserializer> for Material {
+    type Error = Error;
+
+    fn try_from(value: MaterialDeserializer) -> Result<Self, Self::Error> {
+        let default = Self::default();
+
+        let index_of_refraction = match value.index_of_refraction {
+            None => default.index_of_refraction,
+            Some(index_of_refraction) => index_of_refraction.resolve()?,
+        };
+
+        Ok(Self {
+            pattern: value.pattern.unwrap_or(default.pattern),
+            ambient: value.ambient.unwrap_or(default.ambient),
+            diffuse: value.diffuse.unwrap_or(default.diffuse),
+            specular: value.specular.unwrap_or(default.specular),
+            shininess: value.shininess.unwrap_or(default.shininess),
+            index_of_refraction,
+            reflectivity: value.reflectivity.unwrap_or(default.reflectivity),
+            transparency: value.transparency.unwrap_or(default.transparency),
+            dispersion: value.dispersion.unwrap_or(default.dispersion),
+            spectrum: value.spectrum.map(Arc::new),
+            blend: value.blend.map(|blend| Arc::new(blend.inner)),
+            portal: value.portal.map(|portal| portal.inner),
+            dirt: value.dirt.map(|dirt| dirt.inner),
+        })
+    }
+}
+
+impl TryFrom<MaterialOverrideDeserializer> for MaterialOverride {
+    type Error = Error;
+
+    fn try_from(value: MaterialOverrideDeserializer) -> Result<Self, Self::Error> {
+        Ok(Self {
+            pattern: value.pattern,
+            ambient: value.ambient,
+            diffuse: value.diffuse,
+            specular: value.specular,
+            shininess: value.shininess,
+            index_of_refraction: value
+                .index_of_refraction
+                .map(IndexOfRefractionDeserializer::resolve)
+                .transpose()?,
+            reflectivity: value.reflectivity,
+            transparency: value.transparency,
+            dispersion: value.dispersion,
+        })
+    }
+}
+
+impl IndexOfRefractionDeserializer {
+    fn resolve(self) -> Result<f64, Error> {
+        match self {
+            Self::Value(value) => Ok(value),
+            Self::Medium(name) => {
+                self::consts::ior_by_name(&name).ok_or(Error::UnknownMedium(name))
+            }
+        }
+    }
 }
 
 impl Default for Material {
@@ -97,6 +534,21 @@ impl Default for Material {
             index_of_refraction: self::consts::VACUUM_INDEX_OF_REFRACTION,
             reflectivity: 0.0,
             transparency: 0.0,
+            dispersion: 0.0,
+            spectrum: None,
+            blend: None,
+            portal: None,
+            dirt: None,
+        }
+    }
+}
+
+impl Default for Dirt {
+    fn default() -> Self {
+        Self {
+            color: color::consts::BLACK,
+            distance: 1.0,
+            samples: 16,
         }
     }
 }
@@ -111,10 +563,198 @@ impl PartialEq for Material {
             && float::approx(self.shininess, other.shininess)
             && float::approx(self.specular, other.specular)
             && float::approx(self.transparency, other.transparency)
+            && float::approx(self.dispersion, other.dispersion)
+            && self.spectrum == other.spectrum
+            && self.blend == other.blend
+            && self.portal == other.portal
+            && self.dirt == other.dirt
+    }
+}
+
+impl Portal {
+    /// Constructs a pair of portals, where the first one takes rays to the second one with the
+    /// given transform, and the second one takes them back.
+    pub fn pair(transform: Transform) -> (Self, Self) {
+        (
+            Self { transform },
+            Self {
+                transform: transform.inverse(),
+            },
+        )
     }
 }
 
 impl Material {
+    /// Index of refraction of the material for light of the given wavelength, in nanometers.
+    ///
+    /// The [index of refraction](Material::index_of_refraction) of the material is the one at the
+    /// [reference wavelength](consts::REFERENCE_WAVELENGTH), and it grows towards shorter
+    /// wavelengths by the [dispersion](Material::dispersion) of the material.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use raytracer::material::Material;
+    ///
+    /// let crown_glass = Material {
+    ///     index_of_refraction: 1.5168,
+    ///     dispersion: 0.0042,
+    ///     ..Default::default()
+    /// };
+    ///
+    /// assert!(crown_glass.index_of_refraction_at(450.0) > crown_glass.index_of_refraction_at(650.0));
+    /// ```
+    ///
+    pub fn index_of_refraction_at(&self, wavelength: f64) -> f64 {
+        // Cauchy's equation takes the wavelength in micrometers.
+        let inverse_square = |wavelength: f64| (1000.0 / wavelength).powi(2);
+
+        self.index_of_refraction
+            + self.dispersion
+                * (inverse_square(wavelength) - inverse_square(self::consts::REFERENCE_WAVELENGTH))
+    }
+
+    /// Fraction of the light that reaches the surface that leaves it, which is the sum of the
+    /// [ambient](Material::ambient), [diffuse](Material::diffuse),
+    /// [specular](Material::specular), [reflectivity](Material::reflectivity) and
+    /// [transparency](Material::transparency) components of the material.
+    ///
+    /// Physically plausible materials have an energy of at most `1.0`, otherwise their surface
+    /// gives off more light than it receives. The [default](Material::default) material isn't one
+    /// of them, as it's bright highlights are added on top of it's diffuse shading.
+    ///
+    pub fn energy(&self) -> f64 {
+        self.ambient + self.diffuse + self.specular + self.reflectivity + self.transparency
+    }
+
+    /// Scales down the components of the material that add up to it's [energy](Material::energy)
+    /// by the same factor, so the surface doesn't give off more light than it receives. Materials
+    /// with an energy of at most `1.0` are left as they are, and so is the material
+    /// [blended](Material::blend) on top of this one, unless it's energy is also greater than
+    /// `1.0`.
+    ///
+    /// Returns whether any material was changed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use raytracer::material::Material;
+    ///
+    /// let mut mirror = Material {
+    ///     ambient: 0.1,
+    ///     diffuse: 0.5,
+    ///     specular: 0.4,
+    ///     reflectivity: 1.0,
+    ///     ..Default::default()
+    /// };
+    ///
+    /// assert!(mirror.conserve_energy());
+    ///
+    /// assert!((mirror.energy() - 1.0).abs() < 1e-9);
+    /// assert_eq!(mirror.reflectivity, 0.5);
+    /// ```
+    ///
+    pub fn conserve_energy(&mut self) -> bool {
+        let mut changed = false;
+
+        if let Some(blend) = &mut self.blend {
+            if blend.material.energy() > 1.0 {
+                changed |= Arc::make_mut(blend).material.conserve_energy();
+            }
+        }
+
+        let energy = self.energy();
+
+        if energy > 1.0 {
+            for component in [
+                &mut self.ambient,
+                &mut self.diffuse,
+                &mut self.specular,
+                &mut self.reflectivity,
+                &mut self.transparency,
+            ] {
+                *component /= energy;
+            }
+
+            changed = true;
+        }
+
+        changed
+    }
+
+    /// Forgets every color cached by the patterns of the material for a shape.
+    pub(crate) fn forget(&self, id: ShapeId) {
+        self.pattern.forget(id);
+
+        if let Some(blend) = &self.blend {
+            blend.material.forget(id);
+            blend.mask.forget(id);
+        }
+    }
+
+    /// Replaces every image texture of the patterns of the material by the image returned by
+    /// `replace` for it.
+    pub(crate) fn replace_images(
+        &mut self,
+        replace: &mut dyn FnMut(&Arc<ImageTexture>) -> Arc<ImageTexture>,
+    ) {
+        self.pattern.replace_images(replace);
+
+        if let Some(blend) = &mut self.blend {
+            let blend = Arc::make_mut(blend);
+            blend.material.replace_images(replace);
+            blend.mask.replace_images(replace);
+        }
+    }
+
+    /// Flattens the layers of the material at the given point of an object, seen from the given
+    /// distance, into a single material with a solid color. Materials that already are a single
+    /// layer with a solid color are borrowed as they are.
+    pub(crate) fn resolve_at(&self, object: &Shape, point: Point, distance: f64) -> Cow<'_, Self> {
+        let Some(blend) = &self.blend else {
+            if let Pattern3D::Solid(_) = self.pattern {
+                return Cow::Borrowed(self);
+            }
+
+            return Cow::Owned(Self {
+                pattern: Pattern3D::Solid(self.pattern.color_at_distance(object, point, distance)),
+                ..self.clone()
+            });
+        };
+
+        let base_color = self.pattern.color_at_distance(object, point, distance);
+
+        let top = blend.material.resolve_at(object, point, distance);
+
+        let mask = blend.mask.color_at_distance(object, point, distance);
+        let t = ((mask.red + mask.green + mask.blue) / 3.0).clamp(0.0, 1.0);
+
+        let lerp = |a: f64, b: f64| a + (b - a) * t;
+
+        let top_color = top.pattern.color_at_object(object, point);
+
+        Cow::Owned(Self {
+            pattern: Pattern3D::Solid(base_color + (top_color - base_color) * t),
+            ambient: lerp(self.ambient, top.ambient),
+            diffuse: lerp(self.diffuse, top.diffuse),
+            specular: lerp(self.specular, top.specular),
+            shininess: lerp(self.shininess, top.shininess),
+            index_of_refraction: lerp(self.index_of_refraction, top.index_of_refraction),
+            reflectivity: lerp(self.reflectivity, top.reflectivity),
+            transparency: lerp(self.transparency, top.transparency),
+            dispersion: lerp(self.dispersion, top.dispersion),
+            // Spectra can't be mixed like colors, so the layer that shows the most keeps it's own.
+            spectrum: if t < 0.5 {
+                self.spectrum.clone()
+            } else {
+                top.spectrum.clone()
+            },
+            blend: None,
+            portal: self.portal,
+            dirt: if t < 0.5 { self.dirt } else { top.dirt },
+        })
+    }
+
     pub(crate) fn lighting(
         &self,
         object: &Shape,
@@ -124,26 +764,24 @@ impl Material {
         normalv: Vector,
         light_intensity: f64,
     ) -> Color {
-        let effective_color = self.pattern.color_at_object(object, point) * light.effective_color();
+        let surface_color = self.pattern.color_at_object(object, point);
+        let effective_color = surface_color * light.effective_color();
 
         let ambient = effective_color * self.ambient;
 
         let mut light_shade = color::consts::BLACK;
 
-        let light_samples = match light {
-            Light::Area(area_light) => area_light.samples,
-            Light::Point(_) => 1,
-        };
+        let directions = light.directions_at(point);
 
-        for light_cell in light.cells() {
-            let lightv = (light_cell - point)
-                .normalize()
-                .unwrap_or(Vector::new(0.0, 0.0, 0.0));
+        if directions.is_empty() {
+            return ambient;
+        }
 
+        for &(lightv, light_color) in &directions {
             let light_dot_normal = lightv.dot(normalv);
 
             if float::ge(light_dot_normal, 0.0) {
-                let diffuse_contrib = effective_color * self.diffuse * light_dot_normal;
+                let diffuse_contrib = surface_color * light_color * self.diffuse * light_dot_normal;
                 light_shade = light_shade + diffuse_contrib;
 
                 let reflectv = (-lightv).reflect(normalv);
@@ -152,22 +790,50 @@ impl Material {
                 if reflect_dot_eye > 0.0 {
                     let factor = reflect_dot_eye.powf(self.shininess);
 
-                    let specular_contrib = light.effective_color() * self.specular * factor;
+                    let specular_contrib = light_color * self.specular * factor;
                     light_shade = light_shade + specular_contrib;
                 };
             }
         }
 
-        ambient + (light_shade * (1.0 / light_samples as f64)) * light_intensity
+        ambient + (light_shade * (1.0 / directions.len() as f64)) * light_intensity
+    }
+}
+
+impl MaterialOverride {
+    /// Replaces the fields of `material` that the override changes.
+    pub fn apply_to(&self, material: &mut Material) {
+        if let Some(pattern) = &self.pattern {
+            material.pattern = pattern.clone();
+        }
+
+        let fields = [
+            (self.ambient, &mut material.ambient),
+            (self.diffuse, &mut material.diffuse),
+            (self.specular, &mut material.specular),
+            (self.shininess, &mut material.shininess),
+            (self.index_of_refraction, &mut material.index_of_refraction),
+            (self.reflectivity, &mut material.reflectivity),
+            (self.transparency, &mut material.transparency),
+            (self.dispersion, &mut material.dispersion),
+        ];
+
+        for (value, field) in fields {
+            if let Some(value) = value {
+                *field = value;
+            }
+        }
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use serde_test::{assert_de_tokens, assert_de_tokens_error, Token};
+
     use crate::{
         assert_approx,
         light::{AreaLight, AreaLightBuilder, PointLight},
-        pattern::{Pattern3D, Pattern3DSpec},
+        pattern::{LevelOfDetailSpec, Pattern3D, Pattern3DSpec},
         world::test_world,
     };
 
@@ -181,6 +847,83 @@ mod tests {
         )
     }
 
+    #[test]
+    fn the_energy_of_a_material() {
+        let material = Material {
+            ambient: 0.1,
+            diffuse: 0.2,
+            specular: 0.3,
+            reflectivity: 0.4,
+            transparency: 0.5,
+            ..Default::default()
+        };
+
+        assert_approx!(material.energy(), 1.5);
+        assert_approx!(Material::default().energy(), 1.9);
+    }
+
+    #[test]
+    fn conserving_the_energy_of_a_material() {
+        let mut material = Material {
+            ambient: 0.2,
+            diffuse: 0.8,
+            specular: 0.6,
+            reflectivity: 0.4,
+            transparency: 0.0,
+            shininess: 50.0,
+            ..Default::default()
+        };
+
+        assert!(material.conserve_energy());
+
+        assert_approx!(material.energy(), 1.0);
+        assert_approx!(material.ambient, 0.1);
+        assert_approx!(material.diffuse, 0.4);
+        assert_approx!(material.specular, 0.3);
+        assert_approx!(material.reflectivity, 0.2);
+        assert_approx!(material.shininess, 50.0);
+    }
+
+    #[test]
+    fn a_plausible_material_already_conserves_its_energy() {
+        let mut material = Material {
+            ambient: 0.1,
+            diffuse: 0.6,
+            specular: 0.3,
+            ..Default::default()
+        };
+        let original = material.clone();
+
+        assert!(!material.conserve_energy());
+        assert_eq!(material, original);
+    }
+
+    #[test]
+    fn conserving_the_energy_of_a_blended_material() {
+        let mut material = Material {
+            ambient: 0.1,
+            diffuse: 0.5,
+            specular: 0.2,
+            blend: Some(Arc::new(MaterialBlend {
+                material: Material {
+                    diffuse: 0.9,
+                    specular: 0.9,
+                    reflectivity: 0.2,
+                    ambient: 0.0,
+                    ..Default::default()
+                },
+                mask: Pattern3D::Solid(color::consts::WHITE),
+            })),
+            ..Default::default()
+        };
+
+        assert!(material.conserve_energy());
+
+        assert_approx!(material.diffuse, 0.5);
+        let blend = material.blend.unwrap();
+        assert_approx!(blend.material.energy(), 1.0);
+    }
+
     #[test]
     fn the_default_matrial() {
         let material = Material::default();
@@ -204,6 +947,7 @@ mod tests {
         let light = Light::Point(PointLight {
             position: Point::new(0.0, 0.0, -10.0),
             intensity: color::consts::WHITE,
+            group: None,
         });
 
         let shade = material.lighting(&object, &light, position, eyev, normalv, 1.0);
@@ -227,6 +971,7 @@ mod tests {
         let light = Light::Point(PointLight {
             position: Point::new(0.0, 0.0, -10.0),
             intensity: color::consts::WHITE,
+            group: None,
         });
 
         let shade = material.lighting(&object, &light, position, eyev, normalv, 1.0);
@@ -250,6 +995,7 @@ mod tests {
         let light = Light::Point(PointLight {
             position: Point::new(0.0, 10.0, -10.0),
             intensity: color::consts::WHITE,
+            group: None,
         });
 
         let shade = material.lighting(&object, &light, position, eyev, normalv, 1.0);
@@ -273,6 +1019,7 @@ mod tests {
         let light = Light::Point(PointLight {
             position: Point::new(0.0, 10.0, -10.0),
             intensity: color::consts::WHITE,
+            group: None,
         });
 
         let shade = material.lighting(&object, &light, position, eyev, normalv, 1.0);
@@ -296,6 +1043,7 @@ mod tests {
         let light = Light::Point(PointLight {
             position: Point::new(0.0, 0.0, 10.0),
             intensity: color::consts::WHITE,
+            group: None,
         });
 
         let shade = material.lighting(&object, &light, position, eyev, normalv, 0.0);
@@ -319,6 +1067,7 @@ mod tests {
         let light = Light::Point(PointLight {
             position,
             intensity: color::consts::WHITE,
+            group: None,
         });
 
         let shade = material.lighting(&object, &light, position, eyev, normalv, 0.0);
@@ -342,6 +1091,7 @@ mod tests {
         let light = Light::Point(PointLight {
             position: Point::new(0.0, 0.0, -10.0),
             intensity: color::consts::WHITE,
+            group: None,
         });
 
         let shade = material.lighting(&object, &light, position, eyev, normalv, 0.0);
@@ -377,6 +1127,7 @@ mod tests {
         let light = Light::Point(PointLight {
             position: Point::new(0.0, 0.0, -10.0),
             intensity: color::consts::WHITE,
+            group: None,
         });
 
         let shade0 = material.lighting(
@@ -408,6 +1159,7 @@ mod tests {
         let light = Light::Point(PointLight {
             position: Point::new(0.0, 0.0, -10.0),
             intensity: color::consts::WHITE,
+            group: None,
         });
 
         let object = &world.objects[0];
@@ -501,4 +1253,453 @@ mod tests {
             }
         );
     }
+
+    #[test]
+    fn deserializing_an_empty_material_uses_the_default_values() {
+        assert_de_tokens(
+            &Material::default(),
+            &[
+                Token::Struct {
+                    name: "MaterialDeserializer",
+                    len: 0,
+                },
+                Token::StructEnd,
+            ],
+        );
+    }
+
+    #[test]
+    fn deserializing_a_glass_material() {
+        assert_de_tokens(
+            &Material {
+                diffuse: 0.1,
+                shininess: 300.0,
+                reflectivity: 0.9,
+                transparency: 0.9,
+                index_of_refraction: consts::GLASS_INDEX_OF_REFRACTION,
+                ..Default::default()
+            },
+            &[
+                Token::Struct {
+                    name: "MaterialDeserializer",
+                    len: 5,
+                },
+                Token::Str("diffuse"),
+                Token::Some,
+                Token::F64(0.1),
+                Token::Str("shininess"),
+                Token::Some,
+                Token::F64(300.0),
+                Token::Str("reflectivity"),
+                Token::Some,
+                Token::F64(0.9),
+                Token::Str("transparency"),
+                Token::Some,
+                Token::F64(0.9),
+                Token::Str("index_of_refraction"),
+                Token::Some,
+                Token::Str("glass"),
+                Token::StructEnd,
+            ],
+        );
+    }
+
+    #[test]
+    fn deserializing_a_material_with_a_numeric_index_of_refraction() {
+        assert_de_tokens(
+            &Material {
+                index_of_refraction: 1.52,
+                ..Default::default()
+            },
+            &[
+                Token::Struct {
+                    name: "MaterialDeserializer",
+                    len: 1,
+                },
+                Token::Str("index_of_refraction"),
+                Token::Some,
+                Token::F64(1.52),
+                Token::StructEnd,
+            ],
+        );
+    }
+
+    #[test]
+    fn deserializing_a_material_with_a_spectrum() {
+        let material: Material = serde_json::from_str(
+            r#"{ "dispersion": 0.0042, "spectrum": [[400, 0.2], [700, 0.8]] }"#,
+        )
+        .unwrap();
+
+        assert_approx!(material.dispersion, 0.0042);
+        assert_eq!(
+            material.spectrum.as_deref(),
+            Some(&Spectrum::new(vec![(400.0, 0.2), (700.0, 0.8)]).unwrap())
+        );
+    }
+
+    #[test]
+    fn the_index_of_refraction_of_a_dispersive_material_depends_on_the_wavelength() {
+        let material = Material {
+            index_of_refraction: 1.5,
+            dispersion: 0.005,
+            ..Default::default()
+        };
+
+        assert_approx!(
+            material.index_of_refraction_at(consts::REFERENCE_WAVELENGTH),
+            1.5
+        );
+        assert!(material.index_of_refraction_at(400.0) > 1.5);
+        assert!(material.index_of_refraction_at(700.0) < 1.5);
+
+        let material = Material {
+            index_of_refraction: 1.5,
+            ..Default::default()
+        };
+
+        assert_approx!(material.index_of_refraction_at(400.0), 1.5);
+    }
+
+    #[test]
+    fn trying_to_deserialize_a_material_with_an_unknown_medium() {
+        assert_de_tokens_error::<Material>(
+            &[
+                Token::Struct {
+                    name: "MaterialDeserializer",
+                    len: 1,
+                },
+                Token::Str("index_of_refraction"),
+                Token::Some,
+                Token::Str("mercury"),
+                Token::StructEnd,
+            ],
+            "unknown medium for the index of refraction: `mercury`",
+        );
+    }
+
+    #[test]
+    fn an_override_only_changes_the_fields_it_has() {
+        let mut material = Material {
+            diffuse: 0.3,
+            reflectivity: 0.5,
+            ..Default::default()
+        };
+
+        MaterialOverride {
+            pattern: Some(Pattern3D::Solid(color::consts::RED)),
+            reflectivity: Some(0.1),
+            ..Default::default()
+        }
+        .apply_to(&mut material);
+
+        assert_eq!(material.pattern, Pattern3D::Solid(color::consts::RED));
+        assert_approx!(material.reflectivity, 0.1);
+        assert_approx!(material.diffuse, 0.3);
+        assert_approx!(material.specular, Material::default().specular);
+    }
+
+    #[test]
+    fn deserializing_a_material_override() {
+        let material_override: MaterialOverride =
+            serde_json::from_str(r#"{ "diffuse": 0.2, "index_of_refraction": "diamond" }"#)
+                .unwrap();
+
+        assert_eq!(
+            material_override,
+            MaterialOverride {
+                diffuse: Some(0.2),
+                index_of_refraction: Some(consts::DIAMOND_INDEX_OF_REFRACTION),
+                ..Default::default()
+            }
+        );
+
+        let unknown_medium =
+            serde_json::from_str::<MaterialOverride>(r#"{ "index_of_refraction": "mercury" }"#);
+
+        assert!(unknown_medium.is_err());
+    }
+
+    #[test]
+    fn looking_up_indices_of_refraction_by_name() {
+        assert_eq!(
+            consts::ior_by_name("glass"),
+            Some(consts::GLASS_INDEX_OF_REFRACTION)
+        );
+        assert_eq!(
+            consts::ior_by_name("  WATER "),
+            Some(consts::WATER_INDEX_OF_REFRACTION)
+        );
+        assert_eq!(consts::ior_by_name("olive oil"), Some(1.47));
+        assert_eq!(consts::ior_by_name("unobtainium"), None);
+    }
+
+    #[test]
+    fn every_known_medium_is_reachable_by_its_name() {
+        for (name, index_of_refraction) in consts::INDICES_OF_REFRACTION {
+            assert_eq!(consts::ior_by_name(name), Some(*index_of_refraction));
+        }
+    }
+
+    #[test]
+    fn deserializing_a_material_with_a_medium_name_in_any_case() {
+        assert_de_tokens(
+            &Material {
+                index_of_refraction: consts::DIAMOND_INDEX_OF_REFRACTION,
+                ..Default::default()
+            },
+            &[
+                Token::Struct {
+                    name: "MaterialDeserializer",
+                    len: 1,
+                },
+                Token::Str("index_of_refraction"),
+                Token::Some,
+                Token::Str("Diamond"),
+                Token::StructEnd,
+            ],
+        );
+    }
+
+    fn blended_material(mask: Color) -> Material {
+        Material {
+            pattern: Pattern3D::Solid(color::consts::RED),
+            reflectivity: 0.0,
+            specular: 0.0,
+            blend: Some(Arc::new(MaterialBlend {
+                material: Material {
+                    pattern: Pattern3D::Solid(color::consts::BLUE),
+                    reflectivity: 1.0,
+                    specular: 1.0,
+                    ..Default::default()
+                },
+                mask: Pattern3D::Solid(mask),
+            })),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn resolving_a_material_without_layers() {
+        let (object, material, point) = test_object_material_point();
+
+        assert!(matches!(
+            material.resolve_at(&object, point, 0.0),
+            Cow::Borrowed(resolved) if *resolved == material
+        ));
+    }
+
+    #[test]
+    fn resolving_a_material_flattens_its_pattern_at_the_hit_distance() {
+        let (object, _, point) = test_object_material_point();
+        let material = Material {
+            pattern: Pattern3D::LevelOfDetail(Arc::new(LevelOfDetailSpec {
+                detailed: Pattern3D::Solid(color::consts::WHITE),
+                simplified: Pattern3D::Solid(color::consts::BLACK),
+                near: 10.0,
+                far: 20.0,
+            })),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            material.resolve_at(&object, point, 0.0).pattern,
+            Pattern3D::Solid(color::consts::WHITE)
+        );
+        assert_eq!(
+            material.resolve_at(&object, point, 30.0).pattern,
+            Pattern3D::Solid(color::consts::BLACK)
+        );
+    }
+
+    #[test]
+    fn resolving_a_blended_material_with_a_black_mask_shows_the_base_material() {
+        let (object, _, point) = test_object_material_point();
+        let material = blended_material(color::consts::BLACK);
+
+        let resolved = material.resolve_at(&object, point, 0.0);
+
+        assert_eq!(resolved.pattern, Pattern3D::Solid(color::consts::RED));
+        assert_approx!(resolved.reflectivity, 0.0);
+        assert_approx!(resolved.specular, 0.0);
+        assert!(resolved.blend.is_none());
+    }
+
+    #[test]
+    fn resolving_a_blended_material_with_a_white_mask_shows_the_layered_material() {
+        let (object, _, point) = test_object_material_point();
+        let material = blended_material(color::consts::WHITE);
+
+        let resolved = material.resolve_at(&object, point, 0.0);
+
+        assert_eq!(resolved.pattern, Pattern3D::Solid(color::consts::BLUE));
+        assert_approx!(resolved.reflectivity, 1.0);
+        assert_approx!(resolved.specular, 1.0);
+    }
+
+    #[test]
+    fn resolving_a_blended_material_with_a_gray_mask_mixes_both_materials() {
+        let (object, _, point) = test_object_material_point();
+        let material = blended_material(Color {
+            red: 0.5,
+            green: 0.5,
+            blue: 0.5,
+        });
+
+        let resolved = material.resolve_at(&object, point, 0.0);
+
+        assert_eq!(
+            resolved.pattern,
+            Pattern3D::Solid(Color {
+                red: 0.5,
+                green: 0.0,
+                blue: 0.5,
+            })
+        );
+        assert_approx!(resolved.reflectivity, 0.5);
+    }
+
+    #[test]
+    fn a_blended_material_follows_the_mask_pattern() {
+        let object = Shape::Sphere(Default::default());
+        let material = Material {
+            blend: Some(Arc::new(MaterialBlend {
+                material: Material {
+                    reflectivity: 1.0,
+                    ..Default::default()
+                },
+                mask: Pattern3D::Stripe(Pattern3DSpec::new(
+                    color::consts::WHITE,
+                    color::consts::BLACK,
+                    Default::default(),
+                )),
+            })),
+            ..Default::default()
+        };
+
+        let reflectivity_at = |x| {
+            material
+                .resolve_at(&object, Point::new(x, 0.0, 0.0), 0.0)
+                .reflectivity
+        };
+
+        assert_approx!(reflectivity_at(0.5), 1.0);
+        assert_approx!(reflectivity_at(1.5), 0.0);
+    }
+
+    #[test]
+    fn deserializing_a_material_with_dirt() {
+        let material: Material = serde_json::from_str(
+            r#"{ "dirt": { "color": { "red": 255, "green": 0, "blue": 0 }, "distance": 0.5 } }"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            material.dirt,
+            Some(Dirt {
+                color: color::consts::RED,
+                distance: 0.5,
+                ..Default::default()
+            })
+        );
+    }
+
+    #[test]
+    fn resolving_a_blended_material_keeps_the_dirt_of_the_visible_layer() {
+        let (object, _, point) = test_object_material_point();
+        let dirt = Some(Dirt::default());
+
+        let mut material = blended_material(color::consts::WHITE);
+        material.dirt = dirt;
+
+        assert_eq!(material.resolve_at(&object, point, 0.0).dirt, None);
+
+        material = blended_material(color::consts::BLACK);
+        material.dirt = dirt;
+
+        assert_eq!(material.resolve_at(&object, point, 0.0).dirt, dirt);
+    }
+
+    #[test]
+    fn deserializing_a_blended_material() {
+        assert_de_tokens(
+            &blended_material(color::consts::WHITE),
+            &[
+                Token::Struct {
+                    name: "MaterialDeserializer",
+                    len: 4,
+                },
+                Token::Str("pattern"),
+                Token::Some,
+                Token::Map { len: Some(2) },
+                Token::Str("type"),
+                Token::Str("solid"),
+                Token::Str("color"),
+                Token::Map { len: Some(3) },
+                Token::Str("red"),
+                Token::U8(255),
+                Token::Str("green"),
+                Token::U8(0),
+                Token::Str("blue"),
+                Token::U8(0),
+                Token::MapEnd,
+                Token::MapEnd,
+                Token::Str("reflectivity"),
+                Token::Some,
+                Token::F64(0.0),
+                Token::Str("specular"),
+                Token::Some,
+                Token::F64(0.0),
+                Token::Str("blend"),
+                Token::Some,
+                Token::Struct {
+                    name: "MaterialBlend",
+                    len: 2,
+                },
+                Token::Str("material"),
+                Token::Struct {
+                    name: "MaterialDeserializer",
+                    len: 3,
+                },
+                Token::Str("pattern"),
+                Token::Some,
+                Token::Map { len: Some(2) },
+                Token::Str("type"),
+                Token::Str("solid"),
+                Token::Str("color"),
+                Token::Map { len: Some(3) },
+                Token::Str("red"),
+                Token::U8(0),
+                Token::Str("green"),
+                Token::U8(0),
+                Token::Str("blue"),
+                Token::U8(255),
+                Token::MapEnd,
+                Token::MapEnd,
+                Token::Str("reflectivity"),
+                Token::Some,
+                Token::F64(1.0),
+                Token::Str("specular"),
+                Token::Some,
+                Token::F64(1.0),
+                Token::StructEnd,
+                Token::Str("mask"),
+                Token::Map { len: Some(2) },
+                Token::Str("type"),
+                Token::Str("solid"),
+                Token::Str("color"),
+                Token::Map { len: Some(3) },
+                Token::Str("red"),
+                Token::U8(255),
+                Token::Str("green"),
+                Token::U8(255),
+                Token::Str("blue"),
+                Token::U8(255),
+                Token::MapEnd,
+                Token::MapEnd,
+                Token::StructEnd,
+                Token::StructEnd,
+            ],
+        );
+    }
 }
@@ -1,10 +1,16 @@
-use std::num::NonZeroUsize;
+use std::{collections::HashMap, num::NonZeroUsize, sync::Arc};
 
-use indicatif::ProgressBar;
 use thiserror::Error;
 
 use crate::{
-    shape::{Group, GroupBuilder, Shape, SmoothTriangle, Triangle, TriangleBuilder},
+    color::Color,
+    material::Material,
+    pattern::Pattern3D,
+    progress::Stopwatch,
+    shape::{
+        Group, GroupBuilder, Instance, InstanceBuilder, Shape, ShapeBuilder, SmoothTriangle,
+        Sphere, Tangent, Triangle, TriangleBuilder,
+    },
     transform::Transform,
     tuple::{Point, Vector},
 };
@@ -12,6 +18,9 @@ use crate::{
 /// Minimum number of vertices required to create a polygon.
 pub const MIN_POLYGON_VERTICES: usize = 3;
 
+/// Number of spheres below which the groups of a point cloud aren't divided any further.
+const POINT_CLOUD_GROUP_SIZE: usize = 8;
+
 /// The error type when trying to parse a model.
 ///
 /// Errors originate from the model spec format itself.
@@ -54,6 +63,28 @@ pub enum ErrorKind {
     /// The vertex declaration doesn't have the specified component.
     #[error("missing field: `{name}`")]
     MissingField { name: &'static str },
+
+    /// The header of a PLY point cloud is malformed.
+    #[error("invalid PLY header")]
+    InvalidHeader,
+
+    /// The points of a PLY point cloud are stored in a format other than ASCII or binary.
+    #[error("unsupported PLY format: `{0}`")]
+    UnsupportedFormat(String),
+
+    /// A point cloud that must be text, which is every cloud other than a binary PLY file, isn't
+    /// valid UTF-8.
+    #[error("invalid UTF-8 text")]
+    InvalidText,
+
+    /// A PLY point cloud has less points than it's header declares.
+    #[error("expected `{expected}` points but found `{found}`")]
+    MissingPoints { expected: usize, found: usize },
+
+    /// The radius of the points of a cloud is too small to scale a sphere to, which is reported
+    /// at the first line of the cloud.
+    #[error("invalid radius for the points: `{0}`")]
+    InvalidRadius(f64),
 }
 
 /// In-memory Representation of a 3D model
@@ -62,6 +93,16 @@ pub enum ErrorKind {
 /// format](https://en.wikipedia.org/wiki/Wavefront_.obj_file), but maybe more formats are going to
 /// be added in the future.
 ///
+/// Vertices can have a `red green blue` color from `0.0` to `1.0` after their coordinates, like
+/// the results of a simulation exported by most analysis tools. Triangles whose vertices all have
+/// a color blend them across their surface, tinting the color of their material.
+///
+/// The texture coordinates of the faces of a model are kept on it's triangles, along with the
+/// [tangents](crate::shape::Tangent) that tangent-space normal maps are given in, which are
+/// averaged across the triangles that share a vertex. Textures with a
+/// [mesh](crate::pattern::UvMapping::Mesh) mapping are looked up at them, and faces without
+/// texture coordinates get them projected from their vertices instead.
+///
 /// Keep in mind that models get loaded the program runs, there's no caching of previously loaded
 /// models, which can be a performance drawback for really large models. This is a possible future
 /// optimization.
@@ -96,6 +137,8 @@ pub struct Model {
     groups: Vec<PolygonsGroup>,
     normals: Vec<Vector>,
     vertices: Vec<Point>,
+    vertex_colors: Vec<Option<Color>>,
+    texture_coordinates: Vec<(f64, f64)>,
     transform: Transform,
 }
 
@@ -111,12 +154,132 @@ pub struct OBJModelBuilder<'a> {
     pub transform: Transform,
 }
 
+/// Point cloud, such as a LiDAR scan, drawn as small spheres with the color of each point.
+///
+/// Clouds are read from [XYZ](https://en.wikipedia.org/wiki/XYZ_file_format) files, with a point
+/// per line given by it's `x y z` coordinates and optionally it's `red green blue` color from `0` to
+/// `255`, or from ASCII and binary [PLY](https://paulbourke.net/dataformats/ply/) files, which are
+/// told apart by their `ply` header. The colors of a PLY file are read from the `red`, `green` and
+/// `blue` properties of it's vertices, either as integers from `0` to `255` or as floating point
+/// numbers from `0.0` to `1.0`. Points without a color keep the color of the material of the cloud.
+/// List properties, like the indices of faces, are skipped over.
+///
+/// Each point is an [Instance] of a sphere shared by every point of the same color, and the
+/// instances are divided into a hierarchy of groups as they're converted to a [Group], so rays
+/// only check the spheres around them even for clouds with millions of points.
+///
+/// # Examples
+///
+/// A point cloud must be built from a [PointCloudBuilder].
+///
+/// ```
+/// use raytracer::{
+///     model::{PointCloud, PointCloudBuilder},
+///     shape::Group,
+/// };
+///
+/// let cloud_spec = b"\
+/// 0.0 0.0 0.0 255 0 0
+/// 1.0 0.0 0.0 0 255 0
+/// 0.0 1.0 0.0";
+///
+/// let cloud = PointCloud::try_from(PointCloudBuilder {
+///     cloud_spec,
+///     radius: 0.1,
+///     material: Default::default(),
+///     transform: Default::default(),
+/// })
+/// .unwrap();
+///
+/// assert_eq!(cloud.len(), 3);
+///
+/// let group = Group::from(cloud);
+/// ```
+///
+#[derive(Debug, PartialEq)]
+pub struct PointCloud {
+    points: Vec<CloudPoint>,
+    radius: f64,
+    material: Material,
+    transform: Transform,
+}
+
+/// Builder for a [PointCloud].
+#[derive(Clone)]
+pub struct PointCloudBuilder<'a> {
+    /// Contents of a point cloud file in either XYZ, ASCII PLY or binary PLY format.
+    pub cloud_spec: &'a [u8],
+
+    /// Radius of the sphere drawn at each point.
+    pub radius: f64,
+
+    /// Material of the spheres, which takes the color of each point when it has one.
+    pub material: Material,
+
+    /// Transformation that's going to be applied to the cloud once it's converted to a [Group].
+    pub transform: Transform,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+struct CloudPoint {
+    position: Point,
+    color: Option<Color>,
+}
+
+/// Element declared in the header of a PLY file, with it's properties in the order they're
+/// stored in each row.
+struct PlyElement {
+    name: String,
+    count: usize,
+    properties: Vec<PlyProperty>,
+}
+
+/// Property of an element of a PLY file, which is a list when it has the type of the number of
+/// items in the list.
+struct PlyProperty {
+    name: String,
+    scalar: PlyScalar,
+    list: Option<PlyScalar>,
+}
+
+/// Type of the values of a property of a PLY file.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum PlyScalar {
+    Int8,
+    UInt8,
+    Int16,
+    UInt16,
+    Int32,
+    UInt32,
+    Float32,
+    Float64,
+}
+
+/// How the elements of a PLY file are stored after it's header.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum PlyFormat {
+    Ascii,
+    LittleEndian,
+    BigEndian,
+}
+
+/// Rows of the elements of a PLY file that haven't been read yet.
+enum PlyRows<'a> {
+    Ascii(std::str::Lines<'a>),
+    Binary(&'a [u8]),
+}
+
 #[derive(Copy, Clone, Debug, PartialEq)]
 struct FaceVertex {
     vertex: Point,
     normal: Option<Vector>,
+    color: Option<Color>,
+    uv: Option<(f64, f64)>,
 }
 
+/// Exact position, normal and texture coordinates of a vertex of a model.
+type VertexKey = [u64; 8];
+
 #[derive(Debug, PartialEq)]
 struct PolygonsGroup {
     group: Group,
@@ -139,12 +302,12 @@ impl TryFrom<OBJModelBuilder<'_>> for Model {
 
         let mut normals = vec![];
         let mut vertices = vec![];
+        let mut vertex_colors = vec![];
+        let mut texture_coordinates = vec![];
 
-        let progress_bar = if std::env::args().any(|arg| arg == "--progress") {
-            ProgressBar::new_spinner()
-        } else {
-            ProgressBar::hidden()
-        };
+        let stopwatch = Stopwatch::start();
+
+        log::debug!("parsing an OBJ model");
 
         for (line_nr, line) in content.lines().enumerate() {
             let propagate_line_err = |kind| Error { kind, line_nr };
@@ -155,16 +318,40 @@ impl TryFrom<OBJModelBuilder<'_>> for Model {
 
             match data_type {
                 Some("v") => {
-                    let (x, y, z) = Self::parse_coordinate(data).map_err(propagate_line_err)?;
+                    let mut data = data;
+                    let (x, y, z) =
+                        Self::parse_coordinate(&mut data).map_err(propagate_line_err)?;
+
+                    // A single value after the coordinates is the optional weight of the vertex
+                    // rather than a color.
+                    let rest: Vec<_> = data.collect();
+                    let color = match rest.len() {
+                        0 | 1 => None,
+                        _ => {
+                            Self::parse_color(rest.into_iter(), 1.0).map_err(propagate_line_err)?
+                        }
+                    };
+
                     vertices.push(Point::new(x, y, z));
+                    vertex_colors.push(color);
                 }
                 Some("vn") => {
                     let (x, y, z) = Self::parse_coordinate(data).map_err(propagate_line_err)?;
                     normals.push(Vector::new(x, y, z));
                 }
+                Some("vt") => {
+                    let uv = Self::parse_texture_coordinate(data).map_err(propagate_line_err)?;
+                    texture_coordinates.push(uv);
+                }
                 Some("f") => {
-                    let face =
-                        Self::parse_face(data, &normals, &vertices).map_err(propagate_line_err)?;
+                    let face = Self::parse_face(
+                        data,
+                        &normals,
+                        &vertices,
+                        &vertex_colors,
+                        &texture_coordinates,
+                    )
+                    .map_err(propagate_line_err)?;
 
                     // There's always going to be a valid group in the group's queue, as it always
                     // contains at least the "__default" group.
@@ -176,14 +363,25 @@ impl TryFrom<OBJModelBuilder<'_>> for Model {
                 }
                 _ => (),
             }
+        }
+
+        log::info!(
+            "parsed an OBJ model with {} vertices, {} normals and {} groups in {stopwatch}",
+            vertices.len(),
+            normals.len(),
+            groups.len()
+        );
 
-            progress_bar.inc(1);
+        if !texture_coordinates.is_empty() {
+            Self::generate_tangents(&mut groups);
         }
 
         Ok(Model {
             groups,
             normals,
             vertices,
+            vertex_colors,
+            texture_coordinates,
             transform,
         })
     }
@@ -191,6 +389,8 @@ impl TryFrom<OBJModelBuilder<'_>> for Model {
 
 impl From<Model> for Group {
     fn from(model: Model) -> Self {
+        log::debug!("building the group of an OBJ model");
+
         let group_builder = GroupBuilder {
             children: model
                 .groups
@@ -235,10 +435,35 @@ impl Model {
         Ok((x, y, z))
     }
 
+    /// Parses an optional `red green blue` color, where each component goes from `0` to `max`.
+    fn parse_color<'a, T>(mut data: T, max: f64) -> Result<Option<Color>, ErrorKind>
+    where
+        T: Iterator<Item = &'a str>,
+    {
+        let Some(red) = data.next() else {
+            return Ok(None);
+        };
+
+        let green = data
+            .next()
+            .ok_or(ErrorKind::MissingField { name: "green" })?;
+        let blue = data
+            .next()
+            .ok_or(ErrorKind::MissingField { name: "blue" })?;
+
+        Ok(Some(Color {
+            red: red.parse::<f64>()? / max,
+            green: green.parse::<f64>()? / max,
+            blue: blue.parse::<f64>()? / max,
+        }))
+    }
+
     fn parse_face<'a, T>(
         data: T,
         saved_normals: &[Vector],
         saved_vertices: &[Point],
+        saved_colors: &[Option<Color>],
+        saved_uvs: &[(f64, f64)],
     ) -> Result<Vec<Shape>, ErrorKind>
     where
         T: Iterator<Item = &'a str>,
@@ -258,16 +483,31 @@ impl Model {
             // be empty, but it is there. Empty elements are going to be handled when parsing them into
             // numbers from `get_face_element()`.
             #[allow(clippy::unwrap_used)]
-            let vertex = Self::get_face_element(fields.next().unwrap(), saved_vertices)?;
+            let vertex_index = fields.next().unwrap();
+            let vertex = Self::get_face_element(vertex_index, saved_vertices)?;
 
-            fields.next();
+            // Vertices without a color don't have to be stored along with the ones that have it.
+            let color = Self::get_face_element(vertex_index, saved_colors)
+                .ok()
+                .flatten();
+
+            // Texture coordinates that aren't declared are ignored, like the ones of models that
+            // were written before textures were supported.
+            let uv = fields
+                .next()
+                .and_then(|uv_index| Self::get_face_element(uv_index, saved_uvs).ok());
 
             let normal = match fields.next() {
                 Some(normal_index) => Some(Self::get_face_element(normal_index, saved_normals)?),
                 None => None,
             };
 
-            vertices.push(FaceVertex { vertex, normal });
+            vertices.push(FaceVertex {
+                vertex,
+                normal,
+                color,
+                uv,
+            });
         }
 
         Self::fan_triangulation(vertices)
@@ -303,6 +543,18 @@ impl Model {
                 material: Default::default(),
                 vertices: [v0.vertex, v1.vertex, v2.vertex],
             }) {
+                let triangle = match (v0.color, v1.color, v2.color) {
+                    (Some(c0), Some(c1), Some(c2)) => triangle.with_vertex_colors([c0, c1, c2]),
+                    _ => triangle,
+                };
+
+                let triangle = match (v0.uv, v1.uv, v2.uv) {
+                    (Some(uv0), Some(uv1), Some(uv2)) => {
+                        triangle.with_texture_coordinates([uv0, uv1, uv2])
+                    }
+                    _ => triangle,
+                };
+
                 let triangle =
                     if let (Some(n0), Some(n1), Some(n2)) = (v0.normal, v1.normal, v2.normal) {
                         Shape::SmoothTriangle(SmoothTriangle {
@@ -322,6 +574,86 @@ impl Model {
         Ok(triangles)
     }
 
+    fn parse_texture_coordinate<'a, T>(mut data: T) -> Result<(f64, f64), ErrorKind>
+    where
+        T: Iterator<Item = &'a str>,
+    {
+        let u = data
+            .next()
+            .ok_or(ErrorKind::MissingField { name: "u" })?
+            .parse::<f64>()?;
+
+        // The `v` coordinate is optional for one-dimensional textures.
+        let v = match data.next() {
+            Some(v) => v.parse::<f64>()?,
+            None => 0.0,
+        };
+
+        Ok((u, v))
+    }
+
+    /// Averages the tangents of the triangles at the vertices they share, so normal maps are
+    /// smooth across the model.
+    ///
+    /// Like [MikkTSpace](http://www.mikktspace.com), vertices are only shared by triangles that
+    /// have the same position, normal and texture coordinates at them, so the tangents aren't
+    /// averaged across the seams of a texture or the hard edges of a model.
+    ///
+    fn generate_tangents(groups: &mut [PolygonsGroup]) {
+        let mut sums: HashMap<VertexKey, (Vector, Vector)> = HashMap::new();
+
+        for shape in groups.iter().flat_map(|group| &group.group.children) {
+            let Some((triangle, normals)) = triangle_corners(shape) else {
+                continue;
+            };
+
+            let (Some(uvs), Some((tangent, bitangent))) = (
+                triangle.texture_coordinates(),
+                triangle.tangent_and_bitangent(),
+            ) else {
+                continue;
+            };
+
+            for ((position, normal), uv) in triangle.vertices().into_iter().zip(normals).zip(uvs) {
+                let zero = Vector::new(0.0, 0.0, 0.0);
+                let sum = sums
+                    .entry(vertex_key(position, normal, uv))
+                    .or_insert((zero, zero));
+
+                *sum = (sum.0 + tangent, sum.1 + bitangent);
+            }
+        }
+
+        for shape in groups
+            .iter_mut()
+            .flat_map(|group| &mut group.group.children)
+        {
+            let Some((triangle, normals)) = triangle_corners(shape) else {
+                continue;
+            };
+
+            let (Some(uvs), Some(mut tangents)) =
+                (triangle.texture_coordinates(), triangle.tangents())
+            else {
+                continue;
+            };
+
+            let corners = triangle.vertices().into_iter().zip(normals).zip(uvs);
+
+            for (tangent, ((position, normal), uv)) in tangents.iter_mut().zip(corners) {
+                if let Some((t, b)) = sums.get(&vertex_key(position, normal, uv)) {
+                    *tangent = Tangent::new(normal, *t, *b);
+                }
+            }
+
+            match shape {
+                Shape::Triangle(triangle) => triangle.tangents = Some(tangents),
+                Shape::SmoothTriangle(smooth) => smooth.triangle.tangents = Some(tangents),
+                _ => (),
+            }
+        }
+    }
+
     fn parse_group<'a, T>(mut data: T) -> Result<PolygonsGroup, ErrorKind>
     where
         T: Iterator<Item = &'a str>,
@@ -337,6 +669,497 @@ impl Model {
     }
 }
 
+impl TryFrom<PointCloudBuilder<'_>> for PointCloud {
+    type Error = Error;
+
+    fn try_from(builder: PointCloudBuilder<'_>) -> Result<Self, Self::Error> {
+        let PointCloudBuilder {
+            cloud_spec,
+            radius,
+            material,
+            transform,
+        } = builder;
+
+        if Transform::scaling(radius, radius, radius).is_err() {
+            return Err(Error {
+                kind: ErrorKind::InvalidRadius(radius),
+                line_nr: 0,
+            });
+        }
+
+        let stopwatch = Stopwatch::start();
+
+        let points = if cloud_spec.trim_ascii_start().starts_with(b"ply") {
+            log::debug!("parsing a PLY point cloud");
+            Self::parse_ply(cloud_spec)?
+        } else {
+            log::debug!("parsing an XYZ point cloud");
+            Self::parse_xyz(text(cloud_spec, 0)?)?
+        };
+
+        log::info!(
+            "parsed a point cloud with {} points in {stopwatch}",
+            points.len()
+        );
+
+        Ok(Self {
+            points,
+            radius,
+            material,
+            transform,
+        })
+    }
+}
+
+impl From<PointCloud> for Group {
+    fn from(cloud: PointCloud) -> Self {
+        log::debug!("building the group of a point cloud");
+
+        let PointCloud {
+            points,
+            radius,
+            material,
+            transform,
+        } = cloud;
+
+        // The radius was already checked when the cloud was parsed.
+        #[allow(clippy::unwrap_used)]
+        let scaling = Transform::scaling(radius, radius, radius).unwrap();
+
+        // Points of the same color share a single sphere, so a cloud only stores one material for
+        // each of it's colors instead of one for each of it's points.
+        let mut prototypes: HashMap<Option<[u64; 3]>, Arc<Shape>> = HashMap::new();
+
+        let spheres = points.into_iter().map(|point| {
+            let Point(position) = point.position;
+            let key = point
+                .color
+                .map(|color| [color.red, color.green, color.blue].map(f64::to_bits));
+
+            let prototype = prototypes.entry(key).or_insert_with(|| {
+                let mut material = material.clone();
+                if let Some(color) = point.color {
+                    material.pattern = Pattern3D::Solid(color);
+                }
+
+                Arc::new(Shape::Sphere(Sphere::from(ShapeBuilder {
+                    material,
+                    transform: scaling,
+                })))
+            });
+
+            Shape::Instance(Instance::from(InstanceBuilder {
+                prototype: Arc::clone(prototype),
+                transform: Transform::translation(position.x, position.y, position.z),
+                material: None,
+            }))
+        });
+
+        let mut group = Self::from(GroupBuilder {
+            children: spheres,
+            transform,
+        });
+
+        group.divide(POINT_CLOUD_GROUP_SIZE);
+        group
+    }
+}
+
+impl TryFrom<PointCloudBuilder<'_>> for Group {
+    type Error = Error;
+
+    fn try_from(builder: PointCloudBuilder<'_>) -> Result<Self, Self::Error> {
+        let cloud = PointCloud::try_from(builder)?;
+        Ok(Group::from(cloud))
+    }
+}
+
+impl PointCloud {
+    /// Number of points in the cloud.
+    pub fn len(&self) -> usize {
+        self.points.len()
+    }
+
+    /// Whether the cloud has no points at all.
+    pub fn is_empty(&self) -> bool {
+        self.points.is_empty()
+    }
+
+    fn parse_xyz(content: &str) -> Result<Vec<CloudPoint>, Error> {
+        let mut points = vec![];
+
+        for (line_nr, line) in content.lines().enumerate() {
+            let propagate_line_err = |kind| Error { kind, line_nr };
+            let line = line.trim();
+
+            if line.is_empty() || line.starts_with('#') || line.starts_with("//") {
+                continue;
+            }
+
+            let mut fields = line.split_whitespace();
+            let (x, y, z) = Model::parse_coordinate(&mut fields).map_err(propagate_line_err)?;
+
+            let color = Model::parse_color(fields, 255.0).map_err(propagate_line_err)?;
+
+            points.push(CloudPoint {
+                position: Point::new(x, y, z),
+                color,
+            });
+        }
+
+        Ok(points)
+    }
+
+    fn parse_ply(content: &[u8]) -> Result<Vec<CloudPoint>, Error> {
+        let header_err = |line_nr| Error {
+            kind: ErrorKind::InvalidHeader,
+            line_nr,
+        };
+
+        // The header is always text, even when the elements after it are binary, so it's read
+        // line by line up to the end of the header.
+        let mut rest = content;
+        let mut next_line = |line_nr: usize| -> Result<&str, Error> {
+            let end = rest
+                .iter()
+                .position(|&byte| byte == b'\n')
+                .map_or(rest.len(), |end| end + 1);
+            let (line, after) = rest.split_at(end);
+            rest = after;
+
+            if line.is_empty() {
+                return Err(header_err(line_nr.saturating_sub(1)));
+            }
+
+            std::str::from_utf8(line).map_err(|_| header_err(line_nr))
+        };
+
+        if next_line(0)?.trim() != "ply" {
+            return Err(header_err(0));
+        }
+
+        let mut format = PlyFormat::Ascii;
+        let mut elements: Vec<PlyElement> = vec![];
+        let mut header_end = 0;
+
+        for line_nr in 1.. {
+            let line = next_line(line_nr)?;
+            let fields: Vec<_> = line.split_whitespace().collect();
+            header_end = line_nr;
+
+            match fields.as_slice() {
+                ["format", "ascii", _] => format = PlyFormat::Ascii,
+                ["format", "binary_little_endian", _] => format = PlyFormat::LittleEndian,
+                ["format", "binary_big_endian", _] => format = PlyFormat::BigEndian,
+                ["format", format, _] => {
+                    return Err(Error {
+                        kind: ErrorKind::UnsupportedFormat(format.to_string()),
+                        line_nr,
+                    });
+                }
+                ["comment", ..] | ["obj_info", ..] | [] => (),
+                ["element", name, count] => elements.push(PlyElement {
+                    name: name.to_string(),
+                    count: count.parse().map_err(|_| header_err(line_nr))?,
+                    properties: vec![],
+                }),
+                ["property", "list", count, item, name] => {
+                    let element = elements.last_mut().ok_or_else(|| header_err(line_nr))?;
+
+                    element.properties.push(PlyProperty {
+                        name: name.to_string(),
+                        scalar: PlyScalar::parse(item).ok_or_else(|| header_err(line_nr))?,
+                        list: Some(PlyScalar::parse(count).ok_or_else(|| header_err(line_nr))?),
+                    });
+                }
+                ["property", scalar, name] => {
+                    let element = elements.last_mut().ok_or_else(|| header_err(line_nr))?;
+
+                    element.properties.push(PlyProperty {
+                        name: name.to_string(),
+                        scalar: PlyScalar::parse(scalar).ok_or_else(|| header_err(line_nr))?,
+                        list: None,
+                    });
+                }
+                ["end_header"] => break,
+                _ => return Err(header_err(line_nr)),
+            }
+        }
+
+        let mut rows = match format {
+            PlyFormat::Ascii => PlyRows::Ascii(text(rest, header_end + 1)?.lines()),
+            PlyFormat::LittleEndian | PlyFormat::BigEndian => PlyRows::Binary(rest),
+        };
+
+        let mut points = vec![];
+        let mut last_line_nr = header_end;
+
+        for element in elements {
+            let index_of = |name| {
+                element
+                    .properties
+                    .iter()
+                    .position(|property| property.name == name)
+            };
+
+            let mut coordinates = [0; 3];
+            if element.name == "vertex" {
+                for (coordinate, name) in coordinates.iter_mut().zip(["x", "y", "z"]) {
+                    *coordinate = index_of(name).ok_or(Error {
+                        kind: ErrorKind::MissingField { name },
+                        line_nr: header_end,
+                    })?;
+                }
+            }
+
+            let colors = match [
+                index_of("red").or_else(|| index_of("diffuse_red")),
+                index_of("green").or_else(|| index_of("diffuse_green")),
+                index_of("blue").or_else(|| index_of("diffuse_blue")),
+            ] {
+                [Some(red), Some(green), Some(blue)] => Some([red, green, blue]),
+                _ => None,
+            };
+
+            for found in 0..element.count {
+                let missing = Error {
+                    kind: ErrorKind::MissingPoints {
+                        expected: element.count,
+                        found,
+                    },
+                    line_nr: last_line_nr,
+                };
+
+                if format == PlyFormat::Ascii {
+                    last_line_nr += 1;
+                }
+
+                let values = rows
+                    .next(&element, format)
+                    .map_err(|kind| Error {
+                        kind,
+                        line_nr: last_line_nr,
+                    })?
+                    .ok_or(missing)?;
+
+                if element.name != "vertex" {
+                    continue;
+                }
+
+                let point = Self::parse_ply_point(&values, &element, coordinates, colors).map_err(
+                    |kind| Error {
+                        kind,
+                        line_nr: last_line_nr,
+                    },
+                )?;
+
+                points.push(point);
+            }
+        }
+
+        Ok(points)
+    }
+
+    fn parse_ply_point(
+        values: &[Option<f64>],
+        element: &PlyElement,
+        coordinates: [usize; 3],
+        colors: Option<[usize; 3]>,
+    ) -> Result<CloudPoint, ErrorKind> {
+        let value_at = |index: usize, name| -> Result<f64, ErrorKind> {
+            values
+                .get(index)
+                .copied()
+                .flatten()
+                .ok_or(ErrorKind::MissingField { name })
+        };
+
+        // Integer colors go from 0 to 255, while floating point ones go from 0 to 1.
+        let color_at = |index: usize, name| -> Result<f64, ErrorKind> {
+            let value = value_at(index, name)?;
+
+            Ok(if element.properties[index].scalar.is_integer() {
+                value / 255.0
+            } else {
+                value
+            })
+        };
+
+        let color = match colors {
+            Some([red, green, blue]) => Some(Color {
+                red: color_at(red, "red")?,
+                green: color_at(green, "green")?,
+                blue: color_at(blue, "blue")?,
+            }),
+            None => None,
+        };
+
+        Ok(CloudPoint {
+            position: Point::new(
+                value_at(coordinates[0], "x")?,
+                value_at(coordinates[1], "y")?,
+                value_at(coordinates[2], "z")?,
+            ),
+            color,
+        })
+    }
+}
+
+impl PlyScalar {
+    fn parse(name: &str) -> Option<Self> {
+        Some(match name {
+            "char" | "int8" => Self::Int8,
+            "uchar" | "uint8" => Self::UInt8,
+            "short" | "int16" => Self::Int16,
+            "ushort" | "uint16" => Self::UInt16,
+            "int" | "int32" => Self::Int32,
+            "uint" | "uint32" => Self::UInt32,
+            "float" | "float32" => Self::Float32,
+            "double" | "float64" => Self::Float64,
+            _ => return None,
+        })
+    }
+
+    fn is_integer(self) -> bool {
+        !matches!(self, Self::Float32 | Self::Float64)
+    }
+
+    fn size(self) -> usize {
+        match self {
+            Self::Int8 | Self::UInt8 => 1,
+            Self::Int16 | Self::UInt16 => 2,
+            Self::Int32 | Self::UInt32 | Self::Float32 => 4,
+            Self::Float64 => 8,
+        }
+    }
+
+    /// Reads a value from the front of `bytes`, or returns `None` if there aren't enough bytes
+    /// left for it.
+    fn read(self, bytes: &mut &[u8], format: PlyFormat) -> Option<f64> {
+        let (value, rest) = bytes.split_at_checked(self.size())?;
+        *bytes = rest;
+
+        macro_rules! from_bytes {
+            ($type:ty) => {{
+                let value = value.try_into().ok()?;
+
+                (if format == PlyFormat::BigEndian {
+                    <$type>::from_be_bytes(value)
+                } else {
+                    <$type>::from_le_bytes(value)
+                }) as f64
+            }};
+        }
+
+        Some(match self {
+            Self::Int8 => from_bytes!(i8),
+            Self::UInt8 => from_bytes!(u8),
+            Self::Int16 => from_bytes!(i16),
+            Self::UInt16 => from_bytes!(u16),
+            Self::Int32 => from_bytes!(i32),
+            Self::UInt32 => from_bytes!(u32),
+            Self::Float32 => from_bytes!(f32),
+            Self::Float64 => from_bytes!(f64),
+        })
+    }
+}
+
+impl<'a> PlyRows<'a> {
+    /// Reads the values of the next row of `element`, or returns `None` if the file ends before
+    /// it. Lists are skipped over and have no value, and so do the properties missing at the end
+    /// of a row of an ASCII file.
+    fn next(
+        &mut self,
+        element: &PlyElement,
+        format: PlyFormat,
+    ) -> Result<Option<Vec<Option<f64>>>, ErrorKind> {
+        let mut values = Vec::with_capacity(element.properties.len());
+
+        match self {
+            Self::Ascii(lines) => {
+                let Some(line) = lines.next() else {
+                    return Ok(None);
+                };
+
+                let mut fields = line.split_whitespace();
+
+                for property in &element.properties {
+                    let Some(field) = fields.next() else {
+                        break;
+                    };
+
+                    let value = field.parse::<f64>()?;
+
+                    if property.list.is_some() {
+                        fields.by_ref().take(value as usize).for_each(drop);
+                        values.push(None);
+                    } else {
+                        values.push(Some(value));
+                    }
+                }
+            }
+            Self::Binary(bytes) => {
+                for property in &element.properties {
+                    let Some(count) = property.list else {
+                        let Some(value) = property.scalar.read(bytes, format) else {
+                            return Ok(None);
+                        };
+
+                        values.push(Some(value));
+                        continue;
+                    };
+
+                    let Some(count) = count.read(bytes, format) else {
+                        return Ok(None);
+                    };
+
+                    let Some(rest) = bytes.get(count as usize * property.scalar.size()..) else {
+                        return Ok(None);
+                    };
+
+                    *bytes = rest;
+                    values.push(None);
+                }
+            }
+        }
+
+        Ok(Some(values))
+    }
+}
+
+/// Reads bytes that must be text, starting at the line `first_line_nr` of a file.
+fn text(bytes: &[u8], first_line_nr: usize) -> Result<&str, Error> {
+    std::str::from_utf8(bytes).map_err(|error| Error {
+        kind: ErrorKind::InvalidText,
+        line_nr: first_line_nr
+            + bytes[..error.valid_up_to()]
+                .iter()
+                .filter(|&&byte| byte == b'\n')
+                .count(),
+    })
+}
+
+/// Flat triangle of a shape, if it's a triangle, and the unit normals at it's vertices.
+fn triangle_corners(shape: &Shape) -> Option<(&Triangle, [Vector; 3])> {
+    match shape {
+        Shape::Triangle(triangle) => Some((triangle, [triangle.normal(); 3])),
+        Shape::SmoothTriangle(smooth) => {
+            let fallback = smooth.triangle.normal();
+            let normals = smooth
+                .normals()
+                .map(|normal| normal.normalize().unwrap_or(fallback));
+
+            Some((&smooth.triangle, normals))
+        }
+        _ => None,
+    }
+}
+
+fn vertex_key(position: Point, normal: Vector, (u, v): (f64, f64)) -> VertexKey {
+    let (Point(p), Vector(n)) = (position, normal);
+    [p.x, p.y, p.z, n.x, n.y, n.z, u, v].map(f64::to_bits)
+}
+
 #[cfg(test)]
 mod tests {
     use crate::shape::TriangleBuilder;
@@ -478,7 +1301,7 @@ f 1 3 4";
     fn trying_to_parse_a_face_with_insufficient_vertices() {
         let input = "f ".split_whitespace();
 
-        let err = Model::parse_face(input, &[], &[]).unwrap_err();
+        let err = Model::parse_face(input, &[], &[], &[], &[]).unwrap_err();
 
         assert_eq!(err, ErrorKind::InsufficientVertices);
     }
@@ -530,7 +1353,7 @@ f 1 3 4";
 
         let input = "1 2 3".split_whitespace();
 
-        let tri = Model::parse_face(input, &[], &vertices).unwrap();
+        let tri = Model::parse_face(input, &[], &vertices, &[], &[]).unwrap();
 
         assert_eq!(
             tri[0],
@@ -741,7 +1564,7 @@ f 1/0/3 2/102/1 3/14/2";
 
         let input = "1//3 2//2 3//1".split_whitespace();
 
-        let tri = Model::parse_face(input, &normals, &vertices).unwrap();
+        let tri = Model::parse_face(input, &normals, &vertices, &[], &[]).unwrap();
 
         assert_eq!(
             tri[0],
@@ -757,4 +1580,479 @@ f 1/0/3 2/102/1 3/14/2";
             })
         );
     }
+
+    fn cloud(cloud_spec: &str) -> Result<PointCloud, Error> {
+        PointCloud::try_from(PointCloudBuilder {
+            cloud_spec: cloud_spec.as_bytes(),
+            radius: 0.5,
+            material: Default::default(),
+            transform: Default::default(),
+        })
+    }
+
+    #[test]
+    fn parsing_an_xyz_point_cloud() {
+        let input = "\
+# x y z red green blue
+1 2 3 255 0 0
+
+-1.5 0.0 2.0";
+
+        let cloud = cloud(input).unwrap();
+
+        assert_eq!(
+            cloud.points,
+            vec![
+                CloudPoint {
+                    position: Point::new(1.0, 2.0, 3.0),
+                    color: Some(Color {
+                        red: 1.0,
+                        green: 0.0,
+                        blue: 0.0,
+                    }),
+                },
+                CloudPoint {
+                    position: Point::new(-1.5, 0.0, 2.0),
+                    color: None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn trying_to_parse_an_xyz_point_with_an_incomplete_color() {
+        assert_eq!(
+            cloud("0 0 0\n1 2 3 255 0"),
+            Err(Error {
+                kind: ErrorKind::MissingField { name: "blue" },
+                line_nr: 1,
+            })
+        );
+    }
+
+    #[test]
+    fn parsing_a_ply_point_cloud() {
+        let input = "\
+ply
+format ascii 1.0
+comment scanned with a LiDAR
+element vertex 2
+property float x
+property float y
+property float z
+property uchar red
+property uchar green
+property uchar blue
+element face 1
+property list uchar int vertex_indices
+end_header
+0 0 0 0 0 255
+1.5 2 -1 255 255 255
+3 0 1 2";
+
+        let cloud = cloud(input).unwrap();
+
+        assert_eq!(cloud.len(), 2);
+        assert_eq!(cloud.points[0].color, Some(crate::color::consts::BLUE));
+        assert_eq!(cloud.points[1].position, Point::new(1.5, 2.0, -1.0));
+        assert_eq!(cloud.points[1].color, Some(crate::color::consts::WHITE));
+    }
+
+    #[test]
+    fn parsing_a_ply_point_cloud_with_floating_point_colors() {
+        let input = "\
+ply
+format ascii 1.0
+element vertex 1
+property float red
+property float green
+property float blue
+property double x
+property double y
+property double z
+end_header
+0.5 0.25 1 1 2 3";
+
+        let cloud = cloud(input).unwrap();
+
+        assert_eq!(
+            cloud.points,
+            vec![CloudPoint {
+                position: Point::new(1.0, 2.0, 3.0),
+                color: Some(Color {
+                    red: 0.5,
+                    green: 0.25,
+                    blue: 1.0,
+                }),
+            }]
+        );
+    }
+
+    #[test]
+    fn parsing_a_ply_point_cloud_with_a_list_in_it_s_vertices() {
+        let input = "\
+ply
+format ascii 1.0
+element vertex 2
+property list uchar int neighbors
+property float x
+property float y
+property float z
+end_header
+2 1 5 1 2 3
+0 4 5 6";
+
+        let cloud = cloud(input).unwrap();
+
+        assert_eq!(cloud.points[0].position, Point::new(1.0, 2.0, 3.0));
+        assert_eq!(cloud.points[1].position, Point::new(4.0, 5.0, 6.0));
+    }
+
+    #[test]
+    fn parsing_a_binary_ply_point_cloud() {
+        let header = |format| {
+            format!(
+                "ply\nformat {format} 1.0\nelement vertex 2\nproperty float x\nproperty float y\n\
+                 property float z\nproperty list uchar int neighbors\nproperty uchar red\n\
+                 property uchar green\nproperty uchar blue\nelement face 1\n\
+                 property list uchar int vertex_indices\nend_header\n"
+            )
+        };
+
+        let body = |to_bytes: fn(f32) -> [u8; 4], int_to_bytes: fn(i32) -> [u8; 4]| {
+            let mut body = vec![];
+
+            for (position, neighbor, color) in [
+                ([0.0, 1.0, 2.0], 1, [255, 0, 0]),
+                ([3.0, 4.5, -5.0], 0, [0, 0, 255]),
+            ] {
+                body.extend(position.into_iter().flat_map(to_bytes));
+                body.push(1);
+                body.extend(int_to_bytes(neighbor));
+                body.extend(color);
+            }
+
+            body.push(3);
+            body.extend([0, 1, 0].into_iter().flat_map(int_to_bytes));
+            body
+        };
+
+        let little_endian = [
+            header("binary_little_endian").into_bytes(),
+            body(f32::to_le_bytes, i32::to_le_bytes),
+        ]
+        .concat();
+        let big_endian = [
+            header("binary_big_endian").into_bytes(),
+            body(f32::to_be_bytes, i32::to_be_bytes),
+        ]
+        .concat();
+
+        for cloud_spec in [&little_endian, &big_endian] {
+            let cloud = PointCloud::try_from(PointCloudBuilder {
+                cloud_spec,
+                radius: 0.5,
+                material: Default::default(),
+                transform: Default::default(),
+            })
+            .unwrap();
+
+            assert_eq!(
+                cloud.points,
+                vec![
+                    CloudPoint {
+                        position: Point::new(0.0, 1.0, 2.0),
+                        color: Some(crate::color::consts::RED),
+                    },
+                    CloudPoint {
+                        position: Point::new(3.0, 4.5, -5.0),
+                        color: Some(crate::color::consts::BLUE),
+                    },
+                ]
+            );
+        }
+
+        // A file that ends in the middle of a point is missing that point, which is reported at the
+        // end of the header as binary rows have no lines.
+        let truncated = &little_endian[..little_endian.len() - 20];
+
+        assert_eq!(
+            PointCloud::try_from(PointCloudBuilder {
+                cloud_spec: truncated,
+                radius: 0.5,
+                material: Default::default(),
+                transform: Default::default(),
+            }),
+            Err(Error {
+                kind: ErrorKind::MissingPoints {
+                    expected: 2,
+                    found: 1,
+                },
+                line_nr: 12,
+            })
+        );
+    }
+
+    #[test]
+    fn trying_to_parse_an_invalid_ply_point_cloud() {
+        assert_eq!(
+            cloud("ply\nformat compressed 1.0\nend_header"),
+            Err(Error {
+                kind: ErrorKind::UnsupportedFormat("compressed".to_string()),
+                line_nr: 1,
+            })
+        );
+
+        assert_eq!(
+            cloud("ply\nformat ascii 1.0\nelement vertex 1\nproperty fixed x\nend_header"),
+            Err(Error {
+                kind: ErrorKind::InvalidHeader,
+                line_nr: 3,
+            })
+        );
+
+        assert_eq!(
+            cloud("ply\nformat ascii 1.0\nelement vertex 2\nproperty float x"),
+            Err(Error {
+                kind: ErrorKind::InvalidHeader,
+                line_nr: 3,
+            })
+        );
+
+        assert_eq!(
+            cloud("ply\nelement vertex 2\nproperty float x\nproperty float y\nend_header\n1 2"),
+            Err(Error {
+                kind: ErrorKind::MissingField { name: "z" },
+                line_nr: 4,
+            })
+        );
+
+        let input = "\
+ply
+element vertex 2
+property float x
+property float y
+property float z
+end_header
+1 2 3";
+
+        assert_eq!(
+            cloud(input),
+            Err(Error {
+                kind: ErrorKind::MissingPoints {
+                    expected: 2,
+                    found: 1,
+                },
+                line_nr: 6,
+            })
+        );
+    }
+
+    #[test]
+    fn trying_to_build_a_point_cloud_with_a_null_radius() {
+        assert_eq!(
+            PointCloud::try_from(PointCloudBuilder {
+                cloud_spec: b"0 0 0",
+                radius: 0.0,
+                material: Default::default(),
+                transform: Default::default(),
+            }),
+            Err(Error {
+                kind: ErrorKind::InvalidRadius(0.0),
+                line_nr: 0,
+            })
+        );
+    }
+
+    #[test]
+    fn a_point_cloud_is_drawn_as_colored_spheres() {
+        let input = (0..100)
+            .map(|i| format!("{i} 0 0 {} 0 0", i % 2 * 255))
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        let group = Group::from(cloud(&input).unwrap());
+
+        let ray = crate::ray::Ray {
+            origin: Point::new(41.0, 0.0, -5.0),
+            direction: Vector::new(0.0, 0.0, 1.0),
+        };
+
+        let shape = Shape::Group(group.clone());
+        let xs = shape.intersect(&ray);
+        let hit = xs.iter().min_by(|a, b| a.t.total_cmp(&b.t)).unwrap();
+
+        crate::assert_approx!(hit.t, 4.5);
+        assert_eq!(
+            hit.object.material().pattern,
+            Pattern3D::Solid(crate::color::consts::RED)
+        );
+
+        // The spheres are divided into groups, so the top level group only has a few children.
+        assert!(group.children.len() < 100);
+
+        // Points of the same color share their sphere.
+        fn prototypes(shape: &Shape, found: &mut Vec<Arc<Shape>>) {
+            match shape {
+                Shape::Group(group) => {
+                    for child in &group.children {
+                        prototypes(child, found);
+                    }
+                }
+                Shape::Instance(instance) => {
+                    if !found.iter().any(|p| Arc::ptr_eq(p, instance.prototype())) {
+                        found.push(Arc::clone(instance.prototype()));
+                    }
+                }
+                _ => panic!("expected only groups and instances"),
+            }
+        }
+
+        let mut found = vec![];
+        prototypes(&shape, &mut found);
+
+        assert_eq!(found.len(), 2);
+    }
+
+    #[test]
+    fn parsing_vertex_colors() {
+        let input = "\
+v 0 1 0 1 0 0
+v -1 0 0 0 1 0
+v 1 0 0 0 0 1
+v 2 1 0 1.0
+f 1 2 3
+f 1 3 4";
+
+        let model = Model::try_from(OBJModelBuilder {
+            model_spec: input,
+            transform: Default::default(),
+        })
+        .unwrap();
+
+        assert_eq!(model.vertex_colors[0], Some(crate::color::consts::RED));
+        assert_eq!(model.vertex_colors[3], None);
+
+        let Shape::Triangle(colored) = &model.groups[0].group.children[0] else {
+            panic!();
+        };
+
+        assert_eq!(
+            colored.vertex_colors(),
+            Some([
+                crate::color::consts::RED,
+                crate::color::consts::GREEN,
+                crate::color::consts::BLUE,
+            ])
+        );
+
+        // A triangle is only colored when all of it's vertices have a color.
+        let Shape::Triangle(plain) = &model.groups[0].group.children[1] else {
+            panic!();
+        };
+
+        assert_eq!(plain.vertex_colors(), None);
+    }
+
+    #[test]
+    fn trying_to_parse_a_vertex_with_an_incomplete_color() {
+        assert_eq!(
+            Model::try_from(OBJModelBuilder {
+                model_spec: "v 0 0 0 1 0",
+                transform: Default::default(),
+            }),
+            Err(Error {
+                kind: ErrorKind::MissingField { name: "blue" },
+                line_nr: 0,
+            })
+        );
+    }
+
+    #[test]
+    fn parsing_texture_coordinates() {
+        let input = "\
+v 0 0 0
+v 1 0 0
+v 0 1 0
+vt 0 0
+vt 1 0
+vt 0 1
+vn 0 0 1
+f 1/1/1 2/2/1 3/3/1
+f 1//1 2//1 3//1";
+
+        let model = Model::try_from(OBJModelBuilder {
+            model_spec: input,
+            transform: Default::default(),
+        })
+        .unwrap();
+
+        assert_eq!(
+            model.texture_coordinates,
+            vec![(0.0, 0.0), (1.0, 0.0), (0.0, 1.0)]
+        );
+
+        let children = &model.groups[0].group.children;
+
+        let Shape::SmoothTriangle(textured) = &children[0] else {
+            panic!();
+        };
+
+        assert_eq!(
+            textured.triangle.texture_coordinates(),
+            Some([(0.0, 0.0), (1.0, 0.0), (0.0, 1.0)])
+        );
+
+        let Shape::SmoothTriangle(untextured) = &children[1] else {
+            panic!();
+        };
+
+        assert_eq!(untextured.triangle.texture_coordinates(), None);
+        assert_eq!(untextured.triangle.tangents(), None);
+    }
+
+    #[test]
+    fn tangents_are_averaged_at_shared_vertices() {
+        // The second triangle stretches the texture along `v`, which turns it's tangent away from
+        // the tangent of the first one.
+        let input = "\
+v 0 0 0
+v 1 0 0
+v 0 1 0
+v 1 1 0
+vt 0 0
+vt 1 0
+vt 0 1
+vt 1 2
+vt 0.5 0
+f 1/1 2/2 3/3
+f 2/2 4/4 3/3
+f 2/5 4/4 3/3";
+
+        let model = Model::try_from(OBJModelBuilder {
+            model_spec: input,
+            transform: Default::default(),
+        })
+        .unwrap();
+
+        let tangents: Vec<_> = model.groups[0]
+            .group
+            .children
+            .iter()
+            .map(|shape| match shape {
+                Shape::Triangle(triangle) => triangle.tangents().unwrap(),
+                _ => panic!(),
+            })
+            .collect();
+
+        assert_eq!(tangents[0][0].direction, Vector::new(1.0, 0.0, 0.0));
+
+        let shared = Vector::new(3.0, -1.0, 0.0).normalize().unwrap();
+        assert_eq!(tangents[0][1].direction, shared);
+        assert_eq!(tangents[1][0].direction, shared);
+
+        // The third triangle has other texture coordinates at the same position, so it's
+        // tangent isn't averaged with the other triangles there.
+        assert_ne!(tangents[2][0].direction, shared);
+    }
 }
@@ -1,18 +1,35 @@
+use std::sync::Arc;
+
+use serde::Deserialize;
+
 use crate::{
     color::Color,
     float,
-    shape::Shape,
-    transform::Transform,
+    scene::{Checked, UnknownFields},
+    shape::{Shape, ShapeId},
+    transform::{self, Transform},
     tuple::{Point, Tuple},
 };
 
+mod cached;
+mod heatmap;
+mod texture;
+
+pub use self::cached::{CachedPattern, Error as CacheError, DEFAULT_CACHE_CAPACITY};
+pub use self::heatmap::{ColorRamp, ColorStop, Error as HeatmapError, Heatmap, ScalarField};
+pub use self::texture::{
+    AlignCheck, CubeMap, Error as TextureError, ImageTexture, TextureAtlas, TextureMap, UvCheckers,
+    UvMapping, UvPattern,
+};
+
 /// 3-dimensional pattern for materials.
 ///
 /// 3-dimensional means that patterns are "cut out" by shapes instead of adapting each specific
-/// pattern to the coordinate system adecuate to that shape. Pattern and texture mapping might be
-/// added in the future.
+/// pattern to the coordinate system adecuate to that shape. The exception are
+/// [textures](Pattern3D::Texture), which wrap a 2-dimensional pattern around a shape.
 ///
-#[derive(Copy, Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(from = "Pattern3DDeserializer")]
 pub enum Pattern3D {
     /// A solid color.
     Solid(Color),
@@ -28,6 +45,70 @@ pub enum Pattern3D {
 
     /// A checker pattern.
     Checker(Pattern3DSpec),
+
+    /// A pattern that fades between a detailed and a simplified pattern depending on how far
+    /// away from the ray's origin it is seen.
+    LevelOfDetail(Arc<LevelOfDetailSpec>),
+
+    /// A pattern whose colors are cached in a 3-dimensional grid.
+    Cached(Arc<CachedPattern>),
+
+    /// A 2-dimensional pattern mapped onto the surface of a shape.
+    Texture(Arc<TextureMap>),
+
+    /// A false color pattern that maps the values of a scalar field through a color ramp.
+    Heatmap(Arc<Heatmap>),
+}
+
+/// Specification of a [level of detail](Pattern3D::LevelOfDetail) pattern.
+///
+/// Detailed patterns, like a small checkerboard, produce aliasing when they're seen from far away,
+/// for example, on a floor that stretches to the horizon. A level of detail pattern replaces them
+/// with a simplified pattern, like a solid color with their average color, as the distance to the
+/// hit grows. When seen from a single distance, only one of the patterns is evaluated.
+///
+/// The distance is measured from the origin of the ray that hits the surface, so patterns seen in
+/// reflections and refractions use the distance from the reflecting or refracting surface.
+///
+/// # Examples
+///
+/// ```
+/// use std::sync::Arc;
+///
+/// use raytracer::{
+///     color::{self, Color},
+///     pattern::{LevelOfDetailSpec, Pattern3D, Pattern3DSpec},
+/// };
+///
+/// let floor = Pattern3D::LevelOfDetail(Arc::new(LevelOfDetailSpec {
+///     detailed: Pattern3D::Checker(Pattern3DSpec::new(
+///         color::consts::WHITE,
+///         color::consts::BLACK,
+///         Default::default(),
+///     )),
+///     simplified: Pattern3D::Solid(Color {
+///         red: 0.5,
+///         green: 0.5,
+///         blue: 0.5,
+///     }),
+///     near: 10.0,
+///     far: 50.0,
+/// }));
+/// ```
+///
+#[derive(Clone, Debug, Deserialize)]
+pub struct LevelOfDetailSpec {
+    /// Pattern used for surfaces closer than [near](LevelOfDetailSpec::near).
+    pub detailed: Pattern3D,
+
+    /// Pattern used for surfaces farther than [far](LevelOfDetailSpec::far).
+    pub simplified: Pattern3D,
+
+    /// Distance at which the detailed pattern starts fading into the simplified one.
+    pub near: f64,
+
+    /// Distance at which only the simplified pattern is visible.
+    pub far: f64,
 }
 
 /// Specification describing a complex pattern's properties.
@@ -41,6 +122,101 @@ pub struct Pattern3DSpec {
     color_b: Color,
     transform: Transform,
     transform_inverse: Transform,
+    space: PatternSpace,
+}
+
+/// Space in which a pattern is evaluated.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all(deserialize = "snake_case"))]
+pub enum PatternSpace {
+    /// The pattern is anchored to the object, so it moves, rotates and scales along with it.
+    #[default]
+    Object,
+
+    /// The pattern is anchored to the world, so the object moves through it while the pattern
+    /// stays fixed.
+    World,
+}
+
+#[derive(Debug, PartialEq, Deserialize)]
+#[serde(rename_all(deserialize = "snake_case"))]
+#[serde(tag = "type")]
+enum Pattern3DDeserializer {
+    Solid {
+        color: Color,
+
+        #[serde(flatten)]
+        _unknown: UnknownFields,
+    },
+    Stripe(Pattern3DSpecDeserializer),
+    Gradient(Pattern3DSpecDeserializer),
+    Ring(Pattern3DSpecDeserializer),
+    Checker(Pattern3DSpecDeserializer),
+    LevelOfDetail(Box<Checked<LevelOfDetailSpec>>),
+    Cached(Box<CachedPattern>),
+    Texture(Box<TextureMap>),
+    Heatmap(Box<Heatmap>),
+}
+
+#[derive(Debug, PartialEq, Deserialize)]
+struct Pattern3DSpecDeserializer {
+    color_a: Color,
+    color_b: Color,
+
+    #[serde(default, deserialize_with = "transform::deserialize_chain")]
+    transform: Transform,
+
+    #[serde(default)]
+    space: PatternSpace,
+
+    #[serde(flatten)]
+    _unknown: UnknownFields,
+}
+
+impl From<Pattern3DDeserializer> for Pattern3D {
+    fn from(value: Pattern3DDeserializer) -> Self {
+        match value {
+            Pattern3DDeserializer::Solid { color, .. } => Self::Solid(color),
+            Pattern3DDeserializer::Stripe(spec) => Self::Stripe(spec.into()),
+            Pattern3DDeserializer::Gradient(spec) => Self::Gradient(spec.into()),
+            Pattern3DDeserializer::Ring(spec) => Self::Ring(spec.into()),
+            Pattern3DDeserializer::Checker(spec) => Self::Checker(spec.into()),
+            Pattern3DDeserializer::LevelOfDetail(spec) => Self::LevelOfDetail(Arc::new(spec.inner)),
+            Pattern3DDeserializer::Cached(cache) => Self::Cached(Arc::from(cache)),
+            Pattern3DDeserializer::Texture(texture) => Self::Texture(Arc::from(texture)),
+            Pattern3DDeserializer::Heatmap(heatmap) => Self::Heatmap(Arc::from(heatmap)),
+        }
+    }
+}
+
+impl From<Pattern3DSpecDeserializer> for Pattern3DSpec {
+    fn from(value: Pattern3DSpecDeserializer) -> Self {
+        Self::new(value.color_a, value.color_b, value.transform).with_space(value.space)
+    }
+}
+
+impl PartialEq for LevelOfDetailSpec {
+    fn eq(&self, other: &Self) -> bool {
+        self.detailed == other.detailed
+            && self.simplified == other.simplified
+            && float::approx(self.near, other.near)
+            && float::approx(self.far, other.far)
+    }
+}
+
+impl LevelOfDetailSpec {
+    /// Amount of the simplified pattern that is visible at the given distance, from `0.0` to
+    /// `1.0`, following a smooth curve between the near and far distances.
+    fn fade(&self, distance: f64) -> f64 {
+        if distance <= self.near {
+            0.0
+        } else if distance >= self.far {
+            1.0
+        } else {
+            let t = (distance - self.near) / (self.far - self.near);
+            t * t * (3.0 - 2.0 * t)
+        }
+    }
 }
 
 impl Pattern3DSpec {
@@ -51,18 +227,116 @@ impl Pattern3DSpec {
             color_b,
             transform,
             transform_inverse: transform.inverse(),
+            space: PatternSpace::Object,
         }
     }
+
+    /// Changes the space in which the pattern is evaluated, which is the object's space by
+    /// default.
+    pub fn with_space(self, space: PatternSpace) -> Self {
+        Self { space, ..self }
+    }
 }
 
-fn pattern_point(object: &Shape, transform_inverse: Transform, point: Point) -> Point {
-    let object_point = object.as_ref().transform_inverse * point;
-    transform_inverse * object_point
+fn pattern_point(
+    object: &Shape,
+    space: PatternSpace,
+    transform_inverse: Transform,
+    point: Point,
+) -> Point {
+    let space_point = match space {
+        PatternSpace::Object => object.as_ref().transform_inverse * point,
+        PatternSpace::World => point,
+    };
+
+    transform_inverse * space_point
 }
 
 impl Pattern3D {
     pub(crate) fn color_at_object(&self, object: &Shape, point: Point) -> Color {
-        self.color_at(pattern_point(object, self.transform_inverse(), point))
+        self.color_at_distance(object, point, 0.0)
+    }
+
+    /// Computes the color of the pattern at a point of an object, seen from the given distance.
+    pub(crate) fn color_at_distance(&self, object: &Shape, point: Point, distance: f64) -> Color {
+        if let Self::Cached(cache) = self {
+            return cache.color_at(object, point, distance);
+        }
+
+        if let Self::LevelOfDetail(spec) = self {
+            let t = spec.fade(distance);
+
+            return if float::approx(t, 0.0) {
+                spec.detailed.color_at_distance(object, point, distance)
+            } else if float::approx(t, 1.0) {
+                spec.simplified.color_at_distance(object, point, distance)
+            } else {
+                let detailed = spec.detailed.color_at_distance(object, point, distance);
+                let simplified = spec.simplified.color_at_distance(object, point, distance);
+                detailed + (simplified - detailed) * t
+            };
+        }
+
+        self.color_at(pattern_point(
+            object,
+            self.space(),
+            self.transform_inverse(),
+            point,
+        ))
+    }
+
+    /// Forgets every color cached by the pattern for a shape, which is needed when the shape is
+    /// moved, as colors are cached by their position in world space.
+    pub(crate) fn forget(&self, id: ShapeId) {
+        match self {
+            Self::Cached(cache) => {
+                cache.forget(id);
+                cache.pattern.forget(id);
+            }
+            Self::LevelOfDetail(spec) => {
+                spec.detailed.forget(id);
+                spec.simplified.forget(id);
+            }
+            _ => (),
+        }
+    }
+
+    /// Replaces every image texture of the pattern by the image returned by `replace` for it.
+    /// Patterns shared with other materials are copied first, and cached patterns start with an
+    /// empty cache.
+    pub(crate) fn replace_images(
+        &mut self,
+        replace: &mut dyn FnMut(&Arc<ImageTexture>) -> Arc<ImageTexture>,
+    ) {
+        match self {
+            Self::Texture(texture) => Arc::make_mut(texture).replace_images(replace),
+            Self::LevelOfDetail(spec) => {
+                let spec = Arc::make_mut(spec);
+                spec.detailed.replace_images(replace);
+                spec.simplified.replace_images(replace);
+            }
+            Self::Cached(cache) => {
+                let mut pattern = cache.pattern.clone();
+                pattern.replace_images(replace);
+                *cache = Arc::new(cache.with_pattern(pattern));
+            }
+            _ => (),
+        }
+    }
+
+    /// Whether the point of an object is cut out of it's surface by the alpha of a
+    /// [texture](TextureMap::with_alpha_cutoff).
+    pub(crate) fn cuts_out(&self, object: &Shape, point: Point) -> bool {
+        let Self::Texture(texture) = self else {
+            return false;
+        };
+
+        texture.cuts_out(pattern_point(
+            object,
+            self.space(),
+            self.transform_inverse(),
+            point,
+        ))
     }
 
     fn color_at(&self, point: Point) -> Color {
@@ -92,23 +366,45 @@ impl Pattern3D {
                     s.color_b
                 }
             }
+            // A level of detail pattern has no transform of its own, so the detailed pattern
+            // only needs to apply its transform to get to its own space.
+            Self::LevelOfDetail(s) => s.detailed.color_at(s.detailed.transform_inverse() * point),
+            Self::Cached(c) => c.pattern.color_at(c.pattern.transform_inverse() * point),
+            Self::Texture(t) => t.color_at(point),
+            Self::Heatmap(h) => h.color_at(point),
         }
     }
 
     fn transform_inverse(&self) -> Transform {
         match self {
-            Self::Solid(_) => Default::default(),
+            Self::Solid(_) | Self::LevelOfDetail(_) | Self::Cached(_) | Self::Heatmap(_) => {
+                Default::default()
+            }
             Self::Stripe(s) | Self::Gradient(s) | Self::Ring(s) | Self::Checker(s) => {
                 s.transform_inverse
             }
+            Self::Texture(t) => t.transform_inverse(),
+        }
+    }
+
+    fn space(&self) -> PatternSpace {
+        match self {
+            Self::Solid(_)
+            | Self::LevelOfDetail(_)
+            | Self::Cached(_)
+            | Self::Texture(_)
+            | Self::Heatmap(_) => Default::default(),
+            Self::Stripe(s) | Self::Gradient(s) | Self::Ring(s) | Self::Checker(s) => s.space,
         }
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use serde_test::{assert_de_tokens, Token};
+
     use crate::{
-        color,
+        assert_approx, color,
         shape::{ShapeBuilder, Sphere},
     };
 
@@ -129,7 +425,8 @@ mod tests {
 
     impl MockPattern {
         fn color_at_object(&self, object: &Shape, point: Point) -> Color {
-            let pattern_point = pattern_point(object, self.0.transform.inverse(), point);
+            let pattern_point =
+                pattern_point(object, self.0.space, self.0.transform.inverse(), point);
 
             Color {
                 red: pattern_point.0.x,
@@ -251,6 +548,68 @@ mod tests {
         assert_eq!(color_at, color::consts::WHITE);
     }
 
+    #[test]
+    fn a_world_space_pattern_ignores_the_object_transformation() {
+        let object = Shape::Sphere(Sphere::from(ShapeBuilder {
+            transform: Transform::translation(1.0, 0.0, 0.0),
+            ..Default::default()
+        }));
+
+        let object_pattern = Pattern3D::Stripe(Pattern3DSpec::new(
+            color::consts::WHITE,
+            color::consts::BLACK,
+            Default::default(),
+        ));
+
+        let world_pattern = Pattern3D::Stripe(
+            Pattern3DSpec::new(
+                color::consts::WHITE,
+                color::consts::BLACK,
+                Default::default(),
+            )
+            .with_space(PatternSpace::World),
+        );
+
+        let point = Point::new(1.5, 0.0, 0.0);
+
+        assert_eq!(
+            object_pattern.color_at_object(&object, point),
+            color::consts::WHITE
+        );
+        assert_eq!(
+            world_pattern.color_at_object(&object, point),
+            color::consts::BLACK
+        );
+    }
+
+    #[test]
+    fn a_world_space_pattern_applies_the_pattern_transformation() {
+        let object = Shape::Sphere(Sphere::from(ShapeBuilder {
+            transform: Transform::scaling(2.0, 2.0, 2.0).unwrap(),
+            ..Default::default()
+        }));
+
+        let pattern = MockPattern(
+            Pattern3DSpec::new(
+                color::consts::WHITE,
+                color::consts::BLACK,
+                Transform::translation(0.5, 1.0, 1.5),
+            )
+            .with_space(PatternSpace::World),
+        );
+
+        let color_at = pattern.color_at_object(&object, Point::new(2.5, 3.0, 3.5));
+
+        assert_eq!(
+            color_at,
+            Color {
+                red: 2.0,
+                green: 2.0,
+                blue: 2.0
+            }
+        );
+    }
+
     #[test]
     fn a_pattern_with_an_object_transformation() {
         let object = Shape::Sphere(Sphere::from(ShapeBuilder {
@@ -458,4 +817,434 @@ mod tests {
             color::consts::BLACK
         );
     }
+
+    fn color_tokens(red: u8, green: u8, blue: u8) -> [Token; 8] {
+        [
+            Token::Struct {
+                name: "ColorDeserializer",
+                len: 3,
+            },
+            Token::Str("red"),
+            Token::U8(red),
+            Token::Str("green"),
+            Token::U8(green),
+            Token::Str("blue"),
+            Token::U8(blue),
+            Token::StructEnd,
+        ]
+    }
+
+    #[test]
+    fn deserializing_a_solid_pattern() {
+        let tokens: Vec<_> = [
+            Token::Struct {
+                name: "Pattern3DDeserializer",
+                len: 2,
+            },
+            Token::Str("type"),
+            Token::Str("solid"),
+            Token::Str("color"),
+        ]
+        .into_iter()
+        .chain(color_tokens(255, 0, 0))
+        .chain([Token::StructEnd])
+        .collect();
+
+        assert_de_tokens(&Pattern3D::Solid(color::consts::RED), &tokens);
+    }
+
+    #[test]
+    fn deserializing_a_world_space_pattern() {
+        let tokens: Vec<_> = [
+            Token::Struct {
+                name: "Pattern3DDeserializer",
+                len: 4,
+            },
+            Token::Str("type"),
+            Token::Str("stripe"),
+            Token::Str("color_a"),
+        ]
+        .into_iter()
+        .chain(color_tokens(255, 255, 255))
+        .chain([Token::Str("color_b")])
+        .chain(color_tokens(0, 0, 0))
+        .chain([
+            Token::Str("space"),
+            Token::UnitVariant {
+                name: "PatternSpace",
+                variant: "world",
+            },
+            Token::StructEnd,
+        ])
+        .collect();
+
+        assert_de_tokens(
+            &Pattern3D::Stripe(
+                Pattern3DSpec::new(
+                    color::consts::WHITE,
+                    color::consts::BLACK,
+                    Default::default(),
+                )
+                .with_space(PatternSpace::World),
+            ),
+            &tokens,
+        );
+    }
+
+    #[test]
+    fn deserializing_a_checker_pattern() {
+        let tokens: Vec<_> = [
+            Token::Struct {
+                name: "Pattern3DDeserializer",
+                len: 4,
+            },
+            Token::Str("type"),
+            Token::Str("checker"),
+            Token::Str("color_a"),
+        ]
+        .into_iter()
+        .chain(color_tokens(255, 255, 255))
+        .chain([Token::Str("color_b")])
+        .chain(color_tokens(0, 0, 0))
+        .chain([
+            Token::Str("transform"),
+            Token::Map { len: Some(4) },
+            Token::Str("type"),
+            Token::Str("scaling"),
+            Token::Str("x"),
+            Token::F64(0.5),
+            Token::Str("y"),
+            Token::F64(0.5),
+            Token::Str("z"),
+            Token::F64(0.5),
+            Token::MapEnd,
+            Token::StructEnd,
+        ])
+        .collect();
+
+        assert_de_tokens(
+            &Pattern3D::Checker(Pattern3DSpec::new(
+                color::consts::WHITE,
+                color::consts::BLACK,
+                Transform::scaling(0.5, 0.5, 0.5).unwrap(),
+            )),
+            &tokens,
+        );
+    }
+
+    fn level_of_detail_pattern() -> Pattern3D {
+        Pattern3D::LevelOfDetail(Arc::new(LevelOfDetailSpec {
+            detailed: Pattern3D::Solid(color::consts::WHITE),
+            simplified: Pattern3D::Solid(color::consts::BLACK),
+            near: 10.0,
+            far: 20.0,
+        }))
+    }
+
+    #[test]
+    fn a_level_of_detail_pattern_is_detailed_up_close() {
+        let object = Shape::Sphere(Default::default());
+        let pattern = level_of_detail_pattern();
+
+        let point = Point::new(0.0, 0.0, 0.0);
+
+        assert_eq!(
+            pattern.color_at_distance(&object, point, 5.0),
+            color::consts::WHITE
+        );
+        assert_eq!(
+            pattern.color_at_distance(&object, point, 10.0),
+            color::consts::WHITE
+        );
+        assert_eq!(
+            pattern.color_at_object(&object, point),
+            color::consts::WHITE
+        );
+    }
+
+    #[test]
+    fn a_level_of_detail_pattern_is_simplified_far_away() {
+        let object = Shape::Sphere(Default::default());
+        let pattern = level_of_detail_pattern();
+
+        let point = Point::new(0.0, 0.0, 0.0);
+
+        assert_eq!(
+            pattern.color_at_distance(&object, point, 20.0),
+            color::consts::BLACK
+        );
+        assert_eq!(
+            pattern.color_at_distance(&object, point, 1000.0),
+            color::consts::BLACK
+        );
+    }
+
+    #[test]
+    fn a_level_of_detail_pattern_fades_smoothly_between_distances() {
+        let object = Shape::Sphere(Default::default());
+        let pattern = level_of_detail_pattern();
+
+        let point = Point::new(0.0, 0.0, 0.0);
+
+        let halfway = pattern.color_at_distance(&object, point, 15.0);
+        assert_eq!(
+            halfway,
+            Color {
+                red: 0.5,
+                green: 0.5,
+                blue: 0.5
+            }
+        );
+
+        let quarter = pattern.color_at_distance(&object, point, 12.5);
+        assert_eq!(
+            quarter,
+            Color {
+                red: 0.84375,
+                green: 0.84375,
+                blue: 0.84375
+            }
+        );
+    }
+
+    #[test]
+    fn a_level_of_detail_pattern_applies_the_transforms_of_its_patterns() {
+        let object = Shape::Sphere(Default::default());
+        let pattern = Pattern3D::LevelOfDetail(Arc::new(LevelOfDetailSpec {
+            detailed: Pattern3D::Stripe(Pattern3DSpec::new(
+                color::consts::WHITE,
+                color::consts::BLACK,
+                Transform::scaling(2.0, 2.0, 2.0).unwrap(),
+            )),
+            simplified: Pattern3D::Solid(color::consts::BLACK),
+            near: 10.0,
+            far: 20.0,
+        }));
+
+        let color_at = pattern.color_at_distance(&object, Point::new(1.5, 0.0, 0.0), 0.0);
+
+        assert_eq!(color_at, color::consts::WHITE);
+    }
+
+    #[test]
+    fn deserializing_a_level_of_detail_pattern() {
+        let tokens: Vec<_> = [
+            Token::Struct {
+                name: "Pattern3DDeserializer",
+                len: 5,
+            },
+            Token::Str("type"),
+            Token::Str("level_of_detail"),
+            Token::Str("detailed"),
+            Token::Struct {
+                name: "Pattern3DDeserializer",
+                len: 2,
+            },
+            Token::Str("type"),
+            Token::Str("solid"),
+            Token::Str("color"),
+        ]
+        .into_iter()
+        .chain(color_tokens(255, 255, 255))
+        .chain([
+            Token::StructEnd,
+            Token::Str("simplified"),
+            Token::Struct {
+                name: "Pattern3DDeserializer",
+                len: 2,
+            },
+            Token::Str("type"),
+            Token::Str("solid"),
+            Token::Str("color"),
+        ])
+        .chain(color_tokens(0, 0, 0))
+        .chain([
+            Token::StructEnd,
+            Token::Str("near"),
+            Token::F64(10.0),
+            Token::Str("far"),
+            Token::F64(20.0),
+            Token::StructEnd,
+        ])
+        .collect();
+
+        assert_de_tokens(&level_of_detail_pattern(), &tokens);
+    }
+
+    #[test]
+    fn a_cached_pattern_evaluates_the_pattern_at_the_center_of_each_cell() {
+        let object = Shape::Sphere(Default::default());
+        let pattern = Pattern3D::Cached(Arc::new(
+            CachedPattern::new(
+                Pattern3D::Stripe(Pattern3DSpec::new(
+                    color::consts::WHITE,
+                    color::consts::BLACK,
+                    Default::default(),
+                )),
+                0.5,
+            )
+            .unwrap(),
+        ));
+
+        assert_eq!(
+            pattern.color_at_object(&object, Point::new(0.2, 0.0, 0.0)),
+            color::consts::BLACK
+        );
+        assert_eq!(
+            pattern.color_at_object(&object, Point::new(1.9, 0.0, 0.0)),
+            color::consts::BLACK
+        );
+    }
+
+    #[test]
+    fn a_cached_pattern_stores_one_color_per_cell_and_object() {
+        let first = Shape::Sphere(Default::default());
+        let second = Shape::Sphere(Default::default());
+        let cache =
+            Arc::new(CachedPattern::new(Pattern3D::Solid(color::consts::WHITE), 1.0).unwrap());
+        let pattern = Pattern3D::Cached(Arc::clone(&cache));
+
+        assert!(cache.is_empty());
+
+        pattern.color_at_object(&first, Point::new(0.1, 0.1, 0.1));
+        pattern.color_at_object(&first, Point::new(0.9, 0.9, 0.9));
+        assert_eq!(cache.len(), 1);
+
+        pattern.color_at_object(&first, Point::new(1.1, 0.1, 0.1));
+        assert_eq!(cache.len(), 2);
+
+        pattern.color_at_object(&second, Point::new(0.1, 0.1, 0.1));
+        assert_eq!(cache.len(), 3);
+    }
+
+    #[test]
+    fn deserializing_a_cached_pattern() {
+        let tokens: Vec<_> = [
+            Token::Struct {
+                name: "Pattern3DDeserializer",
+                len: 3,
+            },
+            Token::Str("type"),
+            Token::Str("cached"),
+            Token::Str("pattern"),
+            Token::Struct {
+                name: "Pattern3DDeserializer",
+                len: 2,
+            },
+            Token::Str("type"),
+            Token::Str("solid"),
+            Token::Str("color"),
+        ]
+        .into_iter()
+        .chain(color_tokens(255, 255, 255))
+        .chain([
+            Token::StructEnd,
+            Token::Str("resolution"),
+            Token::F64(4.0),
+            Token::StructEnd,
+        ])
+        .collect();
+
+        assert_de_tokens(
+            &Pattern3D::Cached(Arc::new(
+                CachedPattern::new(Pattern3D::Solid(color::consts::WHITE), 4.0).unwrap(),
+            )),
+            &tokens,
+        );
+    }
+
+    fn planar_checkers(transform: Transform) -> Pattern3D {
+        Pattern3D::Texture(Arc::new(TextureMap::new(
+            UvMapping::Planar(UvPattern::Checkers(UvCheckers {
+                width: 2.0,
+                height: 2.0,
+                color_a: color::consts::BLACK,
+                color_b: color::consts::WHITE,
+            })),
+            transform,
+        )))
+    }
+
+    #[test]
+    fn a_texture_follows_the_transformations_of_it_s_object_and_it_s_own() {
+        let point = Point::new(0.75, 0.0, 0.25);
+
+        let object = Shape::Sphere(Default::default());
+
+        assert_eq!(
+            planar_checkers(Default::default()).color_at_object(&object, point),
+            color::consts::WHITE
+        );
+
+        let object = Shape::Sphere(Sphere::from(ShapeBuilder {
+            transform: Transform::translation(0.5, 0.0, 0.0),
+            ..Default::default()
+        }));
+
+        assert_eq!(
+            planar_checkers(Default::default()).color_at_object(&object, point),
+            color::consts::BLACK
+        );
+
+        let object = Shape::Sphere(Default::default());
+
+        assert_eq!(
+            planar_checkers(Transform::translation(0.5, 0.0, 0.0)).color_at_object(&object, point),
+            color::consts::BLACK
+        );
+    }
+
+    #[test]
+    fn deserializing_a_texture_pattern() {
+        let pattern: Pattern3D = serde_json::from_str(
+            r#"{
+                "type": "texture",
+                "mapping": {
+                    "type": "planar",
+                    "pattern": {
+                        "type": "checkers",
+                        "width": 2,
+                        "height": 2,
+                        "color_a": { "red": 0, "green": 0, "blue": 0 },
+                        "color_b": { "red": 255, "green": 255, "blue": 255 }
+                    }
+                },
+                "transform": [{ "type": "translation", "x": 0.5, "y": 0, "z": 0 }]
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            pattern,
+            planar_checkers(Transform::translation(0.5, 0.0, 0.0))
+        );
+    }
+
+    #[test]
+    fn a_heatmap_pattern_is_evaluated_in_the_space_of_the_object() {
+        let pattern = Pattern3D::Heatmap(Arc::new(
+            Heatmap::new(ScalarField::Y, ColorRamp::grayscale(), 0.0, 1.0).unwrap(),
+        ));
+
+        let object = Shape::Sphere(Sphere::from(ShapeBuilder {
+            transform: Transform::scaling(1.0, 2.0, 1.0).unwrap(),
+            ..Default::default()
+        }));
+
+        let color = pattern.color_at_object(&object, Point::new(0.0, 1.0, 0.0));
+        assert_approx!(color.red, 0.5);
+    }
+
+    #[test]
+    fn deserializing_a_heatmap_pattern() {
+        let pattern: Pattern3D =
+            serde_json::from_str(r#"{ "type": "heatmap", "field": "z", "ramp": "heat" }"#).unwrap();
+
+        assert_eq!(
+            pattern,
+            Pattern3D::Heatmap(Arc::new(
+                Heatmap::new(ScalarField::Z, ColorRamp::heat(), 0.0, 1.0).unwrap()
+            ))
+        );
+    }
 }
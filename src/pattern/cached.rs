@@ -0,0 +1,287 @@
+use std::{
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+    sync::RwLock,
+};
+
+use serde::Deserialize;
+use thiserror::Error;
+
+use crate::{
+    color::Color,
+    float,
+    scene::UnknownFields,
+    shape::{Shape, ShapeId},
+    tuple::Point,
+};
+
+use super::Pattern3D;
+
+/// Number of colors kept by a [CachedPattern] unless it's
+/// [capacity](CachedPattern::with_capacity) is changed.
+pub const DEFAULT_CACHE_CAPACITY: usize = 1 << 14;
+
+/// The error type when a cached pattern can't be built.
+#[derive(Clone, Debug, PartialEq, Error)]
+pub enum Error {
+    /// The resolution of the grid is not a positive number.
+    #[error("the resolution of a cached pattern must be a positive number, got {0}")]
+    InvalidResolution(f64),
+}
+
+/// Pattern that caches the colors of another pattern in a 3-dimensional grid.
+///
+/// Heavy procedural patterns are evaluated once for every ray that hits them. A cached pattern
+/// instead evaluates the wrapped pattern once at the center of each cell of a grid in world
+/// space, and reuses that color for every point of the same object inside the cell. This trades
+/// memory for render speed, as well as detail finer than the grid's
+/// [resolution](CachedPattern::resolution).
+///
+/// The cache keeps a fixed number of colors, it's [capacity](CachedPattern::with_capacity), so
+/// it's memory doesn't grow with the size of the world. Each cell has a single slot where it can
+/// be stored, and a cell that takes the slot of another one replaces it's color.
+///
+/// Cached colors ignore the distance from which they're seen, so patterns whose color depends on
+/// it, like [level of detail](Pattern3D::LevelOfDetail) patterns, should not be cached.
+///
+/// Clones of a cached pattern share the same cache.
+///
+/// # Examples
+///
+/// ```
+/// use std::sync::Arc;
+///
+/// use raytracer::{
+///     color,
+///     pattern::{CacheError, CachedPattern, Pattern3D},
+/// };
+///
+/// let cache = CachedPattern::new(Pattern3D::Solid(color::consts::WHITE), 4.0)
+///     .unwrap()
+///     .with_capacity(1024);
+///
+/// let pattern = Pattern3D::Cached(Arc::new(cache));
+///
+/// assert_eq!(
+///     CachedPattern::new(Pattern3D::Solid(color::consts::WHITE), 0.0).unwrap_err(),
+///     CacheError::InvalidResolution(0.0)
+/// );
+/// ```
+///
+/// # Deserialization
+///
+/// A cached pattern is deserialized from the `pattern` it caches and the `resolution` of it's
+/// grid, and optionally it's `capacity`.
+///
+/// ```json
+/// {
+///   "type": "cached",
+///   "pattern": { "type": "solid", "color": { "red": 255, "green": 255, "blue": 255 } },
+///   "resolution": 4,
+///   "capacity": 1024
+/// }
+/// ```
+///
+#[derive(Debug, Deserialize)]
+#[serde(try_from = "CachedPatternDeserializer")]
+pub struct CachedPattern {
+    pub(crate) pattern: Pattern3D,
+    resolution: f64,
+    slots: Box<[RwLock<Option<CachedColor>>]>,
+}
+
+#[derive(Copy, Clone, Debug)]
+struct CachedColor {
+    object: ShapeId,
+    cell: [i64; 3],
+    color: Color,
+}
+
+#[derive(Debug, PartialEq, Deserialize)]
+struct CachedPatternDeserializer {
+    pattern: Pattern3D,
+    resolution: f64,
+
+    #[serde(default = "default_capacity")]
+    capacity: usize,
+
+    #[serde(flatten)]
+    _unknown: UnknownFields,
+}
+
+fn default_capacity() -> usize {
+    DEFAULT_CACHE_CAPACITY
+}
+
+impl TryFrom<CachedPatternDeserializer> for CachedPattern {
+    type Error = Error;
+
+    fn try_from(value: CachedPatternDeserializer) -> Result<Self, Self::Error> {
+        Ok(Self::new(value.pattern, value.resolution)?.with_capacity(value.capacity))
+    }
+}
+
+impl PartialEq for CachedPattern {
+    fn eq(&self, other: &Self) -> bool {
+        self.pattern == other.pattern
+            && float::approx(self.resolution, other.resolution)
+            && self.slots.len() == other.slots.len()
+    }
+}
+
+impl CachedPattern {
+    /// Constructs a cache for a pattern, with a given number of cells per unit of world space.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the resolution is not a positive finite number, as there would be no grid to
+    /// cache colors in.
+    ///
+    pub fn new(pattern: Pattern3D, resolution: f64) -> Result<Self, Error> {
+        if !(resolution.is_finite() && resolution > 0.0) {
+            return Err(Error::InvalidResolution(resolution));
+        }
+
+        Ok(Self {
+            pattern,
+            resolution,
+            slots: empty_slots(DEFAULT_CACHE_CAPACITY),
+        })
+    }
+
+    /// Changes the number of colors the cache keeps, which is [DEFAULT_CACHE_CAPACITY] by
+    /// default. A capacity of `0` is treated as `1`.
+    pub fn with_capacity(self, capacity: usize) -> Self {
+        Self {
+            slots: empty_slots(capacity),
+            ..self
+        }
+    }
+
+    /// Number of cells per unit of world space.
+    pub fn resolution(&self) -> f64 {
+        self.resolution
+    }
+
+    /// Maximum number of colors that the cache keeps.
+    pub fn capacity(&self) -> usize {
+        self.slots.len()
+    }
+
+    /// Number of colors that are currently cached.
+    pub fn len(&self) -> usize {
+        self.slots
+            .iter()
+            .filter(|slot| slot.read().unwrap().is_some())
+            .count()
+    }
+
+    /// Whether no color has been cached yet.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Empty cache for another pattern, with the same resolution and capacity as this one.
+    pub(crate) fn with_pattern(&self, pattern: Pattern3D) -> Self {
+        Self {
+            pattern,
+            resolution: self.resolution,
+            slots: empty_slots(self.capacity()),
+        }
+    }
+
+    /// Forgets the colors cached for a shape, because they were computed where the shape used to
+    /// be.
+    pub(crate) fn forget(&self, id: ShapeId) {
+        for slot in self.slots.iter() {
+            let mut slot = slot.write().unwrap();
+
+            if slot.is_some_and(|cached| cached.object == id) {
+                *slot = None;
+            }
+        }
+    }
+
+    pub(crate) fn color_at(&self, object: &Shape, point: Point, distance: f64) -> Color {
+        let cell = [point.0.x, point.0.y, point.0.z].map(|c| (c * self.resolution).floor() as i64);
+        let slot = &self.slots[self.slot_of(object.id(), cell)];
+
+        if let Some(cached) = *slot.read().unwrap() {
+            if cached.object == object.id() && cached.cell == cell {
+                return cached.color;
+            }
+        }
+
+        let [x, y, z] = cell.map(|c| (c as f64 + 0.5) / self.resolution);
+        let color = self
+            .pattern
+            .color_at_distance(object, Point::new(x, y, z), distance);
+
+        *slot.write().unwrap() = Some(CachedColor {
+            object: object.id(),
+            cell,
+            color,
+        });
+
+        color
+    }
+
+    fn slot_of(&self, object: ShapeId, cell: [i64; 3]) -> usize {
+        let mut hasher = DefaultHasher::new();
+        (object, cell).hash(&mut hasher);
+
+        (hasher.finish() % self.slots.len() as u64) as usize
+    }
+}
+
+fn empty_slots(capacity: usize) -> Box<[RwLock<Option<CachedColor>>]> {
+    (0..capacity.max(1)).map(|_| RwLock::new(None)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::color;
+
+    use super::*;
+
+    #[test]
+    fn trying_to_cache_a_pattern_without_a_valid_resolution() {
+        let pattern = || Pattern3D::Solid(color::consts::WHITE);
+
+        for resolution in [0.0, -1.0, f64::NAN, f64::INFINITY] {
+            assert!(matches!(
+                CachedPattern::new(pattern(), resolution),
+                Err(Error::InvalidResolution(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn deserializing_a_cached_pattern_without_a_valid_resolution() {
+        let spec = r#"{
+            "type": "cached",
+            "pattern": { "type": "solid", "color": { "red": 255, "green": 255, "blue": 255 } },
+            "resolution": -2
+        }"#;
+
+        let err = serde_json::from_str::<Pattern3D>(spec).unwrap_err();
+
+        assert!(err.to_string().contains("positive number"));
+    }
+
+    #[test]
+    fn a_cache_never_keeps_more_colors_than_it_s_capacity() {
+        let object = Shape::Sphere(Default::default());
+        let cache = CachedPattern::new(Pattern3D::Solid(color::consts::WHITE), 1.0)
+            .unwrap()
+            .with_capacity(8);
+
+        for x in 0..100 {
+            let color = cache.color_at(&object, Point::new(f64::from(x), 0.0, 0.0), 0.0);
+            assert_eq!(color, color::consts::WHITE);
+        }
+
+        assert_eq!(cache.capacity(), 8);
+        assert!(cache.len() <= 8);
+    }
+}
@@ -0,0 +1,489 @@
+use std::{fmt, sync::Arc};
+
+use serde::Deserialize;
+use thiserror::Error;
+
+use crate::{
+    color::Color,
+    scene::{Checked, UnknownFields},
+    tuple::{Point, Tuple},
+};
+
+/// The error type when a heatmap can't be built.
+#[derive(Clone, Debug, PartialEq, Error)]
+pub enum Error {
+    /// The color ramp has no stops.
+    #[error("a color ramp needs at least one stop")]
+    EmptyRamp,
+
+    /// The range of values isn't increasing.
+    #[error("the range of a heatmap must go from a lower to a higher value, got {min} to {max}")]
+    InvalidRange {
+        /// Value mapped to the start of the ramp.
+        min: f64,
+
+        /// Value mapped to the end of the ramp.
+        max: f64,
+    },
+}
+
+/// False color pattern, which maps the values of a scalar field, such as the temperature or the
+/// stress of a simulation, through a color ramp, to produce scientific visualization images.
+///
+/// Values from [min](Heatmap::range) to [max](Heatmap::range) are spread over the whole ramp, and
+/// values outside of the range get the color at the nearest end of it.
+///
+/// # Examples
+///
+/// A temperature field defined by a closure, with cold points in blue and hot points in red.
+///
+/// ```
+/// use std::sync::Arc;
+///
+/// use raytracer::pattern::{ColorRamp, Heatmap, Pattern3D, ScalarField};
+///
+/// let temperature = ScalarField::Function(Arc::new(|_, y, _| 20.0 + 5.0 * y));
+///
+/// let pattern = Pattern3D::Heatmap(Arc::new(
+///     Heatmap::new(temperature, ColorRamp::cool_warm(), 0.0, 40.0).unwrap(),
+/// ));
+/// ```
+///
+/// # Deserialization
+///
+/// A heatmap is deserialized from it's `field`, which is one of `x`, `y`, `z`, `distance` or
+/// `vertex`, and optionally a `ramp` and the `min` and `max` of it's range, which go from `0.0` to
+/// `1.0` by default. The ramp is either the name of a preset, which is one of `viridis` (the
+/// default), `heat`, `cool_warm` or `grayscale`, or a list of stops.
+///
+/// ```json
+/// {
+///   "type": "heatmap",
+///   "field": "y",
+///   "ramp": [
+///     { "position": 0, "color": { "red": 0, "green": 0, "blue": 255 } },
+///     { "position": 1, "color": { "red": 255, "green": 0, "blue": 0 } }
+///   ],
+///   "min": -1,
+///   "max": 1
+/// }
+/// ```
+///
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(try_from = "HeatmapDeserializer")]
+pub struct Heatmap {
+    field: ScalarField,
+    ramp: ColorRamp,
+    min: f64,
+    max: f64,
+}
+
+/// Scalar field shown by a [Heatmap], evaluated at the points of a shape in the space of the
+/// shape.
+#[derive(Clone, Deserialize)]
+#[serde(rename_all(deserialize = "snake_case"))]
+pub enum ScalarField {
+    /// The `x` coordinate of the point.
+    X,
+
+    /// The `y` coordinate of the point.
+    Y,
+
+    /// The `z` coordinate of the point.
+    Z,
+
+    /// The distance from the point to the origin.
+    Distance,
+
+    /// Values given at the vertices of triangles with
+    /// [Triangle::with_vertex_values](crate::shape::Triangle::with_vertex_values), such as the
+    /// results of a simulation at the nodes of a mesh, blended across each triangle. Points of
+    /// other shapes, and of triangles without values, have a value of `0.0`.
+    Vertex,
+
+    /// A function of the `x`, `y` and `z` coordinates of the point. It can't be deserialized.
+    #[serde(skip_deserializing)]
+    Function(Arc<dyn Fn(f64, f64, f64) -> f64 + Send + Sync>),
+}
+
+/// Colors that a [Heatmap] goes through, from the lowest to the highest value of it's range.
+///
+/// Colors are blended linearly between stops, and positions before the first stop or after the
+/// last one get the color of that stop.
+///
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(try_from = "ColorRampDeserializer")]
+pub struct ColorRamp {
+    stops: Vec<ColorStop>,
+}
+
+/// Color at a position of a [ColorRamp], which goes from `0.0` to `1.0`.
+#[derive(Copy, Clone, Debug, PartialEq, Deserialize)]
+pub struct ColorStop {
+    /// Position of the stop along the ramp.
+    pub position: f64,
+
+    /// Color of the ramp at the stop.
+    pub color: Color,
+}
+
+#[derive(Debug, PartialEq, Deserialize)]
+struct HeatmapDeserializer {
+    field: ScalarField,
+
+    #[serde(default)]
+    ramp: Option<ColorRamp>,
+
+    #[serde(default)]
+    min: f64,
+
+    #[serde(default = "default_max")]
+    max: f64,
+
+    #[serde(flatten)]
+    _unknown: UnknownFields,
+}
+
+#[derive(Debug, PartialEq, Deserialize)]
+#[serde(untagged)]
+enum ColorRampDeserializer {
+    Preset(RampPreset),
+    Stops(Vec<Checked<ColorStop>>),
+}
+
+#[derive(Debug, PartialEq, Deserialize)]
+#[serde(rename_all(deserialize = "snake_case"))]
+enum RampPreset {
+    Viridis,
+    Heat,
+    CoolWarm,
+    Grayscale,
+}
+
+fn default_max() -> f64 {
+    1.0
+}
+
+impl TryFrom<HeatmapDeserializer> for Heatmap {
+    type Error = Error;
+
+    fn try_from(value: HeatmapDeserializer) -> Result<Self, Self::Error> {
+        let ramp = value.ramp.unwrap_or_else(ColorRamp::viridis);
+        Self::new(value.field, ramp, value.min, value.max)
+    }
+}
+
+impl TryFrom<ColorRampDeserializer> for ColorRamp {
+    type Error = Error;
+
+    fn try_from(value: ColorRampDeserializer) -> Result<Self, Self::Error> {
+        match value {
+            ColorRampDeserializer::Preset(RampPreset::Viridis) => Ok(Self::viridis()),
+            ColorRampDeserializer::Preset(RampPreset::Heat) => Ok(Self::heat()),
+            ColorRampDeserializer::Preset(RampPreset::CoolWarm) => Ok(Self::cool_warm()),
+            ColorRampDeserializer::Preset(RampPreset::Grayscale) => Ok(Self::grayscale()),
+            ColorRampDeserializer::Stops(stops) => {
+                Self::new(stops.into_iter().map(|stop| stop.inner).collect())
+            }
+        }
+    }
+}
+
+impl fmt::Debug for ScalarField {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::X => write!(f, "X"),
+            Self::Y => write!(f, "Y"),
+            Self::Z => write!(f, "Z"),
+            Self::Distance => write!(f, "Distance"),
+            Self::Vertex => write!(f, "Vertex"),
+            Self::Function(_) => write!(f, "Function(..)"),
+        }
+    }
+}
+
+impl PartialEq for ScalarField {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::Function(a), Self::Function(b)) => Arc::ptr_eq(a, b),
+            _ => std::mem::discriminant(self) == std::mem::discriminant(other),
+        }
+    }
+}
+
+impl ScalarField {
+    /// Value of the field at a point.
+    pub fn value_at(&self, point: Point) -> f64 {
+        let Point(Tuple { x, y, z, .. }) = point;
+
+        match self {
+            Self::X => x,
+            Self::Y => y,
+            Self::Z => z,
+            Self::Distance => (x * x + y * y + z * z).sqrt(),
+            Self::Vertex => 0.0,
+            Self::Function(function) => function(x, y, z),
+        }
+    }
+}
+
+impl Heatmap {
+    /// Constructs a heatmap that maps the values of `field` from `min` to `max` through `ramp`.
+    pub fn new(field: ScalarField, ramp: ColorRamp, min: f64, max: f64) -> Result<Self, Error> {
+        if !min.is_finite() || !max.is_finite() || min >= max {
+            return Err(Error::InvalidRange { min, max });
+        }
+
+        Ok(Self {
+            field,
+            ramp,
+            min,
+            max,
+        })
+    }
+
+    /// Field shown by the heatmap.
+    pub fn field(&self) -> &ScalarField {
+        &self.field
+    }
+
+    /// Ramp that values are mapped through.
+    pub fn ramp(&self) -> &ColorRamp {
+        &self.ramp
+    }
+
+    /// Values mapped to the start and the end of the ramp.
+    pub fn range(&self) -> (f64, f64) {
+        (self.min, self.max)
+    }
+
+    /// Color of a value of the field.
+    pub fn color_of(&self, value: f64) -> Color {
+        self.ramp
+            .color_at((value - self.min) / (self.max - self.min))
+    }
+
+    /// Color of the heatmap at a point in the space of the pattern.
+    pub(crate) fn color_at(&self, point: Point) -> Color {
+        self.color_of(self.field.value_at(point))
+    }
+}
+
+impl ColorRamp {
+    /// Constructs a ramp that goes through the given stops, in the order of their positions.
+    pub fn new(mut stops: Vec<ColorStop>) -> Result<Self, Error> {
+        if stops.is_empty() {
+            return Err(Error::EmptyRamp);
+        }
+
+        stops.sort_by(|a, b| a.position.total_cmp(&b.position));
+
+        Ok(Self { stops })
+    }
+
+    /// Perceptually uniform ramp from dark purple through teal to yellow, which reads well in
+    /// grayscale and for color blind viewers.
+    pub fn viridis() -> Self {
+        Self::evenly_spaced(&[
+            (0.267, 0.005, 0.329),
+            (0.229, 0.322, 0.546),
+            (0.128, 0.567, 0.551),
+            (0.369, 0.789, 0.383),
+            (0.993, 0.906, 0.144),
+        ])
+    }
+
+    /// Ramp from black through red and yellow to white, like the glow of heated metal.
+    pub fn heat() -> Self {
+        Self::evenly_spaced(&[
+            (0.0, 0.0, 0.0),
+            (1.0, 0.0, 0.0),
+            (1.0, 1.0, 0.0),
+            (1.0, 1.0, 1.0),
+        ])
+    }
+
+    /// Diverging ramp from blue through light gray to red, for values around a neutral middle.
+    pub fn cool_warm() -> Self {
+        Self::evenly_spaced(&[
+            (0.230, 0.299, 0.754),
+            (0.865, 0.865, 0.865),
+            (0.706, 0.016, 0.150),
+        ])
+    }
+
+    /// Ramp from black to white.
+    pub fn grayscale() -> Self {
+        Self::evenly_spaced(&[(0.0, 0.0, 0.0), (1.0, 1.0, 1.0)])
+    }
+
+    /// Stops of the ramp, in the order of their positions.
+    pub fn stops(&self) -> &[ColorStop] {
+        &self.stops
+    }
+
+    /// Color of the ramp at a position.
+    pub fn color_at(&self, position: f64) -> Color {
+        let first = self.stops[0];
+        let last = self.stops[self.stops.len() - 1];
+
+        if position.is_nan() || position <= first.position {
+            return first.color;
+        }
+
+        if position >= last.position {
+            return last.color;
+        }
+
+        let next = self
+            .stops
+            .iter()
+            .position(|stop| stop.position > position)
+            .unwrap_or(self.stops.len() - 1);
+
+        let (a, b) = (self.stops[next - 1], self.stops[next]);
+        let t = (position - a.position) / (b.position - a.position);
+
+        a.color + (b.color - a.color) * t
+    }
+
+    fn evenly_spaced(colors: &[(f64, f64, f64)]) -> Self {
+        let last = (colors.len() - 1) as f64;
+
+        Self {
+            stops: colors
+                .iter()
+                .enumerate()
+                .map(|(i, &(red, green, blue))| ColorStop {
+                    position: i as f64 / last,
+                    color: Color { red, green, blue },
+                })
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{assert_approx, color};
+
+    use super::*;
+
+    fn black_to_red() -> ColorRamp {
+        ColorRamp::new(vec![
+            ColorStop {
+                position: 1.0,
+                color: color::consts::RED,
+            },
+            ColorStop {
+                position: 0.0,
+                color: color::consts::BLACK,
+            },
+        ])
+        .unwrap()
+    }
+
+    #[test]
+    fn the_stops_of_a_ramp_are_sorted_by_position() {
+        let ramp = black_to_red();
+
+        assert_eq!(ramp.stops()[0].color, color::consts::BLACK);
+        assert_eq!(ramp.stops()[1].color, color::consts::RED);
+    }
+
+    #[test]
+    fn a_ramp_blends_the_colors_between_stops() {
+        let ramp = black_to_red();
+
+        assert_approx!(ramp.color_at(0.25).red, 0.25);
+        assert_eq!(ramp.color_at(-1.0), color::consts::BLACK);
+        assert_eq!(ramp.color_at(2.0), color::consts::RED);
+        assert_eq!(ramp.color_at(f64::NAN), color::consts::BLACK);
+    }
+
+    #[test]
+    fn a_ramp_needs_a_stop() {
+        assert_eq!(ColorRamp::new(vec![]), Err(Error::EmptyRamp));
+    }
+
+    #[test]
+    fn presets_start_and_end_at_the_ends_of_the_ramp() {
+        for ramp in [
+            ColorRamp::viridis(),
+            ColorRamp::heat(),
+            ColorRamp::cool_warm(),
+            ColorRamp::grayscale(),
+        ] {
+            assert_eq!(ramp.stops().first().unwrap().position, 0.0);
+            assert_eq!(ramp.stops().last().unwrap().position, 1.0);
+        }
+
+        assert_eq!(ColorRamp::heat().color_at(1.0), color::consts::WHITE);
+    }
+
+    #[test]
+    fn a_heatmap_spreads_its_range_over_the_ramp() {
+        let heatmap = Heatmap::new(ScalarField::Y, black_to_red(), 10.0, 20.0).unwrap();
+
+        assert_approx!(heatmap.color_at(Point::new(0.0, 12.5, 0.0)).red, 0.25);
+        assert_eq!(
+            heatmap.color_at(Point::new(0.0, 30.0, 0.0)),
+            color::consts::RED
+        );
+    }
+
+    #[test]
+    fn a_heatmap_of_a_function_of_the_point() {
+        let field = ScalarField::Function(Arc::new(|x, _, z| x * z));
+        let heatmap = Heatmap::new(field, black_to_red(), 0.0, 1.0).unwrap();
+
+        assert_approx!(heatmap.color_at(Point::new(0.5, 7.0, 0.5)).red, 0.25);
+    }
+
+    #[test]
+    fn the_range_of_a_heatmap_must_be_increasing() {
+        for (min, max) in [(1.0, 1.0), (2.0, 1.0), (0.0, f64::INFINITY)] {
+            assert_eq!(
+                Heatmap::new(ScalarField::X, ColorRamp::heat(), min, max),
+                Err(Error::InvalidRange { min, max })
+            );
+        }
+    }
+
+    #[test]
+    fn deserializing_a_heatmap() {
+        let heatmap: Heatmap = serde_json::from_str(
+            r#"{
+                "field": "distance",
+                "ramp": [
+                    { "position": 1, "color": { "red": 255, "green": 0, "blue": 0 } },
+                    { "position": 0, "color": { "red": 0, "green": 0, "blue": 0 } }
+                ],
+                "min": -1
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            heatmap,
+            Heatmap::new(ScalarField::Distance, black_to_red(), -1.0, 1.0).unwrap()
+        );
+
+        let preset: Heatmap =
+            serde_json::from_str(r#"{ "field": "vertex", "ramp": "cool_warm" }"#).unwrap();
+
+        assert_eq!(preset.ramp(), &ColorRamp::cool_warm());
+
+        let default: Heatmap = serde_json::from_str(r#"{ "field": "x" }"#).unwrap();
+
+        assert_eq!(default.ramp(), &ColorRamp::viridis());
+        assert_eq!(default.range(), (0.0, 1.0));
+    }
+
+    #[test]
+    fn deserializing_a_heatmap_with_an_invalid_range() {
+        let result = serde_json::from_str::<Heatmap>(r#"{ "field": "x", "min": 1, "max": 0 }"#);
+
+        assert!(result.is_err());
+    }
+}
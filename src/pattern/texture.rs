@@ -0,0 +1,1349 @@
+use std::{
+    f64::consts::PI,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use image::DynamicImage;
+use serde::Deserialize;
+use thiserror::Error;
+
+use crate::{
+    color::{self, Color},
+    scene::{Checked, UnknownFields},
+    transform::{self, Transform},
+    tuple::{Point, Tuple},
+};
+
+use self::tiles::TileCache;
+
+pub use self::atlas::TextureAtlas;
+
+mod atlas;
+mod tiles;
+
+/// The error type when an image texture can't be loaded.
+#[derive(Debug, Error)]
+pub enum Error {
+    /// The image could not be opened or decoded.
+    #[error("failed to open the texture at {path}")]
+    Open {
+        /// Path of the image.
+        path: PathBuf,
+
+        /// Reason why the image could not be opened.
+        #[source]
+        source: image::ImageError,
+    },
+}
+
+/// Pattern that wraps a 2-dimensional [pattern](UvPattern) around a shape, following a
+/// [mapping](UvMapping) from the points of the shape to `u` and `v` coordinates, which go from
+/// `0.0` to `1.0`.
+///
+/// # Examples
+///
+/// A sphere checkered like a globe, with twice as many squares around it as from pole to pole,
+/// so every square looks the same size at the equator.
+///
+/// ```
+/// use std::sync::Arc;
+///
+/// use raytracer::{
+///     color,
+///     pattern::{Pattern3D, TextureMap, UvCheckers, UvMapping, UvPattern},
+/// };
+///
+/// let pattern = Pattern3D::Texture(Arc::new(TextureMap::new(
+///     UvMapping::Spherical(UvPattern::Checkers(UvCheckers {
+///         width: 16.0,
+///         height: 8.0,
+///         color_a: color::consts::BLACK,
+///         color_b: color::consts::WHITE,
+///     })),
+///     Default::default(),
+/// )));
+/// ```
+///
+/// A fence whose holes are cut out of it's surface, so light and rays go through them.
+///
+/// ```no_run
+/// use std::sync::Arc;
+///
+/// use raytracer::pattern::{ImageTexture, Pattern3D, TextureMap, UvMapping, UvPattern};
+///
+/// let fence = ImageTexture::open("fence.png").unwrap();
+///
+/// let pattern = Pattern3D::Texture(Arc::new(
+///     TextureMap::new(
+///         UvMapping::Planar(UvPattern::Image(Arc::new(fence))),
+///         Default::default(),
+///     )
+///     .with_alpha_cutoff(0.5),
+/// ));
+/// ```
+///
+/// # Deserialization
+///
+/// A texture map is deserialized from it's `mapping`, and optionally a `transform` and an
+/// `alpha_cutoff`.
+///
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(from = "TextureMapDeserializer")]
+pub struct TextureMap {
+    mapping: UvMapping,
+    transform: Transform,
+    transform_inverse: Transform,
+    alpha_cutoff: f64,
+}
+
+/// Way in which the points of a shape are mapped to the `u` and `v` coordinates of a pattern.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(from = "UvMappingDeserializer")]
+pub enum UvMapping {
+    /// Wraps the pattern around the origin like the map of a globe, with `u` going around the
+    /// `y` axis and `v` going from the south pole to the north pole.
+    ///
+    /// Every point along the top and bottom edges of the pattern is squeezed into a single point
+    /// at the poles, which pinches textures around them. [Octahedral](UvMapping::Octahedral) and
+    /// [cube](UvMapping::Cube) mappings spread the pattern more evenly over a sphere.
+    ///
+    Spherical(UvPattern),
+
+    /// Wraps the pattern around the origin by folding it into an octahedron, with the north pole
+    /// at the center of the pattern and the south pole at it's corners.
+    ///
+    /// Unlike a [spherical](UvMapping::Spherical) mapping, every part of the sphere gets about
+    /// the same area of the pattern, so textures don't pinch at the poles.
+    ///
+    Octahedral(UvPattern),
+
+    /// Tiles the pattern over the `xz` plane, repeating it every unit.
+    Planar(UvPattern),
+
+    /// Wraps the pattern around the `y` axis, repeating it every unit along the axis.
+    Cylindrical(UvPattern),
+
+    /// Projects a different pattern on each face of a cube centered at the origin.
+    ///
+    /// Points are projected from the origin, so a sphere can be cube mapped to avoid the pinching
+    /// of a [spherical](UvMapping::Spherical) mapping at it's poles.
+    ///
+    Cube(Box<CubeMap>),
+
+    /// Uses the texture coordinates of the triangles of a mesh, such as a
+    /// [Model](crate::model::Model) with `vt` coordinates, repeating the pattern outside of `0.0`
+    /// to `1.0`.
+    ///
+    /// Triangles without texture coordinates are projected on the plane of the axes they face the
+    /// most, one unit of space per repetition of the pattern, so textures can still be applied to
+    /// them approximately. Shapes other than triangles are textured like a
+    /// [planar](UvMapping::Planar) mapping.
+    ///
+    Mesh(UvPattern),
+}
+
+/// 2-dimensional pattern, evaluated at `u` and `v` coordinates that go from `0.0` to `1.0`.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(from = "UvPatternDeserializer")]
+pub enum UvPattern {
+    /// A checker pattern.
+    Checkers(UvCheckers),
+
+    /// A pattern with a different color at each corner, used to check the orientation of a
+    /// mapping.
+    AlignCheck(AlignCheck),
+
+    /// An image.
+    Image(Arc<ImageTexture>),
+}
+
+/// Specification of a [checker](UvPattern::Checkers) pattern.
+///
+/// The number of squares is given for the whole pattern, so a texel debug texture with a square
+/// per texel of an image texture shows how large each texel ends up on a shape.
+///
+#[derive(Copy, Clone, Debug, PartialEq, Deserialize)]
+pub struct UvCheckers {
+    /// Number of squares along the `u` coordinate.
+    pub width: f64,
+
+    /// Number of squares along the `v` coordinate.
+    pub height: f64,
+
+    /// Color of the square at the origin, and every other square.
+    pub color_a: Color,
+
+    /// Color of the rest of the squares.
+    pub color_b: Color,
+}
+
+/// Specification of an [align check](UvPattern::AlignCheck) pattern.
+///
+/// The pattern has a main color with a square of a different color at each corner, which makes
+/// it easy to tell whether a mapping is flipped or rotated.
+///
+#[derive(Copy, Clone, Debug, PartialEq, Deserialize)]
+pub struct AlignCheck {
+    /// Color of the pattern outside of the corners.
+    pub main: Color,
+
+    /// Color of the corner where `u = 0.0` and `v = 1.0`.
+    pub upper_left: Color,
+
+    /// Color of the corner where `u = 1.0` and `v = 1.0`.
+    pub upper_right: Color,
+
+    /// Color of the corner where `u = 0.0` and `v = 0.0`.
+    pub bottom_left: Color,
+
+    /// Color of the corner where `u = 1.0` and `v = 0.0`.
+    pub bottom_right: Color,
+}
+
+/// Image used as a 2-dimensional pattern.
+///
+/// Colors are read from the nearest pixel of the image, with `v = 0.0` at the bottom row of the
+/// image. Channels are divided by their maximum value without any gamma correction, the same way
+/// rendered images are saved, so an image rendered by the ray tracer can be used as a texture
+/// with the same colors. The alpha channel is kept so it can be used to cut out
+/// parts of a surface.
+///
+/// Large images can be [memory mapped](ImageTexture::open_mapped) instead of being kept decoded in
+/// memory, so scenes with many of them don't run out of memory before rendering starts.
+///
+/// # Deserialization
+///
+/// An image texture is deserialized from the `path` of the image, and optionally whether it's
+/// `mapped`, which is `false` by default, and the `cache_dir` of the tiles of a mapped image.
+///
+/// ```json
+/// { "path": "textures/leaves.png", "mapped": true, "cache_dir": "/var/cache/raytracer" }
+/// ```
+///
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(try_from = "ImageTextureDeserializer")]
+pub struct ImageTexture {
+    width: usize,
+    height: usize,
+    texels: Texels,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Texels {
+    Decoded(Vec<(Color, f64)>),
+    Mapped(Arc<TileCache>),
+
+    /// Texels of a texture packed into a [TextureAtlas], whose top left texel is at column `x`
+    /// and row `y` of the atlas.
+    Region {
+        atlas: Arc<ImageTexture>,
+        x: usize,
+        y: usize,
+    },
+}
+
+/// Patterns of each face of a [cube mapping](UvMapping::Cube).
+///
+/// Each face is seen from outside of the cube, with the `up` face seen with the `back` face above
+/// it, and the `down` face seen with the `front` face above it. A skybox is seen from inside of
+/// the cube, so it's faces appear mirrored.
+///
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+pub struct CubeMap {
+    /// Pattern of the face towards `-x`.
+    pub left: UvPattern,
+
+    /// Pattern of the face towards `+z`.
+    pub front: UvPattern,
+
+    /// Pattern of the face towards `+x`.
+    pub right: UvPattern,
+
+    /// Pattern of the face towards `-z`.
+    pub back: UvPattern,
+
+    /// Pattern of the face towards `+y`.
+    pub up: UvPattern,
+
+    /// Pattern of the face towards `-y`.
+    pub down: UvPattern,
+}
+
+#[derive(Debug, PartialEq, Deserialize)]
+struct TextureMapDeserializer {
+    mapping: UvMapping,
+
+    #[serde(default, deserialize_with = "transform::deserialize_chain")]
+    transform: Transform,
+
+    #[serde(default)]
+    alpha_cutoff: f64,
+
+    #[serde(flatten)]
+    _unknown: UnknownFields,
+}
+
+#[derive(Debug, PartialEq, Deserialize)]
+#[serde(rename_all(deserialize = "snake_case"))]
+#[serde(tag = "type")]
+enum UvMappingDeserializer {
+    Spherical(UvMappingPatternDeserializer),
+    Octahedral(UvMappingPatternDeserializer),
+    Planar(UvMappingPatternDeserializer),
+    Cylindrical(UvMappingPatternDeserializer),
+    Cube(Box<Checked<CubeMap>>),
+    Mesh(UvMappingPatternDeserializer),
+}
+
+#[derive(Debug, PartialEq, Deserialize)]
+struct UvMappingPatternDeserializer {
+    pattern: UvPattern,
+
+    #[serde(flatten)]
+    _unknown: UnknownFields,
+}
+
+#[derive(Debug, PartialEq, Deserialize)]
+#[serde(rename_all(deserialize = "snake_case"))]
+#[serde(tag = "type")]
+enum UvPatternDeserializer {
+    Checkers(Checked<UvCheckers>),
+    AlignCheck(Checked<AlignCheck>),
+    Image(Box<ImageTexture>),
+}
+
+#[derive(Debug, PartialEq, Deserialize)]
+struct ImageTextureDeserializer {
+    path: PathBuf,
+
+    #[serde(default)]
+    mapped: bool,
+
+    cache_dir: Option<PathBuf>,
+
+    #[serde(flatten)]
+    _unknown: UnknownFields,
+}
+
+impl From<TextureMapDeserializer> for TextureMap {
+    fn from(value: TextureMapDeserializer) -> Self {
+        Self::new(value.mapping, value.transform).with_alpha_cutoff(value.alpha_cutoff)
+    }
+}
+
+impl From<UvMappingDeserializer> for UvMapping {
+    fn from(value: UvMappingDeserializer) -> Self {
+        match value {
+            UvMappingDeserializer::Spherical(spec) => Self::Spherical(spec.pattern),
+            UvMappingDeserializer::Octahedral(spec) => Self::Octahedral(spec.pattern),
+            UvMappingDeserializer::Planar(spec) => Self::Planar(spec.pattern),
+            UvMappingDeserializer::Cylindrical(spec) => Self::Cylindrical(spec.pattern),
+            UvMappingDeserializer::Cube(faces) => Self::Cube(Box::new(faces.inner)),
+            UvMappingDeserializer::Mesh(spec) => Self::Mesh(spec.pattern),
+        }
+    }
+}
+
+impl From<UvPatternDeserializer> for UvPattern {
+    fn from(value: UvPatternDeserializer) -> Self {
+        match value {
+            UvPatternDeserializer::Checkers(spec) => Self::Checkers(spec.inner),
+            UvPatternDeserializer::AlignCheck(spec) => Self::AlignCheck(spec.inner),
+            UvPatternDeserializer::Image(image) => Self::Image(Arc::new(*image)),
+        }
+    }
+}
+
+impl TryFrom<ImageTextureDeserializer> for ImageTexture {
+    type Error = Error;
+
+    fn try_from(value: ImageTextureDeserializer) -> Result<Self, Self::Error> {
+        if value.mapped {
+            Self::open_mapped_in(
+                value.path,
+                value.cache_dir.unwrap_or_else(TileCache::default_dir),
+            )
+        } else {
+            Self::open(value.path)
+        }
+    }
+}
+
+impl From<&DynamicImage> for ImageTexture {
+    fn from(image: &DynamicImage) -> Self {
+        let image = image.to_rgba32f();
+
+        let texels = image
+            .pixels()
+            .map(|pixel| {
+                let [red, green, blue, alpha] = pixel.0.map(f64::from);
+                (Color { red, green, blue }, alpha)
+            })
+            .collect();
+
+        Self {
+            width: image.width() as usize,
+            height: image.height() as usize,
+            texels: Texels::Decoded(texels),
+        }
+    }
+}
+
+impl TextureMap {
+    /// Constructs a new texture map, with a transformation relative to the shape it's used in.
+    pub fn new(mapping: UvMapping, transform: Transform) -> Self {
+        Self {
+            mapping,
+            transform,
+            transform_inverse: transform.inverse(),
+            alpha_cutoff: 0.0,
+        }
+    }
+
+    /// Cuts out the points of a shape where the alpha of the texture is below `alpha_cutoff`, so
+    /// they don't register hits, not even for shadow rays. Only [images](UvPattern::Image) have
+    /// an alpha, so nothing is cut out of other patterns. By default nothing is cut out.
+    pub fn with_alpha_cutoff(mut self, alpha_cutoff: f64) -> Self {
+        self.alpha_cutoff = alpha_cutoff;
+        self
+    }
+
+    /// Mapping of the texture.
+    pub fn mapping(&self) -> &UvMapping {
+        &self.mapping
+    }
+
+    /// Alpha below which points of a shape are cut out.
+    pub fn alpha_cutoff(&self) -> f64 {
+        self.alpha_cutoff
+    }
+
+    pub(crate) fn transform_inverse(&self) -> Transform {
+        self.transform_inverse
+    }
+
+    /// Replaces every image of the texture by the image returned by `replace` for it.
+    pub(crate) fn replace_images(
+        &mut self,
+        replace: &mut dyn FnMut(&Arc<ImageTexture>) -> Arc<ImageTexture>,
+    ) {
+        self.mapping.replace_images(replace);
+    }
+
+    /// Color of the texture at a point in the space of the texture.
+    pub(crate) fn color_at(&self, point: Point) -> Color {
+        let (pattern, uv) = self.uv_at(point);
+        pattern.color_at(uv)
+    }
+
+    /// Color of the texture at the `u` and `v` texture coordinates of a mesh, or `None` if it
+    /// doesn't have a [mesh](UvMapping::Mesh) mapping.
+    pub(crate) fn mesh_color_at(&self, (u, v): (f64, f64)) -> Option<Color> {
+        match &self.mapping {
+            UvMapping::Mesh(pattern) => {
+                Some(pattern.color_at((u.rem_euclid(1.0), v.rem_euclid(1.0))))
+            }
+            _ => None,
+        }
+    }
+
+    /// Whether a point in the space of the texture is cut out because of it's alpha.
+    pub(crate) fn cuts_out(&self, point: Point) -> bool {
+        if self.alpha_cutoff <= 0.0 {
+            return false;
+        }
+
+        let (pattern, uv) = self.uv_at(point);
+        pattern.alpha_at(uv) < self.alpha_cutoff
+    }
+
+    /// Pattern that a point in the space of the texture is mapped to, and it's coordinates in it.
+    fn uv_at(&self, point: Point) -> (&UvPattern, (f64, f64)) {
+        match &self.mapping {
+            UvMapping::Spherical(pattern) => (pattern, spherical_uv(point)),
+            UvMapping::Octahedral(pattern) => (pattern, octahedral_uv(point)),
+            UvMapping::Planar(pattern) | UvMapping::Mesh(pattern) => (pattern, planar_uv(point)),
+            UvMapping::Cylindrical(pattern) => (pattern, cylindrical_uv(point)),
+            UvMapping::Cube(faces) => faces.face_at(point),
+        }
+    }
+}
+
+impl UvMapping {
+    /// Replaces every image of the mapping by the image returned by `replace` for it.
+    fn replace_images(&mut self, replace: &mut dyn FnMut(&Arc<ImageTexture>) -> Arc<ImageTexture>) {
+        match self {
+            Self::Spherical(pattern)
+            | Self::Octahedral(pattern)
+            | Self::Planar(pattern)
+            | Self::Cylindrical(pattern)
+            | Self::Mesh(pattern) => pattern.replace_images(replace),
+            Self::Cube(faces) => {
+                let CubeMap {
+                    left,
+                    front,
+                    right,
+                    back,
+                    up,
+                    down,
+                } = faces.as_mut();
+
+                for face in [left, front, right, back, up, down] {
+                    face.replace_images(replace);
+                }
+            }
+        }
+    }
+}
+
+impl UvPattern {
+    /// Replaces the image of the pattern, if it's an image, by the image returned by `replace`
+    /// for it.
+    fn replace_images(&mut self, replace: &mut dyn FnMut(&Arc<ImageTexture>) -> Arc<ImageTexture>) {
+        if let Self::Image(image) = self {
+            *image = replace(image);
+        }
+    }
+
+    /// Color of the pattern at the given `u` and `v` coordinates.
+    pub fn color_at(&self, (u, v): (f64, f64)) -> Color {
+        match self {
+            Self::Checkers(checkers) => {
+                let u = (u * checkers.width).floor();
+                let v = (v * checkers.height).floor();
+
+                if (u + v).rem_euclid(2.0) < 1.0 {
+                    checkers.color_a
+                } else {
+                    checkers.color_b
+                }
+            }
+            Self::AlignCheck(align) => match (u < 0.2, u > 0.8, v < 0.2, v > 0.8) {
+                (true, _, _, true) => align.upper_left,
+                (_, true, _, true) => align.upper_right,
+                (true, _, true, _) => align.bottom_left,
+                (_, true, true, _) => align.bottom_right,
+                _ => align.main,
+            },
+            Self::Image(image) => image.color_at((u, v)),
+        }
+    }
+
+    /// Alpha of the pattern at the given `u` and `v` coordinates, which is always `1.0` for
+    /// patterns other than images.
+    pub fn alpha_at(&self, uv: (f64, f64)) -> f64 {
+        match self {
+            Self::Image(image) => image.alpha_at(uv),
+            Self::Checkers(_) | Self::AlignCheck(_) => 1.0,
+        }
+    }
+}
+
+impl ImageTexture {
+    /// Opens the image at `path` as a texture.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the image could not be opened or decoded.
+    ///
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let path = path.as_ref();
+
+        image::open(path)
+            .map(|image| Self::from(&image))
+            .map_err(|source| Error::Open {
+                path: path.to_path_buf(),
+                source,
+            })
+    }
+
+    /// Opens the image at `path` as a texture that's memory mapped instead of decoded into
+    /// memory, with it's tile cache in a `raytracer-tiles` directory in the temporary directory of
+    /// the system.
+    ///
+    /// The same as [ImageTexture::open_mapped_in] with that directory.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the image could not be opened or decoded.
+    ///
+    pub fn open_mapped<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        Self::open_mapped_in(path, TileCache::default_dir())
+    }
+
+    /// Opens the image at `path` as a texture that's memory mapped instead of decoded into
+    /// memory, with it's tile cache in `cache_dir`.
+    ///
+    /// The first time an image is opened this way, the whole image is decoded and written to a
+    /// cache of uncompressed tiles in `cache_dir`, which is reused until the image changes. The
+    /// cache is then memory mapped, so renders only read the tiles they look up from the disk,
+    /// and the operating system can evict them again when memory runs low. Only the images that
+    /// already have a cache are opened without decoding them.
+    ///
+    /// The cache takes 16 bytes per pixel, so a 8k image takes about 1GB of disk space. When the
+    /// cache can't be written, for example because the directory is read-only or the disk is
+    /// full, a warning is logged and the texture is decoded into memory instead.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the image could not be opened or decoded.
+    ///
+    pub fn open_mapped_in<P, D>(path: P, cache_dir: D) -> Result<Self, Error>
+    where
+        P: AsRef<Path>,
+        D: AsRef<Path>,
+    {
+        let (path, cache_dir) = (path.as_ref(), cache_dir.as_ref());
+
+        let cache = match TileCache::open(path, cache_dir) {
+            Some(cache) => cache,
+            None => {
+                let image = image::open(path).map_err(|source| Error::Open {
+                    path: path.to_path_buf(),
+                    source,
+                })?;
+
+                match TileCache::create(&image, path, cache_dir) {
+                    Ok(cache) => cache,
+                    Err(error) => {
+                        log::warn!(
+                            "failed to cache the tiles of {} in {}, decoding it into memory \
+                             instead: {error}",
+                            path.display(),
+                            cache_dir.display()
+                        );
+
+                        return Ok(Self::from(&image));
+                    }
+                }
+            }
+        };
+
+        Ok(Self {
+            width: cache.width(),
+            height: cache.height(),
+            texels: Texels::Mapped(Arc::new(cache)),
+        })
+    }
+
+    /// Width of the image in pixels.
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Height of the image in pixels.
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Color of the pixel at the given `u` and `v` coordinates.
+    pub fn color_at(&self, uv: (f64, f64)) -> Color {
+        self.texel_at(uv).0
+    }
+
+    /// Alpha of the pixel at the given `u` and `v` coordinates, from `0.0` for a fully
+    /// transparent pixel to `1.0` for an opaque one.
+    pub fn alpha_at(&self, uv: (f64, f64)) -> f64 {
+        self.texel_at(uv).1
+    }
+
+    fn texel_at(&self, (u, v): (f64, f64)) -> (Color, f64) {
+        // Coordinates are clamped to the edges of the image, so `u = 1.0` or `v = 1.0` land on
+        // the last column or row instead of outside of the image.
+        let texel = |coord: f64, size: usize| ((coord * size as f64) as usize).min(size - 1);
+
+        if self.width == 0 || self.height == 0 {
+            return (color::consts::BLACK, 0.0);
+        }
+
+        let x = texel(u.clamp(0.0, 1.0), self.width);
+        let y = texel((1.0 - v).clamp(0.0, 1.0), self.height);
+
+        self.texel(x, y)
+    }
+
+    /// Color and alpha of the texel at column `x` and row `y`, counting rows from the top of the
+    /// image.
+    fn texel(&self, x: usize, y: usize) -> (Color, f64) {
+        match &self.texels {
+            Texels::Decoded(texels) => texels[y * self.width + x],
+            Texels::Mapped(cache) => cache.texel(x, y),
+            Texels::Region {
+                atlas,
+                x: left,
+                y: top,
+            } => atlas.texel(left + x, top + y),
+        }
+    }
+}
+
+impl CubeMap {
+    /// Cube with an [align check](UvPattern::AlignCheck) pattern of a different color on each
+    /// face, with the colors at the corners matching the main colors of the faces that meet at
+    /// them. Mapping it to a cube or a skybox shows right away if any face is flipped or
+    /// rotated.
+    pub fn alignment() -> Self {
+        let color = |red, green, blue| Color { red, green, blue };
+
+        let red = color(1.0, 0.0, 0.0);
+        let yellow = color(1.0, 1.0, 0.0);
+        let brown = color(1.0, 0.5, 0.0);
+        let green = color(0.0, 1.0, 0.0);
+        let cyan = color(0.0, 1.0, 1.0);
+        let blue = color(0.0, 0.0, 1.0);
+        let purple = color(1.0, 0.0, 1.0);
+        let white = color(1.0, 1.0, 1.0);
+
+        let face = |main, upper_left, upper_right, bottom_left, bottom_right| {
+            UvPattern::AlignCheck(AlignCheck {
+                main,
+                upper_left,
+                upper_right,
+                bottom_left,
+                bottom_right,
+            })
+        };
+
+        Self {
+            left: face(yellow, cyan, red, blue, brown),
+            front: face(cyan, red, yellow, brown, green),
+            right: face(red, yellow, purple, green, white),
+            back: face(green, purple, cyan, white, blue),
+            up: face(brown, cyan, purple, red, yellow),
+            down: face(purple, brown, green, blue, white),
+        }
+    }
+
+    fn face_at(&self, point: Point) -> (&UvPattern, (f64, f64)) {
+        let Point(Tuple { x, y, z, .. }) = point;
+        let coord = x.abs().max(y.abs()).max(z.abs());
+
+        // Points are projected from the origin onto the surface of the cube, so shapes other
+        // than a unit cube, like spheres, are mapped too.
+        let (x, y, z) = if coord > 0.0 {
+            (x / coord, y / coord, z / coord)
+        } else {
+            (x, y, z)
+        };
+
+        // Coordinates along the face, from `0.0` to `2.0`.
+        let wrap = |c: f64| c.rem_euclid(2.0) / 2.0;
+
+        if x == 1.0 {
+            (&self.right, (wrap(1.0 - z), wrap(y + 1.0)))
+        } else if x == -1.0 {
+            (&self.left, (wrap(z + 1.0), wrap(y + 1.0)))
+        } else if y == 1.0 {
+            (&self.up, (wrap(x + 1.0), wrap(1.0 - z)))
+        } else if y == -1.0 {
+            (&self.down, (wrap(x + 1.0), wrap(z + 1.0)))
+        } else if z == 1.0 {
+            (&self.front, (wrap(x + 1.0), wrap(y + 1.0)))
+        } else {
+            (&self.back, (wrap(1.0 - x), wrap(y + 1.0)))
+        }
+    }
+}
+
+fn spherical_uv(point: Point) -> (f64, f64) {
+    let Point(Tuple { x, y, z, .. }) = point;
+
+    let theta = x.atan2(z);
+    let radius = (x * x + y * y + z * z).sqrt();
+    let phi = if radius > 0.0 {
+        (y / radius).clamp(-1.0, 1.0).acos()
+    } else {
+        0.0
+    };
+
+    let raw_u = theta / (2.0 * PI);
+
+    (1.0 - (raw_u + 0.5), 1.0 - phi / PI)
+}
+
+fn octahedral_uv(point: Point) -> (f64, f64) {
+    let Point(Tuple { x, y, z, .. }) = point;
+
+    let length = x.abs() + y.abs() + z.abs();
+    if length == 0.0 {
+        return (0.5, 0.5);
+    }
+
+    let (x, y, z) = (x / length, y / length, z / length);
+
+    // The lower half of the octahedron is folded over the corners of the upper half.
+    let (x, z) = if y >= 0.0 {
+        (x, z)
+    } else {
+        let sign = |c: f64| if c >= 0.0 { 1.0 } else { -1.0 };
+        ((1.0 - z.abs()) * sign(x), (1.0 - x.abs()) * sign(z))
+    };
+
+    ((x + 1.0) / 2.0, (z + 1.0) / 2.0)
+}
+
+fn planar_uv(point: Point) -> (f64, f64) {
+    (point.0.x.rem_euclid(1.0), point.0.z.rem_euclid(1.0))
+}
+
+fn cylindrical_uv(point: Point) -> (f64, f64) {
+    let Point(Tuple { x, y, z, .. }) = point;
+    let raw_u = x.atan2(z) / (2.0 * PI);
+
+    (1.0 - (raw_u + 0.5), y.rem_euclid(1.0))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{assert_approx, color};
+
+    use super::*;
+
+    fn checkers() -> UvPattern {
+        UvPattern::Checkers(UvCheckers {
+            width: 2.0,
+            height: 2.0,
+            color_a: color::consts::BLACK,
+            color_b: color::consts::WHITE,
+        })
+    }
+
+    fn align_check() -> UvPattern {
+        UvPattern::AlignCheck(AlignCheck {
+            main: color::consts::WHITE,
+            upper_left: color::consts::RED,
+            upper_right: Color {
+                red: 1.0,
+                green: 1.0,
+                blue: 0.0,
+            },
+            bottom_left: color::consts::GREEN,
+            bottom_right: Color {
+                red: 0.0,
+                green: 1.0,
+                blue: 1.0,
+            },
+        })
+    }
+
+    #[test]
+    fn checker_pattern_in_2d() {
+        for (u, v, expected) in [
+            (0.0, 0.0, color::consts::BLACK),
+            (0.5, 0.0, color::consts::WHITE),
+            (0.0, 0.5, color::consts::WHITE),
+            (0.5, 0.5, color::consts::BLACK),
+            (1.0, 1.0, color::consts::BLACK),
+        ] {
+            assert_eq!(checkers().color_at((u, v)), expected);
+        }
+    }
+
+    #[test]
+    fn using_a_spherical_mapping_on_a_3d_point() {
+        for (point, u, v) in [
+            (Point::new(0.0, 0.0, -1.0), 0.0, 0.5),
+            (Point::new(1.0, 0.0, 0.0), 0.25, 0.5),
+            (Point::new(0.0, 0.0, 1.0), 0.5, 0.5),
+            (Point::new(-1.0, 0.0, 0.0), 0.75, 0.5),
+            (Point::new(0.0, 1.0, 0.0), 0.5, 1.0),
+            (Point::new(0.0, -1.0, 0.0), 0.5, 0.0),
+            (
+                Point::new(2_f64.sqrt() / 2.0, 2_f64.sqrt() / 2.0, 0.0),
+                0.25,
+                0.75,
+            ),
+        ] {
+            let (actual_u, actual_v) = spherical_uv(point);
+            assert_approx!(actual_u, u);
+            assert_approx!(actual_v, v);
+        }
+    }
+
+    #[test]
+    fn using_an_octahedral_mapping_on_a_3d_point() {
+        for (point, u, v) in [
+            (Point::new(0.0, 1.0, 0.0), 0.5, 0.5),
+            (Point::new(1.0, 0.0, 0.0), 1.0, 0.5),
+            (Point::new(0.0, 0.0, 1.0), 0.5, 1.0),
+            (Point::new(-1.0, 0.0, 0.0), 0.0, 0.5),
+            (Point::new(0.0, 0.0, -1.0), 0.5, 0.0),
+            (Point::new(0.0, -1.0, 0.0), 1.0, 1.0),
+            (Point::new(1.0, 1.0, 0.0), 0.75, 0.5),
+            (Point::new(1.0, -2.0, 1.0), 0.875, 0.875),
+        ] {
+            let (actual_u, actual_v) = octahedral_uv(point);
+            assert_approx!(actual_u, u);
+            assert_approx!(actual_v, v);
+        }
+    }
+
+    #[test]
+    fn an_octahedral_mapping_does_not_pinch_the_poles() {
+        // Points around the north pole, a hundredth of a radian away from it.
+        let around_the_pole = (0..8).map(|i| {
+            let longitude = i as f64 * PI / 4.0;
+            let (sin, cos) = 0.01_f64.sin_cos();
+            Point::new(sin * longitude.cos(), cos, sin * longitude.sin())
+        });
+
+        let spread = |uv: fn(Point) -> (f64, f64)| {
+            let (us, vs): (Vec<_>, Vec<_>) = around_the_pole.clone().map(uv).unzip();
+            let range = |c: Vec<f64>| {
+                c.iter().copied().fold(f64::MIN, f64::max)
+                    - c.iter().copied().fold(f64::MAX, f64::min)
+            };
+            range(us).max(range(vs))
+        };
+
+        assert!(spread(spherical_uv) > 0.5);
+        assert!(spread(octahedral_uv) < 0.02);
+    }
+
+    #[test]
+    fn using_a_planar_mapping_on_a_3d_point() {
+        for (point, u, v) in [
+            (Point::new(0.25, 0.0, 0.5), 0.25, 0.5),
+            (Point::new(0.25, 0.0, -0.25), 0.25, 0.75),
+            (Point::new(0.25, 0.5, -0.25), 0.25, 0.75),
+            (Point::new(1.25, 0.0, 0.5), 0.25, 0.5),
+            (Point::new(0.25, 0.0, -1.75), 0.25, 0.25),
+            (Point::new(1.0, 0.0, -1.0), 0.0, 0.0),
+            (Point::new(0.0, 0.0, 0.0), 0.0, 0.0),
+        ] {
+            let (actual_u, actual_v) = planar_uv(point);
+            assert_approx!(actual_u, u);
+            assert_approx!(actual_v, v);
+        }
+    }
+
+    #[test]
+    fn a_mesh_mapping_repeats_the_pattern_at_texture_coordinates() {
+        let texture = TextureMap::new(UvMapping::Mesh(align_check()), Default::default());
+
+        assert_eq!(texture.mesh_color_at((0.1, 0.9)), Some(color::consts::RED));
+        assert_eq!(texture.mesh_color_at((1.1, -0.1)), Some(color::consts::RED));
+        assert_eq!(
+            texture.mesh_color_at((0.5, 0.5)),
+            Some(color::consts::WHITE)
+        );
+
+        let planar = TextureMap::new(UvMapping::Planar(align_check()), Default::default());
+        assert_eq!(planar.mesh_color_at((0.1, 0.9)), None);
+    }
+
+    #[test]
+    fn a_mesh_mapping_on_a_3d_point_is_planar() {
+        let texture = TextureMap::new(UvMapping::Mesh(align_check()), Default::default());
+
+        assert_eq!(
+            texture.color_at(Point::new(0.1, 5.0, 0.9)),
+            color::consts::RED
+        );
+    }
+
+    #[test]
+    fn using_a_cylindrical_mapping_on_a_3d_point() {
+        for (point, u, v) in [
+            (Point::new(0.0, 0.0, -1.0), 0.0, 0.0),
+            (Point::new(0.0, 0.5, -1.0), 0.0, 0.5),
+            (Point::new(0.0, 1.0, -1.0), 0.0, 0.0),
+            (Point::new(0.70711, 0.5, -0.70711), 0.125, 0.5),
+            (Point::new(1.0, 0.5, 0.0), 0.25, 0.5),
+            (Point::new(0.70711, 0.5, 0.70711), 0.375, 0.5),
+            (Point::new(0.0, -0.25, 1.0), 0.5, 0.75),
+            (Point::new(-0.70711, 0.5, 0.70711), 0.625, 0.5),
+            (Point::new(-1.0, 1.25, 0.0), 0.75, 0.25),
+            (Point::new(-0.70711, 0.5, -0.70711), 0.875, 0.5),
+        ] {
+            let (actual_u, actual_v) = cylindrical_uv(point);
+            assert_approx!(actual_u, u);
+            assert_approx!(actual_v, v);
+        }
+    }
+
+    #[test]
+    fn using_a_texture_map_with_a_spherical_map() {
+        let texture = TextureMap::new(
+            UvMapping::Spherical(UvPattern::Checkers(UvCheckers {
+                width: 16.0,
+                height: 8.0,
+                color_a: color::consts::BLACK,
+                color_b: color::consts::WHITE,
+            })),
+            Default::default(),
+        );
+
+        for (point, expected) in [
+            (Point::new(0.4315, 0.4670, 0.7719), color::consts::WHITE),
+            (Point::new(-0.9654, 0.2552, -0.0534), color::consts::BLACK),
+            (Point::new(0.1039, 0.7090, 0.6975), color::consts::WHITE),
+            (Point::new(-0.4986, -0.7856, -0.3663), color::consts::BLACK),
+            (Point::new(-0.0317, -0.9395, 0.3411), color::consts::BLACK),
+            (Point::new(0.4809, -0.7721, 0.4154), color::consts::BLACK),
+            (Point::new(0.0285, -0.9612, -0.2745), color::consts::BLACK),
+            (Point::new(-0.5734, -0.2162, -0.7903), color::consts::WHITE),
+            (Point::new(0.7688, -0.1470, 0.6223), color::consts::BLACK),
+            (Point::new(-0.7652, 0.2175, 0.6060), color::consts::BLACK),
+        ] {
+            assert_eq!(texture.color_at(point), expected);
+        }
+    }
+
+    #[test]
+    fn layout_of_the_align_check_pattern() {
+        let pattern = align_check();
+
+        let UvPattern::AlignCheck(colors) = pattern else {
+            unreachable!()
+        };
+
+        for (u, v, expected) in [
+            (0.5, 0.5, colors.main),
+            (0.1, 0.9, colors.upper_left),
+            (0.9, 0.9, colors.upper_right),
+            (0.1, 0.1, colors.bottom_left),
+            (0.9, 0.1, colors.bottom_right),
+        ] {
+            assert_eq!(pattern.color_at((u, v)), expected);
+        }
+    }
+
+    #[test]
+    fn finding_the_colors_on_the_faces_of_the_alignment_cube() {
+        let cube = TextureMap::new(
+            UvMapping::Cube(Box::new(CubeMap::alignment())),
+            Default::default(),
+        );
+        let color = |red, green, blue| Color { red, green, blue };
+
+        let red = color(1.0, 0.0, 0.0);
+        let yellow = color(1.0, 1.0, 0.0);
+        let brown = color(1.0, 0.5, 0.0);
+        let green = color(0.0, 1.0, 0.0);
+        let cyan = color(0.0, 1.0, 1.0);
+        let blue = color(0.0, 0.0, 1.0);
+        let purple = color(1.0, 0.0, 1.0);
+        let white = color(1.0, 1.0, 1.0);
+
+        for (x, y, z, expected) in [
+            (-1.0, 0.0, 0.0, yellow),
+            (-1.0, 0.9, -0.9, cyan),
+            (-1.0, 0.9, 0.9, red),
+            (-1.0, -0.9, -0.9, blue),
+            (-1.0, -0.9, 0.9, brown),
+            (0.0, 0.0, 1.0, cyan),
+            (-0.9, 0.9, 1.0, red),
+            (0.9, 0.9, 1.0, yellow),
+            (-0.9, -0.9, 1.0, brown),
+            (0.9, -0.9, 1.0, green),
+            (1.0, 0.0, 0.0, red),
+            (1.0, 0.9, 0.9, yellow),
+            (1.0, 0.9, -0.9, purple),
+            (1.0, -0.9, 0.9, green),
+            (1.0, -0.9, -0.9, white),
+            (0.0, 0.0, -1.0, green),
+            (0.9, 0.9, -1.0, purple),
+            (-0.9, 0.9, -1.0, cyan),
+            (0.9, -0.9, -1.0, white),
+            (-0.9, -0.9, -1.0, blue),
+            (0.0, 1.0, 0.0, brown),
+            (-0.9, 1.0, -0.9, cyan),
+            (0.9, 1.0, -0.9, purple),
+            (-0.9, 1.0, 0.9, red),
+            (0.9, 1.0, 0.9, yellow),
+            (0.0, -1.0, 0.0, purple),
+            (-0.9, -1.0, 0.9, brown),
+            (0.9, -1.0, 0.9, green),
+            (-0.9, -1.0, -0.9, blue),
+            (0.9, -1.0, -0.9, white),
+        ] {
+            assert_eq!(cube.color_at(Point::new(x, y, z)), expected);
+        }
+    }
+
+    #[test]
+    fn cube_mapping_a_sphere() {
+        let cube = TextureMap::new(
+            UvMapping::Cube(Box::new(CubeMap::alignment())),
+            Default::default(),
+        );
+        let on_sphere = |x: f64, y: f64, z: f64| {
+            let length = (x * x + y * y + z * z).sqrt();
+            Point::new(x / length, y / length, z / length)
+        };
+
+        for (x, y, z) in [
+            (-1.0, 0.9, -0.9),
+            (0.9, -0.9, 1.0),
+            (1.0, 0.0, 0.0),
+            (0.9, 1.0, -0.9),
+        ] {
+            assert_eq!(
+                cube.color_at(on_sphere(x, y, z)),
+                cube.color_at(Point::new(x, y, z))
+            );
+        }
+    }
+
+    #[test]
+    fn deserializing_a_texture_map() {
+        let texture: TextureMap = serde_json::from_str(
+            r#"{
+                "mapping": {
+                    "type": "planar",
+                    "pattern": {
+                        "type": "checkers",
+                        "width": 2,
+                        "height": 2,
+                        "color_a": { "red": 0, "green": 0, "blue": 0 },
+                        "color_b": { "red": 255, "green": 255, "blue": 255 }
+                    }
+                }
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            texture,
+            TextureMap::new(UvMapping::Planar(checkers()), Default::default())
+        );
+    }
+
+    #[test]
+    fn deserializing_a_mesh_mapping() {
+        let mapping: UvMapping = serde_json::from_str(
+            r#"{
+                "type": "mesh",
+                "pattern": {
+                    "type": "checkers",
+                    "width": 2,
+                    "height": 2,
+                    "color_a": { "red": 0, "green": 0, "blue": 0 },
+                    "color_b": { "red": 255, "green": 255, "blue": 255 }
+                }
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(mapping, UvMapping::Mesh(checkers()));
+    }
+
+    fn image() -> ImageTexture {
+        // Red and green on the top row, and blue and a transparent pixel on the bottom row.
+        let pixels = vec![
+            255, 0, 0, 255, 0, 255, 0, 255, //
+            0, 0, 255, 255, 0, 0, 0, 0,
+        ];
+
+        let image = image::RgbaImage::from_raw(2, 2, pixels).unwrap();
+        ImageTexture::from(&DynamicImage::ImageRgba8(image))
+    }
+
+    #[test]
+    fn an_image_texture_has_the_bottom_row_of_the_image_at_the_bottom() {
+        let image = image();
+
+        assert_eq!((image.width(), image.height()), (2, 2));
+
+        for (uv, expected) in [
+            ((0.0, 1.0), color::consts::RED),
+            ((0.75, 0.75), color::consts::GREEN),
+            ((0.25, 0.25), color::consts::BLUE),
+            ((1.0, 0.0), color::consts::BLACK),
+        ] {
+            assert_eq!(image.color_at(uv), expected);
+        }
+    }
+
+    #[test]
+    fn the_alpha_of_an_image_texture_is_kept() {
+        let image = image();
+
+        assert_approx!(image.alpha_at((0.25, 0.25)), 1.0);
+        assert_approx!(image.alpha_at((0.75, 0.25)), 0.0);
+    }
+
+    #[test]
+    fn coordinates_outside_of_an_image_texture_are_clamped_to_its_edges() {
+        let image = image();
+
+        assert_eq!(image.color_at((-1.0, 2.0)), color::consts::RED);
+        assert_eq!(image.color_at((0.2, -0.5)), color::consts::BLUE);
+    }
+
+    #[test]
+    fn using_an_image_texture_as_a_uv_pattern() {
+        let pattern = UvPattern::Image(Arc::new(image()));
+
+        assert_eq!(pattern.color_at((0.75, 0.75)), color::consts::GREEN);
+    }
+
+    #[test]
+    fn deserializing_an_image_texture_that_does_not_exist() {
+        let result = serde_json::from_str::<UvPattern>(
+            r#"{ "type": "image", "path": "textures/does-not-exist.png" }"#,
+        );
+
+        assert!(result
+            .unwrap_err()
+            .to_string()
+            .contains("does-not-exist.png"));
+    }
+
+    #[test]
+    fn cutting_out_a_texture_by_its_alpha() {
+        let texture = TextureMap::new(
+            UvMapping::Planar(UvPattern::Image(Arc::new(image()))),
+            Default::default(),
+        );
+        let (opaque, transparent) = (Point::new(0.25, 0.0, 0.25), Point::new(0.75, 0.0, 0.25));
+
+        assert!(!texture.cuts_out(transparent));
+
+        let texture = texture.with_alpha_cutoff(0.5);
+
+        assert!(!texture.cuts_out(opaque));
+        assert!(texture.cuts_out(transparent));
+
+        let texture = TextureMap::new(UvMapping::Planar(checkers()), Default::default())
+            .with_alpha_cutoff(0.5);
+
+        assert!(!texture.cuts_out(transparent));
+    }
+
+    #[test]
+    fn deserializing_a_texture_map_with_an_alpha_cutoff() {
+        let texture: TextureMap = serde_json::from_str(
+            r#"{
+                "mapping": {
+                    "type": "planar",
+                    "pattern": {
+                        "type": "checkers",
+                        "width": 2,
+                        "height": 2,
+                        "color_a": { "red": 0, "green": 0, "blue": 0 },
+                        "color_b": { "red": 255, "green": 255, "blue": 255 }
+                    }
+                },
+                "alpha_cutoff": 0.5
+            }"#,
+        )
+        .unwrap();
+
+        assert_approx!(texture.alpha_cutoff(), 0.5);
+    }
+
+    fn saved_image(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("raytracer-texture-{name}.png"));
+
+        // Wider than a tile, with a transparent column in the second tile.
+        let image = image::RgbaImage::from_fn(70, 3, |x, y| {
+            let (x, y) = (x as u8, y as u8);
+            image::Rgba([x * 3, y * 80, 255 - x, if x == 65 { 0 } else { 255 }])
+        });
+
+        image.save(&path).unwrap();
+        let _ = std::fs::remove_file(TileCache::cache_path(&path, &TileCache::default_dir()));
+
+        path
+    }
+
+    #[test]
+    fn a_mapped_image_texture_has_the_same_texels_as_a_decoded_one() {
+        let path = saved_image("mapped");
+
+        let decoded = ImageTexture::open(&path).unwrap();
+        let mapped = ImageTexture::open_mapped(&path).unwrap();
+
+        assert_eq!((mapped.width(), mapped.height()), (70, 3));
+        assert!(TileCache::cache_path(&path, &TileCache::default_dir()).exists());
+
+        for y in 0..3 {
+            for x in 0..70 {
+                let uv = ((x as f64 + 0.5) / 70.0, 1.0 - (y as f64 + 0.5) / 3.0);
+
+                assert_eq!(mapped.color_at(uv), decoded.color_at(uv));
+                assert_approx!(mapped.alpha_at(uv), decoded.alpha_at(uv));
+            }
+        }
+    }
+
+    #[test]
+    fn the_tile_cache_of_a_mapped_image_is_reused_until_it_is_invalid() {
+        let path = saved_image("reused");
+        let cache_path = TileCache::cache_path(&path, &TileCache::default_dir());
+
+        ImageTexture::open_mapped(&path).unwrap();
+        let written = std::fs::metadata(&cache_path).unwrap().modified().unwrap();
+
+        ImageTexture::open_mapped(&path).unwrap();
+        let reused = std::fs::metadata(&cache_path).unwrap().modified().unwrap();
+
+        assert_eq!(written, reused);
+
+        // A cache that isn't a valid cache is written again.
+        std::fs::write(&cache_path, b"not a tile cache").unwrap();
+
+        let mapped = ImageTexture::open_mapped(&path).unwrap();
+
+        assert_eq!(
+            mapped.color_at((0.0, 1.0)),
+            ImageTexture::open(&path).unwrap().color_at((0.0, 1.0))
+        );
+    }
+
+    #[test]
+    fn a_mapped_image_is_decoded_when_it_s_tiles_cant_be_cached() {
+        let path = saved_image("uncached");
+
+        // A directory can't be created inside of a file.
+        let cache_dir = path.join("tiles");
+
+        let texture = ImageTexture::open_mapped_in(&path, &cache_dir).unwrap();
+
+        assert!(matches!(texture.texels, Texels::Decoded(_)));
+        assert_eq!(texture, ImageTexture::open(&path).unwrap());
+
+        // A cache that can't replace what's already at it's path leaves nothing behind.
+        let cache_dir = std::env::temp_dir().join("raytracer-texture-blocked");
+        let cache_path = TileCache::cache_path(&path, &cache_dir);
+        std::fs::create_dir_all(cache_path.join("blocked")).unwrap();
+
+        let texture = ImageTexture::open_mapped_in(&path, &cache_dir).unwrap();
+
+        assert!(matches!(texture.texels, Texels::Decoded(_)));
+
+        let mut partial_path = cache_path.into_os_string();
+        partial_path.push(".partial");
+
+        assert!(!PathBuf::from(partial_path).exists());
+    }
+
+    #[test]
+    fn deserializing_a_mapped_image_texture() {
+        let path = saved_image("deserialized");
+
+        let texture: ImageTexture =
+            serde_json::from_value(serde_json::json!({ "path": path, "mapped": true })).unwrap();
+
+        assert_eq!(texture, ImageTexture::open_mapped(&path).unwrap());
+        assert_ne!(texture, ImageTexture::open(&path).unwrap());
+
+        let cache_dir = std::env::temp_dir().join("raytracer-texture-cache-dir");
+
+        let texture: ImageTexture = serde_json::from_value(serde_json::json!({
+            "path": path,
+            "mapped": true,
+            "cache_dir": cache_dir,
+        }))
+        .unwrap();
+
+        assert_eq!(
+            texture,
+            ImageTexture::open_mapped_in(&path, &cache_dir).unwrap()
+        );
+        assert!(TileCache::cache_path(&path, &cache_dir).exists());
+    }
+}
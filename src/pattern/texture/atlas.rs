@@ -0,0 +1,224 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
+
+use crate::color;
+
+use super::{ImageTexture, Texels};
+
+/// Many image textures packed into a single image.
+///
+/// Scenes with hundreds of small textures, such as decals, keep hundreds of separate images in
+/// memory. Packing them into an atlas keeps their texels together in a single image, and replaces
+/// each texture by a region of the atlas that looks exactly the same, so shapes can keep using
+/// their own `u` and `v` coordinates. Usually the textures of a whole world are packed at once
+/// with [World::pack_textures](crate::world::World::pack_textures).
+///
+/// # Examples
+///
+/// ```
+/// use std::sync::Arc;
+///
+/// use raytracer::pattern::{ImageTexture, TextureAtlas};
+///
+/// # let decal = |width, height| {
+/// #     let image = image::RgbaImage::new(width, height);
+/// #     Arc::new(ImageTexture::from(&image::DynamicImage::ImageRgba8(image)))
+/// # };
+/// let (logo, sticker) = (decal(32, 16), decal(8, 8));
+///
+/// let atlas = TextureAtlas::pack([logo.clone(), sticker.clone()]);
+///
+/// let region = atlas.region_of(&logo).unwrap();
+///
+/// assert_eq!((region.width(), region.height()), (32, 16));
+/// assert!(atlas.image().width() >= 32);
+/// ```
+///
+#[derive(Clone, Debug)]
+pub struct TextureAtlas {
+    image: Arc<ImageTexture>,
+    regions: HashMap<usize, (Arc<ImageTexture>, Arc<ImageTexture>)>,
+}
+
+impl TextureAtlas {
+    /// Packs the given textures into a new atlas. Textures that are given more than once are only
+    /// packed once.
+    ///
+    /// Textures are placed on shelves, from the tallest to the shortest, in an atlas about as wide
+    /// as it's tall. Texels are looked up in the region of each texture without blending them with
+    /// their neighbours, so textures don't need padding between them.
+    ///
+    pub fn pack<I>(textures: I) -> Self
+    where
+        I: IntoIterator<Item = Arc<ImageTexture>>,
+    {
+        let mut unique: Vec<Arc<ImageTexture>> = vec![];
+        let mut seen = HashSet::new();
+
+        for texture in textures {
+            if seen.insert(Arc::as_ptr(&texture) as usize) {
+                unique.push(texture);
+            }
+        }
+
+        let mut order: Vec<_> = (0..unique.len()).collect();
+        order.sort_by_key(|&i| std::cmp::Reverse((unique[i].height(), unique[i].width())));
+
+        let area: usize = unique.iter().map(|t| t.width() * t.height()).sum();
+        let widest = unique.iter().map(|t| t.width()).max().unwrap_or(0);
+        let width = widest.max((area as f64).sqrt().ceil() as usize);
+
+        let mut positions = vec![(0, 0); unique.len()];
+        let (mut x, mut y, mut shelf_height) = (0, 0, 0);
+
+        for &i in &order {
+            let texture = &unique[i];
+
+            if x + texture.width() > width {
+                y += shelf_height;
+                (x, shelf_height) = (0, 0);
+            }
+
+            positions[i] = (x, y);
+            x += texture.width();
+            shelf_height = shelf_height.max(texture.height());
+        }
+
+        let height = y + shelf_height;
+        let mut texels = vec![(color::consts::BLACK, 0.0); width * height];
+
+        for (texture, &(left, top)) in unique.iter().zip(&positions) {
+            for row in 0..texture.height() {
+                for column in 0..texture.width() {
+                    texels[(top + row) * width + left + column] = texture.texel(column, row);
+                }
+            }
+        }
+
+        let image = Arc::new(ImageTexture {
+            width,
+            height,
+            texels: Texels::Decoded(texels),
+        });
+
+        let regions = unique
+            .into_iter()
+            .zip(positions)
+            .map(|(texture, (x, y))| {
+                let region = Arc::new(ImageTexture {
+                    width: texture.width(),
+                    height: texture.height(),
+                    texels: Texels::Region {
+                        atlas: image.clone(),
+                        x,
+                        y,
+                    },
+                });
+
+                (Arc::as_ptr(&texture) as usize, (texture, region))
+            })
+            .collect();
+
+        Self { image, regions }
+    }
+
+    /// Image that every texture of the atlas is packed into.
+    pub fn image(&self) -> &Arc<ImageTexture> {
+        &self.image
+    }
+
+    /// Number of textures packed into the atlas.
+    pub fn len(&self) -> usize {
+        self.regions.len()
+    }
+
+    /// Whether no texture was packed into the atlas.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Region of the atlas that replaces a texture, or `None` if the texture wasn't packed into
+    /// the atlas.
+    pub fn region_of(&self, texture: &Arc<ImageTexture>) -> Option<&Arc<ImageTexture>> {
+        self.regions
+            .get(&(Arc::as_ptr(texture) as usize))
+            .map(|(_, region)| region)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{DynamicImage, Rgba, RgbaImage};
+
+    use super::*;
+
+    fn texture(width: u32, height: u32, shade: u8) -> Arc<ImageTexture> {
+        let image = RgbaImage::from_fn(width, height, |x, y| {
+            Rgba([shade, x as u8 * 10, y as u8 * 10, 255])
+        });
+
+        Arc::new(ImageTexture::from(&DynamicImage::ImageRgba8(image)))
+    }
+
+    #[test]
+    fn packing_textures_into_an_atlas() {
+        let textures = [texture(4, 2, 50), texture(3, 3, 100), texture(2, 1, 150)];
+
+        let atlas = TextureAtlas::pack(textures.clone());
+
+        assert_eq!(atlas.len(), 3);
+
+        // The atlas is at least as large as the textures put together, but no wider than needed.
+        let image = atlas.image();
+        assert!(image.width() * image.height() >= 4 * 2 + 3 * 3 + 2);
+        assert_eq!(image.width(), 5);
+
+        for texture in &textures {
+            let region = atlas.region_of(texture).unwrap();
+
+            assert_eq!(
+                (region.width(), region.height()),
+                (texture.width(), texture.height())
+            );
+
+            for row in 0..texture.height() {
+                for column in 0..texture.width() {
+                    assert_eq!(region.texel(column, row), texture.texel(column, row));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn a_region_of_an_atlas_clamps_coordinates_to_its_own_edges() {
+        let (small, large) = (texture(2, 2, 50), texture(4, 4, 200));
+
+        let atlas = TextureAtlas::pack([small.clone(), large]);
+        let region = atlas.region_of(&small).unwrap();
+
+        for uv in [(-1.0, 2.0), (0.99, 0.01), (2.0, -1.0), (0.3, 0.6)] {
+            assert_eq!(region.color_at(uv), small.color_at(uv));
+        }
+    }
+
+    #[test]
+    fn a_texture_given_twice_is_packed_once() {
+        let decal = texture(3, 2, 50);
+
+        let atlas = TextureAtlas::pack([decal.clone(), decal.clone()]);
+
+        assert_eq!(atlas.len(), 1);
+        assert_eq!((atlas.image().width(), atlas.image().height()), (3, 2));
+        assert!(atlas.region_of(&texture(3, 2, 50)).is_none());
+    }
+
+    #[test]
+    fn an_empty_atlas() {
+        let atlas = TextureAtlas::pack([]);
+
+        assert!(atlas.is_empty());
+        assert_eq!(atlas.image().color_at((0.5, 0.5)), color::consts::BLACK);
+    }
+}
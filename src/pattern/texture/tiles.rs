@@ -0,0 +1,235 @@
+use std::{
+    fs::{self, File},
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+use image::DynamicImage;
+use memmap2::Mmap;
+
+use crate::{color::Color, render};
+
+/// Number of texels along each side of a tile.
+const TILE_SIZE: usize = 64;
+
+/// Signature at the start of every tile cache.
+const MAGIC: &[u8; 8] = b"RTTILES1";
+
+/// Length of the header of a tile cache, which is the signature followed by the width, the height
+/// and the size of the tiles of the image.
+const HEADER_LEN: usize = MAGIC.len() + 3 * 4;
+
+/// Length of a texel in a tile cache, which is it's red, green, blue and alpha channels.
+const TEXEL_LEN: usize = 4 * 4;
+
+/// Image decoded once into square tiles of uncompressed texels, stored in a file in a cache
+/// directory and memory mapped, so later renders only read the tiles that are actually looked up
+/// from the disk, and the operating system can evict them again when memory runs low.
+///
+/// Writing the cache decodes the whole image, one image at a time, so it's only the renders that
+/// reuse the cache that don't decode the image at all.
+///
+/// The texels of each tile are contiguous, so the texels around a point of a surface, which are
+/// the ones looked up together, share a few pages of memory instead of one page per row.
+///
+#[derive(Debug)]
+pub(super) struct TileCache {
+    path: PathBuf,
+    map: Mmap,
+    width: usize,
+    height: usize,
+    tiles_per_row: usize,
+}
+
+impl PartialEq for TileCache {
+    fn eq(&self, other: &Self) -> bool {
+        self.path == other.path
+    }
+}
+
+impl TileCache {
+    /// Directory that tile caches are written to when no other directory is given, which is a
+    /// `raytracer-tiles` directory in the temporary directory of the system.
+    pub(super) fn default_dir() -> PathBuf {
+        std::env::temp_dir().join("raytracer-tiles")
+    }
+
+    /// Maps the tile cache of the image at `path` in `cache_dir`, if it has one that was written
+    /// after the image last changed.
+    pub(super) fn open(path: &Path, cache_dir: &Path) -> Option<Self> {
+        let cache_path = Self::cache_path(path, cache_dir);
+
+        if Self::is_fresh(path, &cache_path) {
+            Self::map(&cache_path).ok()
+        } else {
+            None
+        }
+    }
+
+    /// Writes the tile cache of `image`, decoded from the image at `path`, to `cache_dir` and maps
+    /// it.
+    pub(super) fn create(image: &DynamicImage, path: &Path, cache_dir: &Path) -> io::Result<Self> {
+        let cache_path = Self::cache_path(path, cache_dir);
+
+        fs::create_dir_all(cache_dir)?;
+        Self::write(image, &cache_path)?;
+        Self::map(&cache_path)
+    }
+
+    /// Path of the tile cache of the image at `path` in `cache_dir`, which is named after the file
+    /// name of the image and a hash of it's whole path, so images with the same name in different
+    /// directories don't share a cache.
+    pub(super) fn cache_path(path: &Path, cache_dir: &Path) -> PathBuf {
+        let absolute = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+        let hash = render::fnv_hash(absolute.as_os_str().as_encoded_bytes().iter().copied());
+
+        let name = path
+            .file_name()
+            .map_or("image".into(), |name| name.to_string_lossy());
+
+        cache_dir.join(format!("{name}-{hash:016x}.tiles"))
+    }
+
+    pub(super) fn width(&self) -> usize {
+        self.width
+    }
+
+    pub(super) fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Color and alpha of the texel at column `x` and row `y` of the image.
+    pub(super) fn texel(&self, x: usize, y: usize) -> (Color, f64) {
+        let tile = (y / TILE_SIZE) * self.tiles_per_row + x / TILE_SIZE;
+        let texel = tile * TILE_SIZE * TILE_SIZE + (y % TILE_SIZE) * TILE_SIZE + x % TILE_SIZE;
+        let start = HEADER_LEN + texel * TEXEL_LEN;
+
+        let channel = |i: usize| {
+            let offset = start + i * 4;
+            let bytes = [0, 1, 2, 3].map(|b| self.map[offset + b]);
+            f64::from(f32::from_le_bytes(bytes))
+        };
+
+        (
+            Color {
+                red: channel(0),
+                green: channel(1),
+                blue: channel(2),
+            },
+            channel(3),
+        )
+    }
+
+    fn is_fresh(path: &Path, cache_path: &Path) -> bool {
+        let modified = |path: &Path| fs::metadata(path).and_then(|metadata| metadata.modified());
+
+        match (modified(path), modified(cache_path)) {
+            (Ok(image), Ok(cache)) => cache >= image,
+            _ => false,
+        }
+    }
+
+    /// Writes the tiles of `image` to `cache_path`, converting one tile at a time so the whole
+    /// image is never held in memory as floating point texels.
+    ///
+    /// The cache is written to a temporary file that's renamed once it's complete, so other
+    /// renders never map a cache that's only partially written. The temporary file is removed if
+    /// the cache can't be written.
+    ///
+    fn write(image: &DynamicImage, cache_path: &Path) -> io::Result<()> {
+        let mut partial_path = cache_path.as_os_str().to_owned();
+        partial_path.push(".partial");
+        let partial_path = PathBuf::from(partial_path);
+
+        let result = Self::write_tiles(image, &partial_path)
+            .and_then(|()| fs::rename(&partial_path, cache_path));
+
+        if result.is_err() {
+            let _ = fs::remove_file(&partial_path);
+        }
+
+        result
+    }
+
+    fn write_tiles(image: &DynamicImage, partial_path: &Path) -> io::Result<()> {
+        let (width, height) = (image.width() as usize, image.height() as usize);
+
+        let mut file = BufWriter::new(File::create(partial_path)?);
+
+        file.write_all(MAGIC)?;
+        for value in [width, height, TILE_SIZE] {
+            file.write_all(&(value as u32).to_le_bytes())?;
+        }
+
+        let empty_texel = [0; TEXEL_LEN];
+
+        for tile_y in (0..height).step_by(TILE_SIZE) {
+            for tile_x in (0..width).step_by(TILE_SIZE) {
+                let tile = image
+                    .crop_imm(
+                        tile_x as u32,
+                        tile_y as u32,
+                        TILE_SIZE as u32,
+                        TILE_SIZE as u32,
+                    )
+                    .to_rgba32f();
+
+                // Tiles on the right and bottom edges are padded to the full size of a tile, so
+                // every tile starts at the same stride.
+                for y in 0..TILE_SIZE as u32 {
+                    for x in 0..TILE_SIZE as u32 {
+                        match tile.get_pixel_checked(x, y) {
+                            Some(pixel) => {
+                                for channel in pixel.0 {
+                                    file.write_all(&channel.to_le_bytes())?;
+                                }
+                            }
+                            None => file.write_all(&empty_texel)?,
+                        }
+                    }
+                }
+            }
+        }
+
+        file.into_inner()?.sync_all()
+    }
+
+    fn map(cache_path: &Path) -> io::Result<Self> {
+        let file = File::open(cache_path)?;
+
+        // SAFETY: The cache is only ever written through a temporary file that replaces it once
+        // it's complete, so the mapped file isn't modified by the ray tracer while it's mapped.
+        // Caches truncated by other programs are caught by checking their length below.
+        let map = unsafe { Mmap::map(&file)? };
+
+        let invalid = || io::Error::new(io::ErrorKind::InvalidData, "invalid tile cache");
+
+        if map.len() < HEADER_LEN || &map[..MAGIC.len()] != MAGIC {
+            return Err(invalid());
+        }
+
+        let field = |i: usize| {
+            let offset = MAGIC.len() + i * 4;
+            let bytes = [0, 1, 2, 3].map(|b| map[offset + b]);
+            u32::from_le_bytes(bytes) as usize
+        };
+
+        let (width, height, tile_size) = (field(0), field(1), field(2));
+        let tiles_per_row = width.div_ceil(TILE_SIZE);
+        let tiles = tiles_per_row * height.div_ceil(TILE_SIZE);
+
+        if tile_size != TILE_SIZE
+            || map.len() != HEADER_LEN + tiles * TILE_SIZE * TILE_SIZE * TEXEL_LEN
+        {
+            return Err(invalid());
+        }
+
+        Ok(Self {
+            path: cache_path.to_path_buf(),
+            map,
+            width,
+            height,
+            tiles_per_row,
+        })
+    }
+}
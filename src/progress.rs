@@ -0,0 +1,92 @@
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::Instant;
+use std::{fmt, time::Duration};
+
+/// Progress bar of a long running task, which is only displayed when it's asked for, such as with
+/// [RenderSettings::progress](crate::render::RenderSettings::progress).
+///
+/// In the browser there's no terminal to display the progress in, so the bar is never displayed.
+///
+#[derive(Clone)]
+pub(crate) struct ProgressBar {
+    #[cfg(not(target_arch = "wasm32"))]
+    bar: indicatif::ProgressBar,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl ProgressBar {
+    /// Constructs a bar for a task with `len` steps, which is hidden unless `displayed` is set.
+    pub(crate) fn new(len: u64, displayed: bool) -> Self {
+        let bar = if displayed {
+            indicatif::ProgressBar::new(len)
+        } else {
+            indicatif::ProgressBar::hidden()
+        };
+
+        Self { bar }
+    }
+
+    /// Advances the bar by `delta` steps.
+    pub(crate) fn inc(&self, delta: u64) {
+        self.bar.inc(delta);
+    }
+
+    /// Marks the task as done.
+    pub(crate) fn finish(&self) {
+        self.bar.finish();
+    }
+}
+
+/// Measures how long a task takes, for the logs of the task.
+///
+/// In the browser the standard library has no clock, so nothing is measured.
+///
+pub(crate) struct Stopwatch {
+    #[cfg(not(target_arch = "wasm32"))]
+    start: Instant,
+}
+
+impl Stopwatch {
+    /// Starts measuring.
+    pub(crate) fn start() -> Self {
+        Self {
+            #[cfg(not(target_arch = "wasm32"))]
+            start: Instant::now(),
+        }
+    }
+
+    /// Time since the stopwatch started.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) fn elapsed(&self) -> Duration {
+        self.start.elapsed()
+    }
+
+    /// Time since the stopwatch started, which is always zero in the browser.
+    #[cfg(target_arch = "wasm32")]
+    pub(crate) fn elapsed(&self) -> Duration {
+        Duration::ZERO
+    }
+}
+
+impl fmt::Display for Stopwatch {
+    #[cfg(not(target_arch = "wasm32"))]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:.2?}", self.start.elapsed())
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("an unknown time")
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+impl ProgressBar {
+    pub(crate) fn new(_len: u64, _displayed: bool) -> Self {
+        Self {}
+    }
+
+    pub(crate) fn inc(&self, _delta: u64) {}
+
+    pub(crate) fn finish(&self) {}
+}
@@ -3,17 +3,27 @@ use crate::{
     tuple::{Point, Vector},
 };
 
-#[derive(Debug, PartialEq)]
+/// Half-line that starts at an origin point and extends infinitely in a given direction.
+///
+/// Rays for a given pixel of an image can be generated with
+/// [Camera::ray_for_pixel](crate::camera::Camera::ray_for_pixel).
+///
+#[derive(Copy, Clone, Debug, PartialEq)]
 pub struct Ray {
+    /// Starting point of the ray.
     pub origin: Point,
+
+    /// Direction in which the ray travels.
     pub direction: Vector,
 }
 
 impl Ray {
+    /// Computes the point at the distance `t` along the ray.
     pub fn position(&self, t: f64) -> Point {
         self.origin + self.direction * t
     }
 
+    /// Applies a transformation to both the origin and the direction of the ray.
     pub fn transform(&self, transform: Transform) -> Self {
         let origin = transform * self.origin;
         let direction = transform * self.direction;
@@ -0,0 +1,732 @@
+use std::{
+    cell::Cell,
+    cmp::Ordering,
+    ops::{Add, AddAssign},
+    path::PathBuf,
+    sync::Arc,
+};
+
+#[cfg(not(target_arch = "wasm32"))]
+use rayon::ThreadPoolBuilder;
+use rayon::{ScopeFifo, ThreadPoolBuildError};
+use thiserror::Error;
+
+#[cfg(feature = "tokio")]
+use crate::color::Color;
+use crate::{
+    config::Config,
+    integrator::{Integrator, Whitted},
+    sampling::Sampler,
+    tuple::Point,
+};
+
+mod accumulation;
+mod encoder;
+mod farm;
+mod metadata;
+mod queue;
+mod ray_paths;
+mod reprojection;
+mod sweep;
+mod tile_times;
+mod upsampling;
+
+pub use self::{
+    accumulation::AccumulationBuffer,
+    encoder::{register_encoder, unregister_encoder, CanvasEncoder},
+    farm::{FarmReport, JobManifest, ManifestIntegrator, RenderFarm},
+    metadata::RenderMetadata,
+    queue::{QueueProgress, RenderJob, RenderQueue},
+    ray_paths::{RayPath, RayPaths, RaySegment},
+    reprojection::ReprojectionCache,
+    sweep::ParameterSweep,
+    tile_times::TileTimes,
+};
+
+pub(crate) use self::metadata::fnv_hash;
+pub(crate) use self::{
+    ray_paths::{record_path, record_segment},
+    reprojection::ReprojectedPixel,
+    upsampling::{HalfResolutionBuffer, SurfaceSample},
+};
+pub use crate::canvas::{Canvas, ColorRange};
+
+/// Default width and height of the tiles in which an image is divided when rendering.
+pub const DEFAULT_TILE_SIZE: usize = 16;
+
+/// Exposure offsets, in stops, of a bracket that saves an image two stops darker, as it is, and
+/// two stops brighter.
+pub const DEFAULT_EXPOSURE_BRACKET: [f64; 3] = [-2.0, 0.0, 2.0];
+
+/// The error type when a render job fails.
+#[derive(Debug, Error)]
+pub enum Error {
+    /// The thread-pool that renders the image could not be built.
+    #[error("failed to build the thread-pool of the render")]
+    ThreadPool(#[from] ThreadPoolBuildError),
+
+    /// Rendering the image panicked, with the message of the panic.
+    #[error("the render panicked: {0}")]
+    Panicked(String),
+
+    /// The rendered image could not be saved.
+    #[error("failed to save the image to {path}")]
+    Save {
+        /// Path where the image was being saved.
+        path: PathBuf,
+
+        /// Reason why the image could not be saved.
+        #[source]
+        source: image::ImageError,
+    },
+
+    /// A file of a [RenderFarm] could not be read or written.
+    #[error("failed to access {path}")]
+    Io {
+        /// Path of the file.
+        path: PathBuf,
+
+        /// Reason why the file could not be accessed.
+        #[source]
+        source: std::io::Error,
+    },
+
+    /// A job manifest of a [RenderFarm] is not valid.
+    #[error("invalid job manifest {path}")]
+    Manifest {
+        /// Path of the manifest.
+        path: PathBuf,
+
+        /// Reason why the manifest is not valid.
+        #[source]
+        source: serde_json::Error,
+    },
+
+    /// The scene of a job of a [RenderFarm] is not valid.
+    #[error("invalid scene {path}")]
+    Scene {
+        /// Path of the scene.
+        path: PathBuf,
+
+        /// Reason why the scene is not valid.
+        #[source]
+        source: crate::scene::Error,
+    },
+
+    /// The [metadata](RenderMetadata) of an image could not be read.
+    #[error("failed to read the metadata of {path}")]
+    Metadata {
+        /// Path of the image.
+        path: PathBuf,
+
+        /// Reason why the metadata could not be read.
+        #[source]
+        source: image::ImageError,
+    },
+}
+
+/// Settings for the rendering process of a [Camera](crate::camera::Camera).
+///
+/// # Examples
+///
+/// Rendering with the tiles closer to the center of the image first.
+///
+/// ```
+/// use raytracer::{
+///     camera::{Camera, CameraBuilder},
+///     render::{RenderSettings, TileOrder},
+///     world::World,
+/// };
+///
+/// let camera = Camera::try_from(CameraBuilder {
+///     width: 64,
+///     height: 64,
+///     field_of_view: std::f64::consts::FRAC_PI_3,
+///     transform: Default::default(),
+/// }).unwrap();
+///
+/// let settings = RenderSettings {
+///     tile_order: TileOrder::Spiral,
+///     ..Default::default()
+/// };
+///
+/// let image = camera.render_with_settings(&World::default(), &settings);
+/// ```
+///
+#[derive(Clone, Debug)]
+pub struct RenderSettings {
+    /// Number of threads used to render the image.
+    ///
+    /// By default this value is `8`, or the number of [threads](Config::threads) of the
+    /// configuration the settings are [built from](RenderSettings::from_config).
+    ///
+    pub threads: usize,
+
+    /// Width and height of the tiles in which the image is divided. Tiles on the right and bottom
+    /// edges of the image are cropped to fit it. A tile size of `0` is treated as `1`.
+    pub tile_size: usize,
+
+    /// Order in which tiles are rendered.
+    pub tile_order: TileOrder,
+
+    /// Lighting algorithm used to compute the color of each pixel, which is [Whitted] ray
+    /// tracing by default.
+    pub integrator: Arc<dyn Integrator>,
+
+    /// Sampler used to spread the samples of each pixel over it's area when rendering multiple
+    /// samples per pixel.
+    pub sampler: Sampler,
+
+    /// Whether the [indirect color](Integrator::indirect_color_at) of the image is computed at
+    /// half of it's resolution, and then upsampled guided by the depth and normal of the surface
+    /// seen by each pixel.
+    ///
+    /// This roughly divides by four the cost of expensive effects like ambient occlusion or light
+    /// bounced off diffuse surfaces, at the cost of blurring them. It only applies to images
+    /// rendered with a single sample per pixel.
+    ///
+    pub half_resolution_indirect: bool,
+
+    /// Whether a progress bar with the number of pixels that are done is displayed in the
+    /// terminal while the image is rendered. By default this value is `false`, or the
+    /// [progress](Config::progress) of the configuration the settings are
+    /// [built from](RenderSettings::from_config).
+    pub progress: bool,
+}
+
+/// Tile of an image alongside the colors of it's pixels, as streamed by
+/// [Camera::render_async](crate::camera::Camera::render_async).
+#[cfg(feature = "tokio")]
+#[derive(Clone, Debug, PartialEq)]
+pub struct RenderedTile {
+    /// Region of the image covered by the tile.
+    pub tile: Tile,
+
+    /// Colors of the pixels of the tile, row by row.
+    pub pixels: Vec<Color>,
+}
+
+/// Settings for [adaptive sampling](crate::camera::Camera::render_adaptive), which takes more
+/// samples for the noisiest pixels of an image.
+///
+/// Every pixel first takes [min_samples](AdaptiveSampling::min_samples) samples. After that,
+/// pixels whose [noise](AccumulationBuffer::noise) is above the
+/// [noise_threshold](AdaptiveSampling::noise_threshold) take
+/// [samples_per_pass](AdaptiveSampling::samples_per_pass) more samples on each pass, until they're
+/// below the threshold or they reach [max_samples](AdaptiveSampling::max_samples).
+///
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct AdaptiveSampling {
+    /// Number of samples taken for every pixel before estimating it's noise. Values lower than
+    /// `2` are treated as `2`, as noise can't be estimated with less samples.
+    pub min_samples: u32,
+
+    /// Maximum number of samples taken for a single pixel.
+    pub max_samples: u32,
+
+    /// Number of samples added to the noisy pixels on each pass. A value of `0` is treated as
+    /// `1`.
+    pub samples_per_pass: u32,
+
+    /// Noise below which a pixel stops taking samples.
+    pub noise_threshold: f64,
+}
+
+/// Order in which the tiles of an image are rendered.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum TileOrder {
+    /// Row by row, starting from the top-left corner of the image.
+    #[default]
+    Scanline,
+
+    /// Spiraling outwards from the center of the image, so the usually more interesting region of
+    /// the image is available sooner.
+    Spiral,
+
+    /// Following a [Hilbert curve](https://en.wikipedia.org/wiki/Hilbert_curve), which keeps
+    /// consecutive tiles close to each other.
+    Hilbert,
+}
+
+/// Statistics about the rays casted while rendering an image.
+///
+/// Statistics are gathered by [Camera::render_with_stats](crate::camera::Camera::render_with_stats).
+///
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct RenderStats {
+    /// Number of rays casted from the camera.
+    pub primary_rays: u64,
+
+    /// Number of rays casted towards light sources to check if a point is in shadow.
+    pub shadow_rays: u64,
+
+    /// Number of rays casted from reflective surfaces.
+    pub reflection_rays: u64,
+
+    /// Number of rays casted through transparent surfaces.
+    pub refraction_rays: u64,
+
+    /// Number of rays casted out of portals.
+    pub portal_rays: u64,
+
+    /// Number of ray-shape intersection tests, including the ones against groups.
+    pub intersection_tests: u64,
+}
+
+/// Rectangular region of an image.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Tile {
+    /// Column of the top-left pixel of the tile.
+    pub x: usize,
+
+    /// Row of the top-left pixel of the tile.
+    pub y: usize,
+
+    /// Width of the tile in number of pixels.
+    pub width: usize,
+
+    /// Height of the tile in number of pixels.
+    pub height: usize,
+}
+
+impl Default for RenderSettings {
+    fn default() -> Self {
+        Self {
+            threads: Config::DEFAULT.threads,
+            tile_size: DEFAULT_TILE_SIZE,
+            tile_order: Default::default(),
+            integrator: Arc::new(Whitted::default()),
+            sampler: Default::default(),
+            half_resolution_indirect: false,
+            progress: false,
+        }
+    }
+}
+
+impl RenderSettings {
+    /// Default settings with the number of threads, the recursion depth of [Whitted] ray tracing
+    /// and whether the progress is displayed given by a configuration.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use raytracer::{config::Config, render::RenderSettings};
+    ///
+    /// let config = Config {
+    ///     threads: 2,
+    ///     ..Config::DEFAULT
+    /// };
+    ///
+    /// assert_eq!(RenderSettings::from_config(&config).threads, 2);
+    /// ```
+    ///
+    pub fn from_config(config: &Config) -> Self {
+        Self {
+            threads: config.threads,
+            progress: config.progress,
+            integrator: Arc::new(Whitted {
+                recursion_depth: config.recursion_depth,
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+}
+
+impl Default for AdaptiveSampling {
+    fn default() -> Self {
+        Self {
+            min_samples: 4,
+            max_samples: 64,
+            samples_per_pass: 4,
+            noise_threshold: 0.01,
+        }
+    }
+}
+
+impl RenderStats {
+    const EMPTY: Self = Self {
+        primary_rays: 0,
+        shadow_rays: 0,
+        reflection_rays: 0,
+        refraction_rays: 0,
+        portal_rays: 0,
+        intersection_tests: 0,
+    };
+
+    /// Total number of rays casted.
+    pub fn total_rays(&self) -> u64 {
+        self.primary_rays
+            + self.shadow_rays
+            + self.reflection_rays
+            + self.refraction_rays
+            + self.portal_rays
+    }
+}
+
+impl Add for RenderStats {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        Self {
+            primary_rays: self.primary_rays + rhs.primary_rays,
+            shadow_rays: self.shadow_rays + rhs.shadow_rays,
+            reflection_rays: self.reflection_rays + rhs.reflection_rays,
+            refraction_rays: self.refraction_rays + rhs.refraction_rays,
+            portal_rays: self.portal_rays + rhs.portal_rays,
+            intersection_tests: self.intersection_tests + rhs.intersection_tests,
+        }
+    }
+}
+
+impl AddAssign for RenderStats {
+    fn add_assign(&mut self, rhs: Self) {
+        *self = *self + rhs;
+    }
+}
+
+thread_local! {
+    // Each thread keeps it's own counters so recording statistics in the hot paths doesn't need
+    // any synchronization. They're merged into the render totals once a tile is done.
+    static THREAD_STATS: Cell<RenderStats> = const { Cell::new(RenderStats::EMPTY) };
+
+    // Position of the camera rendering the current tile, in the space of the shapes being
+    // intersected, so every ray of a pixel picks the same detail levels as the camera does.
+    static VIEWPOINT: Cell<Option<Point>> = const { Cell::new(None) };
+}
+
+/// Restores the viewpoint that was current before it was created once it's dropped, so the
+/// viewpoint is restored even if the code that changed it panics.
+#[must_use]
+pub(crate) struct ViewpointGuard {
+    previous: Option<Point>,
+}
+
+/// Runs `op` in a thread-pool with the given number of threads, which spawns the tiles of a render
+/// in FIFO order.
+///
+/// # Errors
+///
+/// Fails if [ThreadPoolBuilder::build](https://docs.rs/rayon/latest/rayon/struct.ThreadPoolBuilder.html#method.build) fails.
+///
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn scope_fifo<'scope, OP, R>(threads: usize, op: OP) -> Result<R, ThreadPoolBuildError>
+where
+    OP: FnOnce(&ScopeFifo<'scope>) -> R + Send,
+    R: Send,
+{
+    Ok(ThreadPoolBuilder::new()
+        .num_threads(threads)
+        .build()?
+        .scope_fifo(op))
+}
+
+/// Runs `op` in the global thread-pool.
+///
+/// Browsers can only run code in parallel in web workers, so the number of threads is given by
+/// how the global pool was set up, e.g. with
+/// [wasm-bindgen-rayon](https://docs.rs/wasm-bindgen-rayon). When it wasn't set up, everything
+/// runs in the calling thread.
+///
+#[cfg(target_arch = "wasm32")]
+pub(crate) fn scope_fifo<'scope, OP, R>(_threads: usize, op: OP) -> Result<R, ThreadPoolBuildError>
+where
+    OP: FnOnce(&ScopeFifo<'scope>) -> R + Send,
+    R: Send,
+{
+    Ok(rayon::scope_fifo(op))
+}
+
+/// Updates the statistics of the current thread.
+pub(crate) fn record<F>(update: F)
+where
+    F: FnOnce(&mut RenderStats),
+{
+    THREAD_STATS.with(|stats| {
+        let mut current = stats.get();
+        update(&mut current);
+        stats.set(current);
+    });
+}
+
+/// Position of the camera that the current thread is rendering from, in the space of the shapes
+/// being intersected, or `None` outside of a render.
+pub(crate) fn viewpoint() -> Option<Point> {
+    VIEWPOINT.with(Cell::get)
+}
+
+/// Makes `viewpoint` the position of the camera for the current thread until the returned guard is
+/// dropped.
+pub(crate) fn set_viewpoint(viewpoint: Point) -> ViewpointGuard {
+    ViewpointGuard {
+        previous: VIEWPOINT.replace(Some(viewpoint)),
+    }
+}
+
+impl Drop for ViewpointGuard {
+    fn drop(&mut self) {
+        VIEWPOINT.set(self.previous);
+    }
+}
+
+/// Returns the statistics of the current thread, resetting them.
+pub(crate) fn take_thread_stats() -> RenderStats {
+    THREAD_STATS.with(Cell::take)
+}
+
+impl Tile {
+    /// Returns an iterator over the coordinates of every pixel in the tile, row by row.
+    pub fn pixels(&self) -> impl Iterator<Item = (usize, usize)> {
+        let Tile {
+            x,
+            y,
+            width,
+            height,
+        } = *self;
+
+        (y..y + height).flat_map(move |y| (x..x + width).map(move |x| (x, y)))
+    }
+}
+
+impl TileOrder {
+    /// Divides an image of `width` by `height` pixels in tiles of `tile_size` pixels, and sorts
+    /// them in this order.
+    pub fn tiles(self, width: usize, height: usize, tile_size: usize) -> Vec<Tile> {
+        let tile_size = tile_size.max(1);
+
+        let columns = width.div_ceil(tile_size);
+        let rows = height.div_ceil(tile_size);
+
+        let mut grid: Vec<_> = (0..rows)
+            .flat_map(|row| (0..columns).map(move |column| (column, row)))
+            .collect();
+
+        match self {
+            Self::Scanline => (),
+            Self::Spiral => {
+                let center = ((columns as f64 - 1.0) / 2.0, (rows as f64 - 1.0) / 2.0);
+
+                grid.sort_by(|a, b| {
+                    let a = spiral_key(*a, center);
+                    let b = spiral_key(*b, center);
+                    a.partial_cmp(&b).unwrap_or(Ordering::Equal)
+                });
+            }
+            Self::Hilbert => {
+                let side = columns.max(rows).next_power_of_two();
+                grid.sort_by_key(|&(column, row)| hilbert_index(side, column, row));
+            }
+        }
+
+        grid.into_iter()
+            .map(|(column, row)| {
+                let x = column * tile_size;
+                let y = row * tile_size;
+
+                Tile {
+                    x,
+                    y,
+                    width: tile_size.min(width - x),
+                    height: tile_size.min(height - y),
+                }
+            })
+            .collect()
+    }
+}
+
+/// Sorting key of a tile in a spiral: first by the ring around the center the tile belongs to,
+/// and then by its angle around the center.
+fn spiral_key((column, row): (usize, usize), center: (f64, f64)) -> (f64, f64) {
+    let dx = column as f64 - center.0;
+    let dy = row as f64 - center.1;
+
+    let ring = dx.abs().max(dy.abs()).round();
+    let angle = dy.atan2(dx);
+
+    (ring, angle)
+}
+
+/// Position of the cell `(x, y)` along a Hilbert curve that fills a grid of `side` by `side`
+/// cells, where `side` is a power of two.
+fn hilbert_index(side: usize, mut x: usize, mut y: usize) -> usize {
+    let mut index = 0;
+    let mut s = side / 2;
+
+    while s > 0 {
+        let rx = usize::from(x & s > 0);
+        let ry = usize::from(y & s > 0);
+
+        index += s * s * ((3 * rx) ^ ry);
+
+        // Rotate the quadrant so the curve inside of it has the right orientation.
+        if ry == 0 {
+            if rx == 1 {
+                x = side - 1 - x;
+                y = side - 1 - y;
+            }
+
+            std::mem::swap(&mut x, &mut y);
+        }
+
+        s /= 2;
+    }
+
+    index
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn origins(tiles: &[Tile]) -> Vec<(usize, usize)> {
+        tiles.iter().map(|tile| (tile.x, tile.y)).collect()
+    }
+
+    #[test]
+    fn the_default_tile_order_is_scanline() {
+        assert_eq!(RenderSettings::default().tile_order, TileOrder::Scanline);
+    }
+
+    #[test]
+    fn tiles_on_the_edges_are_cropped_to_fit_the_image() {
+        let tiles = TileOrder::Scanline.tiles(10, 5, 4);
+
+        assert_eq!(tiles.len(), 6);
+        assert_eq!(
+            tiles[2],
+            Tile {
+                x: 8,
+                y: 0,
+                width: 2,
+                height: 4
+            }
+        );
+        assert_eq!(
+            tiles[5],
+            Tile {
+                x: 8,
+                y: 4,
+                width: 2,
+                height: 1
+            }
+        );
+    }
+
+    #[test]
+    fn the_tiles_cover_every_pixel_exactly_once() {
+        for order in [TileOrder::Scanline, TileOrder::Spiral, TileOrder::Hilbert] {
+            let mut pixels: Vec<_> = order
+                .tiles(13, 7, 3)
+                .iter()
+                .flat_map(|tile| tile.pixels())
+                .collect();
+
+            pixels.sort();
+            pixels.dedup();
+
+            assert_eq!(pixels.len(), 13 * 7);
+        }
+    }
+
+    #[test]
+    fn a_null_tile_size_is_treated_as_a_single_pixel() {
+        let tiles = TileOrder::Scanline.tiles(2, 2, 0);
+
+        assert_eq!(tiles.len(), 4);
+    }
+
+    #[test]
+    fn scanline_tiles_are_sorted_row_by_row() {
+        let tiles = TileOrder::Scanline.tiles(4, 4, 2);
+
+        assert_eq!(origins(&tiles), vec![(0, 0), (2, 0), (0, 2), (2, 2)]);
+    }
+
+    #[test]
+    fn spiral_tiles_start_from_the_center() {
+        let tiles = TileOrder::Spiral.tiles(5, 5, 1);
+
+        assert_eq!((tiles[0].x, tiles[0].y), (2, 2));
+
+        // The ring of tiles that surrounds the center is rendered before the outer ring.
+        for tile in &tiles[1..9] {
+            assert!(tile.x.abs_diff(2) <= 1 && tile.y.abs_diff(2) <= 1);
+        }
+    }
+
+    #[test]
+    fn hilbert_tiles_are_always_adjacent() {
+        let tiles = TileOrder::Hilbert.tiles(8, 8, 1);
+
+        assert_eq!((tiles[0].x, tiles[0].y), (0, 0));
+
+        for pair in tiles.windows(2) {
+            let distance = pair[0].x.abs_diff(pair[1].x) + pair[0].y.abs_diff(pair[1].y);
+            assert_eq!(distance, 1);
+        }
+    }
+
+    #[test]
+    fn iterating_over_the_pixels_of_a_tile() {
+        let tile = Tile {
+            x: 3,
+            y: 1,
+            width: 2,
+            height: 2,
+        };
+
+        let pixels: Vec<_> = tile.pixels().collect();
+
+        assert_eq!(pixels, vec![(3, 1), (4, 1), (3, 2), (4, 2)]);
+    }
+
+    #[test]
+    fn adding_render_stats() {
+        let a = RenderStats {
+            primary_rays: 1,
+            shadow_rays: 2,
+            reflection_rays: 3,
+            refraction_rays: 4,
+            portal_rays: 5,
+            intersection_tests: 6,
+        };
+
+        let mut b = a + a;
+        b += a;
+
+        assert_eq!(
+            b,
+            RenderStats {
+                primary_rays: 3,
+                shadow_rays: 6,
+                reflection_rays: 9,
+                refraction_rays: 12,
+                portal_rays: 15,
+                intersection_tests: 18,
+            }
+        );
+        assert_eq!(b.total_rays(), 45);
+    }
+
+    #[test]
+    fn thread_stats_are_reset_when_taken() {
+        take_thread_stats();
+
+        record(|stats| stats.shadow_rays += 2);
+        record(|stats| stats.shadow_rays += 1);
+
+        assert_eq!(take_thread_stats().shadow_rays, 3);
+        assert_eq!(take_thread_stats(), RenderStats::default());
+    }
+
+    #[test]
+    fn thread_stats_are_not_shared_between_threads() {
+        take_thread_stats();
+        record(|stats| stats.primary_rays += 1);
+
+        let other = std::thread::spawn(take_thread_stats).join().unwrap();
+
+        assert_eq!(other, RenderStats::default());
+        assert_eq!(take_thread_stats().primary_rays, 1);
+    }
+}
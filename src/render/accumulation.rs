@@ -0,0 +1,461 @@
+use std::io::{self, Read, Write};
+
+use crate::{
+    canvas::Canvas,
+    color::{self, Color},
+};
+
+/// Buffer that accumulates the samples of an image as they're rendered.
+///
+/// Unlike a canvas, which stores a single final color for each pixel, the buffer keeps the sum
+/// and the number of the samples taken for each pixel. This makes it possible to refine an image
+/// progressively over multiple render passes, to take a different number of samples for each
+/// pixel, and to average all of them correctly in the end.
+///
+/// The buffer also tracks how much the brightness of the samples of each pixel varies, which is
+/// used to estimate how noisy the pixel still is.
+///
+/// # Examples
+///
+/// ```
+/// use raytracer::{
+///     camera::{Camera, CameraBuilder},
+///     render::{AccumulationBuffer, RenderSettings},
+///     world::World,
+/// };
+///
+/// let camera = Camera::try_from(CameraBuilder {
+///     width: 16,
+///     height: 16,
+///     field_of_view: std::f64::consts::FRAC_PI_3,
+///     transform: Default::default(),
+/// }).unwrap();
+///
+/// let world = World::default();
+/// let mut buffer = AccumulationBuffer::new(camera.width(), camera.height());
+///
+/// for _ in 0..4 {
+///     camera.render_samples(&world, &RenderSettings::default(), &mut buffer, 1);
+/// }
+///
+/// assert_eq!(buffer.sample_count(0, 0), 4);
+///
+/// let image = buffer.to_canvas();
+/// ```
+///
+#[derive(Clone, Debug, PartialEq)]
+pub struct AccumulationBuffer {
+    width: usize,
+    height: usize,
+    pixels: Vec<AccumulatedPixel>,
+}
+
+/// Bytes at the start of a saved buffer, which identify the format and it's version.
+const MAGIC: &[u8; 8] = b"RTACCUM1";
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+struct AccumulatedPixel {
+    sum: Color,
+    sum_of_squares: f64,
+    count: u32,
+}
+
+impl Default for AccumulatedPixel {
+    fn default() -> Self {
+        Self {
+            sum: color::consts::BLACK,
+            sum_of_squares: 0.0,
+            count: 0,
+        }
+    }
+}
+
+impl AccumulationBuffer {
+    /// Constructs an empty buffer for an image with the given dimensions.
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            pixels: vec![Default::default(); width * height],
+        }
+    }
+
+    /// Width of the image in number of pixels.
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Height of the image in number of pixels.
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Adds a sample to the pixel at column `x` and row `y`.
+    ///
+    /// # Panics
+    ///
+    /// If the pixel is outside of the image bounds.
+    ///
+    pub fn add_sample(&mut self, x: usize, y: usize, color: Color) {
+        let index = self.index(x, y);
+        let pixel = &mut self.pixels[index];
+
+        pixel.sum = pixel.sum + color;
+        pixel.sum_of_squares += brightness(color).powi(2);
+        pixel.count += 1;
+    }
+
+    /// Number of samples taken for the pixel at column `x` and row `y`.
+    ///
+    /// # Panics
+    ///
+    /// If the pixel is outside of the image bounds.
+    ///
+    pub fn sample_count(&self, x: usize, y: usize) -> u32 {
+        self.pixels[self.index(x, y)].count
+    }
+
+    /// Average color of the samples taken for the pixel at column `x` and row `y`, which is black
+    /// if no sample has been taken yet.
+    ///
+    /// # Panics
+    ///
+    /// If the pixel is outside of the image bounds.
+    ///
+    pub fn mean(&self, x: usize, y: usize) -> Color {
+        let pixel = &self.pixels[self.index(x, y)];
+
+        if pixel.count == 0 {
+            color::consts::BLACK
+        } else {
+            pixel.sum * (1.0 / pixel.count as f64)
+        }
+    }
+
+    /// Estimated noise of the pixel at column `x` and row `y`, as the standard error of the mean
+    /// brightness of it's samples.
+    ///
+    /// The estimate gets smaller as more samples are taken, and it's infinite when less than two
+    /// samples have been taken, as there is nothing to compare them against.
+    ///
+    /// # Panics
+    ///
+    /// If the pixel is outside of the image bounds.
+    ///
+    pub fn noise(&self, x: usize, y: usize) -> f64 {
+        let pixel = &self.pixels[self.index(x, y)];
+
+        if pixel.count < 2 {
+            return f64::INFINITY;
+        }
+
+        let count = pixel.count as f64;
+        let sum = brightness(pixel.sum);
+
+        // Sample variance, clamped as rounding errors could make it slightly negative.
+        let variance = ((pixel.sum_of_squares - sum * sum / count) / (count - 1.0)).max(0.0);
+
+        (variance / count).sqrt()
+    }
+
+    /// Adds the samples of another buffer, such as one rendered in a separate pass, to this one.
+    ///
+    /// # Panics
+    ///
+    /// If the dimensions of both buffers are different.
+    ///
+    pub fn merge(&mut self, other: &Self) {
+        assert_eq!(
+            (self.width, self.height),
+            (other.width, other.height),
+            "merged buffers must have the same dimensions"
+        );
+
+        for (pixel, other) in self.pixels.iter_mut().zip(&other.pixels) {
+            pixel.sum = pixel.sum + other.sum;
+            pixel.sum_of_squares += other.sum_of_squares;
+            pixel.count += other.count;
+        }
+    }
+
+    /// Resolves the buffer into an image with the average color of each pixel.
+    pub fn to_canvas(&self) -> Canvas {
+        let mut canvas = Canvas::new(self.width, self.height);
+
+        for y in 0..self.height {
+            for x in 0..self.width {
+                canvas.write_pixel(x, y, self.mean(x, y));
+            }
+        }
+
+        canvas
+    }
+
+    /// Writes the buffer in a compact binary format, so a render can be stopped and resumed
+    /// later by reading it back with [AccumulationBuffer::read_from].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use raytracer::{color, render::AccumulationBuffer};
+    ///
+    /// let mut buffer = AccumulationBuffer::new(2, 2);
+    /// buffer.add_sample(1, 1, color::consts::RED);
+    ///
+    /// let mut bytes = vec![];
+    /// buffer.write_to(&mut bytes).unwrap();
+    ///
+    /// assert_eq!(AccumulationBuffer::read_from(bytes.as_slice()).unwrap(), buffer);
+    /// ```
+    ///
+    pub fn write_to<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writer.write_all(MAGIC)?;
+        writer.write_all(&(self.width as u64).to_le_bytes())?;
+        writer.write_all(&(self.height as u64).to_le_bytes())?;
+
+        for pixel in &self.pixels {
+            let Color { red, green, blue } = pixel.sum;
+
+            for value in [red, green, blue, pixel.sum_of_squares] {
+                writer.write_all(&value.to_le_bytes())?;
+            }
+
+            writer.write_all(&pixel.count.to_le_bytes())?;
+        }
+
+        writer.flush()
+    }
+
+    /// Reads a buffer written by [AccumulationBuffer::write_to].
+    ///
+    /// # Errors
+    ///
+    /// Fails if the reader fails, or with [InvalidData](io::ErrorKind::InvalidData) if the data is
+    /// not a buffer written by this version of the renderer.
+    ///
+    pub fn read_from<R: Read>(mut reader: R) -> io::Result<Self> {
+        let invalid = |message| io::Error::new(io::ErrorKind::InvalidData, message);
+
+        let mut magic = [0; 8];
+        reader.read_exact(&mut magic)?;
+
+        if &magic != MAGIC {
+            return Err(invalid("not an accumulation buffer"));
+        }
+
+        let mut read_u64 = || -> io::Result<u64> {
+            let mut bytes = [0; 8];
+            reader.read_exact(&mut bytes)?;
+            Ok(u64::from_le_bytes(bytes))
+        };
+
+        let width = usize::try_from(read_u64()?).map_err(|_| invalid("width is too large"))?;
+        let height = usize::try_from(read_u64()?).map_err(|_| invalid("height is too large"))?;
+        let len = width
+            .checked_mul(height)
+            .ok_or_else(|| invalid("dimensions are too large"))?;
+
+        let mut pixels = Vec::new();
+
+        for _ in 0..len {
+            let mut values = [0.0; 4];
+
+            for value in &mut values {
+                let mut bytes = [0; 8];
+                reader.read_exact(&mut bytes)?;
+                *value = f64::from_le_bytes(bytes);
+            }
+
+            let mut count = [0; 4];
+            reader.read_exact(&mut count)?;
+
+            let [red, green, blue, sum_of_squares] = values;
+
+            pixels.push(AccumulatedPixel {
+                sum: Color { red, green, blue },
+                sum_of_squares,
+                count: u32::from_le_bytes(count),
+            });
+        }
+
+        Ok(Self {
+            width,
+            height,
+            pixels,
+        })
+    }
+
+    fn index(&self, x: usize, y: usize) -> usize {
+        assert!(
+            x < self.width && y < self.height,
+            "pixel ({x}, {y}) is outside of the image bounds"
+        );
+
+        y * self.width + x
+    }
+}
+
+fn brightness(color: Color) -> f64 {
+    (color.red + color.green + color.blue) / 3.0
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::assert_approx;
+
+    use super::*;
+
+    #[test]
+    fn creating_an_accumulation_buffer() {
+        let buffer = AccumulationBuffer::new(4, 3);
+
+        assert_eq!(buffer.width(), 4);
+        assert_eq!(buffer.height(), 3);
+
+        for y in 0..3 {
+            for x in 0..4 {
+                assert_eq!(buffer.sample_count(x, y), 0);
+                assert_eq!(buffer.mean(x, y), color::consts::BLACK);
+            }
+        }
+    }
+
+    #[test]
+    fn averaging_the_samples_of_a_pixel() {
+        let mut buffer = AccumulationBuffer::new(4, 3);
+
+        buffer.add_sample(1, 2, color::consts::WHITE);
+        buffer.add_sample(1, 2, color::consts::BLACK);
+        buffer.add_sample(1, 2, color::consts::WHITE);
+        buffer.add_sample(1, 2, color::consts::WHITE);
+
+        assert_eq!(buffer.sample_count(1, 2), 4);
+        assert_eq!(
+            buffer.mean(1, 2),
+            Color {
+                red: 0.75,
+                green: 0.75,
+                blue: 0.75
+            }
+        );
+        assert_eq!(buffer.sample_count(2, 1), 0);
+    }
+
+    #[test]
+    fn merging_buffers_averages_across_passes() {
+        let mut first = AccumulationBuffer::new(2, 2);
+        first.add_sample(0, 0, color::consts::WHITE);
+
+        let mut second = AccumulationBuffer::new(2, 2);
+        second.add_sample(0, 0, color::consts::BLACK);
+        second.add_sample(0, 0, color::consts::BLACK);
+        second.add_sample(0, 0, color::consts::BLACK);
+
+        first.merge(&second);
+
+        assert_eq!(first.sample_count(0, 0), 4);
+        assert_eq!(
+            first.mean(0, 0),
+            Color {
+                red: 0.25,
+                green: 0.25,
+                blue: 0.25
+            }
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "merged buffers must have the same dimensions")]
+    fn merging_buffers_with_different_dimensions() {
+        let mut first = AccumulationBuffer::new(2, 2);
+        first.merge(&AccumulationBuffer::new(3, 2));
+    }
+
+    #[test]
+    fn resolving_a_buffer_into_a_canvas() {
+        let mut buffer = AccumulationBuffer::new(2, 2);
+        buffer.add_sample(1, 0, color::consts::RED);
+        buffer.add_sample(1, 0, color::consts::BLACK);
+
+        let canvas = buffer.to_canvas();
+
+        assert_eq!(
+            canvas.pixel_at(1, 0),
+            &Color {
+                red: 0.5,
+                green: 0.0,
+                blue: 0.0
+            }
+        );
+        assert_eq!(canvas.pixel_at(0, 0), &color::consts::BLACK);
+    }
+
+    #[test]
+    fn the_noise_of_a_pixel_with_less_than_two_samples_is_infinite() {
+        let mut buffer = AccumulationBuffer::new(2, 2);
+
+        assert_eq!(buffer.noise(0, 0), f64::INFINITY);
+
+        buffer.add_sample(0, 0, color::consts::WHITE);
+
+        assert_eq!(buffer.noise(0, 0), f64::INFINITY);
+    }
+
+    #[test]
+    fn the_noise_of_a_pixel_with_identical_samples_is_zero() {
+        let mut buffer = AccumulationBuffer::new(2, 2);
+
+        for _ in 0..4 {
+            buffer.add_sample(0, 0, color::consts::RED);
+        }
+
+        assert_approx!(buffer.noise(0, 0), 0.0);
+    }
+
+    #[test]
+    fn the_noise_of_a_pixel_decreases_with_more_samples() {
+        let mut buffer = AccumulationBuffer::new(2, 2);
+
+        buffer.add_sample(0, 0, color::consts::WHITE);
+        buffer.add_sample(0, 0, color::consts::BLACK);
+
+        // The brightness of the samples is 1 and 0, so their variance is 0.5.
+        assert_approx!(buffer.noise(0, 0), 0.5);
+
+        buffer.add_sample(0, 0, color::consts::WHITE);
+        buffer.add_sample(0, 0, color::consts::BLACK);
+
+        assert!(buffer.noise(0, 0) < 0.5);
+    }
+
+    #[test]
+    fn saving_and_reading_back_a_buffer() {
+        let mut buffer = AccumulationBuffer::new(3, 2);
+
+        buffer.add_sample(0, 0, color::consts::RED);
+        buffer.add_sample(0, 0, color::consts::BLUE);
+        buffer.add_sample(2, 1, color::consts::WHITE);
+
+        let mut bytes = vec![];
+        buffer.write_to(&mut bytes).unwrap();
+
+        let read = AccumulationBuffer::read_from(bytes.as_slice()).unwrap();
+
+        assert_eq!(read, buffer);
+        assert_eq!(read.sample_count(0, 0), 2);
+        assert_approx!(read.noise(0, 0), buffer.noise(0, 0));
+    }
+
+    #[test]
+    fn reading_a_buffer_from_invalid_data() {
+        let err = AccumulationBuffer::read_from(&b"not a buffer"[..]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        let mut bytes = vec![];
+        AccumulationBuffer::new(2, 2).write_to(&mut bytes).unwrap();
+        bytes.truncate(bytes.len() - 1);
+
+        let err = AccumulationBuffer::read_from(bytes.as_slice()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    }
+}
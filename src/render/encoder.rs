@@ -0,0 +1,196 @@
+use std::{
+    collections::BTreeMap,
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+    sync::{Arc, RwLock},
+};
+
+use image::ImageError;
+
+use crate::canvas::Canvas;
+
+use super::ColorRange;
+
+/// Encoders registered with [register_encoder], by the extension of the files they write.
+static ENCODERS: RwLock<BTreeMap<String, Arc<dyn CanvasEncoder>>> = RwLock::new(BTreeMap::new());
+
+/// Writes rendered images in a file format.
+///
+/// Images are saved in the formats supported by the [image](https://docs.rs/image) crate, picked
+/// from the extension of the path they're saved to. Other formats, like proprietary ones, or dumps
+/// of the raw floating point colors of an image, are supported by
+/// [registering](register_encoder) an encoder for their extension, which every render job and
+/// render farm then uses to save their images.
+///
+/// # Examples
+///
+/// An encoder for the PPM format of the book.
+///
+/// ```
+/// use std::io::{self, Write};
+///
+/// use raytracer::render::{self, Canvas, CanvasEncoder, ColorRange};
+///
+/// struct Ppm;
+///
+/// impl CanvasEncoder for Ppm {
+///     fn encode(&self, image: &Canvas, range: ColorRange, writer: &mut dyn Write) -> io::Result<()> {
+///         let pixels = image.to_dynamic_image(range).into_rgb8();
+///
+///         writeln!(writer, "P3\n{} {}\n255", pixels.width(), pixels.height())?;
+///         for pixel in pixels.pixels() {
+///             let [red, green, blue] = pixel.0;
+///             writeln!(writer, "{red} {green} {blue}")?;
+///         }
+///
+///         Ok(())
+///     }
+/// }
+///
+/// render::register_encoder("ppm", Ppm);
+/// ```
+///
+pub trait CanvasEncoder: Send + Sync {
+    /// Writes `image` to `writer`, with it's colors fit into the given `range`.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the image can't be written to `writer`, or if the format can't store colors in
+    /// the given `range`.
+    ///
+    fn encode(&self, image: &Canvas, range: ColorRange, writer: &mut dyn Write) -> io::Result<()>;
+}
+
+/// Registers an encoder for the files with the given `extension`, which is case-insensitive and
+/// can start with a dot. Returns the encoder previously registered for the extension, if any.
+///
+/// Registered encoders take precedence over the formats supported out of the box, so they can
+/// also replace how those are written. Images saved with a registered encoder don't carry any
+/// [metadata](super::RenderMetadata).
+///
+pub fn register_encoder<E>(extension: &str, encoder: E) -> Option<Arc<dyn CanvasEncoder>>
+where
+    E: CanvasEncoder + 'static,
+{
+    ENCODERS
+        .write()
+        .unwrap_or_else(|err| err.into_inner())
+        .insert(normalize(extension), Arc::new(encoder))
+}
+
+/// Removes the encoder registered for the files with the given `extension`, returning it if there
+/// was one.
+pub fn unregister_encoder(extension: &str) -> Option<Arc<dyn CanvasEncoder>> {
+    ENCODERS
+        .write()
+        .unwrap_or_else(|err| err.into_inner())
+        .remove(&normalize(extension))
+}
+
+/// Encoder registered for the extension of `path`, if any.
+pub(super) fn encoder_for(path: &Path) -> Option<Arc<dyn CanvasEncoder>> {
+    let extension = normalize(path.extension()?.to_str()?);
+
+    ENCODERS
+        .read()
+        .unwrap_or_else(|err| err.into_inner())
+        .get(&extension)
+        .cloned()
+}
+
+/// Saves `image` to `path`, with it's colors fit into the given `range`, using the encoder
+/// registered for the extension of the path, or the [image](https://docs.rs/image) crate when
+/// there's none.
+pub(super) fn save(image: &Canvas, path: &Path, range: ColorRange) -> Result<(), ImageError> {
+    let Some(encoder) = encoder_for(path) else {
+        return image.to_dynamic_image(range).save(path);
+    };
+
+    let mut writer = BufWriter::new(File::create(path)?);
+    encoder.encode(image, range, &mut writer)?;
+    writer.flush()?;
+
+    Ok(())
+}
+
+fn normalize(extension: &str) -> String {
+    extension.trim_start_matches('.').to_ascii_lowercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use crate::color;
+
+    use super::*;
+
+    /// Writes the red channel of every pixel as a little endian float.
+    struct RedDump;
+
+    impl CanvasEncoder for RedDump {
+        fn encode(
+            &self,
+            image: &Canvas,
+            range: ColorRange,
+            writer: &mut dyn Write,
+        ) -> io::Result<()> {
+            let pixels = image.to_dynamic_image(range).into_rgb32f();
+
+            for pixel in pixels.pixels() {
+                writer.write_all(&pixel.0[0].to_le_bytes())?;
+            }
+
+            Ok(())
+        }
+    }
+
+    fn image() -> Canvas {
+        let mut image = Canvas::new(2, 1);
+        image.write_pixel(0, 0, color::consts::WHITE * 2.0);
+        image.write_pixel(1, 0, color::consts::RED * 0.5);
+        image
+    }
+
+    fn saved_path(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("raytracer-encoder-{name}"))
+    }
+
+    #[test]
+    fn saving_an_image_with_a_registered_encoder() {
+        assert!(register_encoder(".RedDump", RedDump).is_none());
+
+        let path = saved_path("image.reddump");
+        save(&image(), &path, ColorRange::Hdr).unwrap();
+
+        let bytes = fs::read(&path).unwrap();
+        assert_eq!(bytes, [2.0_f32, 0.5].map(f32::to_le_bytes).concat());
+
+        fs::remove_file(&path).unwrap();
+        assert!(unregister_encoder("reddump").is_some());
+    }
+
+    #[test]
+    fn images_are_saved_without_encoders_when_none_is_registered() {
+        let path = saved_path("image.bmp");
+
+        assert!(encoder_for(&path).is_none());
+        save(&image(), &path, ColorRange::Clamp).unwrap();
+
+        assert_eq!(
+            image::open(&path).unwrap().into_rgb8().get_pixel(0, 0).0,
+            [255, 255, 255]
+        );
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn registering_an_encoder_replaces_the_previous_one() {
+        assert!(register_encoder("dump2", RedDump).is_none());
+        assert!(register_encoder("DUMP2", RedDump).is_some());
+        assert!(unregister_encoder("dump2").is_some());
+        assert!(unregister_encoder("dump2").is_none());
+    }
+}
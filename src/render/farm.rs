@@ -0,0 +1,781 @@
+use std::{
+    fs,
+    io::{self, Read},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use serde::Deserialize;
+
+use crate::{
+    config::Config,
+    integrator::{AmbientOcclusion, Integrator, PathTracer, Spectral, Whitted},
+    scene::{Checked, Scene, SceneLimits},
+};
+
+use super::{
+    metadata, queue, AccumulationBuffer, ColorRange, Error, RenderMetadata, RenderSettings,
+    RenderStats, DEFAULT_TILE_SIZE,
+};
+
+/// Suffix of the names of the manifest files of a farm directory.
+const MANIFEST_SUFFIX: &str = ".job.json";
+
+/// Suffix of the name of the status file of a job, which is saved next to it's manifest.
+const STATUS_SUFFIX: &str = ".status.json";
+
+/// Suffix of the name of the checkpoint of a job, which is saved next to it's manifest.
+const CHECKPOINT_SUFFIX: &str = ".checkpoint";
+
+/// Default number of samples per pixel rendered between checkpoints.
+const DEFAULT_PASS_SAMPLES: usize = 4;
+
+/// Render job described by a manifest file in the directory of a [RenderFarm].
+///
+/// # Deserialization
+///
+/// Manifests are JSON files. Paths are relative to the directory of the manifest, and every
+/// field other than the `scene` and the `output` is optional.
+///
+/// ```json
+/// {
+///     "scene": "scenes/living_room.json",
+///     "output": "renders/living_room.png",
+///     "samples": 64,
+///     "threads": 4,
+///     "tile_size": 32,
+///     "integrator": "path_tracer",
+///     "exposure_bracket": [-2.0, 0.0, 2.0],
+///     "metadata": true,
+///     "color_range": "normalize_to_max"
+/// }
+/// ```
+///
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+pub struct JobManifest {
+    /// Path of the [Scene], described in JSON, to render.
+    pub scene: PathBuf,
+
+    /// Path where the image is saved. The format of the image is deduced from the extension of
+    /// the path.
+    pub output: PathBuf,
+
+    /// Number of samples rendered for each pixel. By default this value is `1`.
+    #[serde(default = "default_samples")]
+    pub samples: usize,
+
+    /// Number of threads used to render the job, which is the number of
+    /// [threads](Config::threads) of the configuration of the farm when it's not set.
+    #[serde(default)]
+    pub threads: Option<usize>,
+
+    /// Width and height of the tiles in which the image is divided. By default this value is
+    /// [DEFAULT_TILE_SIZE].
+    #[serde(default = "default_tile_size")]
+    pub tile_size: usize,
+
+    /// Lighting algorithm used to render the job.
+    #[serde(default)]
+    pub integrator: ManifestIntegrator,
+
+    /// Exposure offsets, in stops, at which the image is saved. See
+    /// [RenderJob::exposure_bracket](super::RenderJob::exposure_bracket) for details.
+    #[serde(default)]
+    pub exposure_bracket: Vec<f64>,
+
+    /// Whether the [metadata](super::RenderMetadata) of the render, including the hash of the
+    /// scene's spec, is embedded in the saved images. By default it's not embedded.
+    #[serde(default)]
+    pub metadata: bool,
+
+    /// How the colors of the image are fit into the range of values of the format it's saved in,
+    /// which is `"clamp"`, `"normalize_to_max"` or `"hdr"`. By default colors are clamped.
+    #[serde(default)]
+    pub color_range: ColorRange,
+}
+
+/// Lighting algorithms that a [JobManifest] can select, each one with it's default settings.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all(deserialize = "snake_case"))]
+pub enum ManifestIntegrator {
+    /// [Whitted] ray tracing.
+    #[default]
+    Whitted,
+
+    /// [Path tracing](PathTracer).
+    PathTracer,
+
+    /// [Ambient occlusion](AmbientOcclusion).
+    AmbientOcclusion,
+
+    /// [Spectral] ray tracing.
+    Spectral,
+}
+
+/// Outcome of a run of a [RenderFarm], with the manifests of the jobs in each outcome.
+#[derive(Debug, Default)]
+pub struct FarmReport {
+    /// Jobs that were rendered, alongside the statistics of the samples rendered by this run.
+    pub rendered: Vec<(PathBuf, RenderStats)>,
+
+    /// Jobs that were already done by a previous run.
+    pub skipped: Vec<PathBuf>,
+
+    /// Jobs that failed, alongside the reason why they failed.
+    pub failed: Vec<(PathBuf, Error)>,
+}
+
+/// Headless worker that renders every job described by the manifest files of a directory.
+///
+/// Every file of the directory whose name ends in `.job.json` is a [JobManifest], and jobs are
+/// rendered one after another, in the order of the names of their manifests. Other files, such
+/// as the scenes of the jobs, are ignored. The worker is meant to be run periodically, such as by
+/// cron or a CI pipeline, so it's safe to stop it at any time and run it again:
+///
+/// * The samples of each job are rendered in passes, and after each pass the samples rendered so
+///   far are saved to a checkpoint next to the manifest, like `room.checkpoint` for
+///   `room.job.json`. A job that was interrupted resumes from it's checkpoint, which is deleted
+///   once the job is done. A checkpoint keeps a hash of the scene spec and the manifest it was
+///   rendered from, and it's discarded if either of them changed since, so the job starts over
+///   instead of blending samples of different scenes.
+/// * The state of each job is written to a JSON status file next to the manifest, like
+///   `room.status.json` for `room.job.json`, with a `state` that is either `rendering`, `done` or
+///   `failed`, the number of `samples` rendered so far or the `error` that made the job fail.
+///   Jobs that are done are skipped, and jobs that failed are tried again.
+///
+/// Checkpoints and status files are replaced atomically, so a worker that is stopped halfway
+/// through writing them leaves the previous ones intact. A directory must only be consumed by a
+/// single worker at a time.
+///
+/// # Examples
+///
+/// ```no_run
+/// use raytracer::render::RenderFarm;
+///
+/// let report = RenderFarm::new("jobs").with_pass_samples(16).run().unwrap();
+///
+/// for (manifest, err) in &report.failed {
+///     eprintln!("{}: {err}", manifest.display());
+/// }
+/// ```
+///
+#[derive(Clone, Debug, PartialEq)]
+pub struct RenderFarm {
+    directory: PathBuf,
+    pass_samples: usize,
+    limits: SceneLimits,
+    config: Config,
+}
+
+fn default_samples() -> usize {
+    1
+}
+
+fn default_tile_size() -> usize {
+    DEFAULT_TILE_SIZE
+}
+
+impl ManifestIntegrator {
+    fn integrator(self, recursion_depth: u8) -> Arc<dyn Integrator> {
+        match self {
+            Self::Whitted => Arc::new(Whitted {
+                recursion_depth,
+                ..Default::default()
+            }),
+            Self::PathTracer => Arc::new(PathTracer {
+                max_depth: recursion_depth,
+                ..Default::default()
+            }),
+            Self::AmbientOcclusion => Arc::new(AmbientOcclusion::default()),
+            Self::Spectral => Arc::new(Spectral {
+                recursion_depth,
+                ..Default::default()
+            }),
+        }
+    }
+}
+
+impl RenderFarm {
+    /// Constructs a worker for the manifests of the given directory.
+    pub fn new<P: Into<PathBuf>>(directory: P) -> Self {
+        Self {
+            directory: directory.into(),
+            pass_samples: DEFAULT_PASS_SAMPLES,
+            limits: SceneLimits::default(),
+            config: Config::DEFAULT,
+        }
+    }
+
+    /// Sets the number of samples per pixel rendered between checkpoints, which is `4` by
+    /// default. A value of `0` is treated as `1`.
+    pub fn with_pass_samples(self, pass_samples: usize) -> Self {
+        Self {
+            pass_samples: pass_samples.max(1),
+            ..self
+        }
+    }
+
+    /// Sets the limits that the scenes of the jobs must be within, which are the
+    /// [default](SceneLimits::default) limits unless they're set. Jobs whose scenes exceed them
+    /// fail, or are rendered with a lower quality, as the limits say.
+    pub fn with_limits(self, limits: SceneLimits) -> Self {
+        Self { limits, ..self }
+    }
+
+    /// Sets the configuration whose defaults are used to render the jobs, which is the
+    /// [default](Config::DEFAULT) configuration unless it's set.
+    pub fn with_config(self, config: Config) -> Self {
+        Self { config, ..self }
+    }
+
+    /// Manifests of the directory, in the order in which their jobs are rendered.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the directory can't be read.
+    ///
+    pub fn manifests(&self) -> Result<Vec<PathBuf>, Error> {
+        let io_error = |source| Error::Io {
+            path: self.directory.clone(),
+            source,
+        };
+
+        let mut manifests = vec![];
+
+        for entry in fs::read_dir(&self.directory).map_err(io_error)? {
+            let path = entry.map_err(io_error)?.path();
+
+            let is_manifest = path
+                .file_name()
+                .is_some_and(|name| name.to_string_lossy().ends_with(MANIFEST_SUFFIX));
+
+            if is_manifest && path.is_file() {
+                manifests.push(path);
+            }
+        }
+
+        manifests.sort();
+
+        Ok(manifests)
+    }
+
+    /// Renders every job of the directory that isn't done yet.
+    ///
+    /// Jobs that fail don't stop the rest of the jobs, and they're reported alongside the reason
+    /// why they failed.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the directory can't be read.
+    ///
+    pub fn run(&self) -> Result<FarmReport, Error> {
+        let mut report = FarmReport::default();
+
+        for manifest in self.manifests()? {
+            if is_done(&manifest) {
+                log::info!("skipping {}, which is already done", manifest.display());
+                report.skipped.push(manifest);
+                continue;
+            }
+
+            log::info!("rendering {}", manifest.display());
+
+            match self.render(&manifest) {
+                Ok(stats) => report.rendered.push((manifest, stats)),
+                Err(err) => {
+                    log::error!("{} failed: {err}", manifest.display());
+
+                    let status = serde_json::json!({
+                        "state": "failed",
+                        "error": error_chain(&err),
+                    });
+
+                    // The job already failed, a status that can't be written doesn't change it.
+                    if let Err(status_err) = write_status(&manifest, status) {
+                        log::error!("{status_err}");
+                    }
+
+                    report.failed.push((manifest, err));
+                }
+            }
+        }
+
+        Ok(report)
+    }
+
+    fn render(&self, manifest_path: &Path) -> Result<RenderStats, Error> {
+        let manifest_spec = read(manifest_path)?;
+        let manifest: JobManifest = serde_json::from_str::<Checked<JobManifest>>(&manifest_spec)
+            .map_err(|source| Error::Manifest {
+                path: manifest_path.to_path_buf(),
+                source,
+            })?
+            .inner;
+
+        let directory = manifest_path.parent().unwrap_or(Path::new(""));
+        let scene_path = directory.join(&manifest.scene);
+        let output = directory.join(&manifest.output);
+
+        let scene_error = |source| Error::Scene {
+            path: scene_path.clone(),
+            source,
+        };
+
+        let spec = read(&scene_path)?;
+        let scene = Scene::from_json_with_limits(&spec, &self.limits)
+            .map_err(scene_error)?
+            .with_config(&self.config);
+
+        let recursion_depth = self
+            .limits
+            .recursion_depth(self.config.recursion_depth)
+            .map_err(|limit| scene_error(limit.into()))?;
+
+        let settings = RenderSettings {
+            threads: manifest.threads.unwrap_or(self.config.threads).max(1),
+            tile_size: manifest.tile_size,
+            integrator: manifest.integrator.integrator(recursion_depth),
+            progress: self.config.progress,
+            ..Default::default()
+        };
+
+        let camera = &scene.camera;
+        let checkpoint = job_file(manifest_path, CHECKPOINT_SUFFIX);
+        let job_hash = metadata::fnv_hash(spec.bytes().chain(manifest_spec.bytes()));
+
+        let mut buffer = read_checkpoint(&checkpoint, job_hash)
+            .filter(|buffer| (buffer.width(), buffer.height()) == (camera.width(), camera.height()))
+            .unwrap_or_else(|| AccumulationBuffer::new(camera.width(), camera.height()));
+
+        let total_samples = manifest.samples.max(1);
+        let mut samples = rendered_samples(&buffer);
+        let mut stats = RenderStats::default();
+
+        if samples > 0 {
+            log::info!("resuming from {samples} samples");
+        }
+
+        while samples < total_samples {
+            let pass = self.pass_samples.min(total_samples - samples);
+
+            stats += camera.render_samples(&scene.world, &settings, &mut buffer, pass);
+            samples += pass;
+
+            let mut bytes = job_hash.to_le_bytes().to_vec();
+            buffer.write_to(&mut bytes).map_err(|source| Error::Io {
+                path: checkpoint.clone(),
+                source,
+            })?;
+            write_atomically(&checkpoint, &bytes)?;
+
+            write_status(
+                manifest_path,
+                serde_json::json!({
+                    "state": "rendering",
+                    "samples": samples,
+                    "total_samples": total_samples,
+                }),
+            )?;
+        }
+
+        let metadata = manifest
+            .metadata
+            .then(|| RenderMetadata::new(camera, samples).with_scene_spec(&spec));
+
+        queue::save_bracket(
+            &buffer.to_canvas(),
+            &output,
+            &manifest.exposure_bracket,
+            metadata.as_ref(),
+            manifest.color_range,
+        )?;
+
+        write_status(
+            manifest_path,
+            serde_json::json!({
+                "state": "done",
+                "samples": samples,
+                "total_samples": total_samples,
+            }),
+        )?;
+
+        match fs::remove_file(&checkpoint) {
+            Err(source) if source.kind() != io::ErrorKind::NotFound => Err(Error::Io {
+                path: checkpoint,
+                source,
+            }),
+            _ => Ok(stats),
+        }
+    }
+}
+
+/// Number of samples rendered for every pixel of the buffer.
+fn rendered_samples(buffer: &AccumulationBuffer) -> usize {
+    (0..buffer.height())
+        .flat_map(|y| (0..buffer.width()).map(move |x| (x, y)))
+        .map(|(x, y)| buffer.sample_count(x, y) as usize)
+        .min()
+        .unwrap_or(0)
+}
+
+/// Path of the file of a job with the given suffix, which is next to it's manifest.
+fn job_file(manifest: &Path, suffix: &str) -> PathBuf {
+    let name = manifest.file_name().unwrap_or_default().to_string_lossy();
+    let stem = name.strip_suffix(MANIFEST_SUFFIX).unwrap_or(&name);
+
+    manifest.with_file_name(format!("{stem}{suffix}"))
+}
+
+fn is_done(manifest: &Path) -> bool {
+    fs::read_to_string(job_file(manifest, STATUS_SUFFIX))
+        .ok()
+        .and_then(|status| serde_json::from_str::<serde_json::Value>(&status).ok())
+        .is_some_and(|status| status["state"] == "done")
+}
+
+/// Reads the checkpoint of a job, if there's a valid one saved for the job with the given hash.
+///
+/// Checkpoints start with the hash of the scene spec and the manifest of the job, followed by the
+/// samples rendered so far.
+///
+fn read_checkpoint(path: &Path, job_hash: u64) -> Option<AccumulationBuffer> {
+    let file = fs::File::open(path).ok()?;
+    let mut reader = io::BufReader::new(file);
+
+    let mut hash = [0; 8];
+    if reader.read_exact(&mut hash).is_err() || u64::from_le_bytes(hash) != job_hash {
+        log::warn!(
+            "discarding checkpoint {}, which was saved for a different scene or manifest",
+            path.display()
+        );
+        return None;
+    }
+
+    match AccumulationBuffer::read_from(reader) {
+        Ok(buffer) => Some(buffer),
+        Err(err) => {
+            log::warn!("ignoring invalid checkpoint {}: {err}", path.display());
+            None
+        }
+    }
+}
+
+fn write_status(manifest: &Path, status: serde_json::Value) -> Result<(), Error> {
+    let path = job_file(manifest, STATUS_SUFFIX);
+    write_atomically(&path, format!("{status:#}\n").as_bytes())
+}
+
+/// Writes a file through a temporary file next to it, so the file is either left as it was or
+/// completely written, even if the program is stopped halfway through.
+fn write_atomically(path: &Path, contents: &[u8]) -> Result<(), Error> {
+    let mut temporary = path.as_os_str().to_owned();
+    temporary.push(".tmp");
+
+    fs::write(&temporary, contents)
+        .and_then(|_| fs::rename(&temporary, path))
+        .map_err(|source| Error::Io {
+            path: path.to_path_buf(),
+            source,
+        })
+}
+
+fn read(path: &Path) -> Result<String, Error> {
+    fs::read_to_string(path).map_err(|source| Error::Io {
+        path: path.to_path_buf(),
+        source,
+    })
+}
+
+/// Message of an error followed by the messages of the errors that caused it.
+fn error_chain(err: &Error) -> String {
+    let mut message = err.to_string();
+    let mut source = std::error::Error::source(err);
+
+    while let Some(err) = source {
+        message = format!("{message}: {err}");
+        source = err.source();
+    }
+
+    message
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{color, scene::LimitAction};
+
+    use super::*;
+
+    /// Empty directory for the jobs of a test, which is deleted when it's dropped.
+    struct JobDirectory(PathBuf);
+
+    impl JobDirectory {
+        fn new(name: &str) -> Self {
+            let path = std::env::temp_dir().join(format!("raytracer-farm-{name}"));
+
+            let _ = fs::remove_dir_all(&path);
+            fs::create_dir_all(&path).unwrap();
+
+            Self(path)
+        }
+
+        fn write(&self, name: &str, contents: &str) {
+            fs::write(self.0.join(name), contents).unwrap();
+        }
+
+        fn status(&self, name: &str) -> serde_json::Value {
+            serde_json::from_str(&fs::read_to_string(self.0.join(name)).unwrap()).unwrap()
+        }
+    }
+
+    impl Drop for JobDirectory {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    const SCENE: &str = r#"{
+        "camera": { "width": 4, "height": 3, "field_of_view": 90.0 },
+        "objects": [{ "type": "sphere" }]
+    }"#;
+
+    const MANIFEST: &str = r#"{ "scene": "scene.json", "output": "image.png", "samples": 3 }"#;
+
+    #[test]
+    fn deserializing_a_job_manifest_with_the_default_settings() {
+        let manifest: JobManifest =
+            serde_json::from_str(r#"{ "scene": "scene.json", "output": "image.png" }"#).unwrap();
+
+        assert_eq!(
+            manifest,
+            JobManifest {
+                scene: "scene.json".into(),
+                output: "image.png".into(),
+                samples: 1,
+                threads: None,
+                tile_size: DEFAULT_TILE_SIZE,
+                integrator: ManifestIntegrator::Whitted,
+                exposure_bracket: vec![],
+                metadata: false,
+                color_range: ColorRange::Clamp,
+            }
+        );
+    }
+
+    #[test]
+    fn rendering_the_jobs_of_a_directory() {
+        let jobs = JobDirectory::new("renders");
+
+        jobs.write("scene.json", SCENE);
+        jobs.write(
+            "a.job.json",
+            r#"{ "scene": "scene.json", "output": "a.png", "samples": 3 }"#,
+        );
+        jobs.write(
+            "b.job.json",
+            r#"{ "scene": "scene.json", "output": "b.png", "integrator": "ambient_occlusion" }"#,
+        );
+
+        let farm = RenderFarm::new(&jobs.0).with_pass_samples(2);
+        let report = farm.run().unwrap();
+
+        assert!(report.failed.is_empty());
+
+        let rendered: Vec<_> = report.rendered.iter().map(|(path, _)| path).collect();
+        assert_eq!(
+            rendered,
+            [&jobs.0.join("a.job.json"), &jobs.0.join("b.job.json")]
+        );
+        assert_eq!(report.rendered[0].1.primary_rays, 3 * 12);
+
+        assert!(jobs.0.join("a.png").exists());
+        assert!(jobs.0.join("b.png").exists());
+        assert!(!jobs.0.join("a.checkpoint").exists());
+
+        assert_eq!(jobs.status("a.status.json")["state"], "done");
+        assert_eq!(jobs.status("a.status.json")["samples"], 3);
+    }
+
+    #[test]
+    fn embedding_the_metadata_of_a_job_in_its_image() {
+        let jobs = JobDirectory::new("metadata");
+
+        jobs.write("scene.json", SCENE);
+        jobs.write(
+            "job.job.json",
+            r#"{ "scene": "scene.json", "output": "image.png", "samples": 2, "metadata": true }"#,
+        );
+
+        RenderFarm::new(&jobs.0).run().unwrap();
+
+        let metadata = RenderMetadata::read(&jobs.0.join("image.png")).unwrap();
+
+        assert_eq!(
+            metadata.scene_hash,
+            RenderMetadata::default().with_scene_spec(SCENE).scene_hash
+        );
+        assert_eq!(metadata.samples, Some(2));
+        assert!(metadata.camera.unwrap().starts_with("width=4 height=3"));
+    }
+
+    #[test]
+    fn saving_the_image_of_a_job_with_hdr_colors() {
+        let jobs = JobDirectory::new("hdr");
+
+        jobs.write("scene.json", SCENE);
+        jobs.write(
+            "job.job.json",
+            r#"{ "scene": "scene.json", "output": "image.exr", "color_range": "hdr" }"#,
+        );
+
+        let report = RenderFarm::new(&jobs.0).run().unwrap();
+
+        assert!(report.failed.is_empty());
+        assert!(matches!(
+            image::open(jobs.0.join("image.exr")).unwrap(),
+            image::DynamicImage::ImageRgba32F(_) | image::DynamicImage::ImageRgb32F(_)
+        ));
+    }
+
+    #[test]
+    fn jobs_that_are_done_are_skipped() {
+        let jobs = JobDirectory::new("skips");
+
+        jobs.write("scene.json", SCENE);
+        jobs.write(
+            "job.job.json",
+            r#"{ "scene": "scene.json", "output": "image.png" }"#,
+        );
+
+        let farm = RenderFarm::new(&jobs.0);
+
+        assert_eq!(farm.run().unwrap().rendered.len(), 1);
+
+        let report = farm.run().unwrap();
+
+        assert!(report.rendered.is_empty());
+        assert_eq!(report.skipped, [jobs.0.join("job.job.json")]);
+    }
+
+    #[test]
+    fn an_interrupted_job_resumes_from_its_checkpoint() {
+        let jobs = JobDirectory::new("resumes");
+
+        jobs.write("scene.json", SCENE);
+        jobs.write("job.job.json", MANIFEST);
+
+        // Checkpoint with all of the samples of the job, all of them red, which the scene can't
+        // render.
+        let mut checkpoint = AccumulationBuffer::new(4, 3);
+        for y in 0..3 {
+            for x in 0..4 {
+                for _ in 0..3 {
+                    checkpoint.add_sample(x, y, color::consts::RED);
+                }
+            }
+        }
+
+        let hash = metadata::fnv_hash(SCENE.bytes().chain(MANIFEST.bytes()));
+        let mut bytes = hash.to_le_bytes().to_vec();
+        checkpoint.write_to(&mut bytes).unwrap();
+        fs::write(jobs.0.join("job.checkpoint"), bytes).unwrap();
+
+        let report = RenderFarm::new(&jobs.0).run().unwrap();
+
+        assert_eq!(report.rendered[0].1.primary_rays, 0);
+
+        let image = image::open(jobs.0.join("image.png")).unwrap().into_rgb8();
+        assert!(image.pixels().all(|pixel| pixel.0 == [255, 0, 0]));
+    }
+
+    #[test]
+    fn a_checkpoint_of_a_job_that_changed_is_discarded() {
+        let jobs = JobDirectory::new("stale");
+
+        jobs.write("scene.json", SCENE);
+        jobs.write("job.job.json", MANIFEST);
+
+        // Checkpoint of the same size, but saved before the scene of the job was edited.
+        let mut checkpoint = AccumulationBuffer::new(4, 3);
+        for y in 0..3 {
+            for x in 0..4 {
+                checkpoint.add_sample(x, y, color::consts::RED);
+            }
+        }
+
+        let hash = metadata::fnv_hash("{}".bytes().chain(MANIFEST.bytes()));
+        let mut bytes = hash.to_le_bytes().to_vec();
+        checkpoint.write_to(&mut bytes).unwrap();
+        fs::write(jobs.0.join("job.checkpoint"), bytes).unwrap();
+
+        let report = RenderFarm::new(&jobs.0).run().unwrap();
+
+        // Every sample is rendered again, none of them red.
+        assert_eq!(report.rendered[0].1.primary_rays, 3 * 12);
+
+        let image = image::open(jobs.0.join("image.png")).unwrap().into_rgb8();
+        assert!(image.pixels().all(|pixel| pixel.0 != [255, 0, 0]));
+    }
+
+    #[test]
+    fn a_job_with_a_missing_scene_fails() {
+        let jobs = JobDirectory::new("fails");
+
+        jobs.write(
+            "job.job.json",
+            r#"{ "scene": "missing.json", "output": "image.png" }"#,
+        );
+
+        let report = RenderFarm::new(&jobs.0).run().unwrap();
+
+        assert!(matches!(report.failed[0].1, Error::Io { .. }));
+
+        let status = jobs.status("job.status.json");
+        assert_eq!(status["state"], "failed");
+        assert!(status["error"].as_str().unwrap().contains("missing.json"));
+    }
+
+    #[test]
+    fn a_job_whose_scene_exceeds_the_limits_of_the_farm() {
+        let jobs = JobDirectory::new("limits");
+
+        jobs.write("scene.json", SCENE);
+        jobs.write(
+            "job.job.json",
+            r#"{ "scene": "scene.json", "output": "image.png" }"#,
+        );
+
+        let limits = SceneLimits {
+            max_pixels: 6,
+            ..Default::default()
+        };
+
+        let report = RenderFarm::new(&jobs.0).with_limits(limits).run().unwrap();
+
+        assert!(matches!(report.failed[0].1, Error::Scene { .. }));
+        assert!(!jobs.0.join("image.png").exists());
+
+        let status = jobs.status("job.status.json");
+        assert!(status["error"]
+            .as_str()
+            .unwrap()
+            .contains("the image has 12 pixels, more than the limit of 6"));
+
+        // Degrading the scene renders it at a lower resolution instead.
+        let limits = SceneLimits {
+            on_exceeded: LimitAction::Degrade,
+            ..limits
+        };
+
+        let report = RenderFarm::new(&jobs.0).with_limits(limits).run().unwrap();
+
+        assert!(report.failed.is_empty());
+        assert_eq!(report.rendered[0].1.primary_rays, 2 * 2);
+    }
+
+    #[test]
+    fn running_a_farm_on_a_missing_directory() {
+        let farm = RenderFarm::new(std::env::temp_dir().join("raytracer-farm-missing"));
+
+        assert!(matches!(farm.run(), Err(Error::Io { .. })));
+    }
+}
@@ -0,0 +1,472 @@
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter},
+    path::Path,
+};
+
+use exr::prelude::{
+    AttributeValue, Encoding, Image, Layer, LayerAttributes, MetaData, SpecificChannels, Text,
+    Vec2, WritableImage,
+};
+use image::{
+    error::{DecodingError, EncodingError, UnsupportedError, UnsupportedErrorKind},
+    DynamicImage, ExtendedColorType, ImageError, ImageFormat,
+};
+
+use crate::{camera::Camera, canvas::Canvas};
+
+use super::{encoder, ColorRange, Error};
+
+/// Keyword of the metadata entry with the name and version of the crate.
+const SOFTWARE_KEY: &str = "Software";
+
+/// Keyword of the metadata entry with the [scene hash](RenderMetadata::scene_hash).
+const SCENE_HASH_KEY: &str = "Raytracer Scene Hash";
+
+/// Keyword of the metadata entry with the [camera parameters](RenderMetadata::camera).
+const CAMERA_KEY: &str = "Raytracer Camera";
+
+/// Keyword of the metadata entry with the [number of samples](RenderMetadata::samples).
+const SAMPLES_KEY: &str = "Raytracer Samples";
+
+/// Description of how an image was rendered, embedded in the image when it's saved so it can be
+/// traced back to the inputs that produced it.
+///
+/// The metadata is stored as text chunks of PNG images and as text attributes of EXR images,
+/// which most image viewers and tools like `exiftool` can display. Images saved in other formats,
+/// or with a [registered encoder](super::register_encoder), don't carry any metadata.
+///
+/// # Examples
+///
+/// ```no_run
+/// use raytracer::{render::RenderMetadata, scene::Scene};
+///
+/// let spec = std::fs::read_to_string("scene.json").unwrap();
+/// let scene = Scene::from_json(&spec).unwrap();
+///
+/// let image = scene.render();
+///
+/// RenderMetadata::new(&scene.camera, 1)
+///     .with_scene_spec(&spec)
+///     .save(&image, "image.png".as_ref(), Default::default())
+///     .unwrap();
+///
+/// let metadata = RenderMetadata::read("image.png".as_ref()).unwrap();
+///
+/// assert_eq!(metadata.samples, Some(1));
+/// ```
+///
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct RenderMetadata {
+    /// Hash of the JSON spec of the scene that was rendered, as sixteen hexadecimal digits. See
+    /// [RenderMetadata::with_scene_spec] for details.
+    pub scene_hash: Option<String>,
+
+    /// Parameters of the camera that rendered the image, such as it's dimensions, field of view,
+    /// position and the direction it looks at.
+    pub camera: Option<String>,
+
+    /// Number of samples rendered for each pixel.
+    pub samples: Option<usize>,
+
+    /// Version of the crate that rendered the image.
+    pub version: Option<String>,
+}
+
+impl RenderMetadata {
+    /// Creates the metadata of an image rendered by `camera` with a number of `samples` per pixel,
+    /// using the current version of the crate.
+    pub fn new(camera: &Camera, samples: usize) -> Self {
+        let center =
+            camera.ray_for_position(camera.width() as f64 / 2.0, camera.height() as f64 / 2.0);
+
+        let position = center.origin.0;
+        let direction = center.direction.0;
+
+        // Coordinates are rounded so floating point errors don't show up in the description.
+        let round = |value: f64| (value * 1e6).round() / 1e6 + 0.0;
+
+        let camera = format!(
+            "width={} height={} field_of_view={} projection={:?} aperture={} focal_distance={} \
+             position=({}, {}, {}) direction=({}, {}, {})",
+            camera.width(),
+            camera.height(),
+            camera.field_of_view().to_degrees(),
+            camera.projection(),
+            camera.aperture(),
+            camera.focal_distance(),
+            round(position.x),
+            round(position.y),
+            round(position.z),
+            round(direction.x),
+            round(direction.y),
+            round(direction.z),
+        );
+
+        Self {
+            scene_hash: None,
+            camera: Some(camera),
+            samples: Some(samples),
+            version: Some(env!("CARGO_PKG_VERSION").to_string()),
+        }
+    }
+
+    /// Sets the [scene hash](RenderMetadata::scene_hash) to the hash of `spec`, which is the JSON
+    /// spec the scene was parsed from.
+    ///
+    /// The hash is the 64 bits [FNV-1a](https://en.wikipedia.org/wiki/Fowler–Noll–Vo_hash_function)
+    /// hash of the bytes of the spec, which doesn't change between versions of the crate or
+    /// platforms, so it can be compared with the hash of a spec computed anywhere else.
+    ///
+    pub fn with_scene_spec(self, spec: &str) -> Self {
+        let hash = fnv_hash(spec.bytes());
+
+        Self {
+            scene_hash: Some(format!("{hash:016x}")),
+            ..self
+        }
+    }
+
+    /// Saves `image` to `path` with this metadata embedded in it, with it's colors fit into the
+    /// given `range`. The format of the image is deduced from the extension of the path.
+    ///
+    /// PNG images are saved with 8 bits per channel, just like images saved without metadata, so
+    /// they can't be saved with a [ColorRange::Hdr] range. EXR images are always saved with 32
+    /// bits floating point channels, so colors fit into a range lose no precision.
+    ///
+    pub fn save(&self, image: &Canvas, path: &Path, range: ColorRange) -> Result<(), Error> {
+        let result = match ImageFormat::from_path(path) {
+            _ if encoder::encoder_for(path).is_some() => encoder::save(image, path, range),
+            Ok(ImageFormat::Png) => self.save_png(image, path, range),
+            Ok(ImageFormat::OpenExr) => self.save_exr(&image.fit_to(range), path),
+            _ => encoder::save(image, path, range),
+        };
+
+        result.map_err(|source| Error::Save {
+            path: path.to_path_buf(),
+            source,
+        })
+    }
+
+    /// Reads the metadata embedded in the image at `path`. Entries that are missing from the
+    /// image, or that can't be parsed, are `None`.
+    pub fn read(path: &Path) -> Result<Self, Error> {
+        let entries = match ImageFormat::from_path(path) {
+            Ok(ImageFormat::Png) => Self::read_png(path),
+            Ok(ImageFormat::OpenExr) => Self::read_exr(path),
+            _ => Ok(vec![]),
+        }
+        .map_err(|source| Error::Metadata {
+            path: path.to_path_buf(),
+            source,
+        })?;
+
+        let entry = |key: &str| {
+            entries
+                .iter()
+                .find(|(keyword, _)| keyword == key)
+                .map(|(_, text)| text.clone())
+        };
+
+        Ok(Self {
+            scene_hash: entry(SCENE_HASH_KEY),
+            camera: entry(CAMERA_KEY),
+            samples: entry(SAMPLES_KEY).and_then(|samples| samples.parse().ok()),
+            version: entry(SOFTWARE_KEY)
+                .and_then(|software| software.strip_prefix("raytracer ").map(str::to_string)),
+        })
+    }
+
+    /// Keywords and texts of the entries of the metadata that are set.
+    fn entries(&self) -> Vec<(&'static str, String)> {
+        [
+            (
+                SOFTWARE_KEY,
+                self.version
+                    .as_ref()
+                    .map(|version| format!("raytracer {version}")),
+            ),
+            (SCENE_HASH_KEY, self.scene_hash.clone()),
+            (CAMERA_KEY, self.camera.clone()),
+            (SAMPLES_KEY, self.samples.map(|samples| samples.to_string())),
+        ]
+        .into_iter()
+        .filter_map(|(keyword, text)| text.map(|text| (keyword, text)))
+        .collect()
+    }
+
+    fn save_png(&self, image: &Canvas, path: &Path, range: ColorRange) -> Result<(), ImageError> {
+        let DynamicImage::ImageRgb8(pixels) = image.to_dynamic_image(range) else {
+            return Err(ImageError::Unsupported(
+                UnsupportedError::from_format_and_kind(
+                    ImageFormat::Png.into(),
+                    UnsupportedErrorKind::Color(ExtendedColorType::Rgb32F),
+                ),
+            ));
+        };
+
+        let file = BufWriter::new(File::create(path)?);
+
+        let mut encoder = png::Encoder::new(file, pixels.width(), pixels.height());
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+
+        let encoding_error =
+            |err| ImageError::Encoding(EncodingError::new(ImageFormat::Png.into(), err));
+
+        for (keyword, text) in self.entries() {
+            encoder
+                .add_text_chunk(keyword.to_string(), text)
+                .map_err(encoding_error)?;
+        }
+
+        encoder
+            .write_header()
+            .and_then(|mut writer| writer.write_image_data(pixels.as_raw()))
+            .map_err(encoding_error)
+    }
+
+    fn save_exr(&self, image: &Canvas, path: &Path) -> Result<(), ImageError> {
+        let pixels = SpecificChannels::rgb(|position: Vec2<usize>| {
+            let color = image.pixel_at(position.x(), position.y());
+            (color.red as f32, color.green as f32, color.blue as f32)
+        });
+
+        let layer = Layer::new(
+            (image.width, image.height),
+            LayerAttributes::default(),
+            Encoding::FAST_LOSSLESS,
+            pixels,
+        );
+
+        let mut exr_image = Image::from_layer(layer);
+
+        for (keyword, text) in self.entries() {
+            exr_image.attributes.other.insert(
+                Text::from(keyword),
+                AttributeValue::Text(Text::from(text.as_str())),
+            );
+        }
+
+        exr_image.write().to_file(path).map_err(|err| {
+            ImageError::Encoding(EncodingError::new(ImageFormat::OpenExr.into(), err))
+        })
+    }
+
+    fn read_png(path: &Path) -> Result<Vec<(String, String)>, ImageError> {
+        let decoder = png::Decoder::new(BufReader::new(File::open(path)?));
+
+        let reader = decoder.read_info().map_err(|err| {
+            ImageError::Decoding(DecodingError::new(ImageFormat::Png.into(), err))
+        })?;
+
+        Ok(reader
+            .info()
+            .uncompressed_latin1_text
+            .iter()
+            .map(|chunk| (chunk.keyword.clone(), chunk.text.clone()))
+            .collect())
+    }
+
+    fn read_exr(path: &Path) -> Result<Vec<(String, String)>, ImageError> {
+        let metadata = MetaData::read_from_file(path, false).map_err(|err| {
+            ImageError::Decoding(DecodingError::new(ImageFormat::OpenExr.into(), err))
+        })?;
+
+        Ok(metadata
+            .headers
+            .iter()
+            .flat_map(|header| {
+                header
+                    .shared_attributes
+                    .other
+                    .iter()
+                    .chain(&header.own_attributes.other)
+            })
+            .filter_map(|(keyword, value)| match value {
+                AttributeValue::Text(text) => Some((keyword.to_string(), text.to_string())),
+                _ => None,
+            })
+            .collect())
+    }
+}
+
+/// 64 bits [FNV-1a](https://en.wikipedia.org/wiki/Fowler–Noll–Vo_hash_function) hash of some
+/// bytes, which is the same for every version of the crate and every platform.
+pub(crate) fn fnv_hash<I: IntoIterator<Item = u8>>(bytes: I) -> u64 {
+    bytes.into_iter().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x100000001b3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        camera::CameraBuilder,
+        color::{self, Color},
+        transform::Transform,
+        tuple::{Point, Vector},
+    };
+
+    use super::*;
+
+    fn camera() -> Camera {
+        Camera::try_from(CameraBuilder {
+            width: 3,
+            height: 2,
+            field_of_view: std::f64::consts::FRAC_PI_2,
+            transform: Transform::view(
+                Point::new(0.0, 1.0, -5.0),
+                Point::new(0.0, 1.0, 0.0),
+                Vector::new(0.0, 1.0, 0.0),
+            )
+            .unwrap(),
+        })
+        .unwrap()
+    }
+
+    fn image() -> Canvas {
+        let mut image = Canvas::new(3, 2);
+        image.write_pixel(0, 0, color::consts::WHITE);
+        image.write_pixel(
+            2,
+            1,
+            Color {
+                red: 4.0,
+                green: 0.5,
+                blue: 0.25,
+            },
+        );
+        image
+    }
+
+    fn output(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("raytracer-metadata-{name}"))
+    }
+
+    #[test]
+    fn describing_the_camera_of_a_render() {
+        let metadata = RenderMetadata::new(&camera(), 16);
+
+        assert_eq!(
+            metadata.camera.as_deref(),
+            Some(
+                "width=3 height=2 field_of_view=90 projection=Perspective aperture=0 \
+                 focal_distance=1 position=(0, 1, -5) direction=(0, 0, 1)"
+            )
+        );
+        assert_eq!(metadata.samples, Some(16));
+        assert_eq!(metadata.version.as_deref(), Some(env!("CARGO_PKG_VERSION")));
+        assert_eq!(metadata.scene_hash, None);
+    }
+
+    #[test]
+    fn hashing_the_spec_of_a_scene() {
+        let hash = |spec| {
+            RenderMetadata::default()
+                .with_scene_spec(spec)
+                .scene_hash
+                .unwrap()
+        };
+
+        assert_eq!(hash(""), "cbf29ce484222325");
+        assert_eq!(hash("a"), "af63dc4c8601ec8c");
+        assert_ne!(hash(r#"{ "objects": [] }"#), hash(r#"{ "objects": [{}] }"#));
+    }
+
+    #[test]
+    fn embedding_metadata_in_a_png_image() {
+        let path = output("embeds.png");
+        let metadata = RenderMetadata::new(&camera(), 4).with_scene_spec("{}");
+
+        metadata.save(&image(), &path, ColorRange::Clamp).unwrap();
+
+        assert_eq!(RenderMetadata::read(&path).unwrap(), metadata);
+
+        let saved = image::open(&path).unwrap().into_rgb8();
+        assert_eq!(saved.get_pixel(0, 0).0, [255, 255, 255]);
+        assert_eq!(saved.get_pixel(1, 0).0, [0, 0, 0]);
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn embedding_metadata_in_an_exr_image() {
+        let path = output("embeds.exr");
+        let metadata = RenderMetadata::new(&camera(), 8).with_scene_spec("{}");
+
+        metadata.save(&image(), &path, ColorRange::Hdr).unwrap();
+
+        assert_eq!(RenderMetadata::read(&path).unwrap(), metadata);
+
+        // Colors brighter than white are not clamped.
+        let saved = image::open(&path).unwrap().into_rgb32f();
+        assert_eq!(saved.get_pixel(2, 1).0, [4.0, 0.5, 0.25]);
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn only_the_entries_that_are_set_are_embedded() {
+        let path = output("partial.png");
+        let metadata = RenderMetadata {
+            samples: Some(2),
+            ..Default::default()
+        };
+
+        metadata.save(&image(), &path, ColorRange::Clamp).unwrap();
+
+        assert_eq!(RenderMetadata::read(&path).unwrap(), metadata);
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn embedding_metadata_in_an_exr_image_with_its_colors_normalized() {
+        let path = output("normalized.exr");
+
+        RenderMetadata::default()
+            .save(&image(), &path, ColorRange::NormalizeToMax)
+            .unwrap();
+
+        let saved = image::open(&path).unwrap().into_rgb32f();
+        assert_eq!(saved.get_pixel(0, 0).0, [0.25, 0.25, 0.25]);
+        assert_eq!(saved.get_pixel(2, 1).0, [1.0, 0.125, 0.0625]);
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn png_images_with_metadata_cant_keep_hdr_colors() {
+        let path = output("hdr.png");
+
+        assert!(matches!(
+            RenderMetadata::default().save(&image(), &path, ColorRange::Hdr),
+            Err(Error::Save { .. })
+        ));
+    }
+
+    #[test]
+    fn reading_the_metadata_of_an_image_without_it() {
+        let path = output("missing.png");
+
+        image().to_image().save(&path).unwrap();
+
+        assert_eq!(
+            RenderMetadata::read(&path).unwrap(),
+            RenderMetadata::default()
+        );
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn reading_the_metadata_of_a_missing_image() {
+        let path = output("does-not-exist.png");
+
+        assert!(matches!(
+            RenderMetadata::read(&path),
+            Err(Error::Metadata { .. })
+        ));
+    }
+}
@@ -0,0 +1,562 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+};
+
+use crate::{
+    canvas::Canvas,
+    progress::{ProgressBar, Stopwatch},
+    scene::Scene,
+};
+
+use super::{encoder, ColorRange, Error, RenderMetadata, RenderSettings, RenderStats};
+
+/// A scene to render, how to render it, and where to save the image.
+///
+/// Jobs are constructed with [RenderJob::new] from the scene and the output path, and every other
+/// field starts with a default value that can be changed with the `with_*` methods.
+///
+#[derive(Clone, Debug)]
+pub struct RenderJob {
+    /// Scene to render.
+    pub scene: Scene,
+
+    /// Settings used to render the scene. The number of threads is capped by the thread budget of
+    /// the queue.
+    pub settings: RenderSettings,
+
+    /// Path where the image is saved. The format of the image is deduced from the extension of
+    /// the path, which can also be the extension of a [registered encoder](super::register_encoder).
+    pub output: PathBuf,
+
+    /// Exposure offsets, in stops, at which the image is saved. When it's empty the image is
+    /// saved to the output path as it is. Otherwise the image is rendered once and saved once for
+    /// each offset, with the offset appended to the name of the file, so an output of
+    /// `image.png` with the [default bracket](super::DEFAULT_EXPOSURE_BRACKET) is saved to
+    /// `image-2ev.png`, `image+0ev.png` and `image+2ev.png`.
+    pub exposure_bracket: Vec<f64>,
+
+    /// Whether the [metadata](RenderMetadata) of the render is embedded in the saved images. The
+    /// scene of a job isn't parsed by the queue, so the metadata doesn't include the hash of it's
+    /// spec.
+    pub metadata: bool,
+
+    /// How the colors of the image are fit into the range of values of the format it's saved in.
+    pub color_range: ColorRange,
+
+    /// Path where a [heatmap](super::TileTimes::to_heatmap) of the time each tile of the image
+    /// took to render is saved, if any, to find the objects and materials that slow down the
+    /// render.
+    pub tile_heatmap: Option<PathBuf>,
+}
+
+/// Progress of a [RenderQueue], reported every time a job is done.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct QueueProgress {
+    /// Number of jobs that are done, including the ones that failed.
+    pub completed_jobs: usize,
+
+    /// Number of jobs in the queue.
+    pub total_jobs: usize,
+
+    /// Statistics of all the jobs that are done so far.
+    pub stats: RenderStats,
+}
+
+/// Batch of render jobs, such as all of the scenes of a showcase, rendered one after another or
+/// a few at a time.
+///
+/// The queue has a budget of threads that is split between the jobs that run at the same time,
+/// so rendering multiple small scenes in parallel doesn't oversubscribe the machine.
+///
+/// # Examples
+///
+/// ```no_run
+/// use raytracer::{
+///     render::{RenderJob, RenderQueue},
+///     scene::Scene,
+/// };
+///
+/// let mut queue = RenderQueue::new(8).with_parallel_jobs(2);
+///
+/// for name in ["first", "second", "third"] {
+///     let spec = std::fs::read_to_string(format!("{name}.json")).unwrap();
+///
+///     queue.push(
+///         RenderJob::new(Scene::from_json(&spec).unwrap(), format!("{name}.png"))
+///             .with_metadata(true),
+///     );
+/// }
+///
+/// let results = queue.run_with_progress(|progress| {
+///     println!("{}/{} scenes done", progress.completed_jobs, progress.total_jobs);
+/// });
+///
+/// for result in results {
+///     if let Err(err) = result {
+///         eprintln!("{err}");
+///     }
+/// }
+/// ```
+///
+#[derive(Clone, Debug)]
+pub struct RenderQueue {
+    jobs: Vec<RenderJob>,
+    threads: usize,
+    parallel_jobs: usize,
+}
+
+impl RenderQueue {
+    /// Constructs an empty queue that uses at most `threads` threads at once. A budget of `0`
+    /// threads is treated as `1`.
+    pub fn new(threads: usize) -> Self {
+        Self {
+            jobs: vec![],
+            threads: threads.max(1),
+            parallel_jobs: 1,
+        }
+    }
+
+    /// Sets the number of jobs that are rendered at the same time, sharing the thread budget of
+    /// the queue. By default, jobs are rendered one after another. A value of `0` is treated as
+    /// `1`.
+    pub fn with_parallel_jobs(mut self, parallel_jobs: usize) -> Self {
+        self.parallel_jobs = parallel_jobs.max(1);
+        self
+    }
+
+    /// Adds a job at the end of the queue.
+    pub fn push(&mut self, job: RenderJob) {
+        self.jobs.push(job);
+    }
+
+    /// Number of jobs in the queue.
+    pub fn len(&self) -> usize {
+        self.jobs.len()
+    }
+
+    /// Whether the queue has no jobs.
+    pub fn is_empty(&self) -> bool {
+        self.jobs.is_empty()
+    }
+
+    /// Renders every job of the queue and saves their images, returning the statistics of each
+    /// job in the same order they were added.
+    ///
+    /// Jobs that fail don't stop the rest of the queue. A progress bar with the number of jobs
+    /// that are done is displayed when the settings of any of the jobs display their
+    /// [progress](RenderSettings::progress).
+    ///
+    /// # Panics:
+    ///
+    /// * If rendering any of the jobs panics.
+    /// * If [Mutex::lock](https://doc.rust-lang.org/std/sync/struct.Mutex.html#method.lock) fails.
+    ///
+    pub fn run(self) -> Vec<Result<RenderStats, Error>> {
+        let displayed = self.jobs.iter().any(|job| job.settings.progress);
+        let progress_bar = ProgressBar::new(self.jobs.len() as u64, displayed);
+
+        let results = self.run_with_progress(|_| progress_bar.inc(1));
+        progress_bar.finish();
+
+        results
+    }
+
+    /// Renders every job of the queue and saves their images, calling `progress` every time a
+    /// job is done. Returns the statistics of each job in the same order they were added.
+    ///
+    /// Jobs that fail don't stop the rest of the queue.
+    ///
+    /// # Panics:
+    ///
+    /// * If rendering any of the jobs panics.
+    /// * If [Mutex::lock](https://doc.rust-lang.org/std/sync/struct.Mutex.html#method.lock) fails.
+    ///
+    pub fn run_with_progress<F>(self, progress: F) -> Vec<Result<RenderStats, Error>>
+    where
+        F: Fn(QueueProgress) + Sync,
+    {
+        let total_jobs = self.jobs.len();
+        let workers = self.parallel_jobs.min(total_jobs).max(1);
+        let threads = threads_per_job(self.threads, workers);
+
+        let next_job = AtomicUsize::new(0);
+        let results: Vec<_> = (0..total_jobs).map(|_| Mutex::new(None)).collect();
+        let totals = Mutex::new((0, RenderStats::default()));
+
+        std::thread::scope(|s| {
+            for _ in 0..workers {
+                s.spawn(|| loop {
+                    let index = next_job.fetch_add(1, Ordering::Relaxed);
+
+                    let Some(job) = self.jobs.get(index) else {
+                        break;
+                    };
+
+                    log::info!("rendering job {index} to {}", job.output.display());
+
+                    let stopwatch = Stopwatch::start();
+                    let result = job.render(threads);
+
+                    match &result {
+                        Ok(_) => log::info!("job {index} done in {stopwatch}"),
+                        Err(err) => log::error!("job {index} failed after {stopwatch}: {err}"),
+                    }
+
+                    let progress_update = {
+                        let mut totals = totals.lock().unwrap();
+                        let (completed_jobs, stats) = &mut *totals;
+
+                        *completed_jobs += 1;
+                        if let Ok(job_stats) = &result {
+                            *stats += *job_stats;
+                        }
+
+                        QueueProgress {
+                            completed_jobs: *completed_jobs,
+                            total_jobs,
+                            stats: *stats,
+                        }
+                    };
+
+                    *results[index].lock().unwrap() = Some(result);
+                    progress(progress_update);
+                });
+            }
+        });
+
+        // Every job is rendered exactly once by the workers, so every result is set.
+        #[allow(clippy::unwrap_used)]
+        results
+            .into_iter()
+            .map(|result| result.into_inner().unwrap().unwrap())
+            .collect()
+    }
+}
+
+impl RenderJob {
+    /// Constructs a job that renders a scene with the [default settings](RenderSettings) and
+    /// saves the image to `output`, without bracketing it's exposure, embedding metadata or
+    /// saving a heatmap of the tiles.
+    pub fn new(scene: Scene, output: impl Into<PathBuf>) -> Self {
+        Self {
+            scene,
+            settings: Default::default(),
+            output: output.into(),
+            exposure_bracket: vec![],
+            metadata: false,
+            color_range: Default::default(),
+            tile_heatmap: None,
+        }
+    }
+
+    /// Changes the settings used to render the scene.
+    pub fn with_settings(self, settings: RenderSettings) -> Self {
+        Self { settings, ..self }
+    }
+
+    /// Changes the [exposure offsets](RenderJob::exposure_bracket) at which the image is saved.
+    pub fn with_exposure_bracket(self, exposure_bracket: Vec<f64>) -> Self {
+        Self {
+            exposure_bracket,
+            ..self
+        }
+    }
+
+    /// Changes whether the [metadata](RenderMetadata) of the render is embedded in the images.
+    pub fn with_metadata(self, metadata: bool) -> Self {
+        Self { metadata, ..self }
+    }
+
+    /// Changes how the colors of the image are fit into the range of it's format.
+    pub fn with_color_range(self, color_range: ColorRange) -> Self {
+        Self {
+            color_range,
+            ..self
+        }
+    }
+
+    /// Changes the path where the [heatmap](RenderJob::tile_heatmap) of the tiles is saved.
+    pub fn with_tile_heatmap(self, path: impl Into<PathBuf>) -> Self {
+        Self {
+            tile_heatmap: Some(path.into()),
+            ..self
+        }
+    }
+
+    fn render(&self, max_threads: usize) -> Result<RenderStats, Error> {
+        let settings = RenderSettings {
+            threads: self.settings.threads.clamp(1, max_threads),
+            ..self.settings.clone()
+        };
+
+        let (image, stats, times) = self
+            .scene
+            .camera
+            .render_with_tile_times(&self.scene.world, &settings);
+
+        let metadata = self
+            .metadata
+            .then(|| RenderMetadata::new(&self.scene.camera, 1));
+
+        save_bracket(
+            &image,
+            &self.output,
+            &self.exposure_bracket,
+            metadata.as_ref(),
+            self.color_range,
+        )?;
+
+        if let Some(path) = &self.tile_heatmap {
+            save(&times.to_heatmap(), path, None, ColorRange::Clamp)?;
+        }
+
+        Ok(stats)
+    }
+}
+
+/// Saves an image to `output`, or once for each exposure of a non-empty bracket, as described in
+/// [RenderJob::exposure_bracket], with the `metadata` embedded in every image when it's set.
+/// Colors are fit into the `range` after changing the exposure of the image.
+pub(super) fn save_bracket(
+    image: &Canvas,
+    output: &Path,
+    bracket: &[f64],
+    metadata: Option<&RenderMetadata>,
+    range: ColorRange,
+) -> Result<(), Error> {
+    if bracket.is_empty() {
+        return save(image, output, metadata, range);
+    }
+
+    for &stops in bracket {
+        save(
+            &image.with_exposure(stops),
+            &bracketed_output(output, stops),
+            metadata,
+            range,
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Path where the image with it's exposure changed by `stops` is saved.
+fn bracketed_output(output: &Path, stops: f64) -> PathBuf {
+    let stem = output.file_stem().unwrap_or_default().to_string_lossy();
+    let mut name = format!("{stem}{stops:+}ev");
+
+    if let Some(extension) = output.extension() {
+        name = format!("{name}.{}", extension.to_string_lossy());
+    }
+
+    output.with_file_name(name)
+}
+
+fn save(
+    image: &Canvas,
+    path: &Path,
+    metadata: Option<&RenderMetadata>,
+    range: ColorRange,
+) -> Result<(), Error> {
+    if let Some(metadata) = metadata {
+        return metadata.save(image, path, range);
+    }
+
+    encoder::save(image, path, range).map_err(|source| Error::Save {
+        path: path.to_path_buf(),
+        source,
+    })
+}
+
+/// Number of threads each job gets when the thread budget is split between the jobs that run at
+/// the same time.
+fn threads_per_job(threads: usize, parallel_jobs: usize) -> usize {
+    (threads / parallel_jobs.max(1)).max(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        camera::{Camera, CameraBuilder},
+        render::DEFAULT_EXPOSURE_BRACKET,
+        world::World,
+    };
+
+    use super::*;
+
+    fn job(name: &str) -> RenderJob {
+        let camera = Camera::try_from(CameraBuilder {
+            width: 4,
+            height: 4,
+            field_of_view: std::f64::consts::FRAC_PI_2,
+            transform: Default::default(),
+        })
+        .unwrap();
+
+        let scene = Scene {
+            camera,
+            world: World::default(),
+            names: Default::default(),
+        };
+
+        RenderJob::new(
+            scene,
+            std::env::temp_dir().join(format!("raytracer-queue-{name}.png")),
+        )
+    }
+
+    #[test]
+    fn the_thread_budget_is_split_between_parallel_jobs() {
+        assert_eq!(threads_per_job(8, 1), 8);
+        assert_eq!(threads_per_job(8, 3), 2);
+        assert_eq!(threads_per_job(2, 4), 1);
+    }
+
+    #[test]
+    fn running_an_empty_queue() {
+        let queue = RenderQueue::new(4);
+
+        assert!(queue.is_empty());
+        assert!(queue.run_with_progress(|_| panic!()).is_empty());
+    }
+
+    #[test]
+    fn running_a_queue_saves_every_image() {
+        let mut queue = RenderQueue::new(4).with_parallel_jobs(2);
+
+        let jobs: Vec<_> = (0..3).map(|i| job(&format!("saves-{i}"))).collect();
+        for job in &jobs {
+            queue.push(job.clone());
+        }
+
+        assert_eq!(queue.len(), 3);
+
+        let updates = Mutex::new(vec![]);
+        let results = queue.run_with_progress(|progress| updates.lock().unwrap().push(progress));
+
+        for (job, result) in jobs.iter().zip(results) {
+            assert_eq!(result.unwrap().primary_rays, 16);
+            assert!(job.output.exists());
+
+            std::fs::remove_file(&job.output).unwrap();
+        }
+
+        let mut updates = updates.into_inner().unwrap();
+        updates.sort_by_key(|progress| progress.completed_jobs);
+
+        assert_eq!(updates.len(), 3);
+        assert_eq!(updates[2].completed_jobs, 3);
+        assert_eq!(updates[2].total_jobs, 3);
+        assert_eq!(updates[2].stats.primary_rays, 3 * 16);
+    }
+
+    #[test]
+    fn a_failed_job_doesnt_stop_the_queue() {
+        let mut queue = RenderQueue::new(1);
+
+        let mut failing = job("fails");
+        failing.output = std::env::temp_dir()
+            .join("raytracer-queue-missing-directory")
+            .join("image.png");
+
+        let succeeding = job("succeeds");
+
+        queue.push(failing);
+        queue.push(succeeding.clone());
+
+        let results = queue.run();
+
+        assert!(matches!(results[0], Err(Error::Save { .. })));
+        assert!(results[1].is_ok());
+
+        std::fs::remove_file(&succeeding.output).unwrap();
+    }
+
+    #[test]
+    fn saving_the_tile_heatmap_of_a_job() {
+        let heatmap = std::env::temp_dir().join("raytracer-queue-heatmap-tiles.png");
+        let job = job("heatmap")
+            .with_tile_heatmap(&heatmap)
+            .with_settings(RenderSettings {
+                tile_size: 2,
+                ..Default::default()
+            });
+
+        let mut queue = RenderQueue::new(1);
+        queue.push(job.clone());
+
+        assert!(queue.run()[0].is_ok());
+
+        let image = image::open(&heatmap).unwrap();
+        assert_eq!((image.width(), image.height()), (4, 4));
+
+        std::fs::remove_file(&job.output).unwrap();
+        std::fs::remove_file(&heatmap).unwrap();
+    }
+
+    #[test]
+    fn naming_the_images_of_an_exposure_bracket() {
+        let job = job("names");
+        let directory = std::env::temp_dir();
+
+        assert_eq!(
+            bracketed_output(&job.output, -2.0),
+            directory.join("raytracer-queue-names-2ev.png")
+        );
+        assert_eq!(
+            bracketed_output(&job.output, 0.0),
+            directory.join("raytracer-queue-names+0ev.png")
+        );
+        assert_eq!(
+            bracketed_output(&job.output, 0.5),
+            directory.join("raytracer-queue-names+0.5ev.png")
+        );
+    }
+
+    #[test]
+    fn a_bracketed_job_saves_an_image_for_each_exposure() {
+        let mut queue = RenderQueue::new(1);
+
+        let mut bracketed = job("bracketed");
+        bracketed.exposure_bracket = DEFAULT_EXPOSURE_BRACKET.to_vec();
+
+        queue.push(bracketed.clone());
+
+        assert!(queue.run()[0].is_ok());
+        assert!(!bracketed.output.exists());
+
+        for &stops in &bracketed.exposure_bracket {
+            let output = bracketed_output(&bracketed.output, stops);
+
+            assert!(output.exists());
+            std::fs::remove_file(output).unwrap();
+        }
+    }
+
+    #[test]
+    fn a_job_with_metadata_embeds_it_in_every_image() {
+        let mut queue = RenderQueue::new(1);
+
+        let job = job("metadata")
+            .with_exposure_bracket(vec![-1.0, 1.0])
+            .with_metadata(true);
+
+        queue.push(job.clone());
+
+        assert!(queue.run()[0].is_ok());
+
+        for &stops in &job.exposure_bracket {
+            let output = bracketed_output(&job.output, stops);
+
+            assert_eq!(
+                RenderMetadata::read(&output).unwrap(),
+                RenderMetadata::new(&job.scene.camera, 1)
+            );
+            std::fs::remove_file(output).unwrap();
+        }
+    }
+}
@@ -0,0 +1,309 @@
+use std::{
+    cell::RefCell,
+    io::{self, Write},
+};
+
+use crate::{
+    ray::Ray,
+    tuple::{Point, Tuple, Vector},
+};
+
+/// Rays casted for a set of pixels, recorded with
+/// [Camera::record_ray_paths](crate::camera::Camera::record_ray_paths) so the way light bounces
+/// around a world can be inspected in an external 3D tool.
+///
+/// Every path can be written as line segments, either in [WaveFront OBJ
+/// format](https://en.wikipedia.org/wiki/Wavefront_.obj_file) or in [PLY
+/// format](https://en.wikipedia.org/wiki/PLY_(file_format)), which also keeps the pixel and the
+/// bounce of each segment.
+///
+/// # Examples
+///
+/// Recording the paths of every 8th pixel of every 8th row.
+///
+/// ```no_run
+/// use std::{fs::File, io::BufWriter, sync::Arc};
+///
+/// use raytracer::{
+///     camera::{Camera, CameraBuilder},
+///     integrator::Whitted,
+///     render::RenderSettings,
+///     world::World,
+/// };
+///
+/// let camera = Camera::try_from(CameraBuilder {
+///     width: 64,
+///     height: 64,
+///     field_of_view: std::f64::consts::FRAC_PI_3,
+///     transform: Default::default(),
+/// }).unwrap();
+///
+/// let pixels = (0..64).step_by(8).flat_map(|y| (0..64).step_by(8).map(move |x| (x, y)));
+/// let settings = RenderSettings {
+///     integrator: Arc::new(Whitted {
+///         record_ray_paths: true,
+///         ..Default::default()
+///     }),
+///     ..Default::default()
+/// };
+///
+/// let paths = camera.record_ray_paths(&World::default(), &settings, pixels);
+///
+/// let file = BufWriter::new(File::create("rays.obj").unwrap());
+/// paths.write_obj(file, 10.0).unwrap();
+/// ```
+///
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RayPaths {
+    /// Recorded paths, in the same order as their pixels were given.
+    pub paths: Vec<RayPath>,
+}
+
+/// Rays casted to compute the color of a single pixel.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RayPath {
+    /// Column and row of the pixel.
+    pub pixel: (usize, usize),
+
+    /// Every ray casted for the pixel, in the order they were traced. Shadow rays are not
+    /// recorded.
+    pub segments: Vec<RaySegment>,
+}
+
+/// Single ray of a path.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct RaySegment {
+    /// Point where the ray starts.
+    pub origin: Point,
+
+    /// Direction of the ray.
+    pub direction: Vector,
+
+    /// Point where the ray hits a surface, or `None` if it leaves the world.
+    pub hit: Option<Point>,
+
+    /// Number of bounces before this ray, which is `0` for the ray casted from the camera.
+    pub bounce: u8,
+}
+
+thread_local! {
+    // Segments are only recorded while a path is being recorded in the current thread, so tracing
+    // rays while rendering an image doesn't keep them.
+    static RECORDING: RefCell<Option<Vec<RaySegment>>> = const { RefCell::new(None) };
+}
+
+impl RaySegment {
+    /// Point where the segment ends, which is it's hit, or a point `miss_length` units along it's
+    /// direction for rays that don't hit anything.
+    pub fn end(&self, miss_length: f64) -> Point {
+        self.hit.unwrap_or_else(|| {
+            // Only rays with a direction can be traced.
+            #[allow(clippy::unwrap_used)]
+            let direction = self.direction.normalize().unwrap();
+            self.origin + direction * miss_length
+        })
+    }
+}
+
+impl RayPaths {
+    /// Writes every segment as a line in OBJ format, with each path as a separate object named
+    /// after it's pixel. Rays that don't hit anything are `miss_length` units long.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the paths could not be written to `writer`.
+    ///
+    pub fn write_obj<W: Write>(&self, mut writer: W, miss_length: f64) -> io::Result<()> {
+        let mut vertex_count = 0;
+
+        for path in &self.paths {
+            let (x, y) = path.pixel;
+            writeln!(writer, "o pixel_{x}_{y}")?;
+
+            for segment in &path.segments {
+                for Point(Tuple { x, y, z, .. }) in [segment.origin, segment.end(miss_length)] {
+                    writeln!(writer, "v {x} {y} {z}")?;
+                }
+            }
+
+            // OBJ vertex indices are 1-indexed and global to the whole file.
+            for _ in &path.segments {
+                writeln!(writer, "l {} {}", vertex_count + 1, vertex_count + 2)?;
+                vertex_count += 2;
+            }
+        }
+
+        writer.flush()
+    }
+
+    /// Writes every segment as an edge in ASCII PLY format. Each edge keeps the pixel and the
+    /// bounce of it's segment, and whether it hit a surface, so they can be filtered or colored
+    /// by them. Rays that don't hit anything are `miss_length` units long.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the paths could not be written to `writer`.
+    ///
+    pub fn write_ply<W: Write>(&self, mut writer: W, miss_length: f64) -> io::Result<()> {
+        let segments = self.segments();
+        let count = segments.clone().count();
+
+        writeln!(writer, "ply")?;
+        writeln!(writer, "format ascii 1.0")?;
+        writeln!(writer, "element vertex {}", count * 2)?;
+        writeln!(writer, "property double x")?;
+        writeln!(writer, "property double y")?;
+        writeln!(writer, "property double z")?;
+        writeln!(writer, "element edge {count}")?;
+        writeln!(writer, "property int vertex1")?;
+        writeln!(writer, "property int vertex2")?;
+        writeln!(writer, "property int pixel_x")?;
+        writeln!(writer, "property int pixel_y")?;
+        writeln!(writer, "property uchar bounce")?;
+        writeln!(writer, "property uchar hit")?;
+        writeln!(writer, "end_header")?;
+
+        for (_, segment) in segments.clone() {
+            for Point(Tuple { x, y, z, .. }) in [segment.origin, segment.end(miss_length)] {
+                writeln!(writer, "{x} {y} {z}")?;
+            }
+        }
+
+        for (i, ((x, y), segment)) in segments.enumerate() {
+            writeln!(
+                writer,
+                "{} {} {x} {y} {} {}",
+                i * 2,
+                i * 2 + 1,
+                segment.bounce,
+                u8::from(segment.hit.is_some())
+            )?;
+        }
+
+        writer.flush()
+    }
+
+    /// Every segment of every path alongside the pixel of it's path.
+    fn segments(&self) -> impl Iterator<Item = ((usize, usize), &RaySegment)> + Clone {
+        self.paths.iter().flat_map(|path| {
+            path.segments
+                .iter()
+                .map(move |segment| (path.pixel, segment))
+        })
+    }
+}
+
+/// Records the rays traced in the current thread while `trace` runs.
+pub(crate) fn record_path<F>(trace: F) -> Vec<RaySegment>
+where
+    F: FnOnce(),
+{
+    let previous = RECORDING.with(|recording| recording.replace(Some(vec![])));
+    trace();
+    RECORDING
+        .with(|recording| recording.replace(previous))
+        .unwrap_or_default()
+}
+
+/// Adds a traced ray to the path being recorded in the current thread, if any.
+pub(crate) fn record_segment(ray: &Ray, hit: Option<f64>, bounce: u8) {
+    RECORDING.with(|recording| {
+        if let Some(segments) = recording.borrow_mut().as_mut() {
+            segments.push(RaySegment {
+                origin: ray.origin,
+                direction: ray.direction,
+                hit: hit.map(|t| ray.position(t)),
+                bounce,
+            });
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn paths() -> RayPaths {
+        RayPaths {
+            paths: vec![RayPath {
+                pixel: (3, 4),
+                segments: vec![
+                    RaySegment {
+                        origin: Point::new(0.0, 0.0, -5.0),
+                        direction: Vector::new(0.0, 0.0, 1.0),
+                        hit: Some(Point::new(0.0, 0.0, -1.0)),
+                        bounce: 0,
+                    },
+                    RaySegment {
+                        origin: Point::new(0.0, 0.0, -1.0),
+                        direction: Vector::new(0.0, 0.0, -2.0),
+                        hit: None,
+                        bounce: 1,
+                    },
+                ],
+            }],
+        }
+    }
+
+    #[test]
+    fn segments_are_only_recorded_while_recording_a_path() {
+        let ray = Ray {
+            origin: Point::new(0.0, 0.0, 0.0),
+            direction: Vector::new(1.0, 0.0, 0.0),
+        };
+
+        record_segment(&ray, Some(1.0), 0);
+
+        let segments = record_path(|| record_segment(&ray, Some(2.0), 1));
+
+        assert_eq!(
+            segments,
+            vec![RaySegment {
+                origin: ray.origin,
+                direction: ray.direction,
+                hit: Some(Point::new(2.0, 0.0, 0.0)),
+                bounce: 1,
+            }]
+        );
+
+        assert!(record_path(|| {}).is_empty());
+    }
+
+    #[test]
+    fn writing_ray_paths_in_obj_format() {
+        let mut obj = vec![];
+        paths().write_obj(&mut obj, 10.0).unwrap();
+
+        assert_eq!(
+            String::from_utf8(obj).unwrap(),
+            "o pixel_3_4\n\
+             v 0 0 -5\n\
+             v 0 0 -1\n\
+             v 0 0 -1\n\
+             v 0 0 -11\n\
+             l 1 2\n\
+             l 3 4\n"
+        );
+    }
+
+    #[test]
+    fn writing_ray_paths_in_ply_format() {
+        let mut ply = vec![];
+        paths().write_ply(&mut ply, 1.0).unwrap();
+
+        let ply = String::from_utf8(ply).unwrap();
+        let (header, body) = ply.split_once("end_header\n").unwrap();
+
+        assert!(header.contains("element vertex 4\n"));
+        assert!(header.contains("element edge 2\n"));
+        assert_eq!(
+            body,
+            "0 0 -5\n\
+             0 0 -1\n\
+             0 0 -1\n\
+             0 0 -2\n\
+             0 1 3 4 0 1\n\
+             2 3 3 4 1 0\n"
+        );
+    }
+}
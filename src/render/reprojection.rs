@@ -0,0 +1,240 @@
+use crate::{camera::Camera, color::Color, shape::ShapeId, tuple::Point};
+
+/// Default tolerance used to decide if a point is still visible from the previous frame, as a
+/// fraction of it's distance to the camera.
+const DEFAULT_DEPTH_TOLERANCE: f64 = 0.01;
+
+/// Results of the previous frame of an animation, used to reuse the colors of the surfaces that
+/// are still visible in the next frame.
+///
+/// When rendering with [Camera::render_reprojected], the primary hit of every pixel is projected
+/// into the image of the previous frame. If that pixel saw the same object at the same distance,
+/// the point was visible in both frames and it's previous color is reused. Only the pixels that
+/// were occluded or outside of the previous image are shaded again.
+///
+/// Reusing colors assumes that the world doesn't change between frames and that surfaces look
+/// the same from every direction, so reflections and refractions lag behind the camera. The cache
+/// must be [cleared](ReprojectionCache::clear) when the world changes.
+///
+/// # Examples
+///
+/// ```
+/// use raytracer::{
+///     camera::{Camera, CameraBuilder},
+///     render::{RenderSettings, ReprojectionCache},
+///     shape::Shape,
+///     transform::Transform,
+///     tuple::{Point, Vector},
+///     world::World,
+/// };
+///
+/// let world = World {
+///     objects: vec![Shape::Sphere(Default::default())],
+///     lights: vec![],
+///     ..Default::default()
+/// };
+///
+/// let mut cache = ReprojectionCache::new(16, 16);
+///
+/// for frame in 0..3 {
+///     let camera = Camera::try_from(CameraBuilder {
+///         width: 16,
+///         height: 16,
+///         field_of_view: std::f64::consts::FRAC_PI_3,
+///         transform: Transform::view(
+///             Point::new(frame as f64 * 0.1, 0.0, -5.0),
+///             Point::new(0.0, 0.0, 0.0),
+///             Vector::new(0.0, 1.0, 0.0),
+///         )
+///         .unwrap(),
+///     })
+///     .unwrap();
+///
+///     let (image, stats) =
+///         camera.render_reprojected(&world, &RenderSettings::default(), &mut cache);
+/// }
+/// ```
+///
+#[derive(Clone, Debug)]
+pub struct ReprojectionCache {
+    width: usize,
+    height: usize,
+    depth_tolerance: f64,
+    camera: Option<Camera>,
+    pixels: Vec<Option<ReprojectedPixel>>,
+    reused_pixels: usize,
+}
+
+/// Primary hit of a pixel in the previous frame.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub(crate) struct ReprojectedPixel {
+    pub(crate) object: ShapeId,
+    pub(crate) point: Point,
+    pub(crate) color: Color,
+}
+
+impl ReprojectionCache {
+    /// Constructs an empty cache for an animation with frames of the given dimensions.
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            depth_tolerance: DEFAULT_DEPTH_TOLERANCE,
+            camera: None,
+            pixels: vec![None; width * height],
+            reused_pixels: 0,
+        }
+    }
+
+    /// Sets how much the distance from the previous camera to a point can differ from the
+    /// distance it saw in that direction, as a fraction of that distance, for the point to still
+    /// count as visible. The default tolerance is `0.01`.
+    pub fn with_depth_tolerance(mut self, tolerance: f64) -> Self {
+        self.depth_tolerance = tolerance;
+        self
+    }
+
+    /// Width of the frames in number of pixels.
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Height of the frames in number of pixels.
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Number of pixels of the last rendered frame whose color was reused from the frame before
+    /// it.
+    pub fn reused_pixels(&self) -> usize {
+        self.reused_pixels
+    }
+
+    /// Forgets the previous frame, so every pixel of the next frame is shaded again.
+    pub fn clear(&mut self) {
+        self.camera = None;
+        self.pixels.fill(None);
+        self.reused_pixels = 0;
+    }
+
+    /// Returns the color that the previous frame computed for the given point of an object, if
+    /// that point was visible in the previous frame.
+    pub(crate) fn lookup(&self, object: ShapeId, point: Point) -> Option<Color> {
+        let camera = self.camera?;
+        let (x, y) = camera.project(point)?;
+
+        let previous = self.pixels[y * self.width + x]?;
+
+        if previous.object != object {
+            return None;
+        }
+
+        let origin = camera.origin();
+        let depth = (point - origin).magnitude();
+        let previous_depth = (previous.point - origin).magnitude();
+
+        ((depth - previous_depth).abs() <= self.depth_tolerance * previous_depth)
+            .then_some(previous.color)
+    }
+
+    /// Replaces the previous frame with the one that was just rendered.
+    pub(crate) fn update(
+        &mut self,
+        camera: Camera,
+        pixels: Vec<Option<ReprojectedPixel>>,
+        reused_pixels: usize,
+    ) {
+        self.camera = Some(camera);
+        self.pixels = pixels;
+        self.reused_pixels = reused_pixels;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        camera::CameraBuilder,
+        color,
+        shape::Shape,
+        transform::Transform,
+        tuple::{Point, Vector},
+    };
+
+    use super::*;
+
+    fn camera() -> Camera {
+        Camera::try_from(CameraBuilder {
+            width: 11,
+            height: 11,
+            field_of_view: std::f64::consts::FRAC_PI_2,
+            transform: Transform::view(
+                Point::new(0.0, 0.0, -5.0),
+                Point::new(0.0, 0.0, 0.0),
+                Vector::new(0.0, 1.0, 0.0),
+            )
+            .unwrap(),
+        })
+        .unwrap()
+    }
+
+    fn cache_with_center(object: ShapeId, point: Point) -> ReprojectionCache {
+        let mut cache = ReprojectionCache::new(11, 11);
+        let mut pixels = vec![None; 11 * 11];
+
+        pixels[5 * 11 + 5] = Some(ReprojectedPixel {
+            object,
+            point,
+            color: color::consts::WHITE,
+        });
+
+        cache.update(camera(), pixels, 0);
+        cache
+    }
+
+    #[test]
+    fn an_empty_cache_has_nothing_to_reuse() {
+        let cache = ReprojectionCache::new(11, 11);
+        let id = Shape::Sphere(Default::default()).id();
+
+        assert_eq!(cache.lookup(id, Point::new(0.0, 0.0, -1.0)), None);
+    }
+
+    #[test]
+    fn reusing_the_color_of_a_point_visible_in_the_previous_frame() {
+        let id = Shape::Sphere(Default::default()).id();
+        let cache = cache_with_center(id, Point::new(0.0, 0.0, -1.0));
+
+        assert_eq!(
+            cache.lookup(id, Point::new(0.0, 0.0, -1.0)),
+            Some(color::consts::WHITE)
+        );
+    }
+
+    #[test]
+    fn points_of_other_objects_are_not_reused() {
+        let id = Shape::Sphere(Default::default()).id();
+        let other = Shape::Sphere(Default::default()).id();
+        let cache = cache_with_center(id, Point::new(0.0, 0.0, -1.0));
+
+        assert_eq!(cache.lookup(other, Point::new(0.0, 0.0, -1.0)), None);
+    }
+
+    #[test]
+    fn occluded_points_are_not_reused() {
+        let id = Shape::Sphere(Default::default()).id();
+        let cache = cache_with_center(id, Point::new(0.0, 0.0, -1.0));
+
+        // The point is behind the one the previous frame saw in the same direction.
+        assert_eq!(cache.lookup(id, Point::new(0.0, 0.0, 1.0)), None);
+    }
+
+    #[test]
+    fn clearing_the_cache() {
+        let id = Shape::Sphere(Default::default()).id();
+        let mut cache = cache_with_center(id, Point::new(0.0, 0.0, -1.0));
+
+        cache.clear();
+
+        assert_eq!(cache.lookup(id, Point::new(0.0, 0.0, -1.0)), None);
+    }
+}
@@ -0,0 +1,212 @@
+use crate::{camera::Camera, canvas::Canvas, render::RenderSettings, world::World};
+
+/// Default number of pixels between the images of a [ParameterSweep].
+const DEFAULT_SPACING: usize = 2;
+
+/// Grid of small renders of a world that changes along one or two parameters, such as the
+/// shininess and the index of refraction of a material, tiled into a single contact sheet.
+///
+/// Every value of the columns is combined with every value of the rows, and the image of each
+/// combination is rendered with the same camera. A sweep without rows has a single row.
+///
+/// # Examples
+///
+/// Comparing glass spheres with different shininess and indices of refraction.
+///
+/// ```
+/// use raytracer::{
+///     camera::{Camera, CameraBuilder},
+///     material::Material,
+///     render::{ParameterSweep, RenderSettings},
+///     shape::{Shape, ShapeBuilder, Sphere},
+///     transform::Transform,
+///     tuple::{Point, Vector},
+///     world::World,
+/// };
+///
+/// let camera = Camera::try_from(CameraBuilder {
+///     width: 16,
+///     height: 16,
+///     field_of_view: std::f64::consts::FRAC_PI_3,
+///     transform: Transform::view(
+///         Point::new(0.0, 0.0, -5.0),
+///         Point::new(0.0, 0.0, 0.0),
+///         Vector::new(0.0, 1.0, 0.0),
+///     )
+///     .unwrap(),
+/// })
+/// .unwrap();
+///
+/// let sweep = ParameterSweep::new(vec![10.0, 100.0, 300.0]).with_rows(vec![1.33, 1.5, 2.4]);
+///
+/// let sheet = sweep.render(&camera, &RenderSettings::default(), |&shininess, &index| World {
+///     objects: vec![Shape::Sphere(Sphere::from(ShapeBuilder {
+///         material: Material {
+///             shininess,
+///             index_of_refraction: index,
+///             transparency: 0.9,
+///             ..Default::default()
+///         },
+///         ..Default::default()
+///     }))],
+///     ..Default::default()
+/// });
+///
+/// assert_eq!(sheet.to_image().dimensions(), (52, 52));
+/// ```
+///
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParameterSweep<C, R = ()> {
+    columns: Vec<C>,
+    rows: Vec<R>,
+    spacing: usize,
+}
+
+impl<C> ParameterSweep<C> {
+    /// Constructs a sweep of a single row, with an image for each value of `columns`.
+    pub fn new(columns: Vec<C>) -> Self {
+        Self {
+            columns,
+            rows: vec![()],
+            spacing: DEFAULT_SPACING,
+        }
+    }
+}
+
+impl<C, R> ParameterSweep<C, R> {
+    /// Sweeps a second parameter, with a row of images for each value of `rows`.
+    pub fn with_rows<T>(self, rows: Vec<T>) -> ParameterSweep<C, T> {
+        ParameterSweep {
+            columns: self.columns,
+            rows,
+            spacing: self.spacing,
+        }
+    }
+
+    /// Changes the number of black pixels between the images of the sheet.
+    pub fn with_spacing(self, spacing: usize) -> Self {
+        Self { spacing, ..self }
+    }
+
+    /// Renders the world built by `world` for each combination of a column and a row value, and
+    /// tiles the images into a contact sheet, with the columns from left to right and the rows
+    /// from top to bottom.
+    pub fn render<F>(&self, camera: &Camera, settings: &RenderSettings, world: F) -> Canvas
+    where
+        F: Fn(&C, &R) -> World,
+    {
+        let (width, height) = (camera.width(), camera.height());
+        let step =
+            |count: usize, size: usize| count * size + count.saturating_sub(1) * self.spacing;
+
+        let mut sheet = Canvas::new(
+            step(self.columns.len(), width),
+            step(self.rows.len(), height),
+        );
+
+        for (row, row_value) in self.rows.iter().enumerate() {
+            for (column, column_value) in self.columns.iter().enumerate() {
+                let image = camera.render_with_settings(&world(column_value, row_value), settings);
+
+                let left = column * (width + self.spacing);
+                let top = row * (height + self.spacing);
+
+                for y in 0..height {
+                    for x in 0..width {
+                        sheet.write_pixel(left + x, top + y, *image.pixel_at(x, y));
+                    }
+                }
+            }
+        }
+
+        sheet
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::FRAC_PI_2;
+
+    use crate::{
+        assert_approx,
+        camera::CameraBuilder,
+        color::{self, Color},
+        light::{Light, PointLight},
+        material::Material,
+        shape::{Plane, Shape, ShapeBuilder},
+        transform::Transform,
+        tuple::Point,
+    };
+
+    use super::*;
+
+    fn light(intensity: f64) -> Light {
+        Light::Point(PointLight {
+            position: Point::new(0.0, 0.0, -10.0),
+            intensity: Color {
+                red: intensity,
+                green: intensity,
+                blue: intensity,
+            },
+            group: None,
+        })
+    }
+
+    fn camera() -> Camera {
+        Camera::try_from(CameraBuilder {
+            width: 3,
+            height: 2,
+            field_of_view: FRAC_PI_2,
+            transform: Default::default(),
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn tiling_a_sweep_of_two_parameters() {
+        let sweep = ParameterSweep::new(vec![1.0, 2.0, 3.0])
+            .with_rows(vec![0.1, 0.2])
+            .with_spacing(1);
+
+        // A world without objects is black everywhere, so the images are told apart by the
+        // ambient light of a plane that fills the view.
+        let sheet = sweep.render(&camera(), &RenderSettings::default(), |&a, &b| World {
+            objects: vec![Shape::Plane(Plane::from(ShapeBuilder {
+                material: Material {
+                    ambient: 1.0,
+                    diffuse: 0.0,
+                    specular: 0.0,
+                    ..Default::default()
+                },
+                transform: Transform::translation(0.0, 0.0, -1.0)
+                    * Transform::rotation_x(FRAC_PI_2),
+            }))],
+            lights: vec![light(a * b)],
+            ..Default::default()
+        });
+
+        assert_eq!((sheet.width, sheet.height), (11, 5));
+
+        for (row, b) in [0.1, 0.2].into_iter().enumerate() {
+            for (column, a) in [1.0, 2.0, 3.0].into_iter().enumerate() {
+                let pixel = sheet.pixel_at(column * 4 + 1, row * 3 + 1);
+                assert_approx!(pixel.red, a * b);
+            }
+        }
+
+        // The spacing between the images is black.
+        assert_eq!(sheet.pixel_at(3, 0), &color::consts::BLACK);
+        assert_eq!(sheet.pixel_at(0, 2), &color::consts::BLACK);
+    }
+
+    #[test]
+    fn a_sweep_without_rows_has_a_single_row() {
+        let sweep = ParameterSweep::new(vec![1.0, 2.0]);
+
+        let sheet = sweep.render(&camera(), &RenderSettings::default(), |_, ()| {
+            World::default()
+        });
+
+        assert_eq!((sheet.width, sheet.height), (3 * 2 + DEFAULT_SPACING, 2));
+    }
+}
@@ -0,0 +1,180 @@
+use std::time::Duration;
+
+use crate::{canvas::Canvas, pattern::ColorRamp};
+
+use super::Tile;
+
+/// Time that each tile of an image took to render.
+///
+/// Tiles that take the longest are the ones that see the most expensive objects and materials of
+/// a scene, so a [heatmap](TileTimes::to_heatmap) of the times points out what to optimize first
+/// without profiling the renderer. Times are gathered by
+/// [Camera::render_with_tile_times](crate::camera::Camera::render_with_tile_times), and can be
+/// saved next to the image of a [RenderJob](super::RenderJob) with it's
+/// [tile_heatmap](super::RenderJob::tile_heatmap).
+///
+/// # Examples
+///
+/// ```
+/// use raytracer::{
+///     camera::{Camera, CameraBuilder},
+///     render::RenderSettings,
+///     world::World,
+/// };
+///
+/// let camera = Camera::try_from(CameraBuilder {
+///     width: 32,
+///     height: 32,
+///     field_of_view: std::f64::consts::FRAC_PI_3,
+///     transform: Default::default(),
+/// }).unwrap();
+///
+/// let settings = RenderSettings {
+///     tile_size: 8,
+///     ..Default::default()
+/// };
+///
+/// let (_, _, times) = camera.render_with_tile_times(&World::default(), &settings);
+///
+/// assert_eq!(times.tiles().len(), 16);
+///
+/// let heatmap = times.to_heatmap();
+/// ```
+///
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TileTimes {
+    width: usize,
+    height: usize,
+    tiles: Vec<(Tile, Duration)>,
+}
+
+impl TileTimes {
+    /// Constructs an empty record of the tiles of an image with the given dimensions.
+    pub(crate) fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            tiles: vec![],
+        }
+    }
+
+    /// Records the time that a tile took to render.
+    pub(crate) fn record(&mut self, tile: Tile, time: Duration) {
+        self.tiles.push((tile, time));
+    }
+
+    /// Tiles of the image with the time each of them took to render, in the order they were
+    /// done. A render made of more than one pass records the tiles of every pass, so they can
+    /// overlap.
+    pub fn tiles(&self) -> &[(Tile, Duration)] {
+        &self.tiles
+    }
+
+    /// Total time spent rendering tiles, across every thread.
+    pub fn total(&self) -> Duration {
+        self.tiles.iter().map(|(_, time)| *time).sum()
+    }
+
+    /// Tile that took the longest to render, if any.
+    pub fn slowest(&self) -> Option<(Tile, Duration)> {
+        self.tiles.iter().copied().max_by_key(|(_, time)| *time)
+    }
+
+    /// Image with the same dimensions as the rendered one, where every pixel is colored with the
+    /// [heat](ColorRamp::heat) ramp by the time spent rendering it.
+    ///
+    /// The time of each tile is spread evenly over it's pixels, so the smaller tiles at the edges
+    /// of an image don't look cheaper than they are, and the times of overlapping tiles add up.
+    /// The most expensive pixels are white, and pixels that took no time at all are black.
+    ///
+    pub fn to_heatmap(&self) -> Canvas {
+        let mut costs = vec![0.0; self.width * self.height];
+
+        for (tile, time) in &self.tiles {
+            let cost = time.as_secs_f64() / (tile.width * tile.height).max(1) as f64;
+
+            for (x, y) in tile.pixels() {
+                if x < self.width && y < self.height {
+                    costs[y * self.width + x] += cost;
+                }
+            }
+        }
+
+        let max = costs.iter().copied().fold(0.0, f64::max);
+        let ramp = ColorRamp::heat();
+
+        let mut image = Canvas::new(self.width, self.height);
+
+        for (i, cost) in costs.into_iter().enumerate() {
+            let position = if max > 0.0 { cost / max } else { 0.0 };
+            image.write_pixel(i % self.width, i / self.width, ramp.color_at(position));
+        }
+
+        image
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::color;
+
+    use super::*;
+
+    fn tile(x: usize, y: usize, width: usize, height: usize) -> Tile {
+        Tile {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    #[test]
+    fn the_slowest_tile_of_a_render() {
+        let mut times = TileTimes::new(4, 2);
+        times.record(tile(0, 0, 2, 2), Duration::from_millis(3));
+        times.record(tile(2, 0, 2, 2), Duration::from_millis(5));
+
+        assert_eq!(times.total(), Duration::from_millis(8));
+        assert_eq!(
+            times.slowest(),
+            Some((tile(2, 0, 2, 2), Duration::from_millis(5)))
+        );
+        assert_eq!(TileTimes::new(4, 2).slowest(), None);
+    }
+
+    #[test]
+    fn the_heatmap_of_the_times_of_the_tiles() {
+        let mut times = TileTimes::new(3, 2);
+        times.record(tile(0, 0, 2, 2), Duration::from_millis(8));
+        times.record(tile(2, 0, 1, 2), Duration::from_millis(2));
+
+        let heatmap = times.to_heatmap();
+
+        assert_eq!((heatmap.width, heatmap.height), (3, 2));
+
+        // The right tile has half of the pixels of the left one, and a quarter of it's time.
+        assert_eq!(heatmap.pixel_at(0, 0), &color::consts::WHITE);
+        assert_eq!(heatmap.pixel_at(1, 1), &color::consts::WHITE);
+        assert_eq!(heatmap.pixel_at(2, 1), &ColorRamp::heat().color_at(0.5));
+    }
+
+    #[test]
+    fn the_times_of_overlapping_tiles_add_up() {
+        let mut times = TileTimes::new(2, 1);
+        times.record(tile(0, 0, 2, 1), Duration::from_millis(2));
+        times.record(tile(1, 0, 1, 1), Duration::from_millis(1));
+
+        let heatmap = times.to_heatmap();
+
+        assert_eq!(heatmap.pixel_at(0, 0), &ColorRamp::heat().color_at(0.5));
+        assert_eq!(heatmap.pixel_at(1, 0), &color::consts::WHITE);
+    }
+
+    #[test]
+    fn the_heatmap_of_a_render_that_took_no_time() {
+        let times = TileTimes::new(2, 2);
+
+        assert_eq!(times.to_heatmap().pixel_at(1, 1), &color::consts::BLACK);
+    }
+}
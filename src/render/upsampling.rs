@@ -0,0 +1,202 @@
+use crate::{
+    color::{self, Color},
+    tuple::Vector,
+};
+
+/// Relative difference in depth at which a low resolution sample stops contributing to a pixel.
+const DEPTH_SIGMA: f64 = 0.1;
+
+/// Sharpness of the falloff of the contribution of a low resolution sample as it's normal
+/// diverges from the normal of the pixel.
+const NORMAL_EXPONENT: i32 = 8;
+
+/// Depth and normal of the surface seen by a pixel, used to tell apart the pixels that see
+/// different surfaces when upsampling an image.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub(crate) struct SurfaceSample {
+    pub(crate) depth: f64,
+    pub(crate) normal: Vector,
+}
+
+/// Image rendered at half the resolution of the final image, alongside the surfaces seen by each
+/// of it's pixels.
+#[derive(Clone, Debug)]
+pub(crate) struct HalfResolutionBuffer {
+    width: usize,
+    height: usize,
+    pixels: Vec<(Color, Option<SurfaceSample>)>,
+}
+
+impl HalfResolutionBuffer {
+    /// Constructs an empty buffer to upsample an image of the given full resolution dimensions.
+    pub(crate) fn new(width: usize, height: usize) -> Self {
+        let width = width.div_ceil(2);
+        let height = height.div_ceil(2);
+
+        Self {
+            width,
+            height,
+            pixels: vec![(color::consts::BLACK, None); width * height],
+        }
+    }
+
+    /// Width of the buffer in number of pixels.
+    pub(crate) fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Height of the buffer in number of pixels.
+    pub(crate) fn height(&self) -> usize {
+        self.height
+    }
+
+    pub(crate) fn write_pixel(
+        &mut self,
+        x: usize,
+        y: usize,
+        color: Color,
+        surface: Option<SurfaceSample>,
+    ) {
+        self.pixels[y * self.width + x] = (color, surface);
+    }
+
+    /// Computes the color of the full resolution pixel at column `x` and row `y` with a
+    /// [joint bilateral filter](https://en.wikipedia.org/wiki/Bilateral_filter), which blends the
+    /// closest low resolution pixels that see the same surface as the full resolution one, so
+    /// the colors of different surfaces don't bleed into each other.
+    pub(crate) fn upsample(&self, x: usize, y: usize, surface: Option<SurfaceSample>) -> Color {
+        // Position of the center of the pixel in the coordinates of the low resolution pixels.
+        let u = ((x as f64 + 0.5) / 2.0 - 0.5).max(0.0);
+        let v = ((y as f64 + 0.5) / 2.0 - 0.5).max(0.0);
+
+        let (x0, y0) = (u as usize, v as usize);
+        let (fx, fy) = (u.fract(), v.fract());
+
+        let neighbors = [
+            (x0, y0, (1.0 - fx) * (1.0 - fy)),
+            (x0 + 1, y0, fx * (1.0 - fy)),
+            (x0, y0 + 1, (1.0 - fx) * fy),
+            (x0 + 1, y0 + 1, fx * fy),
+        ];
+
+        let mut sum = color::consts::BLACK;
+        let mut total_weight = 0.0;
+
+        let mut nearest = None;
+        let mut nearest_weight = f64::NEG_INFINITY;
+
+        for (nx, ny, bilinear) in neighbors {
+            let nx = nx.min(self.width - 1);
+            let ny = ny.min(self.height - 1);
+
+            let (color, neighbor_surface) = self.pixels[ny * self.width + nx];
+
+            let weight = bilinear * similarity(surface, neighbor_surface);
+
+            sum = sum + color * weight;
+            total_weight += weight;
+
+            if bilinear > nearest_weight {
+                nearest = Some(color);
+                nearest_weight = bilinear;
+            }
+        }
+
+        if total_weight > f64::EPSILON {
+            sum * (1.0 / total_weight)
+        } else {
+            // None of the neighbors see the same surface, so the closest one is the best guess.
+            nearest.unwrap_or(color::consts::BLACK)
+        }
+    }
+}
+
+/// How likely it is that two pixels see the same surface, from `0.0` to `1.0`.
+fn similarity(a: Option<SurfaceSample>, b: Option<SurfaceSample>) -> f64 {
+    match (a, b) {
+        (None, None) => 1.0,
+        (Some(a), Some(b)) => {
+            let depth_difference = (a.depth - b.depth) / (DEPTH_SIGMA * a.depth.max(f64::EPSILON));
+            let depth_weight = (-depth_difference.powi(2)).exp();
+            let normal_weight = a.normal.dot(b.normal).max(0.0).powi(NORMAL_EXPONENT);
+
+            depth_weight * normal_weight
+        }
+        _ => 0.0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::assert_approx;
+
+    use super::*;
+
+    fn surface(depth: f64) -> Option<SurfaceSample> {
+        Some(SurfaceSample {
+            depth,
+            normal: Vector::new(0.0, 0.0, -1.0),
+        })
+    }
+
+    #[test]
+    fn a_half_resolution_buffer_covers_the_whole_image() {
+        let buffer = HalfResolutionBuffer::new(5, 4);
+
+        assert_eq!(buffer.width(), 3);
+        assert_eq!(buffer.height(), 2);
+    }
+
+    #[test]
+    fn upsampling_a_flat_image() {
+        let mut buffer = HalfResolutionBuffer::new(4, 4);
+
+        for y in 0..2 {
+            for x in 0..2 {
+                buffer.write_pixel(x, y, color::consts::WHITE, surface(1.0));
+            }
+        }
+
+        for y in 0..4 {
+            for x in 0..4 {
+                assert_eq!(buffer.upsample(x, y, surface(1.0)), color::consts::WHITE);
+            }
+        }
+    }
+
+    #[test]
+    fn upsampling_doesnt_blend_different_surfaces() {
+        let mut buffer = HalfResolutionBuffer::new(4, 2);
+
+        buffer.write_pixel(0, 0, color::consts::WHITE, surface(1.0));
+        buffer.write_pixel(1, 0, color::consts::BLACK, surface(10.0));
+
+        // The pixels in the middle are between both low resolution pixels, but only see the
+        // surface of one of them.
+        assert_eq!(buffer.upsample(1, 0, surface(1.0)), color::consts::WHITE);
+        assert_eq!(buffer.upsample(2, 0, surface(10.0)), color::consts::BLACK);
+    }
+
+    #[test]
+    fn upsampling_a_pixel_with_no_similar_neighbors() {
+        let mut buffer = HalfResolutionBuffer::new(2, 2);
+
+        buffer.write_pixel(0, 0, color::consts::WHITE, surface(1.0));
+
+        assert_eq!(buffer.upsample(1, 1, None), color::consts::WHITE);
+    }
+
+    #[test]
+    fn surfaces_facing_different_directions_are_not_similar() {
+        let a = surface(1.0);
+        let b = Some(SurfaceSample {
+            depth: 1.0,
+            normal: Vector::new(0.0, 1.0, 0.0),
+        });
+
+        assert_approx!(similarity(a, a), 1.0);
+        assert_approx!(similarity(a, b), 0.0);
+        assert_approx!(similarity(a, None), 0.0);
+        assert_approx!(similarity(None, None), 1.0);
+    }
+}
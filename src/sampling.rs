@@ -0,0 +1,359 @@
+use rand::{rngs::ThreadRng, Rng};
+use serde::Deserialize;
+
+/// Generator of the 2-dimensional samples used to spread rays over an area, such as the area of a
+/// pixel or the cells of an area light.
+///
+/// Low-discrepancy sequences, like [Halton](Sampler::Halton) and [Sobol](Sampler::Sobol), cover
+/// the area more evenly than random samples, so images converge faster with the same number of
+/// samples.
+///
+/// # Examples
+///
+/// ```
+/// use raytracer::sampling::Sampler;
+///
+/// for [u, v] in Sampler::Halton.sequence(0).take(16) {
+///     assert!((0.0..1.0).contains(&u));
+///     assert!((0.0..1.0).contains(&v));
+/// }
+/// ```
+///
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all(deserialize = "snake_case"))]
+pub enum Sampler {
+    /// Independent random samples.
+    #[default]
+    Random,
+
+    /// The [Halton sequence](https://en.wikipedia.org/wiki/Halton_sequence) in bases 2 and 3.
+    Halton,
+
+    /// The first two dimensions of the [Sobol
+    /// sequence](https://en.wikipedia.org/wiki/Sobol_sequence).
+    Sobol,
+}
+
+/// Infinite iterator over the samples of a [Sampler], with every coordinate in the range `[0, 1)`.
+///
+/// Every sequence is shifted by an offset that wraps around the unit square, so different
+/// sequences don't produce the exact same samples while keeping their even distribution.
+///
+#[derive(Clone, Debug)]
+pub struct SampleSequence {
+    sampler: Sampler,
+    dimensions: Dimensions,
+    index: u32,
+    offset: [f64; 2],
+    rng: ThreadRng,
+}
+
+/// Pair of dimensions of a sampler that a sequence takes it's samples from.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Dimensions {
+    First,
+    Second,
+}
+
+/// Direction numbers of the third and fourth dimensions of the Sobol sequence, whose primitive
+/// polynomials are `x^2 + x + 1` and `x^3 + x + 1`.
+const SOBOL_THIRD_DIMENSION: [u32; 32] = sobol_directions(2, 1, [1, 3, 0]);
+const SOBOL_FOURTH_DIMENSION: [u32; 32] = sobol_directions(3, 1, [1, 3, 1]);
+
+impl Sampler {
+    /// Returns the sequence of samples of this sampler, starting at the sample with the given
+    /// index.
+    ///
+    /// Starting at the number of samples that were already taken makes it possible to continue a
+    /// sequence across multiple render passes.
+    ///
+    pub fn sequence(self, start: u32) -> SampleSequence {
+        self.seeded_sequence(start, rand::thread_rng().gen())
+    }
+
+    /// Returns the sequence of samples of this sampler, starting at the sample with the given
+    /// index, and shifted by an offset derived from `seed`.
+    ///
+    /// Sequences with the same seed are shifted by the same offset, so a sequence can be
+    /// continued later, such as the sequence of a pixel in the next render pass, without losing
+    /// it's even distribution.
+    ///
+    pub fn seeded_sequence(self, start: u32, seed: u64) -> SampleSequence {
+        let hash = splitmix64(seed);
+
+        // Each half of the hash gives one of the offset's coordinates.
+        let offset = [hash >> 32, hash & 0xffff_ffff].map(|half| half as f64 / 2_f64.powi(32));
+
+        SampleSequence {
+            sampler: self,
+            dimensions: Dimensions::First,
+            index: start,
+            offset,
+            rng: rand::thread_rng(),
+        }
+    }
+}
+
+impl Sampler {
+    /// Returns the sequence of samples of this sampler like [Sampler::seeded_sequence], but taken
+    /// from the next dimensions of the sampler, so they're not correlated with the samples of a
+    /// sequence of the first ones. Two sequences that only differ in their seeds are shifted
+    /// copies of each other instead.
+    pub(crate) fn decorrelated_sequence(self, start: u32, seed: u64) -> SampleSequence {
+        SampleSequence {
+            dimensions: Dimensions::Second,
+            ..self.seeded_sequence(start, seed)
+        }
+    }
+}
+
+impl Iterator for SampleSequence {
+    type Item = [f64; 2];
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let sample = match (self.sampler, self.dimensions) {
+            (Sampler::Random, _) => return Some([self.rng.gen(), self.rng.gen()]),
+            (Sampler::Halton, Dimensions::First) => [
+                radical_inverse(2, self.index),
+                radical_inverse(3, self.index),
+            ],
+            (Sampler::Halton, Dimensions::Second) => [
+                radical_inverse(5, self.index),
+                radical_inverse(7, self.index),
+            ],
+            (Sampler::Sobol, Dimensions::First) => [
+                self.index.reverse_bits() as f64 / 2_f64.powi(32),
+                sobol_second_dimension(self.index) as f64 / 2_f64.powi(32),
+            ],
+            (Sampler::Sobol, Dimensions::Second) => [
+                sobol(&SOBOL_THIRD_DIMENSION, self.index) as f64 / 2_f64.powi(32),
+                sobol(&SOBOL_FOURTH_DIMENSION, self.index) as f64 / 2_f64.powi(32),
+            ],
+        };
+
+        self.index = self.index.wrapping_add(1);
+
+        let [u, v] = sample;
+        Some([(u + self.offset[0]).fract(), (v + self.offset[1]).fract()])
+    }
+}
+
+/// Scrambles the bits of a number so that consecutive seeds give unrelated offsets.
+pub(crate) fn splitmix64(seed: u64) -> u64 {
+    let mut z = seed.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+/// Mirrors the digits of `index` in the given base around the decimal point.
+fn radical_inverse(base: u32, mut index: u32) -> f64 {
+    let inverse_base = 1.0 / base as f64;
+
+    let mut result = 0.0;
+    let mut factor = inverse_base;
+
+    while index > 0 {
+        result += (index % base) as f64 * factor;
+        index /= base;
+        factor *= inverse_base;
+    }
+
+    result
+}
+
+/// Second dimension of the Sobol sequence as a fraction of `2^32`, whose direction numbers come
+/// from the primitive polynomial `x + 1`.
+fn sobol_second_dimension(mut index: u32) -> u32 {
+    let mut result = 0;
+    let mut direction = 1 << 31;
+
+    while index > 0 {
+        if index & 1 == 1 {
+            result ^= direction;
+        }
+
+        index >>= 1;
+        direction ^= direction >> 1;
+    }
+
+    result
+}
+
+/// Dimension of the Sobol sequence with the given direction numbers as a fraction of `2^32`.
+fn sobol(directions: &[u32; 32], mut index: u32) -> u32 {
+    let mut result = 0;
+
+    for direction in directions {
+        if index == 0 {
+            break;
+        }
+
+        if index & 1 == 1 {
+            result ^= direction;
+        }
+
+        index >>= 1;
+    }
+
+    result
+}
+
+/// Direction numbers of a dimension of the Sobol sequence, from the `degree` and the inner
+/// `coefficients` of it's primitive polynomial, and it's first direction numbers as odd integers,
+/// as tabulated by [Joe and Kuo](https://web.maths.unsw.edu.au/~fkuo/sobol/).
+const fn sobol_directions(degree: usize, coefficients: u32, first: [u32; 3]) -> [u32; 32] {
+    let mut directions = [0; 32];
+    let mut k = 0;
+
+    while k < 32 {
+        directions[k] = if k < degree {
+            first[k] << (31 - k)
+        } else {
+            let mut direction = directions[k - degree] ^ (directions[k - degree] >> degree);
+            let mut j = 1;
+
+            while j < degree {
+                if (coefficients >> (degree - 1 - j)) & 1 == 1 {
+                    direction ^= directions[k - j];
+                }
+
+                j += 1;
+            }
+
+            direction
+        };
+
+        k += 1;
+    }
+
+    directions
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::assert_approx;
+
+    use super::*;
+
+    #[test]
+    fn the_radical_inverse_mirrors_the_digits_of_the_index() {
+        let base_2: Vec<_> = (0..8).map(|i| radical_inverse(2, i)).collect();
+        let base_3: Vec<_> = (0..4).map(|i| radical_inverse(3, i)).collect();
+
+        assert_eq!(base_2, [0.0, 0.5, 0.25, 0.75, 0.125, 0.625, 0.375, 0.875]);
+
+        assert_approx!(base_3[0], 0.0);
+        assert_approx!(base_3[1], 1.0 / 3.0);
+        assert_approx!(base_3[2], 2.0 / 3.0);
+        assert_approx!(base_3[3], 1.0 / 9.0);
+    }
+
+    #[test]
+    fn the_second_dimension_of_the_sobol_sequence() {
+        let samples: Vec<_> = (0..8)
+            .map(|i| sobol_second_dimension(i) as f64 / 2_f64.powi(32))
+            .collect();
+
+        assert_eq!(samples, [0.0, 0.5, 0.75, 0.25, 0.625, 0.125, 0.375, 0.875]);
+    }
+
+    #[test]
+    fn the_direction_numbers_of_the_second_dimension_of_the_sobol_sequence() {
+        let directions = sobol_directions(1, 0, [1, 0, 0]);
+
+        for i in 0..64 {
+            assert_eq!(sobol(&directions, i), sobol_second_dimension(i));
+        }
+    }
+
+    #[test]
+    fn decorrelated_samples_cover_every_stratum() {
+        for sampler in [Sampler::Halton, Sampler::Sobol] {
+            let mut strata = [[false; 16]; 2];
+
+            let sequence = SampleSequence {
+                offset: [0.0, 0.0],
+                ..sampler.decorrelated_sequence(0, 0)
+            };
+
+            // The first 49 Halton samples, in bases 5 and 7, and the first 16 Sobol samples cover
+            // every sixteenth of the unit interval in both dimensions.
+            for [u, v] in sequence.take(49) {
+                strata[0][(u * 16.0) as usize] = true;
+                strata[1][(v * 16.0) as usize] = true;
+            }
+
+            assert!(strata.iter().flatten().all(|&covered| covered));
+        }
+    }
+
+    #[test]
+    fn decorrelated_samples_are_not_shifted_copies_of_the_first_ones() {
+        for sampler in [Sampler::Halton, Sampler::Sobol] {
+            let first: Vec<_> = sampler.seeded_sequence(0, 7).take(8).collect();
+            let second: Vec<_> = sampler.decorrelated_sequence(0, 7).take(8).collect();
+
+            let shift =
+                |i: usize| [0, 1].map(|axis| (second[i][axis] - first[i][axis]).rem_euclid(1.0));
+
+            assert!((1..8).any(|i| shift(i) != shift(0)));
+        }
+    }
+
+    #[test]
+    fn every_sampler_produces_samples_in_the_unit_square() {
+        for sampler in [Sampler::Random, Sampler::Halton, Sampler::Sobol] {
+            for [u, v] in sampler.sequence(0).take(256) {
+                assert!((0.0..1.0).contains(&u));
+                assert!((0.0..1.0).contains(&v));
+            }
+        }
+    }
+
+    #[test]
+    fn low_discrepancy_samples_cover_every_stratum() {
+        for sampler in [Sampler::Halton, Sampler::Sobol] {
+            let mut strata = [false; 16];
+
+            // Without the random offset, the first 16 samples of the sequence land in a different
+            // sixteenth of the unit interval each.
+            let sequence = SampleSequence {
+                offset: [0.0, 0.0],
+                ..sampler.sequence(0)
+            };
+
+            for [u, _] in sequence.take(16) {
+                strata[(u * 16.0) as usize] = true;
+            }
+
+            assert!(strata.iter().all(|&covered| covered));
+        }
+    }
+
+    #[test]
+    fn sequences_with_the_same_seed_share_their_offset() {
+        let first = Sampler::Sobol.seeded_sequence(0, 42);
+        let second = Sampler::Sobol.seeded_sequence(0, 42);
+        let third = Sampler::Sobol.seeded_sequence(0, 43);
+
+        assert_eq!(first.offset, second.offset);
+        assert_ne!(first.offset, third.offset);
+        assert!(first.take(8).eq(second.take(8)));
+    }
+
+    #[test]
+    fn continuing_a_sequence_from_a_later_sample() {
+        let full = SampleSequence {
+            offset: [0.0, 0.0],
+            ..Sampler::Halton.sequence(0)
+        };
+
+        let continued = SampleSequence {
+            offset: [0.0, 0.0],
+            ..Sampler::Halton.sequence(4)
+        };
+
+        assert!(full.skip(4).take(8).eq(continued.take(8)));
+    }
+}
@@ -1,5 +1,1399 @@
-#[derive(Debug, PartialEq)]
-pub enum SceneProgress {
-    Enable,
-    Disable,
+use std::{
+    cell::RefCell, collections::HashMap, fmt, io, path::PathBuf, sync::Arc, thread::LocalKey,
+};
+
+use serde::{
+    de::{self, IgnoredAny, MapAccess, Visitor},
+    Deserialize, Deserializer,
+};
+use thiserror::Error;
+
+use crate::{
+    camera::Camera,
+    canvas::Canvas,
+    config::Config,
+    light::{Light, LightFalloff},
+    render::RenderSettings,
+    shape::{Shape, ShapeId},
+    world::{UnitScale, World},
+};
+
+mod format;
+mod limits;
+mod overrides;
+
+pub use self::format::{convert, SceneFormat};
+pub use self::limits::{LimitAction, LimitExceeded, SceneLimits};
+pub use self::overrides::{MaterialOverrides, OVERRIDES_FILE};
+
+pub(crate) use self::limits::{count_triangles, uncount_triangles, within_triangle_limit};
+
+/// The error type when trying to parse a scene.
+#[derive(Debug, Error)]
+pub enum Error {
+    /// The scene spec is not valid JSON, or it doesn't describe a valid scene.
+    #[error(transparent)]
+    InvalidSpec(#[from] serde_json::Error),
+
+    /// The scene spec is not valid YAML, or it couldn't be written as YAML.
+    #[error(transparent)]
+    InvalidYaml(#[from] serde_yaml::Error),
+
+    /// The scene exceeds it's [limits](SceneLimits).
+    #[error(transparent)]
+    LimitExceeded(#[from] LimitExceeded),
+
+    /// The material overrides of a scene could not be read.
+    #[error("failed to read the material overrides at {path}")]
+    ReadOverrides {
+        /// Path of the overrides.
+        path: PathBuf,
+
+        /// Reason why the overrides could not be read.
+        #[source]
+        source: io::Error,
+    },
+}
+
+/// A world alongside the camera used to render it.
+///
+/// # Examples
+///
+/// Scenes can be described in JSON, with a `camera`, a list of `objects`, a list of `lights` and
+/// optionally the `units` the scene is modeled in and the `light_falloff` of it's lights. Check out
+/// the deserialization docs of [Camera], [Shape], [Light], [Material](crate::material::Material),
+/// [UnitScale] and [LightFalloff] for the description of each one of them.
+///
+/// Each object can also have a `name`, which is how [material overrides](MaterialOverrides) find
+/// it, and each light can also have a `group`, with the name of the [light
+/// group](Light::group) it's rendered to.
+///
+/// Scenes with `"energy_conservation": true` [conserve the energy](World::conserve_energy) of
+/// every material as soon as they're parsed, so materials with naive values don't give off more
+/// light than they receive. A warning is logged with the number of materials that were changed.
+///
+/// Fields that don't describe anything are ignored, unless the scene is parsed in
+/// [strict](ParseMode::Strict) mode with [Scene::from_json_with_mode].
+///
+/// Scenes can also be described in YAML with the same fields, and parsed with [Scene::from_yaml].
+/// Specs are translated from one format to the other with [convert].
+///
+/// Shapes that appear many times, like the trees of a forest, can be described once in the
+/// `definitions` of the scene, and then placed as many times as needed as an `instance` of their
+/// name, with it's own transformation and optionally it's own material. Definitions can also have
+/// instances of the definitions listed before them, and they must be listed before the `objects`
+/// of the scene. Every instance shares the geometry of it's definition, so instances save both
+/// writing the definition again and the memory of it's geometry.
+///
+/// ```
+/// use raytracer::scene::Scene;
+///
+/// let scene = Scene::from_json(r#"{
+///     "camera": {
+///         "width": 100,
+///         "height": 50,
+///         "field_of_view": 60.0,
+///         "transform": {
+///             "type": "view",
+///             "from": { "x": 0.0, "y": 1.5, "z": -5.0 },
+///             "to": { "x": 0.0, "y": 1.0, "z": 0.0 },
+///             "up": { "x": 0.0, "y": 1.0, "z": 0.0 }
+///         }
+///     },
+///     "objects": [
+///         {
+///             "type": "sphere",
+///             "material": {
+///                 "diffuse": 0.1,
+///                 "reflectivity": 0.9,
+///                 "transparency": 0.9,
+///                 "index_of_refraction": "glass"
+///             },
+///             "transform": { "type": "translation", "x": 0.0, "y": 1.0, "z": 0.0 }
+///         },
+///         { "type": "plane" }
+///     ],
+///     "lights": [
+///         {
+///             "type": "point",
+///             "position": { "x": -10.0, "y": 10.0, "z": -10.0 },
+///             "intensity": { "red": 255, "green": 255, "blue": 255 }
+///         }
+///     ]
+/// }"#).unwrap();
+///
+/// assert_eq!(scene.world.objects.len(), 2);
+/// ```
+///
+/// A row of glass spheres, one of them red, instanced from a single definition.
+///
+/// ```
+/// use raytracer::scene::Scene;
+///
+/// let scene = Scene::from_json(r#"{
+///     "camera": { "width": 100, "height": 50, "field_of_view": 60.0 },
+///     "definitions": {
+///         "marble": {
+///             "type": "sphere",
+///             "material": { "transparency": 1.0, "index_of_refraction": "glass" },
+///             "transform": { "type": "scaling", "x": 0.5, "y": 0.5, "z": 0.5 }
+///         }
+///     },
+///     "objects": [
+///         { "type": "instance", "of": "marble" },
+///         {
+///             "type": "instance",
+///             "of": "marble",
+///             "transform": { "type": "translation", "x": 1.0, "y": 0.0, "z": 0.0 },
+///             "material": { "color": { "red": 255, "green": 0, "blue": 0 } }
+///         }
+///     ]
+/// }"#).unwrap();
+///
+/// assert_eq!(scene.world.objects.len(), 2);
+/// ```
+///
+#[derive(Clone, Debug, Deserialize)]
+#[serde(from = "SceneDeserializer")]
+pub struct Scene {
+    /// Camera used to render the scene.
+    pub camera: Camera,
+
+    /// World with the objects and lights of the scene.
+    pub world: World,
+
+    /// Identifiers of the objects of the scene that have a `name`, by their name. When many
+    /// objects have the same name, the last one of them keeps it.
+    pub names: HashMap<String, ShapeId>,
+}
+
+/// How strictly a scene spec is parsed.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum ParseMode {
+    /// Fields that don't describe anything, such as a misspelled `"diffuze"`, are ignored and
+    /// reported as [warnings](Warning).
+    #[default]
+    Permissive,
+
+    /// Fields that don't describe anything are rejected.
+    Strict,
+}
+
+/// Problem found in a scene spec that doesn't prevent it from being parsed.
+#[derive(Clone, Debug, PartialEq, Eq, Error)]
+pub enum Warning {
+    /// The spec has a field that doesn't describe anything, so it was ignored.
+    #[error("unknown field `{0}`")]
+    UnknownField(String),
+}
+
+/// Differences between two scenes, as found by [Scene::diff].
+///
+/// Objects and lights are matched by their position in their scene, so they're compared in the
+/// same order they are listed in the JSON spec.
+///
+/// The diff can be displayed as a human readable report, with one difference per line.
+///
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SceneDiff {
+    /// Whether the camera of the scenes is different.
+    pub camera: bool,
+
+    /// Whether the scenes are modeled in different units.
+    pub units: bool,
+
+    /// Whether the light of the scenes falls off differently with distance.
+    pub light_falloff: bool,
+
+    /// Objects that were added, removed, or whose shape or transformation changed.
+    pub objects: Vec<Change>,
+
+    /// Indices of the objects present in both scenes whose material changed.
+    pub materials: Vec<usize>,
+
+    /// Lights that were added, removed or changed.
+    pub lights: Vec<Change>,
+}
+
+/// Change of an element of a scene, with it's index in the list of elements of the scene it
+/// belongs to.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Change {
+    /// The element is only present in the other scene.
+    Added(usize),
+
+    /// The element is only present in the original scene.
+    Removed(usize),
+
+    /// The element is present in both scenes, but it's different.
+    Modified(usize),
+}
+
+#[derive(Debug, Deserialize)]
+struct SceneDeserializer {
+    camera: Camera,
+
+    #[serde(default, rename = "definitions")]
+    _definitions: Definitions,
+
+    #[serde(default)]
+    objects: Vec<SceneObject>,
+
+    #[serde(default)]
+    lights: Vec<Light>,
+
+    #[serde(default)]
+    units: UnitScale,
+
+    #[serde(default)]
+    light_falloff: LightFalloff,
+
+    #[serde(default)]
+    energy_conservation: bool,
+
+    #[serde(flatten)]
+    _unknown: UnknownFields,
+}
+
+/// Object of a scene, which can have a name to find it by.
+#[derive(Debug, Deserialize)]
+struct SceneObject {
+    #[serde(flatten)]
+    shape: Shape,
+
+    name: Option<String>,
+}
+
+/// Deserializer for a struct that reports the fields that don't belong to it according to the
+/// current [ParseMode].
+#[derive(Debug, PartialEq, Deserialize)]
+pub(crate) struct Checked<T> {
+    #[serde(flatten)]
+    pub(crate) inner: T,
+
+    #[serde(flatten)]
+    _unknown: UnknownFields,
+}
+
+/// Shapes defined by a scene to be instanced by it's objects. They're stored for the rest of the
+/// scene as soon as they're parsed, because instances are parsed as shapes on their own.
+#[derive(Debug, Default)]
+struct Definitions;
+
+struct DefinitionsVisitor;
+
+/// Catch-all for the fields of an object that don't match any of the fields of it's
+/// deserializer. Flattened into a deserializer, it receives every field left over by the others.
+#[derive(Debug, Default, PartialEq)]
+pub(crate) struct UnknownFields;
+
+struct UnknownFieldsVisitor;
+
+thread_local! {
+    // Mode and warnings of the scene being parsed in this thread, if any. Components parsed on
+    // their own are parsed in permissive mode and their warnings are discarded.
+    static PARSE_STATE: RefCell<Option<(ParseMode, Vec<Warning>)>> = const { RefCell::new(None) };
+
+    // Shapes defined so far by the scene being parsed in this thread.
+    static DEFINITIONS: RefCell<HashMap<String, Arc<Shape>>> = RefCell::new(HashMap::new());
+}
+
+impl<'de> Deserialize<'de> for UnknownFields {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_map(UnknownFieldsVisitor)
+    }
+}
+
+impl<'de> Visitor<'de> for UnknownFieldsVisitor {
+    type Value = UnknownFields;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a map")
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        while let Some(field) = map.next_key::<String>()? {
+            map.next_value::<IgnoredAny>()?;
+            report(Warning::UnknownField(field)).map_err(de::Error::custom)?;
+        }
+
+        Ok(UnknownFields)
+    }
+}
+
+impl<'de> Deserialize<'de> for Definitions {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_map(DefinitionsVisitor)
+    }
+}
+
+impl<'de> Visitor<'de> for DefinitionsVisitor {
+    type Value = Definitions;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a map of shapes")
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        // Each definition is stored before parsing the next one, so it can be instanced by them.
+        while let Some((name, shape)) = map.next_entry::<String, Shape>()? {
+            // Definitions aren't placed in the scene, so their triangles only count through their
+            // instances.
+            uncount_triangles(shape.triangle_count());
+            DEFINITIONS.with_borrow_mut(|definitions| definitions.insert(name, Arc::new(shape)));
+        }
+
+        Ok(Definitions)
+    }
+}
+
+/// Restores the value that a thread-local of the scene being parsed had before it was created once
+/// it's dropped, so a parse that panics doesn't leave it's state behind for the next scene parsed
+/// in the same thread.
+#[must_use]
+pub(crate) struct ParseGuard<T: Default + 'static> {
+    key: &'static LocalKey<RefCell<T>>,
+    previous: Option<T>,
+}
+
+impl<T: Default> ParseGuard<T> {
+    /// Sets the thread-local at `key` to `value` until the returned guard is dropped or restored.
+    pub(crate) fn set(key: &'static LocalKey<RefCell<T>>, value: T) -> Self {
+        Self {
+            key,
+            previous: Some(key.replace(value)),
+        }
+    }
+
+    /// Restores the previous value of the thread-local, returning the value it had until now.
+    pub(crate) fn restore(mut self) -> T {
+        self.key.replace(self.previous.take().unwrap_or_default())
+    }
+}
+
+impl<T: Default> Drop for ParseGuard<T> {
+    fn drop(&mut self) {
+        if let Some(previous) = self.previous.take() {
+            self.key.replace(previous);
+        }
+    }
+}
+
+/// Shape defined with the given name by the scene being parsed in this thread, shared with every
+/// instance of it.
+pub(crate) fn definition(name: &str) -> Option<Arc<Shape>> {
+    DEFINITIONS.with_borrow(|definitions| definitions.get(name).map(Arc::clone))
+}
+
+/// Records a warning for the scene being parsed, or turns it into an error in strict mode.
+fn report(warning: Warning) -> Result<(), Warning> {
+    PARSE_STATE.with_borrow_mut(|state| match state {
+        Some((ParseMode::Strict, _)) => Err(warning),
+        Some((ParseMode::Permissive, warnings)) => {
+            warnings.push(warning);
+            Ok(())
+        }
+        None => Ok(()),
+    })
+}
+
+impl From<SceneDeserializer> for Scene {
+    fn from(value: SceneDeserializer) -> Self {
+        let names = value
+            .objects
+            .iter()
+            .filter_map(|object| Some((object.name.clone()?, object.shape.id())))
+            .collect();
+
+        let mut world = World {
+            objects: value
+                .objects
+                .into_iter()
+                .map(|object| object.shape)
+                .collect(),
+            lights: value.lights,
+            units: value.units,
+            light_falloff: value.light_falloff,
+        };
+
+        if value.energy_conservation {
+            let changed = world.conserve_energy();
+
+            if changed > 0 {
+                log::warn!("{changed} materials gave off more light than they received");
+            }
+        }
+
+        Self {
+            camera: value.camera,
+            world,
+            names,
+        }
+    }
+}
+
+impl Scene {
+    /// Parses a scene described in JSON.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the spec is not valid JSON or if any of it's components is invalid.
+    ///
+    pub fn from_json(spec: &str) -> Result<Self, Error> {
+        Self::from_json_with_mode(spec, ParseMode::Permissive).map(|(scene, _)| scene)
+    }
+
+    /// Parses a scene described in JSON, returning the warnings found in the spec alongside it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use raytracer::scene::{ParseMode, Scene, Warning};
+    ///
+    /// let spec = r#"{
+    ///     "camera": { "width": 100, "height": 50, "field_of_view": 60.0 },
+    ///     "objects": [{ "type": "sphere", "material": { "diffuze": 0.5 } }]
+    /// }"#;
+    ///
+    /// let (_, warnings) = Scene::from_json_with_mode(spec, ParseMode::Permissive).unwrap();
+    /// assert_eq!(warnings, [Warning::UnknownField("diffuze".to_string())]);
+    ///
+    /// assert!(Scene::from_json_with_mode(spec, ParseMode::Strict).is_err());
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Fails if the spec is not valid JSON, if any of it's components is invalid, or if it has
+    /// unknown fields in [strict](ParseMode::Strict) mode.
+    ///
+    pub fn from_json_with_mode(spec: &str, mode: ParseMode) -> Result<(Self, Vec<Warning>), Error> {
+        let parse_state = ParseGuard::set(&PARSE_STATE, Some((mode, vec![])));
+        let definitions = ParseGuard::set(&DEFINITIONS, HashMap::new());
+        let scene = serde_json::from_str(spec);
+
+        definitions.restore();
+        let (_, warnings) = parse_state.restore().unwrap_or((mode, vec![]));
+
+        for warning in &warnings {
+            log::warn!("{warning} in scene spec");
+        }
+
+        let scene: Self = scene?;
+
+        log::debug!(
+            "parsed a scene with {} objects and {} lights",
+            scene.world.objects.len(),
+            scene.world.lights.len()
+        );
+
+        Ok((scene, warnings))
+    }
+
+    /// Renders the world of the scene using it's camera.
+    pub fn render(&self) -> Canvas {
+        self.camera.render(&self.world)
+    }
+
+    /// Renders the world of the scene using it's camera, with the given settings.
+    pub fn render_with_settings(&self, settings: &RenderSettings) -> Canvas {
+        self.camera.render_with_settings(&self.world, settings)
+    }
+
+    /// Applies the defaults of a configuration that depend on the world, which is the
+    /// [epsilon](Config::epsilon) of it's units. Defaults of the rendering process are applied
+    /// with [RenderSettings::from_config] instead.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use raytracer::{config::Config, render::RenderSettings, scene::Scene};
+    ///
+    /// let config = Config::from_sources(|_| None, ["render", "--epsilon=0.001"].map(String::from))
+    ///     .unwrap();
+    ///
+    /// let scene = Scene::from_json(r#"{
+    ///     "camera": { "width": 16, "height": 16, "field_of_view": 60.0 }
+    /// }"#)
+    /// .unwrap()
+    /// .with_config(&config);
+    ///
+    /// let image = scene.render_with_settings(&RenderSettings::from_config(&config));
+    /// ```
+    ///
+    pub fn with_config(mut self, config: &Config) -> Self {
+        self.world.units = self.world.units.with_epsilon(config.epsilon);
+        self
+    }
+
+    /// Finds the differences between this scene and another one, such as a new version of the
+    /// same scene.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use raytracer::scene::{Change, Scene};
+    ///
+    /// let before = Scene::from_json(r#"{
+    ///     "camera": { "width": 100, "height": 50, "field_of_view": 60.0 },
+    ///     "objects": [{ "type": "sphere" }, { "type": "plane" }]
+    /// }"#).unwrap();
+    ///
+    /// let after = Scene::from_json(r#"{
+    ///     "camera": { "width": 100, "height": 50, "field_of_view": 60.0 },
+    ///     "objects": [{ "type": "sphere", "material": { "diffuse": 0.5 } }]
+    /// }"#).unwrap();
+    ///
+    /// let diff = before.diff(&after);
+    ///
+    /// assert_eq!(diff.materials, [0]);
+    /// assert_eq!(diff.objects, [Change::Removed(1)]);
+    /// assert_eq!(diff.to_string(), "material of object 0 changed\nobject 1 removed");
+    /// ```
+    ///
+    pub fn diff(&self, other: &Self) -> SceneDiff {
+        let objects = diff_lists(&self.world.objects, &other.world.objects, |a, b| {
+            // Only the shape and transformation of the objects are compared here, their materials
+            // are compared separately.
+            let mut a = a.clone();
+            a.as_mut().material = b.as_ref().material.clone();
+            a == *b
+        });
+
+        let materials = self
+            .world
+            .objects
+            .iter()
+            .zip(&other.world.objects)
+            .enumerate()
+            .filter(|(_, (a, b))| a.as_ref().material != b.as_ref().material)
+            .map(|(i, _)| i)
+            .collect();
+
+        SceneDiff {
+            camera: self.camera != other.camera,
+            units: self.world.units != other.world.units,
+            light_falloff: self.world.light_falloff != other.world.light_falloff,
+            objects,
+            materials,
+            lights: diff_lists(&self.world.lights, &other.world.lights, PartialEq::eq),
+        }
+    }
+}
+
+impl SceneDiff {
+    /// Whether the scenes are the same.
+    pub fn is_empty(&self) -> bool {
+        !self.camera
+            && !self.units
+            && !self.light_falloff
+            && self.objects.is_empty()
+            && self.materials.is_empty()
+            && self.lights.is_empty()
+    }
+}
+
+impl fmt::Display for SceneDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut lines = vec![];
+
+        if self.camera {
+            lines.push("camera changed".to_string());
+        }
+
+        if self.units {
+            lines.push("units changed".to_string());
+        }
+
+        if self.light_falloff {
+            lines.push("light falloff changed".to_string());
+        }
+
+        let mut objects: Vec<_> = self
+            .objects
+            .iter()
+            .map(|change| (change.index(), change.describe("object")))
+            .chain(
+                self.materials
+                    .iter()
+                    .map(|i| (*i, format!("material of object {i} changed"))),
+            )
+            .collect();
+
+        // Keep every difference of an object together, and in the order of the objects.
+        objects.sort_by_key(|(i, _)| *i);
+
+        lines.extend(objects.into_iter().map(|(_, line)| line));
+        lines.extend(self.lights.iter().map(|change| change.describe("light")));
+
+        write!(f, "{}", lines.join("\n"))
+    }
+}
+
+impl Change {
+    fn index(&self) -> usize {
+        match self {
+            Self::Added(i) | Self::Removed(i) | Self::Modified(i) => *i,
+        }
+    }
+
+    fn describe(&self, element: &str) -> String {
+        match self {
+            Self::Added(i) => format!("{element} {i} added"),
+            Self::Removed(i) => format!("{element} {i} removed"),
+            Self::Modified(i) => format!("{element} {i} changed"),
+        }
+    }
+}
+
+/// Compares two lists element by element, with the extra elements of the longest list counted as
+/// added or removed.
+fn diff_lists<T, F>(original: &[T], other: &[T], eq: F) -> Vec<Change>
+where
+    F: Fn(&T, &T) -> bool,
+{
+    let modified = original
+        .iter()
+        .zip(other)
+        .enumerate()
+        .filter(|(_, (a, b))| !eq(a, b))
+        .map(|(i, _)| Change::Modified(i));
+
+    let removed = (other.len()..original.len()).map(Change::Removed);
+    let added = (original.len()..other.len()).map(Change::Added);
+
+    modified.chain(removed).chain(added).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        assert_approx, color,
+        intersection::Intersection,
+        light::{AreaLight, AreaLightBuilder, DomeLight, DomeLightBuilder, DomeSource, PointLight},
+        material::{self, Material},
+        ray::Ray,
+        sampling::Sampler,
+        shape::{Cube, Group, GroupBuilder, ShapeBuilder, Sphere},
+        transform::Transform,
+        tuple::{Point, Vector},
+    };
+
+    use super::*;
+
+    const CAMERA: &str = r#"{ "width": 10, "height": 10, "field_of_view": 90.0 }"#;
+
+    fn scene_with_objects(objects: &str) -> Result<Scene, Error> {
+        Scene::from_json(&format!(
+            r#"{{ "camera": {CAMERA}, "objects": [{objects}] }}"#
+        ))
+    }
+
+    #[test]
+    fn parsing_a_scene() {
+        let scene = Scene::from_json(&format!(
+            r#"{{
+                "camera": {CAMERA},
+                "objects": [{{ "type": "cube" }}],
+                "lights": [{{
+                    "type": "point",
+                    "position": {{ "x": 1.0, "y": 2.0, "z": 3.0 }},
+                    "intensity": {{ "red": 255, "green": 255, "blue": 255 }}
+                }}]
+            }}"#
+        ))
+        .unwrap();
+
+        assert_eq!(scene.camera.width(), 10);
+        assert_eq!(scene.camera.height(), 10);
+        assert_eq!(scene.world.objects, vec![Shape::Cube(Default::default())]);
+        assert_eq!(
+            scene.world.lights,
+            vec![Light::Point(PointLight {
+                position: Point::new(1.0, 2.0, 3.0),
+                intensity: color::consts::WHITE,
+                group: None,
+            })]
+        );
+    }
+
+    #[test]
+    fn parsing_the_light_groups_of_a_scene() {
+        let light = |x, group| {
+            format!(
+                r#"{{
+                    "type": "point",
+                    "position": {{ "x": {x}, "y": 2.0, "z": 3.0 }},
+                    "intensity": {{ "red": 255, "green": 255, "blue": 255 }}
+                    {group}
+                }}"#
+            )
+        };
+
+        let lights = [
+            light(0.0, r#", "group": "key""#),
+            light(1.0, ""),
+            light(2.0, r#", "group": "key""#),
+            light(3.0, r#", "group": "fill""#),
+        ]
+        .join(",");
+
+        let (scene, warnings) = Scene::from_json_with_mode(
+            &format!(r#"{{ "camera": {CAMERA}, "lights": [{lights}] }}"#),
+            ParseMode::Strict,
+        )
+        .unwrap();
+
+        assert!(warnings.is_empty());
+        assert_eq!(scene.world.lights.len(), 4);
+        assert_eq!(
+            scene
+                .world
+                .lights
+                .iter()
+                .map(Light::group)
+                .collect::<Vec<_>>(),
+            [Some("key"), None, Some("key"), Some("fill")]
+        );
+    }
+
+    #[test]
+    fn parsing_a_glass_sphere() {
+        let scene = scene_with_objects(
+            r#"{
+                "type": "sphere",
+                "material": {
+                    "diffuse": 0.1,
+                    "shininess": 300.0,
+                    "reflectivity": 0.9,
+                    "transparency": 0.9,
+                    "index_of_refraction": "glass"
+                },
+                "transform": [
+                    { "type": "scaling", "x": 0.5, "y": 0.5, "z": 0.5 },
+                    { "type": "translation", "x": 0.0, "y": 1.0, "z": 0.0 }
+                ]
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            scene.world.objects,
+            vec![Shape::Sphere(Sphere::from(ShapeBuilder {
+                material: Material {
+                    diffuse: 0.1,
+                    shininess: 300.0,
+                    reflectivity: 0.9,
+                    transparency: 0.9,
+                    index_of_refraction: material::consts::GLASS_INDEX_OF_REFRACTION,
+                    ..Default::default()
+                },
+                transform: Transform::translation(0.0, 1.0, 0.0)
+                    * Transform::scaling(0.5, 0.5, 0.5).unwrap(),
+            }))]
+        );
+    }
+
+    #[test]
+    fn parsing_a_group_of_shapes() {
+        let scene = scene_with_objects(
+            r#"{
+                "type": "group",
+                "transform": { "type": "translation", "x": 1.0, "y": 0.0, "z": 0.0 },
+                "children": [{ "type": "sphere" }, { "type": "cube" }]
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            scene.world.objects,
+            vec![Shape::Group(Group::from(GroupBuilder {
+                children: [
+                    Shape::Sphere(Default::default()),
+                    Shape::Cube(Default::default())
+                ],
+                transform: Transform::translation(1.0, 0.0, 0.0),
+            }))]
+        );
+    }
+
+    #[test]
+    fn trying_to_parse_a_material_with_an_unknown_medium() {
+        let scene = scene_with_objects(
+            r#"{ "type": "sphere", "material": { "index_of_refraction": "mercury" } }"#,
+        );
+
+        assert!(scene
+            .unwrap_err()
+            .to_string()
+            .starts_with("unknown medium for the index of refraction: `mercury`"));
+    }
+
+    #[test]
+    fn trying_to_parse_a_collinear_triangle() {
+        let scene = scene_with_objects(
+            r#"{
+                "type": "triangle",
+                "vertices": [
+                    { "x": 0.0, "y": 0.0, "z": 0.0 },
+                    { "x": 1.0, "y": 0.0, "z": 0.0 },
+                    { "x": 2.0, "y": 0.0, "z": 0.0 }
+                ]
+            }"#,
+        );
+
+        assert!(scene.is_err());
+    }
+
+    fn scene_with_definitions(definitions: &str, objects: &str) -> Result<Scene, Error> {
+        Scene::from_json(&format!(
+            r#"{{ "camera": {CAMERA}, "definitions": {{ {definitions} }}, "objects": [{objects}] }}"#
+        ))
+    }
+
+    #[test]
+    fn parsing_instances_of_a_definition() {
+        let scene = scene_with_definitions(
+            r#""pair": {
+                "type": "group",
+                "children": [
+                    { "type": "sphere" },
+                    { "type": "cube", "transform": { "type": "translation", "x": 3.0, "y": 0.0, "z": 0.0 } }
+                ]
+            }"#,
+            r#"{ "type": "instance", "of": "pair" },
+            {
+                "type": "instance",
+                "of": "pair",
+                "transform": { "type": "translation", "x": 0.0, "y": 2.0, "z": 0.0 },
+                "material": { "ambient": 1.0 }
+            }"#,
+        )
+        .unwrap();
+
+        let pair = Shape::Group(Group::from(GroupBuilder {
+            children: [
+                Shape::Sphere(Default::default()),
+                Shape::Cube(Cube::from(ShapeBuilder {
+                    transform: Transform::translation(3.0, 0.0, 0.0),
+                    ..Default::default()
+                })),
+            ],
+            transform: Default::default(),
+        }));
+
+        let [Shape::Instance(first), Shape::Instance(second)] = &scene.world.objects[..] else {
+            panic!("instances aren't parsed as instances");
+        };
+
+        // Both instances share the same geometry, placed and painted differently.
+        assert!(Arc::ptr_eq(first.prototype(), second.prototype()));
+        assert_eq!(first.prototype().as_ref(), &pair);
+
+        assert_eq!(scene.world.objects[0].transform(), Default::default());
+        assert_eq!(
+            scene.world.objects[1].transform(),
+            Transform::translation(0.0, 2.0, 0.0)
+        );
+
+        let ray = Ray {
+            origin: Point::new(0.0, 2.0, -5.0),
+            direction: Vector::new(0.0, 0.0, 1.0),
+        };
+
+        let mut xs = scene.world.intersect(&ray);
+        let hit = Intersection::hit(&mut xs).unwrap();
+
+        assert_approx!(hit.t, 4.0);
+        assert_eq!(hit.prepare_computation(&ray, xs).material.ambient, 1.0);
+    }
+
+    #[test]
+    fn definitions_can_instance_the_definitions_before_them() {
+        let scene = scene_with_definitions(
+            r#""ball": { "type": "sphere" },
+            "big_ball": {
+                "type": "instance",
+                "of": "ball",
+                "transform": { "type": "scaling", "x": 2.0, "y": 2.0, "z": 2.0 }
+            }"#,
+            r#"{ "type": "instance", "of": "big_ball" }"#,
+        )
+        .unwrap();
+
+        let ray = Ray {
+            origin: Point::new(0.0, 0.0, -5.0),
+            direction: Vector::new(0.0, 0.0, 1.0),
+        };
+
+        assert_approx!(scene.world.objects[0].intersect(&ray)[0].t, 3.0);
+    }
+
+    #[test]
+    fn parsing_a_scatter_of_instances() {
+        let scene = scene_with_definitions(
+            r#""rock": { "type": "cube" }"#,
+            r#"{
+                "type": "scatter",
+                "prefab": { "type": "instance", "of": "rock" },
+                "region": {
+                    "type": "volume",
+                    "min": { "x": -5.0, "y": 0.0, "z": -5.0 },
+                    "max": { "x": 5.0, "y": 0.0, "z": 5.0 }
+                },
+                "count": 10,
+                "min_scale": 0.1,
+                "max_scale": 0.5,
+                "seed": 3
+            }"#,
+        )
+        .unwrap();
+
+        let Shape::Group(field) = &scene.world.objects[0] else {
+            unreachable!()
+        };
+
+        assert_eq!(field.children.len(), 10);
+
+        let scene = scene_with_objects(
+            r#"{
+                "type": "scatter",
+                "prefab": { "type": "cube" },
+                "region": { "type": "surface", "shape": { "type": "group", "children": [] } },
+                "count": 10
+            }"#,
+        );
+
+        assert!(scene
+            .unwrap_err()
+            .to_string()
+            .starts_with("scatter region has no surface or volume"));
+    }
+
+    #[test]
+    fn parsing_instances_of_a_model_with_detail_levels() {
+        let scene = scene_with_definitions(
+            r#""detailed_tree": { "type": "sphere" },
+            "simple_tree": { "type": "cube" },
+            "tree": {
+                "type": "group",
+                "children": [
+                    { "type": "instance", "of": "detailed_tree" },
+                    { "type": "instance", "of": "simple_tree" }
+                ],
+                "detail_levels": { "distance": [30.0] }
+            }"#,
+            r#"{ "type": "instance", "of": "tree" },
+            {
+                "type": "instance",
+                "of": "tree",
+                "transform": { "type": "translation", "x": 0.0, "y": 0.0, "z": 100.0 }
+            }"#,
+        )
+        .unwrap();
+
+        // The near tree is seen as a sphere and the far one as a cube, which is hit off center.
+        let ray = |z| Ray {
+            origin: Point::new(0.9, 0.9, z),
+            direction: Vector::new(0.0, 0.0, 1.0),
+        };
+
+        assert!(scene.world.objects[0].intersect(&ray(-5.0)).is_empty());
+        assert_approx!(scene.world.objects[1].intersect(&ray(-5.0))[0].t, 104.0);
+
+        let scene = scene_with_objects(
+            r#"{
+                "type": "group",
+                "children": [{ "type": "sphere" }],
+                "detail_levels": { "screen_size": [0.1] }
+            }"#,
+        );
+
+        assert!(scene
+            .unwrap_err()
+            .to_string()
+            .starts_with("1 detail levels need 0 thresholds, got 1"));
+    }
+
+    #[test]
+    fn trying_to_instance_an_undefined_shape() {
+        let scene = scene_with_definitions("", r#"{ "type": "instance", "of": "tree" }"#);
+
+        assert!(scene
+            .unwrap_err()
+            .to_string()
+            .starts_with("no shape is defined as `tree`"));
+
+        // Definitions are only kept while parsing the scene that has them.
+        scene_with_definitions(r#""tree": { "type": "cylinder" }"#, "").unwrap();
+
+        assert!(scene_with_objects(r#"{ "type": "instance", "of": "tree" }"#).is_err());
+
+        let defined_after_the_objects = Scene::from_json(&format!(
+            r#"{{
+                "camera": {CAMERA},
+                "objects": [{{ "type": "instance", "of": "tree" }}],
+                "definitions": {{ "tree": {{ "type": "cylinder" }} }}
+            }}"#
+        ));
+
+        assert!(defined_after_the_objects.is_err());
+    }
+
+    #[test]
+    fn parsing_an_area_light_with_a_sampler() {
+        let scene = Scene::from_json(&format!(
+            r#"{{
+                "camera": {CAMERA},
+                "lights": [{{
+                    "type": "area",
+                    "corner": {{ "x": 0.0, "y": 0.0, "z": 0.0 }},
+                    "horizontal_dir": {{ "x": 1.0, "y": 0.0, "z": 0.0 }},
+                    "horizontal_cells": 2,
+                    "vertical_dir": {{ "x": 0.0, "y": 1.0, "z": 0.0 }},
+                    "vertical_cells": 2,
+                    "intensity": {{ "red": 255, "green": 255, "blue": 255 }},
+                    "sampler": "sobol"
+                }}]
+            }}"#
+        ))
+        .unwrap();
+
+        assert_eq!(
+            scene.world.lights,
+            [Light::Area(
+                AreaLight::from(AreaLightBuilder {
+                    corner: Point::new(0.0, 0.0, 0.0),
+                    horizontal_dir: Vector::new(1.0, 0.0, 0.0),
+                    horizontal_cells: 2,
+                    vertical_dir: Vector::new(0.0, 1.0, 0.0),
+                    vertical_cells: 2,
+                    intensity: color::consts::WHITE,
+                })
+                .with_sampler(Sampler::Sobol)
+            )]
+        );
+    }
+
+    #[test]
+    fn parsing_a_dome_light() {
+        let scene = Scene::from_json(&format!(
+            r#"{{
+                "camera": {CAMERA},
+                "lights": [{{
+                    "type": "dome",
+                    "source": {{
+                        "type": "gradient",
+                        "top": {{ "red": 255, "green": 255, "blue": 255 }},
+                        "bottom": {{ "red": 0, "green": 0, "blue": 0 }}
+                    }}
+                }}]
+            }}"#
+        ))
+        .unwrap();
+
+        assert_eq!(
+            scene.world.lights,
+            [Light::Dome(DomeLight::from(DomeLightBuilder {
+                source: DomeSource::Gradient {
+                    top: color::consts::WHITE,
+                    bottom: color::consts::BLACK,
+                },
+                intensity: color::consts::WHITE,
+                samples: 64,
+            }))]
+        );
+    }
+
+    #[test]
+    fn trying_to_parse_an_area_light_without_cells() {
+        let scene = Scene::from_json(&format!(
+            r#"{{
+                "camera": {CAMERA},
+                "lights": [{{
+                    "type": "area",
+                    "corner": {{ "x": 0.0, "y": 0.0, "z": 0.0 }},
+                    "horizontal_dir": {{ "x": 1.0, "y": 0.0, "z": 0.0 }},
+                    "horizontal_cells": 0,
+                    "vertical_dir": {{ "x": 0.0, "y": 1.0, "z": 0.0 }},
+                    "vertical_cells": 2,
+                    "intensity": {{ "red": 255, "green": 255, "blue": 255 }}
+                }}]
+            }}"#
+        ));
+
+        assert!(scene
+            .unwrap_err()
+            .to_string()
+            .starts_with("area light must have at least one cell in each direction"));
+    }
+
+    #[test]
+    fn trying_to_parse_a_camera_with_null_dimensions() {
+        let scene = Scene::from_json(
+            r#"{ "camera": { "width": 0, "height": 10, "field_of_view": 90.0 } }"#,
+        );
+
+        assert!(scene.is_err());
+    }
+
+    #[test]
+    fn parsing_the_units_of_a_scene() {
+        let parse = |units: &str| {
+            Scene::from_json(&format!(r#"{{ "camera": {CAMERA}, "units": {units} }}"#))
+                .map(|scene| scene.world.units)
+        };
+
+        assert_eq!(
+            Scene::from_json(&format!(r#"{{ "camera": {CAMERA} }}"#))
+                .unwrap()
+                .world
+                .units,
+            UnitScale::METERS
+        );
+        assert_eq!(parse(r#""millimeters""#).unwrap(), UnitScale::MILLIMETERS);
+        assert_eq!(parse("0.0254").unwrap(), UnitScale::INCHES);
+        assert!(parse("0.0").is_err());
+        assert!(parse(r#""furlongs""#).is_err());
+    }
+
+    #[test]
+    fn parsing_the_light_falloff_of_a_scene() {
+        let parse = |falloff: &str| {
+            Scene::from_json(&format!(
+                r#"{{ "camera": {CAMERA}, "light_falloff": {falloff} }}"#
+            ))
+            .map(|scene| scene.world.light_falloff)
+        };
+
+        assert_eq!(parse(r#"{ "type": "none" }"#).unwrap(), LightFalloff::None);
+        assert_eq!(
+            parse(r#"{ "type": "inverse_square", "reference_distance": 2.0 }"#).unwrap(),
+            LightFalloff::InverseSquare(2.0)
+        );
+        assert!(parse(r#"{ "type": "inverse_square", "reference_distance": -1.0 }"#).is_err());
+    }
+
+    #[test]
+    fn parsing_a_scene_that_conserves_energy() {
+        let parse = |energy_conservation: bool| {
+            let scene = Scene::from_json(&format!(
+                r#"{{
+                    "camera": {CAMERA},
+                    "energy_conservation": {energy_conservation},
+                    "objects": [
+                        {{
+                            "type": "group",
+                            "children": [
+                                {{ "type": "sphere", "material": {{ "reflectivity": 0.9 }} }},
+                                {{
+                                    "type": "cube",
+                                    "material": {{ "ambient": 0.1, "diffuse": 0.5, "specular": 0.4 }}
+                                }}
+                            ]
+                        }}
+                    ]
+                }}"#
+            ))
+            .unwrap();
+
+            let Shape::Group(group) = &scene.world.objects[0] else {
+                panic!("expected a group");
+            };
+
+            group
+                .children
+                .iter()
+                .map(|child| child.material().energy())
+                .collect::<Vec<_>>()
+        };
+
+        let naive = parse(false);
+        assert_approx!(naive[0], 2.8);
+        assert_approx!(naive[1], 1.0);
+
+        let conserved = parse(true);
+        assert_approx!(conserved[0], 1.0);
+        assert_approx!(conserved[1], 1.0);
+    }
+
+    #[test]
+    fn unknown_fields_are_warnings_in_permissive_mode() {
+        let spec = r#"{
+            "camera": { "width": 10, "height": 10, "field_of_view": 90.0, "fov": 60.0 },
+            "objects": [
+                {
+                    "type": "sphere",
+                    "material": { "diffuze": 0.5 },
+                    "transform": { "type": "translation", "x": 1.0, "y": 0.0, "z": 0.0, "w": 1.0 }
+                },
+                { "type": "plane", "colour": "red" }
+            ],
+            "lights": [
+                {
+                    "type": "point",
+                    "position": { "x": 0.0, "y": 0.0, "z": 0.0 },
+                    "intensity": { "red": 255, "green": 255, "blue": 255 },
+                    "radius": 1.0
+                }
+            ],
+            "background": "black"
+        }"#;
+
+        let (scene, warnings) = Scene::from_json_with_mode(spec, ParseMode::Permissive).unwrap();
+
+        assert_eq!(scene.world.objects.len(), 2);
+        assert_eq!(
+            warnings,
+            ["fov", "diffuze", "w", "colour", "radius", "background"]
+                .map(|field| Warning::UnknownField(field.to_string()))
+        );
+
+        assert!(Scene::from_json(spec).is_ok());
+    }
+
+    #[test]
+    fn unknown_fields_are_errors_in_strict_mode() {
+        let spec = format!(
+            r#"{{
+                "camera": {CAMERA},
+                "objects": [{{ "type": "sphere", "material": {{ "diffuze": 0.5 }} }}]
+            }}"#
+        );
+
+        let err = Scene::from_json_with_mode(&spec, ParseMode::Strict).unwrap_err();
+
+        assert!(err.to_string().starts_with("unknown field `diffuze`"));
+    }
+
+    #[test]
+    fn a_valid_scene_has_no_warnings_in_strict_mode() {
+        let spec = format!(
+            r#"{{
+                "camera": {CAMERA},
+                "objects": [
+                    {{
+                        "type": "cylinder",
+                        "min": 0.0,
+                        "max": 1.0,
+                        "material": {{
+                            "pattern": {{
+                                "type": "stripe",
+                                "color_a": {{ "red": 255, "green": 255, "blue": 255 }},
+                                "color_b": {{ "red": 0, "green": 0, "blue": 0 }}
+                            }},
+                            "portal": {{ "transform": {{ "type": "rotation_y", "degrees": 90.0 }} }}
+                        }}
+                    }}
+                ],
+                "lights": [
+                    {{
+                        "type": "area",
+                        "corner": {{ "x": 0.0, "y": 0.0, "z": 0.0 }},
+                        "horizontal_dir": {{ "x": 1.0, "y": 0.0, "z": 0.0 }},
+                        "horizontal_cells": 2,
+                        "vertical_dir": {{ "x": 0.0, "y": 1.0, "z": 0.0 }},
+                        "vertical_cells": 2,
+                        "intensity": {{ "red": 255, "green": 255, "blue": 255 }},
+                        "sampler": "halton"
+                    }}
+                ],
+                "units": "inches"
+            }}"#
+        );
+
+        let (_, warnings) = Scene::from_json_with_mode(&spec, ParseMode::Strict).unwrap();
+
+        assert!(warnings.is_empty());
+    }
+
+    fn scene(objects: &str, lights: &str) -> Scene {
+        Scene::from_json(&format!(
+            r#"{{ "camera": {CAMERA}, "objects": [{objects}], "lights": [{lights}] }}"#
+        ))
+        .unwrap()
+    }
+
+    const LIGHT: &str = r#"{
+        "type": "point",
+        "position": { "x": 0.0, "y": 0.0, "z": 0.0 },
+        "intensity": { "red": 255, "green": 255, "blue": 255 }
+    }"#;
+
+    #[test]
+    fn diffing_equal_scenes() {
+        let a = scene(r#"{ "type": "sphere" }, { "type": "cube" }"#, LIGHT);
+        let b = scene(r#"{ "type": "sphere" }, { "type": "cube" }"#, LIGHT);
+
+        let diff = a.diff(&b);
+
+        assert!(diff.is_empty());
+        assert_eq!(diff.to_string(), "");
+    }
+
+    #[test]
+    fn diffing_the_objects_of_scenes() {
+        let a = scene(
+            r#"{ "type": "sphere" }, { "type": "cube" }, { "type": "plane" }"#,
+            "",
+        );
+        let b = scene(
+            r#"
+            { "type": "sphere", "material": { "reflectivity": 0.5 } },
+            { "type": "cube", "transform": { "type": "translation", "x": 1.0, "y": 0.0, "z": 0.0 } },
+            { "type": "plane" },
+            { "type": "sphere" }
+            "#,
+            "",
+        );
+
+        let diff = a.diff(&b);
+
+        assert_eq!(diff.objects, [Change::Modified(1), Change::Added(3)]);
+        assert_eq!(diff.materials, [0]);
+        assert!(diff.lights.is_empty());
+        assert!(!diff.camera);
+    }
+
+    #[test]
+    fn diffing_the_lights_of_scenes() {
+        let a = scene("", LIGHT);
+        let b = scene("", "");
+
+        assert_eq!(a.diff(&b).lights, [Change::Removed(0)]);
+        assert_eq!(b.diff(&a).lights, [Change::Added(0)]);
+    }
+
+    #[test]
+    fn displaying_the_diff_of_scenes() {
+        let diff = SceneDiff {
+            camera: true,
+            units: false,
+            light_falloff: false,
+            objects: vec![Change::Modified(2), Change::Removed(4)],
+            materials: vec![0, 2],
+            lights: vec![Change::Added(1)],
+        };
+
+        assert_eq!(
+            diff.to_string(),
+            "camera changed\n\
+             material of object 0 changed\n\
+             object 2 changed\n\
+             material of object 2 changed\n\
+             object 4 removed\n\
+             light 1 added"
+        );
+    }
 }
@@ -0,0 +1,184 @@
+use std::path::Path;
+
+use serde_json::Value;
+
+use super::{Error, Scene};
+
+/// Format that a scene is described in.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SceneFormat {
+    /// JSON, the format scenes are usually described in.
+    Json,
+
+    /// YAML, a friendlier format to write by hand.
+    Yaml,
+}
+
+impl SceneFormat {
+    /// Format of the scene at `path`, from it's extension, which is either `.json`, or `.yaml` or
+    /// `.yml`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use raytracer::scene::SceneFormat;
+    ///
+    /// assert_eq!(SceneFormat::from_path("scene.yml"), Some(SceneFormat::Yaml));
+    /// assert_eq!(SceneFormat::from_path("scene.obj"), None);
+    /// ```
+    ///
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Option<Self> {
+        let extension = path.as_ref().extension()?.to_str()?.to_ascii_lowercase();
+
+        match extension.as_str() {
+            "json" => Some(Self::Json),
+            "yaml" | "yml" => Some(Self::Yaml),
+            _ => None,
+        }
+    }
+
+    fn parse(self, spec: &str) -> Result<Value, Error> {
+        Ok(match self {
+            Self::Json => serde_json::from_str(spec)?,
+            Self::Yaml => serde_yaml::from_str(spec)?,
+        })
+    }
+
+    fn write(self, spec: &Value) -> Result<String, Error> {
+        Ok(match self {
+            Self::Json => serde_json::to_string_pretty(spec)? + "\n",
+            Self::Yaml => serde_yaml::to_string(spec)?,
+        })
+    }
+}
+
+impl Scene {
+    /// Parses a scene described in YAML, with the same fields as a scene described in
+    /// [JSON](Scene::from_json).
+    ///
+    /// # Errors
+    ///
+    /// Fails if the spec is not valid YAML or if any of it's components is invalid.
+    ///
+    pub fn from_yaml(spec: &str) -> Result<Self, Error> {
+        let spec = SceneFormat::Yaml.parse(spec)?;
+        Self::from_json(&spec.to_string())
+    }
+}
+
+/// Translates a scene spec from one format to another.
+///
+/// The fields of the spec are kept in the same order, and the spec is checked to describe a valid
+/// scene before it's translated, so the translated spec renders the same scene. Paths to images
+/// and models are kept as they are.
+///
+/// # Examples
+///
+/// ```
+/// use raytracer::scene::{self, SceneFormat};
+///
+/// let yaml = "
+/// camera:
+///   width: 100
+///   height: 50
+///   field_of_view: 60.0
+/// objects:
+///   - type: sphere
+/// ";
+///
+/// let json = scene::convert(yaml, SceneFormat::Yaml, SceneFormat::Json).unwrap();
+///
+/// assert!(json.starts_with("{\n  \"camera\": {\n    \"width\": 100,"));
+/// ```
+///
+/// # Errors
+///
+/// Fails if the spec is not valid in it's format, or if it doesn't describe a valid scene.
+///
+pub fn convert(spec: &str, from: SceneFormat, to: SceneFormat) -> Result<String, Error> {
+    let spec = from.parse(spec)?;
+
+    Scene::from_json(&spec.to_string())?;
+
+    to.write(&spec)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const YAML: &str = r#"
+camera:
+  width: 10
+  height: 10
+  field_of_view: 90.0
+definitions:
+  ball:
+    type: sphere
+objects:
+  - type: instance
+    of: ball
+    transform:
+      - type: translation
+        x: 0.0
+        y: 0.0
+        z: 5.0
+lights:
+  - type: point
+    position: { x: -10.0, y: 10.0, z: -10.0 }
+    intensity: { red: 255, green: 255, blue: 255 }
+"#;
+
+    #[test]
+    fn getting_the_format_of_a_scene_from_it_s_path() {
+        assert_eq!(
+            SceneFormat::from_path("a/scene.json"),
+            Some(SceneFormat::Json)
+        );
+        assert_eq!(
+            SceneFormat::from_path("scene.YAML"),
+            Some(SceneFormat::Yaml)
+        );
+        assert_eq!(SceneFormat::from_path("scene.yml"), Some(SceneFormat::Yaml));
+        assert_eq!(SceneFormat::from_path("scene"), None);
+        assert_eq!(SceneFormat::from_path("scene.txt"), None);
+    }
+
+    #[test]
+    fn a_scene_converted_back_and_forth_renders_the_same() {
+        let json = convert(YAML, SceneFormat::Yaml, SceneFormat::Json).unwrap();
+        let yaml = convert(&json, SceneFormat::Json, SceneFormat::Yaml).unwrap();
+
+        let from_json = Scene::from_json(&json).unwrap();
+        let from_yaml = Scene::from_yaml(&yaml).unwrap();
+
+        let original = Scene::from_yaml(YAML).unwrap().render().to_rgba();
+
+        assert_eq!(from_json.render().to_rgba(), original);
+        assert_eq!(from_yaml.render().to_rgba(), original);
+    }
+
+    #[test]
+    fn converting_keeps_the_order_of_the_fields() {
+        let json = convert(YAML, SceneFormat::Yaml, SceneFormat::Json).unwrap();
+
+        let position = |field: &str| json.find(&format!("\"{field}\"")).unwrap();
+
+        assert!(position("camera") < position("definitions"));
+        assert!(position("definitions") < position("objects"));
+        assert!(position("objects") < position("lights"));
+    }
+
+    #[test]
+    fn trying_to_convert_an_invalid_scene() {
+        assert!(matches!(
+            convert("camera: [", SceneFormat::Yaml, SceneFormat::Json),
+            Err(Error::InvalidYaml(_))
+        ));
+
+        assert!(matches!(
+            convert(r#"{ "objects": [] }"#, SceneFormat::Json, SceneFormat::Yaml),
+            Err(Error::InvalidSpec(_))
+        ));
+    }
+}
@@ -0,0 +1,477 @@
+use std::cell::RefCell;
+
+use thiserror::Error;
+
+use super::{Error as SceneError, ParseGuard, ParseMode, Scene};
+
+/// Guards against scenes that would take hours to parse or render, such as a malformed scene
+/// that scatters millions of copies of a mesh, or renders a gigapixel image.
+///
+/// What happens when a scene exceeds a limit depends on the [LimitAction] of the limits: either
+/// the scene is rejected as early as possible, or it's quality is degraded until it fits within
+/// the limits, with a warning.
+///
+/// # Examples
+///
+/// ```
+/// use raytracer::scene::{LimitAction, Scene, SceneLimits};
+///
+/// let spec = r#"{ "camera": { "width": 4000, "height": 2000, "field_of_view": 60.0 } }"#;
+///
+/// let limits = SceneLimits {
+///     max_pixels: 1000 * 500,
+///     ..Default::default()
+/// };
+///
+/// assert!(Scene::from_json_with_limits(spec, &limits).is_err());
+///
+/// let degrade = SceneLimits {
+///     on_exceeded: LimitAction::Degrade,
+///     ..limits
+/// };
+///
+/// let scene = Scene::from_json_with_limits(spec, &degrade).unwrap();
+///
+/// assert_eq!((scene.camera.width(), scene.camera.height()), (1000, 500));
+/// ```
+///
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct SceneLimits {
+    /// Maximum number of triangles in the scene, counting every copy made by instances and
+    /// scatters. By default this value is `10_000_000`.
+    pub max_triangles: usize,
+
+    /// Maximum number of times a ray can bounce off surfaces. By default this value is `16`.
+    pub max_recursion_depth: u8,
+
+    /// Maximum number of pixels of the rendered image. By default this value is the number of
+    /// pixels of an 8K image.
+    pub max_pixels: usize,
+
+    /// What to do with a scene that exceeds any of the limits.
+    pub on_exceeded: LimitAction,
+}
+
+/// What to do with a scene that exceeds it's [SceneLimits].
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum LimitAction {
+    /// The scene is rejected with a [LimitExceeded] error, as soon as the limit is exceeded.
+    #[default]
+    Error,
+
+    /// The scene is accepted with a warning, degrading it's quality where possible:
+    ///
+    /// * Groups with more triangles than the limit aren't divided.
+    /// * The recursion depth is capped to the limit.
+    /// * The image is scaled down to the limit, keeping it's aspect ratio.
+    ///
+    Degrade,
+}
+
+/// The error type when a scene exceeds it's [SceneLimits].
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Error)]
+pub enum LimitExceeded {
+    /// The scene has too many triangles.
+    #[error("the scene has {triangles} triangles, more than the limit of {max}")]
+    Triangles {
+        /// Number of triangles found so far.
+        triangles: usize,
+
+        /// Maximum number of triangles.
+        max: usize,
+    },
+
+    /// Rays bounce too many times.
+    #[error("rays bounce up to {depth} times, more than the limit of {max}")]
+    RecursionDepth {
+        /// Recursion depth of the render.
+        depth: u8,
+
+        /// Maximum recursion depth.
+        max: u8,
+    },
+
+    /// The image has too many pixels.
+    #[error("the image has {pixels} pixels, more than the limit of {max}")]
+    Pixels {
+        /// Number of pixels of the image.
+        pixels: usize,
+
+        /// Maximum number of pixels.
+        max: usize,
+    },
+}
+
+/// Limits of the scene being parsed in a thread, with the number of triangles placed so far and
+/// the limit it exceeded, if any.
+struct ParseLimits {
+    limits: SceneLimits,
+    triangles: usize,
+    exceeded: Option<LimitExceeded>,
+}
+
+thread_local! {
+    // Limits of the scene being parsed in this thread, if any.
+    static LIMITS: RefCell<Option<ParseLimits>> = const { RefCell::new(None) };
+}
+
+impl Default for SceneLimits {
+    fn default() -> Self {
+        Self {
+            max_triangles: 10_000_000,
+            max_recursion_depth: 16,
+            max_pixels: 7680 * 4320,
+            on_exceeded: LimitAction::Error,
+        }
+    }
+}
+
+impl SceneLimits {
+    /// Recursion depth to render a scene with, given the `depth` it was going to be rendered
+    /// with.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the depth is above the limit and the limits don't [degrade](LimitAction::Degrade)
+    /// the scene.
+    ///
+    pub fn recursion_depth(&self, depth: u8) -> Result<u8, LimitExceeded> {
+        if depth <= self.max_recursion_depth {
+            return Ok(depth);
+        }
+
+        self.exceeded(LimitExceeded::RecursionDepth {
+            depth,
+            max: self.max_recursion_depth,
+        })?;
+
+        Ok(self.max_recursion_depth)
+    }
+
+    /// Fails with the exceeded limit, or logs it as a warning when the scene is degraded instead.
+    fn exceeded(&self, limit: LimitExceeded) -> Result<(), LimitExceeded> {
+        match self.on_exceeded {
+            LimitAction::Error => Err(limit),
+            LimitAction::Degrade => {
+                log::warn!("{limit}, degrading the quality of the scene");
+                Ok(())
+            }
+        }
+    }
+}
+
+impl Scene {
+    /// Parses a scene described in JSON, guarding against scenes that exceed the given limits.
+    ///
+    /// The triangles are counted while the scene is parsed, so a scene with too many of them is
+    /// rejected as soon as it crosses the limit, before the rest of it is parsed or it's groups
+    /// are divided. The recursion depth isn't part of a scene, and it's checked with
+    /// [SceneLimits::recursion_depth] instead.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the spec is not valid JSON, if any of it's components is invalid, or if it exceeds
+    /// any of the limits and the limits don't [degrade](LimitAction::Degrade) the scene.
+    ///
+    pub fn from_json_with_limits(spec: &str, limits: &SceneLimits) -> Result<Self, SceneError> {
+        let guard = ParseGuard::set(
+            &LIMITS,
+            Some(ParseLimits {
+                limits: *limits,
+                triangles: 0,
+                exceeded: None,
+            }),
+        );
+
+        let scene = Self::from_json_with_mode(spec, ParseMode::Permissive);
+        let state = guard.restore();
+
+        if let Some(limit) = state.as_ref().and_then(|state| state.exceeded) {
+            return Err(limit.into());
+        }
+
+        let (mut scene, _) = scene?;
+        let triangles = state.map_or(0, |state| state.triangles);
+
+        if triangles > limits.max_triangles {
+            limits.exceeded(LimitExceeded::Triangles {
+                triangles,
+                max: limits.max_triangles,
+            })?;
+        }
+
+        let (width, height) = (scene.camera.width(), scene.camera.height());
+        let pixels = width.saturating_mul(height);
+
+        if pixels > limits.max_pixels {
+            limits.exceeded(LimitExceeded::Pixels {
+                pixels,
+                max: limits.max_pixels,
+            })?;
+
+            let scale = (limits.max_pixels as f64 / pixels as f64).sqrt();
+            let scaled = |size: usize| ((size as f64 * scale) as usize).max(1);
+
+            scene.camera = scene.camera.with_resolution(scaled(width), scaled(height));
+        }
+
+        Ok(scene)
+    }
+}
+
+/// Counts `triangles` more triangles placed in the scene being parsed in this thread, failing as
+/// soon as the scene exceeds it's limit, unless the scene is degraded instead.
+pub(crate) fn count_triangles(triangles: usize) -> Result<(), LimitExceeded> {
+    LIMITS.with_borrow_mut(|state| {
+        let Some(state) = state else {
+            return Ok(());
+        };
+
+        state.triangles = state.triangles.saturating_add(triangles);
+
+        if state.triangles <= state.limits.max_triangles
+            || state.limits.on_exceeded == LimitAction::Degrade
+        {
+            return Ok(());
+        }
+
+        let limit = LimitExceeded::Triangles {
+            triangles: state.triangles,
+            max: state.limits.max_triangles,
+        };
+
+        state.exceeded = Some(limit);
+        Err(limit)
+    })
+}
+
+/// Stops counting `triangles` that were counted but aren't placed in the scene being parsed in
+/// this thread, like the ones of a definition, which only count through it's instances.
+pub(crate) fn uncount_triangles(triangles: usize) {
+    LIMITS.with_borrow_mut(|state| {
+        if let Some(state) = state {
+            state.triangles = state.triangles.saturating_sub(triangles);
+        }
+    });
+}
+
+/// Whether a group of `triangles` triangles fits within the limits of the scene being parsed in
+/// this thread, if any.
+pub(crate) fn within_triangle_limit(triangles: usize) -> bool {
+    LIMITS.with_borrow(|state| {
+        state
+            .as_ref()
+            .is_none_or(|state| triangles <= state.limits.max_triangles)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::shape::Shape;
+
+    use super::*;
+
+    /// Triangles side by side along the `x` axis.
+    fn triangles(count: usize) -> String {
+        let triangle = |x: usize| {
+            format!(
+                r#"{{
+                    "type": "triangle",
+                    "vertices": [
+                        {{ "x": {x}.5, "y": 1.0, "z": 0.0 }},
+                        {{ "x": {x}.0, "y": 0.0, "z": 0.0 }},
+                        {{ "x": {x}.9, "y": 0.0, "z": 0.0 }}
+                    ]
+                }}"#
+            )
+        };
+
+        (0..count).map(triangle).collect::<Vec<_>>().join(",")
+    }
+
+    fn scene(objects: &str) -> String {
+        format!(
+            r#"{{
+                "camera": {{ "width": 10, "height": 10, "field_of_view": 60.0 }},
+                "objects": [{objects}]
+            }}"#
+        )
+    }
+
+    fn limits(max_triangles: usize, on_exceeded: LimitAction) -> SceneLimits {
+        SceneLimits {
+            max_triangles,
+            on_exceeded,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn a_scene_within_it_s_limits() {
+        let spec = scene(&triangles(3));
+        let scene = Scene::from_json_with_limits(&spec, &limits(3, LimitAction::Error)).unwrap();
+
+        assert_eq!(scene.world.objects.len(), 3);
+    }
+
+    #[test]
+    fn rejecting_a_group_with_too_many_triangles_before_dividing_it() {
+        let spec = scene(&format!(
+            r#"{{ "type": "group", "divide": 1, "children": [{}] }}"#,
+            triangles(4)
+        ));
+
+        assert!(matches!(
+            Scene::from_json_with_limits(&spec, &limits(3, LimitAction::Error)),
+            Err(SceneError::LimitExceeded(LimitExceeded::Triangles {
+                triangles: 4,
+                max: 3
+            }))
+        ));
+    }
+
+    #[test]
+    fn rejecting_a_scene_with_too_many_triangles_in_total() {
+        let spec = scene(&triangles(4));
+
+        assert!(matches!(
+            Scene::from_json_with_limits(&spec, &limits(3, LimitAction::Error)),
+            Err(SceneError::LimitExceeded(LimitExceeded::Triangles {
+                triangles: 4,
+                max: 3
+            }))
+        ));
+
+        // The limits only apply to the scene they're parsed with.
+        assert!(Scene::from_json(&spec).is_ok());
+    }
+
+    #[test]
+    fn rejecting_a_scene_as_soon_as_it_crosses_the_triangle_limit() {
+        let spec = scene(&triangles(10));
+
+        assert!(matches!(
+            Scene::from_json_with_limits(&spec, &limits(3, LimitAction::Error)),
+            Err(SceneError::LimitExceeded(LimitExceeded::Triangles {
+                triangles: 4,
+                max: 3
+            }))
+        ));
+    }
+
+    #[test]
+    fn the_limits_are_restored_when_parsing_panics() {
+        let parse = std::panic::catch_unwind(|| {
+            let _guard = ParseGuard::set(
+                &LIMITS,
+                Some(ParseLimits {
+                    limits: limits(3, LimitAction::Error),
+                    triangles: 0,
+                    exceeded: None,
+                }),
+            );
+
+            panic!("the parse failed");
+        });
+
+        assert!(parse.is_err());
+        assert!(LIMITS.with_borrow(Option::is_none));
+        assert!(count_triangles(10).is_ok());
+    }
+
+    #[test]
+    fn groups_with_too_many_triangles_are_not_divided_when_degrading() {
+        let spec = scene(&format!(
+            r#"{{ "type": "group", "divide": 1, "children": [{}] }}"#,
+            triangles(4)
+        ));
+
+        let divided = Scene::from_json(&spec).unwrap();
+        let degraded =
+            Scene::from_json_with_limits(&spec, &limits(3, LimitAction::Degrade)).unwrap();
+
+        let children = |scene: &Scene| {
+            let Shape::Group(group) = &scene.world.objects[0] else {
+                panic!("expected a group");
+            };
+
+            group.children().len()
+        };
+
+        assert!(children(&divided) < 4);
+        assert_eq!(children(&degraded), 4);
+        assert_eq!(degraded.world.objects[0].triangle_count(), 4);
+    }
+
+    #[test]
+    fn instances_count_every_copy_of_their_definition() {
+        let spec = format!(
+            r#"{{
+                "camera": {{ "width": 10, "height": 10, "field_of_view": 60.0 }},
+                "definitions": {{ "mesh": {{ "type": "group", "children": [{}] }} }},
+                "objects": [
+                    {{ "type": "instance", "of": "mesh" }},
+                    {{ "type": "instance", "of": "mesh" }}
+                ]
+            }}"#,
+            triangles(2)
+        );
+
+        assert!(Scene::from_json_with_limits(&spec, &limits(4, LimitAction::Error)).is_ok());
+        assert!(matches!(
+            Scene::from_json_with_limits(&spec, &limits(3, LimitAction::Error)),
+            Err(SceneError::LimitExceeded(LimitExceeded::Triangles { .. }))
+        ));
+    }
+
+    #[test]
+    fn scaling_down_an_image_with_too_many_pixels() {
+        let spec = r#"{ "camera": { "width": 400, "height": 100, "field_of_view": 60.0 } }"#;
+
+        let limits = SceneLimits {
+            max_pixels: 100 * 25,
+            on_exceeded: LimitAction::Degrade,
+            ..Default::default()
+        };
+
+        let original = Scene::from_json(spec).unwrap();
+        let scene = Scene::from_json_with_limits(spec, &limits).unwrap();
+
+        assert_eq!((scene.camera.width(), scene.camera.height()), (100, 25));
+        assert_eq!(
+            scene.camera.field_of_view(),
+            original.camera.field_of_view()
+        );
+
+        // The corners of the image still look in the same directions.
+        assert_eq!(
+            scene.camera.ray_for_position(0.0, 0.0),
+            original.camera.ray_for_position(0.0, 0.0)
+        );
+        assert_eq!(
+            scene.camera.ray_for_position(100.0, 25.0),
+            original.camera.ray_for_position(400.0, 100.0)
+        );
+    }
+
+    #[test]
+    fn capping_the_recursion_depth() {
+        let limits = SceneLimits {
+            max_recursion_depth: 8,
+            ..Default::default()
+        };
+
+        assert_eq!(limits.recursion_depth(5), Ok(5));
+        assert_eq!(
+            limits.recursion_depth(20),
+            Err(LimitExceeded::RecursionDepth { depth: 20, max: 8 })
+        );
+
+        let degrade = SceneLimits {
+            on_exceeded: LimitAction::Degrade,
+            ..limits
+        };
+
+        assert_eq!(degrade.recursion_depth(20), Ok(8));
+    }
+}
@@ -0,0 +1,227 @@
+use std::{
+    collections::HashMap,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use serde::Deserialize;
+
+use crate::material::MaterialOverride;
+
+use super::{Error, Scene};
+
+/// Name of the file with the material overrides of a scene, which is looked up in the directory of
+/// the scene by [MaterialOverrides::beside].
+pub const OVERRIDES_FILE: &str = "overrides.json";
+
+/// Tweaks to the materials of the objects of a scene, by the `name` of each object.
+///
+/// Overrides keep look-dev iterations out of the scene itself: materials are tweaked in an
+/// [overrides file](OVERRIDES_FILE) next to the scene, and applied on top of a copy of the scene
+/// right before it's rendered. Reading the overrides again before each render picks up the changes
+/// made to them in the meantime, without touching the scene.
+///
+/// # Deserialization
+///
+/// Overrides are deserialized from an object that maps the name of each object to a
+/// [MaterialOverride]. Overrides of groups apply to every shape inside of them.
+///
+/// ```json
+/// {
+///     "floor": { "reflectivity": 0.3 },
+///     "teapot": { "pattern": { "type": "solid", "color": { "red": 200, "green": 0, "blue": 0 } } }
+/// }
+/// ```
+///
+/// # Examples
+///
+/// ```
+/// use raytracer::scene::{MaterialOverrides, Scene};
+///
+/// let scene = Scene::from_json(r#"{
+///     "camera": { "width": 100, "height": 50, "field_of_view": 60.0 },
+///     "objects": [{ "type": "sphere", "name": "ball" }]
+/// }"#).unwrap();
+///
+/// let overrides = MaterialOverrides::from_json(r#"{ "ball": { "diffuse": 0.2 } }"#).unwrap();
+///
+/// let tweaked = scene.with_overrides(&overrides);
+///
+/// assert_eq!(tweaked.world.objects[0].material().diffuse, 0.2);
+/// assert_eq!(scene.world.objects[0].material().diffuse, 0.9);
+/// ```
+///
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+#[serde(transparent)]
+pub struct MaterialOverrides(HashMap<String, MaterialOverride>);
+
+impl MaterialOverrides {
+    /// Parses material overrides described in JSON.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the spec is not valid JSON or if any of it's overrides is invalid.
+    ///
+    pub fn from_json(spec: &str) -> Result<Self, Error> {
+        Ok(serde_json::from_str(spec)?)
+    }
+
+    /// Reads the material overrides at `path`, which are empty when there's no file at `path`.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the file exists but can't be read, or if it doesn't describe valid overrides.
+    ///
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let path = path.as_ref();
+
+        match fs::read_to_string(path) {
+            Ok(spec) => Self::from_json(&spec),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(source) => Err(Error::ReadOverrides {
+                path: path.to_path_buf(),
+                source,
+            }),
+        }
+    }
+
+    /// Reads the material overrides in the [overrides file](OVERRIDES_FILE) of the directory of
+    /// the scene at `scene_path`, which are empty when there's no such file.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the file exists but can't be read, or if it doesn't describe valid overrides.
+    ///
+    pub fn beside<P: AsRef<Path>>(scene_path: P) -> Result<Self, Error> {
+        Self::load(Self::path_beside(scene_path))
+    }
+
+    /// Whether there are no overrides.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Path of the [overrides file](OVERRIDES_FILE) in the directory of the scene at `scene_path`.
+    pub fn path_beside<P: AsRef<Path>>(scene_path: P) -> PathBuf {
+        scene_path
+            .as_ref()
+            .parent()
+            .unwrap_or(Path::new(""))
+            .join(OVERRIDES_FILE)
+    }
+}
+
+impl Scene {
+    /// Copy of the scene with the material overrides applied on top of the materials of it's
+    /// named objects. Overrides of names that no object of the scene has are skipped with a
+    /// warning.
+    pub fn with_overrides(&self, overrides: &MaterialOverrides) -> Self {
+        let mut scene = self.clone();
+
+        for (name, material_override) in &overrides.0 {
+            let applied = scene
+                .names
+                .get(name)
+                .is_some_and(|&id| scene.world.override_material(id, material_override).is_ok());
+
+            if !applied {
+                log::warn!("no object of the scene is named `{name}`, skipping it's overrides");
+            }
+        }
+
+        scene
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{color, pattern::Pattern3D, shape::Shape};
+
+    use super::*;
+
+    const SCENE: &str = r#"{
+        "camera": { "width": 10, "height": 10, "field_of_view": 60.0 },
+        "objects": [
+            { "type": "plane", "name": "floor" },
+            {
+                "type": "group",
+                "name": "props",
+                "children": [{ "type": "sphere" }, { "type": "cube" }]
+            },
+            { "type": "sphere", "material": { "diffuse": 0.5 } }
+        ]
+    }"#;
+
+    #[test]
+    fn parsing_the_names_of_the_objects_of_a_scene() {
+        let scene = Scene::from_json(SCENE).unwrap();
+
+        assert_eq!(scene.names.len(), 2);
+        assert_eq!(scene.names["floor"], scene.world.objects[0].id());
+        assert_eq!(scene.names["props"], scene.world.objects[1].id());
+    }
+
+    #[test]
+    fn applying_overrides_to_a_copy_of_a_scene() {
+        let scene = Scene::from_json(SCENE).unwrap();
+
+        let overrides = MaterialOverrides::from_json(
+            r#"{
+                "floor": { "reflectivity": 0.4 },
+                "props": { "pattern": { "type": "solid", "color": { "red": 255, "green": 0, "blue": 0 } } },
+                "missing": { "diffuse": 0.1 }
+            }"#,
+        )
+        .unwrap();
+
+        let tweaked = scene.with_overrides(&overrides);
+
+        assert_eq!(tweaked.world.objects[0].material().reflectivity, 0.4);
+
+        let Shape::Group(props) = &tweaked.world.objects[1] else {
+            panic!("expected a group");
+        };
+
+        for child in props.children() {
+            assert_eq!(
+                child.material().pattern,
+                Pattern3D::Solid(color::consts::RED)
+            );
+        }
+
+        // Objects without overrides and the original scene are left as they are.
+        assert_eq!(tweaked.world.objects[2].material().diffuse, 0.5);
+        assert_eq!(scene.world.objects[0].material().reflectivity, 0.0);
+    }
+
+    #[test]
+    fn loading_overrides_next_to_a_scene() {
+        let dir = std::env::temp_dir().join("raytracer-overrides-beside");
+        fs::create_dir_all(&dir).unwrap();
+
+        let scene_path = dir.join("scene.json");
+        let overrides_path = dir.join(OVERRIDES_FILE);
+
+        fs::remove_file(&overrides_path).ok();
+        assert!(MaterialOverrides::beside(&scene_path).unwrap().is_empty());
+
+        fs::write(&overrides_path, r#"{ "floor": { "diffuse": 0.3 } }"#).unwrap();
+        let overrides = MaterialOverrides::beside(&scene_path).unwrap();
+
+        assert_eq!(
+            overrides.0["floor"],
+            MaterialOverride {
+                diffuse: Some(0.3),
+                ..Default::default()
+            }
+        );
+
+        fs::write(&overrides_path, "{ not json").unwrap();
+        assert!(matches!(
+            MaterialOverrides::beside(&scene_path),
+            Err(Error::InvalidSpec(_))
+        ));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,39 +1,151 @@
+use std::sync::Arc;
+
+use serde::Deserialize;
+use thiserror::Error;
+
 use crate::{
-    intersection::Intersection,
-    material::Material,
+    intersection::{Intersection, Intersections},
+    material::{Material, MaterialOverride},
+    pattern::ImageTexture,
     ray::Ray,
-    transform::Transform,
+    render,
+    scene::{self, Checked, UnknownFields},
+    transform::{self, Transform},
     tuple::{Point, Vector},
 };
 
+mod billboard;
 mod bounding_box;
 mod cube;
+mod curve;
 mod cylinder;
 mod group;
+mod instance;
 mod object;
 mod plane;
+mod scatter;
 mod smooth_triangle;
 mod sphere;
 mod triangle;
 
 pub use self::{
+    billboard::{Billboard, BillboardBuilder, BillboardOrientation},
     cube::Cube,
+    curve::{Curve, CurveBuilder},
     cylinder::{Cylinder, CylinderBuilder},
-    group::{Group, GroupBuilder},
+    group::{DetailSelection, Group, GroupBuilder},
+    instance::{Instance, InstanceBuilder},
+    object::ShapeId,
     plane::Plane,
+    scatter::{Error as ScatterError, Scatter, ScatterRegion},
     smooth_triangle::SmoothTriangle,
     sphere::Sphere,
-    triangle::{Error as TriangleError, Triangle, TriangleBuilder},
+    triangle::{Error as TriangleError, Tangent, Triangle, TriangleBuilder},
 };
 
-pub(crate) use self::bounding_box::BoundingBox;
+pub(crate) use self::{bounding_box::BoundingBox, instance::EditedPrototypes};
+
+/// The error type when trying to deserialize a shape.
+#[derive(Debug, PartialEq, Error)]
+pub enum Error {
+    /// The error type when a triangle has collinear sides.
+    #[error(transparent)]
+    InvalidTriangle(#[from] TriangleError),
+
+    /// The error type when an instance refers to a definition that the scene doesn't have, or
+    /// that is defined after it.
+    #[error("no shape is defined as `{0}`")]
+    UndefinedShape(String),
+
+    /// The error type when the copies of a [Scatter] can't be placed.
+    #[error(transparent)]
+    InvalidScatter(#[from] ScatterError),
+
+    /// The error type when a group of [detail levels](Group::with_detail_levels) doesn't have one
+    /// threshold less than levels.
+    #[error("{levels} detail levels need {} thresholds, got {thresholds}", levels.saturating_sub(1))]
+    InvalidDetailLevels {
+        /// Number of levels of the group.
+        levels: usize,
+
+        /// Number of thresholds of the selection.
+        thresholds: usize,
+    },
+
+    /// The error type when a threshold of a group of [detail levels](Group::with_detail_levels)
+    /// is negative or NaN.
+    #[error("detail level thresholds cannot be negative or NaN, got {0}")]
+    InvalidDetailThreshold(f64),
+
+    /// The error type when a shape exceeds the [limits](scene::SceneLimits) of the scene being
+    /// parsed.
+    #[error(transparent)]
+    LimitExceeded(#[from] scene::LimitExceeded),
+}
+
+/// Half-size of the surface generated when tessellating unbounded shapes, such as planes or
+/// cylinders without a minimum or maximum.
+pub(crate) const UNBOUNDED_TESSELLATION_EXTENT: f64 = 100.0;
 
 /// Available types of shapes.
-#[derive(Clone, Debug, PartialEq)]
+///
+/// Every shape has a [ShapeId] that identifies it. Clones of a shape are new shapes with new
+/// identifiers, unlike the shapes of a cloned [World](crate::world::World).
+///
+/// # Deserialization
+///
+/// Shapes are deserialized from an object with a `type` field with the name of the shape, and the
+/// fields of that shape's builder. Every field is optional except for a triangle's `vertices` and
+/// a group's `children`. The `points` of a curve default to a straight line from the origin to
+/// `(0, 1, 0)`. A group can also be [divided](Group::divide) with the `divide` field, and it's
+/// children can be made [detail levels](Group::with_detail_levels) of the same model with the
+/// `detail_levels` field, which is a [DetailSelection].
+///
+/// ```json
+/// {
+///     "type": "group",
+///     "children": [
+///         { "type": "instance", "of": "detailed_tree" },
+///         { "type": "instance", "of": "simple_tree" }
+///     ],
+///     "detail_levels": { "distance": [30.0] }
+/// }
+/// ```
+///
+/// ```json
+/// {
+///     "type": "sphere",
+///     "material": { "transparency": 1.0, "index_of_refraction": "glass" },
+///     "transform": [
+///         { "type": "scaling", "x": 0.5, "y": 0.5, "z": 0.5 },
+///         { "type": "translation", "x": 0.0, "y": 1.0, "z": 0.0 }
+///     ]
+/// }
+/// ```
+///
+/// A `transform` can be either a single transformation or a list of transformations, which are
+/// applied in the same order they are listed.
+///
+/// Inside of a [scene](crate::scene::Scene), an `instance` is an [Instance] of one of the shapes in
+/// the `definitions` of the scene, placed with it's own `transform` on top of the transformation
+/// of the definition. Every instance of a definition shares it's geometry. An instance can also
+/// replace the `material` of every shape in the definition.
+///
+/// ```json
+/// { "type": "instance", "of": "tree", "transform": { "type": "translation", "x": 2.0, "y": 0.0, "z": 0.0 } }
+/// ```
+///
+/// A `scatter` is a group with random copies of a shape, as described by a [Scatter].
+///
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(try_from = "ShapeDeserializer")]
 pub enum Shape {
+    Billboard(billboard::Billboard),
     Cube(cube::Cube),
+    Curve(curve::Curve),
     Cylinder(cylinder::Cylinder),
     Group(group::Group),
+    Instance(instance::Instance),
     Plane(plane::Plane),
     SmoothTriangle(smooth_triangle::SmoothTriangle),
     Sphere(sphere::Sphere),
@@ -67,19 +179,147 @@ pub enum Shape {
 /// }));
 /// ```
 ///
-#[derive(Clone, Default)]
+#[derive(Clone, Default, Deserialize)]
+#[serde(default)]
 pub struct ShapeBuilder {
     /// Material of the shape.
     pub material: Material,
 
     /// Transform of the shape.
+    #[serde(deserialize_with = "transform::deserialize_chain")]
     pub transform: Transform,
 }
 
+#[derive(Deserialize)]
+#[serde(rename_all(deserialize = "snake_case"))]
+#[serde(tag = "type")]
+enum ShapeDeserializer {
+    Billboard(Checked<BillboardBuilder>),
+    Cube(Checked<ShapeBuilder>),
+    Curve(Checked<CurveBuilder>),
+    Cylinder(Checked<CylinderBuilder>),
+    Group {
+        children: Vec<Shape>,
+
+        #[serde(default, deserialize_with = "transform::deserialize_chain")]
+        transform: Transform,
+
+        divide: Option<usize>,
+
+        detail_levels: Option<DetailSelection>,
+
+        #[serde(flatten)]
+        _unknown: UnknownFields,
+    },
+    Instance {
+        of: String,
+
+        #[serde(default, deserialize_with = "transform::deserialize_chain")]
+        transform: Transform,
+
+        material: Option<Material>,
+
+        #[serde(flatten)]
+        _unknown: UnknownFields,
+    },
+    Plane(Checked<ShapeBuilder>),
+    Scatter(Box<Scatter>),
+    Sphere(Checked<ShapeBuilder>),
+    Triangle(Checked<TriangleBuilder>),
+}
+
+impl TryFrom<ShapeDeserializer> for Shape {
+    type Error = Error;
+
+    fn try_from(value: ShapeDeserializer) -> Result<Self, Self::Error> {
+        Ok(match value {
+            ShapeDeserializer::Billboard(builder) => {
+                Self::Billboard(Billboard::from(builder.inner))
+            }
+            ShapeDeserializer::Cube(builder) => Self::Cube(Cube::from(builder.inner)),
+            ShapeDeserializer::Curve(builder) => Self::Curve(Curve::from(builder.inner)),
+            ShapeDeserializer::Cylinder(builder) => Self::Cylinder(Cylinder::from(builder.inner)),
+            ShapeDeserializer::Group {
+                children,
+                transform,
+                divide,
+                detail_levels,
+                ..
+            } => {
+                let mut group = Group::from(GroupBuilder {
+                    children,
+                    transform,
+                });
+
+                if let Some(selection) = detail_levels {
+                    group = group.with_detail_levels(selection)?;
+                }
+
+                // The triangles of the children were counted as they were parsed.
+                let triangles = group.children.iter().map(Self::triangle_count).sum();
+                let within_limits = scene::within_triangle_limit(triangles);
+
+                if let Some(threshold) = divide {
+                    // Dividing a huge group can take longer than rendering it, so it's skipped
+                    // when the scene is degraded to fit within it's limits.
+                    if within_limits {
+                        group.divide(threshold);
+                    } else {
+                        log::warn!("skipped dividing a group of {triangles} triangles");
+                    }
+                }
+
+                Self::Group(group)
+            }
+            ShapeDeserializer::Instance {
+                of,
+                transform,
+                material,
+                ..
+            } => {
+                let prototype = scene::definition(&of).ok_or(Error::UndefinedShape(of))?;
+                scene::count_triangles(prototype.triangle_count())?;
+
+                Self::Instance(Instance::from(InstanceBuilder {
+                    prototype,
+                    transform,
+                    material,
+                }))
+            }
+            ShapeDeserializer::Plane(builder) => Self::Plane(Plane::from(builder.inner)),
+            ShapeDeserializer::Scatter(scatter) => {
+                // The prefab is replaced by it's copies, which are counted before any of them is
+                // placed.
+                let triangles = scatter.prefab.triangle_count();
+                scene::uncount_triangles(triangles);
+                scene::count_triangles(triangles.saturating_mul(scatter.count))?;
+
+                Self::Group(scatter.scatter()?)
+            }
+            ShapeDeserializer::Sphere(builder) => Self::Sphere(Sphere::from(builder.inner)),
+            ShapeDeserializer::Triangle(builder) => {
+                scene::count_triangles(1)?;
+                Self::Triangle(Triangle::try_from(builder.inner)?)
+            }
+        })
+    }
+}
+
 fn object_ray(ray: &Ray, transform_inverse: Transform) -> Ray {
     ray.transform(transform_inverse)
 }
 
+/// Whether `t` is strictly between `t_min` and `t_max`.
+fn within(t: f64, t_min: f64, t_max: f64) -> bool {
+    t > t_min && t < t_max
+}
+
+/// Splits the quad `a b c d`, given in counter-clockwise order, into two triangles with the same
+/// orientation.
+fn quad(a: Point, b: Point, c: Point, d: Point) -> [[Point; 3]; 2] {
+    [[a, b, c], [a, c, d]]
+}
+
 fn world_normal<F>(point: Point, transform_inverse: Transform, local_normal_at: F) -> Vector
 where
     F: Fn(Point) -> Vector,
@@ -96,53 +336,469 @@ where
 }
 
 impl Shape {
-    pub(crate) fn intersect(&self, ray: &Ray) -> Vec<Intersection<'_>> {
-        let object_ray = object_ray(ray, self.as_ref().transform_inverse);
+    /// Returns the unique identifier of the shape.
+    pub fn id(&self) -> ShapeId {
+        self.as_ref().id
+    }
+
+    /// Material of the shape. A group doesn't have a material of it's own, so it's the default
+    /// material for a group, and it's the default material for an instance that doesn't replace
+    /// the materials of it's prototype.
+    pub fn material(&self) -> &Material {
+        match self {
+            Self::Instance(Instance {
+                material: Some(material),
+                ..
+            }) => material,
+            _ => &self.as_ref().material,
+        }
+    }
+
+    /// Transform of the shape. The transforms of the groups a shape is in are already applied to
+    /// it, so this is the transform from the space of the shape to the space of the world.
+    pub fn transform(&self) -> Transform {
+        self.as_ref().transform
+    }
+
+    /// Minimum and maximum corners of the box that contains the shape in world space, such as the
+    /// corners of a cube. The corners of unbounded shapes, like planes, aren't finite.
+    pub fn bounds(&self) -> (Point, Point) {
+        let object = self.as_ref();
+
+        // A group's own bounding box is already in world space, because it's transform is baked
+        // into it's children.
+        let BoundingBox { min, max } = match self {
+            Self::Group(_) => object.bounding_box,
+            _ => object.parent_space_bounding_box,
+        };
+
+        (min, max)
+    }
+
+    /// Looks for the shape with the given identifier, either this shape itself or any of its
+    /// descendants if it's a group. The prototype of an instance is shared with other instances,
+    /// so it isn't searched.
+    pub(crate) fn find(&self, id: ShapeId) -> Option<&Shape> {
+        if self.id() == id {
+            return Some(self);
+        }
+
+        match self {
+            Self::Group(group) => group.children.iter().find_map(|child| child.find(id)),
+            _ => None,
+        }
+    }
 
+    /// Number of triangles of the shape, counting the ones of every descendant if it's a group.
+    pub(crate) fn triangle_count(&self) -> usize {
         match self {
-            Self::Cube(cube) => cube.intersect(self, &object_ray),
-            Self::Cylinder(cylinder) => cylinder.intersect(self, &object_ray),
-            Self::Plane(plane) => plane.intersect(self, &object_ray),
-            Self::SmoothTriangle(triangle) => triangle.intersect(self, &object_ray),
-            Self::Sphere(sphere) => sphere.local_intersect(self, &object_ray),
-            Self::Triangle(triangle) => triangle.intersect(self, &object_ray),
+            Self::Group(group) => group.children.iter().map(Self::triangle_count).sum(),
+            Self::Instance(instance) => instance.prototype.triangle_count(),
+            Self::SmoothTriangle(_) | Self::Triangle(_) => 1,
+            _ => 0,
+        }
+    }
+
+    /// Clones the shape keeping the identifiers of the shape and of every one of it's
+    /// descendants, unlike [Clone::clone], which gives the copy new ones.
+    pub(crate) fn clone_with_ids(&self) -> Self {
+        let mut copy = self.clone();
+        copy.keep_ids_of(self);
+        copy
+    }
+
+    fn keep_ids_of(&mut self, original: &Shape) {
+        self.as_mut().id = original.id();
+
+        if let (Self::Group(copy), Self::Group(original)) = (self, original) {
+            for (child, original) in copy.children.iter_mut().zip(&original.children) {
+                child.keep_ids_of(original);
+            }
+        }
+    }
+
+    /// Applies `edit` to the shape with the given identifier, either this shape itself or any of
+    /// it's descendants, and refits the bounding boxes of the groups that contain it. Returns
+    /// whether the shape was found.
+    pub(crate) fn edit<F>(&mut self, id: ShapeId, edit: F) -> bool
+    where
+        F: FnOnce(&mut Shape),
+    {
+        if self.id() == id {
+            edit(self);
+            return true;
+        }
+
+        let Self::Group(group) = self else {
+            return false;
+        };
+
+        let Some(child) = group
+            .children
+            .iter_mut()
+            .find(|child| child.find(id).is_some())
+        else {
+            return false;
+        };
+
+        child.edit(id, edit);
+        group.refit();
+
+        true
+    }
+
+    /// Replaces the transform of the shape, which is the transform from it's space to the space
+    /// of the world. The descendants of a group keep their placement relative to it.
+    pub(crate) fn set_transform(&mut self, transform: Transform) {
+        if let Self::Group(group) = self {
+            let change = transform * group.object_cache.transform_inverse;
+
+            for child in &mut group.children {
+                let child_transform = change * child.transform();
+                child.set_transform(child_transform);
+            }
+        }
+
+        let object = self.as_mut();
+        object.transform = transform;
+        object.transform_inverse = transform.inverse();
+
+        match self {
+            Self::Group(group) => group.refit(),
+            _ => {
+                let object = self.as_mut();
+                object.parent_space_bounding_box = object.bounding_box.transform(transform);
+            }
+        }
+
+        // Cached colors are stored by their position in world space, so the ones of the shape
+        // were computed where it used to be.
+        self.material().forget(self.id());
+    }
+
+    /// Replaces the material of the shape, or of every one of it's descendants if it's a group.
+    /// An instance replaces the material of it's prototype without changing the prototype.
+    pub(crate) fn set_material(&mut self, material: &Material) {
+        match self {
+            Self::Group(group) => {
+                for child in &mut group.children {
+                    child.set_material(material);
+                }
+            }
+            Self::Instance(instance) => instance.material = Some(material.clone()),
+            _ => self.as_mut().material = material.clone(),
+        }
+    }
+
+    /// Applies an override to the material of the shape, or of every one of it's descendants if
+    /// it's a group. An instance that doesn't replace the materials of it's prototype gets a copy
+    /// of the prototype with the override, unless no other instance shares it.
+    pub(crate) fn override_material(&mut self, material_override: &MaterialOverride) {
+        match self {
+            Self::Group(group) => {
+                for child in &mut group.children {
+                    child.override_material(material_override);
+                }
+            }
+            Self::Instance(instance) => match &mut instance.material {
+                Some(material) => material_override.apply_to(material),
+                None => Arc::make_mut(&mut instance.prototype).override_material(material_override),
+            },
+            _ => material_override.apply_to(&mut self.as_mut().material),
+        }
+    }
+
+    /// [Conserves the energy](Material::conserve_energy) of the material of the shape, or of every
+    /// one of it's descendants if it's a group. Returns the number of materials that were changed.
+    ///
+    /// The prototypes of instances are changed once, and stored in `edited` to be shared again by
+    /// the rest of their instances.
+    ///
+    pub(crate) fn conserve_energy(&mut self, edited: &mut EditedPrototypes) -> usize {
+        match self {
+            Self::Group(group) => group
+                .children
+                .iter_mut()
+                .map(|child| child.conserve_energy(edited))
+                .sum(),
+            Self::Instance(instance) => {
+                let replaced = instance
+                    .material
+                    .as_mut()
+                    .map_or(0, |material| usize::from(material.conserve_energy()));
+
+                replaced
+                    + instance.edit_prototype(edited, |prototype, edited| {
+                        prototype.conserve_energy(edited)
+                    })
+            }
+            _ => usize::from(self.as_mut().material.conserve_energy()),
+        }
+    }
+
+    /// Replaces every image texture of the shape, or of every one of it's descendants if it's a
+    /// group, by the image returned by `replace` for it.
+    ///
+    /// The prototypes of instances are changed once, and stored in `edited` to be shared again by
+    /// the rest of their instances.
+    ///
+    pub(crate) fn replace_images(
+        &mut self,
+        replace: &mut dyn FnMut(&Arc<ImageTexture>) -> Arc<ImageTexture>,
+        edited: &mut EditedPrototypes,
+    ) {
+        match self {
+            Self::Group(group) => {
+                for child in &mut group.children {
+                    child.replace_images(replace, edited);
+                }
+
+                return;
+            }
+            Self::Instance(instance) => {
+                if let Some(material) = &mut instance.material {
+                    material.replace_images(replace);
+                }
+
+                instance.edit_prototype(edited, |prototype, edited| {
+                    prototype.replace_images(replace, edited)
+                });
+
+                return;
+            }
+            Self::Billboard(billboard) => {
+                if let Some(texture) = &mut billboard.texture {
+                    *texture = replace(texture);
+                }
+            }
+            _ => (),
+        }
+
+        self.as_mut().material.replace_images(replace);
+    }
+
+    /// Center of the shape in world space, which is the center of it's bounding box, or it's
+    /// origin for unbounded shapes.
+    pub(crate) fn center(&self) -> Point {
+        let (min, max) = self.bounds();
+        let center = min + (max - min) * 0.5;
+
+        if [center.0.x, center.0.y, center.0.z]
+            .iter()
+            .all(|c| c.is_finite())
+        {
+            center
+        } else {
+            self.transform() * Point::new(0.0, 0.0, 0.0)
+        }
+    }
+
+    pub(crate) fn intersect(&self, ray: &Ray) -> Intersections<'_> {
+        self.intersect_within(ray, f64::NEG_INFINITY, f64::INFINITY)
+    }
+
+    /// Intersects the shape with a ray, keeping only the intersections that are strictly between
+    /// `t_min` and `t_max`.
+    ///
+    /// Every shape checks the range before building it's intersections, so hits outside of it are
+    /// never collected, and groups whose bounding box is outside of the range are skipped without
+    /// testing their children.
+    ///
+    pub(crate) fn intersect_within(&self, ray: &Ray, t_min: f64, t_max: f64) -> Intersections<'_> {
+        render::record(|stats| stats.intersection_tests += 1);
+
+        let object_ray = object_ray(ray, self.as_ref().transform_inverse);
+
+        let mut xs = match self {
+            Self::Billboard(billboard) => {
+                billboard.intersect_within(self, &object_ray, t_min, t_max)
+            }
+            Self::Cube(cube) => cube.intersect_within(self, &object_ray, t_min, t_max),
+            Self::Curve(curve) => curve.intersect_within(self, &object_ray, t_min, t_max),
+            Self::Cylinder(cylinder) => cylinder.intersect_within(self, &object_ray, t_min, t_max),
+            Self::Plane(plane) => plane.intersect_within(self, &object_ray, t_min, t_max),
+            Self::SmoothTriangle(triangle) => {
+                triangle.intersect_within(self, &object_ray, t_min, t_max)
+            }
+            Self::Sphere(sphere) => sphere.local_intersect_within(self, &object_ray, t_min, t_max),
+            Self::Triangle(triangle) => triangle.intersect_within(self, &object_ray, t_min, t_max),
 
             // Notice that here we pass the untransformed world ray instead of the `object` ray,
             // because a group's intersections are only the intersections of it's children, which
             // already take into account this conversion when their `Shape::intersect` method it's
             // called.
-            Self::Group(group) => group.local_intersect(ray),
-        }
+            Self::Group(group) => return group.local_intersect_within(ray, t_min, t_max),
+
+            // The prototype of an instance drops the points cut out by it's own materials.
+            Self::Instance(instance) => {
+                return instance.intersect_within(&object_ray, t_min, t_max)
+            }
+        };
+
+        // Points cut out by the texture of the material are dropped here, so every ray skips
+        // them, including shadow rays.
+        let pattern = &self.as_ref().material.pattern;
+        xs.retain(|i| !pattern.cuts_out(self, ray.position(i.t)));
+        xs
     }
 
-    pub(crate) fn normal_at(&self, point: Point, hit: &Intersection<'_>) -> Vector {
-        world_normal(
-            point,
-            self.as_ref().transform_inverse,
-            |object_point| match &self {
-                Self::Cube(inner_cube) => inner_cube.normal_at(object_point),
-                Self::Cylinder(inner_cylinder) => inner_cylinder.normal_at(object_point),
-                Self::Plane(inner_plane) => inner_plane.normal_at(object_point),
-                Self::SmoothTriangle(inner_triangle) => inner_triangle.normal_at(object_point, hit),
-                Self::Sphere(inner_sphere) => inner_sphere.local_normal_at(object_point),
-                Self::Triangle(inner_triangle) => inner_triangle.normal_at(object_point),
-
-                // A group is never going to be asked for it's normal at certain point because the
-                // normals are used to get shading information of an intersected point, however, a
-                // group's intersections are only a collection of it's children intersections, so
-                // the `normal_at` is called for a group's child instead that for the group itself.
-                Self::Group(_) => unreachable!(),
-            },
-        )
+    /// Normal of the shape at a point of it's surface hit by `ray`.
+    pub(crate) fn normal_at(&self, point: Point, hit: &Intersection<'_>, ray: &Ray) -> Vector {
+        let transform_inverse = self.as_ref().transform_inverse;
+
+        world_normal(point, transform_inverse, |object_point| match &self {
+            Self::Billboard(inner_billboard) => {
+                inner_billboard.normal_at(transform_inverse * ray.direction)
+            }
+            Self::Cube(inner_cube) => inner_cube.normal_at(object_point),
+            Self::Curve(inner_curve) => inner_curve.normal_at(object_point),
+            Self::Cylinder(inner_cylinder) => inner_cylinder.normal_at(object_point),
+            Self::Plane(inner_plane) => inner_plane.normal_at(object_point),
+            Self::SmoothTriangle(inner_triangle) => inner_triangle.normal_at(object_point, hit),
+            Self::Sphere(inner_sphere) => inner_sphere.local_normal_at(object_point),
+            Self::Triangle(inner_triangle) => inner_triangle.normal_at(object_point),
+
+            // A group is never going to be asked for it's normal at certain point because the
+            // normals are used to get shading information of an intersected point, however, a
+            // group's intersections are only a collection of it's children intersections, so
+            // the `normal_at` is called for a group's child instead that for the group itself.
+            Self::Group(_) => unreachable!(),
+
+            // Likewise, the intersections of an instance are the ones of it's prototype, and they
+            // know the placement of the instance to get the normal of the prototype.
+            Self::Instance(_) => unreachable!(),
+        })
+    }
+
+    /// Approximates the surface of the shape with triangles in world space.
+    ///
+    /// Curved surfaces are divided into `resolution` segments around their circumference, while
+    /// unbounded ones are clamped to [UNBOUNDED_TESSELLATION_EXTENT] units from their origin.
+    /// Triangles are wound counter-clockwise when looking at the outside of the surface, except
+    /// for triangles, which keep their vertices in the order they were defined.
+    ///
+    pub(crate) fn tessellate(&self, resolution: usize) -> Vec<[Point; 3]> {
+        let triangles = match self {
+            Self::Billboard(billboard) => billboard.tessellate(),
+            Self::Cube(cube) => cube.tessellate(),
+            Self::Curve(curve) => curve.tessellate(resolution),
+            Self::Cylinder(cylinder) => cylinder.tessellate(resolution),
+            Self::Instance(instance) => instance.prototype.tessellate(resolution),
+            Self::Plane(plane) => plane.tessellate(),
+            Self::SmoothTriangle(triangle) => triangle.triangle.tessellate(),
+            Self::Sphere(sphere) => sphere.tessellate(resolution),
+            Self::Triangle(triangle) => triangle.tessellate(),
+
+            // A group's transform is already baked into it's children, so they're tessellated
+            // directly. Only the most detailed level of a group of detail levels is kept.
+            Self::Group(group) if group.detail_levels.is_some() => {
+                return group
+                    .children
+                    .first()
+                    .map_or_else(Vec::new, |level| level.tessellate(resolution));
+            }
+            Self::Group(group) => {
+                return group
+                    .children
+                    .iter()
+                    .flat_map(|child| child.tessellate(resolution))
+                    .collect();
+            }
+        };
+
+        let transform = self.as_ref().transform;
+
+        triangles
+            .into_iter()
+            .map(|vertices| vertices.map(|vertex| transform * vertex))
+            .collect()
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::shape::{group::Group, sphere::Sphere};
+    use crate::{
+        assert_approx,
+        shape::{
+            group::{Group, GroupBuilder},
+            sphere::Sphere,
+        },
+    };
 
     use super::*;
 
+    #[test]
+    fn intersecting_a_shape_within_a_range() {
+        let shape = Shape::Sphere(Default::default());
+
+        let ray = Ray {
+            origin: Point::new(0.0, 0.0, -5.0),
+            direction: Vector::new(0.0, 0.0, 1.0),
+        };
+
+        let xs = shape.intersect_within(&ray, 0.0, 5.0);
+
+        assert_eq!(xs.len(), 1);
+        assert_approx!(xs[0].t, 4.0);
+
+        assert!(shape.intersect_within(&ray, 4.0, 6.0).is_empty());
+        assert_eq!(shape.intersect_within(&ray, 3.0, 7.0).len(), 2);
+    }
+
+    #[test]
+    fn every_kind_of_shape_only_collects_the_hits_within_a_range() {
+        let triangle = Triangle::try_from(TriangleBuilder {
+            material: Default::default(),
+            vertices: [
+                Point::new(0.0, 1.0, 0.0),
+                Point::new(-1.0, -1.0, 0.0),
+                Point::new(1.0, -1.0, 0.0),
+            ],
+        })
+        .unwrap();
+
+        let ray = Ray {
+            origin: Point::new(0.0, 0.0, -5.0),
+            direction: Vector::new(0.0, 0.0, 1.0),
+        };
+
+        for (shape, t) in [
+            (Shape::Cube(Default::default()), 6.0),
+            (Shape::Cylinder(Default::default()), 6.0),
+            (Shape::Triangle(triangle), 5.0),
+        ] {
+            let xs = shape.intersect_within(&ray, 4.5, 10.0);
+
+            assert_eq!(xs.len(), 1);
+            assert_approx!(xs[0].t, t);
+            assert!(shape.intersect_within(&ray, 6.5, 10.0).is_empty());
+        }
+    }
+
+    #[test]
+    fn a_group_outside_of_the_range_skips_its_children() {
+        let group = Shape::Group(Group::from(GroupBuilder {
+            children: [
+                Shape::Sphere(Default::default()),
+                Shape::Sphere(Default::default()),
+            ],
+            transform: Default::default(),
+        }));
+
+        let ray = Ray {
+            origin: Point::new(0.0, 0.0, -5.0),
+            direction: Vector::new(0.0, 0.0, 1.0),
+        };
+
+        render::take_thread_stats();
+
+        assert!(group.intersect_within(&ray, 0.0, 2.0).is_empty());
+        assert_eq!(render::take_thread_stats().intersection_tests, 1);
+
+        assert_eq!(group.intersect_within(&ray, 0.0, 5.0).len(), 2);
+        assert_eq!(render::take_thread_stats().intersection_tests, 3);
+    }
+
     #[test]
     fn intersecting_a_scaled_object_with_a_ray() {
         let ray = Ray {
@@ -237,6 +893,11 @@ mod tests {
                 object: child,
                 u: None,
                 v: None,
+                instance: None,
+            },
+            &Ray {
+                origin: Point::new(0.0, 0.0, -10.0),
+                direction: Vector::new(0.0, 0.0, 1.0),
             },
         );
 
@@ -258,4 +919,206 @@ mod tests {
         assert_eq!(bounding_box.min, Point::new(0.5, -5.0, 1.0));
         assert_eq!(bounding_box.max, Point::new(1.5, -1.0, 9.0));
     }
+
+    fn assert_outwards(triangles: &[[Point; 3]]) {
+        let origin = Point::new(0.0, 0.0, 0.0);
+
+        for [a, b, c] in triangles {
+            let normal = (*b - *a).cross(*c - *a);
+            assert!(normal.dot(*a - origin) > 0.0);
+        }
+    }
+
+    #[test]
+    fn tessellating_a_cube() {
+        let cube = Shape::Cube(Default::default());
+
+        let triangles = cube.tessellate(16);
+
+        assert_eq!(triangles.len(), 12);
+        assert_outwards(&triangles);
+
+        for vertex in triangles.iter().flatten() {
+            assert_eq!(vertex.0.x.abs(), 1.0);
+            assert_eq!(vertex.0.y.abs(), 1.0);
+            assert_eq!(vertex.0.z.abs(), 1.0);
+        }
+    }
+
+    #[test]
+    fn tessellating_a_sphere() {
+        let sphere = Shape::Sphere(Default::default());
+
+        let triangles = sphere.tessellate(8);
+
+        // 8 slices and 4 stacks, where the stacks touching the poles only have one triangle for
+        // each slice.
+        assert_eq!(triangles.len(), 8 * 2 + 8 * 2 * 2);
+        assert_outwards(&triangles);
+
+        for vertex in triangles.iter().flatten() {
+            assert_approx!((*vertex - Point::new(0.0, 0.0, 0.0)).magnitude(), 1.0);
+        }
+    }
+
+    #[test]
+    fn tessellating_a_closed_cylinder() {
+        let cylinder = Shape::Cylinder(Cylinder::from(CylinderBuilder {
+            min: -1.0,
+            max: 2.0,
+            closed: true,
+            ..Default::default()
+        }));
+
+        let triangles = cylinder.tessellate(6);
+
+        assert_eq!(triangles.len(), 6 * 4);
+
+        for vertex in triangles.iter().flatten() {
+            assert!(vertex.0.y == -1.0 || vertex.0.y == 2.0);
+        }
+    }
+
+    #[test]
+    fn tessellating_unbounded_shapes_clamps_their_extent() {
+        let cylinder = Shape::Cylinder(Default::default());
+        let plane = Shape::Plane(Default::default());
+
+        let triangles = cylinder.tessellate(6);
+
+        assert_eq!(triangles.len(), 6 * 2);
+
+        for vertex in triangles.iter().flatten() {
+            assert_approx!(vertex.0.y.abs(), UNBOUNDED_TESSELLATION_EXTENT);
+        }
+
+        let triangles = plane.tessellate(6);
+
+        assert_eq!(triangles.len(), 2);
+
+        for vertex in triangles.iter().flatten() {
+            assert_approx!(vertex.0.x.abs(), UNBOUNDED_TESSELLATION_EXTENT);
+            assert_approx!(vertex.0.z.abs(), UNBOUNDED_TESSELLATION_EXTENT);
+        }
+    }
+
+    #[test]
+    fn tessellating_a_shape_applies_its_transform() {
+        let cube = Shape::Cube(Cube::from(ShapeBuilder {
+            transform: Transform::translation(5.0, 0.0, 0.0),
+            ..Default::default()
+        }));
+
+        let triangles = cube.tessellate(16);
+
+        for vertex in triangles.iter().flatten() {
+            assert!(vertex.0.x == 4.0 || vertex.0.x == 6.0);
+        }
+    }
+
+    #[test]
+    fn tessellating_a_group_applies_the_transforms_of_its_ancestors() {
+        let inner = Group::from(GroupBuilder {
+            children: [Shape::Cube(Cube::from(ShapeBuilder {
+                transform: Transform::translation(5.0, 0.0, 0.0),
+                ..Default::default()
+            }))],
+            transform: Transform::scaling(2.0, 2.0, 2.0).unwrap(),
+        });
+
+        let outer = Shape::Group(Group::from(GroupBuilder {
+            children: [Shape::Group(inner)],
+            transform: Transform::translation(0.0, 10.0, 0.0),
+        }));
+
+        let triangles = outer.tessellate(16);
+
+        assert_eq!(triangles.len(), 12);
+
+        for vertex in triangles.iter().flatten() {
+            assert!(vertex.0.x == 8.0 || vertex.0.x == 12.0);
+            assert!(vertex.0.y == 8.0 || vertex.0.y == 12.0);
+        }
+    }
+
+    #[test]
+    fn tessellating_a_group_of_detail_levels_keeps_the_most_detailed_one() {
+        let group = Group::from(GroupBuilder {
+            children: [
+                Shape::Cube(Default::default()),
+                Shape::Sphere(Default::default()),
+            ],
+            transform: Default::default(),
+        })
+        .with_detail_levels(DetailSelection::Distance(vec![10.0]))
+        .unwrap();
+
+        assert_eq!(Shape::Group(group).tessellate(16).len(), 12);
+    }
+
+    #[test]
+    fn every_shape_has_a_unique_id() {
+        let s0 = Shape::Sphere(Default::default());
+        let s1 = Shape::Sphere(Default::default());
+        let g = Shape::Group(Default::default());
+
+        assert_ne!(s0.id(), s1.id());
+        assert_ne!(s0.id(), g.id());
+
+        // Equality only takes into account the properties of a shape, not its identity.
+        assert_eq!(s0, s1);
+    }
+
+    #[test]
+    fn a_shape_keeps_its_id_when_added_to_a_group() {
+        let s = Shape::Sphere(Default::default());
+        let id = s.id();
+
+        let g = Shape::Group(Group::from(GroupBuilder {
+            children: [s],
+            transform: Transform::translation(1.0, 0.0, 0.0),
+        }));
+
+        let child = g.find(id).unwrap();
+
+        assert_eq!(child.id(), id);
+        assert_eq!(
+            child.as_ref().transform,
+            Transform::translation(1.0, 0.0, 0.0)
+        );
+        assert_eq!(g.find(g.id()).map(Shape::id), Some(g.id()));
+    }
+
+    #[test]
+    fn reading_the_material_transform_and_bounds_of_a_shape() {
+        let material = Material {
+            diffuse: 0.2,
+            ..Default::default()
+        };
+
+        let cube = Shape::Cube(Cube::from(ShapeBuilder {
+            material: material.clone(),
+            transform: Transform::scaling(2.0, 2.0, 2.0).unwrap(),
+        }));
+
+        let group = Shape::Group(Group::from(GroupBuilder {
+            children: [cube],
+            transform: Transform::translation(1.0, 0.0, 0.0),
+        }));
+
+        let Shape::Group(inner_group) = &group else {
+            unreachable!();
+        };
+        let cube = &inner_group.children()[0];
+
+        let expected_bounds = (Point::new(-1.0, -2.0, -2.0), Point::new(3.0, 2.0, 2.0));
+
+        assert_eq!(cube.material(), &material);
+        assert_eq!(
+            cube.transform(),
+            Transform::translation(1.0, 0.0, 0.0) * Transform::scaling(2.0, 2.0, 2.0).unwrap()
+        );
+        assert_eq!(cube.bounds(), expected_bounds);
+        assert_eq!(group.bounds(), expected_bounds);
+    }
 }
@@ -0,0 +1,405 @@
+use std::{borrow::Cow, sync::Arc};
+
+use serde::{Deserialize, Deserializer};
+use smallvec::smallvec;
+
+use crate::{
+    intersection::{Intersection, Intersections},
+    material::Material,
+    pattern::{ImageTexture, Pattern3D},
+    ray::Ray,
+    transform::{self, Transform},
+    tuple::{Point, Vector},
+};
+
+use super::{bounding_box::BoundingBox, object::ObjectCache, Shape};
+
+/// Representation of a billboard, a square that turns to face every ray casted at it.
+///
+/// In object space the billboard is a square from `-1.0` to `1.0` along it's sides, centered at
+/// the origin, with it's up direction towards `+y`. Each ray sees it turned towards the origin of
+/// the ray, so a single flat shape with an image of a tree or a particle looks the same from any
+/// direction, which makes it much cheaper than the model it stands for.
+///
+/// # Examples
+///
+/// A billboard must be built from a [BillboardBuilder].
+///
+/// Building a tree that only turns around it's trunk.
+///
+/// ```no_run
+/// use std::sync::Arc;
+///
+/// use raytracer::{
+///     pattern::ImageTexture,
+///     shape::{Billboard, BillboardBuilder, BillboardOrientation, Shape},
+///     transform::Transform,
+/// };
+///
+/// let tree = Shape::Billboard(Billboard::from(BillboardBuilder {
+///     transform: Transform::scaling(1.0, 2.0, 1.0).unwrap(),
+///     orientation: BillboardOrientation::Cylindrical,
+///     texture: Some(Arc::new(ImageTexture::open("tree.png").unwrap())),
+///     ..Default::default()
+/// }));
+/// ```
+///
+#[derive(Clone, Debug, PartialEq)]
+pub struct Billboard {
+    pub(crate) object_cache: ObjectCache,
+    pub(crate) orientation: BillboardOrientation,
+    pub(crate) texture: Option<Arc<ImageTexture>>,
+    pub(crate) alpha_cutoff: f64,
+}
+
+/// Way in which a [Billboard] turns to face a ray.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all(deserialize = "snake_case"))]
+pub enum BillboardOrientation {
+    /// Turns freely in every direction, like a particle.
+    #[default]
+    Spherical,
+
+    /// Only turns around it's `y` axis, so it stays upright like a tree, and it's seen edge-on
+    /// from right above or below.
+    Cylindrical,
+}
+
+/// Builder for a billboard.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct BillboardBuilder {
+    /// Material of the billboard.
+    pub material: Material,
+
+    /// Transform of the billboard.
+    #[serde(deserialize_with = "transform::deserialize_chain")]
+    pub transform: Transform,
+
+    /// Way in which the billboard turns to face a ray.
+    pub orientation: BillboardOrientation,
+
+    /// Image shown on the billboard, which tints the color of it's material. The bottom row of
+    /// the image is at the bottom of the billboard.
+    #[serde(deserialize_with = "deserialize_texture")]
+    pub texture: Option<Arc<ImageTexture>>,
+
+    /// Points of the texture with an alpha below this value are cut out of the billboard, so rays
+    /// go through them, including shadow rays. By default this value is `0.5`.
+    pub alpha_cutoff: f64,
+}
+
+impl Default for Billboard {
+    fn default() -> Self {
+        Self::from(BillboardBuilder::default())
+    }
+}
+
+impl Default for BillboardBuilder {
+    fn default() -> Self {
+        Self {
+            material: Default::default(),
+            transform: Default::default(),
+            orientation: Default::default(),
+            texture: None,
+            alpha_cutoff: 0.5,
+        }
+    }
+}
+
+impl From<BillboardBuilder> for Billboard {
+    fn from(builder: BillboardBuilder) -> Self {
+        let BillboardBuilder {
+            material,
+            transform,
+            orientation,
+            texture,
+            alpha_cutoff,
+        } = builder;
+
+        // The billboard can turn in any direction around the origin, so the box has to contain
+        // every way in which it can be turned.
+        let extent = match orientation {
+            BillboardOrientation::Spherical => std::f64::consts::SQRT_2,
+            BillboardOrientation::Cylindrical => 1.0,
+        };
+
+        let object_cache = ObjectCache::new(
+            material,
+            transform,
+            BoundingBox {
+                min: Point::new(-extent, -extent, -extent),
+                max: Point::new(extent, extent, extent),
+            },
+        );
+
+        Self {
+            object_cache,
+            orientation,
+            texture,
+            alpha_cutoff,
+        }
+    }
+}
+
+fn deserialize_texture<'de, D>(deserializer: D) -> Result<Option<Arc<ImageTexture>>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Ok(Option::<ImageTexture>::deserialize(deserializer)?.map(Arc::new))
+}
+
+impl Billboard {
+    /// Way in which the billboard turns to face a ray.
+    pub fn orientation(&self) -> BillboardOrientation {
+        self.orientation
+    }
+
+    /// Image shown on the billboard, if any.
+    pub fn texture(&self) -> Option<&Arc<ImageTexture>> {
+        self.texture.as_ref()
+    }
+
+    /// Alpha below which points of the texture are cut out of the billboard.
+    pub fn alpha_cutoff(&self) -> f64 {
+        self.alpha_cutoff
+    }
+
+    pub(crate) fn intersect_within<'a>(
+        &self,
+        object: &'a Shape,
+        ray: &Ray,
+        t_min: f64,
+        t_max: f64,
+    ) -> Intersections<'a> {
+        let Some((normal, up)) = self.orientation_towards(ray.direction) else {
+            return smallvec![];
+        };
+
+        let right = normal.cross(up);
+        let origin = ray.origin - Point::new(0.0, 0.0, 0.0);
+
+        let t = -origin.dot(normal) / ray.direction.dot(normal);
+        if !super::within(t, t_min, t_max) {
+            return smallvec![];
+        }
+
+        let offset = origin + ray.direction * t;
+
+        let (x, y) = (offset.dot(right), offset.dot(up));
+        if x.abs() > 1.0 || y.abs() > 1.0 {
+            return smallvec![];
+        }
+
+        let uv = ((x + 1.0) / 2.0, (y + 1.0) / 2.0);
+        if self.alpha_at(uv) < self.alpha_cutoff {
+            return smallvec![];
+        }
+
+        smallvec![Intersection {
+            t,
+            object,
+            u: Some(uv.0),
+            v: Some(uv.1),
+            instance: None,
+        }]
+    }
+
+    pub(crate) fn tessellate(&self) -> Vec<[Point; 3]> {
+        // Without a ray to face, the billboard is tessellated facing `-z`.
+        super::quad(
+            Point::new(-1.0, -1.0, 0.0),
+            Point::new(1.0, -1.0, 0.0),
+            Point::new(1.0, 1.0, 0.0),
+            Point::new(-1.0, 1.0, 0.0),
+        )
+        .into()
+    }
+
+    /// Normal of the billboard when it's seen along `direction`, both in object space.
+    pub(crate) fn normal_at(&self, direction: Vector) -> Vector {
+        // Rays that don't see the billboard edge-on are the only ones that can hit it.
+        self.orientation_towards(direction)
+            .map_or(Vector::new(0.0, 0.0, -1.0), |(normal, _)| normal)
+    }
+
+    /// Material at the `u` and `v` coordinates of the billboard, with the color of the material
+    /// tinted by the texture.
+    pub(crate) fn material_at<'a>(
+        &self,
+        material: Cow<'a, Material>,
+        uv: (f64, f64),
+    ) -> Cow<'a, Material> {
+        let (Some(texture), Pattern3D::Solid(color)) = (&self.texture, &material.pattern) else {
+            return material;
+        };
+
+        Cow::Owned(Material {
+            pattern: Pattern3D::Solid(*color * texture.color_at(uv)),
+            ..material.into_owned()
+        })
+    }
+
+    fn alpha_at(&self, uv: (f64, f64)) -> f64 {
+        self.texture
+            .as_ref()
+            .map_or(1.0, |texture| texture.alpha_at(uv))
+    }
+
+    /// Normal and up direction of the billboard turned towards the origin of a ray casted along
+    /// `direction`, or `None` if the billboard can't be turned to face it.
+    fn orientation_towards(&self, direction: Vector) -> Option<(Vector, Vector)> {
+        let up = Vector::new(0.0, 1.0, 0.0);
+
+        match self.orientation {
+            BillboardOrientation::Spherical => {
+                let normal = (-direction).normalize().ok()?;
+
+                // Looking straight up or down, the up direction of the billboard is taken from
+                // the `z` axis instead.
+                let up = [up, Vector::new(0.0, 0.0, 1.0)]
+                    .into_iter()
+                    .find_map(|axis| (axis - normal * normal.dot(axis)).normalize().ok())?;
+
+                Some((normal, up))
+            }
+            BillboardOrientation::Cylindrical => {
+                let normal = Vector::new(-direction.0.x, 0.0, -direction.0.z)
+                    .normalize()
+                    .ok()?;
+
+                Some((normal, up))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{DynamicImage, RgbaImage};
+
+    use crate::{assert_approx, color};
+
+    use super::*;
+
+    fn ray(origin: Point, direction: Vector) -> Ray {
+        Ray { origin, direction }
+    }
+
+    /// Billboard whose left half is cut out, and whose right half is red.
+    fn half_cut_out() -> Shape {
+        let pixels = vec![0, 0, 0, 0, 255, 0, 0, 255];
+        let image = DynamicImage::ImageRgba8(RgbaImage::from_raw(2, 1, pixels).unwrap());
+
+        Shape::Billboard(Billboard::from(BillboardBuilder {
+            texture: Some(Arc::new(ImageTexture::from(&image))),
+            ..Default::default()
+        }))
+    }
+
+    #[test]
+    fn a_spherical_billboard_faces_rays_from_every_direction() {
+        let b = Shape::Billboard(Default::default());
+
+        for direction in [
+            Vector::new(0.0, 0.0, 1.0),
+            Vector::new(-1.0, 0.0, 0.0),
+            Vector::new(0.0, -1.0, 0.0),
+            Vector::new(1.0, 1.0, 1.0).normalize().unwrap(),
+        ] {
+            let r = ray(Point::new(0.0, 0.0, 0.0) + direction * -5.0, direction);
+            let xs = b.intersect(&r);
+
+            assert_eq!(xs.len(), 1);
+            assert_approx!(xs[0].t, 5.0);
+            assert_eq!(b.normal_at(r.position(xs[0].t), &xs[0], &r), -direction);
+        }
+    }
+
+    #[test]
+    fn the_coordinates_of_a_billboard_follow_its_right_and_up_directions() {
+        let b = Shape::Billboard(Default::default());
+
+        let r = ray(Point::new(0.5, 0.5, -5.0), Vector::new(0.0, 0.0, 1.0));
+        let xs = b.intersect(&r);
+
+        assert_approx!(xs[0].u.unwrap(), 0.75);
+        assert_approx!(xs[0].v.unwrap(), 0.75);
+
+        let r = ray(Point::new(-5.0, -0.5, 0.5), Vector::new(1.0, 0.0, 0.0));
+        let xs = b.intersect(&r);
+
+        assert_approx!(xs[0].u.unwrap(), 0.25);
+        assert_approx!(xs[0].v.unwrap(), 0.25);
+    }
+
+    #[test]
+    fn a_ray_misses_a_billboard_outside_of_its_square() {
+        let b = Shape::Billboard(Default::default());
+
+        let r = ray(Point::new(1.5, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+
+        assert!(b.intersect(&r).is_empty());
+    }
+
+    #[test]
+    fn a_cylindrical_billboard_only_turns_around_its_y_axis() {
+        let b = Shape::Billboard(Billboard::from(BillboardBuilder {
+            orientation: BillboardOrientation::Cylindrical,
+            ..Default::default()
+        }));
+
+        let direction = Vector::new(0.0, -1.0, 1.0).normalize().unwrap();
+        let r = ray(Point::new(0.0, 0.5, 0.0) + direction * -2.0, direction);
+        let xs = b.intersect(&r);
+
+        assert_eq!(xs.len(), 1);
+        assert_approx!(xs[0].v.unwrap(), 0.75);
+        assert_eq!(
+            b.normal_at(r.position(xs[0].t), &xs[0], &r),
+            Vector::new(0.0, 0.0, -1.0)
+        );
+
+        let r = ray(Point::new(0.0, 5.0, 0.0), Vector::new(0.0, -1.0, 0.0));
+
+        assert!(b.intersect(&r).is_empty());
+    }
+
+    #[test]
+    fn rays_go_through_the_cut_out_parts_of_a_billboard() {
+        let b = half_cut_out();
+
+        let r = ray(Point::new(-0.5, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+        assert!(b.intersect(&r).is_empty());
+
+        let r = ray(Point::new(0.5, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+        assert_eq!(b.intersect(&r).len(), 1);
+    }
+
+    #[test]
+    fn a_billboard_texture_tints_its_material() {
+        let b = half_cut_out();
+
+        let r = ray(Point::new(0.5, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+        let xs = b.intersect(&r);
+        let comps = xs[0].prepare_computation(&r, xs.clone());
+
+        assert_eq!(comps.material.pattern, Pattern3D::Solid(color::consts::RED));
+    }
+
+    #[test]
+    fn deserializing_a_billboard() {
+        let shape: Shape = serde_json::from_str(
+            r#"{ "type": "billboard", "orientation": "cylindrical", "alpha_cutoff": 0.25 }"#,
+        )
+        .unwrap();
+
+        let Shape::Billboard(billboard) = shape else {
+            panic!();
+        };
+
+        assert_eq!(billboard.orientation, BillboardOrientation::Cylindrical);
+        assert_eq!(billboard.texture, None);
+        assert_approx!(billboard.alpha_cutoff, 0.25);
+    }
+}
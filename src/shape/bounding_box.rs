@@ -11,12 +11,8 @@ pub struct BoundingBox {
 impl Default for BoundingBox {
     fn default() -> Self {
         Self {
-            min: Point::new(std::f64::INFINITY, std::f64::INFINITY, std::f64::INFINITY),
-            max: Point::new(
-                std::f64::NEG_INFINITY,
-                std::f64::NEG_INFINITY,
-                std::f64::NEG_INFINITY,
-            ),
+            min: Point::new(f64::INFINITY, f64::INFINITY, f64::INFINITY),
+            max: Point::new(f64::NEG_INFINITY, f64::NEG_INFINITY, f64::NEG_INFINITY),
         }
     }
 }
@@ -61,6 +57,16 @@ impl BoundingBox {
         self.contains_point(other.min) && self.contains_point(other.max)
     }
 
+    /// Checks if the boxes share any space, which boxes that only touch each other don't.
+    pub fn overlaps(&self, other: &BoundingBox) -> bool {
+        self.min.0.x < other.max.0.x
+            && other.min.0.x < self.max.0.x
+            && self.min.0.y < other.max.0.y
+            && other.min.0.y < self.max.0.y
+            && self.min.0.z < other.max.0.z
+            && other.min.0.z < self.max.0.z
+    }
+
     pub fn transform(self, transform: Transform) -> Self {
         let corners = [
             self.min,
@@ -78,9 +84,15 @@ impl BoundingBox {
         BoundingBox::from(corners)
     }
 
+    #[cfg(test)]
     pub fn intersect(&self, ray: &Ray) -> bool {
+        self.intersect_within(ray, f64::NEG_INFINITY, f64::INFINITY)
+    }
+
+    /// Checks if the ray passes through the box somewhere between `t_min` and `t_max`.
+    pub fn intersect_within(&self, ray: &Ray, t_min: f64, t_max: f64) -> bool {
         let (tmin, tmax) = cube::intersect_box_with_bouding_box(ray, self);
-        tmin < tmax
+        tmin < tmax && tmin < t_max && tmax > t_min
     }
 
     pub fn split(&self) -> (Self, Self) {
@@ -229,6 +241,45 @@ mod tests {
         }));
     }
 
+    #[test]
+    fn checking_to_see_if_two_boxes_overlap() {
+        let bounding_box = BoundingBox {
+            min: Point::new(-1.0, -1.0, -1.0),
+            max: Point::new(1.0, 1.0, 1.0),
+        };
+
+        for (min, max, expected) in [
+            (Point::new(0.0, 0.0, 0.0), Point::new(2.0, 2.0, 2.0), true),
+            (
+                Point::new(-0.5, -0.5, -0.5),
+                Point::new(0.5, 0.5, 0.5),
+                true,
+            ),
+            (
+                Point::new(1.0, -1.0, -1.0),
+                Point::new(2.0, 1.0, 1.0),
+                false,
+            ),
+            (
+                Point::new(-1.0, 2.0, -1.0),
+                Point::new(1.0, 3.0, 1.0),
+                false,
+            ),
+            (
+                Point::new(-3.0, -3.0, 1.5),
+                Point::new(3.0, 3.0, 3.0),
+                false,
+            ),
+        ] {
+            let other = BoundingBox { min, max };
+
+            assert_eq!(bounding_box.overlaps(&other), expected);
+            assert_eq!(other.overlaps(&bounding_box), expected);
+        }
+
+        assert!(!bounding_box.overlaps(&BoundingBox::default()));
+    }
+
     #[test]
     fn transforming_a_bounding_box() {
         let bounding_box0 = BoundingBox {
@@ -318,6 +369,25 @@ mod tests {
         }));
     }
 
+    #[test]
+    fn intersecting_a_ray_with_a_bounding_box_within_a_range() {
+        let bounding_box = BoundingBox {
+            min: Point::new(-1.0, -1.0, -1.0),
+            max: Point::new(1.0, 1.0, 1.0),
+        };
+
+        let ray = Ray {
+            origin: Point::new(0.0, 0.0, -5.0),
+            direction: Vector::new(0.0, 0.0, 1.0),
+        };
+
+        // The ray enters the box at `t = 4` and leaves it at `t = 6`.
+        assert!(!bounding_box.intersect_within(&ray, 0.0, 3.0));
+        assert!(bounding_box.intersect_within(&ray, 0.0, 5.0));
+        assert!(bounding_box.intersect_within(&ray, 5.0, 10.0));
+        assert!(!bounding_box.intersect_within(&ray, 7.0, f64::INFINITY));
+    }
+
     #[test]
     fn intersecting_a_ray_with_a_non_cubic_bounding_box() {
         let bounding_box = BoundingBox {
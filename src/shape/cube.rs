@@ -1,6 +1,8 @@
+use smallvec::smallvec;
+
 use crate::{
     float,
-    intersection::Intersection,
+    intersection::{Intersection, Intersections},
     ray::Ray,
     tuple::{Point, Tuple, Vector},
 };
@@ -84,8 +86,8 @@ fn check_axis(origin: f64, direction: f64, min: f64, max: f64) -> (f64, f64) {
         (tmin_numerator / direction, tmax_numerator / direction)
     } else {
         (
-            tmin_numerator * std::f64::INFINITY,
-            tmax_numerator * std::f64::INFINITY,
+            tmin_numerator * f64::INFINITY,
+            tmax_numerator * f64::INFINITY,
         )
     };
 
@@ -98,27 +100,66 @@ fn check_axis(origin: f64, direction: f64, min: f64, max: f64) -> (f64, f64) {
 
 impl Cube {
     /// Computes a cube's local intersections.
-    pub(crate) fn intersect<'a>(&self, object: &'a Shape, ray: &Ray) -> Vec<Intersection<'a>> {
+    #[cfg(test)]
+    pub(crate) fn intersect<'a>(&self, object: &'a Shape, ray: &Ray) -> Intersections<'a> {
+        self.intersect_within(object, ray, f64::NEG_INFINITY, f64::INFINITY)
+    }
+
+    /// Computes a cube's local intersections between `t_min` and `t_max`.
+    pub(crate) fn intersect_within<'a>(
+        &self,
+        object: &'a Shape,
+        ray: &Ray,
+        t_min: f64,
+        t_max: f64,
+    ) -> Intersections<'a> {
         let (tmin, tmax) = intersect_box_with_bouding_box(ray, &self.0.bounding_box);
 
-        if tmin > tmax {
-            vec![]
-        } else {
-            vec![
-                Intersection {
-                    t: tmin,
-                    object,
-                    u: None,
-                    v: None,
-                },
-                Intersection {
-                    t: tmax,
-                    object,
-                    u: None,
-                    v: None,
-                },
-            ]
+        if tmin > tmax || tmax <= t_min || tmin >= t_max {
+            return smallvec![];
         }
+
+        [tmin, tmax]
+            .into_iter()
+            .filter(|&t| super::within(t, t_min, t_max))
+            .map(|t| Intersection {
+                t,
+                object,
+                u: None,
+                v: None,
+                instance: None,
+            })
+            .collect()
+    }
+
+    pub(crate) fn tessellate(&self) -> Vec<[Point; 3]> {
+        let axes = [
+            Vector::new(1.0, 0.0, 0.0),
+            Vector::new(0.0, 1.0, 0.0),
+            Vector::new(0.0, 0.0, 1.0),
+        ];
+
+        let mut triangles = Vec::with_capacity(12);
+
+        for (i, normal) in axes.into_iter().enumerate() {
+            // `u` and `v` are chosen so that `u.cross(v)` points outwards of the face.
+            let u = axes[(i + 1) % 3];
+            let v = axes[(i + 2) % 3];
+
+            for (center, u, v) in [
+                (Point::new(0.0, 0.0, 0.0) + normal, u, v),
+                (Point::new(0.0, 0.0, 0.0) - normal, v, u),
+            ] {
+                triangles.extend(super::quad(
+                    center - u - v,
+                    center + u - v,
+                    center + u + v,
+                    center - u + v,
+                ));
+            }
+        }
+
+        triangles
     }
 
     /// Computes a cube's normal at a given point.
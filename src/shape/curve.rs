@@ -0,0 +1,544 @@
+use serde::Deserialize;
+use smallvec::{smallvec, SmallVec};
+
+use crate::{
+    float,
+    intersection::{Intersection, Intersections},
+    material::Material,
+    ray::Ray,
+    transform::{self, Transform},
+    tuple::{Point, Vector},
+};
+
+use super::{bounding_box::BoundingBox, object::ObjectCache, Shape};
+
+/// Representation of a curve, a thin tube swept along a cubic Bézier curve, such as a hair, a
+/// blade of grass or a cable.
+///
+/// The tube is made of a chain of capsules that follow the curve, each one with the radius of the
+/// curve at it's middle, so the radius tapers from the root to the tip of the curve. A curve is
+/// intersected on it's own, which is much cheaper than a group with a cylinder and a sphere for
+/// every segment of the curve.
+///
+/// # Examples
+///
+/// A curve must be built from a [CurveBuilder].
+///
+/// Building a strand of hair that bends to the side.
+///
+/// ```
+/// use raytracer::{
+///     shape::{Curve, CurveBuilder, Shape},
+///     tuple::Point,
+/// };
+///
+/// let hair = Shape::Curve(Curve::from(CurveBuilder {
+///     points: [
+///         Point::new(0.0, 0.0, 0.0),
+///         Point::new(0.0, 1.0, 0.0),
+///         Point::new(0.5, 1.5, 0.0),
+///         Point::new(1.0, 1.5, 0.0),
+///     ],
+///     root_radius: 0.02,
+///     tip_radius: 0.005,
+///     ..Default::default()
+/// }));
+/// ```
+///
+#[derive(Clone, Debug, PartialEq)]
+pub struct Curve {
+    pub(crate) object_cache: ObjectCache,
+    pub(crate) points: [Point; 4],
+    pub(crate) root_radius: f64,
+    pub(crate) tip_radius: f64,
+    capsules: Vec<Capsule>,
+}
+
+/// Builder for a curve.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct CurveBuilder {
+    /// Material of the curve.
+    pub material: Material,
+
+    /// Transform of the curve.
+    #[serde(deserialize_with = "transform::deserialize_chain")]
+    pub transform: Transform,
+
+    /// Control points of the cubic Bézier curve, from the root to the tip. The curve goes through
+    /// the first and the last points, and bends towards the other two.
+    pub points: [Point; 4],
+
+    /// Radius of the curve at it's root. By default this value is `0.05`.
+    pub root_radius: f64,
+
+    /// Radius of the curve at it's tip. By default this value is `0.05`.
+    pub tip_radius: f64,
+
+    /// Number of capsules the curve is divided into. By default this value is `16`.
+    pub segments: usize,
+}
+
+/// Segment of a curve with the same radius along it's whole length and rounded ends.
+#[derive(Copy, Clone, Debug, PartialEq)]
+struct Capsule {
+    start: Point,
+    end: Point,
+    radius: f64,
+}
+
+impl Default for Curve {
+    fn default() -> Self {
+        Self::from(CurveBuilder::default())
+    }
+}
+
+impl Default for CurveBuilder {
+    fn default() -> Self {
+        Self {
+            material: Default::default(),
+            transform: Default::default(),
+            points: [
+                Point::new(0.0, 0.0, 0.0),
+                Point::new(0.0, 1.0 / 3.0, 0.0),
+                Point::new(0.0, 2.0 / 3.0, 0.0),
+                Point::new(0.0, 1.0, 0.0),
+            ],
+            root_radius: 0.05,
+            tip_radius: 0.05,
+            segments: 16,
+        }
+    }
+}
+
+impl From<CurveBuilder> for Curve {
+    fn from(builder: CurveBuilder) -> Self {
+        let CurveBuilder {
+            material,
+            transform,
+            points,
+            root_radius,
+            tip_radius,
+            segments,
+        } = builder;
+
+        let (root_radius, tip_radius) = (root_radius.abs(), tip_radius.abs());
+        let segments = segments.max(1);
+
+        let capsules: Vec<_> = (0..segments)
+            .map(|i| {
+                let t0 = i as f64 / segments as f64;
+                let t1 = (i + 1) as f64 / segments as f64;
+                let middle = (t0 + t1) / 2.0;
+
+                Capsule {
+                    start: bezier(&points, t0),
+                    end: bezier(&points, t1),
+                    radius: root_radius + (tip_radius - root_radius) * middle,
+                }
+            })
+            .collect();
+
+        let mut bounding_box = BoundingBox::default();
+        for capsule in &capsules {
+            bounding_box.merge(capsule.bounding_box());
+        }
+
+        Self {
+            object_cache: ObjectCache::new(material, transform, bounding_box),
+            points,
+            root_radius,
+            tip_radius,
+            capsules,
+        }
+    }
+}
+
+impl Curve {
+    /// Control points of the Bézier curve, from the root to the tip.
+    pub fn points(&self) -> [Point; 4] {
+        self.points
+    }
+
+    /// Radius of the curve at it's root.
+    pub fn root_radius(&self) -> f64 {
+        self.root_radius
+    }
+
+    /// Radius of the curve at it's tip.
+    pub fn tip_radius(&self) -> f64 {
+        self.tip_radius
+    }
+
+    /// Number of capsules the curve is divided into.
+    pub fn segments(&self) -> usize {
+        self.capsules.len()
+    }
+
+    #[cfg(test)]
+    pub(crate) fn intersect<'a>(&self, object: &'a Shape, ray: &Ray) -> Intersections<'a> {
+        self.intersect_within(object, ray, f64::NEG_INFINITY, f64::INFINITY)
+    }
+
+    pub(crate) fn intersect_within<'a>(
+        &self,
+        object: &'a Shape,
+        ray: &Ray,
+        t_min: f64,
+        t_max: f64,
+    ) -> Intersections<'a> {
+        let mut xs = smallvec![];
+
+        for (i, capsule) in self.capsules.iter().enumerate() {
+            let hits = capsule.intersect(ray);
+
+            for t in hits.into_iter().filter(|&t| super::within(t, t_min, t_max)) {
+                // Capsules overlap where they join, so only the hits that aren't inside of any
+                // other capsule are on the surface of the curve. Hits on the surface of an earlier
+                // capsule too were already kept for that capsule.
+                let point = ray.position(t);
+                let buried = self.capsules.iter().enumerate().any(|(j, other)| {
+                    let distance = other.distance_to(point);
+                    (j != i && distance < -float::EPSILON) || (j < i && distance < float::EPSILON)
+                });
+
+                if !buried {
+                    xs.push(Intersection {
+                        t,
+                        object,
+                        u: None,
+                        v: None,
+                        instance: None,
+                    });
+                }
+            }
+        }
+
+        xs
+    }
+
+    pub(crate) fn normal_at(&self, point: Point) -> Vector {
+        // The capsules are never empty, because a curve has at least one segment.
+        #[allow(clippy::unwrap_used)]
+        let capsule = self
+            .capsules
+            .iter()
+            .min_by(|a, b| a.distance_to(point).total_cmp(&b.distance_to(point)))
+            .unwrap();
+
+        point - capsule.closest_point(point)
+    }
+
+    pub(crate) fn tessellate(&self, resolution: usize) -> Vec<[Point; 3]> {
+        let slices = resolution.max(3);
+
+        let mut triangles = Vec::with_capacity(2 * slices * self.capsules.len());
+
+        for capsule in &self.capsules {
+            let (side, up) = perpendicular_axes(capsule.end - capsule.start);
+
+            let point_at = |center: Point, slice: usize| {
+                let phi = std::f64::consts::TAU * slice as f64 / slices as f64;
+                center + (side * phi.cos() + up * phi.sin()) * capsule.radius
+            };
+
+            for slice in 0..slices {
+                triangles.extend(super::quad(
+                    point_at(capsule.start, slice),
+                    point_at(capsule.start, slice + 1),
+                    point_at(capsule.end, slice + 1),
+                    point_at(capsule.end, slice),
+                ));
+            }
+        }
+
+        triangles
+    }
+}
+
+impl Capsule {
+    fn bounding_box(&self) -> BoundingBox {
+        let r = Vector::new(self.radius, self.radius, self.radius);
+        BoundingBox::from([self.start - r, self.start + r, self.end - r, self.end + r])
+    }
+
+    /// Point of the axis of the capsule that is closest to `point`.
+    fn closest_point(&self, point: Point) -> Point {
+        let axis = self.end - self.start;
+        let length = axis.dot(axis);
+
+        if float::approx(length, 0.0) {
+            return self.start;
+        }
+
+        let t = ((point - self.start).dot(axis) / length).clamp(0.0, 1.0);
+        self.start + axis * t
+    }
+
+    /// Signed distance from `point` to the surface of the capsule, which is negative inside.
+    fn distance_to(&self, point: Point) -> f64 {
+        (point - self.closest_point(point)).magnitude() - self.radius
+    }
+
+    fn intersect(&self, ray: &Ray) -> SmallVec<[f64; 4]> {
+        let mut ts = smallvec![];
+
+        let axis = self.end - self.start;
+        let length = axis.dot(axis);
+        let origin = ray.origin - self.start;
+
+        let axis_direction = axis.dot(ray.direction);
+        let axis_origin = axis.dot(origin);
+
+        // Side of the capsule, which is a cylinder around it's axis cut at both ends.
+        if !float::approx(length, 0.0) {
+            let a = length * ray.direction.dot(ray.direction) - axis_direction.powi(2);
+            let b = length * ray.direction.dot(origin) - axis_origin * axis_direction;
+            let c =
+                length * origin.dot(origin) - axis_origin.powi(2) - self.radius.powi(2) * length;
+
+            let discriminant = b.powi(2) - a * c;
+
+            if !float::approx(a, 0.0) && discriminant >= 0.0 {
+                for t in [
+                    (-b - discriminant.sqrt()) / a,
+                    (-b + discriminant.sqrt()) / a,
+                ] {
+                    let y = axis_origin + t * axis_direction;
+                    if 0.0 < y && y < length {
+                        ts.push(t);
+                    }
+                }
+            }
+        }
+
+        // Rounded ends, which are the halves of a sphere at each end that are beyond the side.
+        let ends = if float::approx(length, 0.0) {
+            &[self.start][..]
+        } else {
+            &[self.start, self.end][..]
+        };
+
+        for (i, &center) in ends.iter().enumerate() {
+            let offset = ray.origin - center;
+
+            let a = ray.direction.dot(ray.direction);
+            let b = ray.direction.dot(offset);
+            let c = offset.dot(offset) - self.radius.powi(2);
+
+            let discriminant = b.powi(2) - a * c;
+            if discriminant < 0.0 {
+                continue;
+            }
+
+            for t in [
+                (-b - discriminant.sqrt()) / a,
+                (-b + discriminant.sqrt()) / a,
+            ] {
+                let y = axis_origin + t * axis_direction;
+                let beyond_side =
+                    ends.len() == 1 || (i == 0 && y <= 0.0) || (i == 1 && y >= length);
+
+                if beyond_side {
+                    ts.push(t);
+                }
+            }
+        }
+
+        ts
+    }
+}
+
+/// Point of a cubic Bézier curve with the given control points at parameter `t`.
+fn bezier(points: &[Point; 4], t: f64) -> Point {
+    let s = 1.0 - t;
+    let weights = [
+        s.powi(3),
+        3.0 * s.powi(2) * t,
+        3.0 * s * t.powi(2),
+        t.powi(3),
+    ];
+
+    let origin = Point::new(0.0, 0.0, 0.0);
+
+    points
+        .iter()
+        .zip(weights)
+        .fold(origin, |point, (control, weight)| {
+            point + (*control - origin) * weight
+        })
+}
+
+/// Two unit vectors perpendicular to `axis` and to each other, or the `x` and `z` axes when the
+/// axis is null.
+fn perpendicular_axes(axis: Vector) -> (Vector, Vector) {
+    let Ok(axis) = axis.normalize() else {
+        return (Vector::new(1.0, 0.0, 0.0), Vector::new(0.0, 0.0, 1.0));
+    };
+
+    let helper = if axis.0.x.abs() > 0.9 {
+        Vector::new(0.0, 1.0, 0.0)
+    } else {
+        Vector::new(1.0, 0.0, 0.0)
+    };
+
+    // The axis is never parallel to the helper, so their cross product is never zero.
+    #[allow(clippy::unwrap_used)]
+    let side = helper.cross(axis).normalize().unwrap();
+
+    (side, axis.cross(side))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::assert_approx;
+
+    use super::*;
+
+    fn straight_curve(radius: f64) -> Curve {
+        Curve::from(CurveBuilder {
+            root_radius: radius,
+            tip_radius: radius,
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn a_curve_goes_through_its_first_and_last_points() {
+        let points = [
+            Point::new(0.0, 0.0, 0.0),
+            Point::new(1.0, 2.0, 0.0),
+            Point::new(2.0, -1.0, 1.0),
+            Point::new(3.0, 0.0, 0.0),
+        ];
+
+        assert_eq!(bezier(&points, 0.0), points[0]);
+        assert_eq!(bezier(&points, 1.0), points[3]);
+        assert_eq!(bezier(&points, 0.5), Point::new(1.5, 0.375, 0.375));
+    }
+
+    #[test]
+    fn a_ray_strikes_the_side_of_a_curve() {
+        let curve = straight_curve(0.1);
+        let object = Shape::Curve(curve.clone());
+
+        let ray = Ray {
+            origin: Point::new(0.0, 0.5, -5.0),
+            direction: Vector::new(0.0, 0.0, 1.0),
+        };
+
+        let mut ts: Vec<_> = curve.intersect(&object, &ray).iter().map(|i| i.t).collect();
+        ts.sort_by(f64::total_cmp);
+
+        // The ray goes through the joint of two capsules, but only the surface of the curve is
+        // hit.
+        assert_eq!(ts.len(), 2);
+        assert_approx!(ts[0], 4.9);
+        assert_approx!(ts[1], 5.1);
+    }
+
+    #[test]
+    fn a_ray_strikes_the_rounded_tip_of_a_curve() {
+        let curve = straight_curve(0.1);
+        let object = Shape::Curve(curve.clone());
+
+        let ray = Ray {
+            origin: Point::new(0.0, 5.0, 0.0),
+            direction: Vector::new(0.0, -1.0, 0.0),
+        };
+
+        let mut ts: Vec<_> = curve.intersect(&object, &ray).iter().map(|i| i.t).collect();
+        ts.sort_by(f64::total_cmp);
+
+        assert_eq!(ts.len(), 2);
+        assert_approx!(ts[0], 3.9);
+        assert_approx!(ts[1], 5.1);
+    }
+
+    #[test]
+    fn a_ray_misses_a_curve() {
+        let curve = straight_curve(0.1);
+        let object = Shape::Curve(curve.clone());
+
+        let ray = Ray {
+            origin: Point::new(0.2, 0.5, -5.0),
+            direction: Vector::new(0.0, 0.0, 1.0),
+        };
+
+        assert!(curve.intersect(&object, &ray).is_empty());
+    }
+
+    #[test]
+    fn a_curve_tapers_from_its_root_to_its_tip() {
+        let curve = Curve::from(CurveBuilder {
+            root_radius: 0.2,
+            tip_radius: 0.0,
+            segments: 4,
+            ..Default::default()
+        });
+        let object = Shape::Curve(curve.clone());
+
+        let width_at = |y| {
+            let ray = Ray {
+                origin: Point::new(-5.0, y, 0.0),
+                direction: Vector::new(1.0, 0.0, 0.0),
+            };
+
+            let ts: Vec<_> = curve.intersect(&object, &ray).iter().map(|i| i.t).collect();
+            ts.iter().fold(f64::NEG_INFINITY, |a, &b| a.max(b))
+                - ts.iter().fold(f64::INFINITY, |a, &b| a.min(b))
+        };
+
+        assert!(width_at(0.1) > width_at(0.9));
+    }
+
+    #[test]
+    fn the_normal_of_a_curve_points_away_from_its_axis() {
+        let curve = straight_curve(0.1);
+
+        assert_eq!(
+            curve.normal_at(Point::new(0.0, 0.5, -0.1)),
+            Vector::new(0.0, 0.0, -0.1)
+        );
+        assert_eq!(
+            curve.normal_at(Point::new(0.0, 1.1, 0.0)),
+            Vector::new(0.0, 0.1, 0.0)
+        );
+    }
+
+    #[test]
+    fn deserializing_a_curve() {
+        let shape: Shape = serde_json::from_str(
+            r#"{
+                "type": "curve",
+                "points": [
+                    { "x": 0.0, "y": 0.0, "z": 0.0 },
+                    { "x": 0.0, "y": 1.0, "z": 0.0 },
+                    { "x": 1.0, "y": 1.0, "z": 0.0 },
+                    { "x": 1.0, "y": 2.0, "z": 0.0 }
+                ],
+                "tip_radius": 0.01,
+                "segments": 8
+            }"#,
+        )
+        .unwrap();
+
+        let Shape::Curve(curve) = shape else {
+            panic!();
+        };
+
+        assert_eq!(curve.points()[3], Point::new(1.0, 2.0, 0.0));
+        assert_approx!(curve.root_radius(), 0.05);
+        assert_approx!(curve.tip_radius(), 0.01);
+        assert_eq!(curve.segments(), 8);
+    }
+
+    #[test]
+    fn a_curve_has_a_bounding_box() {
+        let curve = straight_curve(0.1);
+        let bounding_box = curve.object_cache.bounding_box;
+
+        assert_eq!(bounding_box.min, Point::new(-0.1, -0.1, -0.1));
+        assert_eq!(bounding_box.max, Point::new(0.1, 1.1, 0.1));
+    }
+}
@@ -1,9 +1,12 @@
+use serde::Deserialize;
+use smallvec::smallvec;
+
 use crate::{
     float,
-    intersection::Intersection,
+    intersection::{Intersection, Intersections},
     material::Material,
     ray::Ray,
-    transform::Transform,
+    transform::{self, Transform},
     tuple::{Point, Tuple, Vector},
 };
 
@@ -16,7 +19,7 @@ use super::{bounding_box::BoundingBox, object::ObjectCache, Shape};
 /// A cylinder must be built from a [CylinderBuilder].
 ///
 /// Building a closed cylinder.
-/// 
+///
 /// ```
 /// use raytracer::{
 ///     material::Material,
@@ -47,20 +50,22 @@ pub struct Cylinder {
 }
 
 /// Builder for a cylinder.
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
 pub struct CylinderBuilder {
     /// Material of the cylinder.
     pub material: Material,
 
     /// Transform of the cylinder.
+    #[serde(deserialize_with = "transform::deserialize_chain")]
     pub transform: Transform,
 
     /// Minimum value for a cylinder relative to it's `y` axis. By default this value is
-    /// [std::f64::NEG_INFINITY].
+    /// [f64::NEG_INFINITY].
     pub min: f64,
 
     /// Maximum value for a cylinder relative to it's `y` axis. By default this value is
-    /// [std::f64::INFINITY].
+    /// [f64::INFINITY].
     pub max: f64,
 
     /// Determines wheter the cylinder caps should be closed or not.
@@ -78,8 +83,8 @@ impl Default for CylinderBuilder {
         Self {
             material: Default::default(),
             transform: Default::default(),
-            min: std::f64::NEG_INFINITY,
-            max: std::f64::INFINITY,
+            min: f64::NEG_INFINITY,
+            max: f64::INFINITY,
             closed: false,
         }
     }
@@ -123,13 +128,40 @@ impl PartialEq for Cylinder {
 }
 
 impl Cylinder {
-    pub(crate) fn intersect<'a>(&self, object: &'a Shape, ray: &Ray) -> Vec<Intersection<'a>> {
-        let mut xs = vec![];
+    /// Minimum value of the cylinder relative to it's `y` axis.
+    pub fn min(&self) -> f64 {
+        self.min
+    }
+
+    /// Maximum value of the cylinder relative to it's `y` axis.
+    pub fn max(&self) -> f64 {
+        self.max
+    }
+
+    /// Whether the caps of the cylinder are closed or not.
+    pub fn closed(&self) -> bool {
+        self.closed
+    }
+
+    #[cfg(test)]
+    pub(crate) fn intersect<'a>(&self, object: &'a Shape, ray: &Ray) -> Intersections<'a> {
+        self.intersect_within(object, ray, f64::NEG_INFINITY, f64::INFINITY)
+    }
+
+    pub(crate) fn intersect_within<'a>(
+        &self,
+        object: &'a Shape,
+        ray: &Ray,
+        t_min: f64,
+        t_max: f64,
+    ) -> Intersections<'a> {
+        let mut xs = smallvec![];
+        let within = |t| super::within(t, t_min, t_max);
 
         let a = ray.direction.0.x.powi(2) + ray.direction.0.z.powi(2);
 
         if float::approx(a, 0.0) {
-            return self.intersect_caps(object, ray, xs);
+            return self.intersect_caps(object, ray, xs, within);
         }
 
         let b = 2.0 * ray.origin.0.x * ray.direction.0.x + 2.0 * ray.origin.0.z * ray.direction.0.z;
@@ -147,26 +179,67 @@ impl Cylinder {
         let (t0, t1) = if t0 > t1 { (t1, t0) } else { (t0, t1) };
 
         let y0 = ray.origin.0.y + t0 * ray.direction.0.y;
-        if self.min < y0 && y0 < self.max {
+        if within(t0) && self.min < y0 && y0 < self.max {
             xs.push(Intersection {
                 t: t0,
                 object,
                 u: None,
                 v: None,
+                instance: None,
             });
         }
 
         let y1 = ray.origin.0.y + t1 * ray.direction.0.y;
-        if self.min < y1 && y1 < self.max {
+        if within(t1) && self.min < y1 && y1 < self.max {
             xs.push(Intersection {
                 t: t1,
                 object,
                 u: None,
                 v: None,
+                instance: None,
             });
         }
 
-        self.intersect_caps(object, ray, xs)
+        self.intersect_caps(object, ray, xs, within)
+    }
+
+    pub(crate) fn tessellate(&self, resolution: usize) -> Vec<[Point; 3]> {
+        let extent = super::UNBOUNDED_TESSELLATION_EXTENT;
+        let (min, max) = (self.min.max(-extent), self.max.min(extent));
+
+        let slices = resolution.max(3);
+
+        let point_at = |slice: usize, y: f64| {
+            let phi = std::f64::consts::TAU * slice as f64 / slices as f64;
+            Point::new(phi.cos(), y, phi.sin())
+        };
+
+        let mut triangles = Vec::with_capacity(4 * slices);
+
+        for slice in 0..slices {
+            triangles.extend(super::quad(
+                point_at(slice, min),
+                point_at(slice, max),
+                point_at(slice + 1, max),
+                point_at(slice + 1, min),
+            ));
+
+            if self.closed {
+                triangles.push([
+                    Point::new(0.0, max, 0.0),
+                    point_at(slice + 1, max),
+                    point_at(slice, max),
+                ]);
+
+                triangles.push([
+                    Point::new(0.0, min, 0.0),
+                    point_at(slice, min),
+                    point_at(slice + 1, min),
+                ]);
+            }
+        }
+
+        triangles
     }
 
     pub(crate) fn normal_at(&self, point: Point) -> Vector {
@@ -187,29 +260,32 @@ impl Cylinder {
         &self,
         object: &'a Shape,
         ray: &Ray,
-        mut xs: Vec<Intersection<'a>>,
-    ) -> Vec<Intersection<'a>> {
+        mut xs: Intersections<'a>,
+        within: impl Fn(f64) -> bool,
+    ) -> Intersections<'a> {
         if !self.closed || float::approx(ray.direction.0.y, 0.0) {
             return xs;
         }
 
         let t = (self.min - ray.origin.0.y) / ray.direction.0.y;
-        if check_cap(ray, t) {
+        if within(t) && check_cap(ray, t) {
             xs.push(Intersection {
                 t,
                 object,
                 u: None,
                 v: None,
+                instance: None,
             });
         }
 
         let t = (self.max - ray.origin.0.y) / ray.direction.0.y;
-        if check_cap(ray, t) {
+        if within(t) && check_cap(ray, t) {
             xs.push(Intersection {
                 t,
                 object,
                 u: None,
                 v: None,
+                instance: None,
             });
         }
 
@@ -230,6 +306,20 @@ mod tests {
 
     use super::*;
 
+    #[test]
+    fn reading_the_properties_of_a_cylinder() {
+        let c = Cylinder::from(CylinderBuilder {
+            min: 1.0,
+            max: 2.0,
+            closed: true,
+            ..Default::default()
+        });
+
+        assert_approx!(c.min(), 1.0);
+        assert_approx!(c.max(), 2.0);
+        assert!(c.closed());
+    }
+
     #[test]
     fn a_ray_misses_a_cylinder() {
         let c = Cylinder::default();
@@ -334,8 +424,8 @@ mod tests {
     fn the_default_minimum_and_maximum_for_a_cylinder() {
         let c = Cylinder::default();
 
-        assert_eq!(c.min, std::f64::NEG_INFINITY);
-        assert_eq!(c.max, std::f64::INFINITY);
+        assert_eq!(c.min, f64::NEG_INFINITY);
+        assert_eq!(c.max, f64::INFINITY);
     }
 
     #[test]
@@ -575,8 +665,8 @@ mod tests {
 
         let bounding_box = c.object_cache.bounding_box;
 
-        assert_eq!(bounding_box.max, Point::new(1.0, std::f64::INFINITY, 1.0));
-        assert_eq!(bounding_box.min, Point::new(-1.0, std::f64::NEG_INFINITY, -1.0));
+        assert_eq!(bounding_box.max, Point::new(1.0, f64::INFINITY, 1.0));
+        assert_eq!(bounding_box.min, Point::new(-1.0, f64::NEG_INFINITY, -1.0));
     }
 
     #[test]
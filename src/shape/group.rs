@@ -1,6 +1,32 @@
-use crate::{intersection::Intersection, ray::Ray, transform::Transform};
-
-use super::{bounding_box::BoundingBox, object::ObjectCache, Shape};
+use std::f64::consts::PI;
+
+use serde::Deserialize;
+use serde_json::json;
+use smallvec::smallvec;
+
+use crate::{
+    color::{self, Color},
+    float,
+    intersection::{Intersection, Intersections},
+    material::Material,
+    pattern::Pattern3D,
+    ray::Ray,
+    render,
+    transform::Transform,
+    tuple::{Point, Tuple},
+};
+
+use super::{
+    bounding_box::BoundingBox, cube::Cube, object::ObjectCache, Error, Shape, ShapeBuilder,
+};
+
+/// Colors of the boxes of a [Group::bvh_wireframe], by their depth in the hierarchy.
+const WIREFRAME_COLORS: [Color; 4] = [
+    color::consts::RED,
+    color::consts::GREEN,
+    color::consts::BLUE,
+    color::consts::WHITE,
+];
 
 /// Cluster of multiple shapes.
 ///
@@ -36,6 +62,36 @@ use super::{bounding_box::BoundingBox, object::ObjectCache, Shape};
 pub struct Group {
     pub(crate) children: Vec<Shape>,
     pub(crate) object_cache: ObjectCache,
+    pub(crate) detail_levels: Option<DetailSelection>,
+}
+
+/// Way in which a group of [detail levels](Group::with_detail_levels) picks the level that each
+/// ray sees.
+///
+/// Levels are picked from the position of the camera that renders the world, so shadow and
+/// reflection rays see the same level as the camera, even when they start right next to the
+/// model. Rays casted outside of a render, which have no camera, pick the level from where they
+/// start instead.
+///
+/// # Deserialization
+///
+/// A selection is deserialized from the name of it's criterion with it's thresholds.
+///
+/// ```json
+/// { "distance": [20.0, 50.0] }
+/// ```
+///
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(rename_all(deserialize = "snake_case"))]
+pub enum DetailSelection {
+    /// Switches to the next level at each of the given distances from the camera to the center of
+    /// the group.
+    Distance(Vec<f64>),
+
+    /// Switches to the next level when the size of the group on the screen, the angle in radians
+    /// that the sphere around it's bounding box covers from the camera, drops below each of the
+    /// given sizes.
+    ScreenSize(Vec<f64>),
 }
 
 /// Builder for a group.
@@ -60,6 +116,7 @@ where
                 transform_inverse: builder.transform.inverse(),
                 ..Default::default()
             },
+            detail_levels: None,
         };
 
         group.extend(builder.children);
@@ -68,29 +125,150 @@ where
 }
 
 impl Group {
+    /// Children of the group, in the order they were added, or grouped in subgroups if the group
+    /// was [divided](Group::divide).
+    pub fn children(&self) -> &[Shape] {
+        &self.children
+    }
+
+    /// Turns the children of the group into detail levels of the same model, from the most to the
+    /// least detailed, so each ray only sees the level picked by `selection`. This keeps scenes
+    /// with many copies of a heavy model tractable, as far away copies only test their simpler
+    /// levels.
+    ///
+    /// There must be one threshold less than levels. Thresholds can be given in any order, but
+    /// they can't be negative or NaN.
+    ///
+    /// # Examples
+    ///
+    /// A tree that's replaced by a sphere past 20 units, and by a cube past 50 units.
+    ///
+    /// ```
+    /// use raytracer::shape::{DetailSelection, Group, GroupBuilder, Shape};
+    ///
+    /// # let tree = Shape::Group(Default::default());
+    /// let tree = Group::from(GroupBuilder {
+    ///     children: [
+    ///         tree,
+    ///         Shape::Sphere(Default::default()),
+    ///         Shape::Cube(Default::default()),
+    ///     ],
+    ///     transform: Default::default(),
+    /// })
+    /// .with_detail_levels(DetailSelection::Distance(vec![20.0, 50.0]))
+    /// .unwrap();
+    /// ```
+    ///
+    pub fn with_detail_levels(self, selection: DetailSelection) -> Result<Self, Error> {
+        let mut selection = selection;
+
+        let thresholds = match &mut selection {
+            DetailSelection::Distance(distances) => {
+                distances.sort_by(f64::total_cmp);
+                distances.len()
+            }
+            DetailSelection::ScreenSize(sizes) => {
+                sizes.sort_by(|a, b| b.total_cmp(a));
+                sizes.len()
+            }
+        };
+
+        let (DetailSelection::Distance(values) | DetailSelection::ScreenSize(values)) = &selection;
+
+        if let Some(&threshold) = values
+            .iter()
+            .find(|threshold| threshold.is_nan() || **threshold < 0.0)
+        {
+            return Err(Error::InvalidDetailThreshold(threshold));
+        }
+
+        if self.children.len() != thresholds + 1 {
+            return Err(Error::InvalidDetailLevels {
+                levels: self.children.len(),
+                thresholds,
+            });
+        }
+
+        Ok(Self {
+            detail_levels: Some(selection),
+            ..self
+        })
+    }
+
+    /// Selection of the detail level that each ray sees, if the children of the group are
+    /// [detail levels](Group::with_detail_levels).
+    pub fn detail_levels(&self) -> Option<&DetailSelection> {
+        self.detail_levels.as_ref()
+    }
+
+    /// Index of the child seen from `origin`.
+    pub(crate) fn detail_level(&self, origin: Point) -> usize {
+        let Some(selection) = &self.detail_levels else {
+            return 0;
+        };
+
+        let BoundingBox { min, max } = self.bounding_box();
+        let radius = (max - min).magnitude() * 0.5;
+        let distance = (min + (max - min) * 0.5 - origin).magnitude();
+
+        // Comparisons with the distance to unbounded groups are false, so they always show their
+        // most detailed level.
+        match selection {
+            DetailSelection::Distance(distances) => distances
+                .iter()
+                .take_while(|threshold| distance >= **threshold)
+                .count(),
+            DetailSelection::ScreenSize(sizes) => {
+                let size = if distance <= radius {
+                    PI
+                } else {
+                    2.0 * (radius / distance).asin()
+                };
+
+                sizes
+                    .iter()
+                    .take_while(|threshold| size < **threshold)
+                    .count()
+            }
+        }
+    }
+
     /// Add a child to the group.
     pub fn push(&mut self, mut child: Shape) {
         Self::apply_transform_to_child(&mut child, self.object_cache.transform);
         self.object_cache
             .bounding_box
             .merge(child.as_ref().parent_space_bounding_box);
+        self.object_cache.parent_space_bounding_box = self.object_cache.bounding_box;
 
         self.children.push(child);
     }
 
+    /// Fits the bounding box of the group around it's children again, after any of them changed.
+    pub(crate) fn refit(&mut self) {
+        self.object_cache.bounding_box = self.bounding_box();
+
+        // The transform of the group is already baked into it's children, so it's bounding box is
+        // in the space of it's parent too.
+        self.object_cache.parent_space_bounding_box = self.object_cache.bounding_box;
+    }
+
     fn apply_transform_to_child(child: &mut Shape, transform: Transform) {
+        let new_transform = transform * child.as_ref().transform;
+
+        child.as_mut().transform = new_transform;
+        child.as_mut().transform_inverse = new_transform.inverse();
+
         if let Shape::Group(subgroup) = child {
             for child in &mut subgroup.children {
                 Self::apply_transform_to_child(child, transform);
             }
-        }
 
-        let new_transform = transform * child.as_ref().transform;
-
-        child.as_mut().transform = new_transform;
-        child.as_mut().transform_inverse = new_transform.inverse();
-        child.as_mut().parent_space_bounding_box =
-            child.as_ref().bounding_box.transform(new_transform);
+            subgroup.refit();
+        } else {
+            child.as_mut().parent_space_bounding_box =
+                child.as_ref().bounding_box.transform(new_transform);
+        }
     }
 
     /// Add multiple children at once.
@@ -103,15 +281,32 @@ impl Group {
         }
     }
 
-    pub(crate) fn local_intersect(&self, ray: &Ray) -> Vec<Intersection<'_>> {
-        if !self.bounding_box().intersect(ray) {
-            return vec![];
+    #[cfg(test)]
+    pub(crate) fn local_intersect(&self, ray: &Ray) -> Intersections<'_> {
+        self.local_intersect_within(ray, f64::NEG_INFINITY, f64::INFINITY)
+    }
+
+    /// Intersects the children of the group between `t_min` and `t_max`, skipping all of them at
+    /// once when the group's bounding box is outside of that range.
+    pub(crate) fn local_intersect_within(
+        &self,
+        ray: &Ray,
+        t_min: f64,
+        t_max: f64,
+    ) -> Intersections<'_> {
+        if !self.bounding_box().intersect_within(ray, t_min, t_max) {
+            return smallvec![];
         }
 
-        let mut intersections: Vec<_> = self
+        if self.detail_levels.is_some() {
+            let level = self.detail_level(render::viewpoint().unwrap_or(ray.origin));
+            return self.children[level].intersect_within(ray, t_min, t_max);
+        }
+
+        let mut intersections: Intersections<'_> = self
             .children
             .iter()
-            .flat_map(|child| child.intersect(ray))
+            .flat_map(|child| child.intersect_within(ray, t_min, t_max))
             .collect();
 
         Intersection::sort(&mut intersections);
@@ -127,10 +322,13 @@ impl Group {
     ///
     /// Try with different threshold values to see what division level works best for your group.
     ///
+    /// The children of a group of [detail levels](Group::with_detail_levels) are kept as they
+    /// are, but each one of them is divided.
+    ///
     /// # Arguments
     ///
     /// * `threshold` - The maximum number of children that a subgroup will have after dividing
-    /// their parent group.
+    ///   their parent group.
     ///
     /// # Examples
     ///
@@ -166,7 +364,7 @@ impl Group {
     /// ```
     ///
     pub fn divide(&mut self, threshold: usize) {
-        if threshold <= self.children.len() {
+        if threshold <= self.children.len() && self.detail_levels.is_none() {
             let (left_children, right_children) = self.partition_children();
 
             if !left_children.is_empty() {
@@ -230,6 +428,165 @@ impl Group {
         self.push(Shape::Group(subgroup));
     }
 
+    /// Describes the bounding volume hierarchy built by [Group::divide] as JSON, to find out why
+    /// it doesn't speed up the rendering of a particular group.
+    ///
+    /// Each node of the hierarchy is a group, with it's `depth`, it's `bounds`, the number of
+    /// `leaves` that are direct children of the group, the total number of `shapes` inside of it,
+    /// and the nodes of it's subgroups as it's `children`. Bounds that extend infinitely, such as
+    /// the bounds of a plane, are `null`.
+    ///
+    /// A hierarchy that helps has few leaves in each node, and nodes whose bounds don't overlap
+    /// much with the bounds of their siblings.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use raytracer::{
+    ///     shape::{Group, Shape, ShapeBuilder, Sphere},
+    ///     transform::Transform,
+    /// };
+    ///
+    /// let mut group = Group::default();
+    ///
+    /// for i in 0..8 {
+    ///     group.push(Shape::Sphere(Sphere::from(ShapeBuilder {
+    ///         transform: Transform::translation(f64::from(i) * 3.0, 0.0, 0.0),
+    ///         ..Default::default()
+    ///     })));
+    /// }
+    ///
+    /// group.divide(2);
+    ///
+    /// let bvh = group.dump_bvh();
+    ///
+    /// assert_eq!(bvh["shapes"], 8);
+    /// assert_eq!(bvh["bounds"]["min"]["x"], -1.0);
+    /// assert_eq!(bvh["children"][0]["depth"], 1);
+    ///
+    /// println!("{bvh:#}");
+    /// ```
+    ///
+    pub fn dump_bvh(&self) -> serde_json::Value {
+        self.bvh_node(0)
+    }
+
+    fn bvh_node(&self, depth: usize) -> serde_json::Value {
+        let point = |Point(Tuple { x, y, z, .. })| json!({ "x": x, "y": y, "z": z });
+        let BoundingBox { min, max } = self.bounding_box();
+
+        let children: Vec<_> = self
+            .children
+            .iter()
+            .filter_map(|child| match child {
+                Shape::Group(subgroup) => Some(subgroup.bvh_node(depth + 1)),
+                _ => None,
+            })
+            .collect();
+
+        json!({
+            "depth": depth,
+            "bounds": { "min": point(min), "max": point(max) },
+            "leaves": self.children.len() - children.len(),
+            "shapes": self.shape_count(),
+            "children": children,
+        })
+    }
+
+    /// Number of shapes inside of the group and all of it's subgroups, not counting the groups.
+    fn shape_count(&self) -> usize {
+        self.children
+            .iter()
+            .map(|child| match child {
+                Shape::Group(subgroup) => subgroup.shape_count(),
+                _ => 1,
+            })
+            .sum()
+    }
+
+    /// Builds the edges of the bounding boxes of the group and all of it's subgroups as thin
+    /// cubes of the given thickness, so the hierarchy can be seen by adding them to the world.
+    ///
+    /// Boxes are colored by their depth in the hierarchy, and are lit only by their ambient
+    /// light. Boxes that extend infinitely are left out.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use raytracer::{
+    ///     shape::{Group, Shape},
+    ///     world::World,
+    /// };
+    ///
+    /// let mut group = Group::default();
+    /// group.extend([Shape::Sphere(Default::default()), Shape::Sphere(Default::default())]);
+    /// group.divide(1);
+    ///
+    /// let wireframe = group.bvh_wireframe(0.01);
+    ///
+    /// let world = World {
+    ///     objects: vec![Shape::Group(group), Shape::Group(wireframe)],
+    ///     ..Default::default()
+    /// };
+    /// ```
+    ///
+    pub fn bvh_wireframe(&self, thickness: f64) -> Group {
+        let mut wireframe = Group::default();
+        self.add_wireframe_edges(&mut wireframe, thickness, 0);
+        wireframe
+    }
+
+    fn add_wireframe_edges(&self, wireframe: &mut Group, thickness: f64, depth: usize) {
+        let BoundingBox { min, max } = self.bounding_box();
+        let corners = [min, max].map(|Point(Tuple { x, y, z, .. })| [x, y, z]);
+
+        if corners.iter().flatten().all(|c| c.is_finite()) {
+            let material = Material {
+                pattern: Pattern3D::Solid(WIREFRAME_COLORS[depth % WIREFRAME_COLORS.len()]),
+                ambient: 1.0,
+                diffuse: 0.0,
+                specular: 0.0,
+                ..Default::default()
+            };
+
+            let [min, max] = corners;
+
+            // Each edge runs along one axis, and sits on one of the four corners of the box in
+            // the other two axes.
+            for axis in 0..3 {
+                let (b, c) = ((axis + 1) % 3, (axis + 2) % 3);
+
+                for corner in 0..4 {
+                    let side = |other: usize, bit: usize| {
+                        if (corner >> bit) & 1 == 1 {
+                            max[other]
+                        } else {
+                            min[other]
+                        }
+                    };
+
+                    let mut center = [0.0; 3];
+                    let mut half_size = [thickness / 2.0; 3];
+
+                    // Edges are as long as the box plus their thickness, so they meet at the
+                    // corners.
+                    center[axis] = (min[axis] + max[axis]) / 2.0;
+                    half_size[axis] = (max[axis] - min[axis] + thickness) / 2.0;
+                    center[b] = side(b, 0);
+                    center[c] = side(c, 1);
+
+                    wireframe.push(wireframe_edge(center, half_size, material.clone()));
+                }
+            }
+        }
+
+        for child in &self.children {
+            if let Shape::Group(subgroup) = child {
+                subgroup.add_wireframe_edges(wireframe, thickness, depth + 1);
+            }
+        }
+    }
+
     fn bounding_box(&self) -> BoundingBox {
         let mut bounding_box = BoundingBox::default();
 
@@ -242,13 +599,34 @@ impl Group {
     }
 }
 
+/// Cube centered at `center` that extends `half_size` along each axis.
+fn wireframe_edge([x, y, z]: [f64; 3], half_size: [f64; 3], material: Material) -> Shape {
+    // Boxes of flat shapes have edges without length, which are kept as tiny cubes instead of
+    // being scaled to zero.
+    let [sx, sy, sz] = half_size.map(|size| size.max(2.0 * float::EPSILON));
+
+    // Every scale is far enough from zero.
+    #[allow(clippy::unwrap_used)]
+    let transform = Transform::translation(x, y, z)
+        * Transform::scaling(sx, 1.0, 1.0).unwrap()
+        * Transform::scaling(1.0, sy, 1.0).unwrap()
+        * Transform::scaling(1.0, 1.0, sz).unwrap();
+
+    Shape::Cube(Cube::from(ShapeBuilder {
+        material,
+        transform,
+    }))
+}
+
 #[cfg(test)]
 mod tests {
+    use std::sync::Arc;
+
     use crate::{
         shape::{
             cylinder::{Cylinder, CylinderBuilder},
             sphere::Sphere,
-            ShapeBuilder,
+            Instance, InstanceBuilder, ShapeBuilder,
         },
         transform::Transform,
         tuple::{Point, Vector},
@@ -330,6 +708,27 @@ mod tests {
         assert_eq!(xs.len(), 2);
     }
 
+    #[test]
+    fn intersecting_a_transformed_group_inside_of_another_transformed_group() {
+        let inner_group = Group::from(GroupBuilder {
+            children: [Shape::Sphere(Default::default())],
+            transform: Transform::translation(3.0, 0.0, 0.0),
+        });
+
+        let outer_group = Shape::Group(Group::from(GroupBuilder {
+            children: [Shape::Group(inner_group)],
+            transform: Transform::translation(0.0, -5.0, 0.0),
+        }));
+
+        let ray = Ray {
+            origin: Point::new(3.0, -5.0, -10.0),
+            direction: Vector::new(0.0, 0.0, 1.0),
+        };
+
+        // The transform of the inner group is only applied once to it's bounding box.
+        assert_eq!(outer_group.intersect(&ray).len(), 2);
+    }
+
     #[test]
     fn a_group_has_a_bouding_box_that_contains_its_children() {
         let s0 = Shape::Sphere(Sphere::from(ShapeBuilder {
@@ -438,4 +837,224 @@ mod tests {
         assert_eq!(left_subgroup.children, vec![s0]);
         assert_eq!(right_subgroup.children, vec![s1]);
     }
+
+    fn row_of_spheres(count: i32) -> Group {
+        let mut group = Group::default();
+
+        for i in 0..count {
+            group.push(Shape::Sphere(Sphere::from(ShapeBuilder {
+                transform: Transform::translation(f64::from(i) * 3.0, 0.0, 0.0),
+                ..Default::default()
+            })));
+        }
+
+        group
+    }
+
+    #[test]
+    fn dumping_the_bounding_volume_hierarchy_of_a_group() {
+        let mut group = row_of_spheres(4);
+        group.divide(3);
+
+        let bvh = group.dump_bvh();
+
+        assert_eq!(bvh["depth"], 0);
+        assert_eq!(bvh["leaves"], 0);
+        assert_eq!(bvh["shapes"], 4);
+        assert_eq!(
+            bvh["bounds"],
+            serde_json::json!({
+                "min": { "x": -1.0, "y": -1.0, "z": -1.0 },
+                "max": { "x": 10.0, "y": 1.0, "z": 1.0 },
+            })
+        );
+
+        let children = bvh["children"].as_array().unwrap();
+
+        assert_eq!(children.len(), 2);
+        assert_eq!(children[0]["depth"], 1);
+        assert_eq!(children[0]["leaves"], 2);
+        assert_eq!(children[0]["children"], serde_json::json!([]));
+    }
+
+    #[test]
+    fn unbounded_groups_are_dumped_without_bounds() {
+        let group = Group::from(GroupBuilder {
+            children: [Shape::Plane(Default::default())],
+            transform: Default::default(),
+        });
+
+        let bvh = group.dump_bvh();
+
+        assert!(bvh["bounds"]["min"]["x"].is_null());
+        assert_eq!(bvh["leaves"], 1);
+        assert!(group.bvh_wireframe(0.1).children.is_empty());
+    }
+
+    #[test]
+    fn building_the_wireframe_of_a_bounding_volume_hierarchy() {
+        let mut group = row_of_spheres(4);
+        group.divide(3);
+
+        let wireframe = group.bvh_wireframe(0.1);
+
+        // The edges of the group and it's two subgroups.
+        assert_eq!(wireframe.children.len(), 3 * 12);
+
+        let BoundingBox { min, max } = wireframe.bounding_box();
+
+        assert_eq!(min, Point::new(-1.05, -1.05, -1.05));
+        assert_eq!(max, Point::new(10.05, 1.05, 1.05));
+
+        // Every corner of the outer box is covered by the edges that meet at it.
+        for corner in [Point::new(-1.0, -1.0, -1.0), Point::new(10.0, 1.0, -1.0)] {
+            let covering = wireframe.children[..12]
+                .iter()
+                .filter(|edge| {
+                    edge.as_ref()
+                        .parent_space_bounding_box
+                        .contains_point(corner)
+                })
+                .count();
+
+            assert_eq!(covering, 3);
+        }
+    }
+
+    fn detail_levels(selection: DetailSelection) -> Group {
+        Group::from(GroupBuilder {
+            children: [
+                Shape::Sphere(Default::default()),
+                Shape::Cube(Default::default()),
+                Shape::Sphere(Sphere::from(ShapeBuilder {
+                    transform: Transform::scaling(0.5, 0.5, 0.5).unwrap(),
+                    ..Default::default()
+                })),
+            ],
+            transform: Default::default(),
+        })
+        .with_detail_levels(selection)
+        .unwrap()
+    }
+
+    #[test]
+    fn a_ray_only_sees_the_detail_level_for_its_distance() {
+        let group = detail_levels(DetailSelection::Distance(vec![50.0, 10.0]));
+
+        for (z, level) in [(-5.0, 0), (-20.0, 1), (-80.0, 2)] {
+            let r = Ray {
+                origin: Point::new(0.0, 0.0, z),
+                direction: Vector::new(0.0, 0.0, 1.0),
+            };
+
+            let xs = group.local_intersect(&r);
+
+            assert_eq!(xs.len(), 2);
+            assert!(xs
+                .iter()
+                .all(|i| i.object.id() == group.children[level].id()));
+        }
+    }
+
+    #[test]
+    fn picking_a_detail_level_by_the_size_on_the_screen() {
+        let group = detail_levels(DetailSelection::ScreenSize(vec![0.1, 0.5]));
+
+        assert_eq!(group.detail_level(Point::new(0.0, 0.0, -1.0)), 0);
+        assert_eq!(group.detail_level(Point::new(0.0, 0.0, -2.0)), 0);
+        assert_eq!(group.detail_level(Point::new(0.0, 0.0, -10.0)), 1);
+        assert_eq!(group.detail_level(Point::new(0.0, 0.0, -100.0)), 2);
+    }
+
+    #[test]
+    fn detail_levels_are_picked_from_the_camera_of_a_render() {
+        let prototype = Arc::new(Shape::Group(detail_levels(DetailSelection::Distance(
+            vec![10.0, 50.0],
+        ))));
+        let Shape::Group(group) = prototype.as_ref() else {
+            unreachable!();
+        };
+
+        let level_hit_by = |shape: &Shape, origin| {
+            let r = Ray {
+                origin,
+                direction: Vector::new(0.0, 0.0, 1.0),
+            };
+
+            let xs = shape.intersect(&r);
+            group
+                .children
+                .iter()
+                .position(|child| child.id() == xs[0].object.id())
+                .unwrap()
+        };
+
+        // A ray that starts right next to the group, like a shadow ray, sees the level that the
+        // camera sees.
+        assert_eq!(level_hit_by(&prototype, Point::new(0.0, 0.0, -5.0)), 0);
+
+        let viewpoint = render::set_viewpoint(Point::new(0.0, 0.0, -80.0));
+        assert_eq!(level_hit_by(&prototype, Point::new(0.0, 0.0, -5.0)), 2);
+        drop(viewpoint);
+
+        // The camera is moved into the space of the instances the group is in.
+        let instance = Shape::Instance(Instance::from(InstanceBuilder {
+            prototype: Arc::clone(&prototype),
+            transform: Transform::translation(0.0, 0.0, 70.0),
+            material: None,
+        }));
+
+        let _viewpoint = render::set_viewpoint(Point::new(0.0, 0.0, -5.0));
+        assert_eq!(level_hit_by(&instance, Point::new(0.0, 0.0, 65.0)), 2);
+    }
+
+    #[test]
+    fn detail_levels_need_valid_thresholds() {
+        for threshold in [-1.0, f64::NAN] {
+            let group = Group::from(GroupBuilder {
+                children: [
+                    Shape::Sphere(Default::default()),
+                    Shape::Cube(Default::default()),
+                ],
+                transform: Default::default(),
+            });
+
+            let Err(Error::InvalidDetailThreshold(invalid)) =
+                group.with_detail_levels(DetailSelection::ScreenSize(vec![threshold]))
+            else {
+                panic!("expected an invalid threshold");
+            };
+
+            assert_eq!(invalid.to_bits(), threshold.to_bits());
+        }
+    }
+
+    #[test]
+    fn detail_levels_need_one_threshold_less_than_levels() {
+        let group = Group::from(GroupBuilder {
+            children: [Shape::Sphere(Default::default())],
+            transform: Default::default(),
+        });
+
+        assert_eq!(
+            group.with_detail_levels(DetailSelection::Distance(vec![10.0])),
+            Err(Error::InvalidDetailLevels {
+                levels: 1,
+                thresholds: 1
+            })
+        );
+    }
+
+    #[test]
+    fn dividing_a_group_of_detail_levels_keeps_the_levels() {
+        let mut group = detail_levels(DetailSelection::Distance(vec![10.0, 50.0]));
+        let ids: Vec<_> = group.children.iter().map(Shape::id).collect();
+
+        group.divide(1);
+
+        assert_eq!(
+            group.children.iter().map(Shape::id).collect::<Vec<_>>(),
+            ids
+        );
+    }
 }
@@ -0,0 +1,303 @@
+use std::{collections::HashMap, sync::Arc};
+
+use crate::{
+    intersection::{Intersections, Placement},
+    material::Material,
+    ray::Ray,
+    render,
+    transform::Transform,
+};
+
+use super::{bounding_box::BoundingBox, object::ObjectCache, Shape, ShapeId};
+
+/// Representation of an instance, a shape placed with it's own transform and material while
+/// sharing the geometry of another shape, it's prototype.
+///
+/// Every instance of a prototype refers to the same shape in memory, so a forest of thousands of
+/// trees only stores the triangles of one of them. Rays are moved into the space of the prototype
+/// to intersect it, so the prototype keeps it's own transform, which is applied before the one of
+/// the instance.
+///
+/// The shapes inside of the prototype belong to every one of it's instances at the same time, so
+/// they can't be found or edited through a [World](crate::world::World), only the instance itself.
+///
+/// # Examples
+///
+/// An instance must be built from an [InstanceBuilder].
+///
+/// Placing two spheres that share the same geometry.
+///
+/// ```
+/// use std::sync::Arc;
+///
+/// use raytracer::{
+///     shape::{Instance, InstanceBuilder, Shape},
+///     transform::Transform,
+/// };
+///
+/// let sphere = Arc::new(Shape::Sphere(Default::default()));
+///
+/// let [left, right] = [-2.0, 2.0].map(|x| {
+///     Shape::Instance(Instance::from(InstanceBuilder {
+///         prototype: Arc::clone(&sphere),
+///         transform: Transform::translation(x, 0.0, 0.0),
+///         material: None,
+///     }))
+/// });
+///
+/// assert_eq!(Arc::strong_count(&sphere), 3);
+/// ```
+///
+#[derive(Clone, Debug, PartialEq)]
+pub struct Instance {
+    pub(crate) object_cache: ObjectCache,
+    pub(crate) prototype: Arc<Shape>,
+    pub(crate) material: Option<Material>,
+}
+
+/// Builder for an instance.
+#[derive(Clone, Debug)]
+pub struct InstanceBuilder {
+    /// Shape shared by the instance.
+    pub prototype: Arc<Shape>,
+
+    /// Transform of the instance, applied after the one of the prototype.
+    pub transform: Transform,
+
+    /// Material that replaces the material of every shape of the prototype, if any.
+    pub material: Option<Material>,
+}
+
+/// Prototypes already changed by a pass over every shape of a world, by the identifier they had
+/// before the change, so the instances that shared a prototype keep sharing the changed one.
+pub(crate) type EditedPrototypes = HashMap<ShapeId, Arc<Shape>>;
+
+impl From<InstanceBuilder> for Instance {
+    fn from(builder: InstanceBuilder) -> Self {
+        let InstanceBuilder {
+            prototype,
+            transform,
+            material,
+        } = builder;
+
+        let (min, max) = prototype.bounds();
+        let bounding_box = BoundingBox { min, max };
+
+        Self {
+            object_cache: ObjectCache::new(Default::default(), transform, bounding_box),
+            prototype,
+            material,
+        }
+    }
+}
+
+impl Instance {
+    /// Shape shared by the instance.
+    pub fn prototype(&self) -> &Arc<Shape> {
+        &self.prototype
+    }
+
+    /// Intersects the prototype with a ray already moved into the space of the instance, keeping
+    /// the placement of the instance in every intersection.
+    pub(crate) fn intersect_within<'a>(
+        &'a self,
+        ray: &Ray,
+        t_min: f64,
+        t_max: f64,
+    ) -> Intersections<'a> {
+        let transform_inverse = self.object_cache.transform_inverse;
+
+        // The detail levels of the prototype are picked from the camera in the space of the
+        // instance, like the ray.
+        let _viewpoint = render::viewpoint()
+            .map(|viewpoint| render::set_viewpoint(transform_inverse * viewpoint));
+
+        let mut xs = self.prototype.intersect_within(ray, t_min, t_max);
+
+        // Instances of instances move the ray once for each one of them, so the placement of a
+        // hit goes from the world to the space of the innermost prototype. The material of the
+        // outermost instance replaces the ones of the instances inside of it.
+        for i in &mut xs {
+            i.instance = Some(match i.instance {
+                Some(inner) => Placement {
+                    transform_inverse: inner.transform_inverse * transform_inverse,
+                    material: self.material.as_ref().or(inner.material),
+                },
+                None => Placement {
+                    transform_inverse,
+                    material: self.material.as_ref(),
+                },
+            });
+        }
+
+        xs
+    }
+
+    /// Applies `edit` to the prototype of the instance, copying it first if other instances
+    /// share it, unless `edited` already has the result of the same edit for this prototype.
+    /// Returns what `edit` returns, or the default value if the prototype was already edited.
+    pub(crate) fn edit_prototype<T, F>(&mut self, edited: &mut EditedPrototypes, edit: F) -> T
+    where
+        T: Default,
+        F: FnOnce(&mut Shape, &mut EditedPrototypes) -> T,
+    {
+        let id = self.prototype.id();
+
+        if let Some(prototype) = edited.get(&id) {
+            self.prototype = Arc::clone(prototype);
+            return T::default();
+        }
+
+        let result = edit(Arc::make_mut(&mut self.prototype), edited);
+        edited.insert(id, Arc::clone(&self.prototype));
+
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        assert_approx,
+        intersection::Intersection,
+        shape::{ShapeBuilder, Sphere},
+        tuple::{Point, Vector},
+    };
+
+    use super::*;
+
+    fn instance(prototype: &Arc<Shape>, transform: Transform) -> Shape {
+        Shape::Instance(Instance::from(InstanceBuilder {
+            prototype: Arc::clone(prototype),
+            transform,
+            material: None,
+        }))
+    }
+
+    #[test]
+    fn an_instance_is_bounded_by_it_s_transformed_prototype() {
+        let prototype = Arc::new(Shape::Sphere(Sphere::from(ShapeBuilder {
+            transform: Transform::scaling(2.0, 2.0, 2.0).unwrap(),
+            ..Default::default()
+        })));
+
+        let shape = instance(&prototype, Transform::translation(5.0, 0.0, 0.0));
+
+        assert_eq!(
+            shape.bounds(),
+            (Point::new(3.0, -2.0, -2.0), Point::new(7.0, 2.0, 2.0))
+        );
+    }
+
+    #[test]
+    fn intersecting_an_instance_moves_the_ray_into_the_space_of_it_s_prototype() {
+        let prototype = Arc::new(Shape::Sphere(Default::default()));
+        let shape = instance(&prototype, Transform::translation(5.0, 0.0, 0.0));
+
+        let ray = Ray {
+            origin: Point::new(5.0, 0.0, -5.0),
+            direction: Vector::new(0.0, 0.0, 1.0),
+        };
+
+        let xs = shape.intersect(&ray);
+
+        assert_eq!(xs.len(), 2);
+        assert_approx!(xs[0].t, 4.0);
+        assert_approx!(xs[1].t, 6.0);
+        assert_eq!(xs[0].object.id(), prototype.id());
+
+        let missed = Ray {
+            origin: Point::new(0.0, 0.0, -5.0),
+            ..ray
+        };
+
+        assert!(shape.intersect(&missed).is_empty());
+    }
+
+    #[test]
+    fn the_normal_of_an_instance_follows_it_s_transform() {
+        let prototype = Arc::new(Shape::Sphere(Default::default()));
+        let shape = instance(
+            &prototype,
+            Transform::translation(0.0, 1.0, 0.0) * Transform::scaling(1.0, 0.5, 1.0).unwrap(),
+        );
+
+        let ray = Ray {
+            origin: Point::new(0.0, 5.0, 0.0),
+            direction: Vector::new(0.0, -1.0, 0.0),
+        };
+
+        let mut xs = shape.intersect(&ray);
+        let hit = Intersection::hit(&mut xs).unwrap();
+
+        assert_approx!(hit.t, 3.5);
+        assert_eq!(
+            hit.normal_at(ray.position(hit.t), &ray),
+            Vector::new(0.0, 1.0, 0.0)
+        );
+    }
+
+    #[test]
+    fn instances_of_instances_compose_their_transforms_and_materials() {
+        let prototype = Arc::new(Shape::Sphere(Default::default()));
+
+        let red = Material {
+            diffuse: 0.3,
+            ..Default::default()
+        };
+
+        let inner = Arc::new(Shape::Instance(Instance::from(InstanceBuilder {
+            prototype: Arc::clone(&prototype),
+            transform: Transform::translation(0.0, 0.0, 2.0),
+            material: Some(red.clone()),
+        })));
+        let outer = instance(&inner, Transform::translation(3.0, 0.0, 0.0));
+
+        let ray = Ray {
+            origin: Point::new(3.0, 0.0, -5.0),
+            direction: Vector::new(0.0, 0.0, 1.0),
+        };
+
+        let xs = outer.intersect(&ray);
+
+        assert_eq!(xs.len(), 2);
+        assert_approx!(xs[0].t, 6.0);
+        assert_eq!(xs[0].material(), &red);
+    }
+
+    #[test]
+    fn editing_the_prototypes_of_a_world_keeps_them_shared() {
+        let prototype = Arc::new(Shape::Sphere(Default::default()));
+
+        let mut shapes = [
+            instance(&prototype, Default::default()),
+            instance(&prototype, Transform::translation(3.0, 0.0, 0.0)),
+        ];
+        drop(prototype);
+
+        let mut edited = EditedPrototypes::new();
+        let mut edits = 0;
+
+        for shape in &mut shapes {
+            let Shape::Instance(instance) = shape else {
+                unreachable!();
+            };
+
+            edits += instance.edit_prototype(&mut edited, |prototype, _| {
+                prototype.set_material(&Material {
+                    diffuse: 0.3,
+                    ..Default::default()
+                });
+                1
+            });
+        }
+
+        let [Shape::Instance(first), Shape::Instance(second)] = &shapes else {
+            unreachable!();
+        };
+
+        assert_eq!(edits, 1);
+        assert!(Arc::ptr_eq(&first.prototype, &second.prototype));
+        assert_approx!(first.prototype.material().diffuse, 0.3);
+    }
+}
@@ -1,9 +1,29 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+
 use crate::{material::Material, transform::Transform};
 
 use super::{BoundingBox, Shape};
 
-#[derive(Clone, Debug, Default, PartialEq)]
+/// Handle that uniquely identifies a shape.
+///
+/// An identifier is not an index into a separate store of shapes. Shapes are owned by the world,
+/// group or instance they're in, and the intersections found while rendering borrow the shape they
+/// hit. Identifiers are how a shape is referred to without borrowing it, such as to find it again
+/// with [World::shape](crate::world::World::shape) or to edit it.
+///
+/// Every shape gets a new identifier when it's built, which it keeps even when it's moved into a
+/// group or a world. Cloning a single [Shape] gives the copy and every one of it's descendants new
+/// identifiers, so copies of the same shape in a world can be told apart. Cloning a whole
+/// [World](crate::world::World) keeps the identifiers of it's shapes instead, because the copy
+/// stands in for the original, so identifiers taken from the original, like the ones of the named
+/// objects of a scene, still find the same shapes in the copy.
+///
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ShapeId(usize);
+
+#[derive(Debug, Default)]
 pub(crate) struct ObjectCache {
+    pub id: ShapeId,
     pub material: Material,
     pub transform: Transform,
     pub transform_inverse: Transform,
@@ -11,12 +31,52 @@ pub(crate) struct ObjectCache {
     pub parent_space_bounding_box: BoundingBox,
 }
 
+impl ShapeId {
+    fn next() -> Self {
+        static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+        Self(NEXT_ID.fetch_add(1, Ordering::Relaxed))
+    }
+}
+
+impl Default for ShapeId {
+    fn default() -> Self {
+        Self::next()
+    }
+}
+
+impl Clone for ObjectCache {
+    fn clone(&self) -> Self {
+        Self {
+            id: ShapeId::next(),
+            material: self.material.clone(),
+            transform: self.transform,
+            transform_inverse: self.transform_inverse,
+            bounding_box: self.bounding_box,
+            parent_space_bounding_box: self.parent_space_bounding_box,
+        }
+    }
+}
+
+// Two shapes are equal when they have the same properties, regardless of their identity.
+impl PartialEq for ObjectCache {
+    fn eq(&self, other: &Self) -> bool {
+        self.material == other.material
+            && self.transform == other.transform
+            && self.transform_inverse == other.transform_inverse
+            && self.bounding_box == other.bounding_box
+            && self.parent_space_bounding_box == other.parent_space_bounding_box
+    }
+}
+
 impl AsRef<ObjectCache> for Shape {
     fn as_ref(&self) -> &ObjectCache {
         match self {
+            Self::Billboard(inner_billboard) => &inner_billboard.object_cache,
             Self::Cube(inner_cube) => &inner_cube.0,
+            Self::Curve(inner_curve) => &inner_curve.object_cache,
             Self::Cylinder(inner_cylinder) => &inner_cylinder.object_cache,
             Self::Group(inner_group) => &inner_group.object_cache,
+            Self::Instance(inner_instance) => &inner_instance.object_cache,
             Self::Plane(inner_plane) => &inner_plane.0,
             Self::SmoothTriangle(inner_triangle) => &inner_triangle.triangle.object_cache,
             Self::Sphere(inner_sphere) => &inner_sphere.0,
@@ -28,9 +88,12 @@ impl AsRef<ObjectCache> for Shape {
 impl AsMut<ObjectCache> for Shape {
     fn as_mut(&mut self) -> &mut ObjectCache {
         match self {
+            Self::Billboard(inner_billboard) => &mut inner_billboard.object_cache,
             Self::Cube(inner_cube) => &mut inner_cube.0,
+            Self::Curve(inner_curve) => &mut inner_curve.object_cache,
             Self::Cylinder(inner_cylinder) => &mut inner_cylinder.object_cache,
             Self::Group(inner_group) => &mut inner_group.object_cache,
+            Self::Instance(inner_instance) => &mut inner_instance.object_cache,
             Self::Plane(inner_plane) => &mut inner_plane.0,
             Self::SmoothTriangle(inner_triangle) => &mut inner_triangle.triangle.object_cache,
             Self::Sphere(inner_sphere) => &mut inner_sphere.0,
@@ -42,6 +105,7 @@ impl AsMut<ObjectCache> for Shape {
 impl ObjectCache {
     pub fn new(material: Material, transform: Transform, bounding_box: BoundingBox) -> Self {
         Self {
+            id: ShapeId::next(),
             material,
             transform,
             transform_inverse: transform.inverse(),
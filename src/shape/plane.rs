@@ -1,6 +1,8 @@
+use smallvec::smallvec;
+
 use crate::{
     float,
-    intersection::Intersection,
+    intersection::{Intersection, Intersections},
     ray::Ray,
     tuple::{Point, Vector},
 };
@@ -27,8 +29,8 @@ impl From<ShapeBuilder> for Plane {
         } = builder;
 
         let bounding_box = BoundingBox {
-            min: Point::new(std::f64::NEG_INFINITY, 0.0, std::f64::NEG_INFINITY),
-            max: Point::new(std::f64::INFINITY, 0.0, std::f64::INFINITY),
+            min: Point::new(f64::NEG_INFINITY, 0.0, f64::NEG_INFINITY),
+            max: Point::new(f64::INFINITY, 0.0, f64::INFINITY),
         };
 
         Self(ObjectCache::new(material, transform, bounding_box))
@@ -36,20 +38,49 @@ impl From<ShapeBuilder> for Plane {
 }
 
 impl Plane {
-    pub(crate) fn intersect<'a>(&self, object: &'a Shape, ray: &Ray) -> Vec<Intersection<'a>> {
-        if !float::approx(ray.direction.0.y, 0.0) {
-            let t = -ray.origin.0.y / ray.direction.0.y;
-            vec![Intersection {
+    #[cfg(test)]
+    pub(crate) fn intersect<'a>(&self, object: &'a Shape, ray: &Ray) -> Intersections<'a> {
+        self.intersect_within(object, ray, f64::NEG_INFINITY, f64::INFINITY)
+    }
+
+    pub(crate) fn intersect_within<'a>(
+        &self,
+        object: &'a Shape,
+        ray: &Ray,
+        t_min: f64,
+        t_max: f64,
+    ) -> Intersections<'a> {
+        if float::approx(ray.direction.0.y, 0.0) {
+            return smallvec![];
+        }
+
+        let t = -ray.origin.0.y / ray.direction.0.y;
+
+        if super::within(t, t_min, t_max) {
+            smallvec![Intersection {
                 t,
                 object,
                 u: None,
                 v: None,
+                instance: None,
             }]
         } else {
-            vec![]
+            smallvec![]
         }
     }
 
+    pub(crate) fn tessellate(&self) -> Vec<[Point; 3]> {
+        let extent = super::UNBOUNDED_TESSELLATION_EXTENT;
+
+        super::quad(
+            Point::new(-extent, 0.0, -extent),
+            Point::new(-extent, 0.0, extent),
+            Point::new(extent, 0.0, extent),
+            Point::new(extent, 0.0, -extent),
+        )
+        .into()
+    }
+
     pub(crate) fn normal_at(&self, _: Point) -> Vector {
         Vector::new(0.0, 1.0, 0.0)
     }
@@ -143,11 +174,11 @@ mod tests {
 
         assert_eq!(
             bounding_box.min,
-            Point::new(std::f64::NEG_INFINITY, 0.0, std::f64::NEG_INFINITY)
+            Point::new(f64::NEG_INFINITY, 0.0, f64::NEG_INFINITY)
         );
         assert_eq!(
             bounding_box.max,
-            Point::new(std::f64::INFINITY, 0.0, std::f64::INFINITY)
+            Point::new(f64::INFINITY, 0.0, f64::INFINITY)
         );
     }
 }
@@ -0,0 +1,689 @@
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use serde::Deserialize;
+use thiserror::Error;
+
+use crate::{
+    scene::UnknownFields,
+    transform::Transform,
+    tuple::{Point, Vector},
+};
+
+use super::{BoundingBox, Group, GroupBuilder, Shape};
+
+/// Number of segments used to approximate curved surfaces when scattering copies over them.
+const SURFACE_RESOLUTION: usize = 32;
+
+/// Number of random spots tried for each copy before giving up on placing it without overlapping
+/// the copies already placed.
+const MAX_PLACEMENT_ATTEMPTS: usize = 32;
+
+/// The error type when trying to scatter copies of a shape.
+#[derive(Debug, PartialEq, Error)]
+pub enum Error {
+    /// The error type when the range of scales of the copies is reversed, isn't finite, or
+    /// includes scales so small that they flatten the copies.
+    #[error("copies must be scaled between two positive scales, got {0} to {1}")]
+    InvalidScale(f64, f64),
+
+    /// The error type when the region has no space to place copies in.
+    #[error("scatter region has no surface or volume")]
+    EmptyRegion,
+
+    /// The error type when the corners of a volume aren't finite, or the smallest one has a
+    /// coordinate larger than the largest one.
+    #[error("scatter volume must be between two finite and ordered corners, got {0:?} to {1:?}")]
+    InvalidVolume(Point, Point),
+}
+
+/// Copies of a shape placed at random over a region, each one with a random rotation and scale,
+/// like the rocks of a field.
+///
+/// The copies are placed following a seed, so scattering with the same seed always places them
+/// at the same spots.
+///
+/// # Examples
+///
+/// ```
+/// use raytracer::{
+///     shape::{Scatter, ScatterRegion, Shape},
+///     tuple::{Point, Vector},
+/// };
+///
+/// let rocks = Scatter {
+///     prefab: Shape::Sphere(Default::default()),
+///     region: ScatterRegion::Surface(Box::new(Shape::Plane(Default::default()))),
+///     count: 50,
+///     min_scale: 0.1,
+///     max_scale: 0.3,
+///     max_rotation: Vector::new(0.0, 180.0, 0.0),
+///     seed: 7,
+///     avoid_overlaps: true,
+/// };
+///
+/// let field = rocks.scatter().unwrap();
+/// ```
+///
+/// # Deserialization
+///
+/// In a scene, a `scatter` object becomes a group with the copies. Only the `prefab`, the
+/// `region` and the `count` are required.
+///
+/// ```json
+/// {
+///     "type": "scatter",
+///     "prefab": { "type": "sphere" },
+///     "region": {
+///         "type": "volume",
+///         "min": { "x": -5.0, "y": 0.0, "z": -5.0 },
+///         "max": { "x": 5.0, "y": 2.0, "z": 5.0 }
+///     },
+///     "count": 50,
+///     "min_scale": 0.1,
+///     "max_scale": 0.3,
+///     "max_rotation": { "x": 0.0, "y": 180.0, "z": 0.0 },
+///     "seed": 7,
+///     "avoid_overlaps": true
+/// }
+/// ```
+///
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(try_from = "ScatterDeserializer")]
+pub struct Scatter {
+    /// Shape that is copied.
+    pub prefab: Shape,
+
+    /// Region over which the copies are placed.
+    pub region: ScatterRegion,
+
+    /// Number of copies.
+    pub count: usize,
+
+    /// Smallest scale of a copy, which is applied uniformly along every axis.
+    pub min_scale: f64,
+
+    /// Largest scale of a copy.
+    pub max_scale: f64,
+
+    /// Largest rotation of a copy around the `x`, `y` and `z` axes, in degrees, either way.
+    pub max_rotation: Vector,
+
+    /// Seed of the placement, so different scatters of the same shape can look different.
+    pub seed: u64,
+
+    /// Whether copies are kept from overlapping each other.
+    ///
+    /// Copies are kept apart by their bounding boxes, so shapes that don't fill their box, like
+    /// spheres, might be placed further apart than needed. A copy that can't be placed without
+    /// overlapping after a number of tries is left out, so crowded regions can end up with less
+    /// copies than requested.
+    ///
+    pub avoid_overlaps: bool,
+}
+
+/// Region over which the copies of a [Scatter] are placed.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(try_from = "ScatterRegionDeserializer")]
+pub enum ScatterRegion {
+    /// Copies are placed on the surface of a shape in world space, with the same density all
+    /// over it. Unbounded surfaces, like planes, are clamped around their origin.
+    Surface(Box<Shape>),
+
+    /// Copies are placed inside of a box aligned with the axes, between two of it's corners.
+    Volume {
+        /// Corner of the box with the smallest coordinates.
+        min: Point,
+
+        /// Corner of the box with the largest coordinates.
+        max: Point,
+    },
+}
+
+#[derive(Debug, Deserialize)]
+struct ScatterDeserializer {
+    prefab: Shape,
+    region: ScatterRegion,
+    count: usize,
+
+    #[serde(default = "default_scale")]
+    min_scale: f64,
+
+    #[serde(default = "default_scale")]
+    max_scale: f64,
+
+    max_rotation: Option<Vector>,
+
+    #[serde(default)]
+    seed: u64,
+
+    #[serde(default)]
+    avoid_overlaps: bool,
+
+    #[serde(flatten)]
+    _unknown: UnknownFields,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all(deserialize = "snake_case"))]
+#[serde(tag = "type")]
+enum ScatterRegionDeserializer {
+    Surface {
+        shape: Box<Shape>,
+
+        #[serde(flatten)]
+        _unknown: UnknownFields,
+    },
+    Volume {
+        min: Point,
+        max: Point,
+
+        #[serde(flatten)]
+        _unknown: UnknownFields,
+    },
+}
+
+fn default_scale() -> f64 {
+    1.0
+}
+
+impl TryFrom<ScatterDeserializer> for Scatter {
+    type Error = Error;
+
+    fn try_from(value: ScatterDeserializer) -> Result<Self, Self::Error> {
+        check_scale(value.min_scale, value.max_scale)?;
+
+        Ok(Self {
+            prefab: value.prefab,
+            region: value.region,
+            count: value.count,
+            min_scale: value.min_scale,
+            max_scale: value.max_scale,
+            max_rotation: value
+                .max_rotation
+                .unwrap_or_else(|| Vector::new(0.0, 0.0, 0.0)),
+            seed: value.seed,
+            avoid_overlaps: value.avoid_overlaps,
+        })
+    }
+}
+
+impl TryFrom<ScatterRegionDeserializer> for ScatterRegion {
+    type Error = Error;
+
+    fn try_from(value: ScatterRegionDeserializer) -> Result<Self, Self::Error> {
+        Ok(match value {
+            ScatterRegionDeserializer::Surface { shape, .. } => Self::Surface(shape),
+            ScatterRegionDeserializer::Volume { min, max, .. } => {
+                check_volume(min, max)?;
+                Self::Volume { min, max }
+            }
+        })
+    }
+}
+
+/// Checks that copies can be scaled by every scale between `min` and `max`.
+fn check_scale(min: f64, max: f64) -> Result<(), Error> {
+    let valid =
+        min > 0.0 && min <= max && max.is_finite() && Transform::scaling(min, min, min).is_ok();
+
+    if valid {
+        Ok(())
+    } else {
+        Err(Error::InvalidScale(min, max))
+    }
+}
+
+/// Checks that copies can be placed anywhere between the corners of a volume.
+fn check_volume(min: Point, max: Point) -> Result<(), Error> {
+    let Point(low) = min;
+    let Point(high) = max;
+
+    // The size of the volume is checked as well, because two finite corners can still be too far
+    // apart to pick a point between them.
+    let valid = [(low.x, high.x), (low.y, high.y), (low.z, high.z)]
+        .iter()
+        .all(|(low, high)| low <= high && (high - low).is_finite());
+
+    if valid {
+        Ok(())
+    } else {
+        Err(Error::InvalidVolume(min, max))
+    }
+}
+
+impl Scatter {
+    /// Places the copies of the shape, returning them in a group.
+    ///
+    /// Each copy is rotated and scaled around it's own origin before being moved to it's spot,
+    /// and gets it's own identifier.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the range of scales is invalid, if the region is a volume with invalid corners, or
+    /// if the region is a surface without area.
+    ///
+    pub fn scatter(&self) -> Result<Group, Error> {
+        check_scale(self.min_scale, self.max_scale)?;
+
+        let sampler = RegionSampler::new(&self.region)?;
+        let mut rng = StdRng::seed_from_u64(self.seed);
+
+        let mut copies = Vec::with_capacity(self.count);
+        let mut placed_boxes: Vec<BoundingBox> = vec![];
+
+        let attempts = if self.avoid_overlaps {
+            MAX_PLACEMENT_ATTEMPTS
+        } else {
+            1
+        };
+
+        for _ in 0..self.count {
+            let copy = (0..attempts)
+                .map(|_| self.random_copy(&sampler, &mut rng))
+                .find(|copy| {
+                    !self.avoid_overlaps
+                        || !placed_boxes
+                            .iter()
+                            .any(|placed| placed.overlaps(&copy.object_cache.bounding_box))
+                });
+
+            if let Some(copy) = copy {
+                placed_boxes.push(copy.object_cache.bounding_box);
+                copies.push(Shape::Group(copy));
+            }
+        }
+
+        if copies.len() < self.count {
+            log::warn!(
+                "could only scatter {} of {} copies without overlapping them",
+                copies.len(),
+                self.count
+            );
+        }
+
+        Ok(Group::from(GroupBuilder {
+            children: copies,
+            transform: Default::default(),
+        }))
+    }
+
+    /// Copy of the shape at a random spot, wrapped in a group with it's random transformation.
+    fn random_copy(&self, sampler: &RegionSampler, rng: &mut StdRng) -> Group {
+        let position = sampler.sample(rng);
+        let transform = Transform::translation(position.0.x, position.0.y, position.0.z)
+            * self.random_rotation(rng)
+            * self.random_scaling(rng);
+
+        Group::from(GroupBuilder {
+            children: [self.prefab.clone()],
+            transform,
+        })
+    }
+
+    fn random_rotation(&self, rng: &mut StdRng) -> Transform {
+        let Vector(max) = self.max_rotation;
+        let mut angle = |max: f64| (max.abs() * rng.gen_range(-1.0..=1.0)).to_radians();
+
+        let (x, y, z) = (angle(max.x), angle(max.y), angle(max.z));
+
+        Transform::rotation_z(z) * Transform::rotation_y(y) * Transform::rotation_x(x)
+    }
+
+    fn random_scaling(&self, rng: &mut StdRng) -> Transform {
+        let scale = rng.gen_range(self.min_scale..=self.max_scale);
+
+        // The smallest scale was already checked to be a valid scaling, so every larger one is
+        // valid too.
+        #[allow(clippy::unwrap_used)]
+        Transform::scaling(scale, scale, scale).unwrap()
+    }
+}
+
+/// Picks points uniformly distributed over a region.
+enum RegionSampler {
+    Surface {
+        triangles: Vec<[Point; 3]>,
+
+        // Area of every triangle up to and including each one, to pick them proportionally to
+        // their area.
+        cumulative_areas: Vec<f64>,
+    },
+    Volume {
+        min: Point,
+        max: Point,
+    },
+}
+
+impl RegionSampler {
+    fn new(region: &ScatterRegion) -> Result<Self, Error> {
+        match region {
+            ScatterRegion::Surface(shape) => {
+                let triangles = shape.tessellate(SURFACE_RESOLUTION);
+
+                let cumulative_areas: Vec<_> = triangles
+                    .iter()
+                    .scan(0.0, |total, [a, b, c]| {
+                        *total += (*b - *a).cross(*c - *a).magnitude() / 2.0;
+                        Some(*total)
+                    })
+                    .collect();
+
+                match cumulative_areas.last() {
+                    Some(total) if *total > 0.0 => Ok(Self::Surface {
+                        triangles,
+                        cumulative_areas,
+                    }),
+                    _ => Err(Error::EmptyRegion),
+                }
+            }
+            ScatterRegion::Volume { min, max } => {
+                check_volume(*min, *max)?;
+
+                Ok(Self::Volume {
+                    min: *min,
+                    max: *max,
+                })
+            }
+        }
+    }
+
+    fn sample(&self, rng: &mut StdRng) -> Point {
+        match self {
+            Self::Surface {
+                triangles,
+                cumulative_areas,
+            } => {
+                let total = cumulative_areas.last().copied().unwrap_or_default();
+                let target = rng.gen_range(0.0..total);
+                let index = cumulative_areas
+                    .partition_point(|area| *area <= target)
+                    .min(triangles.len() - 1);
+
+                let [a, b, c] = triangles[index];
+
+                // Folding the samples that land outside of the triangle back into it keeps them
+                // uniformly distributed.
+                let (mut u, mut v): (f64, f64) = (rng.gen(), rng.gen());
+                if u + v > 1.0 {
+                    (u, v) = (1.0 - u, 1.0 - v);
+                }
+
+                a + (b - a) * u + (c - a) * v
+            }
+            Self::Volume { min, max } => {
+                let Point(min) = min;
+                let Point(max) = max;
+                let mut coordinate = |min: f64, max: f64| {
+                    if min < max {
+                        rng.gen_range(min..max)
+                    } else {
+                        min
+                    }
+                };
+
+                Point::new(
+                    coordinate(min.x, max.x),
+                    coordinate(min.y, max.y),
+                    coordinate(min.z, max.z),
+                )
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{assert_approx, shape::ShapeBuilder, shape::Sphere};
+
+    use super::*;
+
+    fn rocks(region: ScatterRegion) -> Scatter {
+        Scatter {
+            prefab: Shape::Sphere(Default::default()),
+            region,
+            count: 20,
+            min_scale: 0.1,
+            max_scale: 0.3,
+            max_rotation: Vector::new(0.0, 180.0, 0.0),
+            seed: 7,
+            avoid_overlaps: false,
+        }
+    }
+
+    fn positions(group: &Group) -> Vec<Point> {
+        group
+            .children
+            .iter()
+            .map(|copy| copy.as_ref().transform * Point::new(0.0, 0.0, 0.0))
+            .collect()
+    }
+
+    #[test]
+    fn scattering_copies_inside_of_a_volume() {
+        let field = rocks(ScatterRegion::Volume {
+            min: Point::new(-5.0, 0.0, -5.0),
+            max: Point::new(5.0, 2.0, 5.0),
+        })
+        .scatter()
+        .unwrap();
+
+        assert_eq!(field.children.len(), 20);
+
+        for Point(position) in positions(&field) {
+            assert!((-5.0..=5.0).contains(&position.x));
+            assert!((0.0..=2.0).contains(&position.y));
+            assert!((-5.0..=5.0).contains(&position.z));
+        }
+    }
+
+    #[test]
+    fn scattering_copies_over_a_surface() {
+        let ground = Shape::Sphere(Sphere::from(ShapeBuilder {
+            transform: Transform::scaling(10.0, 10.0, 10.0).unwrap(),
+            ..Default::default()
+        }));
+
+        let field = rocks(ScatterRegion::Surface(Box::new(ground)))
+            .scatter()
+            .unwrap();
+
+        for position in positions(&field) {
+            let distance = (position - Point::new(0.0, 0.0, 0.0)).magnitude();
+            assert!((9.8..=10.0 + 1e-9).contains(&distance));
+        }
+    }
+
+    #[test]
+    fn copies_are_scaled_within_the_range() {
+        let field = rocks(ScatterRegion::Volume {
+            min: Point::new(0.0, 0.0, 0.0),
+            max: Point::new(1.0, 1.0, 1.0),
+        })
+        .scatter()
+        .unwrap();
+
+        for copy in &field.children {
+            let transform = copy.as_ref().transform;
+            let scale = (transform * Vector::new(0.0, 1.0, 0.0)).magnitude();
+
+            assert!((0.1..=0.3).contains(&scale));
+
+            // The copies are only rotated around the `y` axis.
+            let Vector(up) = transform * Vector::new(0.0, 1.0, 0.0);
+            assert_approx!(up.y, scale);
+        }
+    }
+
+    #[test]
+    fn the_same_seed_places_the_copies_at_the_same_spots() {
+        let region = ScatterRegion::Volume {
+            min: Point::new(-5.0, 0.0, -5.0),
+            max: Point::new(5.0, 0.0, 5.0),
+        };
+
+        let field = rocks(region.clone()).scatter().unwrap();
+        let same_field = rocks(region.clone()).scatter().unwrap();
+        let other_field = Scatter {
+            seed: 8,
+            ..rocks(region)
+        }
+        .scatter()
+        .unwrap();
+
+        assert_eq!(positions(&field), positions(&same_field));
+        assert_ne!(positions(&field), positions(&other_field));
+
+        // Copies are different shapes, even when they're placed at the same spot.
+        assert_ne!(field.children[0].id(), same_field.children[0].id());
+    }
+
+    fn overlapping_pairs(group: &Group) -> usize {
+        let boxes: Vec<_> = group
+            .children
+            .iter()
+            .map(|copy| copy.as_ref().bounding_box)
+            .collect();
+
+        boxes
+            .iter()
+            .enumerate()
+            .map(|(i, a)| boxes[i + 1..].iter().filter(|b| a.overlaps(b)).count())
+            .sum()
+    }
+
+    #[test]
+    fn scattering_copies_without_overlapping_them() {
+        let region = ScatterRegion::Volume {
+            min: Point::new(-5.0, 0.0, -5.0),
+            max: Point::new(5.0, 0.0, 5.0),
+        };
+
+        let crowded = rocks(region.clone()).scatter().unwrap();
+        assert!(overlapping_pairs(&crowded) > 0);
+
+        let scatter = Scatter {
+            min_scale: 0.3,
+            max_scale: 0.5,
+            avoid_overlaps: true,
+            ..rocks(region)
+        };
+
+        let field = scatter.scatter().unwrap();
+        assert_eq!(field.children.len(), 20);
+        assert_eq!(overlapping_pairs(&field), 0);
+    }
+
+    #[test]
+    fn copies_that_do_not_fit_are_left_out() {
+        let scatter = Scatter {
+            min_scale: 1.0,
+            max_scale: 1.0,
+            avoid_overlaps: true,
+            ..rocks(ScatterRegion::Volume {
+                min: Point::new(0.0, 0.0, 0.0),
+                max: Point::new(3.0, 0.0, 0.0),
+            })
+        };
+
+        let field = scatter.scatter().unwrap();
+
+        assert!(field.children.len() < 3);
+        assert_eq!(overlapping_pairs(&field), 0);
+    }
+
+    #[test]
+    fn trying_to_scatter_with_an_invalid_scale() {
+        let region = ScatterRegion::Volume {
+            min: Point::new(0.0, 0.0, 0.0),
+            max: Point::new(1.0, 1.0, 1.0),
+        };
+
+        let scatter = Scatter {
+            min_scale: 0.0,
+            ..rocks(region.clone())
+        };
+        assert_eq!(scatter.scatter(), Err(Error::InvalidScale(0.0, 0.3)));
+
+        let scatter = Scatter {
+            min_scale: 2.0,
+            max_scale: 1.0,
+            ..rocks(region)
+        };
+        assert_eq!(scatter.scatter(), Err(Error::InvalidScale(2.0, 1.0)));
+    }
+
+    #[test]
+    fn trying_to_scatter_with_an_infinite_scale_or_volume() {
+        let region = ScatterRegion::Volume {
+            min: Point::new(0.0, 0.0, 0.0),
+            max: Point::new(1.0, 1.0, 1.0),
+        };
+
+        let scatter = Scatter {
+            max_scale: f64::INFINITY,
+            ..rocks(region)
+        };
+        assert_eq!(
+            scatter.scatter(),
+            Err(Error::InvalidScale(0.1, f64::INFINITY))
+        );
+
+        for (min, max) in [
+            (
+                Point::new(0.0, 0.0, 0.0),
+                Point::new(f64::INFINITY, 1.0, 1.0),
+            ),
+            (
+                Point::new(-f64::MAX, 0.0, 0.0),
+                Point::new(f64::MAX, 1.0, 1.0),
+            ),
+            (Point::new(0.0, 2.0, 0.0), Point::new(1.0, 1.0, 1.0)),
+        ] {
+            assert_eq!(
+                rocks(ScatterRegion::Volume { min, max }).scatter(),
+                Err(Error::InvalidVolume(min, max))
+            );
+        }
+    }
+
+    #[test]
+    fn deserializing_a_scatter_checks_it_s_scale_and_volume() {
+        let scatter = |min_scale: &str, max: &str| {
+            serde_json::from_str::<Scatter>(&format!(
+                r#"{{
+                    "prefab": {{ "type": "sphere" }},
+                    "region": {{
+                        "type": "volume",
+                        "min": {{ "x": 0.0, "y": 0.0, "z": 0.0 }},
+                        "max": {max}
+                    }},
+                    "count": 5,
+                    "min_scale": {min_scale}
+                }}"#
+            ))
+        };
+
+        let max = r#"{ "x": 1.0, "y": 1.0, "z": 1.0 }"#;
+
+        assert!(scatter("0.5", max).is_ok());
+
+        assert!(scatter("2.0", max)
+            .unwrap_err()
+            .to_string()
+            .starts_with("copies must be scaled between two positive scales, got 2 to 1"));
+
+        assert!(scatter("0.5", r#"{ "x": 1.0, "y": -1.0, "z": 1.0 }"#)
+            .unwrap_err()
+            .to_string()
+            .starts_with("scatter volume must be between two finite and ordered corners"));
+    }
+
+    #[test]
+    fn trying_to_scatter_over_a_surface_without_area() {
+        let empty = Shape::Group(Default::default());
+
+        assert_eq!(
+            rocks(ScatterRegion::Surface(Box::new(empty))).scatter(),
+            Err(Error::EmptyRegion)
+        );
+    }
+}
@@ -1,5 +1,5 @@
 use crate::{
-    intersection::Intersection,
+    intersection::{Intersection, Intersections},
     ray::Ray,
     tuple::{Point, Vector},
 };
@@ -19,10 +19,32 @@ pub struct SmoothTriangle {
 }
 
 impl SmoothTriangle {
-    pub(crate) fn intersect<'a>(&self, object: &'a Shape, ray: &Ray) -> Vec<Intersection<'a>> {
+    /// Flat triangle with the vertices of the smooth triangle.
+    pub fn triangle(&self) -> &Triangle {
+        &self.triangle
+    }
+
+    /// Normals of the smooth triangle at each one of it's vertices, in the same order as the
+    /// vertices.
+    pub fn normals(&self) -> [Vector; 3] {
+        [self.n0, self.n1, self.n2]
+    }
+
+    #[cfg(test)]
+    pub(crate) fn intersect<'a>(&self, object: &'a Shape, ray: &Ray) -> Intersections<'a> {
         self.triangle.intersect(object, ray)
     }
 
+    pub(crate) fn intersect_within<'a>(
+        &self,
+        object: &'a Shape,
+        ray: &Ray,
+        t_min: f64,
+        t_max: f64,
+    ) -> Intersections<'a> {
+        self.triangle.intersect_within(object, ray, t_min, t_max)
+    }
+
     pub(crate) fn normal_at(&self, _: Point, hit: &Intersection<'_>) -> Vector {
         // Smooth triangles are always ensured to have a `u` and `v` value. In fact, this is the
         // only kind of shape that has these values.
@@ -81,9 +103,15 @@ mod tests {
             object: &tri,
             u: Some(0.45),
             v: Some(0.25),
+            instance: None,
+        };
+
+        let r = Ray {
+            origin: Point::new(0.0, 0.0, -2.0),
+            direction: Vector::new(0.0, 0.0, 1.0),
         };
 
-        let n = tri.normal_at(Point::new(0.0, 0.0, 0.0), &i);
+        let n = tri.normal_at(Point::new(0.0, 0.0, 0.0), &i, &r);
 
         assert_eq!(n, Vector::new(-0.5547, 0.83205, 0.0));
     }
@@ -97,6 +125,7 @@ mod tests {
             object: &tri,
             u: Some(0.45),
             v: Some(0.25),
+            instance: None,
         };
 
         let r = Ray {
@@ -1,5 +1,7 @@
+use smallvec::smallvec;
+
 use crate::{
-    intersection::Intersection,
+    intersection::{Intersection, Intersections},
     ray::Ray,
     tuple::{Point, Vector},
 };
@@ -35,11 +37,22 @@ impl From<ShapeBuilder> for Sphere {
 }
 
 impl Sphere {
+    #[cfg(test)]
     pub(crate) fn local_intersect<'a>(
         &self,
         object: &'a Shape,
         local_ray: &Ray,
-    ) -> Vec<Intersection<'a>> {
+    ) -> Intersections<'a> {
+        self.local_intersect_within(object, local_ray, f64::NEG_INFINITY, f64::INFINITY)
+    }
+
+    pub(crate) fn local_intersect_within<'a>(
+        &self,
+        object: &'a Shape,
+        local_ray: &Ray,
+        t_min: f64,
+        t_max: f64,
+    ) -> Intersections<'a> {
         let ray_origin_vec = local_ray.origin - Point::new(0.0, 0.0, 0.0);
 
         let a = local_ray.direction.dot(local_ray.direction);
@@ -49,26 +62,60 @@ impl Sphere {
         let discriminant = b.powi(2) - 4.0 * a * c;
 
         if discriminant < 0.0 {
-            return vec![];
+            return smallvec![];
         }
 
         let t0 = (-b - discriminant.sqrt()) / (2.0 * a);
         let t1 = (-b + discriminant.sqrt()) / (2.0 * a);
 
-        vec![
-            Intersection {
-                t: t0,
-                object,
-                u: None,
-                v: None,
-            },
-            Intersection {
-                t: t1,
+        [t0, t1]
+            .into_iter()
+            .filter(|&t| super::within(t, t_min, t_max))
+            .map(|t| Intersection {
+                t,
                 object,
                 u: None,
                 v: None,
-            },
-        ]
+                instance: None,
+            })
+            .collect()
+    }
+
+    pub(crate) fn tessellate(&self, resolution: usize) -> Vec<[Point; 3]> {
+        let slices = resolution.max(3);
+        let stacks = (resolution / 2).max(2);
+
+        let point_at = |stack: usize, slice: usize| {
+            let theta = std::f64::consts::PI * stack as f64 / stacks as f64;
+            let phi = std::f64::consts::TAU * slice as f64 / slices as f64;
+
+            Point::new(
+                theta.sin() * phi.cos(),
+                theta.cos(),
+                theta.sin() * phi.sin(),
+            )
+        };
+
+        let mut triangles = Vec::with_capacity(2 * slices * stacks);
+
+        for stack in 0..stacks {
+            for slice in 0..slices {
+                let a = point_at(stack, slice);
+                let b = point_at(stack, slice + 1);
+                let c = point_at(stack + 1, slice + 1);
+                let d = point_at(stack + 1, slice);
+
+                // The quads that touch the poles collapse into a single triangle.
+                if stack != 0 {
+                    triangles.push([a, b, c]);
+                }
+                if stack != stacks - 1 {
+                    triangles.push([a, c, d]);
+                }
+            }
+        }
+
+        triangles
     }
 
     pub(crate) fn local_normal_at(&self, local_point: Point) -> Vector {
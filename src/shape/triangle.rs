@@ -1,11 +1,17 @@
+use std::borrow::Cow;
+
+use serde::Deserialize;
+use smallvec::smallvec;
 use thiserror::Error;
 
 use crate::{
+    color::Color,
     float,
-    intersection::Intersection,
+    intersection::{Intersection, Intersections},
     material::Material,
+    pattern::{Pattern3D, ScalarField},
     ray::Ray,
-    tuple::{Point, Vector},
+    tuple::{Point, Tuple, Vector},
 };
 
 use super::{bounding_box::BoundingBox, object::ObjectCache, Shape};
@@ -50,15 +56,33 @@ pub struct Triangle {
     pub(crate) v0: Point,
     pub(crate) v1: Point,
     pub(crate) v2: Point,
+    pub(crate) colors: Option<[Color; 3]>,
+    pub(crate) values: Option<[f64; 3]>,
+    pub(crate) texture_coordinates: Option<[(f64, f64); 3]>,
+    pub(crate) tangents: Option<[Tangent; 3]>,
     e0: Vector,
     e1: Vector,
     normal: Vector,
 }
 
+/// Direction along which the `u` texture coordinate of a surface grows at one of it's points,
+/// which together with the normal of the surface makes up the space that tangent-space normal
+/// maps are given in.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Tangent {
+    /// Unit vector perpendicular to the normal of the surface.
+    pub direction: Vector,
+
+    /// Either `1.0` or `-1.0`, depending on whether the `v` texture coordinate grows along the
+    /// cross product of the normal and the tangent, or against it.
+    pub sign: f64,
+}
+
 /// Builder for a triangle.
-#[derive(Clone)]
+#[derive(Clone, Deserialize)]
 pub struct TriangleBuilder {
     /// Material of the triangle.
+    #[serde(default)]
     pub material: Material,
 
     /// Vertices of the triangle.
@@ -93,6 +117,10 @@ impl TryFrom<TriangleBuilder> for Triangle {
             v0,
             v1,
             v2,
+            colors: None,
+            values: None,
+            texture_coordinates: None,
+            tangents: None,
             e0,
             e1,
             normal,
@@ -101,12 +129,186 @@ impl TryFrom<TriangleBuilder> for Triangle {
 }
 
 impl Triangle {
-    pub(crate) fn intersect<'a>(&self, object: &'a Shape, ray: &Ray) -> Vec<Intersection<'a>> {
+    /// Vertices of the triangle, in the order they were defined. They're given in the space of
+    /// the triangle, which is the space of the world unless it was placed with a transform.
+    pub fn vertices(&self) -> [Point; 3] {
+        [self.v0, self.v1, self.v2]
+    }
+
+    /// Normal of the triangle, in the same space as it's vertices.
+    pub fn normal(&self) -> Vector {
+        self.normal
+    }
+
+    /// Colors the triangle with a color at each one of it's vertices, in the same order as the
+    /// vertices, such as the values of a simulation at the nodes of a mesh. The colors are blended
+    /// across the triangle and tint the color of it's material.
+    pub fn with_vertex_colors(self, colors: [Color; 3]) -> Self {
+        Self {
+            colors: Some(colors),
+            ..self
+        }
+    }
+
+    /// Colors of the triangle at each one of it's vertices, if it has any.
+    pub fn vertex_colors(&self) -> Option<[Color; 3]> {
+        self.colors
+    }
+
+    /// Gives the triangle a scalar value at each one of it's vertices, in the same order as the
+    /// vertices, which a [heatmap](crate::pattern::Heatmap) of the
+    /// [vertex field](crate::pattern::ScalarField::Vertex) blends across the triangle.
+    pub fn with_vertex_values(self, values: [f64; 3]) -> Self {
+        Self {
+            values: Some(values),
+            ..self
+        }
+    }
+
+    /// Scalar values of the triangle at each one of it's vertices, if it has any.
+    pub fn vertex_values(&self) -> Option<[f64; 3]> {
+        self.values
+    }
+
+    /// Gives the triangle `u` and `v` texture coordinates at each one of it's vertices, in the
+    /// same order as the vertices, and sets the tangents of the triangle from them.
+    ///
+    /// Triangles that share vertices in a [Model](crate::model::Model) get tangents that are
+    /// averaged across all of them instead.
+    ///
+    pub fn with_texture_coordinates(self, texture_coordinates: [(f64, f64); 3]) -> Self {
+        let triangle = Self {
+            texture_coordinates: Some(texture_coordinates),
+            ..self
+        };
+
+        let tangents = triangle
+            .tangent_and_bitangent()
+            .map(|(tangent, bitangent)| [Tangent::new(triangle.normal, tangent, bitangent); 3]);
+
+        Self {
+            tangents,
+            ..triangle
+        }
+    }
+
+    /// Texture coordinates of the triangle at each one of it's vertices, if it has any.
+    pub fn texture_coordinates(&self) -> Option<[(f64, f64); 3]> {
+        self.texture_coordinates
+    }
+
+    /// Texture coordinates of the vertices of the triangle projected on the plane of the axes that
+    /// the triangle faces the most, as a fallback for triangles without texture coordinates. They
+    /// grow by one every unit of space, so textures repeat every unit like a
+    /// [planar](crate::pattern::UvMapping::Planar) mapping does.
+    pub fn projected_texture_coordinates(&self) -> [(f64, f64); 3] {
+        let Vector(Tuple { x, y, z, .. }) = self.normal;
+        let (x, y, z) = (x.abs(), y.abs(), z.abs());
+
+        [self.v0, self.v1, self.v2].map(|Point(vertex)| {
+            if x >= y && x >= z {
+                (vertex.z, vertex.y)
+            } else if y >= z {
+                (vertex.x, vertex.z)
+            } else {
+                (vertex.x, vertex.y)
+            }
+        })
+    }
+
+    /// Texture coordinates at the point with barycentric coordinates `uv`, blended from the
+    /// texture coordinates of the vertices, or the projected ones if the triangle has none.
+    pub(crate) fn texture_coordinates_at(&self, (u, v): (f64, f64)) -> (f64, f64) {
+        let [(u0, v0), (u1, v1), (u2, v2)] = self
+            .texture_coordinates
+            .unwrap_or_else(|| self.projected_texture_coordinates());
+
+        let w = 1.0 - u - v;
+        (u1 * u + u2 * v + u0 * w, v1 * u + v2 * v + v0 * w)
+    }
+
+    /// Tangents of the triangle at each one of it's vertices, if it has texture coordinates.
+    pub fn tangents(&self) -> Option<[Tangent; 3]> {
+        self.tangents
+    }
+
+    /// Directions along which the `u` and `v` texture coordinates grow across the triangle, scaled
+    /// so larger triangles weigh more when they're averaged at shared vertices.
+    pub(crate) fn tangent_and_bitangent(&self) -> Option<(Vector, Vector)> {
+        let [(u0, v0), (u1, v1), (u2, v2)] = self.texture_coordinates?;
+
+        let (du1, dv1) = (u1 - u0, v1 - v0);
+        let (du2, dv2) = (u2 - u0, v2 - v0);
+
+        // Triangles whose texture coordinates are collinear don't have a tangent space.
+        let determinant = du1 * dv2 - du2 * dv1;
+        if float::approx(determinant, 0.0) {
+            return None;
+        }
+
+        let sign = determinant.signum();
+        let tangent = (self.e0 * dv2 - self.e1 * dv1) * sign;
+        let bitangent = (self.e1 * du1 - self.e0 * du2) * sign;
+
+        Some((tangent, bitangent))
+    }
+
+    /// Material of the triangle at the point with barycentric coordinates `uv`, with it's
+    /// [mesh](crate::pattern::UvMapping::Mesh) texture looked up at the texture coordinates of the
+    /// point, it's [heatmap](crate::pattern::Heatmap) colored by the values of it's vertices, and
+    /// tinted by the colors of it's vertices.
+    pub(crate) fn material_at<'a>(
+        &self,
+        material: Cow<'a, Material>,
+        (u, v): (f64, f64),
+    ) -> Cow<'a, Material> {
+        let texture_color = match &material.pattern {
+            Pattern3D::Texture(texture) => {
+                texture.mesh_color_at(self.texture_coordinates_at((u, v)))
+            }
+            Pattern3D::Heatmap(heatmap) if *heatmap.field() == ScalarField::Vertex => self
+                .values
+                .map(|[a, b, c]| heatmap.color_of(b * u + c * v + a * (1.0 - u - v))),
+            _ => None,
+        };
+
+        let material = match texture_color {
+            Some(color) => Cow::Owned(Material {
+                pattern: Pattern3D::Solid(color),
+                ..material.into_owned()
+            }),
+            None => material,
+        };
+
+        let (Some([c0, c1, c2]), Pattern3D::Solid(color)) = (self.colors, &material.pattern) else {
+            return material;
+        };
+
+        let pattern = Pattern3D::Solid(*color * (c1 * u + c2 * v + c0 * (1.0 - u - v)));
+
+        Cow::Owned(Material {
+            pattern,
+            ..material.into_owned()
+        })
+    }
+
+    #[cfg(test)]
+    pub(crate) fn intersect<'a>(&self, object: &'a Shape, ray: &Ray) -> Intersections<'a> {
+        self.intersect_within(object, ray, f64::NEG_INFINITY, f64::INFINITY)
+    }
+
+    pub(crate) fn intersect_within<'a>(
+        &self,
+        object: &'a Shape,
+        ray: &Ray,
+        t_min: f64,
+        t_max: f64,
+    ) -> Intersections<'a> {
         let dir_cross_e1 = ray.direction.cross(self.e1);
         let det = self.e0.dot(dir_cross_e1);
 
         if float::approx(det.abs(), 0.0) {
-            return vec![];
+            return smallvec![];
         }
 
         let f = 1.0 / det;
@@ -114,29 +316,75 @@ impl Triangle {
         let u = f * p0_to_origin.dot(dir_cross_e1);
 
         if !(0.0..=1.0).contains(&u) {
-            return vec![];
+            return smallvec![];
         }
 
         let origin_cross_e0 = p0_to_origin.cross(self.e0);
         let v = f * ray.direction.dot(origin_cross_e0);
 
-        if v < 0.0 || (u + v) > 1.0 {
-            return vec![];
+        let t = f * self.e1.dot(origin_cross_e0);
+
+        if v < 0.0 || (u + v) > 1.0 || !super::within(t, t_min, t_max) {
+            return smallvec![];
         }
 
-        vec![Intersection {
-            t: f * self.e1.dot(origin_cross_e0),
+        smallvec![Intersection {
+            t,
             object,
             u: Some(u),
             v: Some(v),
+            instance: None,
         }]
     }
 
+    pub(crate) fn tessellate(&self) -> Vec<[Point; 3]> {
+        vec![[self.v0, self.v1, self.v2]]
+    }
+
     pub(crate) fn normal_at(&self, _: Point) -> Vector {
         self.normal
     }
 }
 
+impl Tangent {
+    /// Tangent along `tangent` made perpendicular to `normal`, with the sign given by the side of
+    /// the normal that `bitangent` points to. Tangents parallel to the normal are replaced by any
+    /// direction perpendicular to it.
+    pub(crate) fn new(normal: Vector, tangent: Vector, bitangent: Vector) -> Self {
+        let direction = (tangent - normal * normal.dot(tangent))
+            .normalize()
+            .unwrap_or_else(|_| perpendicular_to(normal));
+
+        let sign = if normal.cross(direction).dot(bitangent) < 0.0 {
+            -1.0
+        } else {
+            1.0
+        };
+
+        Self { direction, sign }
+    }
+
+    /// Direction along which the `v` texture coordinate grows, for a surface with the given
+    /// normal.
+    pub fn bitangent(&self, normal: Vector) -> Vector {
+        normal.cross(self.direction) * self.sign
+    }
+}
+
+/// Any unit vector perpendicular to `normal`.
+fn perpendicular_to(normal: Vector) -> Vector {
+    let helper = if normal.0.x.abs() > 0.9 {
+        Vector::new(0.0, 1.0, 0.0)
+    } else {
+        Vector::new(1.0, 0.0, 0.0)
+    };
+
+    helper
+        .cross(normal)
+        .normalize()
+        .unwrap_or(Vector::new(0.0, 0.0, 1.0))
+}
+
 #[cfg(test)]
 mod tests {
     use crate::assert_approx;
@@ -163,6 +411,24 @@ mod tests {
         assert_eq!(triangle.normal, Vector::new(0.0, 0.0, -1.0));
     }
 
+    #[test]
+    fn reading_the_vertices_and_normal_of_a_triangle() {
+        let vertices = [
+            Point::new(0.0, 1.0, 0.0),
+            Point::new(-1.0, 0.0, 0.0),
+            Point::new(1.0, 0.0, 0.0),
+        ];
+
+        let triangle = Triangle::try_from(TriangleBuilder {
+            material: Default::default(),
+            vertices,
+        })
+        .unwrap();
+
+        assert_eq!(triangle.vertices(), vertices);
+        assert_eq!(triangle.normal(), Vector::new(0.0, 0.0, -1.0));
+    }
+
     #[test]
     fn trying_to_construct_a_triangle_with_collinear_sides() {
         let v0 = Point::new(1.0, 0.0, 0.0);
@@ -336,4 +602,208 @@ mod tests {
         assert_eq!(bounding_box.min, Point::new(-3.0, -1.0, -4.0));
         assert_eq!(bounding_box.max, Point::new(6.0, 7.0, 2.0));
     }
+
+    #[test]
+    fn vertex_colors_are_blended_across_a_triangle() {
+        let triangle = Triangle::try_from(TriangleBuilder {
+            material: Default::default(),
+            vertices: [
+                Point::new(0.0, 1.0, 0.0),
+                Point::new(-1.0, 0.0, 0.0),
+                Point::new(1.0, 0.0, 0.0),
+            ],
+        })
+        .unwrap()
+        .with_vertex_colors([
+            crate::color::consts::RED,
+            crate::color::consts::GREEN,
+            crate::color::consts::BLUE,
+        ]);
+
+        let object = Shape::Triangle(triangle);
+
+        let ray = Ray {
+            origin: Point::new(0.0, 0.5, -2.0),
+            direction: Vector::new(0.0, 0.0, 1.0),
+        };
+
+        let xs = object.intersect(&ray);
+        let comps = xs[0].prepare_computation(&ray, xs.clone());
+
+        // Halfway up the middle of the triangle, the top vertex weighs as much as both bottom
+        // vertices together.
+        assert_eq!(
+            comps.material.pattern,
+            Pattern3D::Solid(Color {
+                red: 0.5,
+                green: 0.25,
+                blue: 0.25,
+            })
+        );
+    }
+
+    #[test]
+    fn the_tangent_of_a_triangle_follows_its_texture_coordinates() {
+        let triangle = Triangle::try_from(TriangleBuilder {
+            material: Default::default(),
+            vertices: [
+                Point::new(0.0, 0.0, 0.0),
+                Point::new(1.0, 0.0, 0.0),
+                Point::new(0.0, 1.0, 0.0),
+            ],
+        })
+        .unwrap()
+        .with_texture_coordinates([(0.0, 0.0), (1.0, 0.0), (0.0, 1.0)]);
+
+        let [tangent, ..] = triangle.tangents().unwrap();
+
+        assert_eq!(tangent.direction, Vector::new(1.0, 0.0, 0.0));
+        assert_eq!(
+            tangent.bitangent(triangle.normal()),
+            Vector::new(0.0, 1.0, 0.0)
+        );
+
+        // Mirroring the texture flips the bitangent, but not the tangent.
+        let mirrored = triangle.with_texture_coordinates([(0.0, 1.0), (1.0, 1.0), (0.0, 0.0)]);
+        let [tangent, ..] = mirrored.tangents().unwrap();
+
+        assert_eq!(tangent.direction, Vector::new(1.0, 0.0, 0.0));
+        assert_eq!(
+            tangent.bitangent(mirrored.normal()),
+            Vector::new(0.0, -1.0, 0.0)
+        );
+    }
+
+    #[test]
+    fn a_triangle_with_collinear_texture_coordinates_has_no_tangents() {
+        let triangle = Triangle::try_from(TriangleBuilder {
+            material: Default::default(),
+            vertices: [
+                Point::new(0.0, 0.0, 0.0),
+                Point::new(1.0, 0.0, 0.0),
+                Point::new(0.0, 1.0, 0.0),
+            ],
+        })
+        .unwrap()
+        .with_texture_coordinates([(0.0, 0.0), (0.5, 0.5), (1.0, 1.0)]);
+
+        assert_eq!(triangle.tangents(), None);
+    }
+
+    #[test]
+    fn a_triangle_without_texture_coordinates_is_projected_on_the_plane_it_faces() {
+        let floor = Triangle::try_from(TriangleBuilder {
+            material: Default::default(),
+            vertices: [
+                Point::new(0.0, 2.0, 0.0),
+                Point::new(0.0, 2.5, 1.0),
+                Point::new(1.0, 2.0, 0.0),
+            ],
+        })
+        .unwrap();
+
+        assert_eq!(
+            floor.projected_texture_coordinates(),
+            [(0.0, 0.0), (0.0, 1.0), (1.0, 0.0)]
+        );
+
+        let (u, v) = floor.texture_coordinates_at((0.5, 0.25));
+        assert_approx!(u, 0.25);
+        assert_approx!(v, 0.5);
+
+        // Texture coordinates of the triangle take precedence over the projection.
+        let textured = floor.with_texture_coordinates([(0.5, 0.5), (0.5, 0.5), (0.5, 0.5)]);
+        assert_eq!(textured.texture_coordinates_at((0.5, 0.25)), (0.5, 0.5));
+    }
+
+    #[test]
+    fn a_mesh_texture_is_looked_up_at_the_texture_coordinates_of_the_hit() {
+        use std::sync::Arc;
+
+        use crate::{
+            color,
+            pattern::{AlignCheck, TextureMap, UvMapping, UvPattern},
+        };
+
+        let texture = TextureMap::new(
+            UvMapping::Mesh(UvPattern::AlignCheck(AlignCheck {
+                main: color::consts::WHITE,
+                upper_left: color::consts::RED,
+                upper_right: color::consts::GREEN,
+                bottom_left: color::consts::BLUE,
+                bottom_right: color::consts::BLACK,
+            })),
+            Default::default(),
+        );
+
+        let triangle = Triangle::try_from(TriangleBuilder {
+            material: Material {
+                pattern: Pattern3D::Texture(Arc::new(texture)),
+                ..Default::default()
+            },
+            vertices: [
+                Point::new(0.0, 0.0, 0.0),
+                Point::new(0.0, 1.0, 0.0),
+                Point::new(1.0, 0.0, 0.0),
+            ],
+        })
+        .unwrap();
+
+        // Close to the first vertex, at the bottom left corner of the projected texture.
+        let material =
+            triangle.material_at(Cow::Borrowed(&triangle.object_cache.material), (0.05, 0.05));
+        assert_eq!(material.pattern, Pattern3D::Solid(color::consts::BLUE));
+
+        // Close to the same vertex, at the upper right corner of the texture given to it.
+        let textured = triangle.with_texture_coordinates([(0.9, 0.9), (0.5, 0.5), (0.5, 0.5)]);
+        let material =
+            textured.material_at(Cow::Borrowed(&textured.object_cache.material), (0.05, 0.05));
+        assert_eq!(material.pattern, Pattern3D::Solid(color::consts::GREEN));
+    }
+
+    #[test]
+    fn a_vertex_heatmap_blends_the_values_of_a_triangle() {
+        use std::sync::Arc;
+
+        use crate::{
+            color,
+            pattern::{ColorRamp, Heatmap},
+        };
+
+        let heatmap = Heatmap::new(ScalarField::Vertex, ColorRamp::grayscale(), 0.0, 10.0).unwrap();
+
+        let triangle = Triangle::try_from(TriangleBuilder {
+            material: Material {
+                pattern: Pattern3D::Heatmap(Arc::new(heatmap)),
+                ..Default::default()
+            },
+            vertices: [
+                Point::new(0.0, 1.0, 0.0),
+                Point::new(-1.0, 0.0, 0.0),
+                Point::new(1.0, 0.0, 0.0),
+            ],
+        })
+        .unwrap();
+
+        let valued = triangle.clone().with_vertex_values([10.0, 0.0, 5.0]);
+        let material =
+            valued.material_at(Cow::Borrowed(&valued.object_cache.material), (0.5, 0.25));
+
+        let Pattern3D::Solid(color) = material.pattern else {
+            panic!("expected a solid pattern");
+        };
+        assert_approx!(color.red, 0.375);
+
+        // Without values the heatmap is left for the pattern to evaluate, which colors it as zero.
+        let material =
+            triangle.material_at(Cow::Borrowed(&triangle.object_cache.material), (0.5, 0.25));
+        assert!(matches!(material.pattern, Pattern3D::Heatmap(_)));
+        assert_eq!(
+            material.pattern.color_at_object(
+                &Shape::Triangle(triangle.clone()),
+                Point::new(0.0, 0.5, 0.0)
+            ),
+            color::consts::BLACK
+        );
+    }
 }
@@ -0,0 +1,328 @@
+use std::sync::OnceLock;
+
+use serde::Deserialize;
+use thiserror::Error;
+
+use crate::color::{self, Color};
+
+/// Shortest wavelength, in nanometers, sampled by spectral renders.
+pub const MIN_WAVELENGTH: f64 = 380.0;
+
+/// Longest wavelength, in nanometers, sampled by spectral renders.
+pub const MAX_WAVELENGTH: f64 = 730.0;
+
+/// Number of wavelengths at which the sensor is sampled to white balance it and to upsample
+/// colors, one for each nanometer of the visible range.
+const SENSOR_SAMPLES: usize = (MAX_WAVELENGTH - MIN_WAVELENGTH) as usize;
+
+/// Wavelengths, in nanometers, at which the red, green and blue bands of an upsampled color
+/// start. Each band ends where the next one starts, and the last one ends at [MAX_WAVELENGTH].
+const BANDS: [f64; 3] = [MIN_WAVELENGTH, 490.0, 580.0];
+
+/// The error type when trying to construct a spectrum.
+#[derive(Debug, PartialEq, Error)]
+pub enum Error {
+    /// The error type when the spectrum has no samples.
+    #[error("a spectrum must have at least one sample")]
+    Empty,
+
+    /// The error type when a sample of the spectrum is not a finite number.
+    #[error("the samples of a spectrum must be finite numbers")]
+    NotFinite,
+}
+
+/// Value of a physical quantity for each wavelength of light, such as the fraction of light that
+/// a surface reflects, or the power that a light source emits.
+///
+/// The spectrum is given by samples at a few wavelengths, in nanometers, and it's linearly
+/// interpolated between them. Wavelengths outside of the samples take the value of the closest
+/// sample.
+///
+/// # Examples
+///
+/// A surface that only reflects yellow light.
+///
+/// ```
+/// use raytracer::spectrum::Spectrum;
+///
+/// let yellow = Spectrum::new(vec![(560.0, 0.0), (580.0, 1.0), (600.0, 0.0)]).unwrap();
+///
+/// assert_eq!(yellow.value_at(570.0), 0.5);
+/// assert_eq!(yellow.value_at(700.0), 0.0);
+/// ```
+///
+/// # Deserialization
+///
+/// A spectrum is deserialized from it's list of samples, each one as a pair of a wavelength and a
+/// value.
+///
+/// ```json
+/// [[400, 0.1], [550, 0.8], [700, 0.1]]
+/// ```
+///
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(try_from = "Vec<(f64, f64)>")]
+pub struct Spectrum {
+    samples: Vec<(f64, f64)>,
+}
+
+/// Sensor curves and upsampling matrix, which are computed numerically the first time they're
+/// needed.
+struct Sensor {
+    /// Scale of each channel of the sensor, so a flat spectrum is seen as white.
+    white_balance: Color,
+
+    /// Inverse of the matrix with the response of each channel of the sensor to each band.
+    upsampling: [[f64; 3]; 3],
+}
+
+impl TryFrom<Vec<(f64, f64)>> for Spectrum {
+    type Error = Error;
+
+    fn try_from(value: Vec<(f64, f64)>) -> Result<Self, Self::Error> {
+        Self::new(value)
+    }
+}
+
+impl Spectrum {
+    /// Constructs a spectrum from pairs of wavelengths, in nanometers, and their values, which can
+    /// be given in any order.
+    pub fn new(mut samples: Vec<(f64, f64)>) -> Result<Self, Error> {
+        if samples.is_empty() {
+            return Err(Error::Empty);
+        }
+
+        if samples
+            .iter()
+            .any(|(w, v)| !w.is_finite() || !v.is_finite())
+        {
+            return Err(Error::NotFinite);
+        }
+
+        samples.sort_by(|(w1, _), (w2, _)| w1.total_cmp(w2));
+
+        Ok(Self { samples })
+    }
+
+    /// Value of the spectrum at the given wavelength, in nanometers.
+    pub fn value_at(&self, wavelength: f64) -> f64 {
+        let index = self.samples.partition_point(|(w, _)| *w < wavelength);
+
+        match (index.checked_sub(1), self.samples.get(index)) {
+            (Some(before), Some(&(w1, v1))) => {
+                let (w0, v0) = self.samples[before];
+                v0 + (v1 - v0) * (wavelength - w0) / (w1 - w0)
+            }
+            (None, Some(&(_, value))) => value,
+            (Some(before), None) => self.samples[before].1,
+            (None, None) => 0.0,
+        }
+    }
+}
+
+impl Sensor {
+    fn get() -> &'static Self {
+        static SENSOR: OnceLock<Sensor> = OnceLock::new();
+
+        SENSOR.get_or_init(Self::new)
+    }
+
+    fn new() -> Self {
+        let sum = wavelengths().fold(color::consts::BLACK, |acc, w| acc + unbalanced_response(w));
+        let white_balance = Color {
+            red: SENSOR_SAMPLES as f64 / sum.red,
+            green: SENSOR_SAMPLES as f64 / sum.green,
+            blue: SENSOR_SAMPLES as f64 / sum.blue,
+        };
+
+        // Average response of each channel to each band, where each column is a band.
+        let mut response = [[0.0; 3]; 3];
+
+        for wavelength in wavelengths() {
+            let Color { red, green, blue } = unbalanced_response(wavelength) * white_balance;
+            let band = band_of(wavelength);
+
+            for (row, value) in [red, green, blue].into_iter().enumerate() {
+                response[row][band] += value / SENSOR_SAMPLES as f64;
+            }
+        }
+
+        Self {
+            white_balance,
+            upsampling: inverse(response),
+        }
+    }
+}
+
+/// Response of the red, green and blue channels of the camera sensor to light of the given
+/// wavelength, in nanometers.
+///
+/// The sensor follows the color matching functions of the human eye, and it's white balanced so a
+/// spectrum with the same value at every wavelength is seen as a gray of that value. Some
+/// wavelengths have a negative response in some channel, because they're more saturated than any
+/// color that can be represented in RGB.
+///
+pub fn sensor_response(wavelength: f64) -> Color {
+    unbalanced_response(wavelength) * Sensor::get().white_balance
+}
+
+/// Value at the given wavelength, in nanometers, of a spectrum that the sensor sees as `color`.
+///
+/// The spectrum is split into a red, a green and a blue band of constant value, chosen so that the
+/// sensor sees it exactly as `color`. This is how surfaces and lights without a [Spectrum] take
+/// part in spectral renders.
+///
+pub fn upsample(color: Color, wavelength: f64) -> f64 {
+    let row = Sensor::get().upsampling[band_of(wavelength)];
+
+    row[0] * color.red + row[1] * color.green + row[2] * color.blue
+}
+
+/// Wavelengths at which the sensor is sampled, at the center of each nanometer of the visible
+/// range.
+pub(crate) fn wavelengths() -> impl Iterator<Item = f64> {
+    (0..SENSOR_SAMPLES).map(|i| MIN_WAVELENGTH + i as f64 + 0.5)
+}
+
+fn band_of(wavelength: f64) -> usize {
+    BANDS
+        .iter()
+        .rposition(|start| wavelength >= *start)
+        .unwrap_or(0)
+}
+
+/// Response of the sensor before white balance, which is the CIE 1931 color matching functions
+/// converted to linear sRGB.
+fn unbalanced_response(wavelength: f64) -> Color {
+    // Multi-lobe fit of the color matching functions by Wyman, Sloan and Shirley:
+    // https://jcgt.org/published/0002/02/01/
+    let lobe = |mean: f64, below: f64, above: f64| {
+        let deviation = if wavelength < mean { below } else { above };
+        (-0.5 * ((wavelength - mean) / deviation).powi(2)).exp()
+    };
+
+    let x = 1.056 * lobe(599.8, 37.9, 31.0) + 0.362 * lobe(442.0, 16.0, 26.7)
+        - 0.065 * lobe(501.1, 20.4, 26.2);
+    let y = 0.821 * lobe(568.8, 46.9, 40.5) + 0.286 * lobe(530.9, 16.3, 31.1);
+    let z = 1.217 * lobe(437.0, 11.8, 36.0) + 0.681 * lobe(459.0, 26.0, 13.8);
+
+    Color {
+        red: 3.2406 * x - 1.5372 * y - 0.4986 * z,
+        green: -0.9689 * x + 1.8758 * y + 0.0415 * z,
+        blue: 0.0557 * x - 0.2040 * y + 1.0570 * z,
+    }
+}
+
+fn inverse(m: [[f64; 3]; 3]) -> [[f64; 3]; 3] {
+    let cofactor =
+        |r0: usize, r1: usize, c0: usize, c1: usize| m[r0][c0] * m[r1][c1] - m[r0][c1] * m[r1][c0];
+
+    let adjugate = [
+        [
+            cofactor(1, 2, 1, 2),
+            -cofactor(0, 2, 1, 2),
+            cofactor(0, 1, 1, 2),
+        ],
+        [
+            -cofactor(1, 2, 0, 2),
+            cofactor(0, 2, 0, 2),
+            -cofactor(0, 1, 0, 2),
+        ],
+        [
+            cofactor(1, 2, 0, 1),
+            -cofactor(0, 2, 0, 1),
+            cofactor(0, 1, 0, 1),
+        ],
+    ];
+
+    let determinant = (0..3).map(|i| m[0][i] * adjugate[i][0]).sum::<f64>();
+
+    adjugate.map(|row| row.map(|value| value / determinant))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::assert_approx;
+
+    use super::*;
+
+    /// Color that the sensor sees for a spectrum, averaged over every sampled wavelength.
+    fn seen_color<F>(spectrum: F) -> Color
+    where
+        F: Fn(f64) -> f64,
+    {
+        wavelengths().fold(color::consts::BLACK, |acc, w| {
+            acc + sensor_response(w) * (spectrum(w) / SENSOR_SAMPLES as f64)
+        })
+    }
+
+    #[test]
+    fn interpolating_the_samples_of_a_spectrum() {
+        let spectrum = Spectrum::new(vec![(600.0, 0.5), (400.0, 1.0), (500.0, 0.0)]).unwrap();
+
+        assert_approx!(spectrum.value_at(350.0), 1.0);
+        assert_approx!(spectrum.value_at(450.0), 0.5);
+        assert_approx!(spectrum.value_at(500.0), 0.0);
+        assert_approx!(spectrum.value_at(575.0), 0.375);
+        assert_approx!(spectrum.value_at(800.0), 0.5);
+    }
+
+    #[test]
+    fn a_spectrum_needs_finite_samples() {
+        assert_eq!(Spectrum::new(vec![]), Err(Error::Empty));
+        assert_eq!(
+            Spectrum::new(vec![(400.0, f64::NAN)]),
+            Err(Error::NotFinite)
+        );
+    }
+
+    #[test]
+    fn a_flat_spectrum_is_seen_as_gray() {
+        assert_eq!(seen_color(|_| 1.0), color::consts::WHITE);
+        assert_eq!(
+            seen_color(|_| 0.5),
+            Color {
+                red: 0.5,
+                green: 0.5,
+                blue: 0.5
+            }
+        );
+    }
+
+    #[test]
+    fn an_upsampled_color_is_seen_as_the_same_color() {
+        let colors = [
+            color::consts::RED,
+            color::consts::GREEN,
+            Color {
+                red: 0.2,
+                green: 0.7,
+                blue: 0.4,
+            },
+        ];
+
+        for color in colors {
+            assert_eq!(seen_color(|w| upsample(color, w)), color);
+        }
+    }
+
+    #[test]
+    fn the_sensor_sees_wavelengths_in_their_colors() {
+        let red = sensor_response(650.0);
+        let blue = sensor_response(450.0);
+
+        assert!(red.red > red.green && red.red > red.blue);
+        assert!(blue.blue > blue.red && blue.blue > blue.green);
+    }
+
+    #[test]
+    fn deserializing_a_spectrum() {
+        let spectrum: Spectrum = serde_json::from_str("[[700, 0.2], [400, 0.8]]").unwrap();
+
+        assert_eq!(
+            spectrum,
+            Spectrum::new(vec![(400.0, 0.8), (700.0, 0.2)]).unwrap()
+        );
+        assert!(serde_json::from_str::<Spectrum>("[]").is_err());
+    }
+}
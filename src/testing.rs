@@ -0,0 +1,436 @@
+use crate::{
+    camera::{Camera, CameraBuilder},
+    canvas::Canvas,
+    color::{self, Color},
+    light::{Light, PointLight},
+    material::{self, Material},
+    model::{Model, OBJModelBuilder},
+    pattern::{Pattern3D, Pattern3DSpec},
+    scene::Scene,
+    shape::{Cube, Group, GroupBuilder, Plane, Shape, ShapeBuilder, Sphere},
+    transform::Transform,
+    tuple::{Point, Vector},
+    world::World,
+};
+
+/// Names of the reference scenes, in the order they're usually checked.
+pub const REFERENCE_SCENES: [&str; 6] = [
+    "shadows",
+    "reflection",
+    "refraction",
+    "patterns",
+    "obj",
+    "groups",
+];
+
+/// Width of the images of the reference scenes, in number of pixels.
+pub const REFERENCE_WIDTH: usize = 48;
+
+/// Height of the images of the reference scenes, in number of pixels.
+pub const REFERENCE_HEIGHT: usize = 36;
+
+/// Square pyramid modeled in the WaveFront OBJ format, used by the `"obj"` reference scene.
+const PYRAMID: &str = "\
+v -1 0 -1
+v 1 0 -1
+v 1 0 1
+v -1 0 1
+v 0 2 0
+
+f 1 2 5
+f 2 3 5
+f 3 4 5
+f 4 1 5
+f 1 4 3 2
+";
+
+/// Builds the reference scene with the given name, which is one of the [REFERENCE_SCENES], or
+/// `None` if there's no reference scene with that name.
+///
+/// Each scene exercises a major feature of the ray tracer with a handful of objects lit by a
+/// single point light, seen by the same camera:
+///
+/// * `"shadows"`: a sphere casting a shadow on a floor.
+/// * `"reflection"`: colored spheres over a mirror-like floor.
+/// * `"refraction"`: a glass sphere in front of a checkered wall.
+/// * `"patterns"`: stripe, gradient, ring and checker patterns on different shapes.
+/// * `"obj"`: a pyramid loaded from a WaveFront OBJ model.
+/// * `"groups"`: nested groups of shapes, each of them with it's own transformation.
+///
+/// Scenes are small enough to be rendered in a fraction of a second, so they can be used to check
+/// custom shapes, materials or integrators by adding them to a scene that's already known to
+/// render correctly.
+///
+pub fn reference_scene(name: &str) -> Option<Scene> {
+    let objects = match name {
+        "shadows" => shadows(),
+        "reflection" => reflection(),
+        "refraction" => refraction(),
+        "patterns" => patterns(),
+        "obj" => obj(),
+        "groups" => groups(),
+        _ => return None,
+    };
+
+    let camera = Camera::try_from(CameraBuilder {
+        width: REFERENCE_WIDTH,
+        height: REFERENCE_HEIGHT,
+        field_of_view: std::f64::consts::FRAC_PI_3,
+        transform: Transform::view(
+            Point::new(0.0, 1.5, -5.0),
+            Point::new(0.0, 1.0, 0.0),
+            Vector::new(0.0, 1.0, 0.0),
+        )
+        .unwrap(),
+    })
+    .unwrap();
+
+    let light = Light::Point(PointLight {
+        position: Point::new(-10.0, 10.0, -10.0),
+        intensity: color::consts::WHITE,
+        group: None,
+    });
+
+    Some(Scene {
+        camera,
+        world: World {
+            objects,
+            lights: vec![light],
+            ..Default::default()
+        },
+        names: Default::default(),
+    })
+}
+
+/// Renders the reference scene with the given name with the default [render
+/// settings](crate::render::RenderSettings).
+///
+/// Reference images are meant to be compared against images rendered before a change to the ray
+/// tracer, which should look exactly the same if the change didn't break any of the features
+/// covered by the scene. See [reference_scene] for the scenes that can be rendered.
+///
+/// # Panics
+///
+/// If `name` isn't one of the [REFERENCE_SCENES].
+///
+/// # Examples
+///
+/// ```
+/// use raytracer::testing::{self, REFERENCE_SCENES};
+///
+/// for name in REFERENCE_SCENES {
+///     let image = testing::render_reference(name).to_image();
+///
+///     assert_eq!(
+///         (image.width() as usize, image.height() as usize),
+///         (testing::REFERENCE_WIDTH, testing::REFERENCE_HEIGHT)
+///     );
+/// }
+/// ```
+///
+pub fn render_reference(name: &str) -> Canvas {
+    let Some(scene) = reference_scene(name) else {
+        panic!("unknown reference scene `{name}`");
+    };
+
+    scene.render()
+}
+
+fn floor(material: Material) -> Shape {
+    Shape::Plane(Plane::from(ShapeBuilder {
+        material,
+        ..Default::default()
+    }))
+}
+
+fn sphere(color: Color, transform: Transform) -> Shape {
+    Shape::Sphere(Sphere::from(ShapeBuilder {
+        material: Material {
+            pattern: Pattern3D::Solid(color),
+            diffuse: 0.7,
+            specular: 0.3,
+            ..Default::default()
+        },
+        transform,
+    }))
+}
+
+fn shadows() -> Vec<Shape> {
+    vec![
+        floor(Default::default()),
+        sphere(
+            Color {
+                red: 0.1,
+                green: 1.0,
+                blue: 0.5,
+            },
+            Transform::translation(0.0, 1.0, 0.0),
+        ),
+    ]
+}
+
+fn reflection() -> Vec<Shape> {
+    let mirror = Material {
+        pattern: Pattern3D::Solid(Color {
+            red: 0.2,
+            green: 0.2,
+            blue: 0.2,
+        }),
+        reflectivity: 0.8,
+        ..Default::default()
+    };
+
+    vec![
+        floor(mirror),
+        sphere(
+            color::consts::RED,
+            Transform::translation(-0.8, 0.5, 0.0) * Transform::scaling(0.5, 0.5, 0.5).unwrap(),
+        ),
+        sphere(
+            color::consts::BLUE,
+            Transform::translation(0.8, 0.5, 0.0) * Transform::scaling(0.5, 0.5, 0.5).unwrap(),
+        ),
+    ]
+}
+
+fn refraction() -> Vec<Shape> {
+    let wall = Shape::Plane(Plane::from(ShapeBuilder {
+        material: Material {
+            pattern: Pattern3D::Checker(Pattern3DSpec::new(
+                color::consts::WHITE,
+                color::consts::BLACK,
+                Transform::scaling(0.5, 0.5, 0.5).unwrap(),
+            )),
+            ..Default::default()
+        },
+        transform: Transform::translation(0.0, 0.0, 3.0)
+            * Transform::rotation_x(std::f64::consts::FRAC_PI_2),
+    }));
+
+    let glass = Shape::Sphere(Sphere::from(ShapeBuilder {
+        material: Material {
+            pattern: Pattern3D::Solid(color::consts::BLACK),
+            ambient: 0.0,
+            diffuse: 0.1,
+            reflectivity: 0.1,
+            transparency: 0.9,
+            index_of_refraction: material::consts::GLASS_INDEX_OF_REFRACTION,
+            ..Default::default()
+        },
+        transform: Transform::translation(0.0, 1.0, 0.0),
+    }));
+
+    vec![floor(Default::default()), wall, glass]
+}
+
+fn patterns() -> Vec<Shape> {
+    let pattern = |pattern: fn(Pattern3DSpec) -> Pattern3D, transform| Material {
+        pattern: pattern(Pattern3DSpec::new(
+            color::consts::WHITE,
+            Color {
+                red: 0.2,
+                green: 0.4,
+                blue: 0.8,
+            },
+            transform,
+        )),
+        ..Default::default()
+    };
+
+    let striped = Shape::Sphere(Sphere::from(ShapeBuilder {
+        material: pattern(
+            Pattern3D::Stripe,
+            Transform::scaling(0.2, 0.2, 0.2).unwrap(),
+        ),
+        transform: Transform::translation(-1.2, 1.0, 0.0)
+            * Transform::scaling(0.6, 0.6, 0.6).unwrap(),
+    }));
+
+    let gradient = Shape::Cube(Cube::from(ShapeBuilder {
+        material: pattern(
+            Pattern3D::Gradient,
+            Transform::translation(-1.0, 0.0, 0.0) * Transform::scaling(2.0, 1.0, 1.0).unwrap(),
+        ),
+        transform: Transform::translation(1.2, 1.0, 0.0)
+            * Transform::rotation_y(std::f64::consts::FRAC_PI_4)
+            * Transform::scaling(0.5, 0.5, 0.5).unwrap(),
+    }));
+
+    let rings = Shape::Plane(Plane::from(ShapeBuilder {
+        material: pattern(Pattern3D::Ring, Transform::scaling(0.5, 0.5, 0.5).unwrap()),
+        transform: Transform::translation(0.0, 0.0, 4.0)
+            * Transform::rotation_x(std::f64::consts::FRAC_PI_2),
+    }));
+
+    vec![
+        floor(pattern(Pattern3D::Checker, Default::default())),
+        rings,
+        striped,
+        gradient,
+    ]
+}
+
+fn obj() -> Vec<Shape> {
+    // The spec is a constant that's known to be valid.
+    let model = Model::try_from(OBJModelBuilder {
+        model_spec: PYRAMID,
+        transform: Transform::rotation_y(std::f64::consts::FRAC_PI_6),
+    })
+    .unwrap();
+
+    let mut pyramid = Shape::Group(Group::from(model));
+    pyramid.set_material(&Material {
+        pattern: Pattern3D::Solid(Color {
+            red: 1.0,
+            green: 0.8,
+            blue: 0.1,
+        }),
+        ..Default::default()
+    });
+
+    vec![floor(Default::default()), pyramid]
+}
+
+fn groups() -> Vec<Shape> {
+    let small_sphere = |x| {
+        sphere(
+            color::consts::GREEN,
+            Transform::translation(x, 0.0, 0.0) * Transform::scaling(0.3, 0.3, 0.3).unwrap(),
+        )
+    };
+
+    let row = Shape::Group(Group::from(GroupBuilder {
+        children: [small_sphere(-0.8), small_sphere(0.0), small_sphere(0.8)],
+        transform: Transform::translation(0.0, 0.8, 0.0),
+    }));
+
+    let cube = Shape::Cube(Cube::from(ShapeBuilder {
+        material: Material {
+            pattern: Pattern3D::Solid(color::consts::RED),
+            ..Default::default()
+        },
+        transform: Transform::scaling(0.4, 0.4, 0.4).unwrap(),
+    }));
+
+    let tower = Shape::Group(Group::from(GroupBuilder {
+        children: [row, cube],
+        transform: Transform::translation(0.0, 0.4, 0.0)
+            * Transform::rotation_y(std::f64::consts::FRAC_PI_6),
+    }));
+
+    vec![floor(Default::default()), tower]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn is_dominant(color: &Color, channel: fn(&Color) -> f64) -> bool {
+        [color.red, color.green, color.blue]
+            .into_iter()
+            .filter(|&value| value >= channel(color))
+            .count()
+            == 1
+    }
+
+    #[test]
+    fn every_reference_scene_can_be_built() {
+        for name in REFERENCE_SCENES {
+            let scene = reference_scene(name).unwrap();
+
+            assert_eq!(scene.camera.width(), REFERENCE_WIDTH);
+            assert_eq!(scene.camera.height(), REFERENCE_HEIGHT);
+        }
+
+        assert!(reference_scene("missing").is_none());
+    }
+
+    #[test]
+    #[should_panic(expected = "unknown reference scene `missing`")]
+    fn rendering_an_unknown_reference_scene() {
+        render_reference("missing");
+    }
+
+    #[test]
+    fn reference_scenes_always_render_the_same_image() {
+        for name in REFERENCE_SCENES {
+            assert_eq!(
+                render_reference(name).to_image(),
+                render_reference(name).to_image()
+            );
+        }
+    }
+
+    #[test]
+    fn the_reference_sphere_casts_a_shadow_on_the_floor() {
+        let image = render_reference("shadows");
+
+        let shadow = image.pixel_at(30, 24);
+        let lit_floor = image.pixel_at(10, 24);
+
+        assert!(shadow.red < lit_floor.red / 2.0);
+        assert!(is_dominant(image.pixel_at(24, 15), |color| color.green));
+    }
+
+    #[test]
+    fn the_reference_floor_reflects_the_spheres() {
+        let image = render_reference("reflection");
+
+        assert!(is_dominant(image.pixel_at(17, 31), |color| color.red));
+        assert!(is_dominant(image.pixel_at(30, 31), |color| color.blue));
+    }
+
+    #[test]
+    fn the_checkered_wall_is_seen_through_the_reference_glass_sphere() {
+        let image = render_reference("refraction");
+
+        let (mut bright, mut dark) = (0, 0);
+
+        for x in 18..30 {
+            let color = image.pixel_at(x, 17);
+
+            if color.red > 0.4 {
+                bright += 1;
+            } else if color.red < 0.1 {
+                dark += 1;
+            }
+        }
+
+        assert!(bright > 0 && dark > 0);
+    }
+
+    #[test]
+    fn the_reference_patterns_alternate_their_colors() {
+        let image = render_reference("patterns");
+
+        for y in [3, 33] {
+            let row: Vec<_> = (0..REFERENCE_WIDTH).map(|x| image.pixel_at(x, y)).collect();
+
+            assert!(row.iter().any(|color| color.red > 0.6));
+            assert!(row
+                .iter()
+                .any(|color| is_dominant(color, |color| color.blue)));
+        }
+    }
+
+    #[test]
+    fn the_reference_obj_model_is_lit_and_casts_a_shadow() {
+        let image = render_reference("obj");
+
+        let lit_face = image.pixel_at(20, 20);
+        assert!(lit_face.red > 0.5 && lit_face.green > 0.4 && lit_face.blue < 0.2);
+
+        assert!(image.pixel_at(34, 22).red < image.pixel_at(8, 22).red / 2.0);
+    }
+
+    #[test]
+    fn the_children_of_the_reference_groups_are_transformed_with_them() {
+        let image = render_reference("groups");
+
+        for x in [17, 23, 29] {
+            assert!(is_dominant(image.pixel_at(x, 15), |color| color.green));
+        }
+
+        assert!(is_dominant(image.pixel_at(21, 22), |color| color.red));
+    }
+}
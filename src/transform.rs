@@ -1,11 +1,15 @@
-use std::ops::Mul;
+use std::{fmt, ops::Mul};
 
-use serde::Deserialize;
+use serde::{
+    de::{self, MapAccess, SeqAccess, Visitor},
+    Deserialize, Deserializer,
+};
 use thiserror::Error;
 
 use crate::{
     float,
     matrix::{self, Matrix},
+    scene::UnknownFields,
     tuple::{Point, Vector},
 };
 
@@ -71,24 +75,39 @@ enum TransformDeserializer {
         x: f64,
         y: f64,
         z: f64,
+
+        #[serde(flatten)]
+        _unknown: UnknownFields,
     },
 
     Scaling {
         x: f64,
         y: f64,
         z: f64,
+
+        #[serde(flatten)]
+        _unknown: UnknownFields,
     },
 
     RotationX {
         degrees: f64,
+
+        #[serde(flatten)]
+        _unknown: UnknownFields,
     },
 
     RotationY {
         degrees: f64,
+
+        #[serde(flatten)]
+        _unknown: UnknownFields,
     },
 
     RotationZ {
         degrees: f64,
+
+        #[serde(flatten)]
+        _unknown: UnknownFields,
     },
 
     Shearing {
@@ -98,12 +117,28 @@ enum TransformDeserializer {
         yz: f64,
         zx: f64,
         zy: f64,
+
+        #[serde(flatten)]
+        _unknown: UnknownFields,
     },
 
     View {
         from: Point,
         to: Point,
         up: Vector,
+
+        #[serde(flatten)]
+        _unknown: UnknownFields,
+    },
+
+    Orbit {
+        target: Point,
+        radius: f64,
+        azimuth: f64,
+        elevation: f64,
+
+        #[serde(flatten)]
+        _unknown: UnknownFields,
     },
 }
 
@@ -112,11 +147,17 @@ impl TryFrom<TransformDeserializer> for Transform {
 
     fn try_from(value: TransformDeserializer) -> Result<Self, Self::Error> {
         Ok(match value {
-            TransformDeserializer::Translation { x, y, z } => Self::translation(x, y, z),
-            TransformDeserializer::Scaling { x, y, z } => Self::scaling(x, y, z)?,
-            TransformDeserializer::RotationX { degrees } => Self::rotation_x(degrees.to_radians()),
-            TransformDeserializer::RotationY { degrees } => Self::rotation_y(degrees.to_radians()),
-            TransformDeserializer::RotationZ { degrees } => Self::rotation_z(degrees.to_radians()),
+            TransformDeserializer::Translation { x, y, z, .. } => Self::translation(x, y, z),
+            TransformDeserializer::Scaling { x, y, z, .. } => Self::scaling(x, y, z)?,
+            TransformDeserializer::RotationX { degrees, .. } => {
+                Self::rotation_x(degrees.to_radians())
+            }
+            TransformDeserializer::RotationY { degrees, .. } => {
+                Self::rotation_y(degrees.to_radians())
+            }
+            TransformDeserializer::RotationZ { degrees, .. } => {
+                Self::rotation_z(degrees.to_radians())
+            }
             TransformDeserializer::Shearing {
                 xy,
                 xz,
@@ -124,12 +165,59 @@ impl TryFrom<TransformDeserializer> for Transform {
                 yz,
                 zx,
                 zy,
+                ..
             } => Self::shearing(xy, xz, yx, yz, zx, zy)?,
-            TransformDeserializer::View { from, to, up } => Self::view(from, to, up)?,
+            TransformDeserializer::View { from, to, up, .. } => Self::view(from, to, up)?,
+            TransformDeserializer::Orbit {
+                target,
+                radius,
+                azimuth,
+                elevation,
+                ..
+            } => Self::orbit(target, radius, azimuth.to_radians(), elevation.to_radians())?,
         })
     }
 }
 
+/// Deserializes either a single transformation or a list of transformations, that are combined
+/// into a single one by applying them in the same order they are listed.
+pub(crate) fn deserialize_chain<'de, D>(deserializer: D) -> Result<Transform, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    struct ChainVisitor;
+
+    impl<'de> Visitor<'de> for ChainVisitor {
+        type Value = Transform;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            formatter.write_str("a transformation or a list of transformations")
+        }
+
+        fn visit_map<A>(self, map: A) -> Result<Self::Value, A::Error>
+        where
+            A: MapAccess<'de>,
+        {
+            Transform::deserialize(de::value::MapAccessDeserializer::new(map))
+        }
+
+        fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+        where
+            A: SeqAccess<'de>,
+        {
+            let mut chain = Transform::default();
+
+            while let Some(transform) = seq.next_element::<Transform>()? {
+                chain = transform * chain;
+            }
+
+            Ok(chain)
+        }
+    }
+
+    deserializer.deserialize_any(ChainVisitor)
+}
+
 impl Default for Transform {
     fn default() -> Self {
         Self(matrix::consts::IDENTITY_4X4)
@@ -235,16 +323,16 @@ impl Transform {
     /// * `from` - Point where the camera is going to be positioned.
     /// * `to` - Point where the center of camera is going to be looking at.
     /// * `up` - Vector that indicated the direction considered at "up". This orientates the camera
-    /// so that your image is not upside-down.
+    ///   so that your image is not upside-down.
     ///
     /// # Errors
     ///
     /// * Fails when the `from` and `to` vectors are the same vectors. This would mean that the
-    /// camera it's looking at itself.
+    ///   camera it's looking at itself.
     ///
     /// * Fails when the resulting vector of subtracting `to - from` is collinear with the `up`
-    /// vector. This would mean that the camera cannot orient itself, there would be a conflict
-    /// between the direction it's looking at and the direction it should consider as "up".
+    ///   vector. This would mean that the camera cannot orient itself, there would be a conflict
+    ///   between the direction it's looking at and the direction it should consider as "up".
     ///
     /// * Fails when the `up` vector is null.
     ///
@@ -274,6 +362,77 @@ impl Transform {
         Ok(orientation * Self::translation(-from.0.x, -from.0.y, -from.0.z))
     }
 
+    /// Constructs a view transformation for a camera that orbits around a target, always looking
+    /// at it.
+    ///
+    /// The camera is placed on a sphere around the target, so a turntable animation only has to
+    /// sweep the `azimuth` from one frame to the next.
+    ///
+    /// # Arguments
+    ///
+    /// * `target` - Point the camera is looking at.
+    /// * `radius` - Distance between the camera and the target.
+    /// * `azimuth` - Angle around the vertical axis of the target, in radians. With an azimuth of
+    ///   `0.0` the camera is in the negative `z` direction from the target, and positive angles
+    ///   move it towards the positive `x` direction.
+    /// * `elevation` - Angle above the horizontal plane of the target, in radians.
+    ///
+    /// The camera is always oriented so that "up" points away from the horizontal plane, even
+    /// when looking straight down or up at the target.
+    ///
+    /// # Errors
+    ///
+    /// * Fails when the `radius` is zero. This would mean that the camera it's looking at itself.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use raytracer::{
+    ///     transform::Transform,
+    ///     tuple::{Point, Vector},
+    /// };
+    ///
+    /// let target = Point::new(0.0, 1.0, 0.0);
+    ///
+    /// let frames: Vec<_> = (0..120)
+    ///     .map(|frame| {
+    ///         let azimuth = f64::from(frame) * std::f64::consts::TAU / 120.0;
+    ///         Transform::orbit(target, 5.0, azimuth, 0.0).unwrap()
+    ///     })
+    ///     .collect();
+    ///
+    /// assert_eq!(
+    ///     frames[0],
+    ///     Transform::view(
+    ///         Point::new(0.0, 1.0, -5.0),
+    ///         target,
+    ///         Vector::new(0.0, 1.0, 0.0),
+    ///     )
+    ///     .unwrap()
+    /// );
+    /// ```
+    ///
+    pub fn orbit(target: Point, radius: f64, azimuth: f64, elevation: f64) -> Result<Self, Error> {
+        let (sin_azimuth, cos_azimuth) = azimuth.sin_cos();
+        let (sin_elevation, cos_elevation) = elevation.sin_cos();
+
+        let offset = Vector::new(
+            cos_elevation * sin_azimuth,
+            sin_elevation,
+            -cos_elevation * cos_azimuth,
+        ) * radius;
+
+        // Direction in which the camera would move if the elevation increased, which is never
+        // collinear to the direction the camera is looking at.
+        let up = Vector::new(
+            -sin_elevation * sin_azimuth,
+            cos_elevation,
+            sin_elevation * cos_azimuth,
+        );
+
+        Self::view(target + offset, target, up)
+    }
+
     pub(crate) fn inverse(self) -> Self {
         // Only isomorphic matrices can be constructed through this type's public API. This means that
         // the matrix associated with every transformation is going to be invertible.
@@ -693,6 +852,7 @@ mod tests {
                 x: 1.0,
                 y: -3.0,
                 z: 0.25,
+                _unknown: UnknownFields,
             },
             &tokens,
         );
@@ -723,6 +883,7 @@ mod tests {
                 x: 1.0,
                 y: -3.0,
                 z: 0.25,
+                _unknown: UnknownFields,
             },
             &tokens,
         );
@@ -766,7 +927,13 @@ mod tests {
             Token::StructEnd,
         ];
 
-        assert_de_tokens(&TransformDeserializer::RotationX { degrees: 60.0 }, &tokens);
+        assert_de_tokens(
+            &TransformDeserializer::RotationX {
+                degrees: 60.0,
+                _unknown: UnknownFields,
+            },
+            &tokens,
+        );
         assert_de_tokens(&Transform::rotation_x(std::f64::consts::FRAC_PI_3), &tokens);
     }
 
@@ -785,7 +952,10 @@ mod tests {
         ];
 
         assert_de_tokens(
-            &TransformDeserializer::RotationY { degrees: 120.0 },
+            &TransformDeserializer::RotationY {
+                degrees: 120.0,
+                _unknown: UnknownFields,
+            },
             &tokens,
         );
         assert_de_tokens(&Transform::rotation_y(120_f64.to_radians()), &tokens);
@@ -806,7 +976,10 @@ mod tests {
         ];
 
         assert_de_tokens(
-            &TransformDeserializer::RotationZ { degrees: 720.0 },
+            &TransformDeserializer::RotationZ {
+                degrees: 720.0,
+                _unknown: UnknownFields,
+            },
             &tokens,
         );
         assert_de_tokens(&Transform::rotation_z(720_f64.to_radians()), &tokens);
@@ -844,6 +1017,7 @@ mod tests {
                 yz: 7.89,
                 zx: 11.1,
                 zy: 0.001,
+                _unknown: UnknownFields,
             },
             &tokens,
         );
@@ -942,10 +1116,128 @@ mod tests {
             Token::StructEnd,
         ];
 
-        assert_de_tokens(&TransformDeserializer::View { from, to, up }, &tokens);
+        assert_de_tokens(
+            &TransformDeserializer::View {
+                from,
+                to,
+                up,
+                _unknown: UnknownFields,
+            },
+            &tokens,
+        );
         assert_de_tokens(&Transform::view(from, to, up).unwrap(), &tokens);
     }
 
+    #[test]
+    fn an_orbit_in_front_of_the_target() {
+        let target = Point::new(1.0, 2.0, 3.0);
+
+        let transform = Transform::orbit(target, 4.0, 0.0, 0.0);
+
+        assert_eq!(
+            transform,
+            Transform::view(
+                Point::new(1.0, 2.0, -1.0),
+                target,
+                Vector::new(0.0, 1.0, 0.0)
+            )
+        );
+    }
+
+    #[test]
+    fn sweeping_the_azimuth_of_an_orbit() {
+        let target = Point::new(0.0, 0.0, 0.0);
+
+        let transform = Transform::orbit(target, 4.0, std::f64::consts::FRAC_PI_2, 0.0);
+
+        assert_eq!(
+            transform,
+            Transform::view(
+                Point::new(4.0, 0.0, 0.0),
+                target,
+                Vector::new(0.0, 1.0, 0.0)
+            )
+        );
+    }
+
+    #[test]
+    fn an_orbit_looking_straight_down_at_the_target() {
+        let target = Point::new(0.0, 0.0, 0.0);
+
+        let transform = Transform::orbit(target, 4.0, 0.0, std::f64::consts::FRAC_PI_2);
+
+        assert_eq!(
+            transform,
+            Transform::view(
+                Point::new(0.0, 4.0, 0.0),
+                target,
+                Vector::new(0.0, 0.0, 1.0)
+            )
+        );
+    }
+
+    #[test]
+    fn trying_to_create_an_orbit_with_a_null_radius() {
+        let transform = Transform::orbit(Point::new(0.0, 0.0, 0.0), 0.0, 1.0, 1.0);
+
+        assert_eq!(transform, Err(Error::EqualFromAndToVectors));
+    }
+
+    #[test]
+    fn deserializing_an_orbit_transformation() {
+        let target = Point::new(0.0, 1.0, 0.0);
+
+        let tokens = [
+            Token::Struct {
+                name: "TransformDeserializer",
+                len: 5,
+            },
+            Token::Str("type"),
+            Token::Str("orbit"),
+            // target: Point
+            Token::Str("target"),
+            Token::Struct {
+                name: "Point",
+                len: 3,
+            },
+            Token::Str("x"),
+            Token::F64(target.0.x),
+            Token::Str("y"),
+            Token::F64(target.0.y),
+            Token::Str("z"),
+            Token::F64(target.0.z),
+            Token::StructEnd,
+            Token::Str("radius"),
+            Token::F64(5.0),
+            Token::Str("azimuth"),
+            Token::F64(90.0),
+            Token::Str("elevation"),
+            Token::F64(30.0),
+            Token::StructEnd,
+        ];
+
+        assert_de_tokens(
+            &TransformDeserializer::Orbit {
+                target,
+                radius: 5.0,
+                azimuth: 90.0,
+                elevation: 30.0,
+                _unknown: UnknownFields,
+            },
+            &tokens,
+        );
+        assert_de_tokens(
+            &Transform::orbit(
+                target,
+                5.0,
+                std::f64::consts::FRAC_PI_2,
+                std::f64::consts::FRAC_PI_6,
+            )
+            .unwrap(),
+            &tokens,
+        );
+    }
+
     #[test]
     fn trying_to_deserialize_an_invalid_view_transformation() {
         let from = Point::new(1.0, 1.0, 1.0);
@@ -1004,4 +1296,82 @@ mod tests {
             "`from` and `to` points cannot be equal",
         );
     }
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Chain(#[serde(deserialize_with = "deserialize_chain")] Transform);
+
+    #[test]
+    fn deserializing_a_chain_with_a_single_transformation() {
+        assert_de_tokens(
+            &Chain(Transform::translation(1.0, 2.0, 3.0)),
+            &[
+                Token::NewtypeStruct { name: "Chain" },
+                Token::Map { len: Some(4) },
+                Token::Str("type"),
+                Token::Str("translation"),
+                Token::Str("x"),
+                Token::F64(1.0),
+                Token::Str("y"),
+                Token::F64(2.0),
+                Token::Str("z"),
+                Token::F64(3.0),
+                Token::MapEnd,
+            ],
+        );
+    }
+
+    #[test]
+    fn deserializing_a_chain_of_transformations() {
+        assert_de_tokens(
+            &Chain(
+                Transform::translation(1.0, 0.0, 0.0) * Transform::scaling(2.0, 2.0, 2.0).unwrap(),
+            ),
+            &[
+                Token::NewtypeStruct { name: "Chain" },
+                Token::Seq { len: Some(2) },
+                Token::Map { len: Some(4) },
+                Token::Str("type"),
+                Token::Str("scaling"),
+                Token::Str("x"),
+                Token::F64(2.0),
+                Token::Str("y"),
+                Token::F64(2.0),
+                Token::Str("z"),
+                Token::F64(2.0),
+                Token::MapEnd,
+                Token::Map { len: Some(4) },
+                Token::Str("type"),
+                Token::Str("translation"),
+                Token::Str("x"),
+                Token::F64(1.0),
+                Token::Str("y"),
+                Token::F64(0.0),
+                Token::Str("z"),
+                Token::F64(0.0),
+                Token::MapEnd,
+                Token::SeqEnd,
+            ],
+        );
+    }
+
+    #[test]
+    fn trying_to_deserialize_a_chain_with_an_invalid_transformation() {
+        assert_de_tokens_error::<Chain>(
+            &[
+                Token::NewtypeStruct { name: "Chain" },
+                Token::Seq { len: Some(1) },
+                Token::Map { len: Some(4) },
+                Token::Str("type"),
+                Token::Str("scaling"),
+                Token::Str("x"),
+                Token::F64(0.0),
+                Token::Str("y"),
+                Token::F64(1.0),
+                Token::Str("z"),
+                Token::F64(1.0),
+                Token::MapEnd,
+            ],
+            "components cannot be scaled to zero",
+        );
+    }
 }
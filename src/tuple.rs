@@ -3,7 +3,7 @@ use std::ops::{Add, Div, Mul, Neg, Sub};
 use serde::Deserialize;
 use thiserror::Error;
 
-use crate::float;
+use crate::{float, scene::UnknownFields};
 
 const POINT_W: f64 = 1.0;
 const VECTOR_W: f64 = 0.0;
@@ -48,6 +48,9 @@ struct CoordinateDeserializer {
     x: f64,
     y: f64,
     z: f64,
+
+    #[serde(flatten)]
+    _unknown: UnknownFields,
 }
 
 impl From<CoordinateDeserializer> for Point {
@@ -1,73 +1,622 @@
+use std::{
+    collections::BTreeMap,
+    io::{self, Write},
+    ops::Deref,
+    sync::Arc,
+};
+
+use serde::Deserialize;
+use thiserror::Error;
+
 use crate::{
-    color::{self, Color},
     float,
-    intersection::{Computation, Intersection},
-    light::Light,
+    intersection::{Intersection, Intersections},
+    light::{Light, LightFalloff},
+    material::{Material, MaterialOverride},
+    pattern::{Pattern3D, TextureAtlas},
     ray::Ray,
-    shape::Shape,
-    tuple::Point,
+    render,
+    shape::{EditedPrototypes, Group, GroupBuilder, Shape, ShapeId},
+    transform::Transform,
+    tuple::{Point, Tuple},
 };
 
-pub(crate) const RECURSION_DEPTH: u8 = 5;
+/// Maximum number of children of each group in the bounding volume hierarchy built when a world
+/// is frozen.
+const FROZEN_GROUP_THRESHOLD: usize = 4;
+
+/// Name of the light group of the lights that don't name any other [light group](Light::group).
+pub const DEFAULT_LIGHT_GROUP: &str = "default";
+
+/// The error type when trying to declare the units of a world.
+#[derive(Debug, PartialEq, Error)]
+pub enum Error {
+    /// The error type when trying to declare units whose length is not a positive number.
+    #[error("units must have a positive length, got {0} meters")]
+    NonPositiveUnitLength(f64),
+
+    /// The error type when trying to edit a shape that is not in the world.
+    #[error("no shape in the world has the identifier {0:?}")]
+    UnknownShape(ShapeId),
+}
 
 /// A collection of shapes and light sources.
-#[derive(Clone, Debug, Default)]
+///
+/// Worlds are [Send] and [Sync], so a single world can be rendered from many threads at once, or
+/// shared between the tasks of a server behind an [Arc].
+///
+/// The shapes of a clone of a world keep their [identifiers](ShapeId), unlike clones of a single
+/// [Shape], so identifiers taken from the original still find the same shapes in the copy.
+///
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
 pub struct World {
     /// Vector of shapes that live in the world.
     pub objects: Vec<Shape>,
 
     /// Vector of lights that live in the world.
     pub lights: Vec<Light>,
+
+    /// Physical length of the units in which the world is modeled.
+    pub units: UnitScale,
+
+    /// How the light of point and area lights fades with distance, which it doesn't by default.
+    pub light_falloff: LightFalloff,
+}
+
+impl Clone for World {
+    fn clone(&self) -> Self {
+        Self {
+            objects: self.objects.iter().map(Shape::clone_with_ids).collect(),
+            lights: self.lights.clone(),
+            units: self.units,
+            light_falloff: self.light_falloff,
+        }
+    }
+}
+
+// A world is shared by every thread that renders it, so it and everything it's made of must be
+// safe to send and share between threads. Patterns or materials that ever hold closures must store
+// them as `Arc<dyn Fn(...) + Send + Sync>` to keep this guarantee.
+const _: () = {
+    const fn assert_send_sync<T: Send + Sync>() {}
+
+    assert_send_sync::<World>();
+    assert_send_sync::<FrozenWorld>();
+    assert_send_sync::<Shape>();
+    assert_send_sync::<Material>();
+    assert_send_sync::<Pattern3D>();
+    assert_send_sync::<Light>();
+};
+
+/// Physical length of one unit of a world, which is one meter by default.
+///
+/// Some tolerances of the rendering process depend on the size of the world, such as how far
+/// above a surface the rays that leave it start, which prevents surfaces from shadowing
+/// themselves. Declaring the units of a world modeled at a very different scale, such as an
+/// architectural model in millimeters, keeps those tolerances proportional to it's size.
+///
+/// # Examples
+///
+/// ```
+/// use raytracer::world::{UnitScale, World};
+///
+/// let world = World {
+///     units: UnitScale::MILLIMETERS,
+///     ..Default::default()
+/// };
+///
+/// assert_eq!(world.units.meters_per_unit(), 0.001);
+/// assert_eq!(UnitScale::new(0.3048), Ok(UnitScale::FEET));
+/// ```
+///
+/// # Deserialization
+///
+/// Units are deserialized either from the name of a common unit, which is one of `meters`,
+/// `centimeters`, `millimeters`, `inches` or `feet`, or from the length of a unit in meters.
+///
+/// ```json
+/// { "units": "millimeters" }
+/// ```
+///
+#[derive(Copy, Clone, Debug, PartialEq, Deserialize)]
+#[serde(try_from = "UnitScaleDeserializer")]
+pub struct UnitScale {
+    meters_per_unit: f64,
+    epsilon: f64,
+}
+
+#[derive(Debug, PartialEq, Deserialize)]
+#[serde(untagged)]
+enum UnitScaleDeserializer {
+    Named(NamedUnit),
+    MetersPerUnit(f64),
+}
+
+#[derive(Debug, PartialEq, Deserialize)]
+#[serde(rename_all(deserialize = "snake_case"))]
+enum NamedUnit {
+    Meters,
+    Centimeters,
+    Millimeters,
+    Inches,
+    Feet,
+}
+
+impl TryFrom<UnitScaleDeserializer> for UnitScale {
+    type Error = Error;
+
+    fn try_from(value: UnitScaleDeserializer) -> Result<Self, Self::Error> {
+        match value {
+            UnitScaleDeserializer::Named(unit) => Ok(match unit {
+                NamedUnit::Meters => Self::METERS,
+                NamedUnit::Centimeters => Self::CENTIMETERS,
+                NamedUnit::Millimeters => Self::MILLIMETERS,
+                NamedUnit::Inches => Self::INCHES,
+                NamedUnit::Feet => Self::FEET,
+            }),
+            UnitScaleDeserializer::MetersPerUnit(meters_per_unit) => Self::new(meters_per_unit),
+        }
+    }
+}
+
+impl Default for UnitScale {
+    fn default() -> Self {
+        Self::METERS
+    }
+}
+
+impl UnitScale {
+    /// Worlds modeled in meters.
+    pub const METERS: Self = Self::with_length(1.0);
+
+    /// Worlds modeled in centimeters.
+    pub const CENTIMETERS: Self = Self::with_length(0.01);
+
+    /// Worlds modeled in millimeters.
+    pub const MILLIMETERS: Self = Self::with_length(0.001);
+
+    /// Worlds modeled in inches.
+    pub const INCHES: Self = Self::with_length(0.0254);
+
+    /// Worlds modeled in feet.
+    pub const FEET: Self = Self::with_length(0.3048);
+
+    /// Constructs the units of a world from the length of one unit in meters.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the length is not a positive finite number.
+    ///
+    pub fn new(meters_per_unit: f64) -> Result<Self, Error> {
+        if meters_per_unit.is_finite() && meters_per_unit > 0.0 {
+            Ok(Self::with_length(meters_per_unit))
+        } else {
+            Err(Error::NonPositiveUnitLength(meters_per_unit))
+        }
+    }
+
+    const fn with_length(meters_per_unit: f64) -> Self {
+        Self {
+            meters_per_unit,
+            epsilon: float::EPSILON,
+        }
+    }
+
+    /// Changes the minimum distance in meters above a surface at which the rays that leave it
+    /// start, which is usually the [epsilon](crate::config::Config::epsilon) of the configuration
+    /// of a program. Distances that are not positive finite numbers keep the default of a
+    /// hundredth of a millimeter.
+    pub fn with_epsilon(self, epsilon: f64) -> Self {
+        if epsilon.is_finite() && epsilon > 0.0 {
+            Self { epsilon, ..self }
+        } else {
+            self
+        }
+    }
+
+    /// Length of one unit in meters.
+    pub fn meters_per_unit(&self) -> f64 {
+        self.meters_per_unit
+    }
+
+    /// Converts a length in meters to this units.
+    pub fn from_meters(&self, meters: f64) -> f64 {
+        meters / self.meters_per_unit
+    }
+
+    /// Minimum distance above a surface at which the rays that leave it start, which is a
+    /// hundredth of a millimeter unless it's [overridden](UnitScale::with_epsilon).
+    pub(crate) fn surface_offset(&self) -> f64 {
+        self.from_meters(self.epsilon)
+    }
+}
+
+/// A world whose geometry can't be modified anymore.
+///
+/// Frozen worlds are meant to be rendered many times, such as in animations where only the camera
+/// moves. The bounding volume hierarchy of their shapes is built once when the world is frozen,
+/// and every shape already caches it's transformation to and from world space, so nothing has to
+/// be recomputed between frames. Clones of a frozen world share the same geometry, so they can be
+/// cheaply handed to multiple threads or render jobs.
+///
+/// A frozen world can be used anywhere a [World] reference is expected.
+///
+/// # Examples
+///
+/// ```
+/// use raytracer::{
+///     camera::{Camera, CameraBuilder},
+///     shape::Shape,
+///     transform::Transform,
+///     tuple::{Point, Vector},
+///     world::World,
+/// };
+///
+/// let world = World {
+///     objects: vec![Shape::Sphere(Default::default())],
+///     lights: vec![],
+///     ..Default::default()
+/// }
+/// .freeze();
+///
+/// for frame in 0..3 {
+///     let camera = Camera::try_from(CameraBuilder {
+///         width: 8,
+///         height: 8,
+///         field_of_view: std::f64::consts::FRAC_PI_3,
+///         transform: Transform::view(
+///             Point::new(frame as f64, 0.0, -5.0),
+///             Point::new(0.0, 0.0, 0.0),
+///             Vector::new(0.0, 1.0, 0.0),
+///         )
+///         .unwrap(),
+///     })
+///     .unwrap();
+///
+///     let image = camera.render(&world);
+/// }
+/// ```
+///
+#[derive(Clone, Debug)]
+pub struct FrozenWorld {
+    world: Arc<World>,
 }
 
 impl World {
-    pub(crate) fn color_at(&self, ray: &Ray, recursion_depth: u8) -> Color {
-        let mut xs = self.intersect(ray);
+    /// Indices of the lights of each light group, including the [DEFAULT_LIGHT_GROUP] with every
+    /// light that doesn't name any other group, sorted by the name of the group.
+    pub(crate) fn light_group_members(&self) -> Vec<(&str, Vec<usize>)> {
+        let mut groups = BTreeMap::from([(DEFAULT_LIGHT_GROUP, Vec::new())]);
+
+        for (i, light) in self.lights.iter().enumerate() {
+            groups
+                .entry(light.group().unwrap_or(DEFAULT_LIGHT_GROUP))
+                .or_default()
+                .push(i);
+        }
 
-        Intersection::hit(&mut xs).map_or(color::consts::BLACK, |hit| {
-            self.shade_hit(hit.prepare_computation(ray, xs), recursion_depth)
-        })
+        groups.into_iter().collect()
     }
 
-    fn intersect(&self, ray: &Ray) -> Vec<Intersection<'_>> {
-        let mut intersections: Vec<_> = self
-            .objects
-            .iter()
-            .flat_map(|obj| obj.intersect(ray))
-            .collect();
+    /// Freezes the world, building a bounding volume hierarchy for it's shapes so it can be
+    /// rendered repeatedly without any further preparation.
+    ///
+    /// Shapes with a finite size are gathered into a single group that is divided into
+    /// subgroups. Unbounded shapes, like planes, stay at the top-level of the world, as they would
+    /// make every bounding volume that contains them unbounded as well.
+    ///
+    pub fn freeze(self) -> FrozenWorld {
+        let (bounded, mut objects): (Vec<_>, Vec<_>) =
+            self.objects.into_iter().partition(|object| {
+                let bounding_box = object.as_ref().parent_space_bounding_box;
+
+                [bounding_box.min, bounding_box.max].iter().all(
+                    |Point(Tuple { x, y, z, .. })| x.is_finite() && y.is_finite() && z.is_finite(),
+                )
+            });
+
+        log::debug!(
+            "freezing a world with {} bounded and {} unbounded objects",
+            bounded.len(),
+            objects.len()
+        );
 
-        Intersection::sort(&mut intersections);
-        intersections
+        if !bounded.is_empty() {
+            let mut group = Group::from(GroupBuilder {
+                children: bounded,
+                transform: Default::default(),
+            });
+
+            group.divide(FROZEN_GROUP_THRESHOLD);
+            objects.push(Shape::Group(group));
+        }
+
+        FrozenWorld {
+            world: Arc::new(World {
+                objects,
+                lights: self.lights,
+                units: self.units,
+                light_falloff: self.light_falloff,
+            }),
+        }
     }
 
-    fn shade_hit(&self, comps: Computation, recursion_depth: u8) -> Color {
-        self.lights.iter().fold(color::consts::BLACK, |acc, light| {
-            let object = comps.intersection.object;
-            let material = &object.as_ref().material;
+    /// Writes the geometry of the world in [WaveFront OBJ
+    /// format](https://en.wikipedia.org/wiki/Wavefront_.obj_file).
+    ///
+    /// Analytic shapes are approximated with triangles, with curved surfaces divided into
+    /// `resolution` segments around their circumference. Every vertex is written in world space,
+    /// so the transforms of the objects are already applied to the exported geometry. Planes and
+    /// open-ended cylinders are clamped to a finite size.
+    ///
+    /// Each object of the world is exported as a separate OBJ object. Lights and materials are
+    /// not exported.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the geometry could not be written to `writer`.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::{fs::File, io::BufWriter};
+    ///
+    /// use raytracer::{shape::Shape, world::World};
+    ///
+    /// let world = World {
+    ///     objects: vec![Shape::Sphere(Default::default())],
+    ///     lights: vec![],
+    ///     ..Default::default()
+    /// };
+    ///
+    /// let file = BufWriter::new(File::create("world.obj").unwrap());
+    /// world.export_obj(file, 32).unwrap();
+    /// ```
+    ///
+    pub fn export_obj<W: Write>(&self, mut writer: W, resolution: usize) -> io::Result<()> {
+        let mut vertex_count = 0;
+
+        for (i, object) in self.objects.iter().enumerate() {
+            let name = match object {
+                Shape::Billboard(_) => "billboard",
+                Shape::Cube(_) => "cube",
+                Shape::Curve(_) => "curve",
+                Shape::Cylinder(_) => "cylinder",
+                Shape::Group(_) => "group",
+                Shape::Instance(_) => "instance",
+                Shape::Plane(_) => "plane",
+                Shape::SmoothTriangle(_) | Shape::Triangle(_) => "triangle",
+                Shape::Sphere(_) => "sphere",
+            };
+
+            writeln!(writer, "o {name}_{}", i + 1)?;
 
-            let light_intensity = light.intensity_at(self, comps.over_point);
+            let triangles = object.tessellate(resolution);
 
-            let surface_color = material.lighting(
-                object,
-                light,
-                comps.over_point,
-                comps.eyev,
-                comps.normalv,
-                light_intensity,
+            for vertex in triangles.iter().flatten() {
+                let Point(Tuple { x, y, z, .. }) = vertex;
+                writeln!(writer, "v {x} {y} {z}")?;
+            }
+
+            // OBJ vertex indices are 1-indexed and global to the whole file.
+            for _ in &triangles {
+                writeln!(
+                    writer,
+                    "f {} {} {}",
+                    vertex_count + 1,
+                    vertex_count + 2,
+                    vertex_count + 3
+                )?;
+
+                vertex_count += 3;
+            }
+        }
+
+        writer.flush()
+    }
+
+    /// Returns the shape with the given identifier, looking inside of groups as well.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use raytracer::{
+    ///     shape::{Group, GroupBuilder, Shape},
+    ///     world::World,
+    /// };
+    ///
+    /// let sphere = Shape::Sphere(Default::default());
+    /// let id = sphere.id();
+    ///
+    /// let world = World {
+    ///     objects: vec![Shape::Group(Group::from(GroupBuilder {
+    ///         children: [sphere],
+    ///         transform: Default::default(),
+    ///     }))],
+    ///     lights: vec![],
+    ///     ..Default::default()
+    /// };
+    ///
+    /// assert_eq!(world.shape(id).map(Shape::id), Some(id));
+    /// ```
+    ///
+    pub fn shape(&self, id: ShapeId) -> Option<&Shape> {
+        self.objects.iter().find_map(|object| object.find(id))
+    }
+
+    /// Moves the shape with the given identifier, either an object of the world or a shape inside
+    /// of one of it's groups, by replacing it's transform with `transform`, which goes from the
+    /// space of the shape to the space of the world. The shapes inside of a group keep their
+    /// placement relative to it.
+    ///
+    /// The bounding boxes of the groups that contain the shape are refit around it, so a
+    /// [divided](Group::divide) group keeps working without being divided again, and colors
+    /// [cached](crate::pattern::CachedPattern) for the shape are forgotten. A group whose shapes
+    /// moved far from each other can get slower though, in which case it's better to divide it
+    /// again.
+    ///
+    /// # Errors
+    ///
+    /// Fails if there's no shape with the given identifier in the world.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use raytracer::{shape::Shape, transform::Transform, world::World};
+    ///
+    /// let sphere = Shape::Sphere(Default::default());
+    /// let id = sphere.id();
+    ///
+    /// let mut world = World {
+    ///     objects: vec![sphere],
+    ///     ..Default::default()
+    /// };
+    ///
+    /// world.set_transform(id, Transform::translation(0.0, 1.0, 0.0)).unwrap();
+    ///
+    /// assert_eq!(world.shape(id).unwrap().transform(), Transform::translation(0.0, 1.0, 0.0));
+    /// ```
+    ///
+    pub fn set_transform(&mut self, id: ShapeId, transform: Transform) -> Result<(), Error> {
+        self.edit(id, |shape| shape.set_transform(transform))
+    }
+
+    /// Replaces the material of the shape with the given identifier, or of every shape inside of
+    /// it if it's a group.
+    ///
+    /// # Errors
+    ///
+    /// Fails if there's no shape with the given identifier in the world.
+    ///
+    pub fn set_material(&mut self, id: ShapeId, material: Material) -> Result<(), Error> {
+        self.edit(id, |shape| shape.set_material(&material))
+    }
+
+    /// Applies an override to the material of the shape with the given identifier, or of every
+    /// shape inside of it if it's a group, keeping the fields that the override doesn't change.
+    ///
+    /// # Errors
+    ///
+    /// Fails if there's no shape with the given identifier in the world.
+    ///
+    pub fn override_material(
+        &mut self,
+        id: ShapeId,
+        material_override: &MaterialOverride,
+    ) -> Result<(), Error> {
+        self.edit(id, |shape| shape.override_material(material_override))
+    }
+
+    /// [Conserves the energy](Material::conserve_energy) of the material of every shape of the
+    /// world, so no surface gives off more light than it receives. Returns the number of materials
+    /// that were changed.
+    ///
+    /// Scenes can do this as soon as they're parsed with their `energy_conservation` field. See
+    /// [Scene](crate::scene::Scene) for details.
+    ///
+    pub fn conserve_energy(&mut self) -> usize {
+        let mut edited = EditedPrototypes::new();
+
+        self.objects
+            .iter_mut()
+            .map(|object| object.conserve_energy(&mut edited))
+            .sum()
+    }
+
+    /// Packs every image texture of the world, from the patterns of the materials and from the
+    /// billboards, into a single [TextureAtlas], and replaces each one of them by it's region of
+    /// the atlas, which looks exactly the same.
+    ///
+    /// Patterns shared by many shapes are copied for each one of them, and
+    /// [cached](crate::pattern::CachedPattern) patterns start with an empty cache. The instances
+    /// of a [prototype](crate::shape::Instance) keep sharing a single copy of it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::sync::Arc;
+    ///
+    /// use raytracer::{
+    ///     material::Material,
+    ///     pattern::{ImageTexture, Pattern3D, TextureMap, UvMapping, UvPattern},
+    ///     shape::{Plane, Shape, ShapeBuilder},
+    ///     world::World,
+    /// };
+    ///
+    /// # let open = |_| ImageTexture::from(&image::DynamicImage::new_rgba8(4, 4));
+    /// let decal = |path| Shape::Plane(Plane::from(ShapeBuilder {
+    ///     material: Material {
+    ///         pattern: Pattern3D::Texture(Arc::new(TextureMap::new(
+    ///             UvMapping::Planar(UvPattern::Image(Arc::new(open(path)))),
+    ///             Default::default(),
+    ///         ))),
+    ///         ..Default::default()
+    ///     },
+    ///     ..Default::default()
+    /// }));
+    ///
+    /// let mut world = World {
+    ///     objects: vec![decal("arrow.png"), decal("stop.png")],
+    ///     ..Default::default()
+    /// };
+    ///
+    /// let atlas = world.pack_textures();
+    ///
+    /// assert_eq!(atlas.len(), 2);
+    /// ```
+    ///
+    pub fn pack_textures(&mut self) -> TextureAtlas {
+        let mut textures = vec![];
+
+        let mut edited = EditedPrototypes::new();
+
+        for object in &mut self.objects {
+            object.replace_images(
+                &mut |image| {
+                    textures.push(image.clone());
+                    image.clone()
+                },
+                &mut edited,
             );
+        }
 
-            let reflected_color = self.reflected_color(&comps, recursion_depth);
-            let refracted_color = self.refracted_color(&comps, recursion_depth);
+        let atlas = TextureAtlas::pack(textures);
+        let mut edited = EditedPrototypes::new();
 
-            let reflectance_color = if (material.reflectivity * material.transparency) > 0.0 {
-                let reflectance = comps.schlick();
-                reflected_color * reflectance + refracted_color * (1.0 - reflectance)
-            } else {
-                reflected_color + refracted_color
-            };
+        for object in &mut self.objects {
+            object.replace_images(
+                &mut |image| atlas.region_of(image).unwrap_or(image).clone(),
+                &mut edited,
+            );
+        }
 
-            acc + surface_color + reflectance_color
-        })
+        atlas
+    }
+
+    fn edit<F>(&mut self, id: ShapeId, edit: F) -> Result<(), Error>
+    where
+        F: FnOnce(&mut Shape),
+    {
+        let object = self
+            .objects
+            .iter_mut()
+            .find(|object| object.find(id).is_some())
+            .ok_or(Error::UnknownShape(id))?;
+
+        object.edit(id, edit);
+        Ok(())
+    }
+
+    pub(crate) fn intersect(&self, ray: &Ray) -> Intersections<'_> {
+        let mut intersections: Intersections<'_> = self
+            .objects
+            .iter()
+            .flat_map(|obj| obj.intersect(ray))
+            .collect();
+
+        Intersection::sort(&mut intersections);
+        intersections
     }
 
     pub(crate) fn is_shadowed(&self, light_position: Point, point: Point) -> bool {
@@ -85,51 +634,34 @@ impl World {
             direction: point_to_light,
         };
 
-        let mut xs = self.intersect(&shadow_ray);
-        let hit = Intersection::hit(&mut xs);
+        render::record(|stats| stats.shadow_rays += 1);
 
-        hit.map_or(false, |hit| hit.t < distance)
+        // Only the hits between the point and the light matter, and a single one of them is
+        // enough to know that the point is in shadow.
+        self.objects.iter().any(|object| {
+            !object
+                .intersect_within(&shadow_ray, 0.0, distance)
+                .is_empty()
+        })
     }
+}
 
-    fn reflected_color(&self, comps: &Computation<'_>, recursion_depth: u8) -> Color {
-        let reflectiveness = comps.intersection.object.as_ref().material.reflectivity;
-
-        if float::approx(reflectiveness, 0.0) || recursion_depth == 0 {
-            return color::consts::BLACK;
-        }
-
-        let reflection_ray = Ray {
-            origin: comps.over_point,
-            direction: comps.reflectv,
-        };
-
-        self.color_at(&reflection_ray, recursion_depth - 1) * reflectiveness
+impl FrozenWorld {
+    /// Turns the frozen world back into a regular world that can be modified. The shapes keep the
+    /// grouping built when the world was frozen.
+    ///
+    /// The geometry is only copied when other clones of the frozen world still exist.
+    ///
+    pub fn thaw(self) -> World {
+        Arc::try_unwrap(self.world).unwrap_or_else(|world| World::clone(&world))
     }
+}
 
-    fn refracted_color(&self, comps: &Computation<'_>, recursion_depth: u8) -> Color {
-        let transparency = comps.intersection.object.as_ref().material.transparency;
-
-        // Snell's Law: n1 * sin(oi) = n2 * sin(ot)
-        let n_ratio = comps.n1 / comps.n2;
-        let cos_i = comps.eyev.dot(comps.normalv);
-        let sin2_t = n_ratio.powi(2) * (1.0 - cos_i.powi(2));
-
-        let is_total_internal_refraction = sin2_t > 1.0;
-
-        if float::approx(transparency, 0.0) || recursion_depth == 0 || is_total_internal_refraction
-        {
-            return color::consts::BLACK;
-        }
-
-        let cos_t = (1.0 - sin2_t).sqrt();
-        let direction = comps.normalv * (n_ratio * cos_i - cos_t) - comps.eyev * n_ratio;
-
-        let refraction_ray = Ray {
-            origin: comps.under_point,
-            direction,
-        };
+impl Deref for FrozenWorld {
+    type Target = World;
 
-        self.color_at(&refraction_ray, recursion_depth - 1) * transparency
+    fn deref(&self) -> &Self::Target {
+        &self.world
     }
 }
 
@@ -137,9 +669,8 @@ impl World {
 // This base world is used in other modules for testing purposes.
 pub(crate) fn test_world() -> World {
     use crate::{
+        color,
         light::PointLight,
-        material::Material,
-        pattern::Pattern3D,
         shape::{ShapeBuilder, Sphere},
         transform::Transform,
     };
@@ -147,6 +678,7 @@ pub(crate) fn test_world() -> World {
     let light = Light::Point(PointLight {
         position: Point::new(-10.0, 10.0, -10.0),
         intensity: color::consts::WHITE,
+        group: None,
     });
 
     let object0 = Shape::Sphere(Sphere::from(ShapeBuilder {
@@ -171,6 +703,7 @@ pub(crate) fn test_world() -> World {
     World {
         objects: vec![object0, object1],
         lights: vec![light],
+        ..Default::default()
     }
 }
 
@@ -178,11 +711,16 @@ pub(crate) fn test_world() -> World {
 mod tests {
     use crate::{
         assert_approx,
-        intersection::Intersection,
+        camera::{Camera, CameraBuilder},
+        color,
+        integrator::{Integrator, Whitted},
         light::PointLight,
         material::Material,
-        pattern::Pattern3D,
-        shape::{Plane, ShapeBuilder, Sphere},
+        model::{Model, OBJModelBuilder},
+        pattern::{CachedPattern, ImageTexture, Pattern3D, TextureMap, UvMapping, UvPattern},
+        shape::{
+            Billboard, BillboardBuilder, Plane, ShapeBuilder, Sphere, Triangle, TriangleBuilder,
+        },
         transform::Transform,
         tuple::Vector,
     };
@@ -190,182 +728,63 @@ mod tests {
     use super::{test_world, *};
 
     #[test]
-    fn creating_a_world() {
-        let world = World::default();
-
-        assert_eq!(world.objects.len(), 0);
-        assert_eq!(world.lights.len(), 0);
-    }
-
-    #[test]
-    fn intersect_a_world_with_a_ray() {
-        let world = test_world();
-        let ray = Ray {
-            origin: Point::new(0.0, 0.0, -5.0),
-            direction: Vector::new(0.0, 0.0, 1.0),
-        };
-
-        let xs = world.intersect(&ray);
-
-        assert_eq!(xs.len(), 4);
-        assert_approx!(xs[0].t, 4.0);
-        assert_approx!(xs[1].t, 4.5);
-        assert_approx!(xs[2].t, 5.5);
-        assert_approx!(xs[3].t, 6.0);
-    }
-
-    #[test]
-    fn shading_an_intersection() {
-        let world = test_world();
-
-        let ray = Ray {
-            origin: Point::new(0.0, 0.0, -5.0),
-            direction: Vector::new(0.0, 0.0, 1.0),
-        };
-
-        let i = Intersection {
-            t: 4.0,
-            object: &world.objects[0],
-            u: None,
-            v: None,
-        };
+    fn conserving_the_energy_of_the_materials_of_a_world() {
+        let mut world = test_world();
 
-        let comps = i.prepare_computation(&ray, [i]);
+        assert_eq!(world.conserve_energy(), 1);
 
-        let shade = world.shade_hit(comps, RECURSION_DEPTH);
+        for object in &world.objects {
+            assert!(object.material().energy() <= 1.0 + 1e-9);
+        }
 
-        assert_eq!(
-            shade,
-            Color {
-                red: 0.38066,
-                green: 0.47583,
-                blue: 0.2855,
-            }
-        );
+        assert_eq!(world.conserve_energy(), 0);
     }
 
     #[test]
-    fn shading_an_intersection_from_the_inside() {
-        let world = World {
-            lights: vec![Light::Point(PointLight {
-                position: Point::new(0.0, 0.25, 0.0),
-                intensity: color::consts::WHITE,
-            })],
-            ..test_world()
-        };
-
-        let ray = Ray {
-            origin: Point::new(0.0, 0.0, 0.0),
-            direction: Vector::new(0.0, 0.0, 1.0),
-        };
-
-        let i = Intersection {
-            t: 0.5,
-            object: &world.objects[1],
-            u: None,
-            v: None,
-        };
-
-        let comps = i.prepare_computation(&ray, [i]);
-
-        let shade = world.shade_hit(comps, RECURSION_DEPTH);
+    fn creating_a_world() {
+        let world = World::default();
 
-        assert_eq!(
-            shade,
-            Color {
-                red: 0.90498,
-                green: 0.90498,
-                blue: 0.90498,
-            }
-        );
+        assert_eq!(world.objects.len(), 0);
+        assert_eq!(world.lights.len(), 0);
     }
 
     #[test]
-    fn shade_hit_when_there_is_no_light() {
-        let world = World {
-            lights: vec![],
-            ..test_world()
-        };
-
+    fn a_world_can_be_shared_between_threads() {
+        let world = Arc::new(test_world());
         let ray = Ray {
-            origin: Point::new(0.0, 0.0, 0.0),
+            origin: Point::new(0.0, 0.0, -5.0),
             direction: Vector::new(0.0, 0.0, 1.0),
         };
 
-        let i = Intersection {
-            t: 0.5,
-            object: &world.objects[1],
-            u: None,
-            v: None,
-        };
-
-        let comps = i.prepare_computation(&ray, [i]);
+        let expected = Whitted::default().color_at(&world, &ray);
 
-        let shade = world.shade_hit(comps, RECURSION_DEPTH);
-
-        assert_eq!(shade, color::consts::BLACK);
-    }
-
-    #[test]
-    fn the_color_when_a_ray_misses() {
-        let world = test_world();
-
-        let ray = Ray {
-            origin: Point::new(0.0, 0.0, -5.0),
-            direction: Vector::new(0.0, 1.0, 0.0),
-        };
-
-        let color_at = world.color_at(&ray, RECURSION_DEPTH);
+        let handles: Vec<_> = (0..4)
+            .map(|_| {
+                let world = Arc::clone(&world);
+                std::thread::spawn(move || Whitted::default().color_at(&world, &ray))
+            })
+            .collect();
 
-        assert_eq!(color_at, color::consts::BLACK);
+        for handle in handles {
+            assert_eq!(handle.join().unwrap(), expected);
+        }
     }
 
     #[test]
-    fn the_color_when_a_ray_hits() {
+    fn intersect_a_world_with_a_ray() {
         let world = test_world();
-
         let ray = Ray {
             origin: Point::new(0.0, 0.0, -5.0),
             direction: Vector::new(0.0, 0.0, 1.0),
         };
 
-        let color_at = world.color_at(&ray, RECURSION_DEPTH);
-
-        assert_eq!(
-            color_at,
-            Color {
-                red: 0.38066,
-                green: 0.47583,
-                blue: 0.2855,
-            }
-        );
-    }
-
-    #[test]
-    fn the_color_when_an_intersection_behind_the_ray() {
-        let mut world = test_world();
-
-        let outer_object = &mut world.objects[0];
-        outer_object.as_mut().material = Material {
-            ambient: 1.0,
-            ..outer_object.as_ref().material.clone()
-        };
-
-        let inner_object = &mut world.objects[1];
-        inner_object.as_mut().material = Material {
-            ambient: 1.0,
-            ..inner_object.as_ref().material.clone()
-        };
-
-        let ray = Ray {
-            origin: Point::new(0.0, 0.0, 0.75),
-            direction: Vector::new(0.0, 0.0, -1.0),
-        };
-
-        let color_at = world.color_at(&ray, RECURSION_DEPTH);
-        let inner = &world.objects[1];
+        let xs = world.intersect(&ray);
 
-        assert_eq!(Pattern3D::Solid(color_at), inner.as_ref().material.pattern);
+        assert_eq!(xs.len(), 4);
+        assert_approx!(xs[0].t, 4.0);
+        assert_approx!(xs[1].t, 4.5);
+        assert_approx!(xs[2].t, 5.5);
+        assert_approx!(xs[3].t, 6.0);
     }
 
     #[test]
@@ -411,450 +830,531 @@ mod tests {
         let light = Light::Point(PointLight {
             position: point,
             intensity: color::consts::WHITE,
+            group: None,
         });
 
         let world = World {
             objects: vec![],
             lights: vec![light],
+            ..Default::default()
         };
 
         assert!(!world.is_shadowed(Point::new(-10.0, 10.0, -10.0), point));
     }
 
     #[test]
-    fn shade_hit_is_given_an_intersection_in_shadow() {
-        let object0 = Shape::Sphere(Default::default());
+    fn is_shadowed_test_for_occlusion_between_two_points() {
+        let world = test_world();
+        let light_position = Point::new(-10.0, -10.0, -10.0);
 
-        let object1 = Shape::Sphere(Sphere::from(ShapeBuilder {
-            transform: Transform::translation(0.0, 0.0, 10.0),
-            ..Default::default()
-        }));
+        assert!(!world.is_shadowed(light_position, Point::new(-10.0, -10.0, 10.0)));
+        assert!(world.is_shadowed(light_position, Point::new(10.0, 10.0, 10.0)));
+        assert!(!world.is_shadowed(light_position, Point::new(-20.0, -20.0, -20.0)));
+        assert!(!world.is_shadowed(light_position, Point::new(-5.0, -5.0, -5.0)));
+    }
 
-        let light = Light::Point(PointLight {
-            position: Point::new(0.0, 0.0, -10.0),
-            intensity: color::consts::WHITE,
+    fn row_of_spheres() -> (World, Vec<ShapeId>) {
+        let spheres: Vec<_> = (0..8)
+            .map(|i| {
+                Shape::Sphere(Sphere::from(ShapeBuilder {
+                    transform: Transform::translation(f64::from(i) * 3.0, 0.0, 0.0),
+                    ..Default::default()
+                }))
+            })
+            .collect();
+
+        let ids = spheres.iter().map(Shape::id).collect();
+
+        let mut group = Group::from(GroupBuilder {
+            children: spheres,
+            transform: Default::default(),
         });
+        group.divide(2);
 
         let world = World {
-            objects: vec![object0, object1.clone()],
-            lights: vec![light],
+            objects: vec![Shape::Group(group)],
+            ..Default::default()
         };
 
+        (world, ids)
+    }
+
+    fn hits(world: &World, origin: Point) -> bool {
         let ray = Ray {
-            origin: Point::new(0.0, 0.0, 5.0),
+            origin,
             direction: Vector::new(0.0, 0.0, 1.0),
         };
 
-        let i = Intersection {
-            t: 4.0,
-            object: &object1,
-            u: None,
-            v: None,
-        };
+        !world.intersect(&ray).is_empty()
+    }
 
-        let comps = i.prepare_computation(&ray, [i]);
+    #[test]
+    fn moving_a_shape_inside_of_a_divided_group() {
+        let (mut world, ids) = row_of_spheres();
 
-        let shade = world.shade_hit(comps, RECURSION_DEPTH);
+        world
+            .set_transform(ids[3], Transform::translation(9.0, 10.0, 0.0))
+            .unwrap();
 
+        assert!(!hits(&world, Point::new(9.0, 0.0, -5.0)));
+        assert!(hits(&world, Point::new(9.0, 10.0, -5.0)));
         assert_eq!(
-            shade,
-            Color {
-                red: 0.1,
-                green: 0.1,
-                blue: 0.1
-            }
+            world.shape(ids[3]).unwrap().bounds(),
+            (Point::new(8.0, 9.0, -1.0), Point::new(10.0, 11.0, 1.0))
         );
+
+        let (min, max) = world.objects[0].bounds();
+
+        assert_eq!(min, Point::new(-1.0, -1.0, -1.0));
+        assert_eq!(max, Point::new(22.0, 11.0, 1.0));
     }
 
     #[test]
-    fn the_reflected_color_for_a_non_reflective_material() {
-        let mut world = test_world();
+    fn moving_a_group_keeps_its_shapes_in_place_relative_to_it() {
+        let (mut world, ids) = row_of_spheres();
+        let id = world.objects[0].id();
 
-        let ray = Ray {
-            origin: Point::new(0.0, 0.0, 0.0),
-            direction: Vector::new(0.0, 0.0, 1.0),
-        };
+        world
+            .set_transform(id, Transform::translation(0.0, -5.0, 0.0))
+            .unwrap();
 
-        let object = &mut world.objects[1];
-        object.as_mut().material = Material {
-            ambient: 1.0,
-            ..object.as_ref().material.clone()
-        };
+        assert!(hits(&world, Point::new(21.0, -5.0, -5.0)));
+        assert!(!hits(&world, Point::new(21.0, 0.0, -5.0)));
+        assert_eq!(
+            world.shape(ids[1]).unwrap().transform(),
+            Transform::translation(3.0, -5.0, 0.0)
+        );
+    }
 
-        let i = Intersection {
-            t: 1.0,
-            object: &world.objects[1],
-            u: None,
-            v: None,
-        };
+    #[test]
+    fn replacing_the_material_of_a_shape() {
+        let (mut world, ids) = row_of_spheres();
 
-        let comps = i.prepare_computation(&ray, [i]);
+        let material = Material {
+            reflectivity: 0.5,
+            ..Default::default()
+        };
 
-        let shade = world.reflected_color(&comps, RECURSION_DEPTH);
+        world.set_material(ids[5], material.clone()).unwrap();
 
-        assert_eq!(shade, color::consts::BLACK);
+        assert_eq!(world.shape(ids[5]).unwrap().material(), &material);
+        assert_eq!(
+            world.shape(ids[4]).unwrap().material(),
+            &Material::default()
+        );
     }
 
     #[test]
-    fn the_reflected_color_for_a_reflective_material() {
-        let world = test_world();
+    fn moving_a_clone_of_a_shape_leaves_the_original_in_place() {
+        let sphere = Shape::Sphere(Default::default());
+        let clone = sphere.clone();
+        let id = clone.id();
 
-        let object = Shape::Plane(Plane::from(ShapeBuilder {
-            material: Material {
-                reflectivity: 0.5,
-                ..Default::default()
-            },
-            transform: Transform::translation(0.0, -1.0, 0.0),
-        }));
-
-        let ray = Ray {
-            origin: Point::new(0.0, 0.0, -3.0),
-            direction: Vector::new(0.0, -2_f64.sqrt() / 2.0, 2_f64.sqrt() / 2.0),
-        };
-
-        let i = Intersection {
-            t: 2_f64.sqrt(),
-            object: &object,
-            u: None,
-            v: None,
+        let mut world = World {
+            objects: vec![sphere, clone],
+            ..Default::default()
         };
 
-        let comps = i.prepare_computation(&ray, [i]);
-
-        let shade = world.reflected_color(&comps, RECURSION_DEPTH);
+        world
+            .set_transform(id, Transform::translation(0.0, 5.0, 0.0))
+            .unwrap();
 
+        assert_eq!(world.objects[0].transform(), Transform::default());
         assert_eq!(
-            shade,
-            Color {
-                red: 0.19033,
-                green: 0.23791,
-                blue: 0.14275,
-            }
+            world.shape(id).unwrap().transform(),
+            world.objects[1].transform()
         );
+        assert!(hits(&world, Point::new(0.0, 5.0, -5.0)));
     }
 
     #[test]
-    fn shade_hit_with_a_reflective_material() {
-        let world = test_world();
+    fn a_copy_of_a_world_keeps_the_identifiers_of_its_shapes() {
+        let (world, ids) = row_of_spheres();
+        let copy = world.clone();
 
-        let object = Shape::Plane(Plane::from(ShapeBuilder {
-            material: Material {
-                reflectivity: 0.5,
-                ..Default::default()
-            },
-            transform: Transform::translation(0.0, -1.0, 0.0),
-        }));
-
-        let ray = Ray {
-            origin: Point::new(0.0, 0.0, -3.0),
-            direction: Vector::new(0.0, -2_f64.sqrt() / 2.0, 2_f64.sqrt() / 2.0),
-        };
-
-        let i = Intersection {
-            t: 2_f64.sqrt(),
-            object: &object,
-            u: None,
-            v: None,
-        };
-
-        let comps = i.prepare_computation(&ray, [i]);
+        assert_eq!(copy.objects[0].id(), world.objects[0].id());
+        assert!(ids.iter().all(|&id| copy.shape(id).is_some()));
+    }
 
-        let shade = world.shade_hit(comps, RECURSION_DEPTH);
+    #[test]
+    fn editing_a_shape_that_is_not_in_the_world() {
+        let (mut world, _) = row_of_spheres();
+        let sphere = Shape::Sphere(Default::default());
 
         assert_eq!(
-            shade,
-            Color {
-                red: 0.87676,
-                green: 0.92435,
-                blue: 0.82918,
-            }
+            world.set_material(sphere.id(), Default::default()),
+            Err(Error::UnknownShape(sphere.id()))
         );
     }
 
     #[test]
-    fn color_at_with_mutually_reflective_surfaces() {
-        let lower_object = Shape::Sphere(Sphere::from(ShapeBuilder {
+    fn moving_a_shape_forgets_its_cached_colors() {
+        let cache =
+            Arc::new(CachedPattern::new(Pattern3D::Solid(color::consts::RED), 4.0).unwrap());
+
+        let sphere = Shape::Sphere(Sphere::from(ShapeBuilder {
             material: Material {
-                reflectivity: 1.0,
+                pattern: Pattern3D::Cached(Arc::clone(&cache)),
                 ..Default::default()
             },
-            transform: Transform::translation(0.0, -1.0, 0.0),
+            transform: Default::default(),
         }));
+        let id = sphere.id();
 
-        let upper_object = Shape::Sphere(Sphere::from(ShapeBuilder {
-            material: lower_object.as_ref().material.clone(),
-            transform: Transform::translation(0.0, 1.0, 0.0),
-        }));
+        sphere
+            .material()
+            .pattern
+            .color_at_object(&sphere, Point::new(0.0, 1.0, 0.0));
 
-        let light = Light::Point(PointLight {
-            position: Point::new(0.0, 0.0, 0.0),
-            intensity: color::consts::WHITE,
-        });
+        assert_eq!(cache.len(), 1);
 
-        let world = World {
-            objects: vec![lower_object, upper_object],
-            lights: vec![light],
+        let mut world = World {
+            objects: vec![sphere],
+            ..Default::default()
         };
 
-        let ray = Ray {
-            origin: Point::new(0.0, 0.0, 0.0),
-            direction: Vector::new(0.0, 1.0, 0.0),
-        };
+        world
+            .set_transform(id, Transform::translation(1.0, 0.0, 0.0))
+            .unwrap();
 
-        // This should not stack overflow, so it should not panic.
-        world.color_at(&ray, RECURSION_DEPTH);
+        assert!(cache.is_empty());
     }
 
     #[test]
-    fn the_reflected_color_at_the_maximum_recursive_depth() {
-        let object = Shape::Sphere(Sphere::from(ShapeBuilder {
+    fn light_goes_through_the_cut_out_parts_of_a_texture() {
+        // Every unit square of the floor has it's left half cut out.
+        let pixels = vec![0, 0, 0, 0, 255, 255, 255, 255];
+        let image = image::RgbaImage::from_raw(2, 1, pixels).unwrap();
+        let texture = ImageTexture::from(&image::DynamicImage::ImageRgba8(image));
+
+        let floor = Shape::Plane(Plane::from(ShapeBuilder {
             material: Material {
-                reflectivity: 0.5,
+                pattern: Pattern3D::Texture(Arc::new(
+                    TextureMap::new(
+                        UvMapping::Planar(UvPattern::Image(Arc::new(texture))),
+                        Default::default(),
+                    )
+                    .with_alpha_cutoff(0.5),
+                )),
                 ..Default::default()
             },
-            transform: Transform::translation(0.0, -1.0, 0.0),
+            transform: Default::default(),
         }));
 
-        let mut w = test_world();
-        w.objects.push(object);
+        let world = World {
+            objects: vec![floor],
+            ..Default::default()
+        };
+
+        assert!(!world.is_shadowed(Point::new(0.25, 10.0, 0.5), Point::new(0.25, -1.0, 0.5)));
+        assert!(world.is_shadowed(Point::new(0.75, 10.0, 0.5), Point::new(0.75, -1.0, 0.5)));
 
         let ray = Ray {
-            origin: Point::new(0.0, 0.0, -3.0),
-            direction: Vector::new(0.0, -2_f64.sqrt() / 2.0, 2_f64.sqrt() / 2.0),
+            origin: Point::new(0.25, 1.0, 0.5),
+            direction: Vector::new(0.0, -1.0, 0.0),
         };
 
-        let i = Intersection {
-            t: 2_f64.sqrt(),
-            object: &w.objects[2],
-            u: None,
-            v: None,
+        assert!(world.intersect(&ray).is_empty());
+    }
+
+    #[test]
+    fn packing_the_textures_of_a_world_keeps_it_looking_the_same() {
+        let texture = |pixels: Vec<u8>| {
+            let image = image::RgbaImage::from_raw(2, pixels.len() as u32 / 8, pixels).unwrap();
+            Arc::new(ImageTexture::from(&image::DynamicImage::ImageRgba8(image)))
         };
 
-        let comps = i.prepare_computation(&ray, [i]);
+        let stripes = texture(vec![255, 0, 0, 255, 0, 255, 0, 255]);
+        let dots = texture(vec![
+            0, 0, 255, 255, 255, 255, 255, 255, //
+            0, 0, 0, 0, 255, 0, 255, 255,
+        ]);
+
+        let textured = |image: &Arc<ImageTexture>, transform| {
+            Shape::Plane(Plane::from(ShapeBuilder {
+                material: Material {
+                    pattern: Pattern3D::Texture(Arc::new(TextureMap::new(
+                        UvMapping::Planar(UvPattern::Image(image.clone())),
+                        Default::default(),
+                    ))),
+                    ..Default::default()
+                },
+                transform,
+            }))
+        };
 
-        let shade = w.reflected_color(&comps, 0);
+        let billboard = Shape::Billboard(Billboard::from(BillboardBuilder {
+            texture: Some(dots.clone()),
+            transform: Transform::translation(0.0, 0.0, 3.0),
+            ..Default::default()
+        }));
 
-        assert_eq!(shade, color::consts::BLACK);
-    }
+        let mut world = World {
+            objects: vec![
+                textured(&stripes, Transform::translation(0.0, -1.0, 0.0)),
+                textured(
+                    &dots,
+                    Transform::translation(0.0, 0.0, 5.0)
+                        * Transform::rotation_x(std::f64::consts::FRAC_PI_2),
+                ),
+                billboard,
+            ],
+            lights: vec![Light::Point(PointLight {
+                position: Point::new(-10.0, 10.0, -10.0),
+                intensity: color::consts::WHITE,
+                group: None,
+            })],
+            ..Default::default()
+        };
 
-    #[test]
-    fn the_refracted_color_with_an_opaque_surface() {
-        let world = test_world();
+        let rays: Vec<_> = (0..20)
+            .map(|i| Ray {
+                origin: Point::new(0.0, 0.0, -5.0),
+                direction: Vector::new(i as f64 * 0.05 - 0.5, (i % 5) as f64 * 0.1 - 0.25, 1.0),
+            })
+            .collect();
 
-        let ray = Ray {
-            origin: Point::new(0.0, 0.0, -5.0),
-            direction: Vector::new(0.0, 0.0, 1.0),
+        let colors = |world: &World| {
+            rays.iter()
+                .map(|ray| Whitted::default().color_at(world, ray))
+                .collect::<Vec<_>>()
         };
 
-        let xs = [
-            Intersection {
-                t: 4.0,
-                object: &world.objects[0],
-                u: None,
-                v: None,
-            },
-            Intersection {
-                t: 6.0,
-                object: &world.objects[0],
-                u: None,
-                v: None,
-            },
-        ];
+        let before = colors(&world);
+        let atlas = world.pack_textures();
 
-        let comps = xs[0].prepare_computation(&ray, xs);
+        assert_eq!(atlas.len(), 2);
+        assert_eq!(colors(&world), before);
+        assert!(before.iter().any(|color| *color != color::consts::BLACK));
 
-        let shade = world.refracted_color(&comps, RECURSION_DEPTH);
+        let Shape::Billboard(billboard) = &world.objects[2] else {
+            unreachable!()
+        };
 
-        assert_eq!(shade, color::consts::BLACK);
+        assert!(Arc::ptr_eq(
+            billboard.texture().unwrap(),
+            atlas.region_of(&dots).unwrap()
+        ));
     }
 
     #[test]
-    fn the_refracted_color_at_the_maximum_recursive_depth() {
-        let mut world = test_world();
-
-        let object = &mut world.objects[0];
-        object.as_mut().material = Material {
-            index_of_refraction: 1.5,
-            transparency: 1.0,
-            ..object.as_ref().material.clone()
+    fn exporting_a_world_to_obj() {
+        let world = World {
+            objects: vec![
+                Shape::Triangle(
+                    Triangle::try_from(TriangleBuilder {
+                        material: Default::default(),
+                        vertices: [
+                            Point::new(0.0, 1.0, 0.0),
+                            Point::new(-1.0, 0.0, 0.0),
+                            Point::new(1.0, 0.0, 0.0),
+                        ],
+                    })
+                    .unwrap(),
+                ),
+                Shape::Plane(Plane::from(ShapeBuilder {
+                    transform: Transform::translation(0.0, -2.0, 0.0),
+                    ..Default::default()
+                })),
+            ],
+            lights: vec![],
+            ..Default::default()
         };
 
-        let ray = Ray {
-            origin: Point::new(0.0, 0.0, -5.0),
-            direction: Vector::new(0.0, 0.0, 1.0),
-        };
+        let mut output = vec![];
+        world.export_obj(&mut output, 16).unwrap();
 
-        let xs = [
-            Intersection {
-                t: 4.0,
-                object: &world.objects[0],
-                u: None,
-                v: None,
-            },
-            Intersection {
-                t: 6.0,
-                object: &world.objects[0],
-                u: None,
-                v: None,
-            },
-        ];
+        let output = String::from_utf8(output).unwrap();
+        let mut lines = output.lines();
 
-        let comps = xs[0].prepare_computation(&ray, xs);
+        assert_eq!(lines.next(), Some("o triangle_1"));
+        assert_eq!(lines.next(), Some("v 0 1 0"));
+        assert_eq!(lines.next(), Some("v -1 0 0"));
+        assert_eq!(lines.next(), Some("v 1 0 0"));
+        assert_eq!(lines.next(), Some("f 1 2 3"));
 
-        let shade = world.refracted_color(&comps, 0);
+        assert_eq!(lines.next(), Some("o plane_2"));
 
-        assert_eq!(shade, color::consts::BLACK);
+        for _ in 0..6 {
+            let line = lines.next().unwrap();
+            assert!(line.starts_with('v') && line.contains(" -2 "));
+        }
+
+        assert_eq!(lines.next(), Some("f 4 5 6"));
+        assert_eq!(lines.next(), Some("f 7 8 9"));
+        assert_eq!(lines.next(), None);
     }
 
     #[test]
-    fn the_refracted_color_under_total_internal_reflection() {
-        let mut world = test_world();
+    fn exported_obj_can_be_loaded_back_as_a_model() {
+        let world = test_world();
 
-        let object = &mut world.objects[0];
-        object.as_mut().material = Material {
-            index_of_refraction: 1.5,
-            transparency: 1.0,
-            ..object.as_ref().material.clone()
-        };
+        let mut output = vec![];
+        world.export_obj(&mut output, 12).unwrap();
 
-        let ray = Ray {
-            origin: Point::new(0.0, 0.0, 2_f64.sqrt() / 2.0),
-            direction: Vector::new(0.0, 1.0, 0.0),
-        };
+        let output = String::from_utf8(output).unwrap();
 
-        let xs = [
-            Intersection {
-                t: -2_f64.sqrt() / 2.0,
-                object: &world.objects[0],
-                u: None,
-                v: None,
-            },
-            Intersection {
-                t: 2_f64.sqrt() / 2.0,
-                object: &world.objects[0],
-                u: None,
-                v: None,
-            },
-        ];
+        let model = Model::try_from(OBJModelBuilder {
+            model_spec: &output,
+            transform: Default::default(),
+        });
 
-        let comps = xs[1].prepare_computation(&ray, xs);
+        assert!(model.is_ok());
+    }
 
-        let shade = world.refracted_color(&comps, RECURSION_DEPTH);
+    #[test]
+    fn looking_up_a_shape_by_its_id() {
+        let world = test_world();
+        let id = world.objects[1].id();
 
-        assert_eq!(shade, color::consts::BLACK);
+        assert_eq!(world.shape(id), Some(&world.objects[1]));
+        assert_eq!(world.shape(Shape::Cube(Default::default()).id()), None);
     }
 
     #[test]
-    fn shade_hit_with_a_transparent_material() {
+    fn freezing_a_world_groups_its_bounded_shapes() {
         let mut world = test_world();
-
-        let floor = Shape::Plane(Plane::from(ShapeBuilder {
-            material: Material {
-                index_of_refraction: 1.5,
-                transparency: 0.5,
-                ..Default::default()
-            },
+        world.objects.push(Shape::Plane(Plane::from(ShapeBuilder {
             transform: Transform::translation(0.0, -1.0, 0.0),
-        }));
+            ..Default::default()
+        })));
 
-        let ball = Shape::Sphere(Sphere::from(ShapeBuilder {
-            material: Material {
-                ambient: 0.5,
-                pattern: Pattern3D::Solid(color::consts::RED),
-                ..Default::default()
-            },
-            transform: Transform::translation(0.0, -3.5, -0.5),
-        }));
+        let ids: Vec<_> = world.objects.iter().map(Shape::id).collect();
 
-        world.objects.push(floor);
-        world.objects.push(ball);
+        let frozen = world.freeze();
 
-        let ray = Ray {
-            origin: Point::new(0.0, 0.0, -3.0),
-            direction: Vector::new(0.0, -2_f64.sqrt() / 2.0, 2_f64.sqrt() / 2.0),
-        };
+        assert_eq!(frozen.objects.len(), 2);
+        assert!(matches!(frozen.objects[0], Shape::Plane(_)));
+        assert!(matches!(frozen.objects[1], Shape::Group(_)));
 
-        let xs = [Intersection {
-            t: 2_f64.sqrt(),
-            object: &world.objects[2],
-            u: None,
-            v: None,
-        }];
+        for id in ids {
+            assert_eq!(frozen.shape(id).map(Shape::id), Some(id));
+        }
+    }
 
-        let comps = xs[0].prepare_computation(&ray, xs);
+    #[test]
+    fn a_frozen_world_looks_the_same() {
+        let world = test_world();
+        let frozen = test_world().freeze();
+
+        let camera = Camera::try_from(CameraBuilder {
+            width: 11,
+            height: 11,
+            field_of_view: std::f64::consts::FRAC_PI_2,
+            transform: Transform::view(
+                Point::new(0.0, 0.0, -5.0),
+                Point::new(0.0, 0.0, 0.0),
+                Vector::new(0.0, 1.0, 0.0),
+            )
+            .unwrap(),
+        })
+        .unwrap();
 
-        let shade = world.shade_hit(comps, RECURSION_DEPTH);
+        for (_, ray) in camera.rays() {
+            assert_eq!(
+                Whitted::default().color_at(&world, &ray),
+                Whitted::default().color_at(&frozen, &ray)
+            );
+        }
+    }
+
+    #[test]
+    fn thawing_a_frozen_world() {
+        let frozen = test_world().freeze();
+        let clone = frozen.clone();
+
+        let thawed = frozen.thaw();
+        assert_eq!(thawed.objects, clone.objects);
+
+        let thawed = clone.thaw();
+        assert_eq!(thawed.lights.len(), 1);
+    }
 
+    #[test]
+    fn declaring_the_units_of_a_world() {
         assert_eq!(
-            shade,
-            Color {
-                red: 0.93642,
-                green: 0.68642,
-                blue: 0.68642
-            }
+            UnitScale::new(0.01).map(|units| units.meters_per_unit()),
+            Ok(0.01)
+        );
+        assert_eq!(UnitScale::new(0.0), Err(Error::NonPositiveUnitLength(0.0)));
+        assert_eq!(
+            UnitScale::new(-1.0),
+            Err(Error::NonPositiveUnitLength(-1.0))
         );
+        assert!(UnitScale::new(f64::INFINITY).is_err());
     }
 
     #[test]
-    fn shade_hit_with_a_reflective_and_transparent_material() {
-        let mut world = test_world();
+    fn the_surface_offset_follows_the_units_of_the_world() {
+        assert_approx!(UnitScale::METERS.surface_offset(), float::EPSILON);
+        assert_approx!(UnitScale::MILLIMETERS.surface_offset(), 0.01);
+        assert_approx!(
+            UnitScale::MILLIMETERS.with_epsilon(1e-4).surface_offset(),
+            0.1
+        );
+        assert_approx!(UnitScale::MILLIMETERS.from_meters(2.0), 2000.0);
+    }
+
+    #[test]
+    fn rays_leave_surfaces_of_worlds_in_other_units_further_away() {
+        let world = World {
+            objects: vec![Shape::Plane(Default::default())],
+            units: UnitScale::MILLIMETERS,
+            ..Default::default()
+        };
 
         let ray = Ray {
-            origin: Point::new(0.0, 0.0, -3.0),
-            direction: Vector::new(0.0, -2_f64.sqrt() / 2.0, 2_f64.sqrt() / 2.0),
+            origin: Point::new(0.0, 1.0, 0.0),
+            direction: Vector::new(0.0, -1.0, 0.0),
         };
 
-        let floor = Shape::Plane(Plane::from(ShapeBuilder {
-            material: Material {
-                index_of_refraction: 1.5,
-                reflectivity: 0.5,
-                transparency: 0.5,
-                ..Default::default()
-            },
-            transform: Transform::translation(0.0, -1.0, 0.0),
-        }));
+        let mut xs = world.intersect(&ray);
+        let hit = Intersection::hit(&mut xs).unwrap();
 
-        let ball = Shape::Sphere(Sphere::from(ShapeBuilder {
-            material: Material {
-                ambient: 0.5,
-                pattern: Pattern3D::Solid(color::consts::RED),
-                ..Default::default()
-            },
-            transform: Transform::translation(0.0, -3.5, -0.5),
-        }));
+        let comps =
+            hit.prepare_computation_with_offset(&ray, xs.clone(), world.units.surface_offset());
+
+        assert_approx!(comps.over_point.0.y, 0.01);
+        assert_approx!(comps.under_point.0.y, -0.01);
+    }
 
-        world.objects.push(floor);
-        world.objects.push(ball);
+    #[test]
+    fn lights_without_a_group_belong_to_the_default_group() {
+        let mut world = test_world();
+        let light = world.lights[0].clone();
 
-        let xs = [Intersection {
-            t: 2_f64.sqrt(),
-            object: &world.objects[2],
-            u: None,
-            v: None,
-        }];
+        world.lights = vec![
+            light.clone().with_group("key"),
+            light.clone(),
+            light.clone().with_group("key"),
+            light.clone().with_group("fill"),
+        ];
 
-        let comps = xs[0].prepare_computation(&ray, xs);
+        assert_eq!(
+            world.light_group_members(),
+            [
+                (DEFAULT_LIGHT_GROUP, vec![1]),
+                ("fill", vec![3]),
+                ("key", vec![0, 2])
+            ]
+        );
 
-        let shade = world.shade_hit(comps, RECURSION_DEPTH);
+        // Lights can also name the default group explicitly.
+        world.lights[0] = light.clone().with_group(DEFAULT_LIGHT_GROUP);
+        world.lights[1] = light.with_group("fill");
 
         assert_eq!(
-            shade,
-            Color {
-                red: 0.93391,
-                green: 0.69643,
-                blue: 0.69243
-            }
+            world.light_group_members(),
+            [
+                (DEFAULT_LIGHT_GROUP, vec![0]),
+                ("fill", vec![1, 3]),
+                ("key", vec![2])
+            ]
         );
-    }
 
-    #[test]
-    fn is_shadowed_test_for_occlusion_between_two_points() {
-        let world = test_world();
-        let light_position = Point::new(-10.0, -10.0, -10.0);
+        // The default group is always there, even without lights.
+        world.lights.clear();
 
-        assert!(!world.is_shadowed(light_position, Point::new(-10.0, -10.0, 10.0)));
-        assert!(world.is_shadowed(light_position, Point::new(10.0, 10.0, 10.0)));
-        assert!(!world.is_shadowed(light_position, Point::new(-20.0, -20.0, -20.0)));
-        assert!(!world.is_shadowed(light_position, Point::new(-5.0, -5.0, -5.0)));
+        assert_eq!(world.light_group_members(), [(DEFAULT_LIGHT_GROUP, vec![])]);
     }
 }